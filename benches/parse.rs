@@ -0,0 +1,109 @@
+//! Parser throughput on generated documents, and that of the lexer and
+//! the events read over it
+//!
+//! Run with `cargo bench`; pass a name to run only the matching cases,
+//! such as `cargo bench -- flat`. Criterion reports the throughput of each
+//! case in MB/s and compares it with the previous run.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use uplang::Parser;
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(20);
+    let cases: [(&str, String, Parser); 6] = [
+        ("flat", flat(100_000), Parser::new()),
+        ("typed", typed(50_000), Parser::new()),
+        ("nested", nested(20_000), Parser::new()),
+        ("multiline", multiline(10_000), Parser::new()),
+        (
+            "trivia",
+            nested(20_000),
+            Parser::new().preserve_trivia(true),
+        ),
+        ("recovery", broken(20_000), Parser::new()),
+    ];
+    for (name, input, parser) in &cases {
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_function(*name, |b| {
+            if *name == "recovery" {
+                b.iter(|| parser.parse_with_recovery(black_box(input)));
+            } else {
+                b.iter(|| parser.parse_document(black_box(input)).unwrap());
+            }
+        });
+    }
+    group.finish();
+}
+
+/// The lexer alone, and events, on the documents of the `nested` case
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    group.sample_size(20);
+    let input = nested(20_000);
+    let parser = Parser::new();
+    group.throughput(Throughput::BytesDecimal(input.len() as u64));
+    group.bench_function("lexer", |b| {
+        b.iter(|| parser.lexer(black_box(&input)).count());
+    });
+    group.bench_function("events", |b| {
+        b.iter(|| parser.events(black_box(input.as_bytes())).count());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, read);
+criterion_main!(benches);
+
+/// Plain `key value` lines
+fn flat(n: usize) -> String {
+    (0..n)
+        .map(|i| format!("key{} some value number {}\n", i, i))
+        .collect()
+}
+
+/// Annotated scalars and comments
+fn typed(n: usize) -> String {
+    (0..n)
+        .map(|i| {
+            format!(
+                "# Entry {}\nport{}!int {}\nratio{}!float 0.{}\non{}!bool true\n",
+                i, i, i, i, i, i
+            )
+        })
+        .collect()
+}
+
+/// Blocks holding lists and blocks
+fn nested(n: usize) -> String {
+    (0..n)
+        .map(|i| {
+            format!(
+                "server{} {{\n  host host{}.example.com\n  port!int 80\n  tags [web, api, \"edge {}\"]\n  limits {{\n    cpu 2\n    mem!int 512\n  }}\n  backends [\n    a\n    b\n  ]\n}}\n",
+                i, i, i
+            )
+        })
+        .collect()
+}
+
+/// Multiline strings
+fn multiline(n: usize) -> String {
+    (0..n)
+        .map(|i| {
+            format!(
+                "script{} ```\n  echo {}\n  echo done\n  exit 0\n```\n",
+                i, i
+            )
+        })
+        .collect()
+}
+
+/// Blocks with a value that does not fit its annotation, for
+/// `parse_with_recovery`
+fn broken(n: usize) -> String {
+    (0..n)
+        .map(|i| format!("block{} {{\n  port!int eighty\n  host h{}\n}}\n", i, i))
+        .collect()
+}
@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uplang::{Document, Parser};
+
+// Any input parses to a document or fails with an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    let _ = uplang::parse_bytes(data);
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let parsers = [
+        Parser::new(),
+        Parser::new().trailing_comments(true).comment_chars("#;"),
+        Parser::new().preserve_trivia(true).spans(true),
+    ];
+    for parser in parsers {
+        if let Ok(doc) = parser.parse_document(input) {
+            let _ = uplang::to_string(&doc);
+        }
+        let _ = parser.parse_with_recovery(input);
+        let _ = parser.parse_borrowed(input);
+        let _ = parser.parse_reader(input.as_bytes());
+    }
+    let _ = uplang::format(input);
+    let _ = uplang::folding_ranges(input);
+    let _ = Document::from_json(input);
+});
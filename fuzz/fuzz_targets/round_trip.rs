@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uplang::Writer;
+use uplang_fuzz::ArbitraryDocument;
+
+// Written documents read back unchanged, and their text is already formatted
+fuzz_target!(|doc: ArbitraryDocument| {
+    let doc = doc.0;
+    let text = uplang::to_string(&doc);
+    match uplang::parse(&text) {
+        Ok(back) => assert_eq!(back, doc, "read back differently:\n{}", text),
+        Err(e) => panic!("written text does not parse: {}\n{}", e, text),
+    }
+    assert_eq!(uplang::format(&text).as_deref(), Ok(text.as_str()));
+
+    let inline = Writer::new().inline_width(40).to_string(&doc);
+    assert_eq!(uplang::parse(&inline).ok(), Some(doc), "{}", inline);
+});
@@ -0,0 +1,99 @@
+//! Arbitrary documents for fuzzing the `uplang` parser and writer
+//!
+//! [`ArbitraryDocument`] and [`ArbitraryValue`] build documents out of the
+//! fuzzer's bytes, limited to what UP text can hold: typed scalars are node
+//! values with the annotation that reads them back, list items are text,
+//! lists, and blocks, and text has no `${` for the parser to interpolate.
+//! Writing such a document and parsing the text must give it back.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use uplang::{Block, Document, Node, Value};
+
+/// How deep generated blocks and lists nest, well within the parser's
+/// default depth limit
+const MAX_DEPTH: usize = 8;
+
+/// A document that reads back unchanged from the text it is written as
+#[derive(Debug, Clone)]
+pub struct ArbitraryDocument(pub Document);
+
+/// A node value, typed scalars included
+#[derive(Debug, Clone)]
+pub struct ArbitraryValue(pub Value);
+
+impl<'a> Arbitrary<'a> for ArbitraryDocument {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self(Document {
+            nodes: nodes(u, 0)?,
+        }))
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        value(u, 0).map(Self)
+    }
+}
+
+/// The annotation a node needs for its value to read back as the same type
+pub fn annotation(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Int(_) => Some("int"),
+        Value::Float(_) => Some("float"),
+        Value::Bool(_) => Some("bool"),
+        Value::Null => Some("null"),
+        _ => None,
+    }
+}
+
+/// Nodes with distinct keys
+fn nodes(u: &mut Unstructured<'_>, depth: usize) -> Result<Vec<Node>> {
+    let mut nodes: Vec<Node> = Vec::new();
+    for _ in 0..u.int_in_range(0..=4)? {
+        let key = text(u)?;
+        if nodes.iter().any(|node| node.key == key) {
+            continue;
+        }
+        let value = value(u, depth)?;
+        nodes.push(Node {
+            type_annotation: annotation(&value).map(str::to_string),
+            ..Node::new(key, value)
+        });
+    }
+    Ok(nodes)
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    Ok(match u.int_in_range(0..=4)? {
+        0 => Value::Int(u.arbitrary()?),
+        1 => {
+            let x: f64 = u.arbitrary()?;
+            Value::Float(if x.is_finite() { x } else { 0.0 })
+        }
+        2 => Value::Bool(u.arbitrary()?),
+        3 => Value::Null,
+        _ => item(u, depth)?,
+    })
+}
+
+/// A value that may also be an item of a list
+fn item(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let nested = if depth < MAX_DEPTH { 2 } else { 0 };
+    Ok(match u.int_in_range(0..=nested)? {
+        0 => Value::String(text(u)?),
+        1 => Value::Block(nodes(u, depth + 1)?.into_iter().collect::<Block>()),
+        _ => {
+            let mut items = Vec::new();
+            for _ in 0..u.int_in_range(0..=4)? {
+                items.push(item(u, depth + 1)?);
+            }
+            Value::List(items)
+        }
+    })
+}
+
+/// Any text but the `$` of references, which have no escape
+fn text(u: &mut Unstructured<'_>) -> Result<String> {
+    let text: String = u.arbitrary()?;
+    Ok(text.replace('$', ""))
+}
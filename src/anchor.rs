@@ -0,0 +1,144 @@
+//! Reusable fragments: `!anchor` nodes and the `!ref` nodes that copy them
+//!
+//! A node annotated `!anchor`, such as `defaults!anchor { ... }`, stays in
+//! the document as written and can be copied by any later node of the same
+//! block or a block nested in it with `server!ref defaults`. Written as
+//! `server!ref defaults {`, the block that follows is merged into the copy,
+//! key by key, overriding what the anchor set. References are resolved
+//! while parsing: an anchor must be defined before it is used, and one
+//! that refers to itself is an [`ErrorKind::ReferenceCycle`] error.
+
+use crate::descent::Reader;
+use crate::interpolate::Scope;
+use crate::merge::merge_blocks;
+use crate::source::clear_spans;
+use crate::{ErrorKind, MergeStrategy, ParseError, Value};
+
+impl Reader<'_, '_> {
+    /// Read the value of a `!ref` node: the anchor's name, optionally
+    /// followed by `{` and a block to merge into its value
+    pub(crate) fn reference(
+        &mut self,
+        val_part: &str,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ParseError> {
+        let name = ref_name(val_part);
+        let merged = val_part.ends_with('{');
+        if name.is_empty() {
+            return Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                "`!ref` needs the name of an anchor",
+            ));
+        }
+        let mut value = match scope.anchor(name) {
+            Some(Some(value)) => value.clone(),
+            Some(None) => {
+                return Err(ParseError::new(
+                    ErrorKind::ReferenceCycle,
+                    format!("anchor `{}` refers to itself", name),
+                ))
+            }
+            None => {
+                return Err(ParseError::new(
+                    ErrorKind::UndefinedReference,
+                    format!("no anchor `{}` is defined before this reference", name),
+                ))
+            }
+        };
+        // The copy has no text of its own
+        clear_spans(&mut value);
+        if !merged {
+            return Ok(value);
+        }
+
+        let Value::Block(mut base) = value else {
+            return Err(ParseError::new(
+                ErrorKind::TypeMismatch,
+                format!(
+                    "cannot merge a block into anchor `{}`, which is a {}",
+                    name,
+                    value.type_name()
+                ),
+            ));
+        };
+        let Value::Block(top) = self.block(scope)? else {
+            unreachable!("a block reads as a block");
+        };
+        merge_blocks(&mut base, top, MergeStrategy::Deep);
+        Ok(Value::Block(base))
+    }
+}
+
+/// The anchor named by the value of a `!ref` node
+pub(crate) fn ref_name(val_part: &str) -> &str {
+    match val_part.strip_suffix('{') {
+        Some(name) => name.trim_end(),
+        None => val_part,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, to_string, ErrorKind, Parser};
+
+    #[test]
+    fn test_refs_copy_anchors() {
+        let input = "defaults!anchor {\n  host db.local\n  port!int 5432\n  pool {\n    size!int 5\n    idle 30s\n  }\n}\nprimary!ref defaults\nreplica!ref defaults {\n  host replica.local\n  pool {\n    size!int 2\n  }\n}\n";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_str("primary.host"), Some("db.local"));
+        assert_eq!(doc.get_int("primary.pool.size"), Some(5));
+        assert_eq!(doc.get_str("replica.host"), Some("replica.local"));
+        assert_eq!(doc.get_int("replica.port"), Some(5432));
+        assert_eq!(doc.get_int("replica.pool.size"), Some(2));
+        assert_eq!(doc.get_str("replica.pool.idle"), Some("30s"));
+        assert_eq!(doc.nodes[0].type_annotation.as_deref(), Some("anchor"));
+        assert_eq!(doc.nodes[1].type_annotation, None);
+        assert_eq!(doc.nodes[2].anchor.as_deref(), Some("defaults"));
+
+        // Written out, the copies read back as plain blocks
+        let text = to_string(&doc);
+        assert!(text.starts_with("defaults!anchor {\n"));
+        assert!(text.contains("\nprimary {\n  host db.local\n"));
+        assert_eq!(parse(&text).unwrap(), doc);
+
+        // Anchors are scoped like `@let`, and may hold any value
+        let doc = parse("a {\n  tags!anchor [x, y]\n  b {\n    t!ref tags\n  }\n}\nc!ref tags\n");
+        assert_eq!(doc.unwrap_err().kind, ErrorKind::UndefinedReference);
+        let doc = parse("a {\n  tags!anchor [x, y]\n  b {\n    t!ref tags\n  }\n}\n").unwrap();
+        assert_eq!(doc.get("a.b.t"), doc.get("a.tags"));
+
+        // Streams and formatting see the merge form as opening a block
+        let parsed = Parser::new().parse_reader(input.as_bytes()).unwrap();
+        assert_eq!(parsed, parse(input).unwrap());
+        assert_eq!(crate::format(input).unwrap(), input);
+    }
+
+    #[test]
+    fn test_ref_errors() {
+        let err = parse("a!ref later\nlater!anchor {\n  x 1\n}\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UndefinedReference);
+        assert_eq!(
+            err.message,
+            "no anchor `later` is defined before this reference"
+        );
+        assert_eq!(err.line(), Some(1));
+
+        let err = parse("a!anchor {\n  b {\n    c!ref a\n  }\n}\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(err.message, "anchor `a` refers to itself");
+        assert_eq!(err.line(), Some(3));
+
+        let err = parse("n!anchor 5\nm!ref n {\n  x 1\n}\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        let err = parse("m!ref\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+
+        let err = Parser::new()
+            .parse_borrowed("a!anchor {\n}\nb!ref a\n")
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "`!ref` values are not supported when parsing borrowed"
+        );
+    }
+}
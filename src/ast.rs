@@ -0,0 +1,355 @@
+//! A stable traversal of documents for other formats, see [`AstSink`]
+
+use crate::{Document, Node, Value};
+
+/// Receives a document piece by piece from [`Document::emit`], for
+/// renderers and serializers written outside this crate
+///
+/// The sink sees every node in document order, repeated top-level keys
+/// included: a [`start_node`](Self::start_node), its value, and an
+/// [`end_node`](Self::end_node). A value is a [`scalar`](Self::scalar), or
+/// a block, list, or table whose contents come between its start and end
+/// calls: nodes in a block, values in a list, and rows of values in a
+/// table.
+///
+/// This is the interface to build on instead of matching on [`Value`],
+/// which grows a variant whenever UP gains a type. Here new methods come
+/// with default implementations, and [`NodeHeader`] and [`Scalar`] are
+/// `#[non_exhaustive]`, so a sink written today keeps compiling across
+/// minor versions. Every method does nothing by default; an error from any
+/// of them stops the traversal and is returned by [`Document::emit`].
+///
+/// ```
+/// use uplang::{AstSink, NodeHeader, Scalar};
+///
+/// /// Renders a document as nested HTML definition lists
+/// #[derive(Default)]
+/// struct Html(String);
+///
+/// impl AstSink for Html {
+///     type Error = std::convert::Infallible;
+///
+///     fn start_node(&mut self, node: NodeHeader<'_>) -> Result<(), Self::Error> {
+///         self.0 += &format!("<dt>{}</dt><dd>", node.key);
+///         Ok(())
+///     }
+///     fn end_node(&mut self) -> Result<(), Self::Error> {
+///         self.0 += "</dd>";
+///         Ok(())
+///     }
+///     fn start_block(&mut self) -> Result<(), Self::Error> {
+///         self.0 += "<dl>";
+///         Ok(())
+///     }
+///     fn end_block(&mut self) -> Result<(), Self::Error> {
+///         self.0 += "</dl>";
+///         Ok(())
+///     }
+///     fn scalar(&mut self, scalar: Scalar<'_>) -> Result<(), Self::Error> {
+///         match scalar {
+///             Scalar::Text(text) | Scalar::Typed { text, .. } => self.0 += text,
+///             Scalar::Int(n) => self.0 += &n.to_string(),
+///             _ => {}
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let doc = uplang::parse("name app\nserver {\n  port!int 80\n}\n").unwrap();
+/// let mut html = Html::default();
+/// doc.emit(&mut html).unwrap();
+/// assert_eq!(html.0, "<dt>name</dt><dd>app</dd><dt>server</dt><dd><dl><dt>port</dt><dd>80</dd></dl></dd>");
+/// ```
+pub trait AstSink {
+    /// What a sink fails with
+    type Error;
+
+    /// A node begins; its value follows
+    fn start_node(&mut self, node: NodeHeader<'_>) -> Result<(), Self::Error> {
+        let _ = node;
+        Ok(())
+    }
+
+    /// The node last started ends
+    fn end_node(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// A value that holds no other values
+    fn scalar(&mut self, scalar: Scalar<'_>) -> Result<(), Self::Error> {
+        let _ = scalar;
+        Ok(())
+    }
+
+    /// A block begins; its nodes follow
+    fn start_block(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The block last started ends
+    fn end_block(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// A list begins; its values follow
+    fn start_list(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The list last started ends
+    fn end_list(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// A table with these column names begins; its rows follow
+    fn start_table(&mut self, columns: &[&str]) -> Result<(), Self::Error> {
+        let _ = columns;
+        Ok(())
+    }
+
+    /// A row of the table begins; its values follow, one per column
+    fn start_row(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The row last started ends
+    fn end_row(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The table last started ends
+    fn end_table(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// What an [`AstSink`] is told about a node as it begins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NodeHeader<'a> {
+    /// The node's key
+    pub key: &'a str,
+    /// Its annotation, such as `int` for `port!int 80`
+    pub annotation: Option<&'a str>,
+    /// The comment lines above it, see [`Node::doc`]
+    pub comment: Option<&'a str>,
+    /// The 1-based line it was defined on, if it was parsed
+    pub line: Option<usize>,
+}
+
+/// A value that holds no other values, as an [`AstSink`] receives it
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Scalar<'a> {
+    /// `null`
+    Null,
+    /// A key written without a value
+    Empty,
+    /// A boolean
+    Bool(bool),
+    /// An integer
+    Int(i64),
+    /// A floating-point number
+    Float(f64),
+    /// A string
+    Text(&'a str),
+    /// A value of any other type, such as a date or an amount of money, as
+    /// the text it has in UP
+    Typed {
+        /// The type's name, such as `date`
+        type_name: &'a str,
+        /// The value's text, such as `2024-03-15`
+        text: &'a str,
+    },
+}
+
+impl Document {
+    /// Send the document to `sink`, node by node in document order
+    ///
+    /// See [`AstSink`] for the calls a sink receives.
+    pub fn emit<S: AstSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        for node in &self.nodes {
+            emit_node(node, sink)?;
+        }
+        Ok(())
+    }
+}
+
+fn emit_node<S: AstSink>(node: &Node, sink: &mut S) -> Result<(), S::Error> {
+    sink.start_node(NodeHeader {
+        key: &node.key,
+        annotation: node.type_annotation.as_deref(),
+        comment: node.doc.as_deref(),
+        line: node.origin.as_ref().map(|origin| origin.line),
+    })?;
+    emit_value(&node.value, sink)?;
+    sink.end_node()
+}
+
+fn emit_value<S: AstSink>(value: &Value, sink: &mut S) -> Result<(), S::Error> {
+    match value {
+        Value::Block(block) => {
+            sink.start_block()?;
+            for node in block.nodes() {
+                emit_node(node, sink)?;
+            }
+            sink.end_block()
+        }
+        Value::List(items) => {
+            sink.start_list()?;
+            for item in items {
+                emit_value(item, sink)?;
+            }
+            sink.end_list()
+        }
+        Value::Table { columns, rows } => {
+            let names: Vec<_> = columns
+                .iter()
+                .map(|column| column.scalar_text().unwrap_or_default())
+                .collect();
+            let names: Vec<&str> = names.iter().map(|name| name.as_ref()).collect();
+            sink.start_table(&names)?;
+            for row in rows {
+                sink.start_row()?;
+                for cell in row {
+                    emit_value(cell, sink)?;
+                }
+                sink.end_row()?;
+            }
+            sink.end_table()
+        }
+        Value::Null => sink.scalar(Scalar::Null),
+        Value::Empty => sink.scalar(Scalar::Empty),
+        Value::Bool(b) => sink.scalar(Scalar::Bool(*b)),
+        Value::Int(n) => sink.scalar(Scalar::Int(*n)),
+        Value::Float(x) => sink.scalar(Scalar::Float(*x)),
+        Value::String(text) => sink.scalar(Scalar::Text(text)),
+        typed => {
+            let text = typed.scalar_text().unwrap_or_default();
+            let type_name = match typed {
+                Value::Custom(custom) => custom.annotation(),
+                typed => typed.type_name(),
+            };
+            sink.scalar(Scalar::Typed {
+                type_name,
+                text: &text,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    /// Writes each call on a line, indented by nesting
+    #[derive(Default)]
+    struct Trace {
+        out: Vec<String>,
+        depth: usize,
+    }
+
+    impl Trace {
+        fn push(&mut self, line: String) {
+            self.out
+                .push(format!("{}{}", "  ".repeat(self.depth), line));
+        }
+    }
+
+    impl AstSink for Trace {
+        type Error = String;
+
+        fn start_node(&mut self, node: NodeHeader<'_>) -> Result<(), String> {
+            if node.key == "stop" {
+                return Err(format!("stopped on line {:?}", node.line));
+            }
+            self.push(format!(
+                "node {} {:?} {:?}",
+                node.key, node.annotation, node.comment
+            ));
+            self.depth += 1;
+            Ok(())
+        }
+        fn end_node(&mut self) -> Result<(), String> {
+            self.depth -= 1;
+            Ok(())
+        }
+        fn scalar(&mut self, scalar: Scalar<'_>) -> Result<(), String> {
+            self.push(format!("{:?}", scalar));
+            Ok(())
+        }
+        fn start_list(&mut self) -> Result<(), String> {
+            self.push("list".to_string());
+            Ok(())
+        }
+        fn start_table(&mut self, columns: &[&str]) -> Result<(), String> {
+            self.push(format!("table {:?}", columns));
+            Ok(())
+        }
+        fn start_row(&mut self) -> Result<(), String> {
+            self.push("row".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_emit() {
+        let doc = parse(
+            "\
+# the app
+name app
+opens!date 2024-03-15
+flag
+server {
+  ratio!float 0.5
+  tags [a, 2]
+}
+users!table {
+  columns [id!int, name]
+  rows [
+    [1, ann]
+  ]
+}
+name again
+",
+        )
+        .unwrap();
+        let mut trace = Trace::default();
+        doc.emit(&mut trace).unwrap();
+        assert_eq!(
+            trace.out,
+            [
+                "node name None Some(\"the app\")",
+                "  Text(\"app\")",
+                "node opens Some(\"date\") None",
+                "  Typed { type_name: \"date\", text: \"2024-03-15\" }",
+                "node flag None None",
+                "  Empty",
+                "node server None None",
+                "  node ratio Some(\"float\") None",
+                "    Float(0.5)",
+                "  node tags None None",
+                "    list",
+                "    Text(\"a\")",
+                "    Text(\"2\")",
+                "node users Some(\"table\") None",
+                "  table [\"id\", \"name\"]",
+                "  row",
+                "  Int(1)",
+                "  Text(\"ann\")",
+                "node name None None",
+                "  Text(\"again\")",
+            ]
+        );
+
+        // An error stops the traversal
+        let doc = parse("a 1\nstop 2\nc 3\n").unwrap();
+        let mut trace = Trace::default();
+        assert_eq!(
+            doc.emit(&mut trace),
+            Err("stopped on line Some(2)".to_string())
+        );
+        assert_eq!(trace.out, ["node a None None", "  Text(\"1\")"]);
+    }
+}
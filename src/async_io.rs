@@ -0,0 +1,225 @@
+//! Reading documents from tokio's async files and readers
+//!
+//! Lines are read with [`AsyncBufRead`], so a slow socket or pipe never
+//! blocks the runtime's thread, and each top-level node is parsed as soon
+//! as its last line arrives, as [`Parser::read_nodes`] does for blocking
+//! readers. Parsing itself runs on the task that polls, a node at a time.
+//! Documents pulled in with `@include` are still read by the parser's
+//! [`IncludeResolver`](crate::IncludeResolver), which blocks.
+//!
+//! ```
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! use futures_core::Stream;
+//! use std::pin::pin;
+//!
+//! let doc = uplang::async_io::parse_reader("name app\nport!int 8080\n".as_bytes()).await?;
+//! assert_eq!(doc.get_int("port"), Some(8080));
+//!
+//! let parser = uplang::Parser::new();
+//! let mut nodes = pin!(parser.read_nodes_async("a 1\nb 2\n".as_bytes()));
+//! while let Some(node) = std::future::poll_fn(|cx| nodes.as_mut().poll_next(cx)).await {
+//!     println!("{}", node?.key);
+//! }
+//! # Ok::<(), uplang::ParseError>(())
+//! # }).unwrap();
+//! ```
+
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::AsyncBufRead;
+
+use crate::source::file_error;
+use crate::stream::NodeChunks;
+use crate::{Document, ErrorKind, Node, ParseError, Parser};
+
+/// Read and parse a file, as [`Parser::parse_file`] does
+pub async fn parse_file(path: impl AsRef<Path>) -> Result<Document, ParseError> {
+    Parser::new().parse_file_async(path).await
+}
+
+/// Read and parse a document from an async reader, as
+/// [`Parser::parse_reader`] does
+pub async fn parse_reader(reader: impl AsyncBufRead + Unpin) -> Result<Document, ParseError> {
+    Parser::new().parse_reader_async(reader).await
+}
+
+impl Parser {
+    /// Read and parse a file without blocking; see [`async_io`](crate::async_io)
+    pub async fn parse_file_async(&self, path: impl AsRef<Path>) -> Result<Document, ParseError> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        self.parse_file_bytes(path, &bytes)
+    }
+
+    /// Read and parse a document from an async reader
+    ///
+    /// Equivalent to collecting [`read_nodes_async`](Self::read_nodes_async)
+    /// into a document.
+    pub async fn parse_reader_async(
+        &self,
+        reader: impl AsyncBufRead + Unpin,
+    ) -> Result<Document, ParseError> {
+        let mut nodes = self.read_nodes_async(reader);
+        let mut doc = Document::new();
+        while let Some(node) = std::future::poll_fn(|cx| Pin::new(&mut nodes).poll_next(cx)).await {
+            doc.nodes.push(node?);
+        }
+        Ok(doc)
+    }
+
+    /// Stream the top-level nodes of a document as they are read from an
+    /// async reader
+    ///
+    /// As with [`read_nodes`](Self::read_nodes), only the lines of the
+    /// node being read are held in memory, line numbers count from the
+    /// start of the stream, and the stream ends after the first error.
+    pub fn read_nodes_async<R: AsyncBufRead + Unpin>(&self, reader: R) -> NodeStream<'_, R> {
+        NodeStream {
+            chunks: NodeChunks::new(self),
+            reader,
+            line: Vec::new(),
+            finished: false,
+        }
+    }
+}
+
+/// Stream of the top-level nodes of an async reader, see
+/// [`Parser::read_nodes_async`]
+pub struct NodeStream<'p, R> {
+    chunks: NodeChunks<'p>,
+    reader: R,
+    /// The part of the next line read so far
+    line: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> NodeStream<'_, R> {
+    /// Read the next line, parsing the node it ends, if any
+    fn poll_line(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ParseError>> {
+        let io_error = |e: std::io::Error| ParseError::new(ErrorKind::Io, e.to_string());
+        loop {
+            let available =
+                ready!(Pin::new(&mut self.reader).poll_fill_buf(cx)).map_err(io_error)?;
+            if available.is_empty() {
+                if self.line.is_empty() {
+                    self.finished = true;
+                    return Poll::Ready(self.chunks.finish());
+                }
+                return Poll::Ready(self.end_line());
+            }
+            // Stop at the limit, for `line_read` to report it
+            let room = usize::try_from(self.chunks.remaining()).unwrap_or(usize::MAX);
+            let room = room.saturating_sub(self.line.len()).max(1);
+            let available = &available[..available.len().min(room)];
+            let (used, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), available.len() == room),
+            };
+            self.line.extend_from_slice(&available[..used]);
+            Pin::new(&mut self.reader).consume(used);
+            if complete {
+                return Poll::Ready(self.end_line());
+            }
+        }
+    }
+
+    /// Add the line read to the node's lines
+    fn end_line(&mut self) -> Result<(), ParseError> {
+        self.chunks.check_size(self.line.len())?;
+        let line = std::mem::take(&mut self.line);
+        let line = String::from_utf8(line)
+            .map_err(|_| ParseError::new(ErrorKind::Io, "stream did not contain valid UTF-8"))?;
+        let start = self.chunks.buffer().len();
+        self.chunks.buffer().push_str(&line);
+        self.chunks.line_read(start)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for NodeStream<'_, R> {
+    type Item = Result<Node, ParseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(node) = this.chunks.pop() {
+                return Poll::Ready(Some(Ok(node)));
+            }
+            if this.finished {
+                return Poll::Ready(None);
+            }
+            if let Err(e) = ready!(this.poll_line(cx)) {
+                this.finished = true;
+                return Poll::Ready(Some(Err(e)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limits, Value};
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_parse_file_and_reader() {
+        let path = std::env::temp_dir().join(format!("uplang-async-{}.up", std::process::id()));
+        std::fs::write(&path, "name app\nserver {\n  port!int 8080\n}\n").unwrap();
+        let doc = parse_file(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(doc.get_int("server.port"), Some(8080));
+        assert_eq!(
+            doc.nodes[1].origin.as_ref().unwrap().file.as_deref(),
+            Some(path.as_path())
+        );
+
+        let err = parse_file(&path).await.unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+
+        let input = "\u{feff}a 1\nb {\n  c 2\n}\n";
+        let doc = parse_reader(input.as_bytes()).await.unwrap();
+        assert_eq!(doc, crate::parse_bytes(input.as_bytes()).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_nodes_arrive_as_they_are_written() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let parser = Parser::new();
+        let mut nodes = parser.read_nodes_async(BufReader::new(reader));
+
+        writer.write_all(b"name app\nlist [\n  a\n").await.unwrap();
+        assert_eq!(next(&mut nodes).await.unwrap().unwrap().key, "name");
+        writer.write_all(b"  b\n]\nport!int x\n").await.unwrap();
+        let list = next(&mut nodes).await.unwrap().unwrap();
+        assert_eq!(
+            list.value,
+            Value::List(vec![Value::String("a".into()), Value::String("b".into())])
+        );
+        let err = next(&mut nodes).await.unwrap().unwrap_err();
+        assert_eq!(err.line(), Some(6));
+        assert!(next(&mut nodes).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_encoding() {
+        let parser = Parser::new().limits(Limits::new().max_size(16));
+        let long = format!("a {}\n", "x".repeat(1000));
+        let err = parser
+            .parse_reader_async(long.as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+
+        let err = parse_reader(&b"a \xff\n"[..]).await.unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+    }
+}
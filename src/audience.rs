@@ -0,0 +1,161 @@
+//! Scoped views of a document for different audiences
+
+use crate::redact::MASK;
+use crate::{Block, Document, Node, Value};
+
+/// Who a view of a document is for, see [`Document::view_for`]
+///
+/// Nodes are tagged with the name of an audience level as their
+/// annotation, such as `db!internal { ... }`. Levels are ordered from the
+/// widest audience to the narrowest, `public` then `internal` unless set
+/// otherwise, and each audience sees the nodes tagged with its own level or
+/// a wider one. Untagged nodes are seen by whoever sees their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audience {
+    name: String,
+    levels: Vec<String>,
+    redact: bool,
+}
+
+impl Audience {
+    /// The audience at level `name`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            levels: vec!["public".to_string(), "internal".to_string()],
+            redact: false,
+        }
+    }
+
+    /// Set the audience levels, widest first (default `public`, `internal`)
+    ///
+    /// An audience whose name is not one of the levels sees only untagged
+    /// nodes.
+    pub fn levels<I>(mut self, levels: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.levels = levels.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Keep the nodes this audience may not see, with `***` for a value,
+    /// instead of leaving them out (default off)
+    pub fn redact(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Whether a node with this annotation may be seen; `None` if the
+    /// annotation is not an audience level
+    fn sees(&self, annotation: &str) -> Option<bool> {
+        let level = self.levels.iter().position(|level| level == annotation)?;
+        let own = self.levels.iter().position(|level| *level == self.name);
+        Some(own.is_some_and(|own| level <= own))
+    }
+}
+
+impl From<&str> for Audience {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl Document {
+    /// The part of the document that `audience` may see
+    ///
+    /// Nodes tagged with a narrower audience level are left out, or masked
+    /// with [`Audience::redact`]. Tags the audience may see are removed, so
+    /// the view reads as a plain document. Source text and overlay history
+    /// are dropped, as they could show what was left out.
+    ///
+    /// ```
+    /// let doc = uplang::parse("name app\ndb!internal {\n  host db.local\n}\nurl!public https://app.example.com\n").unwrap();
+    /// let public = doc.view_for("public");
+    /// assert_eq!(uplang::to_string(&public), "name app\nurl https://app.example.com\n");
+    /// assert_eq!(doc.view_for("internal").get_str("db.host"), Some("db.local"));
+    /// ```
+    pub fn view_for(&self, audience: impl Into<Audience>) -> Document {
+        let audience = audience.into();
+        Document {
+            nodes: view_nodes(self.nodes.iter(), &audience),
+        }
+    }
+}
+
+fn view_nodes<'a>(nodes: impl Iterator<Item = &'a Node>, audience: &Audience) -> Vec<Node> {
+    nodes.filter_map(|node| view_node(node, audience)).collect()
+}
+
+fn view_node(node: &Node, audience: &Audience) -> Option<Node> {
+    let mut node = node.clone();
+    node.trivia = None;
+    node.provenance.clear();
+    match node
+        .type_annotation
+        .as_deref()
+        .and_then(|a| audience.sees(a))
+    {
+        Some(false) if audience.redact => {
+            node.value = Value::String(MASK.to_string());
+            return Some(node);
+        }
+        Some(false) => return None,
+        Some(true) => node.type_annotation = None,
+        None => {}
+    }
+    view_value(&mut node.value, audience);
+    Some(node)
+}
+
+fn view_value(value: &mut Value, audience: &Audience) {
+    match value {
+        Value::Block(block) => {
+            *block = view_nodes(block.nodes(), audience)
+                .into_iter()
+                .collect::<Block>();
+        }
+        Value::List(items) => {
+            for item in items {
+                view_value(item, audience);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string};
+
+    #[test]
+    fn test_views() {
+        let doc = parse("name app\nadmin!internal {\n  token abc\n}\nserver {\n  host!public app.example.com\n  debug!internal true\n}\nnodes [\n  {\n    ip!internal 10.0.0.1\n    region eu\n  }\n]\nport!int 80\n").unwrap();
+
+        let public = doc.view_for("public");
+        assert_eq!(
+            to_string(&public),
+            "name app\nserver {\n  host app.example.com\n}\nnodes [\n  {\n    region eu\n  }\n]\nport!int 80\n"
+        );
+        let internal = doc.view_for("internal");
+        assert_eq!(internal.get_str("admin.token"), Some("abc"));
+        assert_eq!(internal.get_str("nodes[0].ip"), Some("10.0.0.1"));
+        assert!(!to_string(&internal).contains("!internal"));
+
+        let redacted = doc.view_for(Audience::new("public").redact(true));
+        assert_eq!(redacted.get_str("admin"), Some(MASK));
+        assert_eq!(redacted.get_str("server.debug"), Some(MASK));
+        assert!(to_string(&redacted).contains("admin!internal ***\n"));
+
+        // Audiences outside the levels see only untagged nodes
+        let view = doc.view_for("partner");
+        assert!(view.get("server.host").is_none());
+        assert_eq!(view.get_str("name"), Some("app"));
+
+        let doc = parse("a!team 1\nb!everyone 2\n").unwrap();
+        let view = doc.view_for(Audience::new("everyone").levels(["everyone", "team"]));
+        assert_eq!(to_string(&view), "b 2\n");
+    }
+}
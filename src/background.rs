@@ -0,0 +1,289 @@
+//! Parsing on a background thread, with a future to wait for the result
+//!
+//! Each call reads and parses on a thread of its own, with the blocking
+//! [`Read`] and [`BufRead`] of the standard library, and the futures and
+//! streams here only wait for what the thread sends back. That keeps the
+//! parsing off an async caller's executor, whichever it is, but it is not
+//! async I/O: nothing here reads from an async reader, and each call holds
+//! an OS thread until it is done. A socket or pipe owned by a runtime has
+//! to be converted to its blocking form to be handed over; with tokio,
+//! the `async_io` module of the `tokio` feature reads it directly.
+//!
+//! ```
+//! # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+//! #     use std::task::{Context, Poll, Wake, Waker};
+//! #     struct Spin;
+//! #     impl Wake for Spin {
+//! #         fn wake(self: std::sync::Arc<Self>) {}
+//! #     }
+//! #     let waker = Waker::from(std::sync::Arc::new(Spin));
+//! #     let mut future = std::pin::pin!(future);
+//! #     loop {
+//! #         if let Poll::Ready(out) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+//! #             return out;
+//! #         }
+//! #         std::thread::yield_now();
+//! #     }
+//! # }
+//! # block_on(async {
+//! let doc = uplang::background::parse_reader("name app\nport!int 8080\n".as_bytes()).await?;
+//! assert_eq!(doc.get_int("port"), Some(8080));
+//!
+//! let mut nodes = uplang::background::read_nodes("a 1\nb 2\n".as_bytes());
+//! while let Some(node) = nodes.next().await {
+//!     println!("{}", node?.key);
+//! }
+//! # Ok::<(), uplang::ParseError>(())
+//! # }).unwrap();
+//! ```
+
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::{Document, ErrorKind, Node, ParseError, Parser};
+
+/// How many parsed nodes a [`NodeStream`] reads ahead of its consumer
+const READ_AHEAD: usize = 16;
+
+/// Parse a file on a background thread, as [`Parser::parse_file`] does
+pub fn parse_file(path: impl AsRef<Path>) -> Parsing<Document> {
+    Parser::new().parse_file_in_background(path)
+}
+
+/// Parse a document from a reader on a background thread, as
+/// [`Parser::parse_reader`] does
+pub fn parse_reader(reader: impl Read + Send + 'static) -> Parsing<Document> {
+    Parser::new().parse_reader_in_background(reader)
+}
+
+/// Stream the top-level nodes of a document as a background thread reads
+/// them, as [`Parser::read_nodes`] does
+pub fn read_nodes(reader: impl BufRead + Send + 'static) -> NodeStream {
+    Parser::new().read_nodes_in_background(reader)
+}
+
+impl Parser {
+    /// Parse a file on a background thread; see [`background`](crate::background)
+    pub fn parse_file_in_background(&self, path: impl AsRef<Path>) -> Parsing<Document> {
+        let parser = self.clone();
+        let path: PathBuf = path.as_ref().to_path_buf();
+        Parsing::spawn(move || parser.parse_file(path))
+    }
+
+    /// Parse a document from a reader on a background thread
+    pub fn parse_reader_in_background(
+        &self,
+        reader: impl Read + Send + 'static,
+    ) -> Parsing<Document> {
+        let parser = self.clone();
+        Parsing::spawn(move || parser.parse_reader(BufReader::new(reader)))
+    }
+
+    /// Stream the top-level nodes of a document, read and parsed on a
+    /// background thread
+    ///
+    /// The thread reads a few nodes ahead of the consumer and then waits,
+    /// so a large document is never held in memory at once. It stops when
+    /// the stream is dropped. As with [`read_nodes`](Self::read_nodes), the
+    /// stream ends after the first error.
+    pub fn read_nodes_in_background(&self, reader: impl BufRead + Send + 'static) -> NodeStream {
+        let parser = self.clone();
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD);
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let wake = Arc::clone(&waker);
+        thread::spawn(move || {
+            for node in parser.read_nodes(reader) {
+                if sender.send(node).is_err() {
+                    // The stream was dropped
+                    return;
+                }
+                wake_up(&wake);
+            }
+            drop(sender);
+            wake_up(&wake);
+        });
+        NodeStream { receiver, waker }
+    }
+}
+
+/// A document being parsed on a background thread; a future of the result
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct Parsing<T> {
+    shared: Arc<Mutex<Slot<T>>>,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    result: Option<Result<T, ParseError>>,
+    waker: Option<Waker>,
+}
+
+impl<T: Send + 'static> Parsing<T> {
+    fn spawn(parse: impl FnOnce() -> Result<T, ParseError> + Send + 'static) -> Self {
+        let shared = Arc::new(Mutex::new(Slot {
+            result: None,
+            waker: None,
+        }));
+        let slot = Arc::clone(&shared);
+        thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(parse)).unwrap_or_else(|_| {
+                Err(ParseError::new(
+                    ErrorKind::Io,
+                    "the parsing thread stopped without a result",
+                ))
+            });
+            let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { shared }
+    }
+}
+
+impl<T> Future for Parsing<T> {
+    type Output = Result<T, ParseError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(result) = slot.result.take() {
+            return Poll::Ready(result);
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The top-level nodes of a document, read on a background thread; see
+/// [`Parser::read_nodes_in_background`]
+///
+/// [`poll_next`](Self::poll_next) has the shape of the `Stream` trait of
+/// the `futures` crate, so the stream can be adapted to it with
+/// `futures::stream::poll_fn`.
+#[derive(Debug)]
+pub struct NodeStream {
+    receiver: Receiver<Result<Node, ParseError>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl NodeStream {
+    /// The next node, or `None` at the end of the document
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Node, ParseError>>> {
+        match self.receiver.try_recv() {
+            Ok(node) => return Poll::Ready(Some(node)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+            Err(TryRecvError::Empty) => {}
+        }
+        *self.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(cx.waker().clone());
+        // A node sent before the waker was stored would not wake us
+        match self.receiver.try_recv() {
+            Ok(node) => Poll::Ready(Some(node)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+
+    /// Wait for the next node, or `None` at the end of the document
+    pub async fn next(&mut self) -> Option<Result<Node, ParseError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+fn wake_up(waker: &Mutex<Option<Waker>>) {
+    if let Some(waker) = waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        waker.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    /// Counts its wake-ups, so the tests can wait for them
+    struct Counter(AtomicUsize);
+
+    impl Wake for Counter {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Poll `future` to completion, sleeping until it is woken
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(Arc::clone(&counter));
+        let mut future = std::pin::pin!(future);
+        loop {
+            let seen = counter.0.load(Ordering::SeqCst);
+            if let Poll::Ready(out) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return out;
+            }
+            while counter.0.load(Ordering::SeqCst) == seen {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_in_background() {
+        let path =
+            std::env::temp_dir().join(format!("uplang-background-{}.up", std::process::id()));
+        std::fs::write(&path, "name app\nport!int 8080\n").unwrap();
+        let doc = block_on(parse_file(&path)).unwrap();
+        assert_eq!(doc.get_int("port"), Some(8080));
+        std::fs::remove_file(&path).unwrap();
+        let err = block_on(parse_file(&path)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+
+        let doc = block_on(
+            Parser::new()
+                .strict(true)
+                .parse_reader_in_background("a 1\n".as_bytes()),
+        );
+        assert_eq!(doc.unwrap().get_str("a"), Some("1"));
+        let err = block_on(parse_reader("p!int x\n".as_bytes())).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn test_read_nodes_in_background() {
+        let text: String = (0..100)
+            .map(|i| format!("k{} {{\n  v {}\n}}\n", i, i))
+            .collect();
+        let keys = block_on(async move {
+            let mut nodes = read_nodes(std::io::Cursor::new(text));
+            let mut keys = Vec::new();
+            while let Some(node) = nodes.next().await {
+                keys.push(node.unwrap().key);
+            }
+            keys
+        });
+        assert_eq!(keys.len(), 100);
+        assert_eq!(keys[99], "k99");
+
+        // Stops at the first error
+        let results = block_on(async {
+            let mut nodes = read_nodes("a 1\nb!int x\nc 3\n".as_bytes());
+            let mut results = Vec::new();
+            while let Some(node) = nodes.next().await {
+                results.push(node.is_ok());
+            }
+            results
+        });
+        assert_eq!(results, [true, false]);
+    }
+}
@@ -0,0 +1,407 @@
+//! Insertion-ordered blocks
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+use crate::{Node, Value};
+
+/// What the parser does when a block defines a key twice, see
+/// [`Parser::duplicate_keys`](crate::Parser::duplicate_keys)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with [`ErrorKind::DuplicateKey`](crate::ErrorKind::DuplicateKey)
+    Error,
+    /// Keep the first definition and ignore later ones
+    FirstWins,
+    /// Replace the value, keeping the key's first position (default)
+    #[default]
+    LastWins,
+    /// Gather every definition's value into a list, in order
+    Collect,
+}
+
+/// The key/value pairs of a block, in the order they were written
+///
+/// Entries are full [`Node`]s, so nested keys keep their type annotations
+/// and source locations. Lookups by key are O(1). Like a map, a block holds
+/// each key at most once: inserting an existing key replaces its value in
+/// place.
+///
+/// Iteration order is stable: it only changes through
+/// [`insert`](Self::insert) of a new key (at the end),
+/// [`insert_at`](Self::insert_at), [`move_key`](Self::move_key), and
+/// removal, so generated output lists keys exactly where they were put.
+///
+/// Equality ignores order, so two blocks are equal when they hold the same
+/// keys with equal nodes.
+#[derive(Clone, Default)]
+pub struct Block {
+    nodes: Vec<Node>,
+    positions: HashMap<String, usize>,
+}
+
+impl Block {
+    /// Create an empty block
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if the block has no entries
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Check whether `key` is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Look up a value by key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.get_node(key).map(|node| &node.value)
+    }
+
+    /// Look up a value by key for modification
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let i = *self.positions.get(key)?;
+        Some(&mut self.nodes[i].value)
+    }
+
+    /// Look up the full node (annotation, origin) for a key
+    pub fn get_node(&self, key: &str) -> Option<&Node> {
+        self.positions.get(key).map(|&i| &self.nodes[i])
+    }
+
+    /// Look up the full node for modification; callers must not change its key
+    pub(crate) fn get_node_mut(&mut self, key: &str) -> Option<&mut Node> {
+        let i = *self.positions.get(key)?;
+        Some(&mut self.nodes[i])
+    }
+
+    /// Position of `key` in iteration order
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.positions.get(key).copied()
+    }
+
+    /// Key and value at `index` in iteration order
+    pub fn get_index(&self, index: usize) -> Option<(&str, &Value)> {
+        self.nodes
+            .get(index)
+            .map(|node| (node.key.as_str(), &node.value))
+    }
+
+    /// Node at `index` in iteration order
+    pub fn get_node_index(&self, index: usize) -> Option<&Node> {
+        self.nodes.get(index)
+    }
+
+    /// Insert an unannotated value, returning the value it replaced
+    ///
+    /// A new key goes at the end; an existing key keeps its position.
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.insert_node(Node::new(key, value)).map(|old| old.value)
+    }
+
+    /// Insert a node under its own key, returning the node it replaced
+    pub fn insert_node(&mut self, node: Node) -> Option<Node> {
+        match self.positions.get(&node.key) {
+            Some(&i) => Some(std::mem::replace(&mut self.nodes[i], node)),
+            None => {
+                self.positions.insert(node.key.clone(), self.nodes.len());
+                self.nodes.push(node);
+                None
+            }
+        }
+    }
+
+    /// Add a node for a key that is not present, at the end
+    pub(crate) fn push_new(&mut self, node: Node) {
+        debug_assert!(!self.contains_key(&node.key));
+        self.positions.insert(node.key.clone(), self.nodes.len());
+        self.nodes.push(node);
+    }
+
+    /// Insert an unannotated value at `index`, returning the value it
+    /// replaced
+    ///
+    /// An existing entry for `key` is moved, as with
+    /// [`insert_node_at`](Self::insert_node_at).
+    pub fn insert_at(
+        &mut self,
+        index: usize,
+        key: impl Into<String>,
+        value: Value,
+    ) -> Option<Value> {
+        self.insert_node_at(index, Node::new(key, value))
+            .map(|old| old.value)
+    }
+
+    /// Move `key` to `index`, keeping its node; returns `false` if it is
+    /// not present
+    ///
+    /// `index` is clamped to the last position, so `usize::MAX` moves the
+    /// key to the end.
+    pub fn move_key(&mut self, key: &str, index: usize) -> bool {
+        let Some(from) = self.position(key) else {
+            return false;
+        };
+        let to = index.min(self.nodes.len() - 1);
+        if from < to {
+            self.nodes[from..=to].rotate_left(1);
+        } else {
+            self.nodes[to..=from].rotate_right(1);
+        }
+        self.reindex(from.min(to));
+        true
+    }
+
+    /// Insert a node at `index`, moving an existing entry with the same key
+    ///
+    /// `index` counts positions after any such entry has been taken out and
+    /// is clamped to the length of the block. Returns the node it replaced.
+    pub fn insert_node_at(&mut self, index: usize, node: Node) -> Option<Node> {
+        let old = self.remove_node(&node.key);
+        let index = index.min(self.nodes.len());
+        self.nodes.insert(index, node);
+        self.reindex(index);
+        old
+    }
+
+    /// Record the positions of the entries from `start` on
+    fn reindex(&mut self, start: usize) {
+        for (i, node) in self.nodes.iter().enumerate().skip(start) {
+            self.positions.insert(node.key.clone(), i);
+        }
+    }
+
+    /// Remove a key, returning its value; later entries move up one place
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.remove_node(key).map(|node| node.value)
+    }
+
+    /// Remove a key, returning its node
+    pub fn remove_node(&mut self, key: &str) -> Option<Node> {
+        let i = self.positions.remove(key)?;
+        let node = self.nodes.remove(i);
+        for node in &self.nodes[i..] {
+            if let Some(pos) = self.positions.get_mut(&node.key) {
+                *pos -= 1;
+            }
+        }
+        Some(node)
+    }
+
+    /// Iterate over keys and values in order
+    ///
+    /// Use [`Iterator::enumerate`] for positions; they match
+    /// [`position`](Self::position) and [`get_index`](Self::get_index).
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(&self.nodes)
+    }
+
+    /// Iterate over keys and mutable values in order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Value)> {
+        self.nodes
+            .iter_mut()
+            .map(|node| (node.key.as_str(), &mut node.value))
+    }
+
+    /// Iterate over the entries as nodes, in order
+    pub fn nodes(&self) -> std::slice::Iter<'_, Node> {
+        self.nodes.iter()
+    }
+
+    /// Iterate over keys in order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().map(|node| node.key.as_str())
+    }
+
+    /// Iterate over values in order
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.nodes.iter().map(|node| &node.value)
+    }
+
+    /// Mutable access to the nodes; callers must not change their keys
+    pub(crate) fn nodes_mut(&mut self) -> std::slice::IterMut<'_, Node> {
+        self.nodes.iter_mut()
+    }
+
+    /// Consume the block, yielding its nodes in order
+    pub fn into_nodes(self) -> Vec<Node> {
+        self.nodes
+    }
+}
+
+/// Iterator over a block's keys and values, see [`Block::iter`]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    inner: std::slice::Iter<'a, Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|node| (node.key.as_str(), &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|node| (node.key.as_str(), &node.value))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> Iter<'a> {
+    /// Iterate over the keys and values of `nodes`
+    pub(crate) fn new(nodes: &'a [Node]) -> Self {
+        Self {
+            inner: nodes.iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Block {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Index<&str> for Block {
+    type Output = Value;
+
+    /// The value of `key`
+    ///
+    /// # Panics
+    ///
+    /// If the block has no such key; use [`get`](Block::get) otherwise.
+    fn index(&self, key: &str) -> &Value {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no key `{}` in block", key),
+        }
+    }
+}
+
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .nodes
+                .iter()
+                .all(|node| other.get_node(&node.key) == Some(node))
+    }
+}
+
+impl fmt::Debug for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, Value)> for Block {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        let mut block = Block::new();
+        for (key, value) in iter {
+            block.insert(key, value);
+        }
+        block
+    }
+}
+
+impl FromIterator<Node> for Block {
+    fn from_iter<I: IntoIterator<Item = Node>>(iter: I) -> Self {
+        let mut block = Block::new();
+        for node in iter {
+            block.insert_node(node);
+        }
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_block_preserves_order() {
+        let mut block: Block = [("zeta", string("1")), ("alpha", string("2"))]
+            .into_iter()
+            .collect();
+        block.insert("mid", string("3"));
+        assert_eq!(block.keys().collect::<Vec<_>>(), ["zeta", "alpha", "mid"]);
+
+        assert_eq!(block.insert("zeta", string("4")), Some(string("1")));
+        assert_eq!(block.keys().collect::<Vec<_>>(), ["zeta", "alpha", "mid"]);
+
+        assert_eq!(block.remove("zeta"), Some(string("4")));
+        assert_eq!(block.keys().collect::<Vec<_>>(), ["alpha", "mid"]);
+        assert_eq!(block.get("mid"), Some(&string("3")));
+        assert_eq!(block.position("mid"), Some(1));
+    }
+
+    #[test]
+    fn test_block_positions() {
+        let mut block: Block = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|key| (key, string(key)))
+            .collect();
+        let keys = |block: &Block| block.keys().collect::<Vec<_>>().join("");
+
+        assert!(block.move_key("a", 2));
+        assert_eq!(keys(&block), "bcad");
+        assert!(block.move_key("d", 0));
+        assert_eq!(keys(&block), "dbca");
+        assert!(block.move_key("b", usize::MAX));
+        assert_eq!(keys(&block), "dcab");
+        assert!(!block.move_key("x", 0));
+
+        assert_eq!(block.insert_at(1, "e", string("e")), None);
+        assert_eq!(keys(&block), "decab");
+        assert_eq!(block.insert_at(0, "b", string("B")), Some(string("b")));
+        assert_eq!(keys(&block), "bdeca");
+
+        for (i, (key, _)) in block.iter().enumerate() {
+            assert_eq!(block.position(key), Some(i));
+            assert_eq!(block.get_index(i).map(|(k, _)| k), Some(key));
+        }
+        assert_eq!(block.get_index(5), None);
+        assert_eq!(block.get_node_index(0).unwrap().value, string("B"));
+    }
+
+    #[test]
+    fn test_block_equality_ignores_order() {
+        let a: Block = [("x", string("1")), ("y", string("2"))]
+            .into_iter()
+            .collect();
+        let b: Block = [("y", string("2")), ("x", string("1"))]
+            .into_iter()
+            .collect();
+        let c: Block = [("x", string("1"))].into_iter().collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,531 @@
+//! Parsing into values that borrow their text from the input
+
+use std::borrow::Cow;
+
+use crate::error::offset_in;
+use crate::inline::is_inline_block;
+use crate::lexer::{self, Close, Lexer, Token};
+use crate::quote;
+use crate::{
+    collect_comment, continues, dedent_common, fence_lang, keyed_item, Block, Document, ErrorKind,
+    Node, Origin, ParseError, Parser, Value,
+};
+
+/// A document whose keys and text borrow from the parsed input, see
+/// [`Parser::parse_borrowed`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentRef<'a> {
+    /// Top-level nodes in the document
+    pub nodes: Vec<NodeRef<'a>>,
+}
+
+/// A key-value node borrowing from the parsed input
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRef<'a> {
+    /// The key name
+    pub key: &'a str,
+    /// Type annotation, as written
+    pub type_annotation: Option<&'a str>,
+    /// The value
+    pub value: ValueRef<'a>,
+    /// 1-based line number of the key
+    pub line: usize,
+    /// Text of the `#` comment lines directly above the key, without the `#`
+    pub doc: Option<Cow<'a, str>>,
+    /// Language tag of a fenced multiline value
+    pub lang: Option<&'a str>,
+}
+
+/// A value borrowing from the parsed input
+///
+/// Scalars are text; annotations are not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    /// Scalar or multiline text, borrowed unless it had to be rewritten
+    /// (joined continuation lines, escapes, or dedenting)
+    String(Cow<'a, str>),
+    /// Block of nodes in source order, repeated keys included
+    Block(Vec<NodeRef<'a>>),
+    /// List of values
+    List(Vec<ValueRef<'a>>),
+    /// Key written without a value, see [`Value::Empty`]
+    Empty,
+}
+
+impl<'a> DocumentRef<'a> {
+    /// Look up a top-level key; the last definition when it repeats
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        find(&self.nodes, key)
+    }
+
+    /// Copy the document into an owned [`Document`]
+    ///
+    /// Values stay text, as when parsing with
+    /// [`Parser::coerce_types(false)`](Parser::coerce_types).
+    pub fn into_owned(self) -> Document {
+        Document {
+            nodes: self.nodes.into_iter().map(NodeRef::into_owned).collect(),
+        }
+    }
+}
+
+impl NodeRef<'_> {
+    /// Copy the node into an owned [`Node`]
+    pub fn into_owned(self) -> Node {
+        Node {
+            type_annotation: self.type_annotation.map(str::to_string),
+            origin: Some(Origin::line(self.line)),
+            doc: self.doc.map(Cow::into_owned),
+            lang: self.lang.map(str::to_string),
+            ..Node::new(self.key, self.value.into_owned())
+        }
+    }
+}
+
+impl<'a> ValueRef<'a> {
+    /// The text of a string value, `""` for a key without a value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ValueRef::String(text) => Some(text),
+            ValueRef::Empty => Some(""),
+            _ => None,
+        }
+    }
+
+    /// Look up a key of a block; the last definition when it repeats
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        match self {
+            ValueRef::Block(nodes) => find(nodes, key),
+            _ => None,
+        }
+    }
+
+    /// Copy the value into an owned [`Value`]
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::String(text) => Value::String(text.into_owned()),
+            ValueRef::Block(nodes) => {
+                let block: Block = nodes.into_iter().map(NodeRef::into_owned).collect();
+                Value::Block(block)
+            }
+            ValueRef::List(items) => {
+                Value::List(items.into_iter().map(ValueRef::into_owned).collect())
+            }
+            ValueRef::Empty => Value::Empty,
+        }
+    }
+}
+
+fn find<'n, 'a>(nodes: &'n [NodeRef<'a>], key: &str) -> Option<&'n ValueRef<'a>> {
+    nodes
+        .iter()
+        .rev()
+        .find(|node| node.key == key)
+        .map(|node| &node.value)
+}
+
+impl Parser {
+    /// Parse a document without copying its text
+    ///
+    /// Keys, annotations, and most values are slices of `input`, so a large
+    /// document costs little more memory than its text. Only the structure
+    /// of the document is read: annotations are kept as written rather
+    /// than applied, `${...}` references are left as text, and `!table`
+    /// values, inline blocks, embedded `!up`, `!json`, and `!yaml`
+    /// documents, `@let`, and `@include` are errors. Use
+    /// [`DocumentRef::into_owned`] to get a [`Document`].
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use uplang::{Parser, ValueRef};
+    ///
+    /// let input = "name app\nserver {\n  port!int 8080\n}";
+    /// let doc = Parser::new().parse_borrowed(input).unwrap();
+    /// let Some(ValueRef::String(Cow::Borrowed(name))) = doc.get("name") else {
+    ///     panic!("expected borrowed text");
+    /// };
+    /// assert_eq!(*name, "app");
+    /// let port = doc.get("server").and_then(|server| server.get("port"));
+    /// assert_eq!(port.and_then(ValueRef::as_str), Some("8080"));
+    /// ```
+    pub fn parse_borrowed<'a>(&self, input: &'a str) -> Result<DocumentRef<'a>, ParseError> {
+        self.limits.check_size(input.len())?;
+        let mut reader = Reader {
+            parser: self,
+            input,
+            lines: Lexer::new(input, &self.comments),
+            depth: 0,
+        };
+        let nodes = reader.nodes(false)?;
+        Ok(DocumentRef { nodes })
+    }
+}
+
+/// Reads the lines of one input
+struct Reader<'p, 'a> {
+    parser: &'p Parser,
+    input: &'a str,
+    lines: Lexer<'a, 'p>,
+    /// Number of blocks and lists enclosing the current line
+    depth: usize,
+}
+
+impl<'a> Reader<'_, 'a> {
+    /// Read nodes up to the end of input, or the `}` closing a block
+    fn nodes(&mut self, in_block: bool) -> Result<Vec<NodeRef<'a>>, ParseError> {
+        let mut nodes = Vec::new();
+        let mut comment: Vec<&'a str> = Vec::new();
+
+        while let Some(line) = self.lines.next() {
+            let (line_num, trimmed) = (line.number, line.code);
+            match line.token {
+                Token::Close(Close::Block) if in_block => return Ok(nodes),
+                Token::Blank | Token::Comment(_) => {
+                    collect_comment(&mut comment, line.comment());
+                    continue;
+                }
+                Token::Let(_) | Token::Include(_) => {
+                    return Err(self.unsupported(trimmed, "directives"));
+                }
+                Token::Strictness(_) if in_block => {
+                    return Err(self.unsupported(trimmed, "directives"));
+                }
+                Token::Close(_) => self.parser.check_stray(&self.lines, trimmed)?,
+                _ => {}
+            }
+
+            let mut node = self.node(trimmed, line_num)?;
+            node.doc = match comment[..] {
+                [] => None,
+                [text] => Some(Cow::Borrowed(text)),
+                _ => Some(Cow::Owned(comment.join("\n"))),
+            };
+            comment.clear();
+            nodes.push(node);
+        }
+
+        if in_block {
+            self.parser
+                .end_of_input(Value::Null, "block")
+                .map(|_| nodes)
+        } else {
+            Ok(nodes)
+        }
+    }
+
+    fn node(&mut self, line: &'a str, line_num: usize) -> Result<NodeRef<'a>, ParseError> {
+        let (key_part, first) = self.parser.split_key_value(line);
+        let (key, type_annotation) = quote::split_key(key_part)
+            .and_then(|(key, annotation)| {
+                self.parser.limits.check_key(&key)?;
+                Ok((key, annotation))
+            })
+            .map_err(|e| self.lines.locate(e, key_part))?;
+        let Cow::Borrowed(key) = key else {
+            return Err(self.unsupported(key_part, "keys with escapes"));
+        };
+        let value = self.value(first, type_annotation).map_err(|e| {
+            let culprit = if first.is_empty() { key_part } else { first };
+            self.lines.locate(e, culprit)
+        })?;
+        Ok(NodeRef {
+            key,
+            type_annotation,
+            value,
+            line: line_num + 1,
+            doc: None,
+            lang: fence_lang(first),
+        })
+    }
+
+    fn value(
+        &mut self,
+        first: &'a str,
+        type_annotation: Option<&'a str>,
+    ) -> Result<ValueRef<'a>, ParseError> {
+        Ok(match first {
+            "" if type_annotation.is_none() => ValueRef::Empty,
+            _ if type_annotation == Some("ref") => {
+                return Err(self.unsupported(first, "`!ref` values"))
+            }
+            "{" if type_annotation == Some("table") => {
+                return Err(self.unsupported(first, "`!table` values"))
+            }
+            "{" => ValueRef::Block(self.nested(|reader| reader.nodes(true))?),
+            s if s.starts_with("```")
+                && matches!(type_annotation, Some("up" | "json" | "yaml")) =>
+            {
+                return Err(self.unsupported(first, "embedded documents"))
+            }
+            "[" => self.nested(Self::list)?,
+            s if is_inline_block(s) => return Err(self.unsupported(s, "inline blocks")),
+            s if s.starts_with("```") => self.multiline(type_annotation)?,
+            s if s.starts_with('[') && s.ends_with(']') => self.inline_list(s)?,
+            s => match quote::whole(s) {
+                Some(quoted) => ValueRef::String(quote::unescape(quoted)?),
+                None => ValueRef::String(self.continued(s)),
+            },
+        })
+    }
+
+    fn list(&mut self) -> Result<ValueRef<'a>, ParseError> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.next() {
+            let (line_num, trimmed) = (line.number, line.code);
+            match line.token {
+                Token::Close(Close::List) => return Ok(ValueRef::List(items)),
+                Token::Blank | Token::Comment(_) => continue,
+                _ => {}
+            }
+            self.parser
+                .limits
+                .check_items(items.len() + 1, "items in a list")
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+            if is_inline_block(trimmed) {
+                return Err(self.unsupported(trimmed, "inline blocks"));
+            }
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                let item = self
+                    .inline_list(trimmed)
+                    .map_err(|e| self.lines.locate(e, trimmed))?;
+                items.push(item);
+            } else if trimmed.starts_with('{') {
+                let nodes = self
+                    .nested(|reader| reader.nodes(true))
+                    .map_err(|e| self.lines.locate(e, trimmed))?;
+                items.push(ValueRef::Block(nodes));
+            } else if trimmed == "[" {
+                let item = self
+                    .nested(Self::list)
+                    .map_err(|e| self.lines.locate(e, trimmed))?;
+                items.push(item);
+            } else if trimmed.starts_with("```") {
+                items.push(self.multiline(None)?);
+            } else if keyed_item(trimmed).is_some() {
+                items.push(ValueRef::Block(vec![self.node(trimmed, line_num)?]));
+            } else if let Some(quoted) = quote::whole(trimmed) {
+                let item = quote::unescape(quoted).map_err(|e| self.lines.locate(e, trimmed))?;
+                items.push(ValueRef::String(item));
+            } else {
+                if line.token == Token::Close(Close::Block) {
+                    self.parser.check_stray(&self.lines, trimmed)?;
+                }
+                items.push(ValueRef::String(self.continued(trimmed)));
+            }
+        }
+        self.parser
+            .end_of_input(Value::Null, "list")
+            .map(|_| ValueRef::List(items))
+    }
+
+    /// Items of an inline list, borrowed unless quoted, escaped, or nested
+    fn inline_list(&self, s: &'a str) -> Result<ValueRef<'a>, ParseError> {
+        let inner = &s[1..s.len() - 1];
+        if inner
+            .split(self.parser.list_separator)
+            .any(|item| item.trim_start().starts_with('{'))
+        {
+            return Err(self.unsupported(s, "inline blocks"));
+        }
+        let items: Vec<ValueRef<'a>> = if inner.contains(['"', '\\', '[']) {
+            self.parser
+                .parse_inline_list(s)?
+                .into_iter()
+                .map(|item| self.owned_item(s, item))
+                .collect::<Result<_, _>>()?
+        } else if inner.trim().is_empty() {
+            Vec::new()
+        } else {
+            inner
+                .split(self.parser.list_separator)
+                .map(|item| ValueRef::String(Cow::Borrowed(item.trim())))
+                .collect()
+        };
+        self.parser
+            .limits
+            .check_items(items.len(), "items in a list")?;
+        Ok(ValueRef::List(items))
+    }
+
+    /// An item of the inline list `s` as the owning parser read it
+    fn owned_item(&self, s: &'a str, item: Value) -> Result<ValueRef<'a>, ParseError> {
+        match item {
+            Value::String(text) => Ok(ValueRef::String(Cow::Owned(text))),
+            Value::List(items) => items
+                .into_iter()
+                .map(|item| self.owned_item(s, item))
+                .collect::<Result<_, _>>()
+                .map(ValueRef::List),
+            _ => Err(self.unsupported(s, "inline blocks")),
+        }
+    }
+
+    /// Read a block or list one level deeper, within the depth limit
+    fn nested<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.depth += 1;
+        self.parser.limits.check_depth(self.depth)?;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// The body of a fenced multiline value, borrowed unless dedented or
+    /// written with `\r\n` line endings
+    fn multiline(&mut self, type_annotation: Option<&str>) -> Result<ValueRef<'a>, ParseError> {
+        let dedent = type_annotation == Some("dedent");
+        let amount = type_annotation.and_then(|t| t.parse::<usize>().ok());
+        let mut body: Vec<&'a str> = Vec::new();
+        let mut closed = false;
+        while let Some((_, line)) = self.lines.raw() {
+            if lexer::trim(line) == "```" {
+                closed = true;
+                break;
+            }
+            body.push(line);
+        }
+
+        let text = match (body.first(), body.last()) {
+            (Some(first), Some(last)) if !dedent && amount.is_none() => {
+                let start = offset_in(self.input, first).unwrap_or_default();
+                let end = offset_in(self.input, last).unwrap_or_default() + last.len();
+                match self.input.get(start..end) {
+                    Some(text) if !text.contains('\r') => Cow::Borrowed(text),
+                    _ => Cow::Owned(body.join("\n")),
+                }
+            }
+            _ if dedent => Cow::Owned(dedent_common(&body).join("\n")),
+            _ => {
+                let amount = amount.unwrap_or_default();
+                let lines: Vec<&str> = body
+                    .iter()
+                    .map(|line| self.parser.dedent(line, amount))
+                    .collect();
+                Cow::Owned(lines.join("\n"))
+            }
+        };
+        if closed {
+            Ok(ValueRef::String(text))
+        } else {
+            self.parser
+                .end_of_input(Value::Null, "multiline string")
+                .map(|_| ValueRef::String(text))
+        }
+    }
+
+    /// `first`, joined with the following lines if it ends in a `\`
+    fn continued(&mut self, first: &'a str) -> Cow<'a, str> {
+        if !continues(first) {
+            return Cow::Borrowed(first);
+        }
+        let mut joined = first[..first.len() - 1].to_string();
+        while let Some((_, line)) = self.lines.raw() {
+            let line = lexer::trim(line);
+            if continues(line) {
+                joined.push_str(&line[..line.len() - 1]);
+            } else {
+                joined.push_str(line);
+                break;
+            }
+        }
+        Cow::Owned(joined)
+    }
+
+    fn unsupported(&self, text: &'a str, what: &str) -> ParseError {
+        let error = ParseError::new(
+            ErrorKind::InvalidSyntax,
+            format!("{} are not supported when parsing borrowed", what),
+        );
+        self.lines.locate(error, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed_matches_owned() {
+        let input = "# The name\nname app\nserver {\n  host localhost\n  port!int 8080\n}\ntags [a, b\\, c]\nitems [\n  one\n  {\n    x 1\n  }\n  [2, 3]\n]\nbody ```\nline 1\n  line 2\n```\nlong a \\\n  b\nempty\n\"my key\" \"  padded \"\nescaped \"a\\tb\"\n";
+        let parser = Parser::new();
+        let doc = parser.parse_borrowed(input).unwrap();
+        assert_eq!(
+            doc.clone().into_owned(),
+            parser
+                .clone()
+                .coerce_types(false)
+                .parse_document(input)
+                .unwrap()
+        );
+        assert_eq!(doc.nodes[0].doc.as_deref(), Some("The name"));
+
+        let borrowed = |value: Option<&ValueRef<'_>>| {
+            matches!(value, Some(ValueRef::String(Cow::Borrowed(_))))
+        };
+        assert!(borrowed(doc.get("name")));
+        assert!(borrowed(doc.get("body")));
+        assert_eq!(
+            doc.get("body").and_then(ValueRef::as_str),
+            Some("line 1\n  line 2")
+        );
+        assert!(!borrowed(doc.get("long")));
+        assert!(borrowed(doc.get("my key")));
+        assert_eq!(
+            doc.get("my key").and_then(ValueRef::as_str),
+            Some("  padded ")
+        );
+        assert!(!borrowed(doc.get("escaped")));
+        let Some(ValueRef::List(tags)) = doc.get("tags") else {
+            panic!("expected list");
+        };
+        assert_eq!(tags[1], ValueRef::String(Cow::Owned("b, c".into())));
+    }
+
+    #[test]
+    fn test_nested_inline_lists() {
+        let parser = Parser::new();
+        for input in [
+            "a [x, [y, z]]",
+            "a [\"q\", [b]]",
+            "a [x\\,y, [b]]",
+            "a [[], [[c]], d[1]]",
+        ] {
+            let doc = parser.parse_borrowed(input).unwrap();
+            assert_eq!(
+                doc.into_owned(),
+                parser.parse_document(input).unwrap(),
+                "{}",
+                input
+            );
+        }
+        let err = parser.parse_borrowed("a [x, [{ b 1 }]]").unwrap_err();
+        assert_eq!(
+            err.message,
+            "inline blocks are not supported when parsing borrowed"
+        );
+    }
+
+    #[test]
+    fn test_borrowed_errors() {
+        let parser = Parser::new();
+        let err = parser.parse_borrowed("a 1\n@let x 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(2));
+
+        let err = parser.parse_borrowed("t!table {\n}").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        let err = parser.parse_borrowed("\"a\\nb\" 1").unwrap_err();
+        assert_eq!(
+            err.message,
+            "keys with escapes are not supported when parsing borrowed"
+        );
+
+        let strict = Parser::new().strict(true);
+        let err = strict.parse_borrowed("a {\n  b 1\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedDelimiter);
+        let err = strict.parse_borrowed("a 1\n}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+    }
+}
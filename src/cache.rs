@@ -0,0 +1,443 @@
+//! Saving parsed documents in a compact binary form, to skip reparsing
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::hash::fnv;
+use crate::io::write_atomic;
+use crate::{types, Block, Document, ErrorKind, Node, Origin, ParseError, Parser, Value};
+
+/// Start of every cache, followed by the format version
+const MAGIC: &[u8] = b"UPCACHE";
+/// Bumped whenever the encoding changes, so older caches are ignored
+const VERSION: u8 = 3;
+
+const SCALAR: u8 = 0;
+const BLOCK: u8 = 1;
+const LIST: u8 = 2;
+const TABLE: u8 = 3;
+const EMPTY: u8 = 4;
+const CUSTOM: u8 = 5;
+
+impl Document {
+    /// Encode the document, parsed from `source`, as a binary cache
+    ///
+    /// The cache records a format version and a hash of `source`, so
+    /// [`from_cache`](Self::from_cache) can tell when it no longer applies.
+    /// Keys, annotations, values, origins, doc comments, and fence language
+    /// tags are kept; trivia and provenance are not.
+    pub fn to_cache(&self, source: &str) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        encoder.out.extend_from_slice(MAGIC);
+        encoder.out.push(VERSION);
+        encoder
+            .out
+            .extend_from_slice(&fnv(source.as_bytes()).to_le_bytes());
+        encoder.nodes(&self.nodes);
+        encoder.out
+    }
+
+    /// Decode a cache written by [`to_cache`](Self::to_cache) for the same
+    /// `source`
+    ///
+    /// `None` if the cache was written for a different source, by a
+    /// different version of the format, or is damaged; parse `source`
+    /// instead. Values from a custom [`TypeHandler`](crate::TypeHandler)
+    /// cannot be rebuilt without their parser, so caches holding them are
+    /// never loaded.
+    ///
+    /// ```
+    /// use uplang::Document;
+    ///
+    /// let source = "server {\n  port!int 8080\n}";
+    /// let doc = uplang::parse(source).unwrap();
+    /// let cache = doc.to_cache(source);
+    /// assert_eq!(Document::from_cache(&cache, source), Some(doc));
+    /// assert_eq!(Document::from_cache(&cache, "server {\n  port!int 80\n}"), None);
+    /// ```
+    pub fn from_cache(cache: &[u8], source: &str) -> Option<Document> {
+        let rest = cache.strip_prefix(MAGIC)?;
+        let (&version, rest) = rest.split_first()?;
+        if version != VERSION || rest.len() < 8 {
+            return None;
+        }
+        let (hash, rest) = rest.split_at(8);
+        if hash != fnv(source.as_bytes()).to_le_bytes() {
+            return None;
+        }
+        let mut decoder = Decoder {
+            bytes: rest,
+            files: Vec::new(),
+        };
+        let nodes = decoder.nodes()?;
+        decoder.bytes.is_empty().then_some(Document { nodes })
+    }
+}
+
+impl Parser {
+    /// Parse a file, reusing the document cached at `cache` if it was
+    /// saved for the file's current contents
+    ///
+    /// Otherwise the file is parsed with [`parse_file`](Self::parse_file)
+    /// and the result saved to `cache`; failing to save it is not an error.
+    /// The cache does not record the parser's settings or the contents of
+    /// included files, so use one cache path per parser configuration, and
+    /// delete it when an included file changes.
+    pub fn parse_cached(
+        &self,
+        path: impl AsRef<Path>,
+        cache: impl AsRef<Path>,
+    ) -> Result<Document, ParseError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| ParseError::new(ErrorKind::Io, format!("{}: {}", path.display(), e)))?;
+        let cached = fs::read(cache.as_ref())
+            .ok()
+            .and_then(|bytes| Document::from_cache(&bytes, &source));
+        if let Some(doc) = cached {
+            return Ok(doc);
+        }
+        let doc = self.parse_file(path)?;
+        let _ = write_atomic(cache.as_ref(), doc.to_cache(&source));
+        Ok(doc)
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    out: Vec<u8>,
+    /// Files of the origins written so far, each written out once
+    files: Vec<Arc<Path>>,
+}
+
+impl Encoder {
+    /// LEB128, so small numbers take one byte
+    fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.out.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.out.push(n as u8);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.out.push(1);
+                self.str(s);
+            }
+            None => self.out.push(0),
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node]) {
+        self.uint(nodes.len() as u64);
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        self.str(&node.key);
+        self.opt_str(node.type_annotation.as_deref());
+        self.opt_str(node.doc.as_deref());
+        self.opt_str(node.lang.as_deref());
+        match &node.origin {
+            Some(origin) => {
+                self.out.push(1);
+                self.uint(origin.line as u64);
+                self.file(origin.file.as_ref());
+            }
+            None => self.out.push(0),
+        }
+        self.value(&node.value);
+    }
+
+    /// 0 for no file, else the 1-based index of the file, followed by its
+    /// path the first time
+    fn file(&mut self, file: Option<&Arc<Path>>) {
+        let Some(file) = file else {
+            self.uint(0);
+            return;
+        };
+        match self.files.iter().position(|known| known == file) {
+            Some(i) => self.uint(i as u64 + 1),
+            None => {
+                self.files.push(Arc::clone(file));
+                self.uint(self.files.len() as u64);
+                self.str(&file.to_string_lossy());
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Block(block) => {
+                self.out.push(BLOCK);
+                let nodes: Vec<&Node> = block.nodes().collect();
+                self.uint(nodes.len() as u64);
+                for node in nodes {
+                    self.node(node);
+                }
+            }
+            Value::List(items) => {
+                self.out.push(LIST);
+                self.values(items);
+            }
+            Value::Table { columns, rows } => {
+                self.out.push(TABLE);
+                self.values(columns);
+                self.uint(rows.len() as u64);
+                for row in rows {
+                    self.values(row);
+                }
+            }
+            Value::Empty => self.out.push(EMPTY),
+            Value::Custom(custom) => {
+                self.out.push(CUSTOM);
+                self.str(custom.annotation());
+                self.str(custom.text());
+            }
+            // Typed scalars are stored as text and read back by their type
+            scalar => {
+                self.out.push(SCALAR);
+                self.str(scalar.type_name());
+                self.str(&scalar.scalar_text().unwrap_or_default());
+            }
+        }
+    }
+
+    fn values(&mut self, values: &[Value]) {
+        self.uint(values.len() as u64);
+        for value in values {
+            self.value(value);
+        }
+    }
+}
+
+/// Reads what [`Encoder`] wrote; every method returns `None` on damaged
+/// input
+struct Decoder<'b> {
+    bytes: &'b [u8],
+    files: Vec<Arc<Path>>,
+}
+
+impl<'b> Decoder<'b> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&b, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(b)
+    }
+
+    fn uint(&mut self) -> Option<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    /// A count of items that each take at least one byte
+    fn len(&mut self) -> Option<usize> {
+        let n = usize::try_from(self.uint()?).ok()?;
+        (n <= self.bytes.len()).then_some(n)
+    }
+
+    fn str(&mut self) -> Option<&'b str> {
+        let len = self.len()?;
+        let (s, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        std::str::from_utf8(s).ok()
+    }
+
+    fn opt_str(&mut self) -> Option<Option<String>> {
+        match self.byte()? {
+            0 => Some(None),
+            1 => Some(Some(self.str()?.to_string())),
+            _ => None,
+        }
+    }
+
+    fn nodes(&mut self) -> Option<Vec<Node>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.node()).collect()
+    }
+
+    fn node(&mut self) -> Option<Node> {
+        let key = self.str()?;
+        let type_annotation = self.opt_str()?;
+        let doc = self.opt_str()?;
+        let lang = self.opt_str()?;
+        let origin = match self.byte()? {
+            0 => None,
+            1 => {
+                let line = usize::try_from(self.uint()?).ok()?;
+                let file = self.file()?;
+                Some(Origin { file, line })
+            }
+            _ => return None,
+        };
+        Some(Node {
+            type_annotation,
+            origin,
+            doc,
+            lang,
+            ..Node::new(key, self.value()?)
+        })
+    }
+
+    fn file(&mut self) -> Option<Option<Arc<Path>>> {
+        let i = usize::try_from(self.uint()?).ok()?;
+        if i == 0 {
+            return Some(None);
+        }
+        if i == self.files.len() + 1 {
+            let path = Path::new(self.str()?);
+            self.files.push(Arc::from(path));
+        }
+        self.files.get(i - 1).cloned().map(Some)
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        Some(match self.byte()? {
+            SCALAR => {
+                let type_name = self.str()?;
+                let text = self.str()?;
+                match type_name {
+                    "string" => Value::String(text.to_string()),
+                    _ => types::coerce(type_name, text)?.ok()?,
+                }
+            }
+            BLOCK => {
+                let len = self.len()?;
+                let nodes = (0..len)
+                    .map(|_| self.node())
+                    .collect::<Option<Vec<Node>>>()?;
+                Value::Block(nodes.into_iter().collect::<Block>())
+            }
+            LIST => Value::List(self.values()?),
+            TABLE => {
+                let columns = self.values()?;
+                let len = self.len()?;
+                let rows = (0..len).map(|_| self.values()).collect::<Option<_>>()?;
+                Value::Table { columns, rows }
+            }
+            EMPTY => Value::Empty,
+            // Only the types built in, such as `!semver`, can be rebuilt
+            CUSTOM => {
+                let annotation = self.str()?;
+                let text = self.str()?;
+                match types::coerce(annotation, text)?.ok()? {
+                    custom @ Value::Custom(_) => custom,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    fn values(&mut self) -> Option<Vec<Value>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.value()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let source = "# App\nname app\nport!int 8080\nratio!float 0.1\nprice!money 19.99 USD\ncolor!color #FF8800\nwhere!geo 52.5,13.4\ntype!mime text/plain\ndir!path /srv/data\nproxy!null\nflag!bool true\nserver {\n  tags [a, b]\n  query ```sql\n  SELECT 1\n  ```\n}\nusers!table {\n  columns [id!int, name]\n  rows [\n    [1, ann]\n  ]\n}\n";
+        let doc = Parser::new().parse_document(source).unwrap();
+        let cache = doc.to_cache(source);
+        let cached = Document::from_cache(&cache, source).unwrap();
+        assert_eq!(cached, doc);
+        assert_eq!(cached.nodes[0].doc.as_deref(), Some("App"));
+        assert_eq!(cached.nodes[0].origin, Some(Origin::line(2)));
+        let Some(Value::Block(server)) = cached.get("server") else {
+            panic!("expected block");
+        };
+        assert_eq!(
+            server.get_node("query").unwrap().lang.as_deref(),
+            Some("sql")
+        );
+
+        assert_eq!(Document::from_cache(&cache, "name app"), None);
+        assert_eq!(
+            Document::from_cache(&cache[..cache.len() - 1], source),
+            None
+        );
+        let mut newer = cache.clone();
+        newer[MAGIC.len()] = VERSION + 1;
+        assert_eq!(Document::from_cache(&newer, source), None);
+    }
+
+    #[test]
+    fn test_cache_bare_keys() {
+        let source = "debug\nserver {\n  verbose\n}\nflags [\n  x\n]\n";
+        let doc = Parser::new().parse_document(source).unwrap();
+        assert_eq!(doc.get("debug"), Some(&Value::Empty));
+        let cache = doc.to_cache(source);
+        assert_eq!(Document::from_cache(&cache, source), Some(doc));
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_cache_semver() {
+        let source = "version!semver 1.4.2\nrequires!semver-req >=1.4, <2\n";
+        let doc = Parser::new().parse_document(source).unwrap();
+        let cached = Document::from_cache(&doc.to_cache(source), source).unwrap();
+        assert_eq!(cached, doc);
+        assert_eq!(
+            cached.get("version").and_then(Value::semver),
+            Some(&semver::Version::new(1, 4, 2))
+        );
+    }
+
+    #[test]
+    fn test_custom_values_are_not_cached() {
+        let types = crate::TypeRegistry::new().register("port", |text: &str| {
+            Ok(Value::Custom(crate::CustomValue::new("port", text, 80u16)))
+        });
+        let source = "listen!port 80";
+        let doc = Parser::new()
+            .type_registry(types)
+            .parse_document(source)
+            .unwrap();
+        assert_eq!(Document::from_cache(&doc.to_cache(source), source), None);
+    }
+
+    #[test]
+    fn test_parse_cached() {
+        let dir = std::env::temp_dir().join(format!("uplang-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("app.up");
+        let cache = dir.join("app.upc");
+        fs::write(&file, "port!int 80\n").unwrap();
+
+        let parser = Parser::new();
+        let doc = parser.parse_cached(&file, &cache).unwrap();
+        assert_eq!(doc.get_int("port"), Some(80));
+        let saved = fs::read(&cache).unwrap();
+        let cached = parser.parse_cached(&file, &cache).unwrap();
+        assert_eq!(cached, doc);
+        assert_eq!(cached.nodes[0].origin, doc.nodes[0].origin);
+
+        fs::write(&file, "port!int 81\n").unwrap();
+        let doc = parser.parse_cached(&file, &cache).unwrap();
+        assert_eq!(doc.get_int("port"), Some(81));
+        assert_ne!(fs::read(&cache).unwrap(), saved);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
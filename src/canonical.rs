@@ -0,0 +1,143 @@
+//! The canonical form of a document, and the content hash built on it
+
+use crate::hash::fnv;
+use crate::{to_string, Block, Document, Node, Value};
+
+impl Document {
+    /// The document in canonical form: the same content, written one way
+    ///
+    /// Two documents with the same keys and values have the same canonical
+    /// form however they were written. The form is stable, and is the basis
+    /// of [`content_hash`](Self::content_hash), so other implementations of
+    /// UP can produce it too:
+    ///
+    /// - Keys are sorted by their UTF-8 bytes at every level, the top level
+    ///   included. Nodes with the same key keep their order. List items and
+    ///   table rows keep theirs.
+    /// - Doc comments, fence language tags, and source positions are
+    ///   dropped.
+    /// - Typed values lose their annotation, as it follows from the value:
+    ///   `!base64` and `!bytes` become one, `port!int 080` is `port!int 80`.
+    ///   Strings lose `!string` and dedent widths such as `!4`, blocks and
+    ///   lists every annotation. Other annotations on strings, such as
+    ///   `!secret` or `!url`, and on custom types are kept.
+    ///
+    /// Written with [`to_string`], the canonical form
+    /// uses two-space indentation, one space between a key and its value,
+    /// and `\n` line endings; typed values are annotated with their type
+    /// name, such as `!int`, `!float`, or `!dur`, and scalars are written as
+    /// [`Value::scalar_text`] gives them, quoted only where the syntax
+    /// needs it. Lists of single-line scalars are written inline as
+    /// `[a, b]`, strings with line breaks as fenced multiline strings
+    /// without indentation.
+    ///
+    /// ```
+    /// let a = uplang::parse("# App\nserver {\n  port!int 080\n  host h\n}\nname!string app").unwrap();
+    /// let b = uplang::parse("name app\nserver {\n  host h\n  port!int 80\n}").unwrap();
+    /// let canonical = uplang::to_string(&a.canonicalize());
+    /// assert_eq!(canonical, "name app\nserver {\n  host h\n  port!int 80\n}\n");
+    /// assert_eq!(canonical, uplang::to_string(&b.canonicalize()));
+    /// ```
+    pub fn canonicalize(&self) -> Document {
+        Document {
+            nodes: canonical_nodes(self.nodes.iter()),
+        }
+    }
+
+    /// A stable digest of the document's content, independent of
+    /// formatting, comments, key order, and how typed values were written
+    ///
+    /// The digest is the 64-bit FNV-1a hash (offset basis
+    /// `0xcbf29ce484222325`, prime `0x100000001b3`) of the UTF-8 text of
+    /// the [canonical form](Self::canonicalize). It does not change
+    /// between runs, platforms, or versions of this crate, so it can be
+    /// stored for change detection and caching, and computed by other
+    /// implementations. Unlike [`semantic_hash`](Self::semantic_hash), the
+    /// order of top-level keys does not matter either.
+    ///
+    /// ```
+    /// let a = uplang::parse("port!int 80\nname app").unwrap();
+    /// let b = uplang::parse("# Service\nname   app\nport!int 0080\n").unwrap();
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    /// assert_eq!(a.content_hash(), 0x7cb0_8ff9_8b24_e7a4);
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        fnv(to_string(&self.canonicalize()).as_bytes())
+    }
+}
+
+fn canonical_nodes<'a>(nodes: impl Iterator<Item = &'a Node>) -> Vec<Node> {
+    let mut nodes: Vec<Node> = nodes.map(canonical_node).collect();
+    nodes.sort_by(|a, b| a.key.as_bytes().cmp(b.key.as_bytes()));
+    nodes
+}
+
+fn canonical_node(node: &Node) -> Node {
+    let value = canonical_value(&node.value);
+    let type_annotation = match &value {
+        Value::String(_) => node.type_annotation.clone().filter(|a| {
+            !matches!(a.as_str(), "string" | "dedent" | "multiline" | "fold")
+                && a.parse::<usize>().is_err()
+        }),
+        Value::Custom(_) => node.type_annotation.clone(),
+        _ => None,
+    };
+    Node {
+        type_annotation,
+        ..Node::new(node.key.clone(), value)
+    }
+}
+
+fn canonical_value(value: &Value) -> Value {
+    match value {
+        Value::Block(block) => {
+            let block: Block = canonical_nodes(block.nodes()).into_iter().collect();
+            Value::Block(block)
+        }
+        Value::List(items) => Value::List(items.iter().map(canonical_value).collect()),
+        value => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, to_string};
+
+    fn canonical(input: &str) -> String {
+        to_string(&parse(input).unwrap().canonicalize())
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let input = "# Service\nz 1\nlist [\n  {\n    b 2\n    a!string 1\n  }\n  x\n]\nblob!base64 aGk=\nscript!4 ```sh\n    run\n    stop\n```\nurl!url http://h\n\"a b\" {\n  wait!dur 90s\n  flag!bool true\n}\nz 0\n";
+        assert_eq!(
+            canonical(input),
+            "\"a b\" {\n  flag!bool true\n  wait!dur 1m30s\n}\nblob!bytes aGk=\nlist [\n  {\n    a 1\n    b 2\n  }\n  x\n]\nscript ```\nrun\nstop\n```\nurl!url http://h\nz 1\nz 0\n"
+        );
+        // Canonical text is its own canonical form
+        assert_eq!(canonical(&canonical(input)), canonical(input));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let hash = |input: &str| parse(input).unwrap().content_hash();
+        let base = hash("name app\nserver {\n  host h\n  port!int 80\n}\ntags [a, b]\n");
+        let same = [
+            "tags [\n  a\n  b\n]\nserver {\n  port!int 80\n  host!string h\n}\n# App\nname    app\n",
+            "@let h h\nserver!block {\n  host ${h}\n  port!int +80\n}\nname app\ntags!list [a,b]",
+        ];
+        for input in same {
+            assert_eq!(hash(input), base, "{}", input);
+        }
+        let different = [
+            "name app\nserver {\n  host h\n  port 80\n}\ntags [a, b]\n",
+            "name app\nserver {\n  host h\n  port!int 80\n}\ntags [b, a]\n",
+            "name app\nserver {\n  host h\n  port!float 80\n}\ntags [a, b]\n",
+        ];
+        for input in different {
+            assert_ne!(hash(input), base, "{}", input);
+        }
+        // Fixed, so it can be stored and matched elsewhere
+        assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+    }
+}
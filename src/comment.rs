@@ -0,0 +1,121 @@
+//! Which characters start a comment, and where comments may go
+//!
+//! Comments take a line of their own by default. With
+//! [`Parser::trailing_comments`](crate::Parser::trailing_comments) they may
+//! also end a line, after the value: a comment character preceded by
+//! whitespace and outside double quotes starts one. The bodies of
+//! multiline strings are never searched for comments. With
+//! [`Parser::slash_comments`](crate::Parser::slash_comments), `//` starts a
+//! comment too.
+
+/// How comments are written, set on the parser
+#[derive(Debug, Clone)]
+pub(crate) struct Comments {
+    chars: Vec<char>,
+    trailing: bool,
+    slashes: bool,
+}
+
+impl Default for Comments {
+    fn default() -> Self {
+        Self {
+            chars: vec!['#'],
+            trailing: false,
+            slashes: false,
+        }
+    }
+}
+
+impl Comments {
+    pub(crate) fn chars(mut self, chars: &str) -> Self {
+        self.chars = chars.chars().collect();
+        self
+    }
+
+    pub(crate) fn trailing(mut self, trailing: bool) -> Self {
+        self.trailing = trailing;
+        self
+    }
+
+    pub(crate) fn slashes(mut self, slashes: bool) -> Self {
+        self.slashes = slashes;
+        self
+    }
+
+    /// Whether a trimmed line is a comment line
+    pub(crate) fn is_comment(&self, trimmed: &str) -> bool {
+        self.text(trimmed).is_some()
+    }
+
+    /// The text of a comment line, without the comment character and one
+    /// space after it; `None` if the line is not a comment
+    pub(crate) fn text<'a>(&self, trimmed: &'a str) -> Option<&'a str> {
+        let text = trimmed
+            .strip_prefix(self.chars.as_slice())
+            .or_else(|| self.slashes.then(|| trimmed.strip_prefix("//")).flatten())?;
+        Some(text.strip_prefix(' ').unwrap_or(text))
+    }
+
+    /// `line` without its trailing comment, if trailing comments are on
+    ///
+    /// Comment lines are returned as they are.
+    pub(crate) fn strip<'a>(&self, line: &'a str) -> &'a str {
+        if !self.trailing || self.is_comment(line.trim_start()) {
+            return line;
+        }
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut after_space = false;
+        for (i, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                c if !quoted && after_space && self.chars.contains(&c) => {
+                    return line[..i].trim_end();
+                }
+                '/' if !quoted && after_space && self.slashes && line[i..].starts_with("//") => {
+                    return line[..i].trim_end();
+                }
+                _ => {}
+            }
+            after_space = c.is_whitespace();
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip() {
+        let comments = Comments::default().chars("#;").trailing(true);
+        assert_eq!(comments.strip("port!int 8080  # public"), "port!int 8080");
+        assert_eq!(comments.strip("  a 1 ; note"), "  a 1");
+        assert_eq!(comments.strip("url http://h/#top"), "url http://h/#top");
+        assert_eq!(comments.strip("name \"a # b\" # c"), "name \"a # b\"");
+        assert_eq!(comments.strip("name \"a \\\" # b\""), "name \"a \\\" # b\"");
+        assert_eq!(
+            comments.strip("tags [a, \"b #c\"] #d"),
+            "tags [a, \"b #c\"]"
+        );
+        assert_eq!(comments.strip("# whole line"), "# whole line");
+        assert_eq!(comments.text("; note"), Some("note"));
+        assert_eq!(comments.text("a ; note"), None);
+
+        let comments = Comments::default().slashes(true).trailing(true);
+        assert_eq!(comments.text("// note"), Some("note"));
+        assert_eq!(comments.text("# note"), Some("note"));
+        assert_eq!(comments.strip("port!int 80 // public"), "port!int 80");
+        assert_eq!(comments.strip("url http://h/a"), "url http://h/a");
+        assert_eq!(comments.strip("path \"a // b\""), "path \"a // b\"");
+
+        let comments = Comments::default();
+        assert!(!comments.is_comment("// x"));
+        assert_eq!(comments.strip("color #fff"), "color #fff");
+        assert!(comments.is_comment("# x"));
+        assert!(!comments.is_comment("; x"));
+    }
+}
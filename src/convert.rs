@@ -0,0 +1,1204 @@
+//! Converting documents to and from JSON, YAML, and TOML
+//!
+//! All three formats share one data model: blocks become objects (tables
+//! in TOML), lists become arrays, and a table becomes an array of objects
+//! keyed by column name. Integers, floats, booleans, and null keep their
+//! type; every other scalar (money, colors, paths, ...) is written as the
+//! text it would have in UP. When a top-level key is repeated, only its
+//! last (effective) definition is converted. [`Document::to_json_typed`]
+//! keeps the annotations as well, for reading back with
+//! [`Document::from_json_typed`].
+//!
+//! ```
+//! let doc = uplang::parse("name app\nserver {\nport!int 8080\n}").unwrap();
+//! assert_eq!(doc.to_json(), r#"{"name":"app","server":{"port":8080}}"#);
+//! assert_eq!(doc.to_yaml(), "name: app\nserver:\n  port: 8080\n");
+//! assert_eq!(doc.to_toml().unwrap(), "name = \"app\"\n\n[server]\nport = 8080\n");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{types, Block, Document, ErrorKind, Limits, Node, ParseError, Value};
+
+impl Document {
+    /// Convert to compact JSON
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::document(self), None);
+        out
+    }
+
+    /// Convert to JSON indented by two spaces, ending with a newline
+    pub fn to_json_pretty(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::document(self), Some(0));
+        out.push('\n');
+        out
+    }
+
+    /// Convert to compact JSON that keeps type annotations
+    ///
+    /// Each value whose annotation JSON cannot express is wrapped in an
+    /// object holding the annotation and the value as [`to_json`] would
+    /// write it. A value JSON has a type for (`!int`, `!float`, `!bool`,
+    /// `!null`), or an unannotated string, block, or list, is written plainly.
+    /// A key without a value is wrapped with the type `empty`, so that it
+    /// does not read back as an empty string.
+    /// [`from_json_typed`](Self::from_json_typed) reads the result back
+    /// into the same document.
+    ///
+    /// ```
+    /// let doc = uplang::parse("timeout!dur 30s\nport!int 80\nkey!secret abc").unwrap();
+    /// let json = doc.to_json_typed();
+    /// assert_eq!(
+    ///     json,
+    ///     r#"{"timeout":{"$type":"dur","value":"30s"},"port":80,"key":{"$type":"secret","value":"abc"}}"#
+    /// );
+    /// assert_eq!(uplang::Document::from_json_typed(&json).unwrap(), doc);
+    /// ```
+    ///
+    /// [`to_json`]: Self::to_json
+    pub fn to_json_typed(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::typed_document(self), None);
+        out
+    }
+
+    /// Convert to JSON that keeps type annotations, indented by two spaces
+    /// and ending with a newline, see [`to_json_typed`](Self::to_json_typed)
+    pub fn to_json_typed_pretty(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::typed_document(self), Some(0));
+        out.push('\n');
+        out
+    }
+
+    /// Convert to block-style YAML
+    pub fn to_yaml(&self) -> String {
+        let Data::Object(entries) = Data::document(self) else {
+            unreachable!("documents convert to objects");
+        };
+        if entries.is_empty() {
+            return "{}\n".to_string();
+        }
+        let mut out = String::new();
+        yaml_entries(&mut out, &entries, 0);
+        out
+    }
+
+    /// Convert to TOML
+    ///
+    /// Fails with [`ErrorKind::InvalidValue`] if the document contains a
+    /// null, which TOML cannot represent.
+    pub fn to_toml(&self) -> Result<String, ParseError> {
+        let Data::Object(entries) = Data::document(self) else {
+            unreachable!("documents convert to objects");
+        };
+        let mut out = String::new();
+        toml_table(&mut out, "", &entries)?;
+        Ok(out)
+    }
+
+    /// Read a document from JSON, whose top level must be an object
+    ///
+    /// Objects become blocks and arrays become lists. Numbers without a
+    /// fraction or exponent become [`Value::Int`] when they fit, and all
+    /// other numbers [`Value::Float`]. Keys holding numbers, booleans, or
+    /// nulls get the `!int`, `!float`, `!bool`, or `!null` annotation, and
+    /// an array of two or more objects with the same keys and one scalar
+    /// type per key becomes a [`Value::Table`], so the document reads like
+    /// hand-written UP:
+    ///
+    /// ```
+    /// use uplang::Document;
+    ///
+    /// let doc = Document::from_json(
+    ///     r#"{"port": 8080, "users": [{"id": 1, "name": "ann"}, {"id": 2, "name": "bo"}]}"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     uplang::to_string(&doc),
+    ///     "port!int 8080\nusers!table {\n  columns [id!int, name]\n  rows [\n    [1, ann]\n    [2, bo]\n  ]\n}\n"
+    /// );
+    /// ```
+    pub fn from_json(input: &str) -> Result<Document, ParseError> {
+        let mut doc = Self::read_json(input)?;
+        for node in &mut doc.nodes {
+            infer_node(node);
+        }
+        Ok(doc)
+    }
+
+    /// Read JSON into blocks and lists as written, without inferring
+    /// annotations or tables
+    fn read_json(input: &str) -> Result<Document, ParseError> {
+        let mut reader = JsonReader {
+            input,
+            rest: input,
+            depth: 0,
+        };
+        reader.skip_whitespace();
+        if !reader.rest.starts_with('{') {
+            return Err(reader.error("top level must be an object"));
+        }
+        let nodes = reader.object()?;
+        reader.skip_whitespace();
+        if !reader.rest.is_empty() {
+            return Err(reader.error("unexpected text after the top-level object"));
+        }
+        Ok(Document { nodes })
+    }
+
+    /// Read a document from JSON written by
+    /// [`to_json_typed`](Self::to_json_typed)
+    ///
+    /// Objects of the form `{"$type": ..., "value": ...}` become the value
+    /// with that annotation, converted to its type as when parsing UP; an
+    /// object with exactly these two keys is always read this way. Numbers,
+    /// booleans, and nulls get the `!int`, `!float`, `!bool`, or `!null`
+    /// annotation that UP needs to read them back as the same type.
+    pub fn from_json_typed(input: &str) -> Result<Document, ParseError> {
+        let mut doc = Self::read_json(input)?;
+        for node in &mut doc.nodes {
+            untag_node(node)?;
+        }
+        Ok(doc)
+    }
+}
+
+/// Key of the annotation in a typed JSON wrapper
+const TYPE_KEY: &str = "$type";
+/// Key of the value in a typed JSON wrapper
+const VALUE_KEY: &str = "value";
+
+/// Replace a typed JSON wrapper in `node`, and in the values nested in it,
+/// with the value it holds, annotating the node
+fn untag_node(node: &mut Node) -> Result<(), ParseError> {
+    node.type_annotation = match untag(&mut node.value)? {
+        Some(annotation) => Some(annotation),
+        None => native_annotation(&node.value),
+    };
+    Ok(())
+}
+
+/// Annotation UP needs to read a JSON number, boolean, or null back as the
+/// same type
+fn native_annotation(value: &Value) -> Option<String> {
+    match value {
+        Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::Null => {
+            Some(value.type_name().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Annotate `node` and the nodes nested in it by the type of their JSON
+/// value, turning arrays of uniform objects into tables
+fn infer_node(node: &mut Node) {
+    infer(&mut node.value);
+    node.type_annotation = match node.value {
+        Value::Table { .. } => Some("table".to_string()),
+        ref value => native_annotation(value),
+    };
+}
+
+fn infer(value: &mut Value) {
+    match value {
+        Value::Block(block) => {
+            for node in block.nodes_mut() {
+                infer_node(node);
+            }
+        }
+        Value::List(items) => {
+            for item in items.iter_mut() {
+                infer(item);
+            }
+        }
+        _ => {}
+    }
+    if let Some(table) = value.to_table() {
+        *value = table;
+    }
+}
+
+/// Unwrap `value` if it is a typed JSON wrapper, returning its annotation
+fn untag(value: &mut Value) -> Result<Option<String>, ParseError> {
+    let annotation = match value {
+        Value::Block(block) if block.len() == 2 => match block.get(TYPE_KEY) {
+            Some(Value::String(annotation)) if block.contains_key(VALUE_KEY) => {
+                Some(annotation.clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(annotation) = &annotation {
+        let Some(Value::Block(mut block)) = Some(std::mem::replace(value, Value::Null)) else {
+            unreachable!("checked above");
+        };
+        *value = block.remove(VALUE_KEY).expect("checked above");
+        match value {
+            // A key without a value, which has no annotation of its own
+            Value::String(text) if annotation == "empty" && text.is_empty() => {
+                *value = Value::Empty;
+                return Ok(None);
+            }
+            Value::String(text) => {
+                if let Some(typed) = types::coerce(annotation, text) {
+                    *value = typed?;
+                }
+                return Ok(Some(annotation.clone()));
+            }
+            Value::List(rows) if annotation == "table" => {
+                *value = table(std::mem::take(rows))?;
+                return Ok(Some(annotation.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    match value {
+        Value::Block(block) => {
+            for node in block.nodes_mut() {
+                untag_node(node)?;
+            }
+        }
+        Value::List(items) => {
+            for item in items {
+                untag(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(annotation)
+}
+
+/// A table from its rows written as objects, with the first row's keys as
+/// the columns
+fn table(rows: Vec<Value>) -> Result<Value, ParseError> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut cells = Vec::with_capacity(rows.len());
+    for (i, row) in rows.into_iter().enumerate() {
+        let Value::Block(mut row) = row else {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("row {} of a table is not an object", i),
+            ));
+        };
+        if i == 0 {
+            columns = row.keys().map(str::to_string).collect();
+        }
+        let mut values = Vec::with_capacity(columns.len());
+        for column in &columns {
+            let mut cell = row.remove(column).unwrap_or(Value::Null);
+            untag(&mut cell)?;
+            values.push(cell);
+        }
+        cells.push(values);
+    }
+    Ok(Value::Table {
+        columns: columns.into_iter().map(Value::String).collect(),
+        rows: cells,
+    })
+}
+
+/// Parse a JSON value from `text`, a slice of `input` that errors point into
+#[cfg(feature = "json")]
+pub(crate) fn parse_json(input: &str, text: &str) -> Result<Value, ParseError> {
+    let mut reader = JsonReader {
+        input,
+        rest: text,
+        depth: 0,
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if !reader.rest.is_empty() {
+        return Err(reader.error("unexpected text after the value"));
+    }
+    Ok(value)
+}
+
+impl Value {
+    /// Convert to compact JSON, as [`Document::to_json`] converts values,
+    /// for logs and debugging
+    ///
+    /// ```
+    /// use uplang::{up_value, Value};
+    ///
+    /// let value = up_value!({ port 8080 tags [web, api] });
+    /// assert_eq!(value.to_json_string(), r#"{"port":8080,"tags":["web","api"]}"#);
+    /// assert_eq!(Value::from("30s").to_json_string(), r#""30s""#);
+    /// ```
+    pub fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::of(self), None);
+        out
+    }
+
+    /// Convert to JSON indented by two spaces, ending with a newline
+    pub fn to_json_pretty(&self) -> String {
+        let mut out = String::new();
+        json(&mut out, &Data::of(self), Some(0));
+        out.push('\n');
+        out
+    }
+}
+
+/// A value in the data model shared by JSON, YAML, and TOML, and by
+/// property lists and registry files
+pub(crate) enum Data<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(Cow<'a, str>),
+    Array(Vec<Data<'a>>),
+    Object(Vec<(Cow<'a, str>, Data<'a>)>),
+}
+
+impl<'a> Data<'a> {
+    pub(crate) fn document(doc: &'a Document) -> Self {
+        Data::nodes(doc, |node| Data::of(&node.value))
+    }
+
+    fn typed_document(doc: &'a Document) -> Self {
+        Data::nodes(doc, Data::typed_node)
+    }
+
+    /// The effective top-level nodes of `doc`, each converted by `data`
+    fn nodes(doc: &'a Document, data: fn(&'a Node) -> Data<'a>) -> Self {
+        let last: HashMap<&str, usize> = doc
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.key.as_str(), i))
+            .collect();
+        Data::Object(
+            doc.nodes
+                .iter()
+                .enumerate()
+                .filter(|(i, node)| last[node.key.as_str()] == *i)
+                .map(|(_, node)| (Cow::Borrowed(node.key.as_str()), data(node)))
+                .collect(),
+        )
+    }
+
+    fn typed_node(node: &'a Node) -> Self {
+        Data::typed(&node.value, node.type_annotation.as_deref())
+    }
+
+    /// Like [`of`](Self::of), but wrapping values whose annotation JSON
+    /// cannot express, see [`Document::to_json_typed`]
+    fn typed(value: &'a Value, annotation: Option<&'a str>) -> Self {
+        let data = match value {
+            Value::Block(block) => Data::Object(
+                block
+                    .nodes()
+                    .map(|node| (Cow::Borrowed(node.key.as_str()), Data::typed_node(node)))
+                    .collect(),
+            ),
+            Value::List(items) => {
+                Data::Array(items.iter().map(|item| Data::typed(item, None)).collect())
+            }
+            Value::Table { columns, rows } => Data::Array(
+                rows.iter()
+                    .map(|row| {
+                        Data::Object(
+                            columns
+                                .iter()
+                                .zip(row)
+                                .map(|(column, cell)| {
+                                    let key = column.scalar_text().unwrap_or_default();
+                                    (key, Data::typed(cell, None))
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            scalar => Data::of(scalar),
+        };
+        // The annotation a JSON value of this type reads back with
+        let native = match value {
+            Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::Null => {
+                Some(value.type_name())
+            }
+            _ => None,
+        };
+        let implied = match value {
+            Value::String(_) | Value::Block(_) | Value::List(_) => None,
+            Value::Empty => Some("empty"),
+            Value::Custom(custom) => Some(custom.annotation()),
+            typed => Some(typed.type_name()),
+        };
+        match annotation.or(implied) {
+            Some(name) if Some(name) != native => Data::Object(vec![
+                (Cow::Borrowed(TYPE_KEY), Data::Text(Cow::Borrowed(name))),
+                (Cow::Borrowed(VALUE_KEY), data),
+            ]),
+            _ => data,
+        }
+    }
+
+    fn of(value: &'a Value) -> Self {
+        match value {
+            Value::Null => Data::Null,
+            Value::Bool(b) => Data::Bool(*b),
+            Value::Int(n) => Data::Int(*n),
+            Value::Float(x) => Data::Float(*x),
+            Value::Block(block) => Data::block(block),
+            Value::List(items) => Data::Array(items.iter().map(Data::of).collect()),
+            Value::Table { columns, rows } => Data::Array(
+                rows.iter()
+                    .map(|row| {
+                        Data::Object(
+                            columns
+                                .iter()
+                                .zip(row)
+                                .map(|(column, cell)| {
+                                    (column.scalar_text().unwrap_or_default(), Data::of(cell))
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            other => Data::Text(other.scalar_text().unwrap_or_default()),
+        }
+    }
+
+    fn block(block: &'a Block) -> Self {
+        Data::Object(
+            block
+                .iter()
+                .map(|(key, value)| (Cow::Borrowed(key), Data::of(value)))
+                .collect(),
+        )
+    }
+
+    fn is_collection(&self) -> bool {
+        match self {
+            Data::Array(items) => !items.is_empty(),
+            Data::Object(entries) => !entries.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Write `text` as a double-quoted string, escaped as JSON
+///
+/// The escapes used are also valid in TOML basic strings and YAML
+/// double-quoted scalars.
+fn quote(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() && (c as u32) < 0x80 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Write JSON, indenting nested values when `indent` is the current depth
+fn json(out: &mut String, data: &Data<'_>, indent: Option<usize>) {
+    let newline = |out: &mut String, depth: usize| {
+        if indent.is_some() {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+        }
+    };
+    let depth = indent.unwrap_or(0);
+    let inner = indent.map(|depth| depth + 1);
+
+    match data {
+        Data::Null => out.push_str("null"),
+        Data::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Data::Int(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        // JSON has no infinity or NaN
+        Data::Float(x) if !x.is_finite() => out.push_str("null"),
+        Data::Float(x) => {
+            let _ = write!(out, "{:?}", x);
+        }
+        Data::Text(text) => quote(out, text),
+        Data::Array(items) if items.is_empty() => out.push_str("[]"),
+        Data::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, depth + 1);
+                json(out, item, inner);
+            }
+            newline(out, depth);
+            out.push(']');
+        }
+        Data::Object(entries) if entries.is_empty() => out.push_str("{}"),
+        Data::Object(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, depth + 1);
+                quote(out, key);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                json(out, value, inner);
+            }
+            newline(out, depth);
+            out.push('}');
+        }
+    }
+}
+
+/// Whether `text` can be written as a plain (unquoted) YAML scalar or key
+/// and still read back as the same string
+fn yaml_plain(text: &str) -> bool {
+    const RESERVED: &[&str] = &["true", "false", "yes", "no", "on", "off", "null", "y", "n"];
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '/')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'))
+        && !RESERVED.contains(&text.to_ascii_lowercase().as_str())
+}
+
+/// Write a scalar or empty collection on the current line
+fn yaml_inline(out: &mut String, data: &Data<'_>) {
+    match data {
+        Data::Null => out.push_str("null"),
+        Data::Float(x) if x.is_nan() => out.push_str(".nan"),
+        Data::Float(x) if x.is_infinite() => out.push_str(if *x > 0.0 { ".inf" } else { "-.inf" }),
+        Data::Text(text) if yaml_plain(text) => out.push_str(text),
+        Data::Array(_) => out.push_str("[]"),
+        Data::Object(_) => out.push_str("{}"),
+        scalar => json(out, scalar, None),
+    }
+}
+
+fn yaml_entries(out: &mut String, entries: &[(Cow<'_, str>, Data<'_>)], indent: usize) {
+    for (key, value) in entries {
+        out.push_str(&" ".repeat(indent));
+        if yaml_plain(key) {
+            out.push_str(key);
+        } else {
+            quote(out, key);
+        }
+        out.push(':');
+        yaml_nested(out, value, indent + 2);
+    }
+}
+
+fn yaml_items(out: &mut String, items: &[Data<'_>], indent: usize) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        if item.is_collection() {
+            // Start the nested collection on the dash's line
+            let mut nested = String::new();
+            yaml_nested(&mut nested, item, indent + 2);
+            out.push(' ');
+            out.push_str(&nested[indent + 3..]);
+        } else {
+            yaml_nested(out, item, indent + 2);
+        }
+    }
+}
+
+/// Write what follows a key or dash: a scalar on the same line, or a
+/// collection on the following lines at `indent`
+fn yaml_nested(out: &mut String, data: &Data<'_>, indent: usize) {
+    match data {
+        Data::Object(entries) if !entries.is_empty() => {
+            out.push('\n');
+            yaml_entries(out, entries, indent);
+        }
+        Data::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            yaml_items(out, items, indent);
+        }
+        scalar => {
+            out.push(' ');
+            yaml_inline(out, scalar);
+            out.push('\n');
+        }
+    }
+}
+
+fn toml_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        out.push_str(key);
+    } else {
+        quote(out, key);
+    }
+}
+
+/// Whether a value is written as its own `[table]` or `[[array]]` section
+fn toml_section(data: &Data<'_>) -> bool {
+    match data {
+        Data::Object(_) => true,
+        Data::Array(items) => {
+            !items.is_empty() && items.iter().all(|item| matches!(item, Data::Object(_)))
+        }
+        _ => false,
+    }
+}
+
+/// Write the body of the table at `path`: its plain keys, then its sections
+fn toml_table(
+    out: &mut String,
+    path: &str,
+    entries: &[(Cow<'_, str>, Data<'_>)],
+) -> Result<(), ParseError> {
+    for (key, value) in entries.iter().filter(|(_, value)| !toml_section(value)) {
+        toml_key(out, key);
+        out.push_str(" = ");
+        toml_inline(out, value, path, key)?;
+        out.push('\n');
+    }
+
+    for (key, value) in entries.iter().filter(|(_, value)| toml_section(value)) {
+        let mut sub = path.to_string();
+        if !sub.is_empty() {
+            sub.push('.');
+        }
+        toml_key(&mut sub, key);
+
+        let tables = match value {
+            Data::Object(entries) => vec![(false, entries)],
+            Data::Array(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Data::Object(entries) => Some((true, entries)),
+                    _ => None,
+                })
+                .collect(),
+            _ => unreachable!("sections are objects or arrays of objects"),
+        };
+        for (in_array, entries) in tables {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            if in_array {
+                let _ = writeln!(out, "[[{}]]", sub);
+            } else {
+                let _ = writeln!(out, "[{}]", sub);
+            }
+            toml_table(out, &sub, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a value on one line; `path` and `key` locate it for errors
+fn toml_inline(out: &mut String, data: &Data<'_>, path: &str, key: &str) -> Result<(), ParseError> {
+    match data {
+        Data::Null => {
+            let path = if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("TOML has no null value, found at `{}`", path),
+            ));
+        }
+        Data::Float(x) if x.is_nan() => out.push_str("nan"),
+        Data::Float(x) if x.is_infinite() => out.push_str(if *x > 0.0 { "inf" } else { "-inf" }),
+        Data::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                toml_inline(out, item, path, key)?;
+            }
+            out.push(']');
+        }
+        Data::Object(entries) if entries.is_empty() => out.push_str("{}"),
+        Data::Object(entries) => {
+            out.push_str("{ ");
+            for (i, (inner, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                toml_key(out, inner);
+                out.push_str(" = ");
+                toml_inline(out, value, path, key)?;
+            }
+            out.push_str(" }");
+        }
+        scalar => json(out, scalar, None),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Data<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        match self {
+            Data::Null => serializer.serialize_unit(),
+            Data::Bool(b) => serializer.serialize_bool(*b),
+            Data::Int(n) => serializer.serialize_i64(*n),
+            Data::Float(x) => serializer.serialize_f64(*x),
+            Data::Text(text) => serializer.serialize_str(text),
+            Data::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Data::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Serializes with the same data model as [`Document::to_json`], so e.g.
+/// `serde_json::to_value(&doc)` gives the document as a `serde_json::Value`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Document {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Data::document(self).serialize(serializer)
+    }
+}
+
+/// Serializes with the same data model as [`Document::to_json`]
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Data::of(self).serialize(serializer)
+    }
+}
+
+struct JsonReader<'a> {
+    input: &'a str,
+    rest: &'a str,
+    depth: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::new(ErrorKind::InvalidSyntax, message).locate(self.input, self.next_char())
+    }
+
+    /// The character about to be read, for errors to point at
+    fn next_char(&self) -> &'a str {
+        match self.rest.char_indices().nth(1) {
+            Some((end, _)) => &self.rest[..end],
+            None => self.rest,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t', '\n', '\r']);
+    }
+
+    /// Go one object or array deeper, within the default depth limit
+    fn nest(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        Limits::new()
+            .check_depth(self.depth)
+            .map_err(|e| e.locate(self.input, self.next_char()))
+    }
+
+    fn expect(&mut self, token: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                Ok(())
+            }
+            None => Err(self.error(&format!("expected `{}`", token))),
+        }
+    }
+
+    /// Read an object's entries, as nodes in order
+    fn object(&mut self) -> Result<Vec<Node>, ParseError> {
+        self.skip_whitespace();
+        self.nest()?;
+        self.expect('{')?;
+        let mut nodes = Vec::new();
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix('}') {
+            self.rest = rest;
+            self.depth -= 1;
+            return Ok(nodes);
+        }
+        loop {
+            self.skip_whitespace();
+            if !self.rest.starts_with('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            nodes.push(Node::new(key, self.value()?));
+            self.skip_whitespace();
+            match self.rest.chars().next() {
+                Some(',') => self.rest = &self.rest[1..],
+                Some('}') => {
+                    self.rest = &self.rest[1..];
+                    self.depth -= 1;
+                    return Ok(nodes);
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.rest.chars().next() {
+            Some('{') => Ok(Value::Block(self.object()?.into_iter().collect())),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Value::String),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                for (word, value) in [
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ] {
+                    if let Some(rest) = self.rest.strip_prefix(word) {
+                        self.rest = rest;
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+            None => Err(ParseError::new(
+                ErrorKind::UnexpectedEof,
+                "unexpected end of JSON",
+            )),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.nest()?;
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix(']') {
+            self.rest = rest;
+            self.depth -= 1;
+            return Ok(Value::List(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.rest.chars().next() {
+                Some(',') => self.rest = &self.rest[1..],
+                Some(']') => {
+                    self.rest = &self.rest[1..];
+                    self.depth -= 1;
+                    return Ok(Value::List(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+            .unwrap_or(self.rest.len());
+        let text = &self.rest[..end];
+        let integral = !text.contains(['.', 'e', 'E']);
+        let value = match text.parse::<i64>() {
+            Ok(n) if integral => Value::Int(n),
+            _ => match text.parse::<f64>() {
+                Ok(x) if !text.starts_with('+') => Value::Float(x),
+                _ => return Err(self.error("invalid number")),
+            },
+        };
+        self.rest = &self.rest[end..];
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.rest = &self.rest[1..];
+        let mut text = String::new();
+        loop {
+            let mut chars = self.rest.chars();
+            let c = chars.next().ok_or_else(|| {
+                ParseError::new(ErrorKind::UnexpectedEof, "unterminated JSON string")
+            })?;
+            match c {
+                '"' => {
+                    self.rest = chars.as_str();
+                    return Ok(text);
+                }
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            self.rest = chars.as_str();
+                            text.push(self.unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    text.push(escaped);
+                }
+                c if c.is_control() => return Err(self.error("control character in string")),
+                c => text.push(c),
+            }
+            self.rest = chars.as_str();
+        }
+    }
+
+    /// Read the digits of a `\u` escape, and a second one for a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            let low = match self.rest.strip_prefix("\\u") {
+                Some(rest) => {
+                    self.rest = rest;
+                    self.hex4()?
+                }
+                None => 0,
+            };
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate in `\\u` escape"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid `\\u` escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let code = self
+            .rest
+            .get(..4)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid `\\u` escape"))?;
+        self.rest = &self.rest[4..];
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "name app\nport!int 8080\nratio!float 0.5\ndebug!bool true\nowner!null\nprice!money 19.99 USD\ntags [web, api]\nserver {\nhost \"a b\"\ntls {\n}\n}\nservers [\n{\nhost a\n}\n{\nhost b\n}\n]\nusers!table {\ncolumns [id!int, name]\nrows {\n[1, ann]\n}\n}\nname final";
+
+    #[test]
+    fn test_to_json() {
+        let doc = parse(INPUT).unwrap();
+        assert_eq!(
+            doc.to_json(),
+            r#"{"port":8080,"ratio":0.5,"debug":true,"owner":null,"price":"19.99 USD","tags":["web","api"],"server":{"host":"a b","tls":{}},"servers":[{"host":"a"},{"host":"b"}],"users":[{"id":1,"name":"ann"}],"name":"final"}"#
+        );
+
+        let doc = parse("a {\nb [1]\nc {\n}\n}\nd say \"hi\"").unwrap();
+        assert_eq!(
+            doc.to_json_pretty(),
+            "{\n  \"a\": {\n    \"b\": [\n      \"1\"\n    ],\n    \"c\": {}\n  },\n  \"d\": \"say \\\"hi\\\"\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_value_to_json() {
+        let doc = parse(INPUT).unwrap();
+        assert_eq!(
+            doc.get("server").unwrap().to_json_string(),
+            r#"{"host":"a b","tls":{}}"#
+        );
+        assert_eq!(
+            doc.get("users").unwrap().to_json_string(),
+            r#"[{"id":1,"name":"ann"}]"#
+        );
+        assert_eq!(doc.get("owner").unwrap().to_json_string(), "null");
+        assert_eq!(
+            doc.get("tags").unwrap().to_json_pretty(),
+            "[\n  \"web\",\n  \"api\"\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let doc = parse("name app\nport!int 80\nempty []\nmode on\nlists [\n[a, b]\n]\nservers [\n{\nhost a\nport!int 1\n}\n]\nblock {\nkey value\n}").unwrap();
+        assert_eq!(
+            doc.to_yaml(),
+            "name: app\nport: 80\nempty: []\nmode: \"on\"\nlists:\n  - - a\n    - b\nservers:\n  - host: a\n    port: 1\nblock:\n  key: value\n"
+        );
+        assert_eq!(Document::new().to_yaml(), "{}\n");
+    }
+
+    #[test]
+    fn test_to_toml() {
+        let doc = parse(INPUT.replace("owner!null\n", "").as_str()).unwrap();
+        assert_eq!(
+            doc.to_toml().unwrap(),
+            "port = 8080\nratio = 0.5\ndebug = true\nprice = \"19.99 USD\"\ntags = [\"web\", \"api\"]\nname = \"final\"\n\n[server]\nhost = \"a b\"\n\n[server.tls]\n\n[[servers]]\nhost = \"a\"\n\n[[servers]]\nhost = \"b\"\n\n[[users]]\nid = 1\nname = \"ann\"\n"
+        );
+
+        let doc = parse("mixed [\n{\na 1\n}\nb\n]").unwrap();
+        assert_eq!(doc.to_toml().unwrap(), "mixed = [{ a = \"1\" }, \"b\"]\n");
+
+        let err = parse("a {\nb!null\n}").unwrap().to_toml().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert!(err.message.contains("`a.b`"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let doc = parse("name app\nname final\nserver {\nport!int 80\n}\nusers!table {\ncolumns [id!int]\nrows {\n[1]\n}\n}").unwrap();
+        assert_eq!(
+            crate::ser::to_string(&doc).unwrap(),
+            "name final\nserver {\n  port!int 80\n}\nusers [\n  {\n    id!int 1\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let doc = Document::from_json(
+            r#" {"name": "app", "port": 8080, "ratio": 1e3, "on": true, "none": null,
+                "tags": ["a", "é😀\n"], "server": {"host": "h"}, "empty": {}} "#,
+        )
+        .unwrap();
+        assert_eq!(doc.get_str("name"), Some("app"));
+        assert_eq!(doc.get("port"), Some(&Value::Int(8080)));
+        assert_eq!(doc.get("ratio"), Some(&Value::Float(1000.0)));
+        assert_eq!(doc.get_bool("on"), Some(true));
+        assert_eq!(doc.get("none"), Some(&Value::Null));
+        assert_eq!(doc.get_str("tags[1]"), Some("é😀\n"));
+        assert_eq!(doc.get_str("server.host"), Some("h"));
+
+        // Back to the same JSON
+        let json = r#"{"a":[1,2.5,"x",{"b":null}],"c":{}}"#;
+        assert_eq!(Document::from_json(json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn test_from_json_infers_types() {
+        let json = r#"{"port":8080,"ratio":0.5,"debug":false,"owner":null,"name":"app",
+            "server":{"port":80},"users":[{"id":1,"name":"ann"},{"name":"bo","id":2}],
+            "servers":[{"host":"a"},{"host":"b","port":1}],"one":[{"id":1}]}"#;
+        let doc = Document::from_json(json).unwrap();
+        let annotations: Vec<Option<&str>> = doc
+            .nodes
+            .iter()
+            .map(|node| node.type_annotation.as_deref())
+            .collect();
+        assert_eq!(
+            annotations,
+            [
+                Some("int"),
+                Some("float"),
+                Some("bool"),
+                Some("null"),
+                None,
+                None,
+                Some("table"),
+                None,
+                None
+            ]
+        );
+        assert_eq!(doc.get("server.port"), Some(&Value::Int(80)),);
+        let Some(Value::Block(server)) = doc.get("server") else {
+            panic!("expected block");
+        };
+        assert_eq!(
+            server.get_node("port").unwrap().type_annotation.as_deref(),
+            Some("int")
+        );
+
+        // Written as UP and parsed back, the document is unchanged
+        let text = crate::to_string(&doc);
+        assert!(text.contains("users!table {\n  columns [id!int, name]\n  rows [\n    [1, ann]\n    [2, bo]\n  ]\n}\n"));
+        assert_eq!(parse(&text).unwrap(), doc);
+        assert_eq!(
+            doc.to_json(),
+            Document::from_json(&doc.to_json()).unwrap().to_json()
+        );
+
+        // Mixed types in a column keep the objects
+        let doc = Document::from_json(r#"{"rows":[{"a":1},{"a":"x"}]}"#).unwrap();
+        assert!(matches!(doc.get("rows"), Some(Value::List(_))));
+    }
+
+    #[test]
+    fn test_json_typed_round_trip() {
+        let input = "name app\nport!int 8080\nratio!float 0.5\nowner!null\ntimeout!dur 1m30s\nlabel!string 42\nserver {\n  price!money 19.99 USD\n  token!secret abc\n  tags!list [a, b]\n}\nusers!table {\n  columns [id, since]\n  rows {\n    [1, 2024-05-01]\n  }\n}\n";
+        let doc = parse(input).unwrap();
+        let json = doc.to_json_typed();
+        assert!(json.contains(r#""price":{"$type":"money","value":"19.99 USD"}"#));
+        assert!(
+            json.contains(r#""users":{"$type":"table","value":[{"id":"1","since":"2024-05-01"}]}"#)
+        );
+        let back = Document::from_json_typed(&json).unwrap();
+        assert_eq!(back, doc);
+        assert_eq!(crate::to_string(&back), crate::to_string(&doc));
+        assert_eq!(
+            Document::from_json_typed(&doc.to_json_typed_pretty()).unwrap(),
+            doc
+        );
+
+        // Without the wrappers, annotations are lost
+        let plain = Document::from_json(&doc.to_json()).unwrap();
+        assert_eq!(plain.get_str("timeout"), Some("1m30s"));
+        assert_ne!(plain, doc);
+
+        let doc = parse("debug\nserver {\n  verbose\n}\nflags [\n  x\n]\n").unwrap();
+        let json = doc.to_json_typed();
+        assert!(json.starts_with(r#"{"debug":{"$type":"empty","value":""}"#));
+        assert_eq!(Document::from_json_typed(&json).unwrap(), doc);
+
+        let err = Document::from_json_typed(r#"{"t":{"$type":"dur","value":"soon"}}"#).unwrap_err();
+        assert_eq!(err.message, "invalid duration `soon`");
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let err = Document::from_json("[1]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+
+        let err = Document::from_json("{\n\"a\": 1,\n\"b\": tru\n}").unwrap_err();
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.location.unwrap().column, 6);
+
+        let err = Document::from_json("{\"a\": \"open").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedEof);
+        assert!(Document::from_json("{} x").is_err());
+    }
+}
@@ -0,0 +1,710 @@
+//! The recursive-descent reader behind [`Parser::parse_document`]
+//!
+//! A [`Reader`] holds everything parsing one input needs: the parser's
+//! settings, the input, the [`Lexer`] reading its lines, and whether
+//! delimiters are checked strictly where it is. Each kind of value is read
+//! by a method of its own that takes the lines it needs from the lexer and
+//! calls the others for the values nested in it, so a block reads its
+//! nodes, a node its value, and a value in a block another block, down to
+//! the closing line of each. Variables are scoped as the values nest, and
+//! so are passed down rather than kept in the reader.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::inline::is_inline_block;
+use crate::interpolate::Scope;
+use crate::lexer::{self, Close, Lexer, Line, Token};
+#[cfg(any(feature = "json", feature = "yaml"))]
+use crate::parses_data;
+use crate::source::{self, Origin, Span};
+use crate::trivia::TriviaTracker;
+#[cfg(feature = "yaml")]
+use crate::yaml;
+use crate::{
+    anchor, collect_comment, continues, dedent_common, dotted, fence_lang, interpolate_items,
+    keyed_item, quote, stray, table, take_comment, unclosed, Block, ErrorKind, Node, ParseError,
+    Parser, Value,
+};
+#[cfg(feature = "json")]
+use crate::{convert, error};
+
+/// The state of parsing one input
+pub(crate) struct Reader<'p, 'a> {
+    pub(crate) parser: &'p Parser,
+    pub(crate) input: &'a str,
+    pub(crate) lines: Lexer<'a, 'p>,
+    /// Whether unclosed and stray delimiters are errors, as
+    /// [`Parser::strict`] sets it and `@strict` and `@lenient` change it
+    /// for the rest of a block
+    pub(crate) strict: bool,
+}
+
+impl<'p, 'a> Reader<'p, 'a> {
+    pub(crate) fn new(parser: &'p Parser, input: &'a str) -> Self {
+        Self {
+            parser,
+            input,
+            lines: Lexer::new(input, &parser.comments),
+            strict: parser.strict,
+        }
+    }
+
+    /// Read top-level nodes into `nodes`, defining `@let` variables in
+    /// `scope`
+    pub(crate) fn nodes(
+        &mut self,
+        nodes: &mut Vec<Node>,
+        scope: &mut Scope<'_>,
+    ) -> Result<(), ParseError> {
+        let parser = self.parser;
+        let input = self.input;
+        let mut comment = Vec::new();
+        let mut trivia = parser
+            .preserve_trivia
+            .then(|| TriviaTracker::new(input, self.lines.peek().map(|(_, line)| line)));
+        let mut collected = dotted::Collected::new();
+
+        while let Some(next) = self.lines.next() {
+            let (line_num, line) = (next.number, next.text);
+
+            // Skip empty lines and comments
+            if next.is_trivia() {
+                if let Some(trivia) = &mut trivia {
+                    trivia.skipped(input, line, &comment);
+                }
+                collect_comment(&mut comment, next.comment());
+                continue;
+            }
+
+            match next.token {
+                Token::Let(definition) => {
+                    if let Err(e) = parser.define(&self.lines, scope, definition) {
+                        self.recover(line, line_num, e)?;
+                    }
+                    comment.clear();
+                    continue;
+                }
+                Token::Include(path) => {
+                    match parser.include(&self.lines, path, scope.depth()) {
+                        Ok(included) => nodes.extend(included),
+                        Err(e) => self.recover(line, line_num, e)?,
+                    }
+                    comment.clear();
+                    continue;
+                }
+                Token::Close(_) => {
+                    if let Err(e) = self.check_stray(next.code) {
+                        self.recover(line, line_num, e)?;
+                        comment.clear();
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            // Top-level lines are split with their indentation
+            let mut node = match self.node(next.indented(), line_num, scope) {
+                Ok(node) => node,
+                Err(e) => {
+                    self.recover(line, line_num, e)?;
+                    comment.clear();
+                    continue;
+                }
+            };
+            if node.type_annotation.as_deref() == Some("anchor") {
+                scope.define_anchor(&node.key, node.value.clone());
+            }
+            node.doc = take_comment(&mut comment);
+            if let Some(trivia) = &mut trivia {
+                let next = self.lines.peek().map(|(_, next)| next);
+                trivia.attach(input, &mut node, line, next);
+            }
+            // Quoted keys keep their dots
+            if parser.dotted_keys && !line.starts_with('"') && node.key.contains('.') {
+                let inserted = parser.insert_dotted(&self.lines, nodes, node, line, &mut collected);
+                if let Err(e) = inserted {
+                    self.recover(line, line_num, e)?;
+                }
+                continue;
+            }
+            nodes.push(node);
+        }
+
+        if let Some(trivia) = &trivia {
+            trivia.finish(input, nodes.last_mut(), None);
+        }
+        Ok(())
+    }
+
+    /// Read the node on `line`, the `line_num`th line, and the lines its
+    /// value goes on over
+    pub(crate) fn node(
+        &mut self,
+        line: &str,
+        line_num: usize,
+        scope: &Scope<'_>,
+    ) -> Result<Node, ParseError> {
+        let parser = self.parser;
+        let (key_part, first_part) = parser.split_key_value(line);
+        let (key, type_annotation) = quote::split_key(key_part)
+            .and_then(|(key, annotation)| {
+                parser.limits.check_key(&key)?;
+                Ok((key, annotation))
+            })
+            .map_err(|e| self.lines.locate(e, key_part))?;
+        let type_annotation = type_annotation.map(str::to_string);
+        let val_part = self.continued(first_part);
+
+        // An anchor cannot refer to itself
+        let anchoring;
+        let value_scope = if type_annotation.as_deref() == Some("anchor") {
+            anchoring = Scope::anchoring(scope, &key);
+            &anchoring
+        } else {
+            scope
+        };
+        let value = self
+            .value(&val_part, type_annotation.as_deref(), value_scope)
+            .map_err(|e| {
+                let culprit = if first_part.is_empty() {
+                    key_part
+                } else {
+                    first_part
+                };
+                self.lines.locate(e, culprit)
+            })?;
+
+        let span = if parser.spans {
+            let next = self.lines.peek().map(|(_, next)| next);
+            Span::of(self.input, line_num, line, next)
+        } else {
+            None
+        };
+        // A reference reads as a copy of its anchor's value
+        let (type_annotation, anchor) = match type_annotation {
+            Some(a) if a == "ref" => (None, Some(anchor::ref_name(&val_part).to_string())),
+            annotation => (annotation, None),
+        };
+        Ok(Node {
+            key: key.into_owned(),
+            type_annotation,
+            value,
+            origin: Some(Origin::line(line_num + 1)),
+            span,
+            doc: None,
+            lang: fence_lang(first_part).map(str::to_string),
+            anchor,
+            provenance: Vec::new(),
+            generated_from: None,
+            trivia: None,
+        })
+    }
+
+    /// `first`, joined with the following lines if it ends in a single `\`
+    ///
+    /// The backslash is dropped and the next line's leading whitespace is
+    /// stripped, so `a \` followed by `b` reads as `a b`. An even number of
+    /// trailing backslashes is left alone.
+    fn continued<'b>(&mut self, first: &'b str) -> Cow<'b, str> {
+        if !continues(first) {
+            return Cow::Borrowed(first);
+        }
+
+        let mut joined = first[..first.len() - 1].to_string();
+        for line in self.lines.by_ref() {
+            let line = line.code;
+            if continues(line) {
+                joined.push_str(&line[..line.len() - 1]);
+            } else {
+                joined.push_str(line);
+                break;
+            }
+        }
+        Cow::Owned(joined)
+    }
+
+    /// Read a value written as `val_part`, and the lines after it that it
+    /// opens
+    pub(crate) fn value(
+        &mut self,
+        val_part: &str,
+        type_annotation: Option<&str>,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ParseError> {
+        let parser = self.parser;
+        match val_part {
+            "" if type_annotation.is_none() => {
+                return Ok(match parser.flag_keys {
+                    true => Value::Bool(true),
+                    false => Value::Empty,
+                })
+            }
+            _ if type_annotation == Some("ref") => return self.reference(val_part, scope),
+            "{" if type_annotation == Some("table") => return self.table(scope),
+            "{" => return self.block(scope),
+            "[" => return self.list(scope),
+            s if s.starts_with("```") && type_annotation == Some("up") => {
+                return self.embedded(scope)
+            }
+            #[cfg(any(feature = "json", feature = "yaml"))]
+            s if s.starts_with("```") && parses_data(type_annotation) => {
+                return self.data(type_annotation.unwrap_or_default())
+            }
+            s if s.starts_with("```") => return self.multiline(type_annotation),
+            s if is_inline_block(s) => return parser.parse_inline_block(s, scope),
+            s if s.starts_with('[') && s.ends_with(']') => {
+                // Inline list
+                let items = parser.parse_inline_items(s, scope)?;
+                parser.limits.check_items(items.len(), "items in a list")?;
+                return Ok(Value::List(interpolate_items(scope, items)?));
+            }
+            _ => {}
+        }
+
+        if let Some(quoted) = quote::whole(val_part) {
+            let text = quote::unescape(quoted)?;
+            let text = parser.normalizers.apply(&text);
+            return parser.parse_scalar(scope.interpolate(&text)?, type_annotation);
+        }
+        let text = parser.normalizers.apply(val_part);
+        parser.parse_scalar(scope.interpolate(&text)?, type_annotation)
+    }
+
+    /// Read the body of a fenced multiline string
+    fn multiline(&mut self, type_annotation: Option<&str>) -> Result<Value, ParseError> {
+        if type_annotation == Some("dedent") {
+            let (body, closed) = self.lines.fenced();
+            let body: Vec<&str> = body.into_iter().map(|(_, line)| line).collect();
+            let text = Value::String(dedent_common(&body).join("\n"));
+            return match closed {
+                true => Ok(text),
+                false => self.end_of_input(text, "multiline string"),
+            };
+        }
+
+        // Apply dedenting if type annotation is a number
+        let dedent_amount = type_annotation.and_then(|t| t.parse::<usize>().ok());
+
+        // Build the value in place so huge embedded values are copied once
+        let mut text = String::new();
+        let mut first = true;
+
+        while let Some((_, line)) = self.lines.raw() {
+            if lexer::trim(line) == "```" {
+                return Ok(Value::String(text));
+            }
+            if !first {
+                text.push('\n');
+            }
+            first = false;
+            match dedent_amount {
+                Some(amount) => text.push_str(self.parser.dedent(line, amount)),
+                None => text.push_str(line),
+            }
+        }
+
+        self.end_of_input(Value::String(text), "multiline string")
+    }
+
+    /// Read the body of an `!up` multiline value as a nested document
+    ///
+    /// The lines are dedented by their common indentation. `@let`
+    /// definitions inside stay local to the embedded document, which can
+    /// still use the variables of the enclosing scope.
+    fn embedded(&mut self, scope: &Scope<'_>) -> Result<Value, ParseError> {
+        let (body, closed) = self.lines.fenced();
+        let first_line = body.first().map_or(0, |(line_num, _)| *line_num);
+        let body: Vec<&str> = body.into_iter().map(|(_, line)| line).collect();
+        let dedented = dedent_common(&body);
+        let text = dedented.join("\n");
+
+        // The dedented text is not the source, so has no trivia or spans to
+        // keep; errors stop it, to be recovered from as errors of this node
+        let parser = Parser {
+            preserve_trivia: false,
+            spans: false,
+            recovery: None,
+            ..self.parser.clone()
+        };
+        let mut scope = Scope::child(scope);
+        parser.limits.check_depth(scope.depth())?;
+        let mut nodes = Vec::new();
+        let mut reader = Reader::new(&parser, &text);
+        reader.strict = self.strict;
+        reader.nodes(&mut nodes, &mut scope).map_err(|mut e| {
+            // Point at the same text in the enclosing input, unless the
+            // error is in an included file
+            if e.location.as_ref().is_some_and(|l| l.file.is_some()) {
+                return e;
+            }
+            if let Some(location) = e.location.take() {
+                let line = dedented[location.line - 1];
+                let line_start = text[..location.span.start].rfind('\n').map_or(0, |i| i + 1);
+                let start = (location.span.start - line_start).min(line.len());
+                let end = (location.span.end - line_start).clamp(start, line.len());
+                e.location = line
+                    .get(start..end)
+                    .and_then(|culprit| self.lines.location(culprit));
+            }
+            e
+        })?;
+
+        for node in &mut nodes {
+            source::shift_lines(node, first_line, 0);
+        }
+        let block = Value::Block(nodes.into_iter().collect());
+        if closed {
+            Ok(block)
+        } else {
+            self.end_of_input(block, "multiline string")
+        }
+    }
+
+    /// Read the body of a `!json` or `!yaml` multiline value
+    #[cfg(any(feature = "json", feature = "yaml"))]
+    fn data(&mut self, annotation: &str) -> Result<Value, ParseError> {
+        let input = self.input;
+        let (body, closed) = self.lines.fenced();
+        let body: Vec<&str> = body.into_iter().map(|(_, line)| line).collect();
+        let value = match annotation {
+            #[cfg(feature = "json")]
+            "json" => {
+                // The body lines are consecutive slices of the input
+                let text = match (body.first(), body.last()) {
+                    (Some(first), Some(last)) => error::offset_in(input, first)
+                        .zip(error::offset_in(input, last))
+                        .map_or("", |(start, end)| &input[start..end + last.len()]),
+                    _ => "",
+                };
+                convert::parse_json(input, text)?
+            }
+            #[cfg(feature = "yaml")]
+            "yaml" => yaml::parse(input, &body)?,
+            other => unreachable!("`{}` values are not parsed", other),
+        };
+        if closed {
+            Ok(value)
+        } else {
+            self.end_of_input(value, "multiline string")
+        }
+    }
+
+    /// Read the nodes of a block up to its closing `}`
+    ///
+    /// A `@strict` or `@lenient` line holds for the rest of the block, and
+    /// the blocks nested in it.
+    pub(crate) fn block(&mut self, parent: &Scope<'_>) -> Result<Value, ParseError> {
+        let strict = self.strict;
+        let block = self.block_nodes(parent);
+        self.strict = strict;
+        block
+    }
+
+    fn block_nodes(&mut self, parent: &Scope<'_>) -> Result<Value, ParseError> {
+        let parser = self.parser;
+        let input = self.input;
+        let mut block = Block::new();
+        let mut comment = Vec::new();
+        let mut scope = Scope::child(parent);
+        parser.limits.check_depth(scope.depth())?;
+        let mut trivia = parser
+            .preserve_trivia
+            .then(|| TriviaTracker::new(input, self.lines.peek().map(|(_, line)| line)));
+        let mut last: Option<String> = None;
+        let mut collected = HashSet::new();
+
+        while let Some(next) = self.lines.next() {
+            let (line_num, line, trimmed) = (next.number, next.text, next.code);
+
+            // Skip empty lines and comments
+            if next.is_trivia() {
+                if let Some(trivia) = &mut trivia {
+                    trivia.skipped(input, line, &comment);
+                }
+                collect_comment(&mut comment, next.comment());
+                continue;
+            }
+
+            match next.token {
+                Token::Close(Close::Block) => {
+                    if let (Some(trivia), Some(key)) = (&trivia, &last) {
+                        trivia.finish(input, block.get_node_mut(key), Some(line));
+                    }
+                    return Ok(Value::Block(block));
+                }
+                Token::Strictness(strict) => {
+                    self.strict = strict;
+                    comment.clear();
+                    continue;
+                }
+                Token::Let(definition) => {
+                    if let Err(e) = parser.define(&self.lines, &mut scope, definition) {
+                        self.recover(line, line_num, e)?;
+                    }
+                    comment.clear();
+                    continue;
+                }
+                Token::Include(path) => {
+                    let lines = &self.lines;
+                    let included =
+                        parser
+                            .include(lines, path, scope.depth())
+                            .and_then(|included| {
+                                for node in included {
+                                    last = Some(node.key.clone());
+                                    parser.insert_into(
+                                        lines,
+                                        &mut block,
+                                        node,
+                                        trimmed,
+                                        &mut collected,
+                                    )?;
+                                }
+                                Ok(())
+                            });
+                    if let Err(e) = included {
+                        self.recover(line, line_num, e)?;
+                    }
+                    comment.clear();
+                    continue;
+                }
+                Token::Close(_) => {
+                    if let Err(e) = self.check_stray(trimmed) {
+                        self.recover(line, line_num, e)?;
+                        comment.clear();
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            let mut node = match self.node(trimmed, line_num, &scope) {
+                Ok(node) => node,
+                Err(e) => {
+                    self.recover(line, line_num, e)?;
+                    comment.clear();
+                    continue;
+                }
+            };
+            if node.type_annotation.as_deref() == Some("anchor") {
+                scope.define_anchor(&node.key, node.value.clone());
+            }
+            node.doc = take_comment(&mut comment);
+            if let Some(trivia) = &mut trivia {
+                let next = self.lines.peek().map(|(_, next)| next);
+                trivia.attach(input, &mut node, line, next);
+            }
+            let key = node.key.clone();
+            match parser.insert_into(&self.lines, &mut block, node, trimmed, &mut collected) {
+                Ok(()) => last = Some(key),
+                Err(e) => self.recover(line, line_num, e)?,
+            }
+        }
+
+        self.end_of_input(Value::Block(block), "block")
+    }
+
+    /// Read the items of a list up to its closing `]`
+    fn list(&mut self, scope: &Scope<'_>) -> Result<Value, ParseError> {
+        let parser = self.parser;
+        let mut list = Vec::new();
+        let scope = &Scope::child(scope);
+        parser.limits.check_depth(scope.depth())?;
+
+        while let Some(next) = self.lines.next() {
+            let trimmed = next.code;
+            match next.token {
+                Token::Close(Close::List) => return Ok(Value::List(list)),
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                _ => {}
+            }
+            parser
+                .limits
+                .check_items(list.len() + 1, "items in a list")
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+
+            list.push(self.item(next, scope)?);
+        }
+
+        self.end_of_input(Value::List(list), "list")
+    }
+
+    /// Read the list item on `next`, and the lines after it that it opens
+    pub(crate) fn item(&mut self, next: Line<'_>, scope: &Scope<'_>) -> Result<Value, ParseError> {
+        let parser = self.parser;
+        let trimmed = next.code;
+        // Handle inline list within a multiline list
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let inner_list = parser
+                .parse_inline_items(trimmed, scope)
+                .and_then(|items| {
+                    parser.limits.check_items(items.len(), "items in a list")?;
+                    interpolate_items(scope, items)
+                })
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+            Ok(Value::List(inner_list))
+        } else if is_inline_block(trimmed) {
+            parser
+                .parse_inline_block(trimmed, scope)
+                .map_err(|e| self.lines.locate(e, trimmed))
+        } else if trimmed.starts_with('{') {
+            self.block(scope).map_err(|e| self.lines.locate(e, trimmed))
+        } else if trimmed == "[" {
+            self.list(scope).map_err(|e| self.lines.locate(e, trimmed))
+        } else if trimmed.starts_with("```") {
+            self.multiline(None)
+        } else if keyed_item(trimmed).is_some() {
+            // A node of its own, read as a block holding just its key
+            let node = self.node(trimmed, next.number, scope)?;
+            Ok(Value::Block(std::iter::once(node).collect()))
+        } else if let Some(quoted) = quote::whole(trimmed) {
+            let item = quote::unescape(quoted)
+                .and_then(|item| {
+                    let item = parser.normalizers.apply(&item);
+                    Ok(scope.interpolate(&item)?.into_owned())
+                })
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+            Ok(Value::String(item))
+        } else {
+            if next.token == Token::Close(Close::Block) {
+                self.check_stray(trimmed)?;
+            }
+            let item = self.continued(trimmed);
+            let item = parser.normalizers.apply(&item);
+            let item = scope
+                .interpolate(&item)
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+            Ok(Value::String(item.into_owned()))
+        }
+    }
+
+    /// Read the body of a `!table` block: a `columns [...]` header and a
+    /// `rows {` (or `rows [`) section holding one inline list per row
+    ///
+    /// Column names may carry annotations (`columns [id!int, name]`) that
+    /// type every cell in that column; the names are stored without them.
+    fn table(&mut self, scope: &Scope<'_>) -> Result<Value, ParseError> {
+        let parser = self.parser;
+        parser.limits.check_depth(scope.depth() + 1)?;
+        let mut columns: Option<Vec<(String, Option<String>)>> = None;
+        let mut rows = Vec::new();
+
+        while let Some(next) = self.lines.next() {
+            let trimmed = next.code;
+            match next.token {
+                Token::Close(Close::Block) => {
+                    let Some(columns) = columns else {
+                        return Err(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            "table has no `columns`",
+                        ));
+                    };
+                    return Ok(table(columns, rows));
+                }
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                _ => {}
+            }
+
+            match parser.split_key_value(trimmed) {
+                ("columns", header) if header.starts_with('[') && header.ends_with(']') => {
+                    let names = parser
+                        .parse_columns(header)
+                        .map_err(|e| self.lines.locate(e, header))?;
+                    columns = Some(names);
+                }
+                ("rows", open @ ("{" | "[")) => {
+                    let Some(columns) = &columns else {
+                        return Err(self.lines.locate(
+                            ParseError::new(
+                                ErrorKind::InvalidSyntax,
+                                "table `rows` must come after its `columns`",
+                            ),
+                            trimmed,
+                        ));
+                    };
+                    let close = if open == "{" {
+                        Close::Block
+                    } else {
+                        Close::List
+                    };
+                    if !self.rows(close, columns, scope, &mut rows)? {
+                        return self.end_of_input(table(columns.clone(), rows), "table");
+                    }
+                }
+                _ => {
+                    let message = format!(
+                        "expected `columns [...]` or `rows {{` in table, found `{}`",
+                        trimmed
+                    );
+                    return Err(self
+                        .lines
+                        .locate(ParseError::new(ErrorKind::InvalidSyntax, message), trimmed));
+                }
+            }
+        }
+
+        self.end_of_input(table(columns.unwrap_or_default(), rows), "table")
+    }
+
+    /// Read table rows up to `close`, typing each cell by its column
+    ///
+    /// Returns `false` when the input ends before `close`.
+    fn rows(
+        &mut self,
+        close: Close,
+        columns: &[(String, Option<String>)],
+        scope: &Scope<'_>,
+        rows: &mut Vec<Vec<Value>>,
+    ) -> Result<bool, ParseError> {
+        let parser = self.parser;
+        while let Some(next) = self.lines.next() {
+            let trimmed = next.code;
+            match next.token {
+                Token::Close(closed) if closed == close => return Ok(true),
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                _ => {}
+            }
+
+            let row = parser
+                .limits
+                .check_items(rows.len() + 1, "rows in a table")
+                .and_then(|()| parser.parse_row(trimmed, columns, scope))
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+            rows.push(row);
+        }
+
+        Ok(false)
+    }
+
+    /// Finish a block, list, or multiline string that ran into the end of
+    /// input
+    pub(crate) fn end_of_input(&self, value: Value, what: &str) -> Result<Value, ParseError> {
+        unclosed(value, what, self.parser.eof_is_error, self.strict)
+    }
+
+    /// When strict, reject `trimmed`, a line that only closes something of
+    /// a kind that is not open
+    pub(crate) fn check_stray(&self, trimmed: &str) -> Result<(), ParseError> {
+        match self.strict {
+            true => Err(stray(&self.lines, trimmed)),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, ErrorKind};
+
+    #[test]
+    fn test_strictness_reaches_embedded_documents() {
+        let input = "a {\n  @strict\n  doc!up ```\n    b {\n      c 1\n  ```\n}\n";
+        let err = parse(input).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedDelimiter);
+        assert!(parse("a {\n  doc!up ```\n    b {\n  ```\n}\n").is_ok());
+    }
+}
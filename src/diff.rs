@@ -0,0 +1,358 @@
+//! Differences between two documents by key path, see [`diff`]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{Block, Document, Value};
+
+/// One difference between two documents, see [`diff`]
+///
+/// Paths use dots between block keys and `[i]` for list items, e.g.
+/// `servers[2].host`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A key or list item only in the new document
+    Added {
+        /// Path in the new document
+        path: String,
+        /// The new value
+        value: Value,
+    },
+    /// A key or list item only in the old document
+    Removed {
+        /// Path in the old document
+        path: String,
+        /// The old value
+        value: Value,
+    },
+    /// A value that is different in the new document
+    Modified {
+        /// Path in the new document
+        path: String,
+        /// The old value
+        old: Value,
+        /// The new value
+        new: Value,
+    },
+}
+
+impl Change {
+    /// Key path of the changed value
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } => path,
+            Change::Modified { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {} = {}", path, value.truncated()),
+            Change::Removed { path, value } => write!(f, "- {} = {}", path, value.truncated()),
+            Change::Modified { path, old, new } => {
+                write!(f, "~ {}: {} -> {}", path, old.truncated(), new.truncated())
+            }
+        }
+    }
+}
+
+/// Compare two documents value by value
+///
+/// Blocks are compared key by key and lists item by item, so a change deep
+/// inside a document is reported at its own path. Values of different
+/// kinds, and tables, are reported as modified as a whole. Changes come
+/// in the order of the old document, followed by keys added at the end of
+/// each block. See [`Differ`] to match list items that moved.
+///
+/// ```
+/// use uplang::{diff, parse, Change, Value};
+///
+/// let old = parse("server {\n  host a\n  port 80\n}\ndebug true").unwrap();
+/// let new = parse("server {\n  host b\n  port 80\n  tls true\n}").unwrap();
+/// let changes: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+/// assert_eq!(changes, ["~ server.host: a -> b", "+ server.tls = true", "- debug = true"]);
+/// ```
+pub fn diff(old: &Document, new: &Document) -> Vec<Change> {
+    Differ::new().diff(old, new)
+}
+
+/// Options for comparing documents, see [`diff`]
+///
+/// ```
+/// use uplang::{parse, Differ};
+///
+/// let old = parse("hosts [a, b, c]").unwrap();
+/// let new = parse("hosts [a, c]").unwrap();
+/// let changes = Differ::new().match_lists(true).diff(&old, &new);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].to_string(), "- hosts[1] = b");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Differ {
+    match_lists: bool,
+    list_key: Option<String>,
+}
+
+impl Differ {
+    /// Create a differ comparing lists item by item
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match the items of two lists that stayed the same, so an item added
+    /// or removed in the middle is reported alone rather than shifting every
+    /// item after it (default `false`)
+    pub fn match_lists(mut self, enabled: bool) -> Self {
+        self.match_lists = enabled;
+        self
+    }
+
+    /// Match blocks in lists by the value of their `key`, such as `id` or
+    /// `name`, and report how matched blocks changed; turns on
+    /// [`match_lists`](Self::match_lists)
+    pub fn list_key(mut self, key: impl Into<String>) -> Self {
+        self.list_key = Some(key.into());
+        self.match_lists(true)
+    }
+
+    /// Compare `old` with `new`
+    pub fn diff(&self, old: &Document, new: &Document) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let old_nodes = old
+            .nodes
+            .iter()
+            .map(|node| (node.key.as_str(), &node.value));
+        let new_nodes = new
+            .nodes
+            .iter()
+            .map(|node| (node.key.as_str(), &node.value));
+        self.entries(String::new(), old_nodes, new_nodes, &mut changes);
+        changes
+    }
+
+    fn entries<'a>(
+        &self,
+        prefix: String,
+        old: impl Iterator<Item = (&'a str, &'a Value)>,
+        new: impl Iterator<Item = (&'a str, &'a Value)> + Clone,
+        out: &mut Vec<Change>,
+    ) {
+        // The first definition of each new key, found in one pass
+        let mut new_values: HashMap<&str, &Value> = HashMap::new();
+        for (key, value) in new.clone() {
+            new_values.entry(key).or_insert(value);
+        }
+        let mut seen = HashSet::new();
+        for (key, old_value) in old {
+            let path = join(&prefix, key);
+            match new_values.get(key) {
+                Some(new_value) => self.value(path, old_value, new_value, out),
+                None => out.push(Change::Removed {
+                    path,
+                    value: old_value.clone(),
+                }),
+            }
+            seen.insert(key);
+        }
+        for (key, new_value) in new {
+            if seen.insert(key) {
+                out.push(Change::Added {
+                    path: join(&prefix, key),
+                    value: new_value.clone(),
+                });
+            }
+        }
+    }
+
+    fn value(&self, path: String, old: &Value, new: &Value, out: &mut Vec<Change>) {
+        match (old, new) {
+            (Value::Block(old), Value::Block(new)) => {
+                self.entries(path, entries(old), entries(new), out)
+            }
+            (Value::List(old), Value::List(new)) if self.match_lists => {
+                self.matched_items(&path, old, new, out)
+            }
+            (Value::List(old), Value::List(new)) => {
+                for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                    self.value(index(&path, i), old, new, out);
+                }
+                for (i, value) in old.iter().enumerate().skip(new.len()) {
+                    out.push(Change::Removed {
+                        path: index(&path, i),
+                        value: value.clone(),
+                    });
+                }
+                for (i, value) in new.iter().enumerate().skip(old.len()) {
+                    out.push(Change::Added {
+                        path: index(&path, i),
+                        value: value.clone(),
+                    });
+                }
+            }
+            (old, new) if old != new => out.push(Change::Modified {
+                path,
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            _ => {}
+        }
+    }
+
+    /// Compare lists by their longest common run of matching items
+    fn matched_items(&self, path: &str, old: &[Value], new: &[Value], out: &mut Vec<Change>) {
+        // Longest common subsequence lengths of every pair of suffixes
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if self.same_item(&old[i], &new[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && self.same_item(&old[i], &new[j]) {
+                self.value(index(path, j), &old[i], &new[j], out);
+                i += 1;
+                j += 1;
+            } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                out.push(Change::Removed {
+                    path: index(path, i),
+                    value: old[i].clone(),
+                });
+                i += 1;
+            } else {
+                out.push(Change::Added {
+                    path: index(path, j),
+                    value: new[j].clone(),
+                });
+                j += 1;
+            }
+        }
+    }
+
+    /// Whether two list items are the same item, possibly changed
+    fn same_item<'a>(&self, old: &'a Value, new: &'a Value) -> bool {
+        let key = self.list_key.as_deref();
+        let keyed = |value: &'a Value| value.as_block()?.get(key?);
+        match (keyed(old), keyed(new)) {
+            (Some(old), Some(new)) => old == new,
+            _ => old == new,
+        }
+    }
+}
+
+fn entries(block: &Block) -> impl Iterator<Item = (&str, &Value)> + Clone {
+    block.nodes().map(|node| (node.key.as_str(), &node.value))
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn index(path: &str, i: usize) -> String {
+    format!("{}[{}]", path, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn changes(differ: &Differ, old: &str, new: &str) -> Vec<String> {
+        let (old, new) = (parse(old).unwrap(), parse(new).unwrap());
+        differ
+            .diff(&old, &new)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let old =
+            "name app\nserver {\n  host a\n  ports [80, 443]\n}\nlimits {\n  cpu 1\n}\nold x\n";
+        let new = "name app\nserver {\n  host b\n  ports [80, 443, 8080]\n  tls {\n    cert c\n  }\n}\nlimits 4\nnew y\n";
+        assert_eq!(
+            changes(&Differ::new(), old, new),
+            [
+                "~ server.host: a -> b",
+                "+ server.ports[2] = 8080",
+                "+ server.tls = {cert: c}",
+                "~ limits: {cpu: 1} -> 4",
+                "- old = x",
+                "+ new = y",
+            ]
+        );
+        assert!(changes(&Differ::new(), old, old).is_empty());
+
+        let (old, new) = (parse(old).unwrap(), parse(new).unwrap());
+        let found = diff(&old, &new);
+        assert_eq!(
+            found[0],
+            Change::Modified {
+                path: "server.host".into(),
+                old: Value::from("a"),
+                new: Value::from("b"),
+            }
+        );
+        assert_eq!(found[4].path(), "old");
+
+        // Typed values differ from the text they were written as
+        assert_eq!(
+            changes(&Differ::new(), "port 80", "port!int 80"),
+            ["~ port: 80 -> 80"]
+        );
+
+        // Repeated top-level keys compare with the first new definition
+        assert_eq!(
+            changes(&Differ::new(), "a 1\na 2\n", "a 2\na 3\n"),
+            ["~ a: 1 -> 2"]
+        );
+    }
+
+    #[test]
+    fn test_diff_lists() {
+        let (old, new) = ("hosts [a, b, c, d]", "hosts [a, c, d, e]");
+        assert_eq!(
+            changes(&Differ::new(), old, new),
+            [
+                "~ hosts[1]: b -> c",
+                "~ hosts[2]: c -> d",
+                "~ hosts[3]: d -> e"
+            ]
+        );
+        assert_eq!(
+            changes(&Differ::new().match_lists(true), old, new),
+            ["- hosts[1] = b", "+ hosts[3] = e"]
+        );
+
+        let old =
+            "users [\n  {\n    id 1\n    role admin\n  }\n  {\n    id 2\n    role dev\n  }\n]\n";
+        let new = "users [\n  {\n    id 2\n    role ops\n  }\n]\n";
+        assert_eq!(
+            changes(&Differ::new().match_lists(true), old, new),
+            [
+                "- users[0] = {id: 1, role: admin}",
+                "- users[1] = {id: 2, role: dev}",
+                "+ users[0] = {id: 2, role: ops}",
+            ]
+        );
+        assert_eq!(
+            changes(&Differ::new().list_key("id"), old, new),
+            [
+                "- users[0] = {id: 1, role: admin}",
+                "~ users[0].role: dev -> ops"
+            ]
+        );
+    }
+}
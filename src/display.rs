@@ -0,0 +1,173 @@
+//! Size-limited, single-line rendering of values for logs and terminals
+
+use std::fmt;
+
+use crate::{text, Value};
+
+/// A value rendered on one line with long parts cut short, see [`Value::truncated`]
+///
+/// Scalars longer than [`max_chars`](Self::max_chars) characters, or
+/// spanning several lines, end in `…`. Characters are counted as displayed,
+/// so an accented letter or an emoji is never cut in half. Lists, blocks, and tables with more
+/// than [`max_items`](Self::max_items) entries are replaced by a count such
+/// as `[... 1,204 items]`; smaller ones are shown in full, with the same
+/// limits applied to each entry.
+#[derive(Debug, Clone, Copy)]
+pub struct Truncated<'a> {
+    value: &'a Value,
+    max_chars: usize,
+    max_items: usize,
+}
+
+impl<'a> Truncated<'a> {
+    /// Set the longest scalar shown in full (default 60 characters)
+    pub fn max_chars(mut self, max: usize) -> Self {
+        self.max_chars = max;
+        self
+    }
+
+    /// Set the largest list, block, or table shown in full (default 8 entries)
+    pub fn max_items(mut self, max: usize) -> Self {
+        self.max_items = max;
+        self
+    }
+
+    fn nested(&self, value: &'a Value) -> Self {
+        Self { value, ..*self }
+    }
+
+    fn write_items(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        items: &'a [Value],
+        noun: &str,
+    ) -> fmt::Result {
+        if items.len() > self.max_items {
+            return write!(f, "[... {} {}]", thousands(items.len()), noun);
+        }
+        f.write_str("[")?;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", self.nested(item))?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl Value {
+    /// Render on one line with long scalars and large collections cut short
+    ///
+    /// `format!("{}", value.truncated().max_items(3))` is safe to log even
+    /// for a list with a million entries.
+    pub fn truncated(&self) -> Truncated<'_> {
+        Truncated {
+            value: self,
+            max_chars: 60,
+            max_items: 8,
+        }
+    }
+}
+
+impl fmt::Display for Truncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Block(block) if block.len() > self.max_items => {
+                write!(f, "{{... {} keys}}", thousands(block.len()))
+            }
+            Value::Block(block) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in block.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, self.nested(value))?;
+                }
+                f.write_str("}")
+            }
+            Value::List(items) => self.write_items(f, items, "items"),
+            Value::Table { rows, .. } if rows.len() > self.max_items => {
+                write!(f, "table [... {} rows]", thousands(rows.len()))
+            }
+            Value::Table { columns, rows } => {
+                f.write_str("table ")?;
+                self.write_items(f, columns, "columns")?;
+                for row in rows {
+                    f.write_str(" ")?;
+                    self.write_items(f, row, "cells")?;
+                }
+                Ok(())
+            }
+            Value::Null => f.write_str("null"),
+            scalar => {
+                let text = scalar.scalar_text().unwrap_or_default();
+                let line = text.lines().next().unwrap_or_default();
+                match text::truncate(line, self.max_chars) {
+                    Some(short) => write!(f, "{}…", short),
+                    None if line.len() < text.len() => write!(f, "{}…", line),
+                    None => f.write_str(line),
+                }
+            }
+        }
+    }
+}
+
+/// Format a count with `,` between groups of three digits
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_truncate_scalars() {
+        let long = string(&"é".repeat(100));
+        assert_eq!(long.truncated().max_chars(3).to_string(), "ééé…");
+        let flags = string("\u{1f1e9}\u{1f1ea}\u{1f1eb}\u{1f1f7}");
+        assert_eq!(
+            flags.truncated().max_chars(1).to_string(),
+            "\u{1f1e9}\u{1f1ea}…"
+        );
+        assert_eq!(string("short").truncated().to_string(), "short");
+        assert_eq!(string("one\ntwo").truncated().to_string(), "one…");
+        assert_eq!(Value::Int(42).truncated().to_string(), "42");
+        assert_eq!(Value::Null.truncated().to_string(), "null");
+    }
+
+    #[test]
+    fn test_summarize_collections() {
+        let big = Value::List((0..1204).map(Value::Int).collect());
+        assert_eq!(big.truncated().to_string(), "[... 1,204 items]");
+
+        let doc = parse("server {\nhost a\nports [80, 443]\n}").unwrap();
+        let server = doc.get("server").unwrap();
+        assert_eq!(
+            server.truncated().to_string(),
+            "{host: a, ports: [80, 443]}"
+        );
+        assert_eq!(server.truncated().max_items(1).to_string(), "{... 2 keys}");
+    }
+
+    #[test]
+    fn test_thousands() {
+        assert_eq!(thousands(7), "7");
+        assert_eq!(thousands(1000), "1,000");
+        assert_eq!(thousands(1234567), "1,234,567");
+    }
+}
@@ -0,0 +1,173 @@
+//! Dotted top-level keys expanded into nested blocks, see
+//! [`Parser::dotted_keys`]
+
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer::Lexer;
+use crate::{Block, ErrorKind, Node, ParseError, Parser, Value};
+
+/// Keys collected into lists with [`DuplicateKeys::Collect`], by the path
+/// of the block holding them
+///
+/// [`DuplicateKeys::Collect`]: crate::DuplicateKeys::Collect
+pub(crate) type Collected = HashMap<String, HashSet<String>>;
+
+impl Parser {
+    /// Add `node`, whose key is a path such as `server.tls.cert`, to the
+    /// top-level `nodes` as the key `cert` in the block `tls` in the block
+    /// `server`, creating the blocks that do not exist yet
+    pub(crate) fn insert_dotted(
+        &self,
+        lines: &Lexer<'_, '_>,
+        nodes: &mut Vec<Node>,
+        mut node: Node,
+        line: &str,
+        collected: &mut Collected,
+    ) -> Result<(), ParseError> {
+        let path = std::mem::take(&mut node.key);
+        let written = line
+            .trim()
+            .split(char::is_whitespace)
+            .next()
+            .unwrap_or(line);
+        let segments: Vec<&str> = path.split('.').collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(lines.locate(
+                ParseError::new(
+                    ErrorKind::InvalidSyntax,
+                    format!("dotted key `{}` has an empty part", path),
+                ),
+                written,
+            ));
+        }
+        let (leaf, parents) = segments.split_last().expect("split yields a segment");
+        let not_block = |depth: usize, existing: &Node| {
+            let defined = match &existing.origin {
+                Some(origin) => format!(" (defined on line {})", origin.line),
+                None => String::new(),
+            };
+            let message = format!(
+                "cannot set `{}`: `{}` is not a block{}",
+                path,
+                parents[..=depth].join("."),
+                defined
+            );
+            lines.locate(ParseError::new(ErrorKind::DuplicateKey, message), written)
+        };
+        let implicit = |key: &str| Node {
+            origin: node.origin.clone(),
+            ..Node::new(key, Value::Block(Block::new()))
+        };
+
+        let i = match nodes.iter().rposition(|n| n.key == parents[0]) {
+            Some(i) if !matches!(nodes[i].value, Value::Block(_)) => {
+                return Err(not_block(0, &nodes[i]));
+            }
+            Some(i) => i,
+            None => {
+                nodes.push(implicit(parents[0]));
+                nodes.len() - 1
+            }
+        };
+        let Value::Block(block) = &mut nodes[i].value else {
+            unreachable!("checked above");
+        };
+        let mut block = block;
+        for (depth, parent) in parents.iter().enumerate().skip(1) {
+            match block.get_node(parent) {
+                Some(existing) if !matches!(existing.value, Value::Block(_)) => {
+                    return Err(not_block(depth, existing));
+                }
+                Some(_) => {}
+                None => {
+                    block.insert_node(implicit(parent));
+                }
+            }
+            let Some(Value::Block(inner)) = block.get_mut(parent) else {
+                unreachable!("checked above");
+            };
+            block = inner;
+        }
+
+        node.key = leaf.to_string();
+        // The source line reads as a top-level key, not one inside a block
+        node.trivia = None;
+        let collected = collected.entry(parents.join(".")).or_default();
+        self.insert_into(lines, block, node, written, collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string, DuplicateKeys};
+
+    fn parser() -> Parser {
+        Parser::new().dotted_keys(true)
+    }
+
+    #[test]
+    fn test_dotted_keys_nest() {
+        let input = "name app\nserver {\n  port!int 80\n}\nserver.tls.cert /etc/cert.pem\nserver.tls.key /etc/key.pem\nlog.level debug\n";
+        let doc = parser().parse_document(input).unwrap();
+        assert_eq!(
+            doc,
+            parse("name app\nserver {\n  port!int 80\n  tls {\n    cert /etc/cert.pem\n    key /etc/key.pem\n  }\n}\nlog {\n  level debug\n}\n")
+                .unwrap()
+        );
+        assert_eq!(doc.get_str("server.tls.key"), Some("/etc/key.pem"));
+        let log = doc.nodes[2].origin.as_ref().unwrap();
+        assert_eq!(log.line, 7);
+
+        // Off by default: the key keeps its dots
+        let doc = Parser::new().parse_document("log.level debug").unwrap();
+        assert_eq!(doc.nodes[0].key, "log.level");
+
+        // Repeated leaves follow the duplicate key setting
+        let input = "a.b 1\na.b 2\na.b 3\n";
+        let doc = parser().parse_document(input).unwrap();
+        assert_eq!(to_string(&doc), "a {\n  b 3\n}\n");
+        let doc = parser()
+            .duplicate_keys(DuplicateKeys::Collect)
+            .parse_document(input)
+            .unwrap();
+        assert_eq!(to_string(&doc), "a {\n  b [1, 2, 3]\n}\n");
+        let err = parser()
+            .duplicate_keys(DuplicateKeys::Error)
+            .parse_document(input)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DuplicateKey);
+        assert_eq!(err.line(), Some(2));
+
+        // Trivia of expanded lines is dropped rather than written back flat
+        let doc = parser()
+            .preserve_trivia(true)
+            .parse_document("# Server\nserver.port 80\n")
+            .unwrap();
+        assert_eq!(to_string(&doc), "server {\n  # Server\n  port 80\n}\n");
+    }
+
+    #[test]
+    fn test_dotted_key_conflicts() {
+        let err = parser()
+            .parse_document("server localhost\nserver.port!int 80\n")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DuplicateKey);
+        assert_eq!(
+            err.message,
+            "cannot set `server.port`: `server` is not a block (defined on line 1)"
+        );
+        let location = err.location.unwrap();
+        assert_eq!((location.line, location.column), (2, 1));
+
+        let err = parser().parse_document("a.b [1]\na.b.c 2\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "cannot set `a.b.c`: `a.b` is not a block (defined on line 1)"
+        );
+
+        let err = parser().parse_document("x\na..b 1\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(2));
+    }
+}
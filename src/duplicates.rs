@@ -0,0 +1,129 @@
+//! Detecting keys defined in more than one place
+
+use std::collections::BTreeMap;
+
+use crate::{Document, Node, Origin, Value};
+
+/// A key path with more than one definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    /// Exact key path, e.g. `server.port` or `servers[0].host`
+    pub path: String,
+    /// Every definition site in document order; `None` for nodes built in
+    /// code rather than parsed
+    pub origins: Vec<Option<Origin>>,
+}
+
+impl Duplicate {
+    /// Check whether the definitions come from more than one file
+    pub fn is_cross_file(&self) -> bool {
+        let mut files = self
+            .origins
+            .iter()
+            .map(|origin| origin.as_ref().and_then(|o| o.file.as_ref()));
+        let first = files.next().flatten();
+        files.any(|file| file != first)
+    }
+}
+
+impl Document {
+    /// Find every value path defined more than once, with all definition sites
+    ///
+    /// Meant for documents assembled from several files with
+    /// [`Parser::parse_files`](crate::Parser::parse_files) or
+    /// [`Parser::parse_dir`](crate::Parser::parse_dir), where a later file
+    /// silently shadowing an earlier one is usually a mistake. Blocks that
+    /// are repeated only to add keys are not reported; the keys inside them
+    /// are, if they clash. Results are sorted by path.
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut sites = BTreeMap::<String, Vec<Option<Origin>>>::new();
+        for node in &self.nodes {
+            collect(
+                &mut node.key.clone(),
+                node,
+                node.origin.as_ref(),
+                &mut sites,
+            );
+        }
+
+        sites
+            .into_iter()
+            .filter(|(_, origins)| origins.len() > 1)
+            .map(|(path, origins)| Duplicate { path, origins })
+            .collect()
+    }
+}
+
+fn collect(
+    path: &mut String,
+    node: &Node,
+    origin: Option<&Origin>,
+    sites: &mut BTreeMap<String, Vec<Option<Origin>>>,
+) {
+    let origin = node.origin.as_ref().or(origin);
+    match &node.value {
+        Value::Block(block) => {
+            for child in block.nodes() {
+                let len = path.len();
+                path.push('.');
+                path.push_str(&child.key);
+                collect(path, child, origin, sites);
+                path.truncate(len);
+            }
+        }
+        _ => sites.entry(path.clone()).or_default().push(origin.cloned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_duplicates_report_every_site() {
+        let doc =
+            parse("server {\nport 80\nhost a\n}\nname x\nserver {\nport 81\n}\nname y").unwrap();
+        let dups = doc.duplicates();
+
+        let paths: Vec<&str> = dups.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["name", "server.port"]);
+        let lines: Vec<usize> = dups[1]
+            .origins
+            .iter()
+            .map(|o| o.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, [2, 7]);
+        assert!(!dups[1].is_cross_file());
+    }
+
+    #[test]
+    fn test_duplicates_across_files() {
+        use std::path::Path;
+        use std::sync::Arc;
+
+        let mut doc = Document::new();
+        for (file, text) in [("base.up", "port 80"), ("local.up", "port 8080")] {
+            let mut part = parse(text).unwrap();
+            let file: Arc<Path> = Arc::from(Path::new(file));
+            part.nodes[0].origin.as_mut().unwrap().file = Some(file);
+            doc.nodes.extend(part.nodes);
+        }
+
+        let dups = doc.duplicates();
+        assert_eq!(dups.len(), 1);
+        assert!(dups[0].is_cross_file());
+        let sites: Vec<String> = dups[0]
+            .origins
+            .iter()
+            .map(|o| o.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(sites, ["base.up:1", "local.up:1"]);
+    }
+
+    #[test]
+    fn test_no_duplicates() {
+        let doc = parse("server {\nport 80\n}\nserver {\nhost a\n}").unwrap();
+        assert!(doc.duplicates().is_empty());
+    }
+}
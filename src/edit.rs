@@ -0,0 +1,428 @@
+//! Building documents in code and editing them by key path
+
+use crate::keypath::{self, Segment};
+use crate::{Block, Document, Node, Value};
+
+/// Builds a [`Document`], or the contents of a block, one key at a time
+///
+/// ```
+/// use uplang::{Document, Value};
+///
+/// let doc = Document::builder()
+///     .scalar("name", "app")
+///     .block("server", |b| b.value("port", 8080).value("tls", true))
+///     .list("tags", ["web", "api"])
+///     .build();
+/// assert_eq!(doc.get_int("server.port"), Some(8080));
+/// assert_eq!(uplang::to_string(&doc).lines().next(), Some("name app"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DocumentBuilder {
+    nodes: Vec<Node>,
+}
+
+impl DocumentBuilder {
+    /// Add a string value
+    pub fn scalar(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.node(Node::new(key, Value::String(value.into())))
+    }
+
+    /// Add any value, e.g. an `i64`, `bool`, or a [`Value`] built elsewhere
+    pub fn value(self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.node(Node::new(key, value.into()))
+    }
+
+    /// Add a value with a type annotation such as `secret` or `4`
+    pub fn annotated(
+        self,
+        key: impl Into<String>,
+        annotation: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.node(Node {
+            type_annotation: Some(annotation.into()),
+            ..Node::new(key, value.into())
+        })
+    }
+
+    /// Add a block whose contents are built by `build`
+    pub fn block(self, key: impl Into<String>, build: impl FnOnce(Self) -> Self) -> Self {
+        let block: Block = build(Self::default()).nodes.into_iter().collect();
+        self.value(key, block)
+    }
+
+    /// Add a list of values
+    pub fn list<I>(self, key: impl Into<String>, items: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        self.value(key, items.into_iter().map(Into::into).collect::<Vec<_>>())
+    }
+
+    /// Note what the node added last was generated from, such as
+    /// `CLI flag --port`, written as a `# from ...` comment above it
+    ///
+    /// ```
+    /// use uplang::Document;
+    ///
+    /// let doc = Document::builder()
+    ///     .value("port", 8080)
+    ///     .generated_from("CLI flag --port")
+    ///     .build();
+    /// assert_eq!(uplang::to_string(&doc), "# from CLI flag --port\nport!int 8080\n");
+    /// ```
+    pub fn generated_from(mut self, from: impl Into<String>) -> Self {
+        if let Some(node) = self.nodes.last_mut() {
+            node.generated_from = Some(from.into());
+        }
+        self
+    }
+
+    /// Add a prepared node
+    pub fn node(mut self, node: Node) -> Self {
+        self.nodes.push(node);
+        self
+    }
+
+    /// Finish the document
+    pub fn build(self) -> Document {
+        Document { nodes: self.nodes }
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::String(text)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::String(text.to_string())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Self {
+        Value::Int(n.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(x)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<Block> for Value {
+    fn from(block: Block) -> Self {
+        Value::Block(block)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl Document {
+    /// Start building a document in code, see [`DocumentBuilder`]
+    pub fn builder() -> DocumentBuilder {
+        DocumentBuilder::default()
+    }
+
+    /// Store `value` at a key path such as `server.tls.cert` or `servers[0].host`
+    ///
+    /// The value replaces whatever was there, along with its annotation.
+    /// Missing blocks along the path are created and a missing top-level key
+    /// is appended. Returns `false`, leaving the document unchanged, if the
+    /// path is malformed or runs through a scalar or a missing list index.
+    pub fn set_path(&mut self, path: &str, value: Value) -> bool {
+        let Some(segments) = keypath::parse(path) else {
+            return false;
+        };
+        let Some((Segment::Key(key), rest)) = segments.split_first() else {
+            return false;
+        };
+        match self.nodes.iter_mut().rev().find(|node| node.key == *key) {
+            Some(node) => set_in(node, rest, value),
+            None => match nested(rest, value) {
+                Some(value) => {
+                    self.nodes.push(Node::new(*key, value));
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Remove the value at a key path, returning it
+    ///
+    /// A repeated top-level key loses only its last (effective) definition.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        let segments = keypath::parse(path)?;
+        let (last, parent) = segments.split_last()?;
+        if parent.is_empty() {
+            let Segment::Key(key) = last else {
+                return None;
+            };
+            let i = self.nodes.iter().rposition(|node| node.key == *key)?;
+            return Some(self.nodes.remove(i).value);
+        }
+
+        match (self.lookup_mut(parent)?, last) {
+            (Value::Block(block), Segment::Key(key)) => block.remove(key),
+            (Value::List(items), Segment::Index(i)) if *i < items.len() => Some(items.remove(*i)),
+            _ => None,
+        }
+    }
+
+    /// Insert `node` right after the key at `path`, in the same block
+    ///
+    /// At the top level this adds another node; inside a block, an existing
+    /// entry with the same key as `node` is moved. Returns `false` if
+    /// nothing exists at `path` or it is a list item.
+    pub fn insert_after(&mut self, path: &str, node: Node) -> bool {
+        let Some(segments) = keypath::parse(path) else {
+            return false;
+        };
+        let Some((Segment::Key(key), parent)) = segments.split_last() else {
+            return false;
+        };
+        if parent.is_empty() {
+            let Some(i) = self.nodes.iter().rposition(|n| n.key == *key) else {
+                return false;
+            };
+            self.nodes.insert(i + 1, node);
+            return true;
+        }
+
+        let Some(Value::Block(block)) = self.lookup_mut(parent) else {
+            return false;
+        };
+        let Some(i) = block.position(key) else {
+            return false;
+        };
+        block.insert_node_at(i + 1, node);
+        true
+    }
+
+    /// Note what the node at `path` was generated from, as
+    /// [`DocumentBuilder::generated_from`] does, for documents built some
+    /// other way, such as serialized with `uplang::ser::to_document`
+    ///
+    /// Returns `false` if there is no node at `path`, or it is a list item.
+    ///
+    /// ```
+    /// use uplang::parse;
+    ///
+    /// let mut doc = parse("server {\n  port!int 8080\n}\n").unwrap();
+    /// assert!(doc.set_generated_from("server.port", "PORT environment variable"));
+    /// assert_eq!(
+    ///     uplang::to_string(&doc),
+    ///     "server {\n  # from PORT environment variable\n  port!int 8080\n}\n"
+    /// );
+    /// assert!(!doc.set_generated_from("server.host", "nowhere"));
+    /// ```
+    pub fn set_generated_from(&mut self, path: &str, from: impl Into<String>) -> bool {
+        match self.get_node_mut(path) {
+            Some(node) => {
+                node.generated_from = Some(from.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up the node at a key path that ends in a key, for modification,
+    /// see [`get_node`](Self::get_node)
+    pub fn get_node_mut(&mut self, path: &str) -> Option<&mut Node> {
+        let segments = keypath::parse(path)?;
+        let (Segment::Key(key), parent) = segments.split_last()? else {
+            return None;
+        };
+        if parent.is_empty() {
+            return self.nodes.iter_mut().rev().find(|node| node.key == *key);
+        }
+        match self.lookup_mut(parent)? {
+            Value::Block(block) => block.get_node_mut(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn lookup_mut(&mut self, path: &[Segment<'_>]) -> Option<&mut Value> {
+        let (Segment::Key(key), rest) = path.split_first()? else {
+            return None;
+        };
+        let node = self.nodes.iter_mut().rev().find(|node| node.key == *key)?;
+        keypath::lookup_mut(&mut node.value, rest)
+    }
+}
+
+/// Store `value` at `path` below `node`
+fn set_in(node: &mut Node, path: &[Segment<'_>], value: Value) -> bool {
+    if path.is_empty() {
+        node.value = value;
+        // The annotation described the value being replaced
+        node.type_annotation = None;
+        return true;
+    }
+    set_below(&mut node.value, path, value)
+}
+
+/// Store `value` at the non-empty `path` below `target`
+fn set_below(target: &mut Value, path: &[Segment<'_>], value: Value) -> bool {
+    match (target, path.split_first()) {
+        (Value::Block(block), Some((Segment::Key(key), rest))) => match block.get_node_mut(key) {
+            Some(child) => set_in(child, rest, value),
+            None => match nested(rest, value) {
+                Some(value) => {
+                    block.insert(*key, value);
+                    true
+                }
+                None => false,
+            },
+        },
+        (Value::List(items), Some((Segment::Index(i), rest))) => match items.get_mut(*i) {
+            Some(item) if rest.is_empty() => {
+                *item = value;
+                true
+            }
+            Some(item) => set_below(item, rest, value),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Wrap `value` in the blocks named by `path`; `None` if it has an index
+fn nested(path: &[Segment<'_>], value: Value) -> Option<Value> {
+    match path.split_first() {
+        None => Some(value),
+        Some((Segment::Key(key), rest)) => {
+            let mut block = Block::new();
+            block.insert(*key, nested(rest, value)?);
+            Some(Value::Block(block))
+        }
+        Some((Segment::Index(_), _)) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_builder() {
+        let doc = Document::builder()
+            .scalar("name", "app")
+            .annotated("token", "secret", "abc")
+            .block("server", |b| {
+                b.value("port", 8080)
+                    .block("tls", |b| b.scalar("cert", "/etc/cert.pem"))
+            })
+            .list("ports", [80i64, 443])
+            .build();
+        let expected = parse(
+            "name app\ntoken!secret abc\nserver {\nport!int 8080\ntls {\ncert /etc/cert.pem\n}\n}\nports [80, 443]",
+        )
+        .unwrap();
+        let written = parse(&crate::to_string(&doc)).unwrap();
+        assert_eq!(written.nodes[..3], expected.nodes[..3]);
+        assert_eq!(
+            doc.get("ports"),
+            Some(&Value::List(vec![Value::Int(80), Value::Int(443)]))
+        );
+    }
+
+    #[test]
+    fn test_generated_from() {
+        let doc = Document::builder()
+            .generated_from("nothing yet")
+            .block("server", |b| {
+                b.value("port", 8080)
+                    .generated_from("CLI flag --port")
+                    .scalar("host", "localhost")
+            })
+            .generated_from("defaults")
+            .build();
+        let written = crate::Writer::new().inline_width(80).to_string(&doc);
+        assert_eq!(
+            written,
+            "# from defaults\nserver {\n  # from CLI flag --port\n  port!int 8080\n  host localhost\n}\n"
+        );
+        // The comments read back as doc comments
+        let back = parse(&written).unwrap();
+        assert_eq!(back.get_int("server.port"), Some(8080));
+        assert_eq!(back.nodes[0].doc.as_deref(), Some("from defaults"));
+    }
+
+    #[test]
+    fn test_set_and_remove_paths() {
+        let mut doc = parse("server {\nport!int 80\n}\nservers [\n{\nhost a\n}\n]").unwrap();
+
+        assert!(doc.set_path("server.port", Value::String("8080".into())));
+        assert_eq!(doc.get_str("server.port"), Some("8080"));
+        assert!(doc.set_path("server.tls.cert", Value::String("c.pem".into())));
+        assert_eq!(doc.get_str("server.tls.cert"), Some("c.pem"));
+        assert!(doc.set_path("servers[0].host", Value::String("b".into())));
+        assert_eq!(doc.get_str("servers[0].host"), Some("b"));
+        assert!(doc.set_path("debug", Value::Bool(true)));
+        assert_eq!(doc.nodes.last().unwrap().key, "debug");
+
+        assert!(!doc.set_path("servers[3].host", Value::Null));
+        assert!(!doc.set_path("server.port.x", Value::Null));
+        assert!(!doc.set_path("new[0]", Value::Null));
+
+        let text = crate::to_string(&doc);
+        assert!(text.contains("server {\n  port 8080\n"));
+
+        assert_eq!(
+            doc.remove_path("server.tls"),
+            Some(Value::Block(
+                [("cert", Value::String("c.pem".into()))]
+                    .into_iter()
+                    .collect()
+            ))
+        );
+        assert_eq!(
+            doc.remove_path("servers[0].host"),
+            Some(Value::String("b".into()))
+        );
+        assert_eq!(doc.remove_path("debug"), Some(Value::Bool(true)));
+        assert_eq!(doc.remove_path("missing.key"), None);
+    }
+
+    #[test]
+    fn test_insert_after() {
+        let mut doc = parse("a 1\nserver {\nhost h\nport 80\n}\nb 2").unwrap();
+        assert!(doc.insert_after("a", Node::new("a2", Value::Int(2))));
+        assert!(doc.insert_after("server.host", Node::new("port", Value::Int(8080))));
+        assert!(doc.insert_after("server.port", Node::new("tls", Value::Bool(true))));
+        assert!(!doc.insert_after("server.missing", Node::new("x", Value::Null)));
+
+        let keys: Vec<&str> = doc.nodes.iter().map(|n| n.key.as_str()).collect();
+        assert_eq!(keys, ["a", "a2", "server", "b"]);
+        let Some(Value::Block(server)) = doc.get("server") else {
+            panic!("Expected block");
+        };
+        let keys: Vec<&str> = server.keys().collect();
+        assert_eq!(keys, ["host", "port", "tls"]);
+        assert_eq!(server.get("port"), Some(&Value::Int(8080)));
+    }
+}
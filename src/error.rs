@@ -0,0 +1,269 @@
+//! Parse errors with source locations
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// What went wrong while parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Invalid syntax
+    InvalidSyntax,
+    /// A block, list, or multiline string is still open at the end of input
+    UnexpectedEof,
+    /// A block, list, or multiline string is never closed, with
+    /// [`Parser::strict`](crate::Parser::strict)
+    UnclosedDelimiter,
+    /// Invalid list format
+    InvalidList,
+    /// Value does not match its type annotation
+    InvalidValue,
+    /// Scalar cannot be read as its `!int`, `!float`, `!bool`, or `!null` annotation
+    TypeMismatch,
+    /// Variables refer to each other in a loop and can never be resolved,
+    /// or a document includes itself
+    ReferenceCycle,
+    /// A `${...}` reference names neither a key nor an environment
+    /// variable, with [`Parser::with_interpolation`](crate::Parser::with_interpolation)
+    UndefinedReference,
+    /// A block defines the same key twice, with [`DuplicateKeys::Error`](crate::DuplicateKeys::Error)
+    DuplicateKey,
+    /// Reading the input failed
+    Io,
+    /// The input is not valid UTF-8, see [`Parser::parse_bytes`](crate::Parser::parse_bytes)
+    InvalidEncoding,
+    /// The document is too large or too deeply nested for the parser's
+    /// [`Limits`](crate::Limits)
+    LimitExceeded,
+}
+
+impl ErrorKind {
+    /// A stable name for the kind, such as `type-mismatch`, for tools that
+    /// read errors as data
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidSyntax => "invalid-syntax",
+            ErrorKind::UnexpectedEof => "unexpected-eof",
+            ErrorKind::UnclosedDelimiter => "unclosed-delimiter",
+            ErrorKind::InvalidList => "invalid-list",
+            ErrorKind::InvalidValue => "invalid-value",
+            ErrorKind::TypeMismatch => "type-mismatch",
+            ErrorKind::ReferenceCycle => "reference-cycle",
+            ErrorKind::UndefinedReference => "undefined-reference",
+            ErrorKind::DuplicateKey => "duplicate-key",
+            ErrorKind::Io => "io",
+            ErrorKind::InvalidEncoding => "invalid-encoding",
+            ErrorKind::LimitExceeded => "limit-exceeded",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::InvalidSyntax => "Invalid syntax",
+            ErrorKind::UnexpectedEof => "Unexpected end of input",
+            ErrorKind::UnclosedDelimiter => "Unclosed delimiter",
+            ErrorKind::InvalidList => "Invalid list",
+            ErrorKind::InvalidValue => "Invalid value",
+            ErrorKind::TypeMismatch => "Type mismatch",
+            ErrorKind::ReferenceCycle => "Reference cycle",
+            ErrorKind::UndefinedReference => "Undefined reference",
+            ErrorKind::DuplicateKey => "Duplicate key",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::InvalidEncoding => "Invalid encoding",
+            ErrorKind::LimitExceeded => "Limit exceeded",
+        })
+    }
+}
+
+/// Where in the input an error was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// Source file, if the document was loaded from one
+    pub file: Option<Arc<Path>>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column of the start of [`span`](Self::span), in characters
+    pub column: usize,
+    /// Byte range of the offending text within the whole input
+    pub span: Range<usize>,
+    /// The full line containing the error
+    pub snippet: String,
+}
+
+/// A parse failure, with its location when it came from parsing text
+///
+/// `Display` renders a caret diagnostic pointing at the offending text:
+///
+/// ```text
+/// Invalid value: invalid money value `nineteen USD`
+///  --> line 2, column 13
+///   |
+/// 2 | price!money nineteen USD
+///   |             ^^^^^^^^^^^^
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// What went wrong
+    pub kind: ErrorKind,
+    /// Details, without location information
+    pub message: String,
+    /// Where it went wrong; `None` for I/O errors and for values parsed
+    /// outside a document (e.g. `"#FF8800".parse::<Color>()`)
+    pub location: Option<Location>,
+}
+
+impl ParseError {
+    /// Create an error without a location
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    /// Line number of the error, if known
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// Point the error at `text`, a slice of `input`, unless it already has
+    /// a location from a more deeply nested line
+    pub(crate) fn locate(mut self, input: &str, text: &str) -> Self {
+        if self.location.is_none() {
+            self.location = Location::of(input, text);
+        }
+        self
+    }
+}
+
+impl Location {
+    /// Location of `text` within `input`, if it is a slice of it
+    pub(crate) fn of(input: &str, text: &str) -> Option<Self> {
+        let start = offset_in(input, text)?;
+        Self::at_line(input, text, crate::lines::line_number(input, start))
+    }
+
+    /// Location of `text` within `input`, on 1-based line `line`
+    pub(crate) fn at_line(input: &str, text: &str, line: usize) -> Option<Self> {
+        let start = offset_in(input, text)?;
+        let end = start + text.len();
+
+        let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+        let snippet = input[line_start..line_end].trim_end_matches('\r');
+
+        Some(Location {
+            file: None,
+            line,
+            column: input[line_start..start].chars().count() + 1,
+            span: start..end,
+            snippet: snippet.to_string(),
+        })
+    }
+}
+
+/// Byte offset of `text` within `input`, if it is a slice of it
+pub(crate) fn offset_in(input: &str, text: &str) -> Option<usize> {
+    let offset = (text.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+    (offset + text.len() <= input.len()).then_some(offset)
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.kind)?;
+        } else {
+            write!(f, "{}: {}", self.kind, self.message)?;
+        }
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+
+        match &location.file {
+            Some(file) => write!(
+                f,
+                "\n --> {}:{}:{}",
+                file.display(),
+                location.line,
+                location.column
+            )?,
+            None => write!(
+                f,
+                "\n --> line {}, column {}",
+                location.line, location.column
+            )?,
+        }
+
+        let gutter = location.line.to_string().len();
+        let snippet = &location.snippet;
+        let start = snippet
+            .char_indices()
+            .nth(location.column - 1)
+            .map_or(snippet.len(), |(i, _)| i);
+        let end = crate::text::floor_char_boundary(snippet, start + location.span.len());
+        // Wide characters take two columns, so count columns, not chars
+        let pad = crate::text::display_width(&snippet[..start]);
+        let carets = crate::text::display_width(&snippet[start..end]).max(1);
+        write!(
+            f,
+            "\n{:gutter$} |\n{} | {}\n{:gutter$} | {:pad$}{}",
+            "",
+            location.line,
+            location.snippet,
+            "",
+            "",
+            "^".repeat(carets),
+            gutter = gutter,
+            pad = pad
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_and_render() {
+        let input = "name app\nprice!money nineteen USD\n";
+        let value = &input[21..33];
+        assert_eq!(value, "nineteen USD");
+
+        let err = ParseError::new(ErrorKind::InvalidValue, "bad money").locate(input, value);
+        let location = err.location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (2, 13));
+        assert_eq!(location.span, 21..33);
+        assert_eq!(location.snippet, "price!money nineteen USD");
+        assert_eq!(
+            err.to_string(),
+            "Invalid value: bad money\n --> line 2, column 13\n  |\n2 | price!money nineteen USD\n  |             ^^^^^^^^^^^^"
+        );
+
+        // The innermost location wins
+        let outer = &input[..4];
+        assert_eq!(err.clone().locate(input, outer), err);
+
+        // Carets line up under wide characters
+        let input = "名前!int 東京都\n";
+        let err = ParseError::new(ErrorKind::InvalidValue, "bad int").locate(input, &input[11..20]);
+        assert_eq!(err.location.as_ref().unwrap().column, 8);
+        assert_eq!(
+            err.to_string(),
+            "Invalid value: bad int\n --> line 1, column 8\n  |\n1 | 名前!int 東京都\n  |          ^^^^^^"
+        );
+    }
+
+    #[test]
+    fn test_without_location() {
+        let err = ParseError::new(ErrorKind::Io, "config.up: not found");
+        assert_eq!(err.to_string(), "I/O error: config.up: not found");
+        assert_eq!(err.line(), None);
+        assert_eq!(err.clone().locate("other", "unrelated"), err);
+    }
+}
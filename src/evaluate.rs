@@ -0,0 +1,322 @@
+//! Parsing, resolving, and validating in one call, see [`evaluate`]
+
+use crate::policy::Severity;
+use crate::schema::{Schema, ViolationKind};
+use crate::{Document, ParseError, Parser, Stats, Value};
+
+/// What [`evaluate`] does with its input
+///
+/// ```
+/// use uplang::schema::Schema;
+/// use uplang::{EvalOptions, Parser};
+///
+/// let options = EvalOptions::new()
+///     .parser(Parser::new().with_interpolation(true))
+///     .schema(Schema::parse("name!string\nport!int\n").unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    parser: Parser,
+    schema: Option<Schema>,
+    resolve_vars: bool,
+}
+
+impl EvalOptions {
+    /// Parse with [`Parser::new`], resolve `!vars`, and validate nothing
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            schema: None,
+            resolve_vars: true,
+        }
+    }
+
+    /// Parse with `parser`, for its interpolation, includes, limits, and
+    /// the like
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Validate the document against `schema`
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Expand `${vars...}` references with [`Document::resolve_vars`]
+    /// (default on)
+    pub fn resolve_vars(mut self, resolve: bool) -> Self {
+        self.resolve_vars = resolve;
+        self
+    }
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The step of [`evaluate`] that reported an [`EvalDiagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Reading the text, recovering from errors
+    Parse,
+    /// Expanding `!vars` references
+    Resolve,
+    /// Checking the document against the schema
+    Validate,
+}
+
+impl Stage {
+    /// The stage's name, such as `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::Resolve => "resolve",
+            Stage::Validate => "validate",
+        }
+    }
+}
+
+/// One problem found by [`evaluate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalDiagnostic {
+    /// The step that found it
+    pub stage: Stage,
+    /// Whether the document is wrong, or only questionable
+    pub severity: Severity,
+    /// What is wrong, without the line
+    pub message: String,
+    /// Key path of the value concerned, if there is one
+    pub path: Option<String>,
+    /// 1-based line, if the problem has one
+    pub line: Option<usize>,
+}
+
+/// Sizes of an evaluated input and its document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvalMetrics {
+    /// Length of the input in bytes
+    pub bytes: usize,
+    /// Number of lines of the input
+    pub lines: usize,
+    /// Deepest nesting of blocks and lists in the document, 0 for a
+    /// document of scalars only
+    pub depth: usize,
+    /// Key and type statistics of the document
+    pub stats: Stats,
+}
+
+/// Everything [`evaluate`] learned about an input
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    /// The document, without the nodes that failed to parse
+    pub document: Document,
+    /// The problems found, parse errors first, in line order within each
+    /// stage
+    pub diagnostics: Vec<EvalDiagnostic>,
+    /// Sizes of the input and document
+    pub metrics: EvalMetrics,
+}
+
+impl Evaluation {
+    /// Whether no diagnostic is an error
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity != Severity::Error)
+    }
+
+    /// The evaluation as JSON, for a playground or editor to render
+    ///
+    /// An object with `ok`, `document` (the document's own JSON),
+    /// `diagnostics` (objects with `stage`, `severity`, `message`, `path`,
+    /// and `line`), and `metrics` (`bytes`, `lines`, `depth`, `values`).
+    pub fn to_json(&self) -> String {
+        let diagnostics = self.diagnostics.iter().map(|diagnostic| {
+            let block = Document::builder()
+                .value("stage", diagnostic.stage.name())
+                .value("severity", diagnostic.severity.as_str())
+                .value("message", diagnostic.message.as_str())
+                .value(
+                    "path",
+                    diagnostic.path.as_deref().map_or(Value::Null, Value::from),
+                )
+                .value(
+                    "line",
+                    diagnostic
+                        .line
+                        .map_or(Value::Null, |n| Value::Int(n as i64)),
+                )
+                .build();
+            Value::Block(block.nodes.into_iter().collect())
+        });
+        let metrics = &self.metrics;
+        let report = Document::builder()
+            .value("ok", self.is_ok())
+            .value("document", "")
+            .list("diagnostics", diagnostics)
+            .block("metrics", |metrics_block| {
+                metrics_block
+                    .value("bytes", metrics.bytes as i64)
+                    .value("lines", metrics.lines as i64)
+                    .value("depth", metrics.depth as i64)
+                    .value("values", metrics.stats.total() as i64)
+            })
+            .build();
+        // The document is spliced in as its own JSON, keeping its types
+        report.to_json().replacen(
+            "\"document\":\"\"",
+            &format!("\"document\":{}", self.document.to_json()),
+            1,
+        )
+    }
+}
+
+/// Parse `input`, resolve its variables, and validate it, collecting every
+/// problem instead of stopping at the first
+///
+/// This is the whole pipeline a playground or editor plugin runs on each
+/// change: the document is parsed with recovery, so a broken node leaves
+/// out only itself, then its `!vars` references are resolved, then it is
+/// checked against the schema of `options`, if any. An error while
+/// resolving leaves the references as written.
+///
+/// ```
+/// use uplang::schema::Schema;
+/// use uplang::{evaluate, EvalOptions, Stage};
+///
+/// let options = EvalOptions::new().schema(Schema::parse("name!string\nport!int\n").unwrap());
+/// let eval = evaluate("name app\nport!int eighty\n", &options);
+/// assert!(!eval.is_ok());
+/// assert_eq!(eval.document.get_str("name"), Some("app"));
+/// let found: Vec<(Stage, Option<usize>)> =
+///     eval.diagnostics.iter().map(|d| (d.stage, d.line)).collect();
+/// assert_eq!(found, [(Stage::Parse, Some(2)), (Stage::Validate, None)]);
+/// assert_eq!(eval.diagnostics[1].message, "`port` is required (int)");
+/// assert_eq!(eval.metrics.lines, 2);
+/// ```
+pub fn evaluate(input: &str, options: &EvalOptions) -> Evaluation {
+    let (mut document, parsed) = options.parser.parse_with_recovery(input);
+    let mut diagnostics: Vec<EvalDiagnostic> = parsed
+        .into_iter()
+        .map(|diagnostic| from_error(Stage::Parse, diagnostic.error))
+        .collect();
+
+    if options.resolve_vars {
+        if let Err(e) = document.resolve_vars() {
+            diagnostics.push(from_error(Stage::Resolve, e));
+        }
+    }
+
+    if let Some(schema) = &options.schema {
+        let mut violations = schema.validate(&document);
+        violations.sort_by_key(|violation| violation.line().unwrap_or(usize::MAX));
+        diagnostics.extend(violations.into_iter().map(|violation| {
+            let severity = match violation.kind {
+                ViolationKind::Deprecated => Severity::Warning,
+                _ => Severity::Error,
+            };
+            EvalDiagnostic {
+                stage: Stage::Validate,
+                severity,
+                line: violation.line(),
+                message: format!("`{}` {}", violation.path, violation.message),
+                path: Some(violation.path),
+            }
+        }));
+    }
+
+    let metrics = EvalMetrics {
+        bytes: input.len(),
+        lines: input.lines().count(),
+        depth: document
+            .nodes
+            .iter()
+            .map(|node| depth(&node.value))
+            .max()
+            .unwrap_or(0),
+        stats: document.stats(),
+    };
+    Evaluation {
+        document,
+        diagnostics,
+        metrics,
+    }
+}
+
+fn from_error(stage: Stage, error: ParseError) -> EvalDiagnostic {
+    EvalDiagnostic {
+        stage,
+        severity: Severity::Error,
+        line: error.line(),
+        message: error.message,
+        path: None,
+    }
+}
+
+/// Levels of blocks and lists in `value`
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Block(block) => 1 + block.values().map(depth).max().unwrap_or(0),
+        Value::List(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        Value::Table { .. } => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let input = "\
+vars!vars {
+  host db.internal
+}
+db {
+  url postgres://${vars.host}/app
+  pool {
+    size!int 5
+    hosts [a, b]
+  }
+}
+debug!bool maybe
+";
+        let schema = Schema::parse(
+            "db {\n  url!string\n  pool {\n    size!int\n    hosts!list\n  }\n}\nlevel!string\n",
+        )
+        .unwrap();
+        let eval = evaluate(input, &EvalOptions::new().schema(schema.clone()));
+        assert_eq!(
+            eval.document.get_str("db.url"),
+            Some("postgres://db.internal/app")
+        );
+        let found: Vec<(Stage, Option<usize>)> =
+            eval.diagnostics.iter().map(|d| (d.stage, d.line)).collect();
+        assert_eq!(found, [(Stage::Parse, Some(11)), (Stage::Validate, None)]);
+        assert_eq!(eval.metrics.depth, 3);
+        assert_eq!(eval.metrics.bytes, input.len());
+        let json = eval.to_json();
+        assert!(json.starts_with(
+            "{\"ok\":false,\"document\":{\"db\":{\"url\":\"postgres://db.internal/app\","
+        ));
+        assert!(json.contains("{\"stage\":\"parse\",\"severity\":\"error\""));
+        assert!(
+            json.ends_with("\"metrics\":{\"bytes\":136,\"lines\":11,\"depth\":3,\"values\":7}}")
+        );
+
+        // Unresolved references are reported and left as written
+        let eval = evaluate("vars!vars {\n}\nurl ${vars.host}\n", &EvalOptions::new());
+        assert_eq!(eval.diagnostics[0].stage, Stage::Resolve);
+        assert_eq!(eval.document.get_str("url"), Some("${vars.host}"));
+        let eval = evaluate(
+            "url ${vars.host}\n",
+            &EvalOptions::new().resolve_vars(false),
+        );
+        assert!(eval.is_ok());
+    }
+}
@@ -0,0 +1,752 @@
+//! Reading a document as a flat sequence of events, see [`Parser::events`]
+//!
+//! Events are read from the same [`Lexer`](crate::Lexer) tokens as the
+//! tree parser reads, by the same reader: a block or a list becomes its
+//! start event, the events of its contents, and its end event as its
+//! lines are read, without a [`Node`] being built for it. Values that are
+//! only known whole, such as strings, tables, and anchors, are read as the
+//! tree parser reads them and taken apart into events. The events of one
+//! top-level node are produced at a time, so memory use is bounded by the
+//! largest top-level node rather than the whole document.
+
+use std::io::BufRead;
+
+use crate::descent::Reader;
+use crate::inline::is_inline_block;
+use crate::interpolate::Scope;
+use crate::lexer::{Close, Token};
+use crate::source::Origin;
+use crate::stream::{ReadChunks, Streamed};
+use crate::{
+    dotted, keyed_item, quote, Block, Document, DuplicateKeys, ErrorKind, Node, ParseError, Parser,
+    Value,
+};
+
+/// One step through a document, see [`Parser::events`]
+///
+/// Each node is a [`NodeStart`](Self::NodeStart), the events of its value,
+/// and a [`NodeEnd`](Self::NodeEnd). A value is a
+/// [`ScalarValue`](Self::ScalarValue), or a block, list, or table whose
+/// contents come between its start and end events: nodes in a block, values
+/// in a list, and rows in a table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A node begins
+    NodeStart {
+        key: String,
+        type_annotation: Option<String>,
+        /// Where the node was defined
+        origin: Option<Origin>,
+    },
+    /// The node last started ends
+    NodeEnd,
+    /// A value that holds no other values, such as a string or an integer
+    ScalarValue(Value),
+    /// A block begins
+    BlockStart,
+    /// The block last started ends
+    BlockEnd,
+    /// A list begins
+    ListStart,
+    /// The list last started ends
+    ListEnd,
+    /// A table with these columns begins
+    TableStart { columns: Vec<Value> },
+    /// One row of the table
+    Row(Vec<Value>),
+    /// The table last started ends
+    TableEnd,
+}
+
+impl Parser {
+    /// Iterate over the events of a document as it is read, see [`Event`]
+    ///
+    /// Top-level nodes are read one at a time, as by
+    /// [`read_nodes`](Self::read_nodes), and the events of each are
+    /// produced once its last line is read, so at most one of them is held
+    /// in memory and a caller that stops early never reads the rest of the
+    /// input. Skipping part of a node saves walking it, not reading it.
+    /// Values are parsed as in [`parse_document`](Self::parse_document):
+    /// typed by their annotations, with variables and references expanded.
+    /// A block with a duplicate key has an event for each node, which
+    /// [`Document::from_events`] keeps as the parser's default
+    /// [`DuplicateKeys::LastWins`] does; under another policy, blocks are
+    /// read whole and the policy applied first. Iteration stops after the
+    /// first error, and the node it is in has no events.
+    ///
+    /// Looking for one key without building the document:
+    ///
+    /// ```
+    /// use uplang::{Event, Parser};
+    ///
+    /// let input = "name app\nport!int 8080\nusers [\n  alice\n  bob\n]\n";
+    /// let parser = Parser::new();
+    /// let mut events = parser.events(input.as_bytes());
+    /// let mut port = None;
+    /// while let Some(event) = events.next() {
+    ///     match event.unwrap() {
+    ///         Event::NodeStart { key, .. } if key == "port" => {
+    ///             if let Some(Ok(Event::ScalarValue(value))) = events.next() {
+    ///                 port = value.as_int();
+    ///             }
+    ///             break;
+    ///         }
+    ///         Event::NodeStart { .. } => events.skip_node(),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(port, Some(8080));
+    /// ```
+    pub fn events<R: BufRead>(&self, reader: R) -> EventReader<'_, R> {
+        EventReader {
+            events: ReadChunks::new(self, reader),
+        }
+    }
+}
+
+/// Iterator over the events of a stream, see [`Parser::events`]
+///
+/// Events are read from the lexer's tokens, one top-level node at a time.
+pub struct EventReader<'p, R> {
+    events: ReadChunks<'p, R, Event>,
+}
+
+impl<R: BufRead> EventReader<'_, R> {
+    /// Skip the rest of the node whose [`Event::NodeStart`] was returned
+    /// last, up to and including its [`Event::NodeEnd`]
+    pub fn skip_node(&mut self) {
+        let mut depth = 0;
+        while let Some(event) = self.events.pop() {
+            match event {
+                Event::NodeStart { .. } => depth += 1,
+                Event::NodeEnd if depth == 0 => return,
+                Event::NodeEnd => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<'_, R> {
+    type Item = Result<Event, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+}
+
+impl Streamed for Event {
+    fn read(
+        reader: &mut Reader<'_, '_>,
+        scope: &mut Scope<'_>,
+        items: &mut Vec<Self>,
+    ) -> Result<(), ParseError> {
+        reader.events(scope, items)
+    }
+
+    fn shift(&mut self, lines: usize, _bytes: usize) {
+        if let Event::NodeStart {
+            origin: Some(origin),
+            ..
+        } = self
+        {
+            if origin.file.is_none() {
+                origin.line += lines;
+            }
+        }
+    }
+}
+
+impl Reader<'_, '_> {
+    /// Read top-level nodes as events, defining `@let` variables in `scope`
+    ///
+    /// When an error stops reading, only the events of the nodes before it
+    /// are kept.
+    fn events(&mut self, scope: &mut Scope<'_>, events: &mut Vec<Event>) -> Result<(), ParseError> {
+        let parser = self.parser;
+        while let Some(next) = self.lines.next() {
+            let (line_num, line) = (next.number, next.text);
+            // Top-level lines are split with their indentation
+            let code = next.indented();
+            let start = events.len();
+            let read = match next.token {
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                Token::Let(definition) => parser.define(&self.lines, scope, definition),
+                Token::Include(path) => self.included(path, scope.depth(), events),
+                Token::Close(_) => self
+                    .check_stray(next.code)
+                    .and_then(|()| self.node_events(code, line_num, scope, events)),
+                // Quoted keys keep their dots
+                _ if parser.dotted_keys && !line.starts_with('"') && dotted_key(parser, code) => {
+                    self.dotted_events(code, line_num, scope, events)
+                }
+                _ => self.node_events(code, line_num, scope, events),
+            };
+            if let Err(e) = read {
+                events.truncate(start);
+                self.recover(line, line_num, e)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the node on `line` as events
+    ///
+    /// A block or a list is read an event at a time as its lines are
+    /// lexed. Other values, and anchors, references, and tables, which
+    /// need the whole value, are read as by the tree parser.
+    fn node_events(
+        &mut self,
+        line: &str,
+        line_num: usize,
+        scope: &mut Scope<'_>,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        let parser = self.parser;
+        let (key_part, val_part) = parser.split_key_value(line);
+        let streamed = match quote::split_key(key_part) {
+            Ok((key, annotation)) if matches!(val_part, "{" | "[") => match annotation {
+                Some("anchor" | "ref" | "table") => None,
+                _ => Some((key, annotation)),
+            },
+            _ => None,
+        };
+        let Some((key, annotation)) = streamed else {
+            let node = self.node(line, line_num, scope)?;
+            if node.type_annotation.as_deref() == Some("anchor") {
+                scope.define_anchor(&node.key, node.value.clone());
+            }
+            push_node(events, node);
+            return Ok(());
+        };
+
+        parser
+            .limits
+            .check_key(&key)
+            .map_err(|e| self.lines.locate(e, key_part))?;
+        events.push(Event::NodeStart {
+            key: key.into_owned(),
+            type_annotation: annotation.map(str::to_string),
+            origin: Some(Origin::line(line_num + 1)),
+        });
+        let read = match val_part {
+            "{" => self.block_events(scope, events),
+            _ => self.list_events(scope, events),
+        };
+        read.map_err(|e| self.lines.locate(e, val_part))?;
+        events.push(Event::NodeEnd);
+        Ok(())
+    }
+
+    /// Read the top-level node on `line`, whose key is a dotted path, as
+    /// the events of the nodes it expands to
+    fn dotted_events(
+        &mut self,
+        line: &str,
+        line_num: usize,
+        scope: &mut Scope<'_>,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        let node = self.node(line, line_num, scope)?;
+        let mut nodes = Vec::new();
+        let mut collected = dotted::Collected::new();
+        self.parser
+            .insert_dotted(&self.lines, &mut nodes, node, line, &mut collected)?;
+        for node in nodes {
+            push_node(events, node);
+        }
+        Ok(())
+    }
+
+    /// Read the nodes of an `@include`d file as events
+    fn included(
+        &mut self,
+        path: &str,
+        depth: usize,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        for node in self.parser.include(&self.lines, path, depth)? {
+            push_node(events, node);
+        }
+        Ok(())
+    }
+
+    /// Read a block up to its closing `}` as events
+    ///
+    /// Only with [`DuplicateKeys::LastWins`], the default, are its nodes
+    /// read as events as they come. Under other policies, which node a
+    /// duplicate key keeps is only known at the end of the block, so the
+    /// block is read whole.
+    fn block_events(
+        &mut self,
+        parent: &Scope<'_>,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        if !matches!(self.parser.duplicate_keys, DuplicateKeys::LastWins) {
+            let block = self.block(parent)?;
+            push_value(events, block);
+            return Ok(());
+        }
+        let strict = self.strict;
+        let read = self.block_contents(parent, events);
+        self.strict = strict;
+        read
+    }
+
+    fn block_contents(
+        &mut self,
+        parent: &Scope<'_>,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        let parser = self.parser;
+        let mut scope = Scope::child(parent);
+        parser.limits.check_depth(scope.depth())?;
+        events.push(Event::BlockStart);
+
+        while let Some(next) = self.lines.next() {
+            let (line_num, line, trimmed) = (next.number, next.text, next.code);
+            let start = events.len();
+            let read = match next.token {
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                Token::Close(Close::Block) => {
+                    events.push(Event::BlockEnd);
+                    return Ok(());
+                }
+                Token::Strictness(strict) => {
+                    self.strict = strict;
+                    continue;
+                }
+                Token::Let(definition) => parser.define(&self.lines, &mut scope, definition),
+                Token::Include(path) => self.included(path, scope.depth(), events),
+                Token::Close(_) => self
+                    .check_stray(trimmed)
+                    .and_then(|()| self.node_events(trimmed, line_num, &mut scope, events)),
+                _ => self.node_events(trimmed, line_num, &mut scope, events),
+            };
+            if let Err(e) = read {
+                events.truncate(start);
+                self.recover(line, line_num, e)?;
+            }
+        }
+
+        self.end_of_input(Value::Null, "block")?;
+        events.push(Event::BlockEnd);
+        Ok(())
+    }
+
+    /// Read a list up to its closing `]` as events
+    ///
+    /// Blocks and lists in it, and nodes holding one, are read as events;
+    /// other items are read whole.
+    fn list_events(
+        &mut self,
+        scope: &Scope<'_>,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ParseError> {
+        let parser = self.parser;
+        let mut scope = Scope::child(scope);
+        parser.limits.check_depth(scope.depth())?;
+        events.push(Event::ListStart);
+        let mut items = 0;
+
+        while let Some(next) = self.lines.next() {
+            let trimmed = next.code;
+            match next.token {
+                Token::Close(Close::List) => {
+                    events.push(Event::ListEnd);
+                    return Ok(());
+                }
+                // Skip empty lines and comments
+                Token::Blank | Token::Comment(_) => continue,
+                _ => {}
+            }
+            items += 1;
+            parser
+                .limits
+                .check_items(items, "items in a list")
+                .map_err(|e| self.lines.locate(e, trimmed))?;
+
+            let inline = is_inline_block(trimmed) || trimmed.ends_with(']');
+            if trimmed.starts_with('{') && !inline {
+                self.block_events(&scope, events)
+                    .map_err(|e| self.lines.locate(e, trimmed))?;
+            } else if trimmed == "[" {
+                self.list_events(&scope, events)
+                    .map_err(|e| self.lines.locate(e, trimmed))?;
+            } else if keyed_item(trimmed).is_some_and(|(_, value)| matches!(value, "{" | "[")) {
+                // A node of its own, read as a block holding just its key
+                events.push(Event::BlockStart);
+                self.node_events(trimmed, next.number, &mut scope, events)?;
+                events.push(Event::BlockEnd);
+            } else {
+                let item = self.item(next, &scope)?;
+                push_value(events, item);
+            }
+        }
+
+        self.end_of_input(Value::Null, "list")?;
+        events.push(Event::ListEnd);
+        Ok(())
+    }
+}
+
+/// Whether the key on `line` is a dotted path, to be expanded
+fn dotted_key(parser: &Parser, line: &str) -> bool {
+    parser.split_key_value(line).0.contains('.')
+}
+
+fn push_node(events: &mut Vec<Event>, node: Node) {
+    events.push(Event::NodeStart {
+        key: node.key,
+        type_annotation: node.type_annotation,
+        origin: node.origin,
+    });
+    push_value(events, node.value);
+    events.push(Event::NodeEnd);
+}
+
+fn push_value(events: &mut Vec<Event>, value: Value) {
+    match value {
+        Value::Block(block) => {
+            events.push(Event::BlockStart);
+            for node in block.into_nodes() {
+                push_node(events, node);
+            }
+            events.push(Event::BlockEnd);
+        }
+        Value::List(items) => {
+            events.push(Event::ListStart);
+            for item in items {
+                push_value(events, item);
+            }
+            events.push(Event::ListEnd);
+        }
+        Value::Table { columns, rows } => {
+            events.push(Event::TableStart { columns });
+            events.extend(rows.into_iter().map(Event::Row));
+            events.push(Event::TableEnd);
+        }
+        scalar => events.push(Event::ScalarValue(scalar)),
+    }
+}
+
+impl Document {
+    /// Build a document from events, such as those of [`Parser::events`]
+    /// after a streaming transform
+    ///
+    /// Events out of order, such as a value outside a node, are an
+    /// [`ErrorKind::InvalidSyntax`] error, events that stop inside a node an
+    /// [`ErrorKind::UnexpectedEof`] error, and an error among the events is
+    /// returned as it is.
+    ///
+    /// ```
+    /// use uplang::{Document, Event, Parser};
+    ///
+    /// // Leave out every node named `password`
+    /// let input = "user admin\npassword hunter2\ndb {\n  host localhost\n  password s3cret\n}\n";
+    /// let mut skipping = 0;
+    /// let parser = Parser::new();
+    /// let events = parser.events(input.as_bytes()).filter(|event| {
+    ///     match event {
+    ///         Ok(Event::NodeStart { key, .. }) if skipping > 0 || key == "password" => {
+    ///             skipping += 1
+    ///         }
+    ///         Ok(Event::NodeEnd) if skipping > 0 => skipping -= 1,
+    ///         _ if skipping > 0 => {}
+    ///         _ => return true,
+    ///     }
+    ///     false
+    /// });
+    /// let doc = Document::from_events(events).unwrap();
+    /// assert_eq!(
+    ///     uplang::to_string(&doc),
+    ///     "user admin\ndb {\n  host localhost\n}\n"
+    /// );
+    /// ```
+    pub fn from_events(
+        events: impl IntoIterator<Item = Result<Event, ParseError>>,
+    ) -> Result<Document, ParseError> {
+        let mut builder = Builder::default();
+        for event in events {
+            builder.event(event?)?;
+        }
+        builder.finish()
+    }
+}
+
+/// A value being built from events
+enum Open {
+    Node(Box<Node>),
+    Block(Block),
+    List(Vec<Value>),
+    Table {
+        columns: Vec<Value>,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+/// State of [`Document::from_events`]: the nodes built so far and the
+/// values not yet ended, innermost last
+#[derive(Default)]
+struct Builder {
+    nodes: Vec<Node>,
+    open: Vec<Open>,
+}
+
+impl Builder {
+    fn event(&mut self, event: Event) -> Result<(), ParseError> {
+        let unexpected = |event: &Event| {
+            ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("unexpected {:?} event", event),
+            )
+        };
+        match event {
+            Event::NodeStart {
+                key,
+                type_annotation,
+                origin,
+            } => match self.open.last() {
+                None | Some(Open::Block(_)) => self.open.push(Open::Node(Box::new(Node {
+                    type_annotation,
+                    origin,
+                    ..Node::new(key, Value::String(String::new()))
+                }))),
+                _ => {
+                    return Err(unexpected(&Event::NodeStart {
+                        key,
+                        type_annotation,
+                        origin,
+                    }))
+                }
+            },
+            Event::NodeEnd => match self.open.pop() {
+                Some(Open::Node(node)) => match self.open.last_mut() {
+                    None => self.nodes.push(*node),
+                    Some(Open::Block(block)) => {
+                        block.insert_node(*node);
+                    }
+                    Some(_) => return Err(unexpected(&Event::NodeEnd)),
+                },
+                _ => return Err(unexpected(&Event::NodeEnd)),
+            },
+            Event::ScalarValue(value) => return self.value(value, Event::ScalarValue),
+            Event::BlockStart => self.start(Open::Block(Block::new()), &event)?,
+            Event::ListStart => self.start(Open::List(Vec::new()), &event)?,
+            Event::TableStart { ref columns } => self.start(
+                Open::Table {
+                    columns: columns.clone(),
+                    rows: Vec::new(),
+                },
+                &event,
+            )?,
+            Event::Row(row) => match self.open.last_mut() {
+                Some(Open::Table { rows, .. }) => rows.push(row),
+                _ => return Err(unexpected(&Event::Row(row))),
+            },
+            Event::BlockEnd | Event::ListEnd | Event::TableEnd => {
+                let value = match (self.open.pop(), &event) {
+                    (Some(Open::Block(block)), Event::BlockEnd) => Value::Block(block),
+                    (Some(Open::List(items)), Event::ListEnd) => Value::List(items),
+                    (Some(Open::Table { columns, rows }), Event::TableEnd) => {
+                        Value::Table { columns, rows }
+                    }
+                    _ => return Err(unexpected(&event)),
+                };
+                self.value(value, |_| event.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a block, list, or table as the value of the innermost node or
+    /// list
+    fn start(&mut self, open: Open, event: &Event) -> Result<(), ParseError> {
+        match self.open.last() {
+            Some(Open::Node(_) | Open::List(_)) => {
+                self.open.push(open);
+                Ok(())
+            }
+            _ => Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("unexpected {:?} event", event),
+            )),
+        }
+    }
+
+    /// Give a finished value to the innermost node or list
+    fn value(
+        &mut self,
+        value: Value,
+        event: impl FnOnce(Value) -> Event,
+    ) -> Result<(), ParseError> {
+        match self.open.last_mut() {
+            Some(Open::Node(node)) => node.value = value,
+            Some(Open::List(items)) => items.push(value),
+            _ => {
+                return Err(ParseError::new(
+                    ErrorKind::InvalidSyntax,
+                    format!("unexpected {:?} event", event(value)),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Document, ParseError> {
+        match self.open.is_empty() {
+            true => Ok(Document { nodes: self.nodes }),
+            false => Err(ParseError::new(
+                ErrorKind::UnexpectedEof,
+                "events ended inside a node",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "\
+name app
+port!int 8080
+server {
+  hosts [
+    a
+    {
+      weight!int 2
+    }
+  ]
+}
+users!table {
+  columns [id, name]
+  rows {
+    [1, alice]
+  }
+}
+";
+
+    #[test]
+    fn test_events() {
+        let events: Vec<Event> = Parser::new()
+            .events(INPUT.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let kinds: Vec<String> = events
+            .iter()
+            .map(|event| match event {
+                Event::NodeStart { key, .. } => format!("<{}>", key),
+                Event::ScalarValue(value) => value.to_string(),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            kinds.join(" "),
+            "<name> app NodeEnd <port> 8080 NodeEnd \
+             <server> BlockStart <hosts> ListStart a BlockStart <weight> 2 NodeEnd BlockEnd ListEnd NodeEnd BlockEnd NodeEnd \
+             <users> TableStart { columns: [String(\"id\"), String(\"name\")] } Row([String(\"1\"), String(\"alice\")]) TableEnd NodeEnd"
+        );
+        let Event::NodeStart { origin, .. } = &events[3] else {
+            panic!("{:?}", events[3])
+        };
+        assert_eq!(origin.as_ref().map(|origin| origin.line), Some(2));
+
+        let doc = Document::from_events(events.into_iter().map(Ok)).unwrap();
+        assert_eq!(doc, crate::parse(INPUT).unwrap());
+    }
+
+    #[test]
+    fn test_events_match_the_tree() {
+        let inputs = [
+            INPUT,
+            "@let host example.com\nurl https://${host}\nlist [\n  [1, 2]\n  { a 1 }\n  [\n    x\n  ]\n  port!int 80\n  server {\n    host ${host}\n  }\n  \"q\"\n  ```\n  text\n  ```\n]\n",
+            "base!anchor {\n  a 1\n}\ncopy!ref base {\n  b 2\n}\nblock {\n  inner!anchor [\n    1\n  ]\n  again!ref inner\n}\n",
+            "doc!up ```\n  a {\n    b 1\n  }\n  ```\ndup {\n  a 1\n  b 2\n  a 3\n}\nx.y.z 1\n",
+            "a {\n  @strict\n  b 1\n}\n]\nc {\n  d [\n    e\n",
+        ];
+        let parsers = [
+            Parser::new(),
+            Parser::new().duplicate_keys(DuplicateKeys::Collect),
+            Parser::new().dotted_keys(true),
+        ];
+        for parser in &parsers {
+            for input in inputs {
+                let events = parser.events(input.as_bytes());
+                assert_eq!(
+                    Document::from_events(events).unwrap(),
+                    parser.parse_reader(input.as_bytes()).unwrap(),
+                    "{}",
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_of_a_bad_node() {
+        let input = "a 1\nb {\n  c 2\n  d!int x\n}\ne 3\n";
+        let parser = Parser::new();
+        let mut events = parser.events(input.as_bytes());
+        assert!(matches!(events.nth(2), Some(Ok(Event::NodeEnd))));
+        // None of the bad node's events come before its error
+        assert_eq!(events.next().unwrap().unwrap_err().line(), Some(4));
+        assert_eq!(events.next(), None);
+    }
+
+    #[test]
+    fn test_skip_node_stops_early() {
+        let input = "server {\n  port 1\n}\nname app\nport!int eighty\n";
+        let parser = Parser::new();
+        let mut events = parser.events(input.as_bytes());
+        assert!(matches!(
+            events.next(),
+            Some(Ok(Event::NodeStart { key, .. })) if key == "server"
+        ));
+        events.skip_node();
+        assert!(matches!(
+            events.next(),
+            Some(Ok(Event::NodeStart { key, .. })) if key == "name"
+        ));
+        // The invalid line after it is only read when asked for
+        assert_eq!(events.next(), Some(Ok(Event::ScalarValue("app".into()))));
+        assert_eq!(events.next(), Some(Ok(Event::NodeEnd)));
+        assert!(events.next().unwrap().is_err());
+        assert_eq!(events.next(), None);
+    }
+
+    #[test]
+    fn test_from_events_errors() {
+        for (events, message) in [
+            (
+                vec![Event::ScalarValue(Value::Int(1))],
+                "unexpected ScalarValue(Int(1)) event",
+            ),
+            (
+                vec![Event::NodeStart {
+                    key: "a".into(),
+                    type_annotation: None,
+                    origin: None,
+                }],
+                "events ended inside a node",
+            ),
+            (
+                vec![
+                    Event::NodeStart {
+                        key: "a".into(),
+                        type_annotation: None,
+                        origin: None,
+                    },
+                    Event::ListStart,
+                    Event::BlockEnd,
+                ],
+                "unexpected BlockEnd event",
+            ),
+        ] {
+            let err = Document::from_events(events.into_iter().map(Ok)).unwrap_err();
+            assert_eq!(err.message, message);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Following a growing log file, like `tail -f`
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Parser, Records};
+
+impl Parser {
+    /// Iterate over the records appended to a log file from now on
+    ///
+    /// Reading starts at the current end of the file and waits for more
+    /// data instead of stopping there, so the iterator never ends by
+    /// itself. Records are split as in [`read_records`](Self::read_records);
+    /// a record is produced once the blank line after it has been written.
+    /// Line numbers count from where following started. If the file is
+    /// truncated, as by log rotation with `copytruncate`, reading restarts
+    /// at its beginning.
+    pub fn follow(&self, path: impl AsRef<Path>) -> io::Result<Records<'_, BufReader<Tail>>> {
+        Ok(self.read_records(BufReader::new(Tail::open(path)?)))
+    }
+}
+
+/// A reader over a file that waits for the file to grow instead of
+/// reporting the end of it, see [`Parser::follow`]
+#[derive(Debug)]
+pub struct Tail {
+    file: File,
+    position: u64,
+    poll_interval: Duration,
+}
+
+impl Tail {
+    /// Open `path`, positioned at its current end
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let position = file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            file,
+            position,
+            poll_interval: Duration::from_millis(250),
+        })
+    }
+
+    /// Set how long to wait before checking the file for new data again
+    /// (default 250 ms)
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl Read for Tail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            if self.file.metadata()?.len() < self.position {
+                self.position = self.file.seek(SeekFrom::Start(0))?;
+                continue;
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_follow_appended_records() {
+        let path = std::env::temp_dir().join(format!("uplang-follow-{}.up", std::process::id()));
+        std::fs::write(&path, "event old\n\n").unwrap();
+
+        let parser = Parser::new();
+        let records = parser.follow(&path).unwrap();
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut file = OpenOptions::new().append(true).open(path).unwrap();
+                for chunk in ["event start\nat 1\n", "\nevent ", "stop\n\n"] {
+                    file.write_all(chunk.as_bytes()).unwrap();
+                    file.flush().unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                }
+            })
+        };
+
+        let events: Vec<String> = records
+            .take(2)
+            .map(|record| record.unwrap().get_str("event").unwrap().to_string())
+            .collect();
+        writer.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(events, ["start", "stop"]);
+    }
+}
@@ -0,0 +1,337 @@
+//! Canonical formatting of UP source text
+
+use std::collections::HashMap;
+
+use crate::quote;
+use crate::stream::Boundary;
+use crate::writer::write_inline_list;
+use crate::{
+    diff, keyed_item, let_definition, Document, ErrorKind, Node, ParseError, Parser, Value, Writer,
+};
+
+impl Writer {
+    /// Re-emit UP source in canonical style
+    ///
+    /// Unlike [`to_string`](Self::to_string), which writes out a parsed
+    /// document, this works on the text itself, so comments, blank lines,
+    /// `@let` definitions, and `${...}` references are kept as written.
+    /// Nesting is indented by [`indent`](Self::indent) spaces per level, a
+    /// key and its value are separated by one space, inline lists and table
+    /// rows are written as `[a, b]`, runs of blank lines become one, and
+    /// trailing whitespace is removed. Multiline string bodies are left
+    /// untouched.
+    ///
+    /// Fails if `input` does not parse. As a safeguard, also fails if the
+    /// result would parse to a different document, or would change if
+    /// formatted again, unless [`verify`](Self::verify) is off.
+    pub fn format(&self, input: &str) -> Result<String, ParseError> {
+        let original = Parser::new().parse_document(input)?;
+        let out = self.format_text(input);
+        if self.verify {
+            check_meaning(&original, &out)?;
+            if self.format_text(&out) != out {
+                return Err(ParseError::new(
+                    ErrorKind::InvalidSyntax,
+                    "formatting is not stable: the result would change if formatted again",
+                ));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Re-emit `input`, which parses, in canonical style
+    fn format_text(&self, input: &str) -> String {
+        let parser = Parser::new();
+        let mut out = String::new();
+        let mut boundary = Boundary::default();
+        let mut blank = false;
+        let mut opened = false;
+        for line in input.lines() {
+            let trimmed = line.trim();
+            let (before, fence, continuing, in_list) = (
+                boundary.depth(),
+                boundary.in_fence(),
+                boundary.continuing(),
+                boundary.in_list(),
+            );
+            boundary.line(line);
+            let after = boundary.depth();
+
+            if fence {
+                if trimmed == "```" {
+                    self.pad(&mut out, before);
+                    out.push_str("```\n");
+                } else {
+                    out.push_str(line.trim_end_matches('\r'));
+                    out.push('\n');
+                }
+                opened = false;
+                continue;
+            }
+            if continuing {
+                self.pad(&mut out, before + 1);
+                out.push_str(trimmed);
+                out.push('\n');
+                continue;
+            }
+            if trimmed.is_empty() {
+                // Dropped at the start of the file and of a block or list
+                blank = !out.is_empty() && !opened;
+                continue;
+            }
+
+            let closes = after < before;
+            if std::mem::take(&mut blank) && !closes {
+                out.push('\n');
+            }
+            self.pad(&mut out, before.min(after));
+            if trimmed.starts_with('#') || closes {
+                out.push_str(trimmed);
+            } else if let Some(definition) = let_definition(trimmed).filter(|_| !in_list) {
+                out.push_str("@let");
+                for part in split_first_word(definition) {
+                    out.push(' ');
+                    out.push_str(part);
+                }
+            } else if is_inline_list(trimmed) && (in_list || before > 0) {
+                // A list item or table row
+                push_list(&mut out, &parser, trimmed);
+            } else if in_list && keyed_item(trimmed).is_none() {
+                out.push_str(trimmed);
+            } else {
+                let mut parts = split_first_word(trimmed);
+                out.push_str(parts.next().unwrap_or_default());
+                if let Some(value) = parts.next() {
+                    out.push(' ');
+                    if is_inline_list(value) {
+                        push_list(&mut out, &parser, value);
+                    } else {
+                        out.push_str(value);
+                    }
+                }
+            }
+            out.push('\n');
+            opened = after > before;
+        }
+        out
+    }
+}
+
+impl Writer {
+    /// Write an edited version of a document, keeping the text of
+    /// `original` wherever the edit left it alone
+    ///
+    /// Nodes of `edited` are matched to those of `original` by key path
+    /// (by position for list items, and by occurrence for repeated
+    /// top-level keys). A node equal to its match is copied from `original`
+    /// byte for byte, with its comments; a changed or added node is written
+    /// in this writer's style. A diff against `original` then shows only
+    /// what was edited.
+    ///
+    /// Fails if `original` does not parse, or, unless
+    /// [`verify`](Self::verify) is off, if the result would not parse back
+    /// to `edited`.
+    ///
+    /// ```
+    /// use uplang::{Parser, Writer};
+    ///
+    /// let original = "name   app\n# Port\nport!int   80\n";
+    /// let edited = Parser::new()
+    ///     .parse_document("name app\n# Port\nport!int 8080")
+    ///     .unwrap();
+    /// let text = Writer::new().format_changed(original, &edited).unwrap();
+    /// assert_eq!(text, "name   app\n# Port\nport!int 8080\n");
+    /// ```
+    pub fn format_changed(&self, original: &str, edited: &Document) -> Result<String, ParseError> {
+        let parsed = Parser::new()
+            .preserve_trivia(true)
+            .parse_document(original)?;
+        let mut doc = edited.clone();
+        let before: Vec<&Node> = parsed.nodes.iter().collect();
+        carry_trivia(doc.nodes.iter_mut(), &before);
+        let out = self.clone().preserve_trivia(true).to_string(&doc);
+        if self.verify {
+            check_meaning(edited, &out)?;
+        }
+        Ok(out)
+    }
+}
+
+/// Check that formatted text parses to the document it was made from
+fn check_meaning(expected: &Document, text: &str) -> Result<(), ParseError> {
+    let error = |message: String| ParseError::new(ErrorKind::InvalidSyntax, message);
+    let parsed = Parser::new().parse_document(text).map_err(|e| {
+        error(format!(
+            "formatting would produce text that does not parse: {}",
+            e.message
+        ))
+    })?;
+    match diff(expected, &parsed).first() {
+        Some(change) => Err(error(format!(
+            "formatting would change the meaning of the document at `{}`",
+            change.path()
+        ))),
+        // Equal values, but keys or annotations that differ
+        None if parsed != *expected => Err(error(
+            "formatting would change the meaning of the document".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Give each of `nodes` the trivia of its match in `original`, or none
+fn carry_trivia<'n>(nodes: impl Iterator<Item = &'n mut Node>, original: &[&Node]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for node in nodes {
+        let count = seen.entry(node.key.clone()).or_default();
+        let before = original
+            .iter()
+            .filter(|before| before.key == node.key)
+            .nth(*count);
+        *count += 1;
+        // Trivia from any other text would be written in place of the node
+        node.trivia = before.and_then(|before| before.trivia.clone());
+        carry_trivia_in(&mut node.value, before.map(|before| &before.value));
+    }
+}
+
+fn carry_trivia_in(value: &mut Value, original: Option<&Value>) {
+    match value {
+        Value::Block(block) => {
+            let before: Vec<&Node> = match original {
+                Some(Value::Block(before)) => before.nodes().collect(),
+                _ => Vec::new(),
+            };
+            carry_trivia(block.nodes_mut(), &before);
+        }
+        Value::List(items) => {
+            let before = match original {
+                Some(Value::List(before)) => &before[..],
+                _ => &[],
+            };
+            for (i, item) in items.iter_mut().enumerate() {
+                carry_trivia_in(item, before.get(i));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Re-emit UP source in canonical style with the default [`Writer`]
+///
+/// ```
+/// let text = uplang::format("server   {\n      port!int    8080\n\n\n}\ntags [a,b]\n").unwrap();
+/// assert_eq!(text, "server {\n  port!int 8080\n}\ntags [a, b]\n");
+/// ```
+pub fn format(input: &str) -> Result<String, ParseError> {
+    Writer::new().format(input)
+}
+
+/// Split off the first word, which may be a quoted key, and the rest
+/// trimmed if there is any
+fn split_first_word(text: &str) -> impl Iterator<Item = &str> {
+    let start = quote::quoted_len(text).unwrap_or(0);
+    let (first, rest) = match text[start..].find(char::is_whitespace) {
+        Some(i) => (&text[..start + i], text[start + i..].trim()),
+        None => (text, ""),
+    };
+    std::iter::once(first).chain((!rest.is_empty()).then_some(rest))
+}
+
+fn is_inline_list(text: &str) -> bool {
+    text.len() >= 2 && text.starts_with('[') && text.ends_with(']')
+}
+
+/// Write an inline list with canonical spacing, or as it was if it does
+/// not parse or holds inline blocks
+fn push_list(out: &mut String, parser: &Parser, text: &str) {
+    match parser.parse_inline_list(text) {
+        // Inline blocks are kept as written
+        Ok(items) if !items.iter().any(|item| matches!(item, Value::Block(_))) => {
+            write_inline_list(out, &items)
+        }
+        _ => out.push_str(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_format() {
+        let input = "\n\n# App\nname    app\n@let   base   /srv\n\n\n\nserver    {\n\n        host!string localhost   \n  # Port\n  port!int 8080\n\n      }\n   \nlist [\n  a\n      {\n  k   v\n  }\n  [x,y]\n]\nusers!table {\ncolumns [id!int,name]\nrows {\n[1,   ann]\n}\n}\nscript ```\n  keep    this\n\n```\nlong a \\\n      b\npath ${base}/data\n\n";
+        let expected = "# App\nname app\n@let base /srv\n\nserver {\n  host!string localhost\n  # Port\n  port!int 8080\n}\n\nlist [\n  a\n  {\n    k v\n  }\n  [x, y]\n]\nusers!table {\n  columns [id!int, name]\n  rows {\n    [1, ann]\n  }\n}\nscript ```\n  keep    this\n\n```\nlong a \\\n  b\npath ${base}/data\n";
+        let formatted = format(input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert_eq!(
+            Writer::new().indent(4).format("a {\nb 1\n}").unwrap(),
+            "a {\n    b 1\n}\n"
+        );
+        assert_eq!(
+            format("\"two  spaces\"    \"  kept \"\n\"my block\"   {\n}").unwrap(),
+            "\"two  spaces\" \"  kept \"\n\"my block\" {\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_changed() {
+        let original = "# App\nname   app\n\nserver {\n    host    localhost\n    port!int 80\n}\nlist [\n  {\n     k   v\n  }\n]\ntags [a,b]\n# The end\n";
+        let edited = parse(
+            "# App\nname app\nserver {\n  host localhost\n  port!int 8080\n  tls!bool true\n}\nlist [\n  {\n    k v\n  }\n]\nextra 1\n",
+        )
+        .unwrap();
+        let writer = Writer::new();
+        assert_eq!(
+            writer.format_changed(original, &edited).unwrap(),
+            "# App\nname   app\n\nserver {\n    host    localhost\n  port!int 8080\n  tls!bool true\n}\nlist [\n  {\n     k   v\n  }\n]\nextra 1\n"
+        );
+
+        let unchanged = parse(original).unwrap();
+        assert_eq!(
+            writer.format_changed(original, &unchanged).unwrap(),
+            original
+        );
+        assert!(writer.format_changed("a {", &unchanged).is_ok());
+        assert!(writer.format_changed("a!int x", &unchanged).is_err());
+    }
+
+    #[test]
+    fn test_format_verifies() {
+        // References have no escape, so this text reads back as `2`
+        let mut edited = parse("name app\nscript x\n").unwrap();
+        edited.nodes[1].value = Value::String("${1 + 1}".to_string());
+        let err = Writer::new().format_changed("", &edited).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert!(
+            err.message.starts_with("formatting would"),
+            "{}",
+            err.message
+        );
+        // The escape hatch returns the text as written
+        let text = Writer::new()
+            .verify(false)
+            .format_changed("", &edited)
+            .unwrap();
+        assert!(text.contains("script ${1 + 1}\n"));
+
+        let expected = parse("name app\n").unwrap();
+        let err = check_meaning(&expected, "name other\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "formatting would change the meaning of the document at `name`"
+        );
+        assert!(check_meaning(&expected, "name!int 1\n").is_err());
+        assert!(check_meaning(&expected, "name {\n").is_err());
+        assert!(check_meaning(&expected, "name    app").is_ok());
+    }
+
+    #[test]
+    fn test_format_invalid() {
+        let err = format("port!int eighty").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(format("").unwrap(), "");
+    }
+}
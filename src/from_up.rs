@@ -0,0 +1,667 @@
+//! Mapping documents onto Rust types without serde
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{Block, Color, Date, Document, GeoPoint, MediaType, Money, Time, Timestamp, Value};
+
+/// A type that can be read from a UP value
+///
+/// Implemented for strings, numbers, `bool`, paths, the typed values of
+/// this crate, `Option`, `Vec`, and maps with string keys. With the
+/// `derive` feature, `#[derive(FromUp)]` implements it for structs with
+/// named fields, reading each field from the block key of the same name:
+///
+/// ```ignore
+/// #[derive(uplang::FromUp)]
+/// #[up(deny_unknown_fields)]
+/// struct Server {
+///     host: String,
+///     #[up(default)]
+///     port: u16,
+///     #[up(rename = "tls-cert")]
+///     cert: Option<PathBuf>,
+///     #[up(default = "default_workers")]
+///     workers: usize,
+///     // Must be present, but may be `!null`
+///     #[up(required)]
+///     proxy: Option<String>,
+/// }
+/// ```
+///
+/// Errors name the key path and line of the value that failed, e.g.
+/// `server.port (line 3): expected integer, found `eighty``. Reading stops
+/// at the first failure, except with [`from_document_all`], which reports
+/// every one.
+///
+/// [`from_document_all`]: FromUp::from_document_all
+pub trait FromUp: Sized {
+    /// Read a value
+    fn from_up(value: &Value) -> Result<Self, FromUpError>;
+
+    /// The value for a key that is not present, or `None` if the key is
+    /// required
+    ///
+    /// `Option<T>` returns `Some(None)`, so optional fields can be left out.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+
+    /// Read the top-level nodes of a document as a block
+    fn from_document(doc: &Document) -> Result<Self, FromUpError> {
+        Self::from_up(&Value::Block(doc.nodes.iter().cloned().collect()))
+    }
+
+    /// Read a value, going on past failures to report every one
+    ///
+    /// Derived structs read all their fields, and lists and maps all their
+    /// items, before failing. Other types report their one failure, as
+    /// [`from_up`](Self::from_up) does.
+    fn from_up_all(value: &Value) -> Result<Self, Vec<FromUpError>> {
+        Self::from_up(value).map_err(|e| vec![e])
+    }
+
+    /// Read the top-level nodes of a document as a block, reporting every
+    /// failure along with the part of the document that was read
+    ///
+    /// ```ignore
+    /// match Config::from_document_all(&doc) {
+    ///     Ok(config) => run(config),
+    ///     Err(errors) => {
+    ///         // Every mistake at once, one per line
+    ///         eprintln!("{}", errors);
+    ///     }
+    /// }
+    /// ```
+    fn from_document_all(doc: &Document) -> Result<Self, FromUpErrors> {
+        Self::from_up_all(&Value::Block(doc.nodes.iter().cloned().collect()))
+            .map_err(|errors| FromUpErrors::new(doc, errors))
+    }
+}
+
+/// Why a value could not be read by [`FromUp`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromUpError {
+    /// Key path of the value, e.g. `servers[0].port`; empty for the root
+    pub path: String,
+    /// Details, without the path
+    pub message: String,
+    /// Line of the innermost node on the path, when parsed from text
+    pub line: Option<usize>,
+}
+
+impl FromUpError {
+    /// Create an error for the value being read
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            path: String::new(),
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// Error for a value that is not of the `expected` kind
+    pub fn expected(expected: &str, found: &Value) -> Self {
+        let found = match found {
+            Value::String(text) => format!("`{}`", text),
+            other => other.type_name().to_string(),
+        };
+        Self::new(format!("expected {}, found {}", expected, found))
+    }
+
+    /// Put the error inside `key`, defined on `line`
+    pub fn in_key(mut self, key: &str, line: Option<usize>) -> Self {
+        self.path = match self.path.as_str() {
+            "" => key.to_string(),
+            path if path.starts_with('[') => format!("{}{}", key, path),
+            path => format!("{}.{}", key, path),
+        };
+        self.line = self.line.or(line);
+        self
+    }
+
+    pub(crate) fn in_index(mut self, index: usize) -> Self {
+        self.path = match self.path.as_str() {
+            "" => format!("[{}]", index),
+            path if path.starts_with('[') => format!("[{}]{}", index, path),
+            path => format!("[{}].{}", index, path),
+        };
+        self
+    }
+}
+
+impl fmt::Display for FromUpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.path.as_str(), self.line) {
+            ("", None) => {}
+            ("", Some(line)) => write!(f, "line {}: ", line)?,
+            (path, None) => write!(f, "{}: ", path)?,
+            (path, Some(line)) => write!(f, "{} (line {}): ", path, line)?,
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FromUpError {}
+
+/// Every failure of reading a document, see [`FromUp::from_document_all`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromUpErrors {
+    /// The failures, in document order, then the missing keys
+    pub errors: Vec<FromUpError>,
+    /// The document without the values that failed, which is everything
+    /// that was read
+    pub decoded: Document,
+}
+
+impl FromUpErrors {
+    fn new(doc: &Document, mut errors: Vec<FromUpError>) -> Self {
+        let mut decoded = doc.clone();
+        // Last first, so removing a list item leaves the indexes of the
+        // ones before it alone
+        for error in errors.iter().rev() {
+            if error.path.is_empty() {
+                decoded = Document::new();
+                break;
+            }
+            decoded.remove_path(&error.path);
+        }
+        // Missing keys have no line, and go last
+        errors.sort_by_key(|error| error.line.unwrap_or(usize::MAX));
+        Self { errors, decoded }
+    }
+}
+
+impl fmt::Display for FromUpErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FromUpErrors {}
+
+impl FromUp for Value {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromUp for Block {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Block(block) => Ok(block.clone()),
+            other => Err(FromUpError::expected("block", other)),
+        }
+    }
+}
+
+impl FromUp for String {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Null => Err(FromUpError::expected("string", value)),
+            other => other
+                .scalar_text()
+                .map(|text| text.into_owned())
+                .ok_or_else(|| FromUpError::expected("string", other)),
+        }
+    }
+}
+
+impl FromUp for bool {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            Value::String(text) if text == "true" => Ok(true),
+            Value::String(text) if text == "false" => Ok(false),
+            other => Err(FromUpError::expected("bool", other)),
+        }
+    }
+}
+
+macro_rules! from_up_int {
+    ($($ty:ty),*) => {$(
+        impl FromUp for $ty {
+            fn from_up(value: &Value) -> Result<Self, FromUpError> {
+                let n: i128 = match value {
+                    Value::Int(n) => i128::from(*n),
+                    Value::String(text) => text
+                        .parse()
+                        .map_err(|_| FromUpError::expected("integer", value))?,
+                    other => return Err(FromUpError::expected("integer", other)),
+                };
+                <$ty>::try_from(n).map_err(|_| {
+                    FromUpError::new(format!(
+                        "{} is out of range for {}",
+                        n,
+                        stringify!($ty)
+                    ))
+                })
+            }
+        }
+    )*};
+}
+
+from_up_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! from_up_float {
+    ($($ty:ty),*) => {$(
+        impl FromUp for $ty {
+            fn from_up(value: &Value) -> Result<Self, FromUpError> {
+                match value {
+                    Value::Float(x) => Ok(*x as $ty),
+                    Value::Int(n) => Ok(*n as $ty),
+                    Value::String(text) => text
+                        .parse()
+                        .map_err(|_| FromUpError::expected("number", value)),
+                    other => Err(FromUpError::expected("number", other)),
+                }
+            }
+        }
+    )*};
+}
+
+from_up_float!(f32, f64);
+
+macro_rules! from_up_typed {
+    ($($ty:ty => $variant:ident, $name:literal);* $(;)?) => {$(
+        impl FromUp for $ty {
+            fn from_up(value: &Value) -> Result<Self, FromUpError> {
+                match value {
+                    Value::$variant(typed) => Ok(typed.clone()),
+                    Value::String(text) => text
+                        .parse()
+                        .map_err(|e: crate::ParseError| FromUpError::new(e.message)),
+                    other => Err(FromUpError::expected($name, other)),
+                }
+            }
+        }
+    )*};
+}
+
+from_up_typed! {
+    Color => Color, "color";
+    Date => Date, "date";
+    GeoPoint => Geo, "geo";
+    MediaType => Mime, "mime";
+    Money => Money, "money";
+    Time => Time, "time";
+    Timestamp => Timestamp, "ts";
+}
+
+impl FromUp for Duration {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Duration(duration) => Ok(*duration),
+            Value::String(text) => {
+                crate::types::parse_duration(text).map_err(|e| FromUpError::new(e.message))
+            }
+            other => Err(FromUpError::expected("dur", other)),
+        }
+    }
+}
+
+impl FromUp for PathBuf {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Path(path) => Ok(path.clone()),
+            Value::String(text) => Ok(PathBuf::from(text)),
+            other => Err(FromUpError::expected("path", other)),
+        }
+    }
+}
+
+impl<T: FromUp> FromUp for Option<T> {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_up(other).map(Some),
+        }
+    }
+
+    fn from_up_all(value: &Value) -> Result<Self, Vec<FromUpError>> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_up_all(other).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromUp> FromUp for Vec<T> {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        match value {
+            Value::List(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| T::from_up(item).map_err(|e| e.in_index(i)))
+                .collect(),
+            other => Err(FromUpError::expected("list", other)),
+        }
+    }
+
+    fn from_up_all(value: &Value) -> Result<Self, Vec<FromUpError>> {
+        let Value::List(items) = value else {
+            return Err(vec![FromUpError::expected("list", value)]);
+        };
+        collect_all(items.iter().enumerate().map(|(i, item)| {
+            T::from_up_all(item)
+                .map_err(|errors| errors.into_iter().map(|e| e.in_index(i)).collect())
+        }))
+    }
+}
+
+impl<T: FromUp> FromUp for BTreeMap<String, T> {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        map_entries(value).map(|entries| entries.into_iter().collect())
+    }
+
+    fn from_up_all(value: &Value) -> Result<Self, Vec<FromUpError>> {
+        map_entries_all(value).map(|entries| entries.into_iter().collect())
+    }
+}
+
+impl<T: FromUp, S: BuildHasher + Default> FromUp for HashMap<String, T, S> {
+    fn from_up(value: &Value) -> Result<Self, FromUpError> {
+        map_entries(value).map(|entries| entries.into_iter().collect())
+    }
+
+    fn from_up_all(value: &Value) -> Result<Self, Vec<FromUpError>> {
+        map_entries_all(value).map(|entries| entries.into_iter().collect())
+    }
+}
+
+/// The entries of a block read as map entries
+fn map_entries<T: FromUp>(value: &Value) -> Result<Vec<(String, T)>, FromUpError> {
+    let Value::Block(block) = value else {
+        return Err(FromUpError::expected("block", value));
+    };
+    block
+        .nodes()
+        .map(|node| {
+            let line = node.origin.as_ref().map(|origin| origin.line);
+            T::from_up(&node.value)
+                .map(|value| (node.key.clone(), value))
+                .map_err(|e| e.in_key(&node.key, line))
+        })
+        .collect()
+}
+
+/// The entries of a block read as map entries, with every failure
+fn map_entries_all<T: FromUp>(value: &Value) -> Result<Vec<(String, T)>, Vec<FromUpError>> {
+    let Value::Block(block) = value else {
+        return Err(vec![FromUpError::expected("block", value)]);
+    };
+    collect_all(block.nodes().map(|node| {
+        let line = node.origin.as_ref().map(|origin| origin.line);
+        T::from_up_all(&node.value)
+            .map(|value| (node.key.clone(), value))
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|e| e.in_key(&node.key, line))
+                    .collect()
+            })
+    }))
+}
+
+/// The values of `results`, or the failures of all of them
+fn collect_all<T>(
+    results: impl Iterator<Item = Result<T, Vec<FromUpError>>>,
+) -> Result<Vec<T>, Vec<FromUpError>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(e) => errors.extend(e),
+        }
+    }
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
+}
+
+/// `TryFrom<&Value>` for the most common types, reading them as [`FromUp`]
+/// does
+macro_rules! try_from_value {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<&Value> for $ty {
+            type Error = FromUpError;
+
+            fn try_from(value: &Value) -> Result<Self, FromUpError> {
+                <$ty>::from_up(value)
+            }
+        }
+    )*};
+}
+
+try_from_value!(String, i64, f64, bool, Vec<String>);
+
+/// Helpers for code generated by `#[derive(FromUp)]`, `up_doc!`, and
+/// `include_up!`
+#[doc(hidden)]
+pub mod __private {
+    use super::{FromUp, FromUpError};
+    use crate::{suggest, types, Block, CustomValue, Node, Origin, Value};
+
+    /// An annotated node of `up_doc!`, its value read as the annotation
+    /// reads it
+    pub fn annotated(key: &str, annotation: &str, value: Value) -> Node {
+        let typed = value
+            .scalar_text()
+            .and_then(|text| types::coerce(annotation, &text));
+        let value = match typed {
+            Some(Ok(typed)) => typed,
+            Some(Err(err)) => panic!("`{}!{}`: {}", key, annotation, err),
+            None => value,
+        };
+        Node {
+            type_annotation: Some(annotation.to_string()),
+            ..Node::new(key, value)
+        }
+    }
+
+    /// A node of `include_up!`, as it was parsed at compile time
+    pub fn embedded_node(
+        key: &str,
+        annotation: Option<&str>,
+        value: Value,
+        doc: Option<&str>,
+        lang: Option<&str>,
+        line: usize,
+    ) -> Node {
+        Node {
+            type_annotation: annotation.map(str::to_string),
+            origin: (line > 0).then(|| Origin::line(line)),
+            doc: doc.map(str::to_string),
+            lang: lang.map(str::to_string),
+            ..Node::new(key, value)
+        }
+    }
+
+    /// A typed scalar of `include_up!`, read again from the text it was
+    /// written as at compile time
+    pub fn embedded_scalar(type_name: &str, text: &str) -> Value {
+        match types::coerce(type_name, text) {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => panic!("embedded `!{}` value `{}`: {}", type_name, text, err),
+            None => Value::String(text.to_string()),
+        }
+    }
+
+    /// A custom value of `include_up!`, such as a `!semver`, rebuilt if its
+    /// type is built in and otherwise kept as tagged text
+    pub fn embedded_custom(annotation: &str, text: &str) -> Value {
+        match types::coerce(annotation, text) {
+            Some(Ok(value @ Value::Custom(_))) => value,
+            _ => Value::Custom(CustomValue::tagged(annotation, text)),
+        }
+    }
+
+    pub fn block<'a>(value: &'a Value, name: &str) -> Result<&'a Block, FromUpError> {
+        match value {
+            Value::Block(block) => Ok(block),
+            other => Err(FromUpError::expected(&format!("a `{}` block", name), other)),
+        }
+    }
+
+    /// Read `key`, falling back to `missing` when it is not present
+    pub fn field<T: FromUp>(
+        block: &Block,
+        key: &str,
+        missing: impl FnOnce() -> Option<T>,
+    ) -> Result<T, FromUpError> {
+        match block.get_node(key) {
+            Some(node) => T::from_up(&node.value)
+                .map_err(|e| e.in_key(key, node.origin.as_ref().map(|origin| origin.line))),
+            None => {
+                missing().ok_or_else(|| FromUpError::new("missing required key").in_key(key, None))
+            }
+        }
+    }
+
+    /// Read `key` as [`field`] does, adding its failures to `errors`
+    pub fn field_all<T: FromUp>(
+        block: &Block,
+        key: &str,
+        missing: impl FnOnce() -> Option<T>,
+        errors: &mut Vec<FromUpError>,
+    ) -> Option<T> {
+        let read = match block.get_node(key) {
+            Some(node) => T::from_up_all(&node.value).map_err(|e| {
+                let line = node.origin.as_ref().map(|origin| origin.line);
+                e.into_iter().map(|e| e.in_key(key, line)).collect()
+            }),
+            None => missing()
+                .ok_or_else(|| vec![FromUpError::new("missing required key").in_key(key, None)]),
+        };
+        read.map_err(|e| errors.extend(e)).ok()
+    }
+
+    pub fn deny_unknown(block: &Block, known: &[&str]) -> Result<(), FromUpError> {
+        let mut errors = Vec::new();
+        deny_unknown_all(block, known, &mut errors);
+        errors.into_iter().next().map_or(Ok(()), Err)
+    }
+
+    /// Add a failure to `errors` for every key not in `known`
+    pub fn deny_unknown_all(block: &Block, known: &[&str], errors: &mut Vec<FromUpError>) {
+        for node in block
+            .nodes()
+            .filter(|node| !known.contains(&node.key.as_str()))
+        {
+            let message = match suggest::closest(&node.key, known.iter().copied()) {
+                Some(key) => format!("unknown key; did you mean `{}`?", key),
+                None => "unknown key".to_string(),
+            };
+            errors.push(
+                FromUpError::new(message)
+                    .in_key(&node.key, node.origin.as_ref().map(|origin| origin.line)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_from_up_values() {
+        let doc = parse("port!int 8080\nratio 0.5\ntags [a, b]\nlimits {\n  cpu 2\n  mem!int 512\n}\nproxy!null\ncolor!color #FF8800\nwait 1m\nday!date 2024-05-01\n").unwrap();
+        let block = Block::from_document(&doc).unwrap();
+        let get = |key| block.get(key).unwrap();
+
+        assert_eq!(u16::from_up(get("port")), Ok(8080));
+        assert_eq!(String::from_up(get("port")).unwrap(), "8080");
+        assert_eq!(f64::from_up(get("ratio")), Ok(0.5));
+        assert_eq!(Vec::<String>::from_up(get("tags")).unwrap(), ["a", "b"]);
+        let limits = BTreeMap::<String, u32>::from_up(get("limits")).unwrap();
+        assert_eq!(limits["cpu"] + limits["mem"], 514);
+        assert_eq!(Option::<String>::from_up(get("proxy")), Ok(None));
+        assert_eq!(
+            Color::from_up(get("color")).unwrap(),
+            "#FF8800".parse().unwrap()
+        );
+        assert_eq!(Duration::from_up(get("wait")), Ok(Duration::from_secs(60)));
+        assert_eq!(Date::from_up(get("day")).unwrap().to_string(), "2024-05-01");
+        assert!(Timestamp::from_up(get("day")).is_err());
+        assert_eq!(Option::<u8>::from_missing(), Some(None));
+        assert_eq!(u8::from_missing(), None);
+    }
+
+    #[test]
+    fn test_errors_name_the_path() {
+        let doc = parse("a {\n  b [\n    1\n    x\n  ]\n}\n").unwrap();
+        let err = BTreeMap::<String, BTreeMap<String, Vec<u8>>>::from_document(&doc).unwrap_err();
+        assert_eq!(err.path, "a.b[1]");
+        assert_eq!(err.line, Some(2));
+        assert_eq!(
+            err.to_string(),
+            "a.b[1] (line 2): expected integer, found `x`"
+        );
+
+        let err = u8::from_up(&Value::Int(300)).unwrap_err();
+        assert_eq!(err.to_string(), "300 is out of range for u8");
+    }
+
+    #[test]
+    fn test_every_error() {
+        let doc =
+            parse("a {\n  b [\n    1\n    x\n    y\n    3\n  ]\n  c [2]\n}\nd [z]\n").unwrap();
+        let errors =
+            BTreeMap::<String, BTreeMap<String, Vec<u8>>>::from_document_all(&doc).unwrap_err();
+        let paths: Vec<&str> = errors.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["a.b[1]", "a.b[2]", "d"]);
+        assert_eq!(errors.decoded.get_str("a.b[0]"), Some("1"));
+        assert_eq!(errors.decoded.get_str("a.b[1]"), Some("3"));
+        assert_eq!(errors.decoded.get_str("a.c[0]"), Some("2"));
+        assert!(errors.decoded.get("d").is_none());
+
+        let errors = Vec::<u8>::from_document_all(&doc).unwrap_err();
+        assert_eq!(errors.errors.len(), 1);
+        assert!(errors.decoded.is_empty());
+    }
+
+    #[test]
+    fn test_try_from_value() {
+        let doc = parse(
+            "port!int 8080
+ratio 0.5
+debug true
+tags [a, b]
+server {
+}
+",
+        )
+        .unwrap();
+        let get = |key| doc.get(key).unwrap();
+
+        assert_eq!(i64::try_from(get("port")), Ok(8080));
+        assert_eq!(f64::try_from(get("ratio")), Ok(0.5));
+        assert_eq!(bool::try_from(get("debug")), Ok(true));
+        assert_eq!(String::try_from(get("port")).unwrap(), "8080");
+        let tags: Vec<String> = get("tags").try_into().unwrap();
+        assert_eq!(tags, ["a", "b"]);
+
+        let err = bool::try_from(get("ratio")).unwrap_err();
+        assert_eq!(err.to_string(), "expected bool, found `0.5`");
+        let err = String::try_from(get("server")).unwrap_err();
+        assert_eq!(err.to_string(), "expected string, found block");
+    }
+}
@@ -0,0 +1,196 @@
+//! Node/edge view of a document's structure
+//!
+//! [`Document::to_graph`] turns the key tree into a plain graph that can be
+//! rendered as Graphviz DOT ([`Graph::to_dot`]) or, with the `petgraph`
+//! feature, handed to `petgraph` for analysis.
+
+use std::fmt::Write;
+
+use crate::{text, Document, Value};
+
+/// Identifier of a vertex within a [`Graph`] (its index in [`Graph::nodes`])
+pub type VertexId = usize;
+
+/// A vertex: the document root or a value at some key path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vertex {
+    /// Key path of the value; empty for the document root
+    pub path: String,
+    /// Short human-readable label
+    pub label: String,
+    /// Value type name, or `document` for the root
+    pub kind: &'static str,
+}
+
+/// Relationship between two vertices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// The target is nested inside the source block, list, or document
+    Contains,
+}
+
+/// A directed edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// Source vertex
+    pub from: VertexId,
+    /// Target vertex
+    pub to: VertexId,
+    /// What the edge represents
+    pub kind: EdgeKind,
+}
+
+/// Graph of a document's keys and their nesting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    /// Vertices; the root is always at index 0
+    pub nodes: Vec<Vertex>,
+    /// Edges between vertices
+    pub edges: Vec<Edge>,
+}
+
+const MAX_LABEL_VALUE: usize = 32;
+
+impl Graph {
+    fn add(&mut self, parent: Option<VertexId>, vertex: Vertex) -> VertexId {
+        let id = self.nodes.len();
+        self.nodes.push(vertex);
+        if let Some(from) = parent {
+            self.edges.push(Edge {
+                from,
+                to: id,
+                kind: EdgeKind::Contains,
+            });
+        }
+        id
+    }
+
+    fn add_value(&mut self, parent: VertexId, path: String, name: &str, value: &Value) {
+        let label = match value.scalar_text() {
+            Some(text) => match text::truncate(&text, MAX_LABEL_VALUE) {
+                Some(short) => format!("{} = {}…", name, short),
+                None => format!("{} = {}", name, text),
+            },
+            None => name.to_string(),
+        };
+        let id = self.add(
+            Some(parent),
+            Vertex {
+                path: path.clone(),
+                label,
+                kind: value.type_name(),
+            },
+        );
+
+        match value {
+            Value::Block(block) => {
+                for (key, child) in block {
+                    self.add_value(id, format!("{}.{}", path, key), key, child);
+                }
+            }
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let index = format!("[{}]", i);
+                    self.add_value(id, format!("{}{}", path, index), &index, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Render as a Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph up {\n    node [shape=box];\n");
+        for (id, vertex) in self.nodes.iter().enumerate() {
+            let shape = match vertex.kind {
+                "document" => "doubleoctagon",
+                "block" | "list" | "table" => "folder",
+                _ => "box",
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}\", shape={}];",
+                id,
+                escape(&vertex.label),
+                shape
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(dot, "    n{} -> n{};", edge.from, edge.to);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Convert into a `petgraph` directed graph
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::graph::DiGraph<Vertex, EdgeKind> {
+        let mut graph = petgraph::graph::DiGraph::with_capacity(self.nodes.len(), self.edges.len());
+        let indices: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|vertex| graph.add_node(vertex.clone()))
+            .collect();
+        for edge in &self.edges {
+            graph.add_edge(indices[edge.from], indices[edge.to], edge.kind);
+        }
+        graph
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Document {
+    /// Build a graph of this document's structure
+    pub fn to_graph(&self) -> Graph {
+        let mut graph = Graph::default();
+        let root = graph.add(
+            None,
+            Vertex {
+                path: String::new(),
+                label: "document".to_string(),
+                kind: "document",
+            },
+        );
+        for node in &self.nodes {
+            graph.add_value(root, node.key.clone(), &node.key, &node.value);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_graph_structure() {
+        let doc = parse("server {\nhost \"local\" box\n}\nports [80, 443]").unwrap();
+        let graph = doc.to_graph();
+
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.edges.len(), 5);
+        assert_eq!(graph.nodes[2].path, "server.host");
+        assert_eq!(graph.nodes[5].label, "[1] = 443");
+        assert!(graph.edges.iter().all(|e| e.kind == EdgeKind::Contains));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph up {"));
+        assert!(dot.contains(r#"n2 [label="host = \"local\" box", shape=box];"#));
+        assert!(dot.contains("n3 -> n5;"));
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn test_to_petgraph() {
+        let graph = parse("a {\nb c\n}").unwrap().to_graph().to_petgraph();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+    }
+}
@@ -0,0 +1,276 @@
+//! A shared, swappable handle to the current configuration
+
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{Document, ParseError, Parser};
+
+/// The latest good version of a document, shared between threads
+///
+/// Readers [`load`](Self::load) an `Arc` snapshot that stays valid and
+/// unchanged however long they hold it, while a background task replaces
+/// the document with [`reload`](Self::reload) or
+/// [`store`](Self::store). Cloning the handle shares it.
+///
+/// Loading is lock-free: readers never wait for each other or for a
+/// reload, which parses (and validates) the new document before swapping
+/// the pointer to it. Replacing the document waits only for loads already
+/// under way to finish taking their reference to the old one. A failed
+/// reload leaves the old document in place, and a thread that panicked
+/// while replacing it does not keep others from doing so.
+///
+/// ```
+/// use uplang::ConfigHandle;
+///
+/// let config = ConfigHandle::new(uplang::parse("port!int 80").unwrap());
+/// let reader = config.clone();
+///
+/// assert!(config.reload(|| uplang::parse("port!int eighty")).is_err());
+/// assert_eq!(reader.load().get("port"), Some(&uplang::Value::Int(80)));
+///
+/// config.reload(|| uplang::parse("port!int 8080")).unwrap();
+/// assert_eq!(reader.load().get("port"), Some(&uplang::Value::Int(8080)));
+/// assert_eq!(reader.generation(), 1);
+/// ```
+#[derive(Clone)]
+pub struct ConfigHandle {
+    inner: Arc<Shared>,
+}
+
+/// The current document as a pointer from [`Arc::into_raw`], which owns
+/// one strong reference to it
+///
+/// A load announces itself in the reader count for the current epoch
+/// before reading the pointer, and takes its own reference before leaving
+/// the count. A store swaps the pointer, moves on to the next epoch, and
+/// waits for the count of the previous one to drain before it releases
+/// the old document: every load that could still hold the old pointer is
+/// counted there, since loads that start later see the new epoch and the
+/// new pointer. Stores are serialized, so only two epochs are ever in use.
+struct Shared {
+    current: AtomicPtr<Document>,
+    epoch: AtomicUsize,
+    /// Loads under way, by the parity of the epoch they started in
+    readers: [AtomicUsize; 2],
+    /// Held by a store, for the whole swap
+    writer: Mutex<()>,
+    generation: AtomicU64,
+}
+
+// Snapshots are handed from the thread that stored them to the ones that
+// load them, which is sound only because documents are `Send + Sync`
+const _: fn() = || {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<Document>();
+};
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let current = *self.current.get_mut();
+        // SAFETY: the pointer came from `Arc::into_raw` and its reference is
+        // owned by `self`, which no load can be using any more
+        drop(unsafe { Arc::from_raw(current) });
+    }
+}
+
+impl fmt::Debug for ConfigHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigHandle")
+            .field("current", &self.load())
+            .field("generation", &self.generation())
+            .finish()
+    }
+}
+
+impl ConfigHandle {
+    /// Create a handle holding `doc`
+    pub fn new(doc: Document) -> Self {
+        Self {
+            inner: Arc::new(Shared {
+                current: AtomicPtr::new(Arc::into_raw(Arc::new(doc)).cast_mut()),
+                epoch: AtomicUsize::new(0),
+                readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+                writer: Mutex::new(()),
+                generation: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Parse a file into a new handle
+    pub fn from_file(parser: &Parser, path: impl AsRef<Path>) -> Result<Self, ParseError> {
+        parser.parse_file(path).map(Self::new)
+    }
+
+    /// Snapshot of the current document
+    ///
+    /// Never blocks and never fails, see [`ConfigHandle`].
+    pub fn load(&self) -> Arc<Document> {
+        let shared = &*self.inner;
+        loop {
+            let epoch = shared.epoch.load(Ordering::SeqCst);
+            let readers = &shared.readers[epoch % 2];
+            readers.fetch_add(1, Ordering::SeqCst);
+            // A store that moved on before this load was counted may not
+            // wait for it, so start again in the new epoch
+            if shared.epoch.load(Ordering::SeqCst) != epoch {
+                readers.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let current = shared.current.load(Ordering::SeqCst);
+            // SAFETY: the store that swaps `current` out waits for this
+            // epoch's count to drain before releasing its reference, so
+            // the document is alive until ours is taken
+            let doc = unsafe {
+                Arc::increment_strong_count(current);
+                Arc::from_raw(current)
+            };
+            readers.fetch_sub(1, Ordering::SeqCst);
+            return doc;
+        }
+    }
+
+    /// Replace the document, returning the previous one
+    pub fn store(&self, doc: Document) -> Arc<Document> {
+        let shared = &*self.inner;
+        // Nothing panics while the lock is held, but a poisoned lock would
+        // still serialize stores
+        let _writer = shared.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let doc = Arc::into_raw(Arc::new(doc)).cast_mut();
+        let previous = shared.current.swap(doc, Ordering::SeqCst);
+        let epoch = shared.epoch.fetch_add(1, Ordering::SeqCst);
+        while shared.readers[epoch % 2].load(Ordering::SeqCst) != 0 {
+            thread::yield_now();
+        }
+        shared.generation.fetch_add(1, Ordering::Release);
+        // SAFETY: the reference owned by `current` moves to the caller, and
+        // no load can still be reading the pointer without its own
+        unsafe { Arc::from_raw(previous) }
+    }
+
+    /// Replace the document with the one `load` produces, if it succeeds
+    ///
+    /// On error the current document is kept and the error returned, so a
+    /// broken edit to a watched file does not take down its readers.
+    pub fn reload<E>(&self, load: impl FnOnce() -> Result<Document, E>) -> Result<(), E> {
+        self.store(load()?);
+        Ok(())
+    }
+
+    /// Re-read the file at `path` with `parser`, see [`reload`](Self::reload)
+    pub fn reload_file(&self, parser: &Parser, path: impl AsRef<Path>) -> Result<(), ParseError> {
+        self.reload(|| parser.parse_file(path))
+    }
+
+    /// Number of times the document has been replaced
+    ///
+    /// Cheap to check, so a reader can tell whether a snapshot it derived
+    /// settings from is out of date.
+    pub fn generation(&self) -> u64 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Value};
+    use std::thread;
+
+    #[test]
+    fn test_readers_see_consistent_snapshots() {
+        let config = ConfigHandle::new(parse("a!int 0\nb!int 0").unwrap());
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let config = config.clone();
+                thread::spawn(move || {
+                    for _ in 0..200 {
+                        let doc = config.load();
+                        assert_eq!(doc.get("a"), doc.get("b"));
+                    }
+                })
+            })
+            .collect();
+        for i in 1..=50 {
+            let text = format!("a!int {i}\nb!int {i}");
+            config.reload(|| parse(&text)).unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(config.load().get("a"), Some(&Value::Int(50)));
+        assert_eq!(config.generation(), 50);
+    }
+
+    #[test]
+    fn test_concurrent_stores() {
+        let config = ConfigHandle::new(parse("a!int 0").unwrap());
+        let threads: Vec<_> = (0..6)
+            .map(|i| {
+                let config = config.clone();
+                thread::spawn(move || {
+                    for n in 0..500 {
+                        if i < 2 {
+                            config.store(parse(&format!("a!int {}", n)).unwrap());
+                        } else {
+                            let doc = config.load();
+                            assert!(matches!(doc.get("a"), Some(Value::Int(0..=499))));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(config.generation(), 1000);
+
+        // Replaced documents are released once their last snapshot is
+        let doc = config.load();
+        assert_eq!(Arc::strong_count(&doc), 2);
+        let previous = config.store(parse("a!int 1").unwrap());
+        assert!(Arc::ptr_eq(&doc, &previous));
+        drop(previous);
+        assert_eq!(Arc::strong_count(&doc), 1);
+    }
+
+    #[test]
+    fn test_poisoned_lock() {
+        let config = ConfigHandle::new(parse("a!int 1").unwrap());
+        let shared = config.clone();
+        let panicked = thread::spawn(move || {
+            let _guard = shared.inner.writer.lock().unwrap();
+            panic!("while holding the lock");
+        })
+        .join();
+        assert!(panicked.is_err());
+        assert!(config.inner.writer.is_poisoned());
+
+        assert_eq!(config.load().get("a"), Some(&Value::Int(1)));
+        config.reload(|| parse("a!int 2")).unwrap();
+        assert_eq!(config.load().get("a"), Some(&Value::Int(2)));
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_document() {
+        let path = std::env::temp_dir().join(format!("uplang-handle-{}.up", std::process::id()));
+        std::fs::write(&path, "port!int 80\n").unwrap();
+        let parser = Parser::new();
+        let config = ConfigHandle::from_file(&parser, &path).unwrap();
+        let before = config.load();
+
+        std::fs::write(&path, "port!int eighty\n").unwrap();
+        let err = config.reload_file(&parser, &path).unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        assert!(Arc::ptr_eq(&before, &config.load()));
+        assert_eq!(config.generation(), 0);
+
+        std::fs::write(&path, "port!int 8080\n").unwrap();
+        config.reload_file(&parser, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.load().get("port"), Some(&Value::Int(8080)));
+        assert_eq!(before.get("port"), Some(&Value::Int(80)));
+    }
+}
@@ -0,0 +1,140 @@
+//! Hashing documents by content, for caching
+
+use crate::{Document, Node, Value};
+
+impl Document {
+    /// A hash of the document's content that ignores how it was written
+    ///
+    /// Formatting, comments, `@let` variables that expand to the same text,
+    /// and the order of keys within a block do not change the hash; keys,
+    /// annotations, and values do. Documents that are equal (`==`) hash the
+    /// same. The hash is stable across runs and platforms, so build systems
+    /// can store it and skip work when a configuration change is only
+    /// cosmetic.
+    ///
+    /// ```
+    /// let a = uplang::parse("# Server\nserver {\n  host h\n  port!int 80\n}").unwrap();
+    /// let b = uplang::parse("server   {\n\n    port!int 80\n    host h\n}\n").unwrap();
+    /// let c = uplang::parse("server {\n  host h\n  port!int 81\n}").unwrap();
+    /// assert_eq!(a.semantic_hash(), b.semantic_hash());
+    /// assert_ne!(a.semantic_hash(), c.semantic_hash());
+    /// ```
+    pub fn semantic_hash(&self) -> u64 {
+        let mut hasher = Fnv::new();
+        hasher.count(self.nodes.len());
+        for node in &self.nodes {
+            hasher.node(node);
+        }
+        hasher.0
+    }
+}
+
+/// 64-bit FNV-1a hash of `bytes`
+pub(crate) fn fnv(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.bytes(bytes);
+    hasher.0
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hashers is specified
+/// and so gives the same result everywhere
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn count(&mut self, n: usize) {
+        self.bytes(&(n as u64).to_le_bytes());
+    }
+
+    /// Length-prefixed, so that `ab`, `c` and `a`, `bc` differ
+    fn str(&mut self, s: &str) {
+        self.count(s.len());
+        self.bytes(s.as_bytes());
+    }
+
+    fn node(&mut self, node: &Node) {
+        self.str(&node.key);
+        match &node.type_annotation {
+            Some(annotation) => {
+                self.bytes(&[1]);
+                self.str(annotation);
+            }
+            None => self.bytes(&[0]),
+        }
+        self.value(&node.value);
+    }
+
+    fn value(&mut self, value: &Value) {
+        self.str(value.type_name());
+        match value {
+            Value::Block(block) => {
+                // Blocks compare equal regardless of key order
+                let mut nodes: Vec<&Node> = block.nodes().collect();
+                nodes.sort_by(|a, b| a.key.cmp(&b.key));
+                self.count(nodes.len());
+                for node in nodes {
+                    self.node(node);
+                }
+            }
+            Value::List(items) => self.values(items),
+            Value::Table { columns, rows } => {
+                self.values(columns);
+                self.count(rows.len());
+                for row in rows {
+                    self.values(row);
+                }
+            }
+            Value::Float(x) => self.bytes(&x.to_bits().to_le_bytes()),
+            scalar => self.str(&scalar.scalar_text().unwrap_or_default()),
+        }
+    }
+
+    fn values(&mut self, values: &[Value]) {
+        self.count(values.len());
+        for value in values {
+            self.value(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    fn hash(input: &str) -> u64 {
+        parse(input).unwrap().semantic_hash()
+    }
+
+    #[test]
+    fn test_semantic_hash() {
+        let base = hash("name app\nserver {\n  host h\n  port!int 80\n}\ntags [a, b]\n");
+        let same = [
+            "# App\nname    app\n\n\nserver {\n    port!int 80\n    host h\n}\ntags [a,b]",
+            "@let h h\nname app\nserver {\n  host ${h}\n  port!int 080\n}\ntags [\n  a\n  b\n]\n",
+        ];
+        for input in same {
+            assert_eq!(hash(input), base, "{}", input);
+        }
+        let different = [
+            "name app\nserver {\n  host h\n  port 80\n}\ntags [a, b]\n",
+            "name app\nserver {\n  host h\n  port!int 80\n}\ntags [b, a]\n",
+            "server {\n  host h\n  port!int 80\n}\nname app\ntags [a, b]\n",
+            "name app\nserver {\n  host h\n  port!int 80\n}\ntags [ab]\n",
+        ];
+        for input in different {
+            assert_ne!(hash(input), base, "{}", input);
+        }
+        // Fixed, so it can be stored
+        assert_eq!(hash(""), 0xa8c7_f832_281a_39c5);
+    }
+}
@@ -0,0 +1,604 @@
+//! `@include` directives and how included documents are found
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::lexer::Lexer;
+use crate::source::set_file;
+use crate::{Document, ErrorKind, Node, Origin, ParseError, Parser, Value};
+
+/// A document found for an `@include` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Included {
+    /// Identifies the document: recorded as the file of its nodes' origins,
+    /// compared to detect include cycles, and passed back to the resolver
+    /// for includes inside it
+    pub path: PathBuf,
+    /// The document's text
+    pub text: String,
+}
+
+/// Finds the documents named by `@include` lines
+///
+/// Set on a parser with [`Parser::include_resolver`]. The crate provides
+/// [`FileResolver`] for the filesystem, [`MemoryResolver`] for documents
+/// held in memory (such as assets compiled into the binary), and
+/// [`DenyIncludes`].
+pub trait IncludeResolver: Send + Sync {
+    /// Find the document for `path` as written after `@include`, included
+    /// from the document identified by `from` (`None` at the top level)
+    fn resolve(&self, path: &str, from: Option<&Path>) -> Result<Included, ParseError>;
+}
+
+/// Reads included files from disk, relative to the including file
+///
+/// Includes in a document that was not read from a file are relative to
+/// the resolver's base directory.
+#[derive(Debug, Clone, Default)]
+pub struct FileResolver {
+    base: PathBuf,
+}
+
+impl FileResolver {
+    /// Resolve top-level includes relative to `base`
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+}
+
+impl IncludeResolver for FileResolver {
+    fn resolve(&self, path: &str, from: Option<&Path>) -> Result<Included, ParseError> {
+        let dir = match from.and_then(Path::parent) {
+            Some(dir) => dir,
+            None => &self.base,
+        };
+        let full = dir.join(path);
+        let io_error = |e: std::io::Error| {
+            ParseError::new(ErrorKind::Io, format!("{}: {}", full.display(), e))
+        };
+        let text = fs::read_to_string(&full).map_err(io_error)?;
+        // The same file reached by different paths must count as a cycle
+        let path = fs::canonicalize(&full).map_err(io_error)?;
+        Ok(Included { path, text })
+    }
+}
+
+/// Serves included documents from memory, by name
+///
+/// Names are matched exactly as written after `@include`.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    documents: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    /// Create a resolver with no documents
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a document under `name`
+    pub fn insert(mut self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        self.documents.insert(name.into(), text.into());
+        self
+    }
+}
+
+impl IncludeResolver for MemoryResolver {
+    fn resolve(&self, path: &str, _from: Option<&Path>) -> Result<Included, ParseError> {
+        match self.documents.get(path) {
+            Some(text) => Ok(Included {
+                path: PathBuf::from(path),
+                text: text.clone(),
+            }),
+            None => Err(ParseError::new(
+                ErrorKind::Io,
+                format!("no document named `{}`", path),
+            )),
+        }
+    }
+}
+
+/// Passes includes on to another resolver, noting the path of each
+/// document it finds
+struct Recording {
+    inner: Arc<dyn IncludeResolver>,
+    found: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl IncludeResolver for Recording {
+    fn resolve(&self, path: &str, from: Option<&Path>) -> Result<Included, ParseError> {
+        let included = self.inner.resolve(path, from)?;
+        if let Ok(mut found) = self.found.lock() {
+            found.push(included.path.clone());
+        }
+        Ok(included)
+    }
+}
+
+/// Rejects every `@include`, as a parser without a resolver does
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenyIncludes;
+
+impl IncludeResolver for DenyIncludes {
+    fn resolve(&self, path: &str, _from: Option<&Path>) -> Result<Included, ParseError> {
+        Err(ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("cannot include `{}`: includes are not allowed", path),
+        ))
+    }
+}
+
+/// The resolver set on a parser, and the documents being included
+#[derive(Clone, Default)]
+pub(crate) struct Includes {
+    resolver: Option<Arc<dyn IncludeResolver>>,
+    /// The including documents, outermost first
+    stack: Vec<PathBuf>,
+    /// Results for the `@include` lines of the document being parsed,
+    /// parsed ahead of time, see [`Parser::prefetch_includes`]
+    #[cfg(feature = "parallel")]
+    prefetched: Option<Arc<Prefetched>>,
+}
+
+/// Parsed includes by the address of their path in the including text,
+/// which tells apart lines that include the same path
+#[cfg(feature = "parallel")]
+type Prefetched = Mutex<HashMap<usize, (String, Result<Vec<Node>, ParseError>)>>;
+
+impl fmt::Debug for Includes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Includes")
+            .field("enabled", &self.resolver.is_some())
+            .field("stack", &self.stack)
+            .finish()
+    }
+}
+
+impl Includes {
+    pub(crate) fn set_resolver(&mut self, resolver: Arc<dyn IncludeResolver>) {
+        self.resolver = Some(resolver);
+    }
+
+    /// Add the path of every document the resolver finds to `found`
+    pub(crate) fn record(&mut self, found: Arc<Mutex<Vec<PathBuf>>>) {
+        if let Some(inner) = self.resolver.take() {
+            self.resolver = Some(Arc::new(Recording { inner, found }));
+        }
+    }
+
+    /// The file being parsed, if the document was read from one
+    pub(crate) fn current_file(&self) -> Option<&Path> {
+        self.stack.last().map(PathBuf::as_path)
+    }
+
+    /// Note that the document being parsed is the file at `path`
+    pub(crate) fn enter(&mut self, path: PathBuf) {
+        self.stack.push(path);
+        #[cfg(feature = "parallel")]
+        {
+            self.prefetched = None;
+        }
+    }
+
+    /// The prefetched result for the `@include` line naming `path`
+    #[cfg(feature = "parallel")]
+    fn take_prefetched(&self, path: &str) -> Option<Result<Vec<Node>, ParseError>> {
+        let mut prefetched = self.prefetched.as_ref()?.lock().ok()?;
+        let key = path.as_ptr() as usize;
+        match prefetched.remove(&key)? {
+            (written, result) if written == path => Some(result),
+            _ => None,
+        }
+    }
+}
+
+/// The path after `@include` on a directive line, if it is one, with the
+/// prefix of an `as` clause still attached
+pub(crate) fn include_directive(trimmed: &str) -> Option<&str> {
+    let rest = trimmed.strip_prefix("@include")?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// The path and prefix key of `path as prefix`
+fn split_prefix(directive: &str) -> (&str, Option<&str>) {
+    match directive.rsplit_once(" as ") {
+        Some((path, prefix)) => (path.trim_end(), Some(prefix.trim_start())),
+        None => (directive, None),
+    }
+}
+
+impl Parser {
+    /// Parse the document named by an `@include` line of `input`, nested
+    /// `depth` levels deep
+    ///
+    /// The included document's blocks and lists count from `depth` towards
+    /// [`Limits::max_depth`](crate::Limits::max_depth), and so does each
+    /// include within an include. With a prefix, as in
+    /// `@include common.up as common`, the document's nodes are mounted in
+    /// a block under that key, one level deeper.
+    pub(crate) fn include(
+        &self,
+        lines: &Lexer<'_, '_>,
+        directive: &str,
+        depth: usize,
+    ) -> Result<Vec<Node>, ParseError> {
+        #[cfg(feature = "parallel")]
+        if depth == 0 {
+            if let Some(result) = self.includes.take_prefetched(directive) {
+                return result;
+            }
+        }
+        let (path, prefix) = split_prefix(directive);
+        let located = |e: ParseError| lines.locate(e, directive);
+        if path.is_empty() {
+            return Err(located(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                "`@include` needs a path",
+            )));
+        }
+        if let Some(prefix) = prefix.filter(|prefix| prefix.contains(char::is_whitespace)) {
+            return Err(located(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("`{}` cannot be a prefix: it is not a key", prefix),
+            )));
+        }
+        let depth = depth + usize::from(prefix.is_some());
+        let resolver = match &self.includes.resolver {
+            Some(resolver) => resolver,
+            None => &(Arc::new(DenyIncludes) as Arc<dyn IncludeResolver>),
+        };
+        let stack = &self.includes.stack;
+        self.limits.check_depth(stack.len() + 1).map_err(located)?;
+        let included = resolver
+            .resolve(path, self.includes.current_file())
+            .map_err(located)?;
+
+        if let Some(start) = stack.iter().position(|p| *p == included.path) {
+            let chain: Vec<String> = stack[start..]
+                .iter()
+                .chain([&included.path])
+                .map(|p| format!("`{}`", p.display()))
+                .collect();
+            return Err(located(ParseError::new(
+                ErrorKind::ReferenceCycle,
+                format!("include cycle: {}", chain.join(" -> ")),
+            )));
+        }
+
+        self.limits
+            .check_size(included.text.len())
+            .map_err(located)?;
+
+        let mut parser = self.clone();
+        parser.includes.enter(included.path.clone());
+        let parser = parser.prefetch_includes(&included.text);
+        let file: Arc<Path> = Arc::from(included.path.as_path());
+        let mut nodes = Vec::new();
+        parser
+            .parse_nodes(
+                &included.text,
+                &mut nodes,
+                &mut parser.root_scope().at_depth(depth),
+            )
+            .map_err(|mut e| {
+                match &mut e.location {
+                    Some(location) => {
+                        location.file.get_or_insert_with(|| Arc::clone(&file));
+                    }
+                    None => e.message = format!("{}: {}", included.path.display(), e.message),
+                }
+                e
+            })?;
+        for node in &mut nodes {
+            set_file(node, &file);
+        }
+        let Some(prefix) = prefix else {
+            return Ok(nodes);
+        };
+        let mut mount = Node::new(prefix, Value::Block(nodes.into_iter().collect()));
+        mount.origin = lines.line_of(directive).map(Origin::line);
+        Ok(vec![mount])
+    }
+
+    /// A parser holding the documents for the top-level `@include` lines
+    /// of `input`, each resolved and parsed on a worker thread
+    ///
+    /// Only unindented lines are taken to be at the top level. Documents
+    /// included inside a block nest deeper, so they are parsed as they are
+    /// reached, counting from the block's depth.
+    ///
+    /// Parsing `input` then splices each result in at its line, as without
+    /// prefetching, so the order of nodes and which error is reported do
+    /// not depend on how the threads ran.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn prefetch_includes(&self, input: &str) -> Cow<'_, Parser> {
+        let paths: Vec<&str> = match &self.includes.resolver {
+            Some(_) => input
+                .lines()
+                .filter_map(|line| include_directive(line.trim_end()))
+                .filter(|path| !path.is_empty())
+                .collect(),
+            None => Vec::new(),
+        };
+        if paths.len() < 2 {
+            return Cow::Borrowed(self);
+        }
+
+        let mut parser = self.clone();
+        parser.includes.prefetched = None;
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(paths.len());
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let prefetched = Mutex::new(HashMap::new());
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    let Some(&path) = paths.get(i) else {
+                        break;
+                    };
+                    let result = parser.include(&Lexer::new(input, &parser.comments), path, 0);
+                    if let Ok(mut prefetched) = prefetched.lock() {
+                        prefetched.insert(path.as_ptr() as usize, (path.to_string(), result));
+                    }
+                });
+            }
+        });
+        parser.includes.prefetched = Some(Arc::new(prefetched));
+        Cow::Owned(parser)
+    }
+
+    /// Without the `parallel` feature, includes are parsed as they are
+    /// reached
+    #[cfg(not(feature = "parallel"))]
+    pub(crate) fn prefetch_includes(&self, _input: &str) -> Cow<'_, Parser> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Document {
+    /// A copy of the document that stands alone, for distributing a
+    /// configuration assembled from `@include`d files as a single file
+    ///
+    /// Parsing already splices included documents in, and each node's
+    /// origin keeps the file and line it was defined at. So that the written
+    /// copy keeps them too, each node from a file other than that of the
+    /// node before it (or, for the first node of a block, of the block)
+    /// gets a `from <file>:<line>` line at the end of its doc comment.
+    ///
+    /// ```
+    /// use uplang::{MemoryResolver, Parser};
+    ///
+    /// let assets = MemoryResolver::new().insert("db.up", "host db\nport 5432\n");
+    /// let doc = Parser::new()
+    ///     .include_resolver(assets)
+    ///     .parse_document("name app\n@include db.up\n")
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     uplang::to_string(&doc.inline_includes()),
+    ///     "name app\n# from db.up:1\nhost db\nport 5432\n"
+    /// );
+    /// ```
+    pub fn inline_includes(&self) -> Document {
+        let mut doc = self.clone();
+        mark_files(doc.nodes.iter_mut(), None);
+        doc
+    }
+}
+
+/// Note the origin of each node in `nodes` whose file differs from the one
+/// before it, starting from `file`
+fn mark_files<'n>(nodes: impl Iterator<Item = &'n mut Node>, mut file: Option<Arc<Path>>) {
+    for node in nodes {
+        let Some(origin) = node.origin.clone() else {
+            continue;
+        };
+        if origin.file.is_some() && origin.file != file {
+            let note = format!("from {}", origin);
+            node.doc = Some(match node.doc.take() {
+                Some(doc) => format!("{}\n{}", doc, note),
+                None => note,
+            });
+            // The source text would be written in place of the new comment
+            node.trivia = None;
+        }
+        mark_files_in(&mut node.value, &origin.file);
+        file = origin.file;
+    }
+}
+
+fn mark_files_in(value: &mut Value, file: &Option<Arc<Path>>) {
+    match value {
+        Value::Block(block) => mark_files(block.nodes_mut(), file.clone()),
+        Value::List(items) => items.iter_mut().for_each(|item| mark_files_in(item, file)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string};
+
+    fn parser(resolver: impl IncludeResolver + 'static) -> Parser {
+        Parser::new().include_resolver(resolver)
+    }
+
+    #[test]
+    fn test_include_splices_nodes() {
+        let assets = MemoryResolver::new()
+            .insert("db.up", "@let port 5432\nhost db\nport!int ${port}\n")
+            .insert("tags.up", "@include db.up\ntags [a]\n");
+        let doc = parser(assets)
+            .parse_document("name app\n@include db.up\nserver {\n  @include tags.up\n}\n")
+            .unwrap();
+        assert_eq!(
+            doc,
+            parse("name app\nhost db\nport!int 5432\nserver {\n  host db\n  port!int 5432\n  tags [a]\n}\n")
+                .unwrap()
+        );
+
+        let origin = doc.nodes[2].origin.as_ref().unwrap();
+        assert_eq!(origin.to_string(), "db.up:3");
+        let Some(Value::Block(server)) = doc.get("server") else {
+            panic!("expected block");
+        };
+        let origin = server.get_node("tags").unwrap().origin.as_ref().unwrap();
+        assert_eq!(origin.to_string(), "tags.up:2");
+    }
+
+    #[test]
+    fn test_include_with_prefix() {
+        let assets = MemoryResolver::new()
+            .insert("db.up", "host db\nport!int 5432\n")
+            .insert("cache.up", "host cache\nport!int 6379\n");
+        let parser = parser(assets);
+        let input = "host app\n@include db.up as db\nservices {\n  @include cache.up as cache\n}\n";
+        let doc = parser.parse_document(input).unwrap();
+        assert_eq!(
+            doc,
+            parse("host app\ndb {\n  host db\n  port!int 5432\n}\nservices {\n  cache {\n    host cache\n    port!int 6379\n  }\n}\n")
+                .unwrap()
+        );
+        assert_eq!(doc.nodes[1].origin.as_ref().unwrap().line, 2);
+        let Some(Value::Block(db)) = doc.get("db") else {
+            panic!("expected block");
+        };
+        let origin = db.get_node("port").unwrap().origin.as_ref().unwrap();
+        assert_eq!(origin.to_string(), "db.up:2");
+
+        let err = parser
+            .parse_document("@include db.up as my db")
+            .unwrap_err();
+        assert_eq!(err.message, "`my db` cannot be a prefix: it is not a key");
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let err = parse("@include other.up").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert_eq!(
+            err.message,
+            "cannot include `other.up`: includes are not allowed"
+        );
+        assert_eq!(err.location.unwrap().column, 10);
+
+        let assets = MemoryResolver::new()
+            .insert("a.up", "x 1\n@include b.up\n")
+            .insert("b.up", "@include a.up\n")
+            .insert("bad.up", "\nport!int eighty\n");
+        let parser = parser(assets);
+        let err = parser.parse_document("@include a.up").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(err.message, "include cycle: `a.up` -> `b.up` -> `a.up`");
+        assert_eq!(err.location.unwrap().file.unwrap().to_str(), Some("b.up"));
+
+        let err = parser.parse_document("a 1\n@include bad.up").unwrap_err();
+        let location = err.location.unwrap();
+        assert_eq!(location.file.unwrap().to_str(), Some("bad.up"));
+        assert_eq!(location.line, 2);
+
+        let err = parser.parse_document("@include missing.up").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+        assert_eq!(err.line(), Some(1));
+    }
+
+    #[test]
+    fn test_inline_includes() {
+        let assets = MemoryResolver::new()
+            .insert(
+                "db.up",
+                "# Database\nhost db\ntls {\n  @include tls.up\n}\n",
+            )
+            .insert("tls.up", "cert a.pem\n");
+        let input = "name app\n@include db.up\nserver {\n  port!int 80\n  @include tls.up\n}\n";
+        let doc = parser(assets).parse_document(input).unwrap();
+        let inlined = doc.inline_includes();
+        assert_eq!(inlined, doc);
+        assert_eq!(
+            to_string(&inlined),
+            "name app\n# Database\n# from db.up:2\nhost db\ntls {\n  # from tls.up:1\n  cert a.pem\n}\nserver {\n  port!int 80\n  # from tls.up:1\n  cert a.pem\n}\n"
+        );
+        // Without the includes, the written copy parses to the same values
+        assert_eq!(parse(&to_string(&inlined)).unwrap(), doc);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_includes_keep_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        /// Answers later includes faster, so threads finish out of order
+        struct Slow(MemoryResolver);
+
+        impl IncludeResolver for Slow {
+            fn resolve(&self, path: &str, from: Option<&Path>) -> Result<Included, ParseError> {
+                CALLS.fetch_add(1, Ordering::Relaxed);
+                let n: u64 = path.trim_end_matches(".up").parse().unwrap_or(0);
+                std::thread::sleep(std::time::Duration::from_millis(20 - n));
+                self.0.resolve(path, from)
+            }
+        }
+
+        let mut assets = MemoryResolver::new();
+        let mut input = String::new();
+        let mut expected = String::new();
+        for n in 0..8 {
+            assets = assets.insert(
+                format!("{}.up", n),
+                format!("k{} {}\n@include {}.up\n", n, n, n + 10),
+            );
+            assets = assets.insert(format!("{}.up", n + 10), format!("n{} {}\n", n, n));
+            input.push_str(&format!("@include {}.up\nsep {}\n", n, n));
+            expected.push_str(&format!("k{} {}\nn{} {}\nsep {}\n", n, n, n, n, n));
+        }
+        let parser = parser(Slow(assets.insert("bad.up", "x!int y\n")));
+        let doc = parser.parse_document(&input).unwrap();
+        // Each document was resolved once, ahead of time
+        assert_eq!(CALLS.load(Ordering::Relaxed), 16);
+        assert_eq!(doc, parse(&expected).unwrap());
+        assert_eq!(doc.nodes[1].origin.as_ref().unwrap().to_string(), "10.up:1");
+
+        // The first failing include in the document is reported
+        let err = parser
+            .parse_document("@include 3.up\n@include missing.up\n@include bad.up\n")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+        assert_eq!(err.line(), Some(2));
+        let err = parser
+            .parse_document("a {\n  @include bad.up\n  @include missing.up\n}\n")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn test_file_resolver() {
+        let dir = std::env::temp_dir().join(format!("uplang-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf")).unwrap();
+        fs::write(dir.join("main.up"), "@include conf/db.up\n").unwrap();
+        fs::write(dir.join("conf/db.up"), "@include shared.up\nhost db\n").unwrap();
+        fs::write(dir.join("conf/shared.up"), "region eu\n").unwrap();
+        fs::write(dir.join("conf/loop.up"), "@include ../conf/loop.up\n").unwrap();
+
+        let parser = parser(FileResolver::new(&dir));
+        let doc = parser.parse_file(dir.join("main.up")).unwrap();
+        assert_eq!(doc, parse("region eu\nhost db").unwrap());
+        let origin = doc.nodes[0].origin.as_ref().unwrap();
+        assert!(origin.file.as_ref().unwrap().ends_with("conf/shared.up"));
+
+        let doc = parser.parse_document("@include conf/shared.up").unwrap();
+        assert_eq!(doc.get_str("region"), Some("eu"));
+
+        let err = parser.parse_document("@include conf/loop.up").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
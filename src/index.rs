@@ -0,0 +1,88 @@
+//! Precomputed path lookups
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{walk, Document, Value};
+
+/// A path → value map built once by [`Document::index`] for O(1) lookups
+///
+/// Paths use the same syntax as [`Document::find`]: dotted block keys and
+/// `[i]` list indices. The index borrows the document, so it cannot outlive
+/// or observe later edits to it.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentIndex<'a> {
+    entries: HashMap<String, &'a Value>,
+}
+
+impl<'a> DocumentIndex<'a> {
+    /// Look up the value at `path`
+    pub fn get(&self, path: &str) -> Option<&'a Value> {
+        self.entries.get(path).copied()
+    }
+
+    /// Check whether `path` exists
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Number of indexed paths
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over all indexed paths and values in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &'a Value)> + '_ {
+        self.entries
+            .iter()
+            .map(|(path, value)| (path.as_str(), *value))
+    }
+}
+
+impl Document {
+    /// Build a path index for repeated lookups
+    ///
+    /// Like [`Document::get`], a repeated top-level key resolves to its last
+    /// definition, and only that definition's children are indexed.
+    pub fn index(&self) -> DocumentIndex<'_> {
+        let mut entries = HashMap::new();
+        let mut seen = HashSet::new();
+
+        for node in self.nodes.iter().rev() {
+            if seen.insert(node.key.as_str()) {
+                walk::walk_node(node, &mut |path, value| {
+                    entries.insert(path.to_string(), value);
+                });
+            }
+        }
+
+        DocumentIndex { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    #[test]
+    fn test_index_lookup() {
+        let input = "server {\nhost old\nport 80\n}\nserver {\nhost new\n}\nports [80, 443]";
+        let doc = parse(input).unwrap();
+        let index = doc.index();
+
+        assert_eq!(
+            index.get("server.host"),
+            Some(&Value::String("new".to_string()))
+        );
+        assert!(!index.contains("server.port"));
+        assert_eq!(
+            index.get("ports[1]"),
+            Some(&Value::String("443".to_string()))
+        );
+        assert_eq!(index.len(), 5);
+    }
+}
@@ -0,0 +1,349 @@
+//! Reading INI and Java properties files into documents
+//!
+//! Both formats hold text only, so every value becomes a string, and both
+//! keep keys as written: `server.port` in a properties file is one key, as
+//! it is in UP unless the document is read back with
+//! [`Parser::dotted_keys`](crate::Parser::dotted_keys). Comments directly
+//! above a key or section become its [`Node::doc`], so they survive the
+//! move to UP. A key defined twice keeps its last value, in the position
+//! and with the comment of its first definition.
+
+use crate::{take_comment, Block, Document, ErrorKind, Node, Origin, ParseError, Value};
+
+impl Document {
+    /// Read a document from an INI file
+    ///
+    /// Each `[section]` becomes a block, holding the `key = value` or
+    /// `key: value` lines under it; lines before the first section are
+    /// top-level nodes. A section named twice is one block. A line with a
+    /// key and no separator is a key without a value, [`Value::Empty`].
+    /// Values are trimmed, and one pair of surrounding quotes is removed.
+    /// Lines starting with `;` or `#` are comments.
+    ///
+    /// ```
+    /// use uplang::Document;
+    ///
+    /// let doc = Document::from_ini("; database settings\n[db]\nhost = localhost\nname: \"app\"\n").unwrap();
+    /// assert_eq!(
+    ///     uplang::to_string(&doc),
+    ///     "# database settings\ndb {\n  host localhost\n  name app\n}\n"
+    /// );
+    /// ```
+    pub fn from_ini(input: &str) -> Result<Document, ParseError> {
+        let mut top = Block::new();
+        let mut section: Option<String> = None;
+        let mut comment = Vec::new();
+        for (line_num, line) in input.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                comment.clear();
+                continue;
+            }
+            if let Some(text) = trimmed.strip_prefix([';', '#']) {
+                comment.push(text.strip_prefix(' ').unwrap_or(text));
+                continue;
+            }
+            let doc = take_comment(&mut comment);
+            let origin = Some(Origin::line(line_num + 1));
+
+            if let Some(header) = trimmed.strip_prefix('[') {
+                let name = header
+                    .strip_suffix(']')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| {
+                        ParseError::new(ErrorKind::InvalidSyntax, "expected `[section name]`")
+                            .locate(input, trimmed)
+                    })?;
+                // Sections named again add to the first block
+                if top
+                    .get(name)
+                    .is_some_and(|value| matches!(value, Value::Block(_)))
+                {
+                    section = Some(name.to_string());
+                    continue;
+                }
+                top.insert_node(Node {
+                    doc,
+                    origin,
+                    ..Node::new(name, Value::Block(Block::new()))
+                });
+                section = Some(name.to_string());
+                continue;
+            }
+
+            let (key, value) = match trimmed.find(['=', ':']) {
+                Some(i) => {
+                    let value = unquote(trimmed[i + 1..].trim());
+                    (trimmed[..i].trim_end(), Value::from(value))
+                }
+                None => (trimmed, Value::Empty),
+            };
+            if key.is_empty() {
+                return Err(
+                    ParseError::new(ErrorKind::InvalidSyntax, "expected a key before `=`")
+                        .locate(input, trimmed),
+                );
+            }
+            let node = Node {
+                doc,
+                origin,
+                ..Node::new(key, value)
+            };
+            let block = match &section {
+                Some(name) => match top.get_node_mut(name).map(|node| &mut node.value) {
+                    Some(Value::Block(block)) => block,
+                    _ => unreachable!("sections are blocks"),
+                },
+                None => &mut top,
+            };
+            insert(block, node);
+        }
+        Ok(Document {
+            nodes: top.into_nodes(),
+        })
+    }
+
+    /// Read a document from a Java properties file
+    ///
+    /// A key ends at the first unescaped `=`, `:`, or whitespace, and the
+    /// value is the rest of the line, without the separator and the
+    /// whitespace around it. A line ending in an odd number of backslashes
+    /// continues on the next line, whose leading whitespace is dropped.
+    /// The escapes `\t`, `\n`, `\r`, `\f`, and `\uXXXX` are read as the
+    /// characters they stand for, and a backslash before any other
+    /// character as that character. Lines starting with `#` or `!` are
+    /// comments.
+    ///
+    /// ```
+    /// use uplang::Document;
+    ///
+    /// let input = "# where to listen\nserver.port=8080\ngreeting = Hello, \\\n    world\\u0021\n";
+    /// let doc = Document::from_properties(input).unwrap();
+    /// assert_eq!(doc.nodes[0].key, "server.port");
+    /// assert_eq!(doc.nodes[0].doc.as_deref(), Some("where to listen"));
+    /// assert_eq!(doc.nodes[1].value.as_str(), Some("Hello, world!"));
+    /// ```
+    pub fn from_properties(input: &str) -> Result<Document, ParseError> {
+        let mut top = Block::new();
+        let mut comment = Vec::new();
+        let mut lines = input.lines().enumerate();
+        while let Some((line_num, line)) = lines.next() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
+                comment.clear();
+                continue;
+            }
+            if let Some(text) = trimmed.strip_prefix(['#', '!']) {
+                comment.push(text.strip_prefix(' ').unwrap_or(text));
+                continue;
+            }
+
+            let mut logical = trimmed.to_string();
+            while continues(&logical) {
+                logical.pop();
+                match lines.next() {
+                    Some((_, next)) => logical.push_str(next.trim_start()),
+                    None => break,
+                }
+            }
+            let (key, value) = split_property(&logical);
+            let node = Node {
+                doc: take_comment(&mut comment),
+                origin: Some(Origin::line(line_num + 1)),
+                ..Node::new(
+                    unescape(key, input, line)?,
+                    Value::String(unescape(value, input, line)?),
+                )
+            };
+            insert(&mut top, node);
+        }
+        Ok(Document {
+            nodes: top.into_nodes(),
+        })
+    }
+}
+
+/// Add `node` to `block`, replacing the value of a key defined before but
+/// keeping its comment if `node` has none
+fn insert(block: &mut Block, node: Node) {
+    let Some(old) = block.get_node_mut(&node.key) else {
+        block.push_new(node);
+        return;
+    };
+    old.value = node.value;
+    old.origin = node.origin;
+    if node.doc.is_some() {
+        old.doc = node.doc;
+    }
+}
+
+/// `value` without one pair of matching quotes around it
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Whether a properties line ends in an odd number of backslashes
+fn continues(line: &str) -> bool {
+    line.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 1
+}
+
+/// The escaped key and value of a logical properties line
+fn split_property(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' | ':' => return (&line[..i], line[i + 1..].trim_start()),
+            c if c.is_whitespace() => {
+                let rest = line[i..].trim_start();
+                let rest = match rest.strip_prefix(['=', ':']) {
+                    Some(rest) => rest.trim_start(),
+                    None => rest,
+                };
+                return (&line[..i], rest);
+            }
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Read the escapes of a properties key or value, from `line` of `input`
+fn unescape(text: &str, input: &str, line: &str) -> Result<String, ParseError> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\u{c}'),
+            Some('u') => {
+                let digits: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 4)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            ErrorKind::InvalidValue,
+                            format!("`\\u{}` is not a valid escape", digits),
+                        )
+                        .locate(input, line)
+                    })?;
+                out.push(c);
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string};
+
+    #[test]
+    fn test_from_ini() {
+        let input = "\
+name = legacy
+; the database
+# used by the api
+[db]
+host = localhost
+port: 5432
+password = \"p;w=d\"
+readonly
+
+[cache]
+ttl = 60
+[db]
+port = 6432
+";
+        let doc = Document::from_ini(input).unwrap();
+        assert_eq!(
+            to_string(&doc),
+            "\
+name legacy
+# the database
+# used by the api
+db {
+  host localhost
+  port 6432
+  password p;w=d
+  readonly
+}
+cache {
+  ttl 60
+}
+"
+        );
+        assert_eq!(doc.nodes[1].origin.as_ref().map(|o| o.line), Some(4));
+        assert_eq!(doc.get("db.readonly"), Some(&Value::Empty));
+        assert_eq!(doc.get_str("db.password"), Some("p;w=d"));
+        assert_eq!(parse(&to_string(&doc)).unwrap(), doc);
+
+        let err = Document::from_ini("[db\nhost = x\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(1));
+        assert_eq!(Document::from_ini("= x").unwrap_err().line(), Some(1));
+    }
+
+    #[test]
+    fn test_from_properties() {
+        let input = "\
+# Application
+! settings
+app.name = demo
+app.title:Demo App
+key\\ with\\ spaces value
+path c:\\\\temp
+list = a, \\
+       b, \\
+       c
+tab=\\t|\\u00e9
+empty=
+app.name = final
+";
+        let doc = Document::from_properties(input).unwrap();
+        let entries: Vec<(&str, &str)> = doc
+            .nodes
+            .iter()
+            .map(|node| (node.key.as_str(), node.value.as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("app.name", "final"),
+                ("app.title", "Demo App"),
+                ("key with spaces", "value"),
+                ("path", "c:\\temp"),
+                ("list", "a, b, c"),
+                ("tab", "\t|\u{e9}"),
+                ("empty", ""),
+            ]
+        );
+        assert_eq!(doc.nodes[0].doc.as_deref(), Some("Application\nsettings"));
+        assert_eq!(doc.nodes[4].origin.as_ref().map(|o| o.line), Some(7));
+
+        let err = Document::from_properties("a=\\u12\nb=1\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert_eq!(err.line(), Some(1));
+    }
+}
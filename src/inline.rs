@@ -0,0 +1,313 @@
+//! Inline blocks such as `point { x 1, y 2 }`
+//!
+//! An inline block holds the nodes of a block on one line, separated like
+//! the items of an inline list. Each node is written as on a line of its
+//! own, `key value` or `key!type value`, and its value may be a scalar, an
+//! inline list, or another inline block; values with a separator or brace
+//! in them are quoted. Inline blocks are also items of inline lists, as in
+//! `points [{ x 1, y 2 }, { x 3, y 4 }]`, and of multiline lists.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::descent::Reader;
+use crate::interpolate::Scope;
+use crate::{interpolate_items, quote, Block, ErrorKind, Node, ParseError, Parser, Value};
+
+impl Parser {
+    /// Parse an inline block, braces included
+    pub(crate) fn parse_inline_block(
+        &self,
+        s: &str,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ParseError> {
+        let scope = &Scope::child(scope);
+        self.limits.check_depth(scope.depth())?;
+        let body = s.trim();
+        let body = body.strip_prefix('{').unwrap_or(body);
+        let body = body.strip_suffix('}').unwrap_or(body);
+
+        let mut block = Block::new();
+        if body.trim().is_empty() {
+            return Ok(Value::Block(block));
+        }
+        for entry in split_entries(body, self.list_separator)? {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                return Err(ParseError::new(
+                    ErrorKind::InvalidSyntax,
+                    "empty entry in inline block",
+                ));
+            }
+            let node = self.parse_inline_node(entry, scope)?;
+            block.insert_node(node);
+        }
+        Ok(Value::Block(block))
+    }
+
+    /// Parse one `key value` entry of an inline block
+    fn parse_inline_node(&self, entry: &str, scope: &Scope<'_>) -> Result<Node, ParseError> {
+        let (key_part, val_part) = self.split_key_value(entry);
+        let (key, type_annotation) = quote::split_key(key_part)?;
+        self.limits.check_key(&key)?;
+        // Values that go on over more lines cannot be inline
+        if val_part == "[" || val_part.ends_with('{') || val_part.starts_with("```") {
+            return Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!(
+                    "`{}` cannot be inline; write the block over several lines",
+                    val_part
+                ),
+            ));
+        }
+        let value = Reader::new(self, "").value(val_part, type_annotation, scope)?;
+        let (type_annotation, anchor) = match type_annotation {
+            Some("ref") => (None, Some(val_part.to_string())),
+            annotation => (annotation.map(str::to_string), None),
+        };
+        Ok(Node {
+            type_annotation,
+            anchor,
+            ..Node::new(key.into_owned(), value)
+        })
+    }
+
+    /// Parse the items of an inline list, whose inline blocks are read in
+    /// `scope`
+    pub(crate) fn parse_inline_items(
+        &self,
+        s: &str,
+        scope: &Scope<'_>,
+    ) -> Result<Vec<Value>, ParseError> {
+        let s = s.trim();
+        let s = s.strip_prefix('[').unwrap_or(s);
+        let s = s.strip_suffix(']').unwrap_or(s);
+
+        if s.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut items = Vec::new();
+        let mut chars = s.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            let item = if chars.next_if_eq(&'"').is_some() {
+                let item = self.parse_quoted(&mut chars)?;
+                self.end_item(&mut chars, "quoted item")?;
+                Value::String(self.normalizers.apply(&item).into_owned())
+            } else if chars.peek() == Some(&'{') {
+                let block = braced(&mut chars)?;
+                self.end_item(&mut chars, "inline block")?;
+                self.parse_inline_block(&block, scope)?
+            } else if chars.peek() == Some(&'[') {
+                let list = braced(&mut chars)?;
+                self.end_item(&mut chars, "inline list")?;
+                let scope = &Scope::child(scope);
+                self.limits.check_depth(scope.depth())?;
+                let items = self.parse_inline_items(&list, scope)?;
+                self.limits.check_items(items.len(), "items in a list")?;
+                Value::List(interpolate_items(scope, items)?)
+            } else {
+                let mut item = String::new();
+                while let Some(c) = chars.next_if(|&c| c != self.list_separator) {
+                    if c == '\\' && chars.peek() == Some(&self.list_separator) {
+                        item.push(self.list_separator);
+                        chars.next();
+                    } else {
+                        item.push(c);
+                    }
+                }
+                Value::String(self.normalizers.apply(item.trim_end()).into_owned())
+            };
+            items.push(item);
+
+            if chars.next().is_none() {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Check that only whitespace is left of an item before the separator
+    fn end_item(&self, chars: &mut Peekable<Chars<'_>>, what: &str) -> Result<(), ParseError> {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.peek() {
+            None => Ok(()),
+            Some(&c) if c == self.list_separator => Ok(()),
+            Some(c) => Err(ParseError::new(
+                ErrorKind::InvalidList,
+                format!("unexpected `{}` after {}", c, what),
+            )),
+        }
+    }
+}
+
+/// Whether a value is an inline block, such as `{ x 1, y 2 }` or `{}`
+pub(crate) fn is_inline_block(s: &str) -> bool {
+    s.len() >= 2 && s.starts_with('{') && s.ends_with('}')
+}
+
+/// Read an inline block or list from its opening bracket to the matching
+/// closing one
+fn braced(chars: &mut Peekable<Chars<'_>>) -> Result<String, ParseError> {
+    let opener = chars.peek().copied().unwrap_or('{');
+    let mut text = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        text.push(c);
+        match c {
+            '\\' if quoted => text.extend(chars.next()),
+            '"' => quoted = !quoted,
+            '{' | '[' if !quoted => depth += 1,
+            '}' | ']' if !quoted => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(ParseError::new(
+        ErrorKind::InvalidList,
+        format!("unclosed `{}` in inline list", opener),
+    ))
+}
+
+/// Split the body of an inline block at the separators outside quotes,
+/// lists, and nested blocks
+fn split_entries(body: &str, separator: char) -> Result<Vec<&str>, ParseError> {
+    let mut entries = Vec::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if quoted => {}
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        format!("unmatched `{}` in inline block", c),
+                    )
+                })?;
+            }
+            c if c == separator && depth == 0 => {
+                entries.push(&body[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if quoted || depth > 0 {
+        return Err(ParseError::new(
+            ErrorKind::InvalidSyntax,
+            "unclosed quote or bracket in inline block",
+        ));
+    }
+    entries.push(&body[start..]);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, to_string, ErrorKind, Parser, Value, Writer};
+
+    #[test]
+    fn test_inline_blocks() {
+        let doc = parse("point { x!int 1, y!int 2 }\nempty {}\nbox { min { x 0, y 0 }, tags [a, b], name \"a, b\" }\n").unwrap();
+        assert_eq!(doc.get_int("point.x"), Some(1));
+        assert_eq!(doc.get_int("point.y"), Some(2));
+        assert_eq!(
+            doc.get("empty").and_then(Value::as_block).map(|b| b.len()),
+            Some(0)
+        );
+        assert_eq!(doc.get_str("box.min.y"), Some("0"));
+        assert_eq!(doc.get_str("box.tags[1]"), Some("b"));
+        assert_eq!(doc.get_str("box.name"), Some("a, b"));
+
+        // The same as writing the blocks out
+        let long = parse("point {\n  x!int 1\n  y!int 2\n}\n").unwrap();
+        assert_eq!(doc.get("point"), long.get("point"));
+
+        // In inline and multiline lists, with variables of the enclosing scope
+        let doc = parse("@let h db\npoints [{ x 1 }, { host ${h}, port!int 5 }, c]\nmore [\n  { x 2 }\n  {\n    x 3\n  }\n]\n").unwrap();
+        assert_eq!(doc.get_str("points[0].x"), Some("1"));
+        assert_eq!(doc.get_str("points[1].host"), Some("db"));
+        assert_eq!(doc.get_int("points[1].port"), Some(5));
+        assert_eq!(doc.get_str("points[2]"), Some("c"));
+        assert_eq!(doc.get_str("more[0].x"), Some("2"));
+        assert_eq!(doc.get_str("more[1].x"), Some("3"));
+        let streamed = Parser::new()
+            .parse_reader("more [\n  { x 2 }\n]\nnext 1\n".as_bytes())
+            .unwrap();
+        assert_eq!(streamed.get_str("next"), Some("1"));
+    }
+
+    #[test]
+    fn test_nested_inline_lists() {
+        let doc =
+            parse("@let x 9\na [[1, 2], 3]\nb [[], [a, [${x}, \"c]\"]], { k [1, 2] }]\n").unwrap();
+        let strings = |items: &[&str]| Value::List(items.iter().map(|&s| Value::from(s)).collect());
+        assert_eq!(
+            doc.get("a"),
+            Some(&Value::List(vec![strings(&["1", "2"]), Value::from("3")]))
+        );
+        assert_eq!(doc.get("b[0]"), Some(&Value::List(Vec::new())));
+        assert_eq!(doc.get_str("b[1][0]"), Some("a"));
+        assert_eq!(doc.get("b[1][1]"), Some(&strings(&["9", "c]"])));
+        assert_eq!(doc.get_str("b[2].k[1]"), Some("2"));
+
+        let kind = |input: &str| parse(input).unwrap_err().kind;
+        assert_eq!(kind("l [[a, b] c]"), ErrorKind::InvalidList);
+        assert_eq!(kind("l [[a, b]"), ErrorKind::InvalidList);
+    }
+
+    #[test]
+    fn test_inline_block_errors() {
+        let kind = |input: &str| parse(input).unwrap_err().kind;
+        assert_eq!(kind("p { x!int a }"), ErrorKind::TypeMismatch);
+        assert_eq!(kind("p { x 1,, y 2 }"), ErrorKind::InvalidSyntax);
+        assert_eq!(kind("p { x [a, b }"), ErrorKind::InvalidSyntax);
+        assert_eq!(kind("p { x \"a }"), ErrorKind::InvalidSyntax);
+        assert_eq!(kind("p { x { }"), ErrorKind::InvalidSyntax);
+        assert_eq!(kind("l [{ x 1 } y]"), ErrorKind::InvalidList);
+        assert_eq!(kind("l [{ x 1, b]"), ErrorKind::InvalidList);
+        let err = Parser::new().parse_borrowed("p { x 1 }").unwrap_err();
+        assert_eq!(
+            err.message,
+            "inline blocks are not supported when parsing borrowed"
+        );
+    }
+
+    #[test]
+    fn test_write_inline_blocks() {
+        let input = "name app\npoint {\n  x!int 1\n  y a, b\n}\nempty {\n}\nserver {\n  host a.example.com\n  tls {\n    cert /etc/ssl/cert.pem\n    key /etc/ssl/key.pem\n  }\n}\nlist [\n  {\n    k v\n  }\n  [a, b]\n]\n";
+        let doc = parse(input).unwrap();
+        // Off by default
+        assert_eq!(to_string(&doc), input);
+
+        let text = Writer::new().inline_width(40).to_string(&doc);
+        assert_eq!(
+            text,
+            "name app\npoint { x!int 1, y \"a, b\" }\nempty {}\nserver {\n  host a.example.com\n  tls {\n    cert /etc/ssl/cert.pem\n    key /etc/ssl/key.pem\n  }\n}\nlist [\n  { k v }\n  [a, b]\n]\n"
+        );
+        assert_eq!(parse(&text).unwrap(), doc);
+        assert_eq!(crate::format(&text).unwrap(), text);
+
+        // Blocks with comments or multiline values stay as they are
+        let doc = parse("a {\n  # Note\n  b 1\n}\nc {\n  d ```\n  x\n  y\n  ```\n}\n").unwrap();
+        let text = Writer::new().inline_width(80).to_string(&doc);
+        assert!(text.starts_with("a {\n  # Note\n"));
+        assert!(text.contains("c {\n  d ```"));
+    }
+}
@@ -0,0 +1,840 @@
+//! Block-scoped `@let` variables and `${...}` interpolation
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{ErrorKind, Limits, Location, ParseError, Value};
+
+/// A function callable from interpolation, see [`Parser::function`]
+///
+/// Functions receive their arguments as text, already evaluated, and return
+/// the text to substitute or a message explaining why they cannot. Closures
+/// of the form `Fn(&[String]) -> Result<String, String>` implement it:
+///
+/// ```
+/// use uplang::Parser;
+///
+/// let parser = Parser::new().function("repeat", |args: &[String]| match args {
+///     [text, count] => {
+///         let count = count.parse().map_err(|_| "count must be a number")?;
+///         Ok(text.repeat(count))
+///     }
+///     _ => Err("expected 2 arguments".to_string()),
+/// });
+/// let doc = parser.parse_document("line ${repeat(\"-\", 3)}").unwrap();
+/// assert_eq!(doc.get_str("line"), Some("---"));
+/// ```
+///
+/// [`Parser::function`]: crate::Parser::function
+pub trait Function: Send + Sync {
+    /// Compute the result for `args`
+    fn call(&self, args: &[String]) -> Result<String, String>;
+}
+
+impl<F> Function for F
+where
+    F: Fn(&[String]) -> Result<String, String> + Send + Sync,
+{
+    fn call(&self, args: &[String]) -> Result<String, String> {
+        self(args)
+    }
+}
+
+/// Custom functions registered on a parser, by name
+#[derive(Clone, Default)]
+pub(crate) struct Functions(BTreeMap<String, Arc<dyn Function>>);
+
+impl Functions {
+    pub(crate) fn insert(&mut self, name: String, function: Arc<dyn Function>) {
+        self.0.insert(name, function);
+    }
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+static NO_FUNCTIONS: Functions = Functions(BTreeMap::new());
+
+/// Variables visible at one nesting level of a document
+///
+/// Each block gets its own scope whose lookups fall back to the enclosing
+/// one, so a definition inside a block shadows an outer variable of the same
+/// name without changing it for the rest of the document.
+#[derive(Debug)]
+pub(crate) struct Scope<'p> {
+    vars: HashMap<String, Variable>,
+    /// Values of `!anchor` nodes by key; `None` while the anchor's own
+    /// value is being parsed
+    anchors: HashMap<String, Option<Value>>,
+    parent: Option<&'p Scope<'p>>,
+    functions: &'p Functions,
+    env_access: bool,
+    /// Number of blocks and lists enclosing this scope
+    depth: usize,
+}
+
+impl Default for Scope<'_> {
+    fn default() -> Self {
+        Self::root(&NO_FUNCTIONS, false)
+    }
+}
+
+impl<'p> Scope<'p> {
+    /// Create the top-level scope of a document
+    ///
+    /// `env:NAME` references read the process environment only when
+    /// `env_access` is set; otherwise they count as undefined.
+    pub(crate) fn root(functions: &'p Functions, env_access: bool) -> Self {
+        Self {
+            vars: HashMap::new(),
+            anchors: HashMap::new(),
+            parent: None,
+            functions,
+            env_access,
+            depth: 0,
+        }
+    }
+
+    /// Count nesting from `depth`, for a document parsed inside another
+    pub(crate) fn at_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Create an empty scope nested inside `parent`
+    pub(crate) fn child(parent: &'p Scope<'p>) -> Self {
+        Self {
+            vars: HashMap::new(),
+            anchors: HashMap::new(),
+            parent: Some(parent),
+            functions: parent.functions,
+            env_access: parent.env_access,
+            depth: parent.depth + 1,
+        }
+    }
+
+    /// Create the scope for parsing the value of the anchor `name`, in
+    /// which a reference to `name` is a cycle
+    pub(crate) fn anchoring(parent: &'p Scope<'p>, name: &str) -> Self {
+        Self {
+            anchors: HashMap::from([(name.to_string(), None)]),
+            depth: parent.depth,
+            ..Self::child(parent)
+        }
+    }
+
+    /// Define the anchor `name` for the rest of this scope
+    pub(crate) fn define_anchor(&mut self, name: &str, value: Value) {
+        self.anchors.insert(name.to_string(), Some(value));
+    }
+
+    /// Look up an anchor: `Some(None)` if it is still being parsed
+    pub(crate) fn anchor(&self, name: &str) -> Option<Option<&Value>> {
+        match self.anchors.get(name) {
+            Some(value) => Some(value.as_ref()),
+            None => self.parent?.anchor(name),
+        }
+    }
+
+    /// Number of blocks and lists enclosing this scope
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Define a variable for the rest of this scope, replacing any earlier one
+    #[cfg(test)]
+    pub(crate) fn define(&mut self, name: &str, value: String) {
+        let variable = Variable {
+            value,
+            refs: Vec::new(),
+            location: None,
+        };
+        self.vars.insert(name.to_string(), variable);
+    }
+
+    /// Interpolate `text` and define the result as `name`
+    ///
+    /// `location` is where `text` was written, for reporting cycles. A
+    /// value that would still refer to `name` after interpolation, directly
+    /// or through variables that were undefined when they were set (`@let a
+    /// ${b}` followed by `@let b ${a}`), can never be resolved and is an
+    /// [`ErrorKind::ReferenceCycle`] error listing each hop.
+    pub(crate) fn define_from(
+        &mut self,
+        name: &str,
+        text: &str,
+        location: Option<Location>,
+    ) -> Result<(), ParseError> {
+        let value = self.interpolate(text)?.into_owned();
+        let refs: Vec<String> = references(text)
+            .into_iter()
+            .filter(|r| self.lookup(r).is_some())
+            .collect();
+
+        let mut chain = vec![format!("`{}`", name)];
+        if self.cycle(
+            name,
+            &refs,
+            &references(&value),
+            &mut chain,
+            &mut Vec::new(),
+        ) {
+            chain.push(format!("`{}`", name));
+            return Err(ParseError::new(
+                ErrorKind::ReferenceCycle,
+                chain.join(" -> "),
+            ));
+        }
+
+        let variable = Variable {
+            value,
+            refs,
+            location,
+        };
+        self.vars.insert(name.to_string(), variable);
+        Ok(())
+    }
+
+    /// Look for a path back to `target` from a value that used the
+    /// variables `refs` and still refers to the undefined names `pending`,
+    /// extending `chain` with each variable along it
+    ///
+    /// Only pending names can refer to `target`: the variables in `refs`
+    /// were defined before it.
+    fn cycle(
+        &self,
+        target: &str,
+        refs: &[String],
+        pending: &[String],
+        chain: &mut Vec<String>,
+        visited: &mut Vec<String>,
+    ) -> bool {
+        for name in refs.iter().chain(pending) {
+            if name == target || visited.contains(name) {
+                continue;
+            }
+            visited.push(name.clone());
+            let Some(variable) = self.variable(name) else {
+                continue;
+            };
+            chain.push(match &variable.location {
+                Some(at) => format!("`{}` (line {}, column {})", name, at.line, at.column),
+                None => format!("`{}`", name),
+            });
+            let next = references(&variable.value);
+            if self.cycle(target, &variable.refs, &next, chain, visited) {
+                return true;
+            }
+            chain.pop();
+        }
+        pending.iter().any(|name| name == target)
+    }
+
+    fn variable(&self, name: &str) -> Option<&Variable> {
+        match self.vars.get(name) {
+            Some(variable) => Some(variable),
+            None => self.parent.and_then(|parent| parent.variable(name)),
+        }
+    }
+
+    /// Find the innermost definition of `name`
+    pub(crate) fn lookup(&self, name: &str) -> Option<&str> {
+        self.variable(name).map(|variable| variable.value.as_str())
+    }
+
+    /// Replace every `${...}` in `text` with its value
+    ///
+    /// A reference is a variable name or an expression over variables,
+    /// numbers, quoted strings, `env:NAME` lookups, and function calls, such
+    /// as `${port + 1}` or `${default(env:PORT, 8080)}`. References that use
+    /// undefined variables, or that are not valid expressions, are left as
+    /// written, so text such as shell snippets that happens to contain
+    /// `${HOME}` is unaffected.
+    pub(crate) fn interpolate<'t>(&self, text: &'t str) -> Result<Cow<'t, str>, ParseError> {
+        if !text.contains("${") {
+            return Ok(Cow::Borrowed(text));
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            match self.evaluate(rest[2..end].trim())? {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(Cow::Owned(out))
+    }
+
+    /// Value of the text between `${` and `}`, or `None` to leave it as is
+    fn evaluate(&self, reference: &str) -> Result<Option<String>, ParseError> {
+        if let Some(value) = self.lookup(reference) {
+            return Ok(Some(value.to_string()));
+        }
+        let mut names = |name: &str| Ok(self.lookup(name).map(str::to_string));
+        match evaluate(reference, self.functions, self.env_access, &mut names)? {
+            Evaluated::Value(value) => Ok(Some(value)),
+            Evaluated::Undefined(_) | Evaluated::Malformed => Ok(None),
+        }
+    }
+}
+
+/// Outcome of evaluating the text of a `${...}` reference
+pub(crate) enum Evaluated {
+    Value(String),
+    /// The expression uses this name, or `env:NAME`, which is undefined
+    Undefined(String),
+    /// The text is not an expression
+    Malformed,
+}
+
+/// Evaluate `reference`, the text between `${` and `}`, with `names`
+/// giving the value of each name it uses, or `None` if it is undefined
+pub(crate) fn evaluate(
+    reference: &str,
+    functions: &Functions,
+    env_access: bool,
+    names: &mut dyn FnMut(&str) -> Result<Option<String>, ParseError>,
+) -> Result<Evaluated, ParseError> {
+    let Some(tokens) = tokenize(reference) else {
+        return Ok(Evaluated::Malformed);
+    };
+    let mut expr = Expr {
+        names,
+        functions,
+        env_access,
+        reference,
+        tokens: &tokens,
+        pos: 0,
+        depth: 0,
+    };
+    match expr.sum() {
+        Ok(operand) if expr.pos == tokens.len() => Ok(Evaluated::Value(operand.into_text())),
+        Ok(_) | Err(Failure::Malformed) => Ok(Evaluated::Malformed),
+        Err(Failure::Undefined(name)) => Ok(Evaluated::Undefined(name)),
+        Err(Failure::Error(e)) => Err(e),
+    }
+}
+
+/// A `@let` variable and what its definition referred to
+#[derive(Debug)]
+struct Variable {
+    value: String,
+    /// Variables the definition used that were defined at the time
+    refs: Vec<String>,
+    /// Where the definition's value was written
+    location: Option<Location>,
+}
+
+/// Names of the variables used in the `${...}` references of `text`
+fn references(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let tokens = tokenize(rest[2..end].trim()).unwrap_or_default();
+        for (i, token) in tokens.iter().enumerate() {
+            if let Token::Name(name) = token {
+                let is_call = tokens.get(i + 1) == Some(&Token::Op('('));
+                if !is_call && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Env(String),
+    Number(Number),
+    Text(String),
+    Op(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl Number {
+    fn parse(text: &str) -> Option<Self> {
+        match text.parse() {
+            Ok(n) => Some(Number::Int(n)),
+            Err(_) => text.parse().ok().map(Number::Float),
+        }
+    }
+
+    fn float(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(x) => x,
+        }
+    }
+}
+
+/// Intermediate result of an expression
+enum Operand {
+    Number(Number),
+    Text(String),
+}
+
+impl Operand {
+    fn into_text(self) -> String {
+        match self {
+            Operand::Number(number) => number.to_string(),
+            Operand::Text(text) => text,
+        }
+    }
+}
+
+/// Why an expression produced no value
+enum Failure {
+    /// A name, or `env:NAME`, is undefined
+    Undefined(String),
+    /// The tokens do not form an expression
+    Malformed,
+    /// Report an error
+    Error(ParseError),
+}
+
+/// Split an expression into tokens, or `None` if it is not one
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' | '%' | '(' | ')' | ',' => tokens.push(Token::Op(c)),
+            '"' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next()? {
+                        (_, '"') => break,
+                        (_, '\\') => literal.push(chars.next()?.1),
+                        (_, c) => literal.push(c),
+                    }
+                }
+                tokens.push(Token::Text(literal));
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.') {
+                    end = i + c.len_utf8();
+                }
+                tokens.push(Token::Number(Number::parse(&text[start..end])?));
+            }
+            c if is_name(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|&(_, c)| is_name(c)) {
+                    end = i + c.len_utf8();
+                }
+                // Key paths such as `servers[0].host` are one name
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| is_name(c) || matches!(c, '.' | '[' | ']'))
+                {
+                    end = i + c.len_utf8();
+                }
+                let name = &text[start..end];
+                if chars.next_if(|&(_, c)| c == ':').is_none() {
+                    tokens.push(Token::Name(name.to_string()));
+                    continue;
+                }
+                if name != "env" {
+                    return None;
+                }
+                let var_start = chars.peek().map_or(text.len(), |&(i, _)| i);
+                let mut var_end = var_start;
+                while let Some((i, c)) = chars.next_if(|&(_, c)| is_name(c)) {
+                    var_end = i + c.len_utf8();
+                }
+                if var_end == var_start {
+                    return None;
+                }
+                tokens.push(Token::Env(text[var_start..var_end].to_string()));
+            }
+            _ => return None,
+        }
+    }
+    (!tokens.is_empty()).then_some(tokens)
+}
+
+/// Recursive-descent evaluator for `+ - * / %`, unary minus, parentheses,
+/// and function calls
+struct Expr<'a> {
+    names: &'a mut dyn FnMut(&str) -> Result<Option<String>, ParseError>,
+    functions: &'a Functions,
+    env_access: bool,
+    reference: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+    /// Parentheses, calls, and unary minuses being evaluated
+    depth: usize,
+}
+
+impl Expr<'_> {
+    fn next_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                self.pos += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Operand, Failure> {
+        let mut value = self.product()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            let rhs = self.product()?;
+            value = self.apply(op, value, rhs)?;
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<Operand, Failure> {
+        let mut value = self.operand()?;
+        while let Some(op) = self.next_op(&['*', '/', '%']) {
+            let rhs = self.operand()?;
+            value = self.apply(op, value, rhs)?;
+        }
+        Ok(value)
+    }
+
+    fn operand(&mut self) -> Result<Operand, Failure> {
+        self.depth += 1;
+        let value = self.nested_operand();
+        self.depth -= 1;
+        value
+    }
+
+    fn nested_operand(&mut self) -> Result<Operand, Failure> {
+        Limits::new()
+            .check_depth(self.depth)
+            .map_err(|e| self.error(e.kind, e.message))?;
+        if self.next_op(&['-']).is_some() {
+            let value = self.operand()?;
+            return self.apply('-', Operand::Number(Number::Int(0)), value);
+        }
+        if self.next_op(&['(']).is_some() {
+            let value = self.sum()?;
+            return match self.next_op(&[')']) {
+                Some(_) => Ok(value),
+                None => Err(Failure::Malformed),
+            };
+        }
+        let token = self.tokens.get(self.pos).ok_or(Failure::Malformed)?;
+        self.pos += 1;
+        match token {
+            Token::Number(number) => Ok(Operand::Number(*number)),
+            Token::Text(text) => Ok(Operand::Text(text.clone())),
+            Token::Name(name) if self.next_op(&['(']).is_some() => self.call(name),
+            Token::Name(name) => match (self.names)(name).map_err(Failure::Error)? {
+                Some(value) => Ok(Operand::Text(value)),
+                None => Err(Failure::Undefined(name.clone())),
+            },
+            Token::Env(name) if self.env_access => std::env::var(name)
+                .map(Operand::Text)
+                .map_err(|_| Failure::Undefined(format!("env:{}", name))),
+            Token::Env(name) => Err(Failure::Undefined(format!("env:{}", name))),
+            Token::Op(_) => Err(Failure::Malformed),
+        }
+    }
+
+    /// Evaluate a call whose opening parenthesis was just consumed
+    fn call(&mut self, name: &str) -> Result<Operand, Failure> {
+        let mut args = Vec::new();
+        let mut undefined = String::new();
+        if self.next_op(&[')']).is_none() {
+            loop {
+                let start = self.pos;
+                match self.sum() {
+                    Ok(value) => args.push(Some(value.into_text())),
+                    // `default` is the one function that accepts undefined arguments
+                    Err(Failure::Undefined(missing)) if name == "default" => {
+                        undefined = missing;
+                        self.pos = start;
+                        self.skip_argument();
+                        args.push(None);
+                    }
+                    Err(failure) => return Err(failure),
+                }
+                match self.next_op(&[',', ')']) {
+                    Some(',') => {}
+                    Some(_) => break,
+                    None => return Err(Failure::Malformed),
+                }
+            }
+        }
+
+        if name == "default" {
+            return match args.as_slice() {
+                [value, fallback] => value
+                    .clone()
+                    .or_else(|| fallback.clone())
+                    .map(Operand::Text)
+                    .ok_or(Failure::Undefined(undefined)),
+                _ => Err(self.arity(name, "2 arguments", args.len())),
+            };
+        }
+        let args: Vec<String> = args.into_iter().flatten().collect();
+
+        if let Some(function) = self.functions.0.get(name) {
+            return function.call(&args).map(Operand::Text).map_err(|message| {
+                self.error(ErrorKind::InvalidValue, format!("`{}`: {}", name, message))
+            });
+        }
+
+        match (name, args.as_slice()) {
+            ("upper", [text]) => Ok(Operand::Text(text.to_uppercase())),
+            ("lower", [text]) => Ok(Operand::Text(text.to_lowercase())),
+            ("trim", [text]) => Ok(Operand::Text(text.trim().to_string())),
+            ("replace", [text, from, to]) => Ok(Operand::Text(text.replace(from, to))),
+            ("concat", args) => Ok(Operand::Text(args.concat())),
+            ("upper" | "lower" | "trim", _) => Err(self.arity(name, "1 argument", args.len())),
+            ("replace", _) => Err(self.arity(name, "3 arguments", args.len())),
+            _ => Err(self.error(
+                ErrorKind::InvalidValue,
+                format!("unknown function `{}`", name),
+            )),
+        }
+    }
+
+    /// Move past one call argument, up to the next `,` or `)` outside parentheses
+    fn skip_argument(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.tokens.get(self.pos) {
+            match token {
+                Token::Op('(') => depth += 1,
+                Token::Op(',' | ')') if depth == 0 => return,
+                Token::Op(')') => depth -= 1,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn apply(&self, op: char, a: Operand, b: Operand) -> Result<Operand, Failure> {
+        let (a, b) = (self.number(a)?, self.number(b)?);
+        if matches!(op, '/' | '%') && b.float() == 0.0 {
+            return Err(self.error(ErrorKind::InvalidValue, "division by zero".to_string()));
+        }
+        if let (Number::Int(a), Number::Int(b)) = (a, b) {
+            let result = match op {
+                '+' => a.checked_add(b),
+                '-' => a.checked_sub(b),
+                '*' => a.checked_mul(b),
+                '/' => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            return result
+                .map(|n| Operand::Number(Number::Int(n)))
+                .ok_or_else(|| {
+                    self.error(ErrorKind::InvalidValue, "arithmetic overflow".to_string())
+                });
+        }
+        let (a, b) = (a.float(), b.float());
+        Ok(Operand::Number(Number::Float(match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' => a / b,
+            _ => a % b,
+        })))
+    }
+
+    fn number(&self, operand: Operand) -> Result<Number, Failure> {
+        match operand {
+            Operand::Number(number) => Ok(number),
+            Operand::Text(text) => Number::parse(text.trim()).ok_or_else(|| {
+                self.error(
+                    ErrorKind::TypeMismatch,
+                    format!("`{}` is not a number", text),
+                )
+            }),
+        }
+    }
+
+    fn arity(&self, name: &str, expected: &str, found: usize) -> Failure {
+        self.error(
+            ErrorKind::InvalidValue,
+            format!("`{}` takes {}, found {}", name, expected, found),
+        )
+    }
+
+    fn error(&self, kind: ErrorKind, message: String) -> Failure {
+        Failure::Error(ParseError::new(
+            kind,
+            format!("{} in `${{{}}}`", message, self.reference),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadowing() {
+        let mut root = Scope::default();
+        root.define("base", "/srv/app".to_string());
+        root.define("env", "prod".to_string());
+
+        let mut inner = Scope::child(&root);
+        inner.define("base", "/opt/app".to_string());
+        assert_eq!(
+            inner.interpolate("${base}/${ env }").unwrap(),
+            "/opt/app/prod"
+        );
+        assert_eq!(root.interpolate("${base}/logs").unwrap(), "/srv/app/logs");
+    }
+
+    #[test]
+    fn test_undefined_and_unclosed_references() {
+        let scope = Scope::default();
+        assert!(matches!(
+            scope.interpolate("plain"),
+            Ok(Cow::Borrowed("plain"))
+        ));
+        assert_eq!(scope.interpolate("echo ${HOME}").unwrap(), "echo ${HOME}");
+        assert_eq!(scope.interpolate("a ${b").unwrap(), "a ${b");
+        assert_eq!(
+            scope.interpolate("${VAR:-fallback} ${x + 1}").unwrap(),
+            "${VAR:-fallback} ${x + 1}"
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut scope = Scope::default();
+        scope.define("port", "8080".to_string());
+        scope.define("replicas", "3".to_string());
+        scope.define("ratio", "0.5".to_string());
+        scope.define("name", "web".to_string());
+
+        let eval = |text: &str| scope.interpolate(text).map(Cow::into_owned);
+        assert_eq!(eval("${port + 1}").unwrap(), "8081");
+        assert_eq!(eval("${replicas * 2 - -1}").unwrap(), "7");
+        assert_eq!(eval("${(replicas + 1) * 2}").unwrap(), "8");
+        assert_eq!(eval("${replicas / 2}, ${replicas % 2}").unwrap(), "1, 1");
+        assert_eq!(eval("${replicas * ratio}").unwrap(), "1.5");
+        assert_eq!(eval("${10}").unwrap(), "10");
+
+        let err = eval("${name + 1}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.message, "`web` is not a number in `${name + 1}`");
+        assert_eq!(
+            eval("${port / 0}").unwrap_err().kind,
+            ErrorKind::InvalidValue
+        );
+        assert_eq!(
+            eval("${9223372036854775807 + 1}").unwrap_err().kind,
+            ErrorKind::InvalidValue
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut functions = Functions::default();
+        functions.insert(
+            "twice".to_string(),
+            Arc::new(|args: &[String]| Ok(args.concat().repeat(2))),
+        );
+        let mut scope = Scope::root(&functions, false);
+        scope.define("name", "web".to_string());
+        scope.define("port", "80".to_string());
+
+        let eval = |text: &str| scope.interpolate(text).map(Cow::into_owned);
+        assert_eq!(eval("${upper(name)}").unwrap(), "WEB");
+        assert_eq!(eval("${concat(name, \"-\", port + 1)}").unwrap(), "web-81");
+        assert_eq!(
+            eval("${replace(lower(\"A.B\"), \".\", \"/\")}").unwrap(),
+            "a/b"
+        );
+        assert_eq!(eval("${twice(name)}").unwrap(), "webweb");
+        assert_eq!(eval("${default(missing, 8080)}").unwrap(), "8080");
+        assert_eq!(eval("${default(port, 8080)}").unwrap(), "80");
+        assert_eq!(eval("${upper(missing)}").unwrap(), "${upper(missing)}");
+
+        let err = eval("${upper(name, port)}").unwrap_err();
+        assert_eq!(
+            err.message,
+            "`upper` takes 1 argument, found 2 in `${upper(name, port)}`"
+        );
+        assert_eq!(
+            eval("${shout(name)}").unwrap_err().kind,
+            ErrorKind::InvalidValue
+        );
+    }
+
+    #[test]
+    fn test_reference_cycles() {
+        let mut scope = Scope::default();
+        scope.define_from("base", "/srv", None).unwrap();
+        scope.define_from("a", "${b}/x", None).unwrap();
+        scope.define_from("c", "${a}", None).unwrap();
+
+        let mut inner = Scope::child(&scope);
+        inner.define_from("base", "${base}/app", None).unwrap();
+        assert_eq!(inner.lookup("base"), Some("/srv/app"));
+
+        let err = inner
+            .define_from("b", "${concat(c, \"!\")}", None)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(err.message, "`b` -> `c` -> `a` -> `b`");
+
+        let err = scope.define_from("self", "${self}", None).unwrap_err();
+        assert_eq!(err.message, "`self` -> `self`");
+        assert!(scope
+            .define_from("port", "${default(port, 80)}", None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_env_access() {
+        std::env::set_var("UPLANG_TEST_PORT", "9090");
+        let functions = Functions::default();
+        let sandboxed = Scope::root(&functions, false);
+        let allowed = Scope::root(&functions, true);
+
+        let text = "${default(env:UPLANG_TEST_PORT, 8080)} ${env:UPLANG_TEST_UNSET}";
+        assert_eq!(
+            sandboxed.interpolate(text).unwrap(),
+            "8080 ${env:UPLANG_TEST_UNSET}"
+        );
+        assert_eq!(
+            allowed.interpolate(text).unwrap(),
+            "9090 ${env:UPLANG_TEST_UNSET}"
+        );
+    }
+}
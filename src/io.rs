@@ -0,0 +1,180 @@
+//! Replacing files safely
+//!
+//! [`write_atomic`] replaces a file so that a crash or power loss leaves
+//! either the old contents or the new, never a truncated mix of the two.
+//! The `up` tool writes every file it edits in place this way.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Options for replacing a file, see [`write_atomic`]
+///
+/// ```
+/// use uplang::io::AtomicWrite;
+///
+/// let path = std::env::temp_dir().join(format!("uplang-doc-{}.up", std::process::id()));
+/// std::fs::write(&path, "port!int 80\n")?;
+/// AtomicWrite::new().backup(true).write(&path, "port!int 8080\n")?;
+/// assert_eq!(std::fs::read_to_string(&path)?, "port!int 8080\n");
+/// let backup = uplang::io::backup_path(&path);
+/// assert_eq!(std::fs::read_to_string(&backup)?, "port!int 80\n");
+/// # std::fs::remove_file(&path)?;
+/// # std::fs::remove_file(&backup)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AtomicWrite {
+    backup: bool,
+}
+
+impl AtomicWrite {
+    /// Replace files without keeping a backup
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the contents being replaced as a `.bak` file next to the file,
+    /// see [`backup_path`] (default off)
+    pub fn backup(mut self, keep: bool) -> Self {
+        self.backup = keep;
+        self
+    }
+
+    /// Replace the contents of `path`, or create it, in one step
+    ///
+    /// The contents are written to a temporary file in the same directory,
+    /// flushed to disk, and renamed over `path`, so readers see the old
+    /// file or the new one and nothing in between. The new file keeps the
+    /// permissions of the one it replaces. On failure `path` is left as it
+    /// was, and the temporary file is removed.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let path = path.as_ref();
+        let temp = temp_path(path)?;
+        let replaced = write_temp(&temp, path, contents.as_ref()).and_then(|()| {
+            if self.backup && path.exists() {
+                fs::copy(path, backup_path(path))?;
+            }
+            fs::rename(&temp, path)
+        });
+        if replaced.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        replaced?;
+        sync_dir(path);
+        Ok(())
+    }
+}
+
+/// Replace the contents of `path`, or create it, without ever leaving it
+/// half written; see [`AtomicWrite::write`]
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    AtomicWrite::new().write(path, contents)
+}
+
+/// Where [`AtomicWrite::backup`] keeps the previous contents of `path`:
+/// the same name with `.bak` added, so `app.up` is backed up to `app.up.bak`
+pub fn backup_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// A hidden file next to `path` that no other write is using
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        )
+    })?;
+    Ok(path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+/// Write the temporary file with the permissions of `path`, and flush it
+fn write_temp(temp: &Path, path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(temp)?;
+    file.write_all(contents)?;
+    match fs::metadata(path) {
+        Ok(metadata) => file.set_permissions(metadata.permissions())?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    file.sync_all()
+}
+
+/// Flush the directory holding `path`, so the rename itself survives a
+/// crash; not every platform can open a directory, so failures are ignored
+fn sync_dir(path: &Path) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("uplang-io-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.up");
+
+        write_atomic(&path, "name app\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "name app\n");
+        write_atomic(&path, "name new\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "name new\n");
+        assert!(!backup_path(&path).exists());
+
+        AtomicWrite::new()
+            .backup(true)
+            .write(&path, "name newer\n")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "name newer\n");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "name new\n"
+        );
+
+        // Only the file and its backup are left
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["app.up", "app.up.bak"]);
+
+        // A failed write leaves nothing behind
+        let missing = dir.join("missing").join("app.up");
+        assert!(write_atomic(&missing, "x").is_err());
+        assert!(write_atomic(&dir, "x").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("uplang-io-mode-{}.up", std::process::id()));
+        fs::write(&path, "secret s\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_atomic(&path, "secret t\n").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_file(&path).unwrap();
+    }
+}
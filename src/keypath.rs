@@ -0,0 +1,108 @@
+//! Key path syntax: `server.tls.cert`, `servers[2].host`
+
+use crate::Value;
+
+/// One step of a key path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    /// Block key or top-level node key
+    Key(&'a str),
+    /// List index
+    Index(usize),
+}
+
+/// Split a path into segments, or `None` if it is malformed
+pub(crate) fn parse(path: &str) -> Option<Vec<Segment<'_>>> {
+    split(path, false)
+}
+
+/// Like [`parse`], but also accept paths that start with an index, such as
+/// `[0].host`, for lookups that start from a list
+pub(crate) fn parse_relative(path: &str) -> Option<Vec<Segment<'_>>> {
+    split(path, true)
+}
+
+fn split(path: &str, leading_index: bool) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+
+    for part in path.split('.') {
+        let (key, mut rest) = match part.find('[') {
+            Some(i) => part.split_at(i),
+            None => (part, ""),
+        };
+        if key.is_empty() && ((segments.is_empty() && !leading_index) || rest.is_empty()) {
+            return None;
+        }
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+        while !rest.is_empty() {
+            let close = rest.find(']')?;
+            let index = rest[1..close].parse().ok()?;
+            segments.push(Segment::Index(index));
+            rest = &rest[close + 1..];
+            if !rest.is_empty() && !rest.starts_with('[') {
+                return None;
+            }
+        }
+    }
+
+    Some(segments)
+}
+
+/// Follow `path` down from `value` through blocks and lists
+pub(crate) fn lookup<'a>(value: &'a Value, path: &[Segment<'_>]) -> Option<&'a Value> {
+    match (path.split_first(), value) {
+        (None, value) => Some(value),
+        (Some((Segment::Key(key), rest)), Value::Block(block)) => lookup(block.get(key)?, rest),
+        (Some((Segment::Index(i), rest)), Value::List(items)) => lookup(items.get(*i)?, rest),
+        _ => None,
+    }
+}
+
+/// Follow `path` down from `value` for modification
+pub(crate) fn lookup_mut<'a>(value: &'a mut Value, path: &[Segment<'_>]) -> Option<&'a mut Value> {
+    match (path.split_first(), value) {
+        (None, value) => Some(value),
+        (Some((Segment::Key(key), rest)), Value::Block(block)) => {
+            lookup_mut(block.get_mut(key)?, rest)
+        }
+        (Some((Segment::Index(i), rest)), Value::List(items)) => {
+            lookup_mut(items.get_mut(*i)?, rest)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths() {
+        assert_eq!(
+            parse("servers[2].host"),
+            Some(vec![
+                Segment::Key("servers"),
+                Segment::Index(2),
+                Segment::Key("host")
+            ])
+        );
+        assert_eq!(
+            parse("matrix[0][1]"),
+            Some(vec![
+                Segment::Key("matrix"),
+                Segment::Index(0),
+                Segment::Index(1)
+            ])
+        );
+        for bad in ["", "a..b", "a[x]", "a[1", "a[1]b", "[0]"] {
+            assert_eq!(parse(bad), None, "accepted {:?}", bad);
+        }
+        assert_eq!(
+            parse_relative("[0].host"),
+            Some(vec![Segment::Index(0), Segment::Key("host")])
+        );
+        assert_eq!(parse_relative(".host"), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! Telling what each line of a document is, see [`Parser::lexer`]
+//!
+//! The [`Lexer`] reads the input a line at a time and classifies each line
+//! as it goes, with one scan of its bytes: blank, a comment, a line that
+//! only closes something, a directive, or code for the parser to read as a
+//! node or a list item. Whitespace is skipped a byte at a time, and nothing
+//! is copied: lines and their parts are slices of the input. Multiline
+//! string bodies are read as they are, without being classified.
+//!
+//! Both the tree parser and [`Parser::events`] read their input through
+//! it.
+
+use crate::comment::Comments;
+use crate::error::Location;
+use crate::include::include_directive;
+use crate::lines::Cursor;
+use crate::quote;
+use crate::{let_definition, strictness_directive, ParseError, Parser};
+
+/// What a line of a document is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Nothing but whitespace
+    Blank,
+    /// A comment on a line of its own, with the text after its comment
+    /// character
+    Comment(&'a str),
+    /// A line that only closes a block, a list, or a multiline string
+    Close(Close),
+    /// `@let`, with the definition after it
+    Let(&'a str),
+    /// `@include`, with the path after it
+    Include(&'a str),
+    /// `@strict` (`true`) or `@lenient` (`false`)
+    Strictness(bool),
+    /// Anything else: a node, or an item in a list
+    Code,
+}
+
+/// What a [`Token::Close`] line closes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Close {
+    /// `}`
+    Block,
+    /// `]`
+    List,
+    /// A closing fence
+    Fence,
+}
+
+/// A line read by the [`Lexer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// 0-based line number
+    pub number: usize,
+    /// The whole line, without its line ending
+    pub text: &'a str,
+    /// The line without the whitespace around it or a trailing comment
+    pub code: &'a str,
+    /// What the line is
+    pub token: Token<'a>,
+}
+
+impl<'a> Line<'a> {
+    /// Classify `text`, the `number`th line of a document
+    pub(crate) fn new(number: usize, text: &'a str, comments: &Comments) -> Self {
+        let trimmed = trim(text);
+        let (code, token) = if trimmed.is_empty() {
+            (trimmed, Token::Blank)
+        } else if let Some(comment) = comments.text(trimmed) {
+            (trimmed, Token::Comment(comment))
+        } else {
+            let code = comments.strip(trimmed);
+            (code, token(code))
+        };
+        Self {
+            number,
+            text,
+            code,
+            token,
+        }
+    }
+
+    /// The line up to the end of its code, with its indentation
+    pub fn indented(&self) -> &'a str {
+        let start = self.code.as_ptr() as usize - self.text.as_ptr() as usize;
+        &self.text[..start + self.code.len()]
+    }
+
+    /// The text of a comment line, `None` for other lines
+    pub fn comment(&self) -> Option<&'a str> {
+        match self.token {
+            Token::Comment(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Whether the line is blank or a comment
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.token, Token::Blank | Token::Comment(_))
+    }
+}
+
+impl Parser {
+    /// Read `input` a line at a time, telling what each line is as the
+    /// parser does, with its comment characters
+    ///
+    /// The lexer only tells lines apart; reading a node's key and value,
+    /// and the values it opens, is left to the caller. The body of a
+    /// multiline string is read with [`Lexer::raw`], which does not
+    /// classify it.
+    ///
+    /// ```
+    /// use uplang::{Close, Parser, Token};
+    ///
+    /// let parser = Parser::new();
+    /// let tokens: Vec<Token> = parser
+    ///     .lexer("# settings\nserver {\n  port 80\n}\n")
+    ///     .map(|line| line.token)
+    ///     .collect();
+    /// assert_eq!(
+    ///     tokens,
+    ///     [
+    ///         Token::Comment("settings"),
+    ///         Token::Code,
+    ///         Token::Code,
+    ///         Token::Close(Close::Block),
+    ///     ]
+    /// );
+    /// ```
+    pub fn lexer<'a>(&self, input: &'a str) -> Lexer<'a, '_> {
+        Lexer::new(input, &self.comments)
+    }
+}
+
+/// The token of a line of code
+fn token(code: &str) -> Token<'_> {
+    match code.as_bytes() {
+        b"}" => Token::Close(Close::Block),
+        b"]" => Token::Close(Close::List),
+        b"```" => Token::Close(Close::Fence),
+        [b'@', ..] => {
+            if let Some(definition) = let_definition(code) {
+                Token::Let(definition)
+            } else if let Some(path) = include_directive(code) {
+                Token::Include(path)
+            } else if let Some(strict) = strictness_directive(code) {
+                Token::Strictness(strict)
+            } else {
+                Token::Code
+            }
+        }
+        _ => Token::Code,
+    }
+}
+
+/// `text` without the whitespace around it, as [`str::trim`] strips it
+///
+/// ASCII whitespace is skipped a byte at a time; only a non-ASCII
+/// character at either end is decoded.
+pub(crate) fn trim(text: &str) -> &str {
+    let bytes = text.as_bytes();
+    let Some(start) = bytes.iter().position(|&b| !is_space(b)) else {
+        return "";
+    };
+    let end = bytes
+        .iter()
+        .rposition(|&b| !is_space(b))
+        .map_or(start, |i| i + 1);
+    let trimmed = &text[start..end];
+    if bytes[start].is_ascii() && bytes[end - 1].is_ascii() {
+        trimmed
+    } else {
+        trimmed.trim()
+    }
+}
+
+/// Split the line of a node at the first whitespace after its key, into
+/// the key and the value, without the whitespace around them
+///
+/// Whitespace inside a quoted key does not end it. A line with no
+/// whitespace is all key.
+pub(crate) fn split_entry(line: &str) -> (&str, &str) {
+    let bytes = line.as_bytes();
+    let mut i = quote::quoted_len(line).unwrap_or(0);
+    while let Some(&b) = bytes.get(i) {
+        let width = if b.is_ascii() {
+            if is_space(b) {
+                break;
+            }
+            1
+        } else {
+            let c = line[i..].chars().next().expect("not at the end");
+            if c.is_whitespace() {
+                break;
+            }
+            c.len_utf8()
+        };
+        i += width;
+    }
+    if i == bytes.len() {
+        return (line, "");
+    }
+    (trim(&line[..i]), trim(&line[i..]))
+}
+
+/// Whether `b` is an ASCII character that [`char::is_whitespace`] accepts
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// The lines of a document, classified as they are read, see
+/// [`Parser::lexer`]
+#[derive(Debug, Clone)]
+pub struct Lexer<'a, 'c> {
+    lines: Cursor<'a>,
+    comments: &'c Comments,
+}
+
+impl<'a, 'c> Lexer<'a, 'c> {
+    /// A lexer at the first line of `input`, for a parser that writes
+    /// comments as `comments` says
+    pub(crate) fn new(input: &'a str, comments: &'c Comments) -> Self {
+        Self {
+            lines: Cursor::new(input),
+            comments,
+        }
+    }
+
+    /// The next line as it is, for the body of a multiline string
+    pub fn raw(&mut self) -> Option<(usize, &'a str)> {
+        self.lines.next()
+    }
+
+    /// The next line as it is, without moving past it
+    pub fn peek(&self) -> Option<(usize, &'a str)> {
+        self.lines.peek()
+    }
+
+    /// Move past the lines up to and including line `end`
+    pub(crate) fn skip_through(&mut self, end: usize) {
+        while self
+            .lines
+            .next_if(|(line_num, _)| *line_num <= end)
+            .is_some()
+        {}
+    }
+
+    /// Take the lines of a fenced multiline value up to its closing fence,
+    /// and whether the fence was closed
+    pub(crate) fn fenced(&mut self) -> (Vec<(usize, &'a str)>, bool) {
+        let mut body = Vec::new();
+        while let Some((line_num, line)) = self.raw() {
+            if trim(line) == "```" {
+                return (body, true);
+            }
+            body.push((line_num, line));
+        }
+        (body, false)
+    }
+
+    /// 1-based number of the line holding `text`, see [`Cursor::line_of`]
+    pub(crate) fn line_of(&self, text: &str) -> Option<usize> {
+        self.lines.line_of(text)
+    }
+
+    /// Location of `text`, if it is a slice of the input
+    pub(crate) fn location(&self, text: &str) -> Option<Location> {
+        self.lines.location(text)
+    }
+
+    /// Point `error` at `text`, as [`ParseError::locate`] does
+    pub(crate) fn locate(&self, error: ParseError, text: &str) -> ParseError {
+        self.lines.locate(error, text)
+    }
+}
+
+impl<'a> Iterator for Lexer<'a, '_> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (number, text) = self.lines.next()?;
+        Some(Line::new(number, text, self.comments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_matches_str_trim() {
+        for text in [
+            "",
+            "   ",
+            "a",
+            "  a b \t",
+            "\u{3000}a\u{a0}",
+            " \u{2003} a",
+            "é ",
+            "\x0ba\x0c",
+        ] {
+            assert_eq!(trim(text), text.trim(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        let comments = Comments::default().trailing(true);
+        let input = "a 1\n\n  # note\nb { # open\n  }\n]\n```\n@let x 1\n@include a.up\n@strict\n@lenient\n@other\n";
+        let tokens: Vec<(&str, Token)> = Lexer::new(input, &comments)
+            .map(|line| (line.code, line.token))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                ("a 1", Token::Code),
+                ("", Token::Blank),
+                ("# note", Token::Comment("note")),
+                ("b {", Token::Code),
+                ("}", Token::Close(Close::Block)),
+                ("]", Token::Close(Close::List)),
+                ("```", Token::Close(Close::Fence)),
+                ("@let x 1", Token::Let("x 1")),
+                ("@include a.up", Token::Include("a.up")),
+                ("@strict", Token::Strictness(true)),
+                ("@lenient", Token::Strictness(false)),
+                ("@other", Token::Code),
+            ]
+        );
+
+        let line = Lexer::new("  key value # c", &comments).next().unwrap();
+        assert_eq!(line.indented(), "  key value");
+        assert!(!line.is_trivia());
+    }
+
+    #[test]
+    fn test_raw_lines() {
+        let comments = Comments::default();
+        let mut lexer = Lexer::new("  # not a comment\n  body\n```\nafter", &comments);
+        assert_eq!(
+            lexer.fenced(),
+            (vec![(0, "  # not a comment"), (1, "  body")], true)
+        );
+        assert_eq!(lexer.peek(), Some((3, "after")));
+        lexer.skip_through(3);
+        assert_eq!(lexer.next(), None);
+    }
+}
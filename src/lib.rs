@@ -14,13 +14,160 @@
 //! let doc = parse(input).unwrap();
 //! ```
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::comment::Comments;
+use crate::descent::Reader;
+use crate::include::Includes;
+use crate::interpolate::{Functions, Scope};
+
+mod anchor;
+mod ast;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+mod audience;
+#[cfg(feature = "background")]
+pub mod background;
+mod block;
+mod borrowed;
+#[cfg(feature = "cache")]
+mod cache;
+mod canonical;
+mod comment;
+mod convert;
+mod descent;
+mod diff;
+mod display;
+mod dotted;
+mod duplicates;
+mod edit;
+mod error;
+mod evaluate;
+mod events;
+#[cfg(feature = "follow")]
+mod follow;
+mod format;
+mod from_up;
+pub mod graph;
+mod handle;
+mod hash;
+mod include;
+mod index;
+mod ini;
+mod inline;
+mod interpolate;
+pub mod io;
+mod keypath;
+mod lexer;
+mod limits;
+mod lines;
+pub mod lint;
+mod loader;
+mod locale;
+mod macros;
+mod merge;
+mod migrate;
+pub mod normalize;
+mod outline;
+mod patch;
+mod persistent;
+mod platform;
+pub mod policy;
+mod position;
+mod query;
+mod quote;
+mod recovery;
+mod redact;
+mod reference;
+pub mod regression;
+pub mod schema;
+mod search;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod sniff;
+mod source;
+mod stats;
+mod stream;
+mod substitute;
+mod suggest;
+mod table;
+mod text;
+mod trivia;
+pub mod types;
+mod vars;
+mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod writer;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+pub use ast::{AstSink, NodeHeader, Scalar};
+pub use audience::Audience;
+pub use block::{Block, DuplicateKeys};
+pub use borrowed::{DocumentRef, NodeRef, ValueRef};
+pub use diff::{diff, Change, Differ};
+pub use display::Truncated;
+pub use duplicates::Duplicate;
+pub use edit::DocumentBuilder;
+pub use error::{ErrorKind, Location, ParseError};
+pub use evaluate::{evaluate, EvalDiagnostic, EvalMetrics, EvalOptions, Evaluation, Stage};
+pub use events::{Event, EventReader};
+#[cfg(feature = "follow")]
+pub use follow::Tail;
+pub use format::format;
+#[doc(hidden)]
+pub use from_up::__private;
+pub use from_up::{FromUp, FromUpError, FromUpErrors};
+pub use handle::ConfigHandle;
+pub use include::{DenyIncludes, FileResolver, IncludeResolver, Included, MemoryResolver};
+pub use index::DocumentIndex;
+pub use interpolate::Function;
+pub use lexer::{Close, Lexer, Line, Token};
+pub use limits::Limits;
+pub use loader::{ChangeSet, ConfigError, ConfigLoader, ConfigWatcher};
+pub use locale::{DateFormat, Locale};
+pub use merge::{MergeStrategy, Provenance};
+pub use migrate::{Migration, MigrationError, MigrationSteps, Step};
+pub use outline::{folding_ranges, FoldKind, FoldingRange, OutlineItem};
+pub use patch::{Op, Patch, PatchError};
+pub use persistent::PersistentDocument;
+pub use position::NodeAt;
+pub use recovery::{Diagnostic, ParseOutput};
+pub use redact::Redacted;
+pub use reference::{Reference, ReferenceEntry};
+pub use search::Match;
+pub use sniff::{sniff, Confidence, Lookalike};
+pub use source::{Origin, Span};
+pub use stats::Stats;
+pub use stream::{Nodes, Records, Rows};
+pub use table::{Row, Table};
+pub use trivia::Trivia;
+pub use types::{
+    Annotation, AnnotationCatalog, Color, CustomValue, Date, GeoPoint, MediaType, Money, PathCheck,
+    Shape, Time, Timestamp, TypeHandler, TypeRegistry,
+};
+#[cfg(feature = "derive")]
+pub use uplang_derive::FromUp;
+pub use writer::{to_string, Writer};
+
+#[cfg(feature = "semver")]
+pub use semver;
 
 /// Parse UP document from a string (convenience function)
 pub fn parse(input: &str) -> Result<Document, ParseError> {
     Parser::new().parse_document(input)
 }
 
+/// Parse UP document from bytes (convenience function), see
+/// [`Parser::parse_bytes`]
+pub fn parse_bytes(input: &[u8]) -> Result<Document, ParseError> {
+    Parser::new().parse_bytes(input)
+}
+
 /// Represents a parsed UP document
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Document {
@@ -38,10 +185,33 @@ impl Document {
     pub fn is_empty(&self) -> bool {
         self.nodes.is_empty()
     }
+
+    /// Check whether a top-level key is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.nodes.iter().any(|node| node.key == key)
+    }
+
+    /// Iterate over top-level keys and values in order, repeated keys
+    /// included
+    pub fn iter(&self) -> block::Iter<'_> {
+        block::Iter::new(&self.nodes)
+    }
+}
+
+impl<'a> IntoIterator for &'a Document {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = block::Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 /// A key-value node with optional type annotation
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Equality compares the key, annotation, and value only; source metadata
+/// such as [`origin`](Self::origin) is ignored.
+#[derive(Debug, Clone)]
 pub struct Node {
     /// The key name
     pub key: String,
@@ -49,6 +219,58 @@ pub struct Node {
     pub type_annotation: Option<String>,
     /// The value
     pub value: Value,
+    /// Where the node was defined, when parsed from text
+    pub origin: Option<Origin>,
+    /// Lines and bytes of the node's source text, when parsed with
+    /// [`Parser::spans`]
+    pub span: Option<Span>,
+    /// Text of the `#` comment lines directly above the key, without the `#`
+    pub doc: Option<String>,
+    /// Language tag of a fenced multiline value, such as `sql` for a value
+    /// opened with ```` ```sql ````
+    pub lang: Option<String>,
+    /// For a node written as `key!ref name`, the name of the anchor its
+    /// value was copied from
+    pub anchor: Option<String>,
+    /// Every definition of this node across tracked overlays, oldest first
+    ///
+    /// Empty unless the document was built with
+    /// [`Document::overlay_tracked`].
+    pub provenance: Vec<Provenance>,
+    /// What a node built in code was generated from, such as
+    /// `CLI flag --port`, written as a `# from ...` comment above it so
+    /// readers of generated documents can trace each value
+    pub generated_from: Option<String>,
+    /// Source text and surrounding comments, when parsed with
+    /// [`Parser::preserve_trivia`]
+    pub trivia: Option<Box<Trivia>>,
+}
+
+impl Node {
+    /// Create an unannotated node
+    pub fn new(key: impl Into<String>, value: Value) -> Self {
+        Self {
+            key: key.into(),
+            type_annotation: None,
+            value,
+            origin: None,
+            span: None,
+            doc: None,
+            lang: None,
+            anchor: None,
+            provenance: Vec::new(),
+            generated_from: None,
+            trivia: None,
+        }
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.type_annotation == other.type_annotation
+            && self.value == other.value
+    }
 }
 
 /// Represents any UP value
@@ -56,8 +278,8 @@ pub struct Node {
 pub enum Value {
     /// String scalar
     String(String),
-    /// Block (nested key-value pairs)
-    Block(HashMap<String, Value>),
+    /// Block (nested key-value pairs, in source order)
+    Block(Block),
     /// List of values
     List(Vec<Value>),
     /// Table with columns and rows
@@ -65,98 +287,558 @@ pub enum Value {
         columns: Vec<Value>,
         rows: Vec<Vec<Value>>,
     },
+    /// Integer (`!int`)
+    Int(i64),
+    /// Floating-point number (`!float`)
+    Float(f64),
+    /// Boolean (`!bool`)
+    Bool(bool),
+    /// Exact monetary amount (`!money`)
+    Money(Money),
+    /// RGBA color (`!color`)
+    Color(Color),
+    /// Latitude/longitude pair (`!geo`)
+    Geo(GeoPoint),
+    /// Platform-normalized filesystem path (`!path`)
+    Path(PathBuf),
+    /// Validated media type (`!mime`)
+    Mime(MediaType),
+    /// Calendar date (`!date`)
+    Date(Date),
+    /// Time of day (`!time`)
+    Time(Time),
+    /// Date and time with a UTC offset (`!ts`)
+    Timestamp(Timestamp),
+    /// Span of time (`!dur`)
+    Duration(std::time::Duration),
+    /// Binary data (`!bytes` or `!base64`)
+    Bytes(Vec<u8>),
+    /// Scalar converted by an application's [`TypeHandler`], or by a type
+    /// of an optional feature, such as `!semver`
+    Custom(CustomValue),
+    /// Explicit null (`key!null`)
+    Null,
+    /// Key written without a value (`debug` alone on a line), see
+    /// [`Parser::flag_keys`]
+    Empty,
 }
 
-/// Parse errors
-#[derive(Debug, Clone, PartialEq)]
-pub enum ParseError {
-    /// Invalid syntax
-    InvalidSyntax(String),
-    /// Unexpected end of input
-    UnexpectedEof,
-    /// Invalid list format
-    InvalidList(String),
-}
+impl Value {
+    /// Name of the value's type, matching its annotation where there is one
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Block(_) => "block",
+            Value::List(_) => "list",
+            Value::Table { .. } => "table",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Money(_) => "money",
+            Value::Color(_) => "color",
+            Value::Geo(_) => "geo",
+            Value::Path(_) => "path",
+            Value::Mime(_) => "mime",
+            Value::Date(_) => "date",
+            Value::Time(_) => "time",
+            Value::Timestamp(_) => "ts",
+            Value::Duration(_) => "dur",
+            Value::Bytes(_) => "bytes",
+            Value::Custom(_) => "custom",
+            Value::Null => "null",
+            Value::Empty => "empty",
+        }
+    }
+
+    /// Text of a scalar value as it would be written in UP
+    ///
+    /// `None` for blocks, lists, and tables.
+    pub fn scalar_text(&self) -> Option<Cow<'_, str>> {
+        Some(match self {
+            Value::String(s) => Cow::Borrowed(s.as_str()),
+            Value::Block(_) | Value::List(_) | Value::Table { .. } => return None,
+            Value::Int(n) => Cow::Owned(n.to_string()),
+            Value::Float(x) => Cow::Owned(x.to_string()),
+            Value::Bool(b) => Cow::Borrowed(if *b { "true" } else { "false" }),
+            Value::Money(money) => Cow::Owned(money.to_string()),
+            Value::Color(color) => Cow::Owned(color.to_string()),
+            Value::Geo(point) => Cow::Owned(point.to_string()),
+            Value::Path(path) => path.to_string_lossy(),
+            Value::Mime(mime) => Cow::Owned(mime.to_string()),
+            Value::Date(date) => Cow::Owned(date.to_string()),
+            Value::Time(time) => Cow::Owned(time.to_string()),
+            Value::Timestamp(ts) => Cow::Owned(ts.to_string()),
+            Value::Duration(duration) => Cow::Owned(types::format_duration(*duration)),
+            Value::Bytes(bytes) => Cow::Owned(types::encode_base64(bytes)),
+            Value::Custom(custom) => Cow::Borrowed(custom.text()),
+            Value::Null | Value::Empty => Cow::Borrowed(""),
+        })
+    }
+
+    /// Check for an explicit null (`key!null`)
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Check for a present but empty value: a bare key, an empty list or block
+    ///
+    /// Null is not considered empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(s) => s.is_empty(),
+            Value::Empty => true,
+            Value::Block(block) => block.is_empty(),
+            Value::List(list) => list.is_empty(),
+            Value::Table { rows, .. } => rows.is_empty(),
+            _ => false,
+        }
+    }
 
-impl std::fmt::Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Read a string value as a byte stream
+    ///
+    /// Lets very large embedded values be copied straight to a file or socket
+    /// with [`std::io::copy`] instead of being cloned first. Returns `None` for
+    /// values that are not strings.
+    pub fn reader(&self) -> Option<impl std::io::Read + '_> {
         match self {
-            ParseError::InvalidSyntax(msg) => write!(f, "Invalid syntax: {}", msg),
-            ParseError::UnexpectedEof => write!(f, "Unexpected end of input"),
-            ParseError::InvalidList(msg) => write!(f, "Invalid list: {}", msg),
+            Value::String(s) => Some(s.as_bytes()),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for ParseError {}
+/// Outcome of [`Parser::parse_partial`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialDocument {
+    /// Every node that was completely parsed before `error`
+    pub document: Document,
+    /// Why parsing stopped early, or `None` if the whole input parsed
+    pub error: Option<ParseError>,
+}
+
+impl PartialDocument {
+    /// Check whether the whole input was parsed
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+}
 
 /// UP document parser with configurable behavior
-pub struct Parser;
+#[derive(Debug, Clone)]
+pub struct Parser {
+    path_check: PathCheck,
+    list_separator: char,
+    coerce_types: bool,
+    eof_is_error: bool,
+    functions: Functions,
+    env_access: bool,
+    preserve_trivia: bool,
+    spans: bool,
+    duplicate_keys: DuplicateKeys,
+    strict: bool,
+    includes: Includes,
+    interpolation: bool,
+    types: TypeRegistry,
+    dotted_keys: bool,
+    flag_keys: bool,
+    limits: Limits,
+    comments: Comments,
+    normalizers: normalize::Normalizers,
+    recovery: Option<recovery::Diagnostics>,
+}
 
 impl Parser {
     /// Create a new parser with default configuration
     pub fn new() -> Self {
-        Self
+        Self {
+            path_check: PathCheck::None,
+            list_separator: ',',
+            coerce_types: true,
+            eof_is_error: false,
+            functions: Functions::default(),
+            env_access: false,
+            preserve_trivia: false,
+            spans: false,
+            duplicate_keys: DuplicateKeys::LastWins,
+            strict: false,
+            includes: Includes::default(),
+            interpolation: false,
+            types: TypeRegistry::new(),
+            dotted_keys: false,
+            flag_keys: false,
+            limits: Limits::new(),
+            comments: Comments::default(),
+            normalizers: normalize::Normalizers::default(),
+            recovery: None,
+        }
     }
 
-    /// Parse a UP document from a string
-    pub fn parse_document(&self, input: &str) -> Result<Document, ParseError> {
-        let lines: Vec<&str> = input.lines().collect();
-        let mut line_iter = lines.iter().enumerate().peekable();
-        let mut nodes = Vec::new();
+    /// Set the item separator for inline lists (default `,`)
+    ///
+    /// A separator preceded by a backslash is kept as part of the item, so
+    /// `[a\, b, c]` holds the two items `a, b` and `c`.
+    pub fn list_separator(mut self, separator: char) -> Self {
+        self.list_separator = separator;
+        self
+    }
+
+    /// Set the characters that start a comment (default `#`)
+    ///
+    /// A line whose first non-blank character is one of them is a comment,
+    /// so `"#;"` also takes INI-style `;` comments. With
+    /// [`trailing_comments`](Self::trailing_comments), they also start a
+    /// comment after a value.
+    pub fn comment_chars(mut self, chars: &str) -> Self {
+        self.comments = self.comments.chars(chars);
+        self
+    }
 
-        while let Some((line_num, line)) = line_iter.next() {
-            let trimmed = line.trim();
+    /// Allow a comment after the value on a line, as in
+    /// `port!int 8080  # public port` (default off)
+    ///
+    /// A comment character starts a trailing comment when whitespace comes
+    /// before it and it is outside double quotes, so `url http://h/#top`
+    /// keeps its fragment but `color #fff` needs quotes to keep its color.
+    /// The bodies of multiline strings are never searched for comments.
+    /// Trailing comments are dropped rather than kept as doc comments.
+    ///
+    /// ```
+    /// let doc = uplang::Parser::new()
+    ///     .trailing_comments(true)
+    ///     .parse_document("port!int 8080  # public port\nname \"a # b\" # quoted")
+    ///     .unwrap();
+    /// assert_eq!(doc.get_int("port"), Some(8080));
+    /// assert_eq!(doc.get_str("name"), Some("a # b"));
+    /// ```
+    pub fn trailing_comments(mut self, enabled: bool) -> Self {
+        self.comments = self.comments.trailing(enabled);
+        self
+    }
 
-            // Skip empty lines and comments
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
+    /// Also read `//` as the start of a comment, as in JSON5 and HCL
+    /// (default off)
+    ///
+    /// Without it, `// note` is a key `//` with the value `note`, which
+    /// goes unnoticed until the value is missed; the linter's
+    /// `slash-comment` rule points such lines out either way, suggesting
+    /// `#`. With [`trailing_comments`](Self::trailing_comments), `//` after
+    /// whitespace ends a line too, so `url http://h/a` keeps its value.
+    ///
+    /// ```
+    /// let doc = uplang::Parser::new()
+    ///     .slash_comments(true)
+    ///     .parse_document("// Service settings\nname app\n")
+    ///     .unwrap();
+    /// assert_eq!(doc.nodes.len(), 1);
+    /// assert_eq!(doc.nodes[0].doc.as_deref(), Some("Service settings"));
+    /// ```
+    pub fn slash_comments(mut self, enabled: bool) -> Self {
+        self.comments = self.comments.slashes(enabled);
+        self
+    }
 
-            match self.parse_line(&mut line_iter, line, line_num) {
-                Ok(node) => nodes.push(node),
-                Err(e) => {
-                    return Err(ParseError::InvalidSyntax(format!(
-                        "line {}: {}",
-                        line_num + 1,
-                        e
-                    )))
-                }
-            }
-        }
+    /// Turn annotated scalars into typed values (default `true`)
+    ///
+    /// With coercion on, `age!int 30` parses to [`Value::Int`] and a scalar
+    /// that does not fit its annotation is an error. Turning it off keeps
+    /// every scalar a [`Value::String`], as earlier versions of this crate
+    /// did; annotations are still recorded on the node.
+    pub fn coerce_types(mut self, coerce: bool) -> Self {
+        self.coerce_types = coerce;
+        self
+    }
+
+    /// Check `!path` values against the filesystem while parsing
+    ///
+    /// Paths that fail the check produce an [`ErrorKind::InvalidValue`]
+    /// error pointing at the offending value. A relative path is checked
+    /// against the directory of the file it is written in, as
+    /// [`parse_file`](Self::parse_file) and `@include` read it, and against
+    /// the current directory in text that was not read from a file, such as
+    /// with [`parse_document`](Self::parse_document). The value keeps the
+    /// path as written.
+    pub fn check_paths(mut self, check: PathCheck) -> Self {
+        self.path_check = check;
+        self
+    }
+
+    /// Make a custom function available in `${...}` interpolation
+    ///
+    /// Built-in functions are `upper`, `lower`, `trim`, `replace(text,
+    /// from, to)`, `concat(...)`, and `default(value, fallback)`, which
+    /// yields `fallback` when `value` is undefined. A custom function with
+    /// the same name replaces the built-in one.
+    pub fn function(mut self, name: impl Into<String>, function: impl Function + 'static) -> Self {
+        self.functions.insert(name.into(), Arc::new(function));
+        self
+    }
+
+    /// Convert scalars with custom annotations using `types`
+    ///
+    /// Handlers take precedence over the built-in annotations of the same
+    /// name; see [`TypeRegistry`].
+    pub fn type_registry(mut self, types: TypeRegistry) -> Self {
+        self.types = types;
+        self
+    }
+
+    /// Clean up the text of scalar values with `normalizer` before
+    /// converting them, after any normalizers added before it
+    ///
+    /// See [`normalize`] for the built-in normalizers.
+    pub fn normalizer(mut self, normalizer: impl normalize::Normalizer + 'static) -> Self {
+        self.normalizers.push(Arc::new(normalizer));
+        self
+    }
+
+    /// Let `${env:NAME}` read environment variables (default `false`)
+    ///
+    /// Interpolation is sandboxed by default: `env:` references count as
+    /// undefined, so they are left as written or fall back through
+    /// `default(env:PORT, 8080)`.
+    pub fn env_access(mut self, allow: bool) -> Self {
+        self.env_access = allow;
+        self
+    }
+
+    /// Keep each node's source text, comments, and blank lines (default
+    /// `false`)
+    ///
+    /// The text is stored in [`Node::trivia`], so that writing the document
+    /// back reproduces the input byte for byte, and an edited document
+    /// differs only where it was changed. Text after the last node is kept
+    /// with that node; comments in a document or block without nodes, and
+    /// inside `!up` values, are not kept.
+    pub fn preserve_trivia(mut self, preserve: bool) -> Self {
+        self.preserve_trivia = preserve;
+        self
+    }
+
+    /// Record where each node is in the input (default `false`)
+    ///
+    /// The lines and byte offsets of every node's text, from its key to
+    /// its last line, are stored in [`Node::span`], for linters, editors,
+    /// and other tools that point back into the source. Nodes of an
+    /// included file are located in that file; nodes inside `!up` values
+    /// have no span.
+    ///
+    /// ```
+    /// let input = "name app\nserver {\n  port!int 80\n}\n";
+    /// let doc = uplang::Parser::new().spans(true).parse_document(input).unwrap();
+    /// let span = doc.nodes[1].span.as_ref().unwrap();
+    /// assert_eq!((span.start_line, span.end_line), (2, 4));
+    /// assert_eq!(&input[span.bytes.clone()], "server {\n  port!int 80\n}");
+    /// ```
+    pub fn spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Choose what happens when a block defines a key twice (default
+    /// [`DuplicateKeys::LastWins`])
+    ///
+    /// Keys repeated at the top level of a document are kept as separate
+    /// nodes; see [`Document::duplicates`] to find them.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Check that delimiters are balanced (default `false`)
+    ///
+    /// A block, list, table, or multiline string still open at the end of
+    /// the input is an [`ErrorKind::UnclosedDelimiter`] error located at its
+    /// opening line, instead of ending quietly. A closing `}`, `]`, or
+    /// `` ``` `` line with nothing of its kind open is an
+    /// [`ErrorKind::InvalidSyntax`] error, instead of being read as a key.
+    ///
+    /// A document can override this for the rest of a block, and the blocks
+    /// in it, with a `@strict` or `@lenient` line, such as a section of
+    /// generated content that a strict parser should not reject:
+    ///
+    /// ```
+    /// use uplang::Parser;
+    ///
+    /// let input = "vendor {\n  @lenient\n  ]\n  name x\n}\n";
+    /// assert!(Parser::new().strict(true).parse_document(input).is_ok());
+    /// assert!(Parser::new().parse_document("a {\n  @strict\n  ]\n}\n").is_err());
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Expand dotted top-level keys into nested blocks (default `false`)
+    ///
+    /// `server.tls.cert /etc/cert.pem` then sets `cert` in the block `tls`
+    /// in the block `server`, as in TOML. Missing blocks are created, and
+    /// existing ones, whether written out or created by another dotted key,
+    /// are added to. A path through a key that holds something other than a
+    /// block is an [`ErrorKind::DuplicateKey`] error, and a leaf key set
+    /// twice follows [`duplicate_keys`](Self::duplicate_keys).
+    pub fn dotted_keys(mut self, expand: bool) -> Self {
+        self.dotted_keys = expand;
+        self
+    }
+
+    /// Read keys written without a value as boolean flags (default `false`)
+    ///
+    /// A line with only a key, such as `verbose`, is then `true`, as if
+    /// written `verbose!bool true`. Otherwise it is [`Value::Empty`], which
+    /// reads as `""` with [`Value::as_str`] but is written back as a bare
+    /// key, unlike a quoted `""`.
+    ///
+    /// ```
+    /// use uplang::{Parser, Value};
+    ///
+    /// let input = "verbose\nname \"\"\n";
+    /// let doc = Parser::new().flag_keys(true).parse_document(input).unwrap();
+    /// assert_eq!(doc.get_bool("verbose"), Some(true));
+    /// assert_eq!(doc.get_str("name"), Some(""));
+    ///
+    /// let doc = Parser::new().parse_document(input).unwrap();
+    /// assert_eq!(doc.get("verbose"), Some(&Value::Empty));
+    /// assert_eq!(uplang::to_string(&doc), input);
+    /// ```
+    pub fn flag_keys(mut self, flags: bool) -> Self {
+        self.flag_keys = flags;
+        self
+    }
+
+    /// Bound the size, nesting, list lengths, and key lengths of documents
+    /// (default unlimited)
+    ///
+    /// Set limits before parsing input from untrusted sources, so that a
+    /// huge or deeply nested document fails with
+    /// [`ErrorKind::LimitExceeded`] instead of using up memory or stack.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Allow `@include path` lines, finding the documents they name with
+    /// `resolver`
+    ///
+    /// The nodes of the included document take the place of the `@include`
+    /// line, at the top level or inside a block, and keep the file and line
+    /// they were defined at in [`Node::origin`]. The included document is
+    /// parsed with a fresh scope, so it cannot see `@let` variables of the
+    /// document including it. Includes are denied by default: without a
+    /// resolver an `@include` line is an [`ErrorKind::InvalidValue`] error,
+    /// and a document that includes itself, directly or not, is an
+    /// [`ErrorKind::ReferenceCycle`] error.
+    ///
+    /// With the `parallel` feature, a document with several `@include`
+    /// lines has them resolved and parsed concurrently before its own text
+    /// is read; the resolver may then be asked for a document that is never
+    /// reached, for instance when an earlier line fails to parse.
+    pub fn include_resolver(mut self, resolver: impl IncludeResolver + 'static) -> Self {
+        self.includes.set_resolver(Arc::new(resolver));
+        self
+    }
 
+    /// Expand `${...}` references left in string values once the whole
+    /// document is parsed (default `false`)
+    ///
+    /// `@let` variables are expanded while parsing as usual; a reference
+    /// that is still in the text afterwards names a key path of the
+    /// document, such as `${server.host}`, or is an expression over key
+    /// paths, such as `${port + 1}` or `${upper(name)}`, written as for
+    /// `@let` variables. With [`env_access`](Self::env_access), a name
+    /// that is not a key reads the environment variable of that name, such
+    /// as `${HOME}`, as `${env:NAME}` does. Keys may refer to keys defined
+    /// after them. Write `$${` for a literal `${`. A reference to nothing
+    /// is an [`ErrorKind::UndefinedReference`] error, one that is not a
+    /// valid expression is an [`ErrorKind::InvalidSyntax`] error, and keys
+    /// that refer to each other in a loop are an
+    /// [`ErrorKind::ReferenceCycle`] error. Annotations are applied after
+    /// expansion, so `port!int ${PORT}` is an integer.
+    ///
+    /// References are resolved within each parsed document: files loaded
+    /// with [`parse_files`](Self::parse_files) cannot refer to each other.
+    ///
+    /// ```
+    /// use uplang::Parser;
+    ///
+    /// let parser = Parser::new().with_interpolation(true);
+    /// let doc = parser
+    ///     .parse_document("url http://${server.host}:${server.port}\nserver {\n  host localhost\n  port!int 80\n}")
+    ///     .unwrap();
+    /// assert_eq!(doc.get_str("url"), Some("http://localhost:80"));
+    /// assert_eq!(doc.get_int("server.port"), Some(80));
+    /// ```
+    pub fn with_interpolation(mut self, enabled: bool) -> Self {
+        self.interpolation = enabled;
+        self
+    }
+
+    /// Parse a UP document from a string
+    pub fn parse_document(&self, input: &str) -> Result<Document, ParseError> {
+        self.limits.check_size(input.len())?;
+        if self.interpolation {
+            // Coerced once references are expanded
+            let parser = Parser {
+                coerce_types: false,
+                interpolation: false,
+                ..self.clone()
+            };
+            let mut doc = parser.parse_document(input)?;
+            self.substitute(input, &mut doc)?;
+            return Ok(doc);
+        }
+        let parser = self.prefetch_includes(input);
+        let mut nodes = Vec::new();
+        parser.parse_nodes(input, &mut nodes, &mut parser.root_scope())?;
         Ok(Document { nodes })
     }
 
-    fn parse_line<'a, I>(
+    /// Create the scope for variables defined at the top level of a document
+    fn root_scope(&self) -> Scope<'_> {
+        Scope::root(&self.functions, self.env_access)
+    }
+
+    /// Parse top-level nodes into `nodes`, defining `@let` variables in `scope`
+    fn parse_nodes(
         &self,
-        lines: &mut std::iter::Peekable<I>,
-        line: &str,
-        _line_num: usize,
-    ) -> Result<Node, ParseError>
-    where
-        I: Iterator<Item = (usize, &'a &'a str)>,
-    {
-        let (key_part, val_part) = self.split_key_value(line);
-        let (key, type_annotation) = self.parse_key_and_type(key_part);
-
-        let value = self.parse_value(lines, val_part, type_annotation.as_deref())?;
-
-        Ok(Node {
-            key: key.to_string(),
-            type_annotation,
-            value,
-        })
+        input: &str,
+        nodes: &mut Vec<Node>,
+        scope: &mut Scope<'_>,
+    ) -> Result<(), ParseError> {
+        Reader::new(self, input).nodes(nodes, scope)
     }
 
-    fn split_key_value<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
-        if let Some(idx) = line.find(|c: char| c.is_whitespace()) {
-            (line[..idx].trim(), line[idx..].trim())
-        } else {
-            (line, "")
+    /// Parse as much of a possibly truncated document as possible
+    ///
+    /// Unlike [`parse_document`](Self::parse_document), a block, list, or
+    /// multiline string that is still open at the end of the input is
+    /// reported as [`ErrorKind::UnexpectedEof`]. Either way, every node
+    /// completed before the failure is returned, so callers can salvage or
+    /// inspect interrupted uploads.
+    pub fn parse_partial(&self, input: &str) -> PartialDocument {
+        if let Err(error) = self.limits.check_size(input.len()) {
+            return PartialDocument {
+                document: Document::new(),
+                error: Some(error),
+            };
+        }
+        let parser = Parser {
+            eof_is_error: true,
+            ..self.clone()
+        };
+        let mut nodes = Vec::new();
+        let error = parser
+            .parse_nodes(input, &mut nodes, &mut parser.root_scope())
+            .err();
+        PartialDocument {
+            document: Document { nodes },
+            error,
         }
     }
 
+    fn split_key_value<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        lexer::split_entry(line)
+    }
+
     fn parse_key_and_type<'a>(&self, key_part: &'a str) -> (&'a str, Option<String>) {
         if let Some(idx) = key_part.find('!') {
             (&key_part[..idx], Some(key_part[idx + 1..].to_string()))
@@ -165,144 +847,359 @@ impl Parser {
         }
     }
 
-    fn parse_value<'a, I>(
+    /// Turn scalar text into a value of its annotated type
+    fn parse_scalar(
         &self,
-        lines: &mut std::iter::Peekable<I>,
-        val_part: &str,
+        text: Cow<'_, str>,
         type_annotation: Option<&str>,
-    ) -> Result<Value, ParseError>
-    where
-        I: Iterator<Item = (usize, &'a &'a str)>,
-    {
-        match val_part {
-            "{" => self.parse_block(lines),
-            "[" => self.parse_list(lines),
-            s if s.starts_with("```") => self.parse_multiline(lines, type_annotation),
-            s if s.starts_with('[') && s.ends_with(']') => {
-                // Inline list
-                Ok(Value::List(self.parse_inline_list(s)?))
+    ) -> Result<Value, ParseError> {
+        if !self.coerce_types {
+            return Ok(Value::String(text.into_owned()));
+        }
+        let typed = type_annotation.and_then(|t| {
+            self.types
+                .coerce(t, &text)
+                .or_else(|| types::coerce(t, &text))
+        });
+        match typed {
+            Some(Ok(Value::Path(path))) => {
+                let base = self.includes.current_file().and_then(Path::parent);
+                types::check_path(&path, base, self.path_check)?;
+                Ok(Value::Path(path))
             }
-            _ => Ok(Value::String(val_part.to_string())),
+            Some(typed) => typed,
+            None => Ok(Value::String(text.into_owned())),
         }
     }
 
-    fn parse_multiline<'a, I>(
+    /// Parse the body of a `!json` or `!yaml` multiline value
+    /// Add a node parsed from `line` to a block, applying the duplicate key
+    /// policy; `collected` holds the keys already turned into lists
+    fn insert_into(
         &self,
-        lines: &mut std::iter::Peekable<I>,
-        type_annotation: Option<&str>,
-    ) -> Result<Value, ParseError>
-    where
-        I: Iterator<Item = (usize, &'a &'a str)>,
-    {
-        let mut content = Vec::new();
-
-        for (_, line) in lines.by_ref() {
-            let trimmed = line.trim();
-            if trimmed == "```" {
-                break;
+        lines: &Lexer<'_, '_>,
+        block: &mut Block,
+        node: Node,
+        line: &str,
+        collected: &mut HashSet<String>,
+    ) -> Result<(), ParseError> {
+        let Some(existing) = block.get_node_mut(&node.key) else {
+            block.push_new(node);
+            return Ok(());
+        };
+        match self.duplicate_keys {
+            DuplicateKeys::LastWins => {
+                block.insert_node(node);
+            }
+            DuplicateKeys::FirstWins => {}
+            DuplicateKeys::Error => {
+                let first = match &existing.origin {
+                    Some(origin) => format!(" (first defined on line {})", origin.line),
+                    None => String::new(),
+                };
+                let (key, _) = self.split_key_value(line);
+                return Err(lines.locate(
+                    ParseError::new(
+                        ErrorKind::DuplicateKey,
+                        format!("duplicate key `{}`{first}", node.key),
+                    ),
+                    key,
+                ));
+            }
+            DuplicateKeys::Collect => {
+                if collected.insert(node.key.clone()) {
+                    let first = std::mem::replace(&mut existing.value, Value::Null);
+                    existing.value = Value::List(vec![first]);
+                    existing.type_annotation = None;
+                }
+                if let Value::List(items) = &mut existing.value {
+                    items.push(node.value);
+                }
             }
-            content.push(line.to_string());
         }
+        Ok(())
+    }
 
-        let mut text = content.join("\n");
+    /// Read the names and annotations of the columns in a table's
+    /// `columns [...]` header
+    fn parse_columns(&self, header: &str) -> Result<Vec<(String, Option<String>)>, ParseError> {
+        let names = self.parse_inline_list(header)?;
+        Ok(names
+            .iter()
+            .map(|name| {
+                let name = name.scalar_text().unwrap_or_default();
+                let (name, annotation) = self.parse_key_and_type(&name);
+                (name.to_string(), annotation)
+            })
+            .collect())
+    }
 
-        // Apply dedenting if type annotation is a number
-        if let Some(type_str) = type_annotation {
-            if let Ok(dedent_amount) = type_str.parse::<usize>() {
-                text = self.dedent(&text, dedent_amount);
-            }
+    /// Read one `[...]` table row, typing each cell by its column
+    fn parse_row(
+        &self,
+        trimmed: &str,
+        columns: &[(String, Option<String>)],
+        scope: &Scope<'_>,
+    ) -> Result<Vec<Value>, ParseError> {
+        if !(trimmed.starts_with('[') && trimmed.ends_with(']')) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("expected a `[...]` table row, found `{}`", trimmed),
+            ));
         }
+        let cells = self.parse_inline_list(trimmed)?;
+        if cells.len() != columns.len() {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!(
+                    "table row has {} cells but the table has {} columns",
+                    cells.len(),
+                    columns.len()
+                ),
+            ));
+        }
+        cells
+            .iter()
+            .zip(columns)
+            .map(|(cell, (_, annotation))| {
+                let text = cell.scalar_text().unwrap_or_default();
+                let text = scope.interpolate(&text)?;
+                self.parse_scalar(text, annotation.as_deref())
+            })
+            .collect()
+    }
 
-        Ok(Value::String(text))
+    /// Handle the text after `@let`, adding the variable to `scope`
+    ///
+    /// The value may itself refer to variables defined earlier.
+    fn define(
+        &self,
+        lines: &Lexer<'_, '_>,
+        scope: &mut Scope<'_>,
+        definition: &str,
+    ) -> Result<(), ParseError> {
+        let (name, value) = self.split_key_value(definition);
+        if name.is_empty() {
+            return Err(lines.locate(
+                ParseError::new(ErrorKind::InvalidSyntax, "`@let` needs a variable name"),
+                definition,
+            ));
+        }
+        scope
+            .define_from(name, value, lines.location(value))
+            .map_err(|e| lines.locate(e, value))
     }
 
-    fn parse_block<'a, I>(&self, lines: &mut std::iter::Peekable<I>) -> Result<Value, ParseError>
-    where
-        I: Iterator<Item = (usize, &'a &'a str)>,
-    {
-        let mut block = HashMap::new();
+    /// Finish a block, list, or multiline string that ran into the end of input
+    fn end_of_input(&self, value: Value, what: &str) -> Result<Value, ParseError> {
+        unclosed(value, what, self.eof_is_error, self.strict)
+    }
 
-        while let Some((line_num, line)) = lines.next() {
-            let trimmed = line.trim();
+    /// In strict mode, reject `trimmed`, a line that only closes something
+    /// of a kind that is not open
+    fn check_stray(&self, lines: &Lexer<'_, '_>, trimmed: &str) -> Result<(), ParseError> {
+        match self.strict {
+            true => Err(stray(lines, trimmed)),
+            false => Ok(()),
+        }
+    }
 
-            if trimmed == "}" {
-                break;
-            }
+    fn parse_inline_list(&self, s: &str) -> Result<Vec<Value>, ParseError> {
+        self.parse_inline_items(s, &self.root_scope())
+    }
 
-            // Skip empty lines and comments
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
+    /// Read a double-quoted string whose opening quote was already consumed
+    ///
+    /// Separators and brackets inside the quotes are ordinary characters;
+    /// `\"` and `\\` escape a quote and a backslash.
+    fn parse_quoted(
+        &self,
+        chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    ) -> Result<String, ParseError> {
+        let mut text = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(text),
+                '\\' if matches!(chars.peek(), Some('"' | '\\')) => {
+                    text.extend(chars.next());
+                }
+                c => text.push(c),
             }
+        }
+        Err(ParseError::new(
+            ErrorKind::InvalidList,
+            "unterminated quoted item",
+        ))
+    }
 
-            let node = self.parse_line(lines, trimmed, line_num)?;
-            block.insert(node.key, node.value);
+    /// Remove the first `amount` characters of a line, for a numeric
+    /// dedent annotation; shorter lines are kept whole
+    fn dedent<'a>(&self, line: &'a str, amount: usize) -> &'a str {
+        match line.char_indices().nth(amount) {
+            Some((i, _)) => &line[i..],
+            None if line.chars().count() == amount => "",
+            None => line,
         }
+    }
+}
 
-        Ok(Value::Block(block))
+/// Strip the leading whitespace shared by every line that is not blank,
+/// for `!dedent` and `!up` multiline values
+///
+/// The shared prefix is compared character by character, so lines indented
+/// with tabs and lines indented with spaces share none. Blank lines become
+/// empty.
+fn dedent_common<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let mut prefix: Option<&str> = None;
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        prefix = Some(match prefix {
+            None => indent,
+            Some(prefix) => {
+                let shared = prefix
+                    .char_indices()
+                    .zip(indent.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(prefix.len().min(indent.len()), |((i, _), _)| i);
+                &prefix[..shared]
+            }
+        });
     }
+    let prefix = prefix.unwrap_or_default();
+    lines
+        .iter()
+        .map(|line| match line.trim().is_empty() {
+            true => "",
+            false => &line[prefix.len()..],
+        })
+        .collect()
+}
 
-    fn parse_list<'a, I>(&self, lines: &mut std::iter::Peekable<I>) -> Result<Value, ParseError>
-    where
-        I: Iterator<Item = (usize, &'a &'a str)>,
-    {
-        let mut list = Vec::new();
+/// The language tag after the opening fence of a multiline value, such as
+/// `sql` in ```` ```sql ````
+fn fence_lang(value: &str) -> Option<&str> {
+    let lang = value.strip_prefix("```")?.trim();
+    (!lang.is_empty()).then_some(lang)
+}
 
-        while let Some((_, line)) = lines.next() {
-            let trimmed = line.trim();
+/// Whether a multiline value with this annotation is parsed as JSON or YAML
+#[cfg(any(feature = "json", feature = "yaml"))]
+fn parses_data(annotation: Option<&str>) -> bool {
+    (cfg!(feature = "json") && annotation == Some("json"))
+        || (cfg!(feature = "yaml") && annotation == Some("yaml"))
+}
 
-            if trimmed == "]" {
-                break;
-            }
+/// Check whether a line ends in a single (odd) `\` that joins the next line
+fn continues(s: &str) -> bool {
+    s.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 1
+}
 
-            // Skip empty lines and comments
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
+/// The key and value of a multiline list item written as a node, such as
+/// `port!int 80` or `server {`, rather than as text
+///
+/// An item is a node when its key has an annotation, or when its value
+/// opens a block, a list, or a multiline string.
+fn keyed_item(trimmed: &str) -> Option<(&str, &str)> {
+    let start = quote::quoted_len(trimmed).unwrap_or(0);
+    let end = start + trimmed[start..].find(char::is_whitespace)?;
+    let (key, value) = (&trimmed[..end], trimmed[end..].trim());
+    let opens = matches!(value, "{" | "[") || value.starts_with("```");
+    let annotated = quote::split_key(key).is_ok_and(|(key, annotation)| {
+        !key.is_empty() && annotation.is_some_and(|a| !a.is_empty())
+    });
+    (opens || annotated).then_some((key, value))
+}
 
-            // Handle inline list within a multiline list
-            if trimmed.starts_with('[') && trimmed.ends_with(']') {
-                let inner_list = self.parse_inline_list(trimmed)?;
-                list.push(Value::List(inner_list));
-            } else if trimmed.starts_with('{') {
-                let block = self.parse_block(lines)?;
-                list.push(block);
-            } else {
-                list.push(Value::String(trimmed.to_string()));
-            }
-        }
+/// The definition following `@let` on a variable line, if it is one
+fn let_definition(trimmed: &str) -> Option<&str> {
+    let rest = trimmed.strip_prefix("@let")?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim_start())
+}
 
-        Ok(Value::List(list))
+/// `Some(true)` for a `@strict` line and `Some(false)` for `@lenient`, the
+/// directives that turn [`Parser::strict`] on or off for the rest of a block
+fn strictness_directive(trimmed: &str) -> Option<bool> {
+    match trimmed {
+        "@strict" => Some(true),
+        "@lenient" => Some(false),
+        _ => None,
     }
+}
 
-    fn parse_inline_list(&self, s: &str) -> Result<Vec<Value>, ParseError> {
-        let s = s.trim();
-        let s = s.strip_prefix('[').unwrap_or(s);
-        let s = s.strip_suffix(']').unwrap_or(s);
+/// `value`, a block, list, or multiline string that ran into the end of
+/// input, or the error for it when the end of input is an error or
+/// delimiters are checked strictly
+fn unclosed(
+    value: Value,
+    what: &str,
+    eof_is_error: bool,
+    strict: bool,
+) -> Result<Value, ParseError> {
+    let kind = if eof_is_error {
+        ErrorKind::UnexpectedEof
+    } else if strict {
+        ErrorKind::UnclosedDelimiter
+    } else {
+        return Ok(value);
+    };
+    Err(ParseError::new(kind, format!("{} is never closed", what)))
+}
 
-        if s.trim().is_empty() {
-            return Ok(Vec::new());
-        }
+/// The error for `trimmed`, a line that only closes something of a kind
+/// that is not open
+fn stray(lines: &Lexer<'_, '_>, trimmed: &str) -> ParseError {
+    let (closer, open) = match trimmed {
+        "}" => ("`}`", "block"),
+        "]" => ("`]`", "list"),
+        _ => ("closing fence", "multiline string"),
+    };
+    lines.locate(
+        ParseError::new(
+            ErrorKind::InvalidSyntax,
+            format!("unexpected {} with no {} open", closer, open),
+        ),
+        trimmed,
+    )
+}
 
-        let items: Vec<Value> = s
-            .split(',')
-            .map(|item| Value::String(item.trim().to_string()))
-            .collect();
+/// Build a table value from parsed column names and rows
+fn table(columns: Vec<(String, Option<String>)>, rows: Vec<Vec<Value>>) -> Value {
+    Value::Table {
+        columns: columns
+            .into_iter()
+            .map(|(name, _)| Value::String(name))
+            .collect(),
+        rows,
+    }
+}
+
+/// Substitute variables in the items of an inline list
+fn interpolate_items(scope: &Scope<'_>, items: Vec<Value>) -> Result<Vec<Value>, ParseError> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::String(text) => Ok(Value::String(scope.interpolate(&text)?.into_owned())),
+            other => Ok(other),
+        })
+        .collect()
+}
 
-        Ok(items)
+/// Remember the text of a comment line for the next key; a blank line,
+/// with no text, forgets them
+fn collect_comment<'a>(comment: &mut Vec<&'a str>, text: Option<&'a str>) {
+    match text {
+        Some(text) => comment.push(text),
+        None => comment.clear(),
     }
+}
 
-    fn dedent(&self, text: &str, amount: usize) -> String {
-        text.lines()
-            .map(|line| {
-                if line.len() >= amount {
-                    &line[amount..]
-                } else {
-                    line
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
+fn take_comment(comment: &mut Vec<&str>) -> Option<String> {
+    if comment.is_empty() {
+        return None;
     }
+    let text = comment.join("\n");
+    comment.clear();
+    Some(text)
 }
 
 impl Default for Parser {
@@ -340,7 +1237,40 @@ mod tests {
         assert_eq!(doc.nodes.len(), 1);
         assert_eq!(doc.nodes[0].key, "age");
         assert_eq!(doc.nodes[0].type_annotation, Some("int".to_string()));
-        assert_eq!(doc.nodes[0].value, Value::String("30".to_string()));
+        assert_eq!(doc.nodes[0].value, Value::Int(30));
+    }
+
+    #[test]
+    fn test_scalar_coercion() {
+        let doc = parse("n!int -7\nx!float 2.5\non!bool true\noff!bool false\nnone!null").unwrap();
+        let values: Vec<&Value> = doc.nodes.iter().map(|n| &n.value).collect();
+        assert_eq!(
+            values,
+            [
+                &Value::Int(-7),
+                &Value::Float(2.5),
+                &Value::Bool(true),
+                &Value::Bool(false),
+                &Value::Null
+            ]
+        );
+
+        for input in ["n!int 3.5", "x!float fast", "on!bool yes", "none!null x"] {
+            let err = parse(&format!("name app\n{}", input)).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::TypeMismatch, "{}", input);
+            assert_eq!(err.line(), Some(2));
+        }
+    }
+
+    #[test]
+    fn test_coercion_can_be_disabled() {
+        let doc = Parser::new()
+            .coerce_types(false)
+            .parse_document("n!int 3.5\nprice!money cheap")
+            .unwrap();
+        assert_eq!(doc.nodes[0].value, Value::String("3.5".to_string()));
+        assert_eq!(doc.nodes[0].type_annotation.as_deref(), Some("int"));
+        assert_eq!(doc.nodes[1].value, Value::String("cheap".to_string()));
     }
 
     #[test]
@@ -368,6 +1298,18 @@ port!int 8080
         }
     }
 
+    #[test]
+    fn test_block_keeps_source_order() {
+        let doc = parse("server {\nzeta 1\nport!int 80\nalpha 2\n}").unwrap();
+        let Value::Block(block) = &doc.nodes[0].value else {
+            panic!("Expected block");
+        };
+        assert_eq!(block.keys().collect::<Vec<_>>(), ["zeta", "port", "alpha"]);
+        let port = block.get_node("port").unwrap();
+        assert_eq!(port.type_annotation.as_deref(), Some("int"));
+        assert_eq!(port.origin.as_ref().unwrap().line, 3);
+    }
+
     #[test]
     fn test_parse_list() {
         let input = r#"
@@ -390,6 +1332,40 @@ cherry
         }
     }
 
+    #[test]
+    fn test_parse_list_nodes() {
+        let input = "items [\n  plain text\n  size!int 5\n  server {\n    host a\n  }\n  [\n    {\n      x 1\n    }\n    [a, b]\n  ]\n  ```\n  line one\n  line two\n  ```\n  tags [\n    c\n  ]\n]\nafter 1\n";
+        let doc = parse(input).unwrap();
+        let Some(Value::List(items)) = doc.get("items") else {
+            panic!("Expected list");
+        };
+        assert_eq!(items.len(), 6);
+        assert_eq!(items[0], Value::from("plain text"));
+        assert_eq!(doc.get_int("items[1].size"), Some(5));
+        assert_eq!(doc.get_str("items[2].server.host"), Some("a"));
+        assert_eq!(doc.get_str("items[3][0].x"), Some("1"));
+        assert_eq!(doc.get_str("items[3][1][1]"), Some("b"));
+        assert_eq!(items[4], Value::from("  line one\n  line two"));
+        assert_eq!(doc.get_str("items[5].tags[0]"), Some("c"));
+        assert_eq!(doc.get_str("after"), Some("1"));
+
+        // Every reader agrees on where the list ends
+        let streamed = Parser::new().parse_reader(input.as_bytes()).unwrap();
+        assert_eq!(streamed, doc);
+        let borrowed = Parser::new().parse_borrowed(input).unwrap().into_owned();
+        assert_eq!(borrowed.get("items[3]"), doc.get("items[3]"));
+        assert_eq!(borrowed.get("items[4]"), doc.get("items[4]"));
+
+        // And the writer and formatter keep the items as they are
+        let written = to_string(&doc);
+        assert_eq!(parse(&written).unwrap(), doc);
+        assert_eq!(format(input).unwrap(), input);
+        assert_eq!(
+            parse("a [\n  size!int x\n]").unwrap_err().kind,
+            ErrorKind::TypeMismatch
+        );
+    }
+
     #[test]
     fn test_parse_inline_list() {
         let result = parse("colors [red, green, blue]");
@@ -426,6 +1402,97 @@ Line 2
         }
     }
 
+    #[test]
+    fn test_fence_lang() {
+        let input =
+            "query ```sql\nSELECT 1\n```\nscript!2 ``` sh \n  echo hi\n```\nplain ```\nx\n```";
+        let doc = parse(input).unwrap();
+        let langs: Vec<Option<&str>> = doc.nodes.iter().map(|n| n.lang.as_deref()).collect();
+        assert_eq!(langs, [Some("sql"), Some("sh"), None]);
+        assert_eq!(doc.get_str("query"), Some("SELECT 1"));
+        assert_eq!(doc.get_str("script"), Some("echo hi"));
+
+        // The tag is written back, even on a value that fits on one line
+        let written = to_string(&doc);
+        assert!(written.starts_with("query ```sql\nSELECT 1\n```\nscript!2 ```sh\n"));
+        let doc = parse(&written).unwrap();
+        assert_eq!(doc.nodes[0].lang.as_deref(), Some("sql"));
+    }
+
+    #[test]
+    fn test_dedent() {
+        let input = "sql!dedent ```\n    SELECT *\n      FROM t\n   \n    WHERE x\n```\ntabs!dedent ```\n\t\ta\n\t b\n```\nwide!2 ```\n\u{3000}\u{3000}x\n \n```";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_str("sql"), Some("SELECT *\n  FROM t\n\nWHERE x"));
+        assert_eq!(doc.get_str("tabs"), Some("\ta\n b"));
+        // Numeric dedents count characters, not bytes
+        assert_eq!(doc.get_str("wide"), Some("x\n "));
+
+        let doc = parse("server {\n  query!dedent ```\n  a\n    b\n  ```\n}").unwrap();
+        let written = to_string(&doc);
+        assert_eq!(
+            written,
+            "server {\n  query!dedent ```\n    a\n      b\n  ```\n}\n"
+        );
+        assert_eq!(parse(&written).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_parse_embedded_up() {
+        let input = "@let env prod\nservice!up ```\n  @let name api\n  name ${name}-${env}\n  port!int 8080\n  sidecar!up ```\n    image proxy\n  ```\n```\nafter ${name}";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_str("service.name"), Some("api-prod"));
+        assert_eq!(doc.get_int("service.port"), Some(8080));
+        assert_eq!(doc.get_str("service.sidecar.image"), Some("proxy"));
+        // Definitions inside stay inside
+        assert_eq!(doc.get_str("after"), Some("${name}"));
+
+        let Some(Value::Block(service)) = doc.get("service") else {
+            panic!("Expected block");
+        };
+        assert_eq!(
+            service.get_node("port").unwrap().origin,
+            Some(Origin::line(5))
+        );
+    }
+
+    #[test]
+    fn test_embedded_up_error_location() {
+        let input = "a 1\nconfig!up ```\n  port!int eighty\n```";
+        let err = parse(input).unwrap_err();
+        let location = err.location.unwrap();
+        assert_eq!((location.line, location.column), (3, 12));
+        assert_eq!(location.snippet, "  port!int eighty");
+        assert_eq!(&input[location.span], "eighty");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_parse_embedded_json() {
+        let input = "name app\npolicy!json ```\n{\n  \"version\": 2,\n  \"rules\": [{\"allow\": true}]\n}\n```\nraw!4 ```\n    {}\n```";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_int("policy.version"), Some(2));
+        assert_eq!(doc.get_bool("policy.rules[0].allow"), Some(true));
+        assert_eq!(doc.get_str("raw"), Some("{}"));
+
+        let err = parse("policy!json ```\n{\n  \"a\": tru\n}\n```").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.location.unwrap().column, 8);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_parse_embedded_yaml() {
+        let input = "deploy {\n  manifest!yaml ```\n    kind: Service\n    ports:\n      - port: 80\n        name: http\n  ```\n}";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_str("deploy.manifest.kind"), Some("Service"));
+        assert_eq!(doc.get_int("deploy.manifest.ports[0].port"), Some(80));
+
+        let err = parse("m!yaml ```\na: 1\n   b: 2\n```").unwrap_err();
+        assert_eq!(err.line(), Some(3));
+    }
+
     #[test]
     fn test_skip_comments() {
         let input = r#"
@@ -440,6 +1507,589 @@ age!int 30
         assert_eq!(doc.nodes.len(), 2);
     }
 
+    #[test]
+    fn test_trailing_comments() {
+        let input = "port!int 8080  # public port\nname \"a # b\" # quoted\nurl http://h/#top\nserver {  # main\n  host a # primary\n}\ntags [  # some\n  x # first\n  y\n]\ntext ```\na # kept\n```\nlong a \\  # joined\n  b\n";
+        let parser = Parser::new().trailing_comments(true);
+        let doc = parser.parse_document(input).unwrap();
+        assert_eq!(doc.get_int("port"), Some(8080));
+        assert_eq!(doc.get_str("name"), Some("a # b"));
+        assert_eq!(doc.get_str("url"), Some("http://h/#top"));
+        assert_eq!(doc.get_str("server.host"), Some("a"));
+        assert_eq!(doc.get_str("tags[0]"), Some("x"));
+        assert_eq!(doc.get_str("tags[1]"), Some("y"));
+        assert_eq!(doc.get_str("text"), Some("a # kept"));
+        assert_eq!(doc.get_str("long"), Some("a b"));
+        assert_eq!(parser.parse_reader(input.as_bytes()).unwrap(), doc);
+
+        // Off by default, so `#` in values is kept
+        let doc = parse("color #fff\nport 8080 # x\n").unwrap();
+        assert_eq!(doc.get_str("color"), Some("#fff"));
+        assert_eq!(doc.get_str("port"), Some("8080 # x"));
+
+        // Other comment characters
+        let parser = Parser::new().comment_chars(";").trailing_comments(true);
+        let doc = parser
+            .parse_document("; note\na 1 ; one\ncolor #fff\n")
+            .unwrap();
+        assert_eq!(doc.get_str("a"), Some("1"));
+        assert_eq!(doc.get_str("color"), Some("#fff"));
+        assert_eq!(doc.nodes[0].doc.as_deref(), Some("note"));
+    }
+
+    #[test]
+    fn test_parse_money() {
+        let doc = parse("price!money 19.99 USD").unwrap();
+        assert_eq!(doc.nodes[0].value, Value::Money(Money::new(1999, 2, "USD")));
+
+        let err = parse("price!money nineteen USD").unwrap_err();
+        assert!(err.to_string().contains("invalid money value"));
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let err = parse("name app\nserver {\n  port!int 80\n  debug!bool maybe\n}").unwrap_err();
+        let location = err.location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (4, 14));
+        assert_eq!(location.snippet, "  debug!bool maybe");
+        assert_eq!(location.span, 45..50);
+        assert!(err
+            .to_string()
+            .ends_with("4 |   debug!bool maybe\n  |              ^^^^^"));
+
+        let err = parse("tags [\na\n[\"open, b]\n]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidList);
+        assert_eq!(err.line(), Some(3));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        let doc = parse("accent!color #FF8800").unwrap();
+        assert_eq!(doc.nodes[0].value, Value::Color(Color::rgb(255, 136, 0)));
+    }
+
+    #[test]
+    fn test_parse_geo() {
+        let doc = parse("location!geo 52.5200,13.4050").unwrap();
+        assert_eq!(
+            doc.nodes[0].value,
+            Value::Geo(GeoPoint::new(52.52, 13.405).unwrap())
+        );
+        assert!(parse("location!geo 95,13").is_err());
+    }
+
+    #[test]
+    fn test_parse_path_check() {
+        let input = "config!path ./Cargo.toml\nmissing!path ./no/such/file.up";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.nodes[0].value, Value::Path(PathBuf::from("Cargo.toml")));
+
+        let err = Parser::new()
+            .check_paths(PathCheck::Exists)
+            .parse_document(input)
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+        assert!(err.to_string().contains("does not exist"));
+
+        // Relative to the file the path is written in
+        let dir = std::env::temp_dir().join(format!("uplang-paths-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("certs")).unwrap();
+        std::fs::write(dir.join("certs/a.pem"), "").unwrap();
+        std::fs::write(dir.join("app.up"), "cert!path certs/a.pem\n").unwrap();
+        let doc = Parser::new()
+            .check_paths(PathCheck::Readable)
+            .parse_file(dir.join("app.up"))
+            .unwrap();
+        assert_eq!(
+            doc.get("cert"),
+            Some(&Value::Path(PathBuf::from("certs/a.pem")))
+        );
+        let err = Parser::new()
+            .check_paths(PathCheck::Exists)
+            .parse_document("cert!path certs/a.pem")
+            .unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_mime() {
+        let doc = parse("accept!mime application/json").unwrap();
+        match &doc.nodes[0].value {
+            Value::Mime(mime) => assert_eq!(mime.to_string(), "application/json"),
+            other => panic!("Expected media type, got {:?}", other),
+        }
+        assert!(parse("accept!mime application json").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_and_bytes() {
+        let doc = parse(
+            "day!date 2024-05-01\nat!time 09:30\nwhen!ts 2024-05-01T09:30:00Z\ntimeout!dur 1m30s\nkey!base64 aGk=\nraw!bytes AAE=",
+        )
+        .unwrap();
+        assert_eq!(
+            doc.get("day"),
+            Some(&Value::Date(Date::new(2024, 5, 1).unwrap()))
+        );
+        assert_eq!(
+            doc.get("at"),
+            Some(&Value::Time(Time::new(9, 30, 0, 0).unwrap()))
+        );
+        assert!(matches!(doc.get("when"), Some(Value::Timestamp(ts)) if ts.offset == 0));
+        assert_eq!(
+            doc.get("timeout"),
+            Some(&Value::Duration(std::time::Duration::from_secs(90)))
+        );
+        assert_eq!(doc.get("key"), Some(&Value::Bytes(b"hi".to_vec())));
+        assert_eq!(doc.get("raw"), Some(&Value::Bytes(vec![0, 1])));
+        assert_eq!(
+            to_string(&doc),
+            "day!date 2024-05-01\nat!time 09:30:00\nwhen!ts 2024-05-01T09:30:00Z\ntimeout!dur 1m30s\nkey!base64 aGk=\nraw!bytes AAE=\n"
+        );
+
+        let err = parse("a 1\ntimeout!dur soon").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert_eq!(err.line(), Some(2));
+        assert!(parse("when!ts 2024-05-01").is_err());
+    }
+
+    #[test]
+    fn test_type_registry() {
+        let types = TypeRegistry::new()
+            .register("upper", |text: &str| {
+                Ok(Value::Custom(CustomValue::new(
+                    "upper",
+                    text,
+                    text.to_uppercase(),
+                )))
+            })
+            .register("int", |text: &str| {
+                Ok(Value::Custom(CustomValue::tagged("int", text)))
+            });
+        let parser = Parser::new().type_registry(types);
+        let doc = parser
+            .parse_document("name!upper app\nport!int 0x50")
+            .unwrap();
+        let Some(Value::Custom(name)) = doc.get("name") else {
+            panic!("expected a custom value, got {:?}", doc.get("name"));
+        };
+        assert_eq!(name.annotation(), "upper");
+        assert_eq!(
+            name.downcast_ref::<String>().map(String::as_str),
+            Some("APP")
+        );
+        assert_eq!(name.downcast_ref::<i64>(), None);
+        assert_eq!(
+            doc.get("port"),
+            Some(&Value::Custom(CustomValue::tagged("int", "0x50")))
+        );
+        assert_eq!(to_string(&doc), "name!upper app\nport!int 0x50\n");
+        assert_eq!(
+            format!("{:?}", parser)
+                .matches("types: {\"int\", \"upper\"}")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let input = "command run --verbose \\\n    --output out.txt \\\n    --force\nnext value";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.nodes.len(), 2);
+        assert_eq!(
+            doc.nodes[0].value,
+            Value::String("run --verbose --output out.txt --force".to_string())
+        );
+
+        let doc = parse("path C:\\\\").unwrap();
+        assert_eq!(doc.nodes[0].value, Value::String("C:\\\\".to_string()));
+    }
+
+    #[test]
+    fn test_parse_huge_values() {
+        let big = "x".repeat(8 * 1024 * 1024);
+        let input = format!("blob {}\ndata ```\n{}\n{}\n```\n", big, big, big);
+        let doc = parse(&input).unwrap();
+        assert_eq!(doc.nodes.len(), 2);
+
+        let mut out = Vec::new();
+        let mut reader = doc.nodes[1].value.reader().unwrap();
+        let copied = std::io::copy(&mut reader, &mut out).unwrap();
+        assert_eq!(copied as usize, big.len() * 2 + 1);
+        assert!(Value::List(vec![]).reader().is_none());
+    }
+
+    #[test]
+    fn test_inline_list_separator() {
+        let doc = parse(r"names [Doe\, John, Roe\, Jane]").unwrap();
+        assert_eq!(
+            doc.nodes[0].value,
+            Value::List(vec![
+                Value::String("Doe, John".to_string()),
+                Value::String("Roe, Jane".to_string()),
+            ])
+        );
+
+        let doc = Parser::new()
+            .list_separator(';')
+            .parse_document(r"pairs [a,b; c,d; e\;f]")
+            .unwrap();
+        assert_eq!(
+            doc.nodes[0].value,
+            Value::List(vec![
+                Value::String("a,b".to_string()),
+                Value::String("c,d".to_string()),
+                Value::String("e;f".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_inline_list_quoted_items() {
+        let doc = parse(r#"tags ["to do", "won't fix", blocked, "[x], \"y\""]"#).unwrap();
+        assert_eq!(
+            doc.nodes[0].value,
+            Value::List(vec![
+                Value::String("to do".to_string()),
+                Value::String("won't fix".to_string()),
+                Value::String("blocked".to_string()),
+                Value::String(r#"[x], "y""#.to_string()),
+            ])
+        );
+
+        assert!(parse(r#"tags ["open, closed]"#).is_err());
+        assert!(parse(r#"tags ["a" b, c]"#).is_err());
+    }
+
+    #[test]
+    fn test_quoted_keys_and_values() {
+        let input = r#""my key!" "  two words "
+"port number"!int "8080"
+escaped "line\nbreak \"quoted\" \\ \u{e9}"
+"#;
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.get_str("my key!"), Some("  two words "));
+        assert_eq!(doc.nodes[1].key, "port number");
+        assert_eq!(doc.nodes[1].value, Value::Int(8080));
+        assert_eq!(doc.get_str("escaped"), Some("line\nbreak \"quoted\" \\ é"));
+
+        // Only a value that is entirely quoted is unquoted
+        let doc = parse(r#"greeting say "hi""#).unwrap();
+        assert_eq!(doc.get_str("greeting"), Some(r#"say "hi""#));
+
+        // Quoted keys can start with characters that begin other lines
+        let doc = parse("\"# not a comment\" 1\n\"@let\" 2\n\"}\" 3\n").unwrap();
+        let keys: Vec<&str> = doc.nodes.iter().map(|n| n.key.as_str()).collect();
+        assert_eq!(keys, ["# not a comment", "@let", "}"]);
+
+        // Quoted keys keep their dots
+        let doc = Parser::new()
+            .dotted_keys(true)
+            .parse_document("\"a.b\" 1")
+            .unwrap();
+        assert_eq!(doc.nodes[0].key, "a.b");
+
+        let err = parse(r#"path "C:\dir""#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.message, r"invalid escape `\d`");
+        let err = parse("a 1\n\"open key 2").unwrap_err();
+        assert_eq!(err.message, "unterminated quoted key");
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn test_empty_null_and_absent() {
+        let doc = parse("debug\nproxy!null\nlimits {\n}").unwrap();
+
+        let debug = doc.get("debug").unwrap();
+        assert!(debug.is_empty() && !debug.is_null());
+
+        let proxy = doc.get("proxy").unwrap();
+        assert!(proxy.is_null() && !proxy.is_empty());
+
+        assert!(doc.get("limits").unwrap().is_empty());
+        assert!(doc.get("timeout").is_none());
+        assert!(!doc.contains_key("timeout"));
+
+        assert!(parse("proxy!null http://proxy").is_err());
+
+        // A bare key and an explicitly empty string stay apart
+        let doc = parse("debug\nname \"\"\nopts { quiet, tag \"\" }\n").unwrap();
+        assert_eq!(debug, &Value::Empty);
+        assert_eq!(doc.get("name"), Some(&Value::from("")));
+        assert_eq!(doc.get("opts.quiet"), Some(&Value::Empty));
+        assert_eq!(doc.get_str("opts.quiet"), Some(""));
+        assert_eq!(
+            to_string(&doc),
+            "debug\nname \"\"\nopts {\n  quiet\n  tag \"\"\n}\n"
+        );
+        assert_eq!(parse(&to_string(&doc)).unwrap(), doc);
+
+        let flags = Parser::new().flag_keys(true);
+        let doc = flags
+            .parse_document("debug\nname \"\"\nlog!string\n")
+            .unwrap();
+        assert_eq!(doc.get_bool("debug"), Some(true));
+        assert_eq!(doc.get("name"), Some(&Value::from("")));
+        assert_eq!(doc.get("log"), Some(&Value::from("")));
+    }
+
+    #[test]
+    fn test_parse_partial_on_truncated_input() {
+        let input = "name app\nserver {\nhost localhost\nport 80";
+
+        // The lenient parser silently closes the block at end of input
+        assert_eq!(parse(input).unwrap().nodes.len(), 2);
+
+        let partial = Parser::new().parse_partial(input);
+        assert!(!partial.is_complete());
+        let error = partial.error.as_ref().unwrap();
+        assert_eq!(error.kind, ErrorKind::UnexpectedEof);
+        assert_eq!(error.message, "block is never closed");
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(partial.document.nodes.len(), 1);
+        assert_eq!(partial.document.nodes[0].key, "name");
+
+        let partial = Parser::new().parse_partial("notes ```\nunfinished");
+        assert_eq!(partial.error.unwrap().kind, ErrorKind::UnexpectedEof);
+
+        let complete = Parser::new().parse_partial("name app\ntags [\na\n]");
+        assert!(complete.is_complete());
+        assert_eq!(complete.document.nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_node_origin_lines() {
+        let doc = parse("\n# comment\nname app\n\nport 80").unwrap();
+        let lines: Vec<usize> = doc
+            .nodes
+            .iter()
+            .map(|n| n.origin.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, [3, 5]);
+        assert_eq!(doc, parse("name app\nport 80").unwrap());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = "# Service name\n#\n#   indented\nname app\n# detached\n\nport 80\nserver {\n# Listen port\nport 8080\n}";
+        let doc = parse(input).unwrap();
+        assert_eq!(
+            doc.nodes[0].doc.as_deref(),
+            Some("Service name\n\n  indented")
+        );
+        assert_eq!(doc.nodes[1].doc, None);
+        let Value::Block(server) = &doc.nodes[2].value else {
+            panic!("Expected block");
+        };
+        let port = server.get_node("port").unwrap();
+        assert_eq!(port.doc.as_deref(), Some("Listen port"));
+    }
+
+    #[test]
+    fn test_let_variables() {
+        let input = "@let base /srv/app\n@let logs ${base}/logs\nroot ${base}\nworker {\n@let base /opt/worker\nhome ${base}\nlogs!path ${logs}\nports [${port}, 81]\n}\nafter ${base}\nscript ${HOME}/bin";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.nodes.len(), 4);
+        assert_eq!(doc.get("root"), Some(&Value::String("/srv/app".into())));
+        assert_eq!(doc.get("after"), Some(&Value::String("/srv/app".into())));
+        assert_eq!(
+            doc.get("script"),
+            Some(&Value::String("${HOME}/bin".into()))
+        );
+
+        let worker = doc.get("worker").unwrap();
+        let Value::Block(worker) = worker else {
+            panic!("Expected block");
+        };
+        assert_eq!(worker.len(), 3);
+        assert_eq!(
+            worker.get("home"),
+            Some(&Value::String("/opt/worker".into()))
+        );
+        assert_eq!(
+            worker.get("logs"),
+            Some(&Value::Path(PathBuf::from("/srv/app/logs")))
+        );
+
+        let doc = parse("@let replicas 3\nworkers!int ${replicas * 2}\nports [${replicas + 8000}]")
+            .unwrap();
+        assert_eq!(doc.get("workers"), Some(&Value::Int(6)));
+        assert_eq!(
+            doc.get("ports"),
+            Some(&Value::List(vec![Value::String("8003".into())]))
+        );
+        let err = parse("@let name web\nport ${name + 1}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.line(), Some(2));
+
+        let err = parse("@let a ${b}\n@let b ${c}\n@let c ${a}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(
+            err.message,
+            "`c` -> `a` (line 1, column 8) -> `b` (line 2, column 8) -> `c`"
+        );
+        assert_eq!(err.line(), Some(3));
+
+        let err = parse("@let\nname app").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(1));
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let input = "@let host db1\nusers!table {\ncolumns [id!int, name, active!bool]\n# Staff\nrows {\n[1, Ann, true]\n[2, \"Bo, Jr.\", false]\n[3, ${host}, true]\n}\n}";
+        let doc = parse(input).unwrap();
+        let Some(Value::Table { columns, rows }) = doc.get("users") else {
+            panic!("Expected table");
+        };
+        assert_eq!(
+            columns,
+            &[
+                Value::String("id".into()),
+                Value::String("name".into()),
+                Value::String("active".into())
+            ]
+        );
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            [
+                Value::Int(2),
+                Value::String("Bo, Jr.".into()),
+                Value::Bool(false)
+            ]
+        );
+        assert_eq!(rows[2][1], Value::String("db1".into()));
+
+        let err =
+            parse("t!table {\ncolumns [a!int, b]\nrows [\n[1, x]\n[two, y]\n]\n}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.line(), Some(5));
+
+        let err = parse("t!table {\ncolumns [a, b]\nrows {\n[1]\n}\n}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert_eq!(err.line(), Some(4));
+
+        let err = parse("t!table {\nrows {\n}\n}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+
+        let partial = Parser::new().parse_partial("t!table {\ncolumns [a]\nrows {\n[1]\n");
+        assert_eq!(partial.error.unwrap().kind, ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = Parser::new().strict(true);
+        for (input, line) in [
+            ("a 1\nserver {\n  port 80\n", 2),
+            ("hosts [\n  a\n  {\n    b 1\n  }\n", 1),
+            ("hosts [\n  a\n  {\n    b 1\n", 3),
+            ("a {\n  b 1\n}\nscript ```\nrun\n", 4),
+            ("t!table {\ncolumns [a]\nrows {\n[1]\n}\n", 1),
+        ] {
+            assert!(parse(input).is_ok());
+            let err = strict.parse_document(input).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::UnclosedDelimiter, "{:?}", input);
+            assert_eq!(err.line(), Some(line), "{:?}", input);
+        }
+        // Parsing a fragment still reports running out of input
+        let partial = strict.parse_partial("a {\n");
+        assert_eq!(partial.error.unwrap().kind, ErrorKind::UnexpectedEof);
+
+        for (input, message, line) in [
+            ("a 1\n}\n", "unexpected `}` with no block open", 2),
+            (
+                "a {\n  b 1\n  ]\n}\n",
+                "unexpected `]` with no list open",
+                3,
+            ),
+            ("a [\n  b\n  }\n]\n", "unexpected `}` with no block open", 3),
+            (
+                "a 1\n```\n",
+                "unexpected closing fence with no multiline string open",
+                2,
+            ),
+        ] {
+            assert!(parse(input).is_ok());
+            let err = strict.parse_document(input).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+            assert_eq!(err.message, message);
+            assert_eq!(err.line(), Some(line));
+        }
+
+        let input = "a {\n  b [\n    x\n  ]\n  s ```\n  }\n  ```\n}\nc [\n  {\n    d 1\n  }\n]\n";
+        assert_eq!(strict.parse_document(input).unwrap(), parse(input).unwrap());
+    }
+
+    #[test]
+    fn test_strictness_directives() {
+        let strict = Parser::new().strict(true);
+        // `@lenient` relaxes the rest of its block and the blocks inside it
+        let input = "a {\n  b 1\n  @lenient\n  ]\n  c {\n    ```\n  }\n}\n";
+        let doc = strict.parse_document(input).unwrap();
+        assert_eq!(doc.get_str("a.b"), Some("1"));
+        assert_eq!(doc.get_str("a.]"), Some(""));
+        assert_eq!(doc.get_str("a.c.```"), Some(""));
+        assert!(doc.get("a.@lenient").is_none());
+
+        // Lines before the directive, and the enclosing block, keep the
+        // parser's setting
+        let err = strict
+            .parse_document("a {\n  ]\n  @lenient\n}\n")
+            .unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        let err = strict
+            .parse_document("a {\n  b {\n    @lenient\n  }\n  ]\n}\n")
+            .unwrap_err();
+        assert_eq!(err.line(), Some(5));
+
+        // `@strict` tightens a block of a lenient parser
+        let err = parse("a {\n  @strict\n  b [\n    x\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedDelimiter);
+        assert_eq!(err.line(), Some(3));
+        assert!(parse("a {\n  @strict\n  b 1\n}\n").is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let input = "server {\n  port!int 80\n  host a\n  port!int 8080\n  port!int 9090\n}";
+        let port = |policy| {
+            let doc = Parser::new().duplicate_keys(policy).parse_document(input)?;
+            let Some(Value::Block(server)) = doc.get("server") else {
+                panic!("Expected block");
+            };
+            assert_eq!(server.keys().collect::<Vec<_>>(), ["port", "host"]);
+            Ok::<_, ParseError>(server.get("port").unwrap().clone())
+        };
+
+        assert_eq!(port(DuplicateKeys::LastWins).unwrap(), Value::Int(9090));
+        assert_eq!(port(DuplicateKeys::FirstWins).unwrap(), Value::Int(80));
+        assert_eq!(
+            port(DuplicateKeys::Collect).unwrap(),
+            Value::List(vec![Value::Int(80), Value::Int(8080), Value::Int(9090)])
+        );
+
+        let err = port(DuplicateKeys::Error).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DuplicateKey);
+        assert_eq!(
+            err.message,
+            "duplicate key `port` (first defined on line 2)"
+        );
+        let location = err.location.unwrap();
+        assert_eq!((location.line, location.column), (4, 3));
+        assert_eq!(&input[location.span], "port!int");
+
+        // Top-level keys are left alone
+        let doc = Parser::new()
+            .duplicate_keys(DuplicateKeys::Error)
+            .parse_document("a 1\na 2")
+            .unwrap();
+        assert_eq!(doc.nodes.len(), 2);
+    }
+
     #[test]
     fn test_document_default() {
         let doc = Document::default();
@@ -0,0 +1,285 @@
+//! Resource limits for parsing untrusted input, see [`Parser::limits`]
+//!
+//! [`Parser::limits`]: crate::Parser::limits
+
+use crate::{ErrorKind, ParseError};
+
+/// Upper bounds on what a document may hold, for parsing untrusted input
+///
+/// Every limit is off by default, except that nesting stops at
+/// [`DEFAULT_MAX_DEPTH`](Self::DEFAULT_MAX_DEPTH) levels so that no input
+/// can exhaust the stack. A document that goes past one fails with
+/// [`ErrorKind::LimitExceeded`], pointing at the offending text where there
+/// is one.
+///
+/// ```
+/// use uplang::{ErrorKind, Limits, Parser};
+///
+/// let parser = Parser::new().limits(Limits::new().max_depth(2).max_items(100));
+/// assert!(parser.parse_document("a {\n  b [\n    c\n  ]\n}").is_ok());
+///
+/// let err = parser.parse_document("a {\n  b {\n    c {\n    }\n  }\n}").unwrap_err();
+/// assert_eq!(err.kind, ErrorKind::LimitExceeded);
+/// assert_eq!(err.line(), Some(3));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    max_depth: Option<usize>,
+    max_size: Option<usize>,
+    max_items: Option<usize>,
+    max_key_length: Option<usize>,
+}
+
+impl Limits {
+    /// How deep blocks, lists, and expressions may nest without
+    /// [`max_depth`](Self::max_depth), which is also the limit for JSON and
+    /// YAML read with [`Document::from_json`](crate::Document::from_json)
+    /// and embedded `!json` and `!yaml` values
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Create limits with every limit off but the default nesting depth
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow blocks, lists, tables, and embedded documents to nest at most
+    /// `depth` levels deep
+    ///
+    /// An included document nests as deep as the `@include` line, and
+    /// includes within includes may also go `depth` levels deep.
+    ///
+    /// Parsing recurses once per level, so a depth much above
+    /// [`DEFAULT_MAX_DEPTH`](Self::DEFAULT_MAX_DEPTH) needs a thread with a
+    /// larger stack.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Allow documents of at most `bytes` bytes
+    ///
+    /// Streams read with [`Parser::read_nodes`](crate::Parser::read_nodes)
+    /// stop reading once they pass the limit; each record read with
+    /// [`Parser::read_records`](crate::Parser::read_records) counts on its
+    /// own.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Allow at most `items` items in a list and rows in a table
+    pub fn max_items(mut self, items: usize) -> Self {
+        self.max_items = Some(items);
+        self
+    }
+
+    /// Allow keys of at most `bytes` bytes
+    pub fn max_key_length(mut self, bytes: usize) -> Self {
+        self.max_key_length = Some(bytes);
+        self
+    }
+
+    /// Check the nesting level of a block, list, or table being opened
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), ParseError> {
+        let max = self.max_depth.unwrap_or(Self::DEFAULT_MAX_DEPTH);
+        match depth > max {
+            true => Err(exceeded(format!("nesting deeper than {} levels", max))),
+            false => Ok(()),
+        }
+    }
+
+    /// Check the size of a document, or of the part of a stream read so far
+    pub(crate) fn check_size(&self, bytes: usize) -> Result<(), ParseError> {
+        match self.max_size {
+            Some(max) if bytes > max => {
+                Err(exceeded(format!("document larger than {} bytes", max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Number of bytes a stream may still read after `used` bytes, one
+    /// more than allowed so that passing the limit is noticed
+    pub(crate) fn remaining(&self, used: usize) -> u64 {
+        match self.max_size {
+            Some(max) => max.saturating_sub(used) as u64 + 1,
+            None => u64::MAX,
+        }
+    }
+
+    /// Check the length of a list, or the number of rows of a table
+    pub(crate) fn check_items(&self, items: usize, what: &str) -> Result<(), ParseError> {
+        match self.max_items {
+            Some(max) if items > max => Err(exceeded(format!("more than {} {}", max, what))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the length of a key
+    pub(crate) fn check_key(&self, key: &str) -> Result<(), ParseError> {
+        match self.max_key_length {
+            Some(max) if key.len() > max => Err(exceeded(format!("key longer than {} bytes", max))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn exceeded(message: String) -> ParseError {
+    ParseError::new(ErrorKind::LimitExceeded, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_depth_limit() {
+        let parser = Parser::new().limits(Limits::new().max_depth(2));
+        for ok in ["a {\n  b {\n  }\n}", "a [\n  {\n    b [x]\n  }\n]"] {
+            assert!(parser.parse_document(ok).is_ok(), "{:?}", ok);
+            assert!(parser.parse_borrowed(ok).is_ok(), "{:?}", ok);
+        }
+        for deep in [
+            "a {\n  b {\n    c {\n    }\n  }\n}",
+            "a [\n  {\n    b [\n    ]\n  }\n]",
+            "a [\n  {\n    t!table {\n      columns [x]\n    }\n  }\n]\n",
+            "a {\n  doc!up ```\n    b {\n    }\n  ```\n}",
+        ] {
+            let err = parser.parse_document(deep).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::LimitExceeded, "{:?}", deep);
+            assert_eq!(err.message, "nesting deeper than 2 levels");
+        }
+        let err = parser
+            .parse_borrowed("a {\n  b {\n    c {\n    }\n  }\n}")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+        assert_eq!(err.line(), Some(3));
+
+        // Far deeper than the stack would allow without the limit
+        let deep = "a {\n".repeat(100_000);
+        let err = Parser::new()
+            .limits(Limits::new().max_depth(64))
+            .parse_document(&deep)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+    }
+
+    #[test]
+    fn test_default_depth_limit() {
+        let parser = Parser::new();
+        let at_limit = format!("{}{}", "a {\n".repeat(128), "}\n".repeat(128));
+        assert!(parser.parse_document(&at_limit).is_ok());
+
+        // Nothing nests past the default limit, so no input runs out of stack
+        let mut deep = vec![
+            "a {\n".repeat(10_000),
+            format!("a {}", "[\n".repeat(10_000)),
+            format!("a {}{}", "{ b ".repeat(10_000), "}".repeat(10_000)),
+            format!("a ${{{}1}}", "(".repeat(10_000)),
+            format!("a ${{{}1}}", "-".repeat(10_000)),
+        ];
+        if cfg!(feature = "json") {
+            deep.push(format!("a!json ```\n{}\n```\n", "[".repeat(10_000)));
+        }
+        if cfg!(feature = "yaml") {
+            deep.push(format!("a!yaml ```\n{}\n```\n", "- ".repeat(10_000)));
+            deep.push(format!("a!yaml ```\nb: {}\n```\n", "[".repeat(10_000)));
+        }
+        for input in &deep {
+            let err = parser.parse_document(input).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::LimitExceeded, "{}", &input[..20]);
+            assert!(err.message.starts_with("nesting deeper than 128 levels"));
+            assert!(parser.parse_reader(input.as_bytes()).is_err());
+            assert!(crate::format(input).is_err());
+        }
+        assert!(parser.parse_borrowed(&deep[0]).is_err());
+
+        // Included documents nest inside the including block, and chains
+        // of includes are as limited as nesting
+        let mut files = crate::MemoryResolver::new()
+            .insert("outer.up", "a {\n  @include inner.up\n}\n")
+            .insert("inner.up", "b {\n  c 1\n}\n");
+        for n in 0..10_000 {
+            files = files.insert(format!("{}.up", n), format!("@include {}.up\n", n + 1));
+        }
+        let parser = Parser::new()
+            .limits(Limits::new().max_depth(2))
+            .include_resolver(files.clone());
+        assert!(parser.parse_document("@include outer.up").is_ok());
+        let err = parser
+            .parse_document("x {\n  @include outer.up\n}\n")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+        let err = Parser::new()
+            .include_resolver(files)
+            .parse_document("@include 0.up")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+        assert!(parser.parse_borrowed(&deep[1]).is_err());
+        let err = crate::Document::from_json(&"[".repeat(10_000)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        let err =
+            crate::Document::from_json(&format!("{{{}", "\"a\":{".repeat(10_000))).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let parser = Parser::new().limits(Limits::new().max_size(8));
+        assert!(parser.parse_document("a 1\nb 2\n").is_ok());
+        let err = parser.parse_document("a 1\nb 2\nc 3\n").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+        assert_eq!(err.message, "document larger than 8 bytes");
+        assert!(err.location.is_none());
+        assert!(parser.parse_bytes(b"a 1\nb 2\nc 3\n").is_err());
+        assert!(parser.parse_borrowed("a 1\nb 2\nc 3\n").is_err());
+        assert!(parser.parse_partial("a 1\nb 2\nc 3\n").error.is_some());
+
+        // Streams stop reading at the limit, even within one long line
+        let mut nodes = parser.read_nodes("a 1\nb 2\nc 3\n".as_bytes());
+        assert!(nodes.next().unwrap().is_ok());
+        assert!(nodes.next().unwrap().is_ok());
+        let err = nodes.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+        assert!(nodes.next().is_none());
+        let long = format!("a {}", "x".repeat(1 << 20));
+        let err = parser.parse_reader(long.as_bytes()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LimitExceeded);
+
+        // Each record of a log counts on its own
+        let records: Vec<_> = parser
+            .read_records("a 1\n\nb 2\n\nc 3\n".as_bytes())
+            .collect();
+        assert!(records.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_item_and_key_limits() {
+        let parser = Parser::new().limits(Limits::new().max_items(2).max_key_length(4));
+        assert!(parser
+            .parse_document("tags [a, b]\nlist [\n  x\n  y\n]")
+            .is_ok());
+        for long in [
+            "tags [a, b, c]",
+            "list [\n  x\n  [y]\n  z\n]",
+            "list [\n  [x, y, z]\n]",
+            "t!table {\n  columns [x]\n  rows {\n    [1]\n    [2]\n    [3]\n  }\n}",
+        ] {
+            let err = parser.parse_document(long).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::LimitExceeded, "{:?}", long);
+            assert!(parser.parse_borrowed(long).is_err() || long.starts_with("t!"));
+        }
+        let err = parser
+            .parse_document("list [\n  x\n  y\n  z\n]")
+            .unwrap_err();
+        assert_eq!(err.message, "more than 2 items in a list");
+        assert_eq!(err.line(), Some(4));
+
+        let err = parser.parse_document("name 1\nhostname 2").unwrap_err();
+        assert_eq!(err.message, "key longer than 4 bytes");
+        assert_eq!(err.line(), Some(2));
+        assert!(parser.parse_document("\"a b\"!int 1").is_ok());
+        assert!(parser.parse_borrowed("hostname 2").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Reading a document one line at a time
+
+use crate::error::{offset_in, Location};
+use crate::ParseError;
+
+/// 1-based number of the line holding byte `offset` of `input`
+pub(crate) fn line_number(input: &str, offset: usize) -> usize {
+    count_lines(&input[..offset]) + 1
+}
+
+fn count_lines(text: &str) -> usize {
+    text.bytes().filter(|&b| b == b'\n').count()
+}
+
+/// The parser's position in a document: the lines not yet read, with
+/// their 0-based numbers
+///
+/// Lines are split as [`str::lines`] splits them, found one at a time as
+/// they are asked for, so the input is read once and no list of lines is
+/// built. The cursor can also be started at any line, which lets recovery
+/// look ahead from where a failed node starts without going back to the
+/// start of the input.
+///
+/// It also locates errors: lines are counted from the line it last read,
+/// which is where the parser finds them, so locating each of many errors,
+/// as recovery does, does not read the input again from the start.
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor<'a> {
+    input: &'a str,
+    /// Byte offset of the next line
+    pos: usize,
+    /// Number of the next line
+    line: usize,
+    /// Byte offset and number of the line last read
+    last: (usize, usize),
+}
+
+impl<'a> Cursor<'a> {
+    /// A cursor at the first line of `input`
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            line: 0,
+            last: (0, 0),
+        }
+    }
+
+    /// A cursor at `line`, the `line_num`th line of `input` and a slice of
+    /// it, or at the end of the input if it is not a slice of it
+    pub(crate) fn at(input: &'a str, line: &str, line_num: usize) -> Self {
+        let pos = match crate::error::offset_in(input, line) {
+            Some(pos) => input[..pos].rfind('\n').map_or(0, |i| i + 1),
+            None => input.len(),
+        };
+        Self {
+            input,
+            pos,
+            line: line_num,
+            last: (pos, line_num),
+        }
+    }
+
+    /// The next line, without moving past it
+    pub(crate) fn peek(&self) -> Option<(usize, &'a str)> {
+        self.split().map(|(line, _)| (self.line, line))
+    }
+
+    /// Move past the next line if `take` accepts it
+    pub(crate) fn next_if(
+        &mut self,
+        take: impl FnOnce(&(usize, &'a str)) -> bool,
+    ) -> Option<(usize, &'a str)> {
+        let (line, next) = self.split()?;
+        let item = (self.line, line);
+        if !take(&item) {
+            return None;
+        }
+        self.last = (self.pos, self.line);
+        self.pos = next;
+        self.line += 1;
+        Some(item)
+    }
+
+    /// 1-based number of the line holding `text`, if it is a slice of the
+    /// input
+    pub(crate) fn line_of(&self, text: &str) -> Option<usize> {
+        let offset = offset_in(self.input, text)?;
+        let (pos, line) = self.last;
+        Some(if offset >= pos {
+            line + count_lines(&self.input[pos..offset]) + 1
+        } else {
+            line + 1 - count_lines(&self.input[offset..pos])
+        })
+    }
+
+    /// Location of `text`, if it is a slice of the input
+    pub(crate) fn location(&self, text: &str) -> Option<Location> {
+        Location::at_line(self.input, text, self.line_of(text)?)
+    }
+
+    /// Point `error` at `text`, as [`ParseError::locate`] does
+    pub(crate) fn locate(&self, mut error: ParseError, text: &str) -> ParseError {
+        if error.location.is_none() {
+            error.location = self.location(text);
+        }
+        error
+    }
+
+    /// The next line without its line ending, and the offset after it
+    fn split(&self) -> Option<(&'a str, usize)> {
+        let rest = self.input.get(self.pos..).filter(|rest| !rest.is_empty())?;
+        let (line, next) = match rest.find('\n') {
+            Some(end) => (&rest[..end], self.pos + end + 1),
+            None => (rest, self.input.len()),
+        };
+        Some((line.strip_suffix('\r').unwrap_or(line), next))
+    }
+}
+
+impl<'a> Iterator for Cursor<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_if(|_| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_match_str_lines() {
+        for input in [
+            "",
+            "a",
+            "a\n",
+            "a\nb",
+            "a\r\nb\r\n",
+            "\n\n",
+            "a\rb\n",
+            "\r\n",
+        ] {
+            let scanned: Vec<&str> = Cursor::new(input).map(|(_, line)| line).collect();
+            assert_eq!(scanned, input.lines().collect::<Vec<_>>(), "{:?}", input);
+        }
+        let numbers: Vec<usize> = Cursor::new("a\n\nb").map(|(i, _)| i).collect();
+        assert_eq!(numbers, [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cursor_at_line() {
+        let input = "a\nb {\n  c\n}\nd";
+        let line = input.lines().nth(1).unwrap();
+        let mut cursor = Cursor::at(input, line, 1);
+        assert_eq!(cursor.peek(), Some((1, "b {")));
+        assert_eq!(
+            cursor.next_if(|(_, line)| line.starts_with('b')),
+            Some((1, "b {"))
+        );
+        assert_eq!(cursor.next_if(|(_, line)| line.starts_with('b')), None);
+        let rest: Vec<(usize, &str)> = cursor.collect();
+        assert_eq!(rest, [(2, "  c"), (3, "}"), (4, "d")]);
+
+        // From the middle of a line, the cursor starts at its beginning
+        let mut cursor = Cursor::at(input, &input[8..9], 2);
+        assert_eq!(cursor.next(), Some((2, "  c")));
+        assert_eq!(Cursor::at(input, "elsewhere", 0).next(), None);
+    }
+
+    #[test]
+    fn test_line_number() {
+        let input = "a\nb\nc\nd";
+        assert_eq!(line_number(input, 0), 1);
+        assert_eq!(line_number(input, 6), 4);
+
+        // A cursor counts from the line it last read, in either direction
+        let mut cursor = Cursor::new(input);
+        assert_eq!(cursor.line_of(&input[6..]), Some(4));
+        cursor.next();
+        cursor.next();
+        assert_eq!(cursor.line_of(&input[6..]), Some(4));
+        assert_eq!(cursor.line_of(&input[2..3]), Some(2));
+        assert_eq!(cursor.line_of(&input[..1]), Some(1));
+        assert_eq!(cursor.line_of("elsewhere"), None);
+        let cursor = Cursor::at(input, &input[4..5], 2);
+        assert_eq!(cursor.line_of(&input[0..1]), Some(1));
+        assert_eq!(cursor.location(&input[6..]).map(|l| l.line), Some(4));
+    }
+}
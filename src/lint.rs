@@ -0,0 +1,1010 @@
+//! Checks for likely mistakes in documents, see [`Linter`]
+//!
+//! A [`Linter`] parses a document, recovering from errors, and runs rules
+//! over the result. Each rule has an ID, reported with its [`Finding`]s and
+//! used to turn it off or change its [`Severity`]. The built-in rules are:
+//!
+//! - `syntax` (error): text that does not parse
+//! - `unclosed-delimiter` (error): a block, list, table, or multiline
+//!   string that is never closed, or a closer with nothing open
+//! - `duplicate-key` (warning): a key defined twice in the same block, or
+//!   at the top level
+//! - `unknown-annotation` (warning): an annotation missing from the
+//!   linter's [`AnnotationCatalog`]
+//! - `inconsistent-indentation` (warning): a key indented differently from
+//!   the rest of the document, or with both tabs and spaces
+//! - `empty-block` (warning): a block with nothing in it
+//! - `unused-anchor` (warning): an `!anchor` that no `!ref` copies
+//! - `unused-var` (warning): a `@let` or `!vars` variable that no `${...}`
+//!   refers to
+//! - `slash-comment` (warning): a line starting with `//`, which is a key
+//!   unless the parser reads [`slash_comments`](Parser::slash_comments),
+//!   and a comment style other UP tools do not read either way
+//!
+//! With [`Linter::suggestions`], rules that suggest a more compact layout
+//! also run, all as warnings:
+//!
+//! - `use-table`: a list of at least three blocks with the same keys, which
+//!   is shorter as a `!table`
+//! - `repeated-block`: a block of two or more entries written out again
+//!   elsewhere, which could be defined once as an `!anchor` and copied with
+//!   `!ref`
+//! - `large-file`: a document of more than 500 lines, with its largest
+//!   blocks as candidates for files of their own read with `@include`
+//!
+//! Applications add their own rules by implementing [`Lint`]:
+//!
+//! ```
+//! use uplang::lint::{Lint, Linter, Report};
+//! use uplang::Node;
+//!
+//! struct NoTodo;
+//!
+//! impl Lint for NoTodo {
+//!     fn id(&self) -> &str {
+//!         "no-todo"
+//!     }
+//!
+//!     fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+//!         if node.value.as_str().is_some_and(|s| s.contains("TODO")) {
+//!             report.node(node, path, format!("`{}` is not finished", path));
+//!         }
+//!     }
+//! }
+//!
+//! let findings = Linter::new().rule(NoTodo).lint("name app\nowner TODO\n");
+//! assert_eq!(findings.len(), 1);
+//! assert_eq!(findings[0].to_string(), "line 2: warning: `owner` is not finished [no-todo]");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+pub use crate::policy::Severity;
+use crate::types::AnnotationCatalog;
+use crate::{Document, DuplicateKeys, ErrorKind, Node, Parser, Value};
+
+const SYNTAX: &str = "syntax";
+const UNCLOSED_DELIMITER: &str = "unclosed-delimiter";
+const DUPLICATE_KEY: &str = "duplicate-key";
+
+/// A rule run by a [`Linter`]
+pub trait Lint: Send + Sync {
+    /// Identifier reported with the rule's findings and used to configure
+    /// it, such as `empty-block`
+    fn id(&self) -> &str;
+
+    /// Severity of the rule's findings unless the linter is set to another
+    /// (default [`Severity::Warning`])
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Check one node, at key path `path`
+    ///
+    /// Called for every node of the document in the order of
+    /// [`Document::walk`], parents before children. Nodes parsed by the
+    /// linter carry their [`span`](Node::span).
+    fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+        let _ = (node, path, report);
+    }
+
+    /// Check the document as a whole, parsed from `input`, after its nodes
+    fn check_document(&self, input: &str, document: &Document, report: &mut Report<'_>) {
+        let _ = (input, document, report);
+    }
+}
+
+/// Where a [`Lint`] rule reports what it finds
+pub struct Report<'a> {
+    rule: &'a str,
+    severity: Severity,
+    findings: &'a mut Vec<Finding>,
+}
+
+impl Report<'_> {
+    /// Report a problem with `node`, at key path `path`, on its first line
+    pub fn node(&mut self, node: &Node, path: &str, message: impl Into<String>) {
+        let line = match (&node.span, &node.origin) {
+            (Some(span), _) => Some(span.start_line),
+            (None, Some(origin)) => Some(origin.line),
+            (None, None) => None,
+        };
+        self.push(Some(path.to_string()), line, message.into());
+    }
+
+    /// Report a problem on 1-based line `line`
+    pub fn line(&mut self, line: usize, message: impl Into<String>) {
+        self.push(None, Some(line), message.into());
+    }
+
+    fn push(&mut self, path: Option<String>, line: Option<usize>, message: String) {
+        self.findings.push(Finding {
+            rule: self.rule.to_string(),
+            severity: self.severity,
+            message,
+            path,
+            line,
+        });
+    }
+}
+
+/// One problem found by a [`Linter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// ID of the rule that found it
+    pub rule: String,
+    /// How bad it is
+    pub severity: Severity,
+    /// What is wrong
+    pub message: String,
+    /// Key path of the node concerned, if there is one
+    pub path: Option<String>,
+    /// 1-based line, if the problem has one
+    pub line: Option<usize>,
+}
+
+impl Finding {
+    /// Whether the problem is an error rather than a warning
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if self.severity == Severity::Warning {
+            f.write_str("warning: ")?;
+        }
+        write!(f, "{} [{}]", self.message, self.rule)
+    }
+}
+
+/// Runs lint rules over documents, see the [module docs](self)
+///
+/// ```
+/// use uplang::lint::{Linter, Severity};
+///
+/// let input = "server {\n  host a\n   port!int 80\n}\ncache {\n}\n";
+/// let findings = Linter::new().severity("empty-block", Severity::Error).lint(input);
+/// let rules: Vec<&str> = findings.iter().map(|f| f.rule.as_str()).collect();
+/// assert_eq!(rules, ["inconsistent-indentation", "empty-block"]);
+/// assert!(findings[1].is_error());
+///
+/// let findings = Linter::new().allow("empty-block").lint(input);
+/// assert_eq!(findings.len(), 1);
+/// ```
+pub struct Linter {
+    parser: Parser,
+    catalog: AnnotationCatalog,
+    rules: Vec<Box<dyn Lint>>,
+    allowed: HashSet<String>,
+    severities: HashMap<String, Severity>,
+    suggestions: bool,
+}
+
+impl Linter {
+    /// Create a linter with the built-in rules, knowing the built-in
+    /// annotations
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            catalog: AnnotationCatalog::builtin(),
+            rules: Vec::new(),
+            allowed: HashSet::new(),
+            severities: HashMap::new(),
+            suggestions: false,
+        }
+    }
+
+    /// Parse documents with `parser`, for its list separator, comment
+    /// characters, types, and the like (default [`Parser::new`])
+    ///
+    /// Spans, strict delimiters, and duplicate keys as errors are turned on
+    /// whatever the parser's settings, so the rules can see them.
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Set the annotations `unknown-annotation` accepts (default
+    /// [`AnnotationCatalog::builtin`])
+    pub fn annotations(mut self, catalog: AnnotationCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    /// Add a rule, run after the built-in ones
+    pub fn rule(mut self, rule: impl Lint + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Turn off the rule with ID `rule`
+    pub fn allow(mut self, rule: impl Into<String>) -> Self {
+        self.allowed.insert(rule.into());
+        self
+    }
+
+    /// Report the findings of the rule with ID `rule` as `severity`
+    pub fn severity(mut self, rule: impl Into<String>, severity: Severity) -> Self {
+        self.severities.insert(rule.into(), severity);
+        self
+    }
+
+    /// Also run the rules that suggest a more compact layout, such as
+    /// `use-table` (default off); see the [module docs](self)
+    ///
+    /// ```
+    /// use uplang::lint::Linter;
+    ///
+    /// let input = "users [\n  { id 1, name a }\n  { id 2, name b }\n  { id 3, name c }\n]\n";
+    /// assert!(Linter::new().lint(input).is_empty());
+    /// let findings = Linter::new().suggestions(true).lint(input);
+    /// assert_eq!(
+    ///     findings[0].to_string(),
+    ///     "line 1: warning: `users` holds 3 blocks with the same keys; write it as a `!table` [use-table]"
+    /// );
+    /// ```
+    pub fn suggestions(mut self, suggest: bool) -> Self {
+        self.suggestions = suggest;
+        self
+    }
+
+    /// Check a document, returning what the rules find in line order
+    pub fn lint(&self, input: &str) -> Vec<Finding> {
+        let parser = self
+            .parser
+            .clone()
+            .spans(true)
+            .strict(true)
+            .duplicate_keys(DuplicateKeys::Error);
+        let (document, diagnostics) = parser.parse_with_recovery(input);
+
+        let mut findings = Vec::new();
+        for diagnostic in diagnostics {
+            let error = diagnostic.error;
+            let rule = match error.kind {
+                ErrorKind::UnclosedDelimiter => UNCLOSED_DELIMITER,
+                ErrorKind::InvalidSyntax if error.message.starts_with("unexpected ") => {
+                    UNCLOSED_DELIMITER
+                }
+                ErrorKind::DuplicateKey => DUPLICATE_KEY,
+                _ => SYNTAX,
+            };
+            let default = match rule {
+                DUPLICATE_KEY => Severity::Warning,
+                _ => Severity::Error,
+            };
+            if let Some(mut report) = self.report(rule, default, &mut findings) {
+                let line = error.line();
+                report.push(None, line, error.message);
+            }
+        }
+
+        let unknown = UnknownAnnotation(&self.catalog);
+        let builtin: [&dyn Lint; 7] = [
+            &DuplicateKey,
+            &unknown,
+            &Indentation,
+            &EmptyBlock,
+            &UnusedAnchor,
+            &UnusedVar,
+            &SlashComment,
+        ];
+        let suggestions: [&dyn Lint; 3] = [&UseTable, &RepeatedBlock, &LargeFile];
+        let suggestions = match self.suggestions {
+            true => &suggestions[..],
+            false => &[],
+        };
+        let nodes: Vec<(String, &Node)> = document.walk().collect();
+        for rule in builtin
+            .into_iter()
+            .chain(suggestions.iter().copied())
+            .chain(self.rules.iter().map(|rule| &**rule))
+        {
+            let Some(mut report) = self.report(rule.id(), rule.severity(), &mut findings) else {
+                continue;
+            };
+            for (path, node) in &nodes {
+                rule.check_node(node, path, &mut report);
+            }
+            rule.check_document(input, &document, &mut report);
+        }
+        findings.sort_by_key(|finding| finding.line.unwrap_or(usize::MAX));
+        findings
+    }
+
+    /// Where the rule `rule` reports, unless it is turned off
+    fn report<'a>(
+        &self,
+        rule: &'a str,
+        default: Severity,
+        findings: &'a mut Vec<Finding>,
+    ) -> Option<Report<'a>> {
+        if self.allowed.contains(rule) {
+            return None;
+        }
+        Some(Report {
+            rule,
+            severity: self.severities.get(rule).copied().unwrap_or(default),
+            findings,
+        })
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keys repeated at the top level, which the parser keeps as separate
+/// nodes; repeats inside blocks are reported by the parser
+struct DuplicateKey;
+
+impl Lint for DuplicateKey {
+    fn id(&self) -> &str {
+        DUPLICATE_KEY
+    }
+
+    fn check_document(&self, _input: &str, document: &Document, report: &mut Report<'_>) {
+        for duplicate in document.duplicates() {
+            let mut lines = duplicate.origins.iter().flatten().map(|o| o.line);
+            let Some(first) = lines.next() else {
+                continue;
+            };
+            for line in lines {
+                report.line(
+                    line,
+                    format!(
+                        "duplicate key `{}` (first defined on line {})",
+                        duplicate.path, first
+                    ),
+                );
+            }
+        }
+    }
+}
+
+struct UnknownAnnotation<'a>(&'a AnnotationCatalog);
+
+impl Lint for UnknownAnnotation<'_> {
+    fn id(&self) -> &str {
+        "unknown-annotation"
+    }
+
+    fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+        if let Some(annotation) = &node.type_annotation {
+            if !self.0.contains(annotation) {
+                report.node(
+                    node,
+                    path,
+                    format!("`{}` has an unknown annotation `!{}`", path, annotation),
+                );
+            }
+        }
+    }
+}
+
+/// Keys indented by a different amount than their depth calls for, in the
+/// unit of the first indented key
+struct Indentation;
+
+impl Lint for Indentation {
+    fn id(&self) -> &str {
+        "inconsistent-indentation"
+    }
+
+    fn check_document(&self, input: &str, document: &Document, report: &mut Report<'_>) {
+        let mut check = IndentCheck {
+            lines: input.lines().collect(),
+            unit: None,
+            report,
+        };
+        for node in &document.nodes {
+            check.node(node, &node.key, 0, None);
+        }
+    }
+}
+
+struct IndentCheck<'i, 'r, 'a> {
+    lines: Vec<&'i str>,
+    /// The indentation of one level, once known
+    unit: Option<String>,
+    report: &'r mut Report<'a>,
+}
+
+impl IndentCheck<'_, '_, '_> {
+    /// Check `node`, at `depth`, and the nodes in it; `parent_line` is the
+    /// line of the node holding it
+    fn node(&mut self, node: &Node, path: &str, depth: usize, parent_line: Option<usize>) {
+        // Nodes without a span, such as those of `!up` values, are checked
+        // with the text they came from
+        let Some(span) = &node.span else {
+            return;
+        };
+        let line = span.start_line;
+        if parent_line != Some(line) {
+            if let Some(text) = self.lines.get(line - 1) {
+                let indent = &text[..text.len() - text.trim_start().len()];
+                self.indent(node, path, depth, indent);
+            }
+        }
+        self.value(&node.value, path, depth + 1, line);
+    }
+
+    fn value(&mut self, value: &Value, path: &str, depth: usize, line: usize) {
+        match value {
+            Value::Block(block) => {
+                for child in block.nodes() {
+                    let path = format!("{}.{}", path, child.key);
+                    self.node(child, &path, depth, Some(line));
+                }
+            }
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.value(item, &format!("{}[{}]", path, i), depth + 1, line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn indent(&mut self, node: &Node, path: &str, depth: usize, indent: &str) {
+        if indent.contains(' ') && indent.contains('\t') {
+            self.report.node(
+                node,
+                path,
+                format!("`{}` is indented with both tabs and spaces", path),
+            );
+            return;
+        }
+        let expected = match &self.unit {
+            _ if depth == 0 => String::new(),
+            Some(unit) => unit.repeat(depth),
+            // The first indented key sets the unit
+            None if !indent.is_empty() && indent.len() % depth == 0 => {
+                self.unit = Some(indent[..indent.len() / depth].to_string());
+                return;
+            }
+            None => {
+                self.report.node(
+                    node,
+                    path,
+                    format!(
+                        "`{}` is indented by {}, which is not {} equal levels",
+                        path,
+                        describe(indent),
+                        depth
+                    ),
+                );
+                return;
+            }
+        };
+        if indent != expected {
+            self.report.node(
+                node,
+                path,
+                format!(
+                    "`{}` is indented by {}; expected {}",
+                    path,
+                    describe(indent),
+                    describe(&expected)
+                ),
+            );
+        }
+    }
+}
+
+/// How much `indent` is, such as `4 spaces`
+fn describe(indent: &str) -> String {
+    match (indent.len(), indent.starts_with('\t')) {
+        (0, _) => "nothing".to_string(),
+        (1, true) => "1 tab".to_string(),
+        (n, true) => format!("{} tabs", n),
+        (1, false) => "1 space".to_string(),
+        (n, false) => format!("{} spaces", n),
+    }
+}
+
+struct EmptyBlock;
+
+impl Lint for EmptyBlock {
+    fn id(&self) -> &str {
+        "empty-block"
+    }
+
+    fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+        if matches!(&node.value, Value::Block(block) if block.is_empty()) {
+            report.node(node, path, format!("`{}` is an empty block", path));
+        }
+    }
+}
+
+/// Lines starting with `//`, outside multiline strings
+struct SlashComment;
+
+impl Lint for SlashComment {
+    fn id(&self) -> &str {
+        "slash-comment"
+    }
+
+    fn check_document(&self, input: &str, document: &Document, report: &mut Report<'_>) {
+        let mut keys = HashSet::new();
+        let mut bodies = Vec::new();
+        for (_, node) in document.walk() {
+            let Some(span) = &node.span else {
+                continue;
+            };
+            keys.insert(span.start_line);
+            if matches!(node.value, Value::String(_)) && span.end_line > span.start_line {
+                bodies.push(span.start_line + 1..span.end_line + 1);
+            }
+        }
+        for (i, line) in input.lines().enumerate() {
+            let line_num = i + 1;
+            if !line.trim_start().starts_with("//")
+                || bodies.iter().any(|body| body.contains(&line_num))
+            {
+                continue;
+            }
+            let message = match keys.contains(&line_num) {
+                true => "`//` is read as a key here, not a comment; write comments with `#`",
+                false => "write comments with `#`, not `//`",
+            };
+            report.line(line_num, message);
+        }
+    }
+}
+
+struct UnusedAnchor;
+
+impl Lint for UnusedAnchor {
+    fn id(&self) -> &str {
+        "unused-anchor"
+    }
+
+    fn check_document(&self, _input: &str, document: &Document, report: &mut Report<'_>) {
+        let nodes: Vec<(String, &Node)> = document.walk().collect();
+        let copied: HashSet<&str> = nodes
+            .iter()
+            .filter_map(|(_, node)| node.anchor.as_deref())
+            .collect();
+        for (path, node) in &nodes {
+            if node.type_annotation.as_deref() == Some("anchor")
+                && !copied.contains(node.key.as_str())
+            {
+                report.node(
+                    node,
+                    path,
+                    format!("anchor `{}` is never copied with `!ref`", node.key),
+                );
+            }
+        }
+    }
+}
+
+/// `@let` variables and the values of `!vars` blocks that no `${...}` in
+/// the text refers to
+struct UnusedVar;
+
+impl Lint for UnusedVar {
+    fn id(&self) -> &str {
+        "unused-var"
+    }
+
+    fn check_document(&self, input: &str, document: &Document, report: &mut Report<'_>) {
+        let names: Vec<&str> = references(input)
+            .into_iter()
+            .flat_map(|reference| {
+                reference.split(|c: char| !(c.is_alphanumeric() || "_-.[]".contains(c)))
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+        let used = |var: &str| names.iter().any(|name| overlaps(name, var));
+
+        for (i, line) in input.lines().enumerate() {
+            let Some(definition) = crate::let_definition(line.trim()) else {
+                continue;
+            };
+            let name = definition.split_whitespace().next().unwrap_or_default();
+            if !name.is_empty() && !used(name) {
+                report.line(i + 1, format!("variable `{}` is never used", name));
+            }
+        }
+        for node in &document.nodes {
+            if node.type_annotation.as_deref() == Some("vars") {
+                unused_vars(node, &node.key, &used, report);
+            }
+        }
+    }
+}
+
+/// Lists of uniform blocks, which [`Value::to_table`] can turn into tables
+struct UseTable;
+
+impl Lint for UseTable {
+    fn id(&self) -> &str {
+        "use-table"
+    }
+
+    fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+        let Value::List(items) = &node.value else {
+            return;
+        };
+        if items.len() >= 3 && node.type_annotation.is_none() && node.value.to_table().is_some() {
+            report.node(
+                node,
+                path,
+                format!(
+                    "`{}` holds {} blocks with the same keys; write it as a `!table`",
+                    path,
+                    items.len()
+                ),
+            );
+        }
+    }
+}
+
+/// Blocks written out more than once, reported at their first copy
+///
+/// Blocks inside a repeated block repeat with it, so they are not reported
+/// again; nor are blocks already copied with `!ref`.
+struct RepeatedBlock;
+
+impl Lint for RepeatedBlock {
+    fn id(&self) -> &str {
+        "repeated-block"
+    }
+
+    fn check_document(&self, _input: &str, document: &Document, report: &mut Report<'_>) {
+        let nodes: Vec<(String, &Node)> = document.walk().collect();
+        let mut copies: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut order = Vec::new();
+        for (i, (_, node)) in nodes.iter().enumerate() {
+            let Value::Block(block) = &node.value else {
+                continue;
+            };
+            if block.len() < 2 || node.anchor.is_some() {
+                continue;
+            }
+            let text = node.value.to_string();
+            let found = copies.entry(text.clone()).or_default();
+            if found.is_empty() {
+                order.push(text);
+            }
+            found.push(i);
+        }
+
+        let mut reported: Vec<&str> = Vec::new();
+        for text in &order {
+            let found = &copies[text];
+            let paths: Vec<&str> = found
+                .iter()
+                .map(|&i| nodes[i].0.as_str())
+                .filter(|path| !reported.iter().any(|outer| overlaps(outer, path)))
+                .collect();
+            let [first, others @ ..] = &paths[..] else {
+                continue;
+            };
+            if others.is_empty() {
+                continue;
+            }
+            let others: Vec<String> = others.iter().map(|path| format!("`{}`", path)).collect();
+            report.node(
+                nodes[found[0]].1,
+                first,
+                format!(
+                    "`{}` is repeated at {}; define it once as an `!anchor` and copy it with `!ref`",
+                    first,
+                    others.join(", ")
+                ),
+            );
+            reported.extend(paths);
+        }
+    }
+}
+
+/// Documents too long to review comfortably
+struct LargeFile;
+
+impl LargeFile {
+    const MAX_LINES: usize = 500;
+}
+
+impl Lint for LargeFile {
+    fn id(&self) -> &str {
+        "large-file"
+    }
+
+    fn check_document(&self, input: &str, document: &Document, report: &mut Report<'_>) {
+        let lines = input.lines().count();
+        if lines <= Self::MAX_LINES {
+            return;
+        }
+        let mut blocks: Vec<(usize, &str)> = document
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.value, Value::Block(_) | Value::List(_)))
+            .filter_map(|node| {
+                let span = node.span.as_ref()?;
+                Some((span.end_line - span.start_line + 1, node.key.as_str()))
+            })
+            .collect();
+        blocks.sort_by_key(|&(size, _)| std::cmp::Reverse(size));
+        let largest: Vec<String> = blocks
+            .iter()
+            .take(3)
+            .map(|(size, key)| format!("`{}` ({} lines)", key, size))
+            .collect();
+        let mut message = format!(
+            "the document has {} lines, more than {}",
+            lines,
+            Self::MAX_LINES
+        );
+        if !largest.is_empty() {
+            message.push_str(&format!(
+                "; move its largest parts, {}, to files read with `@include`",
+                largest.join(", ")
+            ));
+        }
+        report.line(1, message);
+    }
+}
+
+/// Report the values in `node`, at `path`, that `used` says are not
+fn unused_vars(node: &Node, path: &str, used: &dyn Fn(&str) -> bool, report: &mut Report<'_>) {
+    match &node.value {
+        Value::Block(block) => {
+            for child in block.nodes() {
+                unused_vars(child, &format!("{}.{}", path, child.key), used, report);
+            }
+        }
+        _ if !used(path) => {
+            report.node(node, path, format!("variable `{}` is never used", path));
+        }
+        _ => {}
+    }
+}
+
+/// The text inside each `${...}` of `input`, leaving out escaped `$${...}`
+fn references(input: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        let escaped = rest[..start].ends_with('$');
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        if !escaped {
+            found.push(rest[..end].trim());
+        }
+        rest = &rest[end + 1..];
+    }
+    found
+}
+
+/// Whether key paths `a` and `b` are the same, or one is inside the other
+fn overlaps(a: &str, b: &str) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    long.strip_prefix(short)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Annotation, Shape};
+
+    fn rules(findings: &[Finding]) -> Vec<(&str, Option<usize>)> {
+        findings.iter().map(|f| (f.rule.as_str(), f.line)).collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let input = "\
+name app
+name other
+server {
+  host a
+  host b
+  tls!tls on
+  empty {
+  }
+    port!int 80
+}
+defaults!anchor {
+  x 1
+}
+@let unused 1
+@let region eu
+zone ${region}-1
+vars!vars {
+  host db
+  port 5432
+}
+url ${vars.host}
+";
+        let findings = Linter::new().lint(input);
+        assert_eq!(
+            rules(&findings),
+            [
+                ("duplicate-key", Some(2)),
+                ("duplicate-key", Some(5)),
+                ("unknown-annotation", Some(6)),
+                ("empty-block", Some(7)),
+                ("inconsistent-indentation", Some(9)),
+                ("unused-anchor", Some(11)),
+                ("unused-var", Some(14)),
+                ("unused-var", Some(19)),
+            ]
+        );
+        assert_eq!(
+            findings[1].to_string(),
+            "line 5: warning: duplicate key `host` (first defined on line 4) [duplicate-key]"
+        );
+        assert_eq!(
+            findings[4].message,
+            "`server.port` is indented by 4 spaces; expected 2 spaces"
+        );
+        assert_eq!(findings[4].path.as_deref(), Some("server.port"));
+        assert_eq!(findings[7].message, "variable `vars.port` is never used");
+        assert!(findings.iter().all(|f| !f.is_error()));
+
+        assert!(Linter::new()
+            .lint("a {\n  b {\n    c 1\n  }\n}\nd [\n  {\n    e 2\n  }\n]\n")
+            .is_empty());
+        let findings = Linter::new().lint("a {\n\tb 1\n\t c 2\n}\n");
+        assert_eq!(
+            findings[0].message,
+            "`a.c` is indented with both tabs and spaces"
+        );
+    }
+
+    #[test]
+    fn test_slash_comments() {
+        let input =
+            "// Service\nname app\nnote ```\n// kept\n```\nserver {\n  //port 80\n  port 8080\n}\n";
+        let findings = Linter::new().lint(input);
+        assert_eq!(
+            rules(&findings),
+            [("slash-comment", Some(1)), ("slash-comment", Some(7))]
+        );
+        assert_eq!(
+            findings[0].message,
+            "`//` is read as a key here, not a comment; write comments with `#`"
+        );
+
+        let findings = Linter::new()
+            .parser(Parser::new().slash_comments(true))
+            .lint(input);
+        assert_eq!(
+            rules(&findings),
+            [("slash-comment", Some(1)), ("slash-comment", Some(7))]
+        );
+        assert_eq!(findings[0].message, "write comments with `#`, not `//`");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let findings = Linter::new().lint("a!int x\nb {\n  c 1\n}\n}\nd [\n  e\n");
+        assert_eq!(
+            rules(&findings),
+            [
+                ("syntax", Some(1)),
+                ("unclosed-delimiter", Some(5)),
+                ("unclosed-delimiter", Some(6)),
+            ]
+        );
+        assert!(findings.iter().all(Finding::is_error));
+        assert_eq!(
+            findings[2].to_string(),
+            "line 6: list is never closed [unclosed-delimiter]"
+        );
+    }
+
+    #[test]
+    fn test_configure_rules() {
+        struct NoDebug;
+
+        impl Lint for NoDebug {
+            fn id(&self) -> &str {
+                "no-debug"
+            }
+
+            fn severity(&self) -> Severity {
+                Severity::Error
+            }
+
+            fn check_node(&self, node: &Node, path: &str, report: &mut Report<'_>) {
+                if node.value.as_str() == Some("debug") {
+                    let line = node.span.as_ref().map(|span| span.start_line);
+                    assert_eq!(line, Some(3));
+                    report.node(node, path, "debug logging");
+                }
+            }
+        }
+
+        let input = "id!uuid 42\nlog {\n  level debug\n}\n";
+        let findings = Linter::new().rule(NoDebug).lint(input);
+        assert_eq!(
+            rules(&findings),
+            [("unknown-annotation", Some(1)), ("no-debug", Some(3))]
+        );
+        assert!(findings[1].is_error());
+        assert_eq!(findings[1].path.as_deref(), Some("log.level"));
+
+        let mut catalog = AnnotationCatalog::builtin();
+        catalog.register(Annotation::new("uuid", Shape::Scalar, "UUID"));
+        let findings = Linter::new()
+            .annotations(catalog)
+            .rule(NoDebug)
+            .severity("no-debug", Severity::Warning)
+            .lint(input);
+        assert_eq!(rules(&findings), [("no-debug", Some(3))]);
+        assert!(!findings[0].is_error());
+        assert!(Linter::new()
+            .rule(NoDebug)
+            .allow("no-debug")
+            .allow("unknown-annotation")
+            .lint(input)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let input = "\
+staging {
+  db {
+    host db.internal
+    port!int 5432
+  }
+}
+production {
+  db {
+    host db.internal
+    port!int 5432
+  }
+}
+backup {
+  host db.internal
+  port!int 5432
+}
+hosts [
+  { name a, port 1 }
+  { name b, port 2 }
+  { name c, port 3 }
+]
+pair [
+  { name a }
+  { name b }
+]
+";
+        let findings = Linter::new().suggestions(true).lint(input);
+        assert_eq!(
+            rules(&findings),
+            [("repeated-block", Some(2)), ("use-table", Some(17))]
+        );
+        assert_eq!(
+            findings[0].message,
+            "`staging.db` is repeated at `production.db`, `backup`; define it once as an `!anchor` and copy it with `!ref`"
+        );
+
+        let mut large = String::from("server {\n");
+        for i in 0..600 {
+            large.push_str(&format!("  key{} {}\n", i, i));
+        }
+        large.push_str("}\nname app\n");
+        let findings = Linter::new().suggestions(true).lint(&large);
+        assert_eq!(
+            findings[0].to_string(),
+            "line 1: warning: the document has 603 lines, more than 500; move its largest parts, `server` (602 lines), to files read with `@include` [large-file]"
+        );
+        assert!(Linter::new().lint(&large).is_empty());
+    }
+}
@@ -0,0 +1,821 @@
+//! One-call configuration loading for applications
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::schema::{Schema, Violation};
+use crate::{
+    diff, types, Change, ConfigHandle, Document, ErrorKind, FromUp, FromUpError, MergeStrategy,
+    ParseError, Parser, Value,
+};
+
+/// Parses, validates, and decodes an application's configuration
+///
+/// The glue every application writes around this crate: read one or more
+/// files, directories, or strings with a configured [`Parser`] (which
+/// resolves `@let` variables and `${...}` interpolation), layer them with a
+/// deep merge, apply overrides from environment variables, check the result
+/// against a [`Schema`], and read it into a type with [`FromUp`]. Each step
+/// reports failure through the one [`ConfigError`]. With
+/// [`watch`](Self::watch), the configuration is loaded again whenever one of
+/// its files changes.
+///
+/// ```
+/// use uplang::{ConfigLoader, FromUp, FromUpError, Value};
+/// use uplang::schema::Schema;
+///
+/// struct Config {
+///     port: u16,
+/// }
+///
+/// impl FromUp for Config {
+///     fn from_up(value: &Value) -> Result<Self, FromUpError> {
+///         let Value::Block(block) = value else {
+///             return Err(FromUpError::expected("block", value));
+///         };
+///         let port = block.get("port").ok_or_else(|| FromUpError::new("no port"))?;
+///         Ok(Config { port: u16::from_up(port)? })
+///     }
+/// }
+///
+/// let config: Config = ConfigLoader::new()
+///     .source("@let base 8000\nport!int ${base + 80}")
+///     .schema(Schema::parse("port!int").unwrap())
+///     .load()
+///     .unwrap();
+/// assert_eq!(config.port, 8080);
+///
+/// let err = ConfigLoader::new()
+///     .source("port high")
+///     .schema(Schema::parse("port!int").unwrap())
+///     .load_document()
+///     .unwrap_err();
+/// assert_eq!(err.to_string(), "line 1: `port` should be int, found `high`");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    parser: Parser,
+    sources: Vec<Source>,
+    schema: Option<Schema>,
+    env_prefix: Option<String>,
+    poll_interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    File(PathBuf),
+    OptionalFile(PathBuf),
+    Dir(PathBuf),
+    Text(String),
+}
+
+/// Why [`ConfigLoader`] failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// A source could not be read or parsed
+    Parse(ParseError),
+    /// The document does not match the schema; holds every violation
+    Schema(Vec<Violation>),
+    /// The document could not be read into the requested type
+    Decode(FromUpError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(e) => e.fmt(f),
+            ConfigError::Schema(violations) => {
+                for (i, violation) in violations.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("\n")?;
+                    }
+                    violation.fmt(f)?;
+                }
+                Ok(())
+            }
+            ConfigError::Decode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::Schema(_) => None,
+            ConfigError::Decode(e) => Some(e),
+        }
+    }
+}
+
+impl From<ParseError> for ConfigError {
+    fn from(e: ParseError) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl From<FromUpError> for ConfigError {
+    fn from(e: FromUpError) -> Self {
+        ConfigError::Decode(e)
+    }
+}
+
+impl ConfigLoader {
+    /// Create a loader with the default parser and no sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse with `parser` instead of [`Parser::new`]
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Add a file
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File(path.into()));
+        self
+    }
+
+    /// Add a file that is skipped if it does not exist, such as local
+    /// overrides kept out of version control
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::OptionalFile(path.into()));
+        self
+    }
+
+    /// Add every `*.up` file in a directory, see [`Parser::parse_dir`]
+    pub fn dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::Dir(path.into()));
+        self
+    }
+
+    /// Add UP text, such as built-in defaults
+    pub fn source(mut self, text: impl Into<String>) -> Self {
+        self.sources.push(Source::Text(text.into()));
+        self
+    }
+
+    /// Fill in the schema's defaults for missing keys, then validate the
+    /// document against it
+    ///
+    /// Deprecated keys alone do not make loading fail; when another
+    /// violation does, [`ConfigError::Schema`] lists them too.
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Override values with the environment variables whose names start
+    /// with `prefix`, after every source has been read
+    ///
+    /// The rest of the name is the key path, lowercased, with `__` between
+    /// keys: `APP_SERVER__PORT` sets `server.port`, and `APP_LOG_LEVEL` sets
+    /// `log_level`. A variable replacing a typed value, such as an `!int`,
+    /// is read as that type. Loading fails on a variable with the prefix
+    /// whose name or value is not valid Unicode.
+    pub fn env_overrides(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// How often [`watch`](Self::watch) checks the files for changes
+    /// (default 1 second)
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Parse the sources in the order they were added, each overriding the
+    /// ones before it as in a [`MergeStrategy::Deep`] merge, then apply
+    /// environment overrides and complete and validate the result
+    pub fn load_document(&self) -> Result<Document, ConfigError> {
+        self.load_with(&self.parser)
+    }
+
+    /// Load the document, noting the documents included along the way,
+    /// up to the error if loading fails
+    fn load_tracked(&self) -> (Result<Document, ConfigError>, Vec<PathBuf>) {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let mut parser = self.parser.clone();
+        parser.includes.record(Arc::clone(&found));
+        let loaded = self.load_with(&parser);
+        let mut included = found.lock().map(|found| found.clone()).unwrap_or_default();
+        included.sort();
+        included.dedup();
+        (loaded, included)
+    }
+
+    fn load_with(&self, parser: &Parser) -> Result<Document, ConfigError> {
+        let mut doc = Document::new();
+        for source in &self.sources {
+            let part = match source {
+                Source::File(path) => parser.parse_file(path)?,
+                Source::OptionalFile(path) if !path.exists() => continue,
+                Source::OptionalFile(path) => parser.parse_file(path)?,
+                Source::Dir(path) => parser.parse_dir(path)?,
+                Source::Text(text) => parser.parse_document(text)?,
+            };
+            doc = doc.merge(&part, MergeStrategy::Deep);
+        }
+        if let Some(prefix) = &self.env_prefix {
+            apply_env(&mut doc, prefix, std::env::vars_os())?;
+        }
+
+        if let Some(schema) = &self.schema {
+            schema.fill_defaults(&mut doc);
+            let violations = schema.validate(&doc);
+            if violations.iter().any(Violation::is_error) {
+                return Err(ConfigError::Schema(violations));
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Load the document and read it into `T`
+    pub fn load<T: FromUp>(&self) -> Result<T, ConfigError> {
+        let doc = self.load_document()?;
+        Ok(T::from_document(&doc)?)
+    }
+
+    /// Load the document, then load it again in the background whenever
+    /// one of its files or directories changes
+    ///
+    /// The files brought in by `@include` lines are watched too, as the
+    /// latest load found them: an include added or removed by an edit is
+    /// watched, or not, from that reload on. After a failed reload the
+    /// includes of the last good load stay watched as well.
+    /// Files are checked every [`poll_interval`](Self::poll_interval). Each
+    /// new document is stored in the watcher's [`ConfigHandle`] and passed
+    /// to `on_change`; a reload that fails, such as after a broken edit,
+    /// keeps the previous document and passes the error instead. Watching
+    /// stops when the watcher is dropped.
+    pub fn watch<F>(self, on_change: F) -> Result<ConfigWatcher, ConfigError>
+    where
+        F: Fn(Result<Arc<Document>, ConfigError>) + Send + 'static,
+    {
+        self.watch_reloads(move |handle, reloaded| match reloaded {
+            Ok(doc) => {
+                handle.store(doc);
+                on_change(Ok(handle.load()));
+            }
+            Err(e) => on_change(Err(e)),
+        })
+    }
+
+    /// As [`watch`](Self::watch), but pass `on_change` what changed
+    ///
+    /// Each reload is compared with the document before it by [`diff`], and
+    /// a reload that changes no value, such as one that only edits
+    /// comments, is not reported. Applications can check which keys a
+    /// [`ChangeSet`] touches and react to those alone.
+    ///
+    /// ```no_run
+    /// use uplang::ConfigLoader;
+    ///
+    /// let watcher = ConfigLoader::new()
+    ///     .file("app.up")
+    ///     .watch_changes(|changes| match changes {
+    ///         Ok(changes) if changes.touches("log") => println!("log settings changed"),
+    ///         Ok(_) => {}
+    ///         Err(e) => eprintln!("keeping the old config: {}", e),
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn watch_changes<F>(self, on_change: F) -> Result<ConfigWatcher, ConfigError>
+    where
+        F: Fn(Result<ChangeSet, ConfigError>) + Send + 'static,
+    {
+        self.watch_reloads(move |handle, reloaded| match reloaded {
+            Ok(doc) => {
+                let previous = handle.load();
+                let changes = diff(&previous, &doc);
+                handle.store(doc);
+                if !changes.is_empty() {
+                    on_change(Ok(ChangeSet {
+                        changes,
+                        previous,
+                        current: handle.load(),
+                    }));
+                }
+            }
+            Err(e) => on_change(Err(e)),
+        })
+    }
+
+    /// Load the document, and pass each reload after a change of its files
+    /// to `on_reload` with the handle to store it in
+    fn watch_reloads<F>(self, on_reload: F) -> Result<ConfigWatcher, ConfigError>
+    where
+        F: Fn(&ConfigHandle, Result<Document, ConfigError>) + Send + 'static,
+    {
+        let (loaded, mut included) = self.load_tracked();
+        let handle = ConfigHandle::new(loaded?);
+        let stop = Arc::new(Stop::default());
+        let interval = self.poll_interval.unwrap_or(Duration::from_secs(1));
+        let thread = {
+            let handle = handle.clone();
+            let stop = Arc::clone(&stop);
+            let mut seen = self.fingerprint(&included);
+            thread::spawn(move || {
+                while !stop.wait(interval) {
+                    let now = self.fingerprint(&included);
+                    if now == seen || stop.is_set() {
+                        continue;
+                    }
+                    seen = now;
+                    let (reloaded, mut now_included) = self.load_tracked();
+                    if reloaded.is_err() {
+                        now_included.extend(included.iter().cloned());
+                        now_included.sort();
+                        now_included.dedup();
+                    }
+                    if now_included != included {
+                        included = now_included;
+                        seen = self.fingerprint(&included);
+                    }
+                    on_reload(&handle, reloaded);
+                }
+            })
+        };
+        Ok(ConfigWatcher {
+            handle,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// When each watched file, and each of the `included` ones, was last
+    /// changed, and how large it is
+    fn fingerprint(&self, included: &[PathBuf]) -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+        let stat = |path: &Path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        };
+        let mut files = Vec::new();
+        for source in &self.sources {
+            match source {
+                Source::File(path) | Source::OptionalFile(path) => {
+                    files.push((path.clone(), stat(path)));
+                }
+                Source::Dir(path) => {
+                    let mut entries: Vec<PathBuf> = fs::read_dir(path)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|entry| Some(entry.ok()?.path()))
+                        .collect();
+                    entries.sort();
+                    files.extend(entries.into_iter().map(|path| {
+                        let stat = stat(&path);
+                        (path, stat)
+                    }));
+                }
+                Source::Text(_) => {}
+            }
+        }
+        files.extend(included.iter().map(|path| (path.clone(), stat(path))));
+        files
+    }
+}
+
+/// The configuration kept up to date by [`ConfigLoader::watch`]
+///
+/// Dropping the watcher stops watching.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    handle: ConfigHandle,
+    stop: Arc<Stop>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Tells the watching thread to finish, waking it if it is waiting
+#[derive(Debug, Default)]
+struct Stop {
+    set: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Stop {
+    fn set(&self) {
+        *self.set.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.wake.notify_all();
+    }
+
+    fn is_set(&self) -> bool {
+        *self.set.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait up to `timeout` for the stop, returning whether it came
+    fn wait(&self, timeout: Duration) -> bool {
+        let set = self.set.lock().unwrap_or_else(|e| e.into_inner());
+        let (set, _) = self
+            .wake
+            .wait_timeout_while(set, timeout, |set| !*set)
+            .unwrap_or_else(|e| e.into_inner());
+        *set
+    }
+}
+
+impl ConfigWatcher {
+    /// The handle holding the latest good document, to share with readers
+    pub fn handle(&self) -> &ConfigHandle {
+        &self.handle
+    }
+
+    /// Snapshot of the latest good document
+    pub fn load(&self) -> Arc<Document> {
+        self.handle.load()
+    }
+}
+
+/// What one reload of a watched configuration changed, see
+/// [`ConfigLoader::watch_changes`]
+#[derive(Debug, Clone)]
+pub struct ChangeSet {
+    changes: Vec<Change>,
+    previous: Arc<Document>,
+    current: Arc<Document>,
+}
+
+impl ChangeSet {
+    /// The changes, in the order [`diff`] reports them
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// The document before the reload
+    pub fn previous(&self) -> &Arc<Document> {
+        &self.previous
+    }
+
+    /// The document after the reload, as now stored in the watcher's handle
+    pub fn current(&self) -> &Arc<Document> {
+        &self.current
+    }
+
+    /// Whether anything at or under `path` changed
+    ///
+    /// A change of a whole block or list touches every path inside it, so
+    /// `touches("server.port")` is true when `server` was added.
+    pub fn touches(&self, path: &str) -> bool {
+        self.changes_under(path).next().is_some()
+    }
+
+    /// The changes at or under `path`, and those of the blocks and lists
+    /// holding it
+    pub fn changes_under<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a Change> + 'a {
+        self.changes.iter().filter(move |change| {
+            let changed = change.path();
+            within(changed, path) || within(path, changed)
+        })
+    }
+}
+
+impl fmt::Display for ChangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `path` is `outer` or a path inside it
+fn within(path: &str, outer: &str) -> bool {
+    outer.is_empty()
+        || path
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.stop.set();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Set the values named by the variables in `vars` that start with `prefix`
+fn apply_env(
+    doc: &mut Document,
+    prefix: &str,
+    vars: impl Iterator<Item = (OsString, OsString)>,
+) -> Result<(), ParseError> {
+    let mut matching = Vec::new();
+    for (name, text) in vars {
+        // Invalid bytes read as U+FFFD, so the prefix is still found
+        let lossy = name.to_string_lossy().into_owned();
+        if lossy.len() <= prefix.len() || !lossy.starts_with(prefix) {
+            continue;
+        }
+        match (name.into_string(), text.into_string()) {
+            (Ok(name), Ok(text)) => matching.push((name, text)),
+            _ => {
+                return Err(ParseError::new(
+                    ErrorKind::InvalidValue,
+                    format!("environment variable {}: not valid Unicode", lossy),
+                ))
+            }
+        }
+    }
+    // The environment has no order; sorting keeps nested overrides stable
+    matching.sort();
+    for (name, text) in matching {
+        let path = name[prefix.len()..].to_lowercase().replace("__", ".");
+        let error = |message: String| {
+            ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("environment variable {}: {}", name, message),
+            )
+        };
+        let value = match doc.get(&path).map(Value::type_name) {
+            Some("string") | None => Value::String(text),
+            Some(type_name) => match types::coerce(type_name, &text) {
+                Some(typed) => typed.map_err(|e| error(e.message))?,
+                None => Value::String(text),
+            },
+        };
+        if !doc.set_path(&path, value) {
+            return Err(error(format!("cannot set `{}`", path)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ViolationKind;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_load_from_files() {
+        let dir = std::env::temp_dir().join(format!("uplang-loader-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        std::fs::write(dir.join("main.up"), "name app\n").unwrap();
+        std::fs::write(dir.join("conf.d/10-port.up"), "port!int 80\n").unwrap();
+
+        let loader = ConfigLoader::new()
+            .source("port!int 1\n")
+            .file(dir.join("main.up"))
+            .dir(dir.join("conf.d"));
+        let config: BTreeMap<String, String> = loader.load().unwrap();
+        assert_eq!(config["name"], "app");
+        assert_eq!(config["port"], "80");
+
+        let config: BTreeMap<String, String> = loader
+            .clone()
+            .schema(Schema::parse("name!string\nport!int\nmode?!string dev").unwrap())
+            .load()
+            .unwrap();
+        assert_eq!(config["mode"], "dev");
+
+        let err = loader
+            .clone()
+            .schema(Schema::parse("name!string\nhost!string\nport!bool").unwrap())
+            .load_document()
+            .unwrap_err();
+        let ConfigError::Schema(violations) = &err else {
+            panic!("expected schema violations, got {:?}", err);
+        };
+        let kinds: Vec<ViolationKind> = violations.iter().map(|v| v.kind).collect();
+        // The port of the defaults is overridden, so only one is wrong
+        assert_eq!(kinds, [ViolationKind::WrongType, ViolationKind::Missing]);
+        assert!(err
+            .to_string()
+            .contains("10-port.up:1: `port` should be bool"));
+
+        let err = loader
+            .clone()
+            .file(dir.join("missing.up"))
+            .load_document()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Parse(_)));
+
+        let err = loader.load::<BTreeMap<String, u8>>().unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::Decode(FromUpError {
+                path: "name".into(),
+                message: "expected integer, found `app`".into(),
+                line: Some(1),
+            })
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_layers_and_env_overrides() {
+        let dir = std::env::temp_dir().join(format!("uplang-layers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.up"), "server {\n  host a\n  port!int 80\n}\n").unwrap();
+        std::fs::write(dir.join("app.local.up"), "server {\n  port!int 8080\n}\n").unwrap();
+
+        let doc = ConfigLoader::new()
+            .file(dir.join("app.up"))
+            .optional_file(dir.join("app.local.up"))
+            .optional_file(dir.join("missing.up"))
+            .load_document()
+            .unwrap();
+        // Later files override single keys of a block, not the whole block
+        assert_eq!(doc.get_str("server.host"), Some("a"));
+        assert_eq!(doc.get_int("server.port"), Some(8080));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let vars = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(name, value)| (OsString::from(name), OsString::from(value)))
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        let mut doc = doc;
+        apply_env(
+            &mut doc,
+            "APP_",
+            vars(&[
+                ("APP_SERVER__PORT", "9090"),
+                ("APP_LOG_LEVEL", "debug"),
+                ("OTHER_X", "1"),
+                ("APP_", "ignored"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(doc.get("server.port"), Some(&Value::Int(9090)));
+        assert_eq!(doc.get_str("log_level"), Some("debug"));
+        assert!(doc.get("other_x").is_none());
+
+        let err = apply_env(&mut doc, "APP_", vars(&[("APP_SERVER__PORT", "high")])).unwrap_err();
+        assert!(err
+            .message
+            .starts_with("environment variable APP_SERVER__PORT: "));
+        let err = apply_env(&mut doc, "APP_", vars(&[("APP_LOG_LEVEL__X", "1")])).unwrap_err();
+        assert!(err.message.ends_with("cannot set `log_level.x`"));
+
+        // Variables that are not Unicode fail only if they have the prefix
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+
+            let bad = || OsString::from_vec(b"caf\xe9".to_vec());
+            let mut bad_name = b"APP_X".to_vec();
+            bad_name.push(0xff);
+            apply_env(
+                &mut doc,
+                "APP_",
+                [(OsString::from("OTHER"), bad())].into_iter(),
+            )
+            .unwrap();
+            let err = apply_env(
+                &mut doc,
+                "APP_",
+                [(OsString::from("APP_NAME"), bad())].into_iter(),
+            )
+            .unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidValue);
+            assert_eq!(
+                err.message,
+                "environment variable APP_NAME: not valid Unicode"
+            );
+            let err = apply_env(
+                &mut doc,
+                "APP_",
+                [(OsString::from_vec(bad_name), OsString::from("1"))].into_iter(),
+            )
+            .unwrap_err();
+            assert_eq!(
+                err.message,
+                "environment variable APP_X\u{fffd}: not valid Unicode"
+            );
+        }
+    }
+
+    #[test]
+    fn test_watch() {
+        use std::sync::mpsc;
+
+        let path = std::env::temp_dir().join(format!("uplang-watch-{}.up", std::process::id()));
+        std::fs::write(&path, "port!int 80\n").unwrap();
+        let (sender, changes) = mpsc::channel();
+        let watcher = ConfigLoader::new()
+            .file(&path)
+            .poll_interval(Duration::from_millis(10))
+            .watch(move |change| {
+                let _ = sender.send(change.map(|doc| doc.get_int("port")));
+            })
+            .unwrap();
+        assert_eq!(watcher.load().get_int("port"), Some(80));
+
+        let wait = || changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        // A different size is a change even within the clock's resolution
+        std::fs::write(&path, "port!int eighty\n").unwrap();
+        assert!(matches!(wait(), Err(ConfigError::Parse(_))));
+        assert_eq!(watcher.load().get_int("port"), Some(80));
+
+        std::fs::write(&path, "port!int 8080\n").unwrap();
+        assert_eq!(wait().unwrap(), Some(8080));
+        assert_eq!(watcher.handle().load().get_int("port"), Some(8080));
+        drop(watcher);
+
+        // Dropping wakes the thread rather than waiting out the interval
+        let watcher = ConfigLoader::new()
+            .file(&path)
+            .poll_interval(Duration::from_secs(3600))
+            .watch(|_| {})
+            .unwrap();
+        let start = std::time::Instant::now();
+        drop(watcher);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_watch_includes() {
+        use std::sync::mpsc;
+
+        let dir = std::env::temp_dir().join(format!("uplang-watch-inc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+        file("main.up", "@include db.up\nname app\n");
+        file("db.up", "port!int 80\n");
+        file("other.up", "port!int 9\n");
+        let (sender, changes) = mpsc::channel();
+        let watcher = ConfigLoader::new()
+            .parser(Parser::new().include_resolver(crate::FileResolver::new(&dir)))
+            .file(dir.join("main.up"))
+            .poll_interval(Duration::from_millis(10))
+            .watch(move |change| {
+                let _ = sender.send(change.map(|doc| doc.get_int("port")));
+            })
+            .unwrap();
+        assert_eq!(watcher.load().get_int("port"), Some(80));
+        let wait = || changes.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        file("db.up", "port!int 8080\n");
+        assert_eq!(wait().unwrap(), Some(8080));
+
+        // A new include is watched from the reload that finds it, and one
+        // no longer included is not
+        file("main.up", "@include other.up\n");
+        assert_eq!(wait().unwrap(), Some(9));
+        file("other.up", "port!int 10\n");
+        assert_eq!(wait().unwrap(), Some(10));
+        file("db.up", "port!int 1\n");
+        assert!(changes.recv_timeout(Duration::from_millis(200)).is_err());
+
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watch_changes() {
+        use std::sync::mpsc;
+
+        let path =
+            std::env::temp_dir().join(format!("uplang-watch-changes-{}.up", std::process::id()));
+        std::fs::write(&path, "log {\n  level info\n}\nport!int 80\n").unwrap();
+        let (sender, changes) = mpsc::channel();
+        let _watcher = ConfigLoader::new()
+            .file(&path)
+            .poll_interval(Duration::from_millis(10))
+            .watch_changes(move |changes| {
+                let _ = sender.send(changes);
+            })
+            .unwrap();
+        let wait = || changes.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // Only a comment changes, so nothing is reported before the port
+        std::fs::write(&path, "# Logging\nlog {\n  level info\n}\nport!int 80\n").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        std::fs::write(&path, "log {\n  level info\n}\nport!int 8080\ntls true\n").unwrap();
+        let set = wait().unwrap();
+        assert_eq!(set.to_string(), "~ port: 80 -> 8080\n+ tls = true");
+        assert!(set.touches("port"));
+        assert!(set.touches("tls.cert"));
+        assert!(set.touches(""));
+        assert!(!set.touches("log"));
+        assert!(!set.touches("po"));
+        assert_eq!(set.previous().get_int("port"), Some(80));
+        assert_eq!(set.current().get_int("port"), Some(8080));
+
+        std::fs::write(&path, "log {\n  level debug\n}\nport!int 8080\ntls true\n").unwrap();
+        let set = wait().unwrap();
+        let paths: Vec<&str> = set.changes_under("log").map(Change::path).collect();
+        assert_eq!(paths, ["log.level"]);
+        assert!(!set.touches("port"));
+
+        std::fs::write(&path, "port!int eighty\n").unwrap();
+        assert!(matches!(wait(), Err(ConfigError::Parse(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
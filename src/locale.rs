@@ -0,0 +1,269 @@
+//! Writing numbers and dates in regional conventions, see [`Locale`]
+
+use crate::types::{self, Date};
+use crate::{Document, Node, Value};
+
+/// How a [`Locale`] writes dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// `2024-03-15`, as UP reads them
+    Iso,
+    /// `15.03.2024` with `.` as the separator
+    DayMonthYear(char),
+    /// `03/15/2024` with `/` as the separator
+    MonthDayYear(char),
+}
+
+/// Regional conventions for writing typed values, see [`Writer::locale`]
+///
+/// Integers, floats, and money amounts are written with the decimal
+/// separator and digit grouping of the locale, and dates in its date
+/// format. The default locale writes them as UP reads them, so it changes
+/// nothing.
+///
+/// Text in a regional format is not what `!int` or `!date` accept, so a
+/// value the locale writes differently becomes a plain string, without its
+/// type annotation: the output still parses, to the strings people read.
+///
+/// ```
+/// use uplang::{parse, DateFormat, Locale, Writer};
+///
+/// let doc = parse("price!float 1234.5\nseats!int 12000\nopens!date 2024-03-15\nroom!int 12\n").unwrap();
+/// let german = Locale::new()
+///     .decimal_separator(',')
+///     .digit_grouping('.')
+///     .date_format(DateFormat::DayMonthYear('.'));
+/// assert_eq!(
+///     Writer::new().locale(german).to_string(&doc),
+///     "price 1.234,5\nseats 12.000\nopens 15.03.2024\nroom!int 12\n"
+/// );
+/// ```
+///
+/// [`Writer::locale`]: crate::Writer::locale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    decimal_separator: char,
+    digit_grouping: Option<char>,
+    date_format: DateFormat,
+}
+
+impl Locale {
+    /// A locale that writes values as UP reads them: `.` before decimals,
+    /// no digit grouping, and [`DateFormat::Iso`]
+    pub fn new() -> Self {
+        Self {
+            decimal_separator: '.',
+            digit_grouping: None,
+            date_format: DateFormat::Iso,
+        }
+    }
+
+    /// Set the character before the decimals of floats and money amounts
+    pub fn decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Separate the whole part of numbers into groups of three digits with
+    /// `separator`, such as `,` for `1,234,567`
+    pub fn digit_grouping(mut self, separator: char) -> Self {
+        self.digit_grouping = Some(separator);
+        self
+    }
+
+    /// Set how dates are written
+    pub fn date_format(mut self, format: DateFormat) -> Self {
+        self.date_format = format;
+        self
+    }
+
+    /// The text of a scalar in this locale, if it differs from the text UP
+    /// reads
+    pub fn format(&self, value: &Value) -> Option<String> {
+        let text = match value {
+            Value::Int(n) => self.format_number(&n.to_string()),
+            Value::Float(x) if x.is_finite() => self.format_number(&x.to_string()),
+            Value::Money(money) => self.format_number(&money.to_string()),
+            Value::Date(date) => self.format_date(date),
+            _ => return None,
+        };
+        (value.scalar_text()? != text).then_some(text)
+    }
+
+    /// `text`, a number in UP's form followed by anything, with this
+    /// locale's separators
+    fn format_number(&self, text: &str) -> String {
+        let (sign, rest) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text),
+        };
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (whole, tail) = rest.split_at(end);
+        let mut out = sign.to_string();
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(separator) = self.digit_grouping {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    out.push(separator);
+                }
+            }
+            out.push(digit);
+        }
+        match tail.strip_prefix('.') {
+            Some(decimals) => {
+                out.push(self.decimal_separator);
+                out.push_str(decimals);
+            }
+            None => out.push_str(tail),
+        }
+        out
+    }
+
+    fn format_date(&self, date: &Date) -> String {
+        match self.date_format {
+            DateFormat::Iso => date.to_string(),
+            DateFormat::DayMonthYear(sep) => {
+                format!(
+                    "{:02}{}{:02}{}{:04}",
+                    date.day, sep, date.month, sep, date.year
+                )
+            }
+            DateFormat::MonthDayYear(sep) => {
+                format!(
+                    "{:02}{}{:02}{}{:04}",
+                    date.month, sep, date.day, sep, date.year
+                )
+            }
+        }
+    }
+
+    /// A copy of `doc` with the values this locale writes differently
+    /// turned into strings, or `None` if there are none
+    pub(crate) fn localize(&self, doc: &Document) -> Option<Document> {
+        if *self == Locale::new() {
+            return None;
+        }
+        let mut doc = doc.clone();
+        let mut changed = false;
+        for node in &mut doc.nodes {
+            changed |= self.localize_node(node);
+        }
+        changed.then_some(doc)
+    }
+
+    fn localize_node(&self, node: &mut Node) -> bool {
+        if let Some(text) = self.format(&node.value) {
+            // Keep annotations that are not types, such as `!secret`
+            if let Some(annotation) = &node.type_annotation {
+                if matches!(types::coerce(annotation, &text), Some(Err(_))) {
+                    node.type_annotation = None;
+                }
+            }
+            node.value = Value::String(text);
+            return true;
+        }
+        self.localize_value(&mut node.value)
+    }
+
+    fn localize_value(&self, value: &mut Value) -> bool {
+        let mut changed = false;
+        match value {
+            Value::Block(block) => {
+                for node in block.nodes_mut() {
+                    changed |= self.localize_node(node);
+                }
+            }
+            Value::List(items) => {
+                for item in items {
+                    changed |= self.localize_value(item);
+                }
+            }
+            Value::Table { rows, .. } => {
+                for cell in rows.iter_mut().flatten() {
+                    changed |= self.localize_value(cell);
+                }
+            }
+            scalar => {
+                if let Some(text) = self.format(scalar) {
+                    *scalar = Value::String(text);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Writer};
+
+    #[test]
+    fn test_format_numbers_and_dates() {
+        let us = Locale::new()
+            .digit_grouping(',')
+            .date_format(DateFormat::MonthDayYear('/'));
+        assert_eq!(us.format(&Value::Int(-1234567)), Some("-1,234,567".into()));
+        assert_eq!(us.format(&Value::Int(999)), None);
+        assert_eq!(us.format(&Value::Float(1234.25)), Some("1,234.25".into()));
+        assert_eq!(us.format(&Value::Float(f64::NAN)), None);
+        assert_eq!(
+            us.format(&Value::Money("1234.50 USD".parse().unwrap())),
+            Some("1,234.50 USD".into())
+        );
+        assert_eq!(
+            us.format(&Value::Date("2024-03-05".parse().unwrap())),
+            Some("03/05/2024".into())
+        );
+        assert_eq!(us.format(&Value::from("1234")), None);
+
+        let french = Locale::new()
+            .decimal_separator(',')
+            .digit_grouping('\u{202f}');
+        assert_eq!(
+            french.format(&Value::Float(12345.5)),
+            Some("12\u{202f}345,5".into())
+        );
+        assert_eq!(Locale::new().format(&Value::Float(12345.5)), None);
+    }
+
+    #[test]
+    fn test_writer_locale() {
+        let input = "\
+price!float 1234.5
+limits {
+  max!int 5000
+  min!int 1
+}
+sizes [1000, 20]
+key!secret 1000
+";
+        let mut doc = parse(input).unwrap();
+        doc.nodes[2].value = Value::List(vec![Value::Int(1000), Value::Int(20)]);
+        doc.nodes[3].value = Value::Int(1000);
+        let writer = Writer::new().locale(Locale::new().digit_grouping('\''));
+        let written = writer.to_string(&doc);
+        assert_eq!(
+            written,
+            "price 1'234.5\nlimits {\n  max 5'000\n  min!int 1\n}\nsizes [1'000, 20]\nkey!secret 1'000\n"
+        );
+        // The output parses, to strings where the locale changed the text
+        let back = parse(&written).unwrap();
+        assert_eq!(back.get_str("limits.max"), Some("5'000"));
+        assert_eq!(back.get_int("limits.min"), Some(1));
+
+        // Without a locale the document is written as usual
+        assert_eq!(
+            Writer::new().locale(Locale::new()).to_string(&doc),
+            Writer::new().to_string(&doc)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! `up_doc!` and `up_value!`, for writing documents in Rust source
+
+/// Build a [`Document`](crate::Document) from UP written inline
+///
+/// Nodes are a key and a value, with an optional `!annotation` between,
+/// one after another with no separator. Keys are words or string literals.
+/// Values are:
+///
+/// - bare words, which are text as in UP, and string literals;
+/// - number literals, `true`, `false`, and `null`;
+/// - `{ ... }` blocks of nodes and `[a, b, ...]` lists of values;
+/// - any Rust expression in parentheses, converted with `Value::from`, such
+///   as `(port + 1)` or `(-1)`.
+///
+/// An annotated value is read as its annotation reads it, so
+/// `timeout!dur "30s"` is a duration. The document is built by the
+/// expanded code, with no text to parse at run time.
+///
+/// # Panics
+///
+/// If an annotated value does not read as its annotation, such as
+/// `port!int "eighty"`.
+///
+/// ```
+/// use uplang::up_doc;
+///
+/// let base = 8000;
+/// let doc = up_doc! {
+///     name app
+///     port!int (base + 80)
+///     timeout!dur "30s"
+///     server {
+///         host "0.0.0.0"
+///         tls!bool true
+///     }
+///     tags [web, api]
+/// };
+/// assert_eq!(doc, uplang::parse("name app\nport!int 8080\ntimeout!dur 30s\nserver {\n  host 0.0.0.0\n  tls!bool true\n}\ntags [web, api]\n").unwrap());
+/// ```
+#[macro_export]
+macro_rules! up_doc {
+    ($($body:tt)*) => {
+        $crate::Document {
+            nodes: $crate::__up_nodes!([] $($body)*),
+        }
+    };
+}
+
+/// Build a [`Value`](crate::Value) from UP written inline, as the values of
+/// [`up_doc!`](crate::up_doc) are written
+///
+/// ```
+/// use uplang::{up_value, Value};
+///
+/// assert_eq!(up_value!(web), Value::from("web"));
+/// assert_eq!(up_value!([1, (-2), { x 3 }]).as_list().unwrap().len(), 3);
+/// assert_eq!(up_value!({ point { x 1 } }).as_block().unwrap().len(), 1);
+/// ```
+#[macro_export]
+macro_rules! up_value {
+    (null) => {
+        $crate::Value::Null
+    };
+    (true) => {
+        $crate::Value::Bool(true)
+    };
+    (false) => {
+        $crate::Value::Bool(false)
+    };
+    ({ $($body:tt)* }) => {
+        $crate::Value::Block(
+            $crate::__up_nodes!([] $($body)*)
+                .into_iter()
+                .collect::<$crate::Block>(),
+        )
+    };
+    ([ $($item:tt),* $(,)? ]) => {
+        $crate::Value::List(::std::vec![$($crate::up_value!($item)),*])
+    };
+    ($word:ident) => {
+        $crate::Value::String(::std::string::String::from(stringify!($word)))
+    };
+    ($value:expr) => {
+        $crate::Value::from($value)
+    };
+}
+
+/// The nodes of [`up_doc!`](crate::up_doc), gathered one at a time
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __up_nodes {
+    ([$($nodes:expr,)*]) => {
+        ::std::vec![$($nodes,)*]
+    };
+    ([$($nodes:expr,)*] $key:tt ! $annotation:ident $value:tt $($rest:tt)*) => {
+        $crate::__up_nodes!(
+            [$($nodes,)* $crate::__private::annotated(
+                $crate::__up_key!($key),
+                stringify!($annotation),
+                $crate::up_value!($value),
+            ),]
+            $($rest)*
+        )
+    };
+    ([$($nodes:expr,)*] $key:tt $value:tt $($rest:tt)*) => {
+        $crate::__up_nodes!(
+            [$($nodes,)* $crate::Node::new($crate::__up_key!($key), $crate::up_value!($value)),]
+            $($rest)*
+        )
+    };
+}
+
+/// A key of [`up_doc!`](crate::up_doc), a word or a string literal
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __up_key {
+    ($key:ident) => {
+        stringify!($key)
+    };
+    ($key:literal) => {
+        $key
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    #[test]
+    fn test_up_doc() {
+        let host = "db.local";
+        let doc = up_doc! {
+            name app
+            "max-conn"!int 10
+            ratio 0.5
+            proxy null
+            offset (-3)
+            db {
+                host (host)
+                port!int "5432"
+                opts { ssl true }
+            }
+            tags [web, "two words", (1 + 1)]
+            servers [
+                { name a },
+                { name b },
+            ]
+            started!date "2024-05-01"
+            token!secret abc
+        };
+        assert_eq!(doc.get_str("name"), Some("app"));
+        assert_eq!(doc.get_int("max-conn"), Some(10));
+        assert_eq!(doc.get("ratio"), Some(&Value::Float(0.5)));
+        assert_eq!(doc.get("proxy"), Some(&Value::Null));
+        assert_eq!(doc.get_int("offset"), Some(-3));
+        assert_eq!(doc.get_str("db.host"), Some("db.local"));
+        assert_eq!(doc.get_int("db.port"), Some(5432));
+        assert_eq!(doc.get_bool("db.opts.ssl"), Some(true));
+        assert_eq!(doc.get_str("tags[1]"), Some("two words"));
+        assert_eq!(doc.get("tags[2]"), Some(&Value::Int(2)));
+        assert_eq!(doc.get_str("servers[1].name"), Some("b"));
+        assert!(matches!(doc.get("started"), Some(Value::Date(_))));
+        let token = doc.nodes.iter().find(|node| node.key == "token").unwrap();
+        assert_eq!(token.type_annotation.as_deref(), Some("secret"));
+        assert_eq!(token.value, Value::from("abc"));
+        assert!(up_doc! {}.is_empty());
+
+        // Annotated throughout, the same document as parsed
+        let doc = up_doc! {
+            name app
+            port!int 8080
+            server { tls!bool true }
+            tags [a, b]
+        };
+        assert_eq!(
+            doc,
+            parse("name app\nport!int 8080\nserver {\n  tls!bool true\n}\ntags [a, b]\n").unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "port!int")]
+    fn test_up_doc_bad_annotation() {
+        up_doc! { port!int eighty };
+    }
+}
@@ -0,0 +1,1781 @@
+//! `up` command-line tool
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use uplang::io::AtomicWrite;
+use uplang::lint::{Finding, Linter};
+use uplang::policy::{Policy, Violation};
+use uplang::schema::{self, Schema};
+use uplang::{
+    Block, Change, Differ, Document, MergeStrategy, Migration, MigrationSteps, Node, ParseError,
+    Parser, Patch, Value, Writer,
+};
+
+const USAGE: &str = "\
+Usage: up <command> [args]
+
+Commands:
+  convert <file> --to <format> [--pretty] [--typed] [--audience <level>]
+          [--reg-key <key>] [-o <out>]
+                            Convert to json, yaml, toml, plist, or reg; a .json,
+                            .ini, or .properties file converts to up.
+                            `--to reg` writes a Windows registry file for the
+                            `--reg-key`, such as HKEY_CURRENT_USER\\Software\\App.
+                            `--typed` keeps annotations in JSON as `$type` objects, and
+                            `--audience` leaves out nodes tagged `!internal` for `public`
+  diff <old> <new> [--match-lists] [--list-key <key>] [--format text|json]
+       [--color auto|always|never]
+                            Show the values added, removed, and modified by key path.
+                            `--match-lists` matches list items that moved, and
+                            `--list-key` matches blocks in lists by one key's value
+  docs <file> [--schema <schema>] [-o <out>]
+                            Render a Markdown reference of keys, types, and defaults
+  explain <file>... <path>  Show where a value came from across layered files
+  export <file> --format plist|reg [--reg-key <key>] [-o <out>]
+                            Write a property list or a Windows registry file, as
+                            `convert --to plist` or `--to reg` does
+  fmt <file>... [--check] [--changed <original>] [--no-verify] [--dry-run] [--diff]
+                            Rewrite files in canonical style; `-` formats stdin to
+                            stdout. With `--changed`, only the parts that differ
+                            from the original version are rewritten. Output that
+                            would not parse back to the same document is refused
+                            unless `--no-verify` is given
+  get <file> <path> [--json]
+                            Print the value at a key path: scalars as their raw
+                            text, blocks and lists as UP, or anything as JSON
+  graph <file>              Print the document structure as a Graphviz DOT graph
+  init [--schema <schema>] [-o <out>]
+                            Write a commented starter config with every key of a
+                            schema, or of a built-in service template
+  lint <file>... [--allow <rule>]... [--suggest] [--format text|json]
+                            Check files for likely mistakes such as duplicate keys,
+                            unknown annotations, and unused anchors or variables;
+                            warnings are reported without failing. `--suggest`
+                            also recommends tables, anchors, and file splits
+  merge <file>... [--strategy deep|append-lists|shallow] [-o <out> | --in-place]
+        [--dry-run] [--diff]
+                            Layer files left to right and print the result;
+                            `--in-place` writes it over the first file
+  migrate <file> --migrations <file> [--from <n>] [--to <n>]
+          [-o <out> | --in-place] [--dry-run] [--diff]
+                            Upgrade a config to a newer version, updating its
+                            `version` key
+  parse <file> [--format json|up|debug]
+                            Print the parsed document structure: as JSON keeping
+                            annotations as `$type` objects, as canonical UP, or
+                            as the Rust debug dump
+  patch <file> <patch> [-o <out> | --in-place] [--dry-run] [--diff]
+                            Apply the `add`, `replace`, `remove`, and `append`
+                            operations of a patch file; nothing is changed if
+                            any of them fails
+  policy check <policy> <file>... [--format text|json]
+                            Check files against the rules of a policy; warnings are
+                            reported without failing
+  schema export <schema> [--example] [-o <out>]
+                            Write a schema as JSON Schema; with `--example`, the
+                            schema is inferred from an annotated example document
+  set <file>... <path> <value> [--type <type>] [--in-place] [--dry-run] [--diff]
+                            Set a key path to a value and print the result, or
+                            with `--in-place` write it back to every file; quoted
+                            patterns such as 'conf.d/*.up' are expanded. The value
+                            keeps the annotation of the one it replaces, or takes
+                            `--type` string, int, float, bool, or null
+  stats <file>              Show key usage and value type statistics
+  table <file> <path> [--join <path> --on <column>] [--where <column>=<value>]
+        [--sort <column>] [--select <column>,...] [--format up|csv]
+                            Print a table, or a list of uniform blocks, joined with
+                            another table of the file, filtered, sorted, and cut
+                            down to some columns, in that order; as UP or CSV
+  validate <file>... [--format text|json]
+                            Check that files parse; `-` reads stdin
+
+Commands that write files replace them atomically, keeping the old file as
+<file>.bak with `--backup`. `--diff` shows a unified diff of the changes, and
+`--dry-run` shows it without writing anything.";
+
+enum Error {
+    /// Bad command line; print usage
+    Usage(String),
+    /// The command ran and failed
+    Failed(String),
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(msg)) => {
+            eprintln!("up: {}\n\n{}", msg, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Failed(msg)) => {
+            eprintln!("up: {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| Error::Usage("missing command".to_string()))?;
+
+    match command.as_str() {
+        "convert" => convert(args),
+        "diff" => diff(args),
+        "docs" => docs(args),
+        "explain" => explain(args),
+        "export" => export(args),
+        "fmt" => fmt(args),
+        "get" => get(args),
+        "graph" => {
+            print!("{}", load_one(args)?.to_graph().to_dot());
+            Ok(())
+        }
+        "init" => init(args),
+        "lint" => lint(args),
+        "merge" => merge(args),
+        "migrate" => migrate(args),
+        "parse" => parse(args),
+        "patch" => patch(args),
+        "policy" => policy(args),
+        "schema" => schema(args),
+        "set" => set(args),
+        "stats" => {
+            print_stats(&load_one(args)?);
+            Ok(())
+        }
+        "table" => table(args),
+        "validate" => validate(args),
+        other => Err(Error::Usage(format!("unknown command `{}`", other))),
+    }
+}
+
+fn load(file: &str) -> Result<Document, Error> {
+    Parser::new()
+        .parse_file(file)
+        .map_err(|e| Error::Failed(e.to_string()))
+}
+
+fn load_one(args: &[String]) -> Result<Document, Error> {
+    match args {
+        [file] => load(file),
+        _ => Err(Error::Usage("expected exactly one file".to_string())),
+    }
+}
+
+fn render(value: &Value) -> String {
+    value.truncated().max_items(4).to_string()
+}
+
+fn convert(args: &[String]) -> Result<(), Error> {
+    let mut file = None;
+    let mut format = None;
+    let mut out = None;
+    let mut pretty = false;
+    let mut typed = false;
+    let mut audience = None;
+    let mut reg_key = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--pretty" => {
+                pretty = true;
+                continue;
+            }
+            "--typed" => {
+                typed = true;
+                continue;
+            }
+            "--to" => &mut format,
+            "--audience" => &mut audience,
+            "--reg-key" => &mut reg_key,
+            "-o" | "--output" => &mut out,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => &mut file,
+        };
+        let value = match arg.as_str() {
+            "--to" | "--audience" | "--reg-key" | "-o" | "--output" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?,
+            _ => arg,
+        };
+        if slot.replace(value).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let file = file.ok_or_else(|| Error::Usage("expected a file".to_string()))?;
+    let format = format.ok_or_else(|| Error::Usage("expected `--to <format>`".to_string()))?;
+    let extension = Path::new(file).extension().and_then(|e| e.to_str());
+    let doc = match extension {
+        Some("json" | "ini" | "properties") => {
+            let text =
+                fs::read_to_string(file).map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+            let doc = match extension {
+                Some("ini") => Document::from_ini(&text),
+                Some("properties") => Document::from_properties(&text),
+                _ if typed => Document::from_json_typed(&text),
+                _ => Document::from_json(&text),
+            };
+            doc.map_err(|e| Error::Failed(format!("{}: {}", file, e)))?
+        }
+        _ => load(file)?,
+    };
+    let doc = match audience {
+        Some(audience) => doc.view_for(audience.as_str()),
+        None => doc,
+    };
+
+    let text = match format.as_str() {
+        "json" if typed && pretty => doc.to_json_typed_pretty(),
+        "json" if typed => doc.to_json_typed() + "\n",
+        "json" if pretty => doc.to_json_pretty(),
+        "json" => doc.to_json() + "\n",
+        "yaml" => doc.to_yaml(),
+        "toml" => doc.to_toml().map_err(|e| Error::Failed(e.to_string()))?,
+        "plist" => doc.to_plist().map_err(|e| Error::Failed(e.to_string()))?,
+        "reg" => {
+            let key = reg_key
+                .ok_or_else(|| Error::Usage("`--to reg` needs `--reg-key <key>`".to_string()))?;
+            doc.to_reg(key).map_err(|e| Error::Failed(e.to_string()))?
+        }
+        "up" => uplang::to_string(&doc),
+        other => return Err(Error::Usage(format!("unknown format `{}`", other))),
+    };
+    match out {
+        Some(path) => fs::write(path, text).map_err(|e| Error::Failed(format!("{}: {}", path, e))),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// `convert` to a platform format, named with `--format`
+fn export(args: &[String]) -> Result<(), Error> {
+    let mut converted = Vec::with_capacity(args.len());
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some(name @ ("plist" | "reg")) => format = Some(name),
+                _ => {
+                    return Err(Error::Usage(
+                        "`--format` must be `plist` or `reg`".to_string(),
+                    ))
+                }
+            },
+            "--to" => return Err(Error::Usage("unknown option `--to`".to_string())),
+            _ => converted.push(arg.clone()),
+        }
+    }
+    let format = format.ok_or_else(|| Error::Usage("expected `--format plist|reg`".to_string()))?;
+    converted.extend(["--to".to_string(), format.to_string()]);
+    convert(&converted)
+}
+
+fn diff(args: &[String]) -> Result<(), Error> {
+    let mut files = Vec::new();
+    let mut differ = Differ::new();
+    let mut json = false;
+    let mut color = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--match-lists" => differ = differ.match_lists(true),
+            "--list-key" => {
+                let key = args
+                    .next()
+                    .ok_or_else(|| Error::Usage("`--list-key` needs a key".to_string()))?;
+                differ = differ.list_key(key);
+            }
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => json = false,
+                Some("json") => json = true,
+                Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+                None => return Err(Error::Usage("`--format` needs a value".to_string())),
+            },
+            "--color" => match args.next().map(String::as_str) {
+                Some("auto") => color = None,
+                Some("always") => color = Some(true),
+                Some("never") => color = Some(false),
+                _ => {
+                    return Err(Error::Usage(
+                        "`--color` must be `auto`, `always`, or `never`".to_string(),
+                    ))
+                }
+            },
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => files.push(arg),
+        }
+    }
+    let [old, new] = files[..] else {
+        return Err(Error::Usage("expected an old and a new file".to_string()));
+    };
+
+    let changes = differ.diff(&load(old)?, &load(new)?);
+    if json {
+        println!("{}", change_report(&changes).to_json());
+        return Ok(());
+    }
+    let color =
+        color.unwrap_or_else(|| io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none());
+    for change in &changes {
+        let code = match change {
+            Change::Added { .. } => "32",
+            Change::Removed { .. } => "31",
+            Change::Modified { .. } => "33",
+        };
+        if color {
+            println!("\x1b[{}m{}\x1b[0m", code, change);
+        } else {
+            println!("{}", change);
+        }
+    }
+    Ok(())
+}
+
+/// The changes found by `diff` as a document, for `--format json`
+fn change_report(changes: &[Change]) -> Document {
+    let changes = changes.iter().map(|change| {
+        let fields: Vec<(&str, Value)> = match change {
+            Change::Added { path, value } => vec![
+                ("change", Value::from("added")),
+                ("path", Value::from(path.as_str())),
+                ("value", value.clone()),
+            ],
+            Change::Removed { path, value } => vec![
+                ("change", Value::from("removed")),
+                ("path", Value::from(path.as_str())),
+                ("value", value.clone()),
+            ],
+            Change::Modified { path, old, new } => vec![
+                ("change", Value::from("modified")),
+                ("path", Value::from(path.as_str())),
+                ("old", old.clone()),
+                ("new", new.clone()),
+            ],
+        };
+        Value::Block(fields.into_iter().collect())
+    });
+    Document::builder().list("changes", changes).build()
+}
+
+fn docs(args: &[String]) -> Result<(), Error> {
+    let mut file = None;
+    let mut schema = None;
+    let mut out = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--schema" => &mut schema,
+            "-o" | "--output" => &mut out,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => &mut file,
+        };
+        let value = match arg.as_str() {
+            "--schema" | "-o" | "--output" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a file", arg)))?,
+            _ => arg,
+        };
+        if slot.replace(value).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let file = file.ok_or_else(|| Error::Usage("expected a file".to_string()))?;
+    let schema = schema.map(|path| load(path)).transpose()?;
+    let markdown = load(file)?.reference(schema.as_ref()).to_markdown();
+    match out {
+        Some(path) => {
+            fs::write(path, markdown).map_err(|e| Error::Failed(format!("{}: {}", path, e)))
+        }
+        None => {
+            print!("{}", markdown);
+            Ok(())
+        }
+    }
+}
+
+/// Schema of the configuration `up init` writes without `--schema`
+const STARTER_SCHEMA: &str = "\
+# Name of the service
+name!string
+# Deployment environment, e.g. dev or prod
+environment?!string dev
+server!block {
+  # Address to listen on
+  host?!string 127.0.0.1
+  port?!int 8080
+  # Request timeout in seconds
+  timeout?!int 30
+}
+log!block {
+  # One of error, warn, info, debug
+  level?!string info
+  # Write JSON lines instead of text
+  json?!bool false
+}
+# Labels attached to metrics
+tags?!list string
+";
+
+fn init(args: &[String]) -> Result<(), Error> {
+    let mut schema = None;
+    let mut out = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--schema" => &mut schema,
+            "-o" | "--output" => &mut out,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => &mut schema,
+        };
+        let value = match arg.as_str() {
+            "--schema" | "-o" | "--output" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?,
+            _ => arg,
+        };
+        if slot.replace(value).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let schema = match schema {
+        Some(file) => {
+            let text =
+                fs::read_to_string(file).map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+            Schema::parse(&text).map_err(|e| Error::Failed(format!("{}: {}", file, e)))?
+        }
+        None => Schema::parse(STARTER_SCHEMA).expect("built-in schema is valid"),
+    };
+    let text = uplang::to_string(&schema.starter_document());
+    match out {
+        // Never overwrite a configuration someone has already edited
+        Some(path) if Path::new(path).exists() => {
+            Err(Error::Failed(format!("{}: file already exists", path)))
+        }
+        Some(path) => fs::write(path, text).map_err(|e| Error::Failed(format!("{}: {}", path, e))),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn fmt(args: &[String]) -> Result<(), Error> {
+    let mut check = false;
+    let mut verify = true;
+    let mut changed = None;
+    let mut files = Vec::new();
+    // Files are always formatted in place
+    let mut rewrite = Rewrite {
+        in_place: true,
+        ..Rewrite::default()
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            flag if rewrite.flag(flag) => {}
+            "--check" => check = true,
+            "--no-verify" => verify = false,
+            "--changed" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| Error::Usage("`--changed` needs a file".to_string()))?;
+                if changed.replace(path).is_some() {
+                    return Err(Error::Usage("`--changed` given twice".to_string()));
+                }
+            }
+            "-" => files.push(arg),
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::Usage("expected at least one file".to_string()));
+    }
+    // Unchanged parts are copied from the original, which is one file
+    let original = match changed {
+        Some(_) if files.len() > 1 => {
+            return Err(Error::Usage(
+                "`--changed` formats exactly one file".to_string(),
+            ))
+        }
+        Some(path) => {
+            Some(fs::read_to_string(path).map_err(|e| Error::Failed(format!("{}: {}", path, e)))?)
+        }
+        None => None,
+    };
+
+    let writer = Writer::new().verify(verify);
+    let mut unformatted = 0;
+    for file in files {
+        let mut input = String::new();
+        let read = if file == "-" {
+            io::stdin().read_to_string(&mut input).map(drop)
+        } else {
+            fs::read_to_string(file).map(|text| input = text)
+        };
+        read.map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+        let formatted = match &original {
+            Some(original) => Parser::new()
+                .parse_document(&input)
+                .and_then(|edited| writer.format_changed(original, &edited)),
+            None => writer.format(&input),
+        }
+        .map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+
+        if check {
+            if formatted != input {
+                unformatted += 1;
+                println!("Diff in {}:", file);
+                print_diff(&input, &formatted);
+            }
+        } else {
+            let target = (file != "-").then_some(file.as_str());
+            rewrite.finish(file, &input, &formatted, target)?;
+        }
+    }
+
+    if unformatted > 0 {
+        return Err(Error::Failed(format!(
+            "{} file(s) would be reformatted",
+            unformatted
+        )));
+    }
+    Ok(())
+}
+
+/// Print the lines that differ between `old` and `new`, numbered as in each
+fn print_diff(old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    for edit in line_edits(&old, &new) {
+        match edit {
+            Edit::Same(..) => {}
+            Edit::Added(j) => println!("+{:>4} | {}", j + 1, new[j]),
+            Edit::Removed(i) => println!("-{:>4} | {}", i + 1, old[i]),
+        }
+    }
+}
+
+/// How a command that rewrites a file shows and saves its result
+#[derive(Default)]
+struct Rewrite {
+    /// Write the result over the input file
+    in_place: bool,
+    /// Show the changes without writing anything
+    dry_run: bool,
+    /// Show the changes as a unified diff
+    diff: bool,
+    /// Keep the file being replaced as a `.bak` file
+    backup: bool,
+}
+
+impl Rewrite {
+    /// Take `arg` if it is one of the rewrite flags
+    fn flag(&mut self, arg: &str) -> bool {
+        match arg {
+            "-i" | "--in-place" => self.in_place = true,
+            "--dry-run" => self.dry_run = true,
+            "--diff" => self.diff = true,
+            "--backup" => self.backup = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// The file to write the result to, `file` itself with `--in-place`,
+    /// or `None` for stdout
+    fn target<'a>(&self, file: &'a str, out: Option<&'a String>) -> Result<Option<&'a str>, Error> {
+        match out {
+            Some(_) if self.in_place => Err(Error::Usage(
+                "`--in-place` and `-o` cannot be used together".to_string(),
+            )),
+            Some(path) => Ok(Some(path)),
+            None => Ok(self.in_place.then_some(file)),
+        }
+    }
+
+    /// Show how `new` differs from what it replaces, if asked, and write it
+    /// to `target` unless this is a dry run
+    ///
+    /// The diff is against the target file as it is, or against `old`, the
+    /// text of the input `file`, when writing to stdout or a new file.
+    fn finish(&self, file: &str, old: &str, new: &str, target: Option<&str>) -> Result<(), Error> {
+        let replaced = match target {
+            Some(path) if path != file && Path::new(path).exists() => Some(read(path)?),
+            _ => None,
+        };
+        let (before, name) = match &replaced {
+            Some(text) => (text.as_str(), target.unwrap_or(file)),
+            None => (old, file),
+        };
+        if self.diff || self.dry_run {
+            print!(
+                "{}",
+                unified_diff(before, new, name, target.unwrap_or(name))
+            );
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+        match target {
+            Some(path) if before != new || !Path::new(path).exists() => AtomicWrite::new()
+                .backup(self.backup)
+                .write(path, new)
+                .map_err(|e| Error::Failed(format!("{}: {}", path, e))),
+            Some(_) => Ok(()),
+            // The diff stands in for the result
+            None if self.diff => Ok(()),
+            None => {
+                print!("{}", new);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn read(path: &str) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|e| Error::Failed(format!("{}: {}", path, e)))
+}
+
+/// One line of a line-by-line comparison, by index into the old or new lines
+#[derive(Clone, Copy)]
+enum Edit {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Compare two texts line by line, keeping the longest run of lines they
+/// share
+fn line_edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // Longest common subsequence lengths of every pair of suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            edits.push(Edit::Added(j));
+            j += 1;
+        } else {
+            edits.push(Edit::Removed(i));
+            i += 1;
+        }
+    }
+    edits
+}
+
+/// A unified diff from `old` to `new` with three lines of context, empty
+/// if they are the same
+fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    const CONTEXT: usize = 3;
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut edits = line_edits(&old, &new);
+    // Removed lines come before the lines added in their place
+    for run in edits.split_mut(|edit| matches!(edit, Edit::Same(..))) {
+        run.sort_by_key(|edit| matches!(edit, Edit::Added(_)));
+    }
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&k| !matches!(edits[k], Edit::Same(..)))
+        .collect();
+    let Some(&first) = changed.first() else {
+        return String::new();
+    };
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    // Changes closer than twice the context share a hunk
+    let mut hunks = vec![(first, first)];
+    for &k in &changed[1..] {
+        let last = hunks.last_mut().expect("hunks start with one");
+        if k - last.1 <= 2 * CONTEXT {
+            last.1 = k;
+        } else {
+            hunks.push((k, k));
+        }
+    }
+    for (start, end) in hunks {
+        let edits = &edits[start.saturating_sub(CONTEXT)..(end + CONTEXT + 1).min(edits.len())];
+        // Line numbers are where the hunk starts in each text
+        let (mut old_start, mut new_start) = (old.len(), new.len());
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut body = String::new();
+        for edit in edits {
+            let (prefix, line) = match *edit {
+                Edit::Same(i, j) => {
+                    old_start = old_start.min(i);
+                    new_start = new_start.min(j);
+                    old_count += 1;
+                    new_count += 1;
+                    (' ', old[i])
+                }
+                Edit::Removed(i) => {
+                    old_start = old_start.min(i);
+                    old_count += 1;
+                    ('-', old[i])
+                }
+                Edit::Added(j) => {
+                    new_start = new_start.min(j);
+                    new_count += 1;
+                    ('+', new[j])
+                }
+            };
+            body.push(prefix);
+            body.push_str(line);
+            body.push('\n');
+        }
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        out.push_str(&body);
+    }
+    out
+}
+
+/// The `start,count` of a hunk in one text, with `start` 1-based, or the
+/// line before it for a hunk with no lines there
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+fn get(args: &[String]) -> Result<(), Error> {
+    let mut positional = Vec::new();
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [file, path] = positional[..] else {
+        return Err(Error::Usage("expected a file and a key path".to_string()));
+    };
+
+    let doc = load(file)?;
+    let value = doc
+        .get(path)
+        .ok_or_else(|| Error::Failed(format!("{}: no value at `{}`", file, path)))?;
+    let text = match value {
+        _ if json => value.to_json_string(),
+        Value::Block(_) => doc.emit_subtree(path).unwrap_or_default(),
+        Value::List(_) | Value::Table { .. } => value.to_string(),
+        scalar => scalar.scalar_text().unwrap_or_default().into_owned(),
+    };
+    println!("{}", text.strip_suffix('\n').unwrap_or(&text));
+    Ok(())
+}
+
+fn explain(args: &[String]) -> Result<(), Error> {
+    let (path, files) = match args.split_last() {
+        Some((path, files)) if !files.is_empty() => (path, files),
+        _ => return Err(Error::Usage("expected files and a key path".to_string())),
+    };
+
+    let mut merged = Document::new();
+    for file in files {
+        merged.overlay_tracked(load(file)?);
+    }
+
+    let chain = merged.explain(path);
+    let Some(effective) = chain.last() else {
+        return Err(Error::Failed(format!("`{}` is not defined", path)));
+    };
+
+    println!("{} = {}", path, render(&effective.value));
+    for (i, step) in chain.iter().enumerate() {
+        let origin = step
+            .origin
+            .as_ref()
+            .map_or_else(|| "<unknown>".to_string(), ToString::to_string);
+        let marker = if i + 1 == chain.len() {
+            "  (effective)"
+        } else {
+            ""
+        };
+        println!("  {:<32} {}{}", origin, render(&step.value), marker);
+    }
+    Ok(())
+}
+
+fn merge(args: &[String]) -> Result<(), Error> {
+    let mut files = Vec::new();
+    let mut strategy = MergeStrategy::Deep;
+    let mut out = None;
+    let mut rewrite = Rewrite::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            flag if rewrite.flag(flag) => {}
+            "--strategy" => match args.next().map(String::as_str) {
+                Some("deep") => strategy = MergeStrategy::Deep,
+                Some("append-lists") => strategy = MergeStrategy::AppendLists,
+                Some("shallow") => strategy = MergeStrategy::Shallow,
+                _ => {
+                    return Err(Error::Usage(
+                        "`--strategy` must be `deep`, `append-lists`, or `shallow`".to_string(),
+                    ))
+                }
+            },
+            "-o" | "--output" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| Error::Usage(format!("`{}` needs a file", arg)))?;
+                if out.replace(path).is_some() {
+                    return Err(Error::Usage(format!("`{}` given twice", arg)));
+                }
+            }
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::Usage("expected at least one file".to_string()));
+    }
+
+    let target = rewrite.target(files[0], out)?;
+
+    let mut merged = Document::new();
+    for file in &files {
+        merged = merged.merge(&load(file)?, strategy);
+    }
+    rewrite.finish(
+        files[0],
+        &read(files[0])?,
+        &uplang::to_string(&merged),
+        target,
+    )
+}
+
+fn migrate(args: &[String]) -> Result<(), Error> {
+    let mut file = None;
+    let mut migrations = None;
+    let mut from = None;
+    let mut to = None;
+    let mut out = None;
+    let mut rewrite = Rewrite::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if rewrite.flag(arg) {
+            continue;
+        }
+        let slot = match arg.as_str() {
+            "--migrations" => &mut migrations,
+            "--from" => &mut from,
+            "--to" => &mut to,
+            "-o" | "--output" => &mut out,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => &mut file,
+        };
+        let value = match arg.as_str() {
+            "--migrations" | "--from" | "--to" | "-o" | "--output" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?,
+            _ => arg,
+        };
+        if slot.replace(value).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let file = file.ok_or_else(|| Error::Usage("expected a file".to_string()))?;
+    let target = rewrite.target(file, out)?;
+    let migrations =
+        migrations.ok_or_else(|| Error::Usage("expected `--migrations <file>`".to_string()))?;
+    let version = |flag: &str, text: &String| {
+        text.parse::<u64>()
+            .map_err(|_| Error::Usage(format!("`{}` must be a version number", flag)))
+    };
+    let text = fs::read_to_string(migrations)
+        .map_err(|e| Error::Failed(format!("{}: {}", migrations, e)))?;
+    let migrations = MigrationSteps::parse_all(&text)
+        .map_err(|e| Error::Failed(format!("{}: {}", migrations, e)))?;
+
+    let mut doc = load(file)?;
+    let from = match from {
+        Some(text) => version("--from", text)?,
+        None => match doc.get("version") {
+            Some(value) => value
+                .as_int()
+                .and_then(|n| u64::try_from(n).ok())
+                .ok_or_else(|| {
+                    Error::Failed(format!("{}: `version` must be a version number", file))
+                })?,
+            None => 1,
+        },
+    };
+    let to = match to {
+        Some(text) => version("--to", text)?,
+        None => migrations
+            .iter()
+            .map(|m| m.version())
+            .max()
+            .unwrap_or(from)
+            .max(from),
+    };
+    doc.migrate(from, to, &migrations)
+        .map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+    let to = i64::try_from(to).map_err(|_| Error::Usage("`--to` is too large".to_string()))?;
+    match doc
+        .nodes
+        .iter_mut()
+        .rev()
+        .find(|node| node.key == "version")
+    {
+        Some(node) => node.value = Value::Int(to),
+        None => doc.nodes.insert(0, Node::new("version", Value::Int(to))),
+    }
+
+    rewrite.finish(file, &read(file)?, &uplang::to_string(&doc), target)
+}
+
+fn parse(args: &[String]) -> Result<(), Error> {
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--format" => &mut format,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => {
+                positional.push(arg.as_str());
+                continue;
+            }
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?;
+        if slot.replace(value.as_str()).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let [file] = positional[..] else {
+        return Err(Error::Usage("expected exactly one file".to_string()));
+    };
+    let doc = load(file)?;
+    match format {
+        None | Some("json") => print!("{}", doc.to_json_typed_pretty()),
+        Some("up") => print!("{}", doc),
+        Some("debug") => println!("{:#?}", doc),
+        Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+    }
+    Ok(())
+}
+
+fn lint(args: &[String]) -> Result<(), Error> {
+    let mut files = Vec::new();
+    let mut linter = Linter::new();
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--allow" => match args.next() {
+                Some(rule) => linter = linter.allow(rule.as_str()),
+                None => return Err(Error::Usage("`--allow` needs a rule".to_string())),
+            },
+            "--suggest" => linter = linter.suggestions(true),
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => json = false,
+                Some("json") => json = true,
+                Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+                None => return Err(Error::Usage("`--format` needs a value".to_string())),
+            },
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::Usage("expected at least one file".to_string()));
+    }
+
+    let mut results = Vec::new();
+    for file in &files {
+        results.push((file.as_str(), linter.lint(&read(file)?)));
+    }
+    let failed = results
+        .iter()
+        .filter(|(_, findings)| findings.iter().any(Finding::is_error))
+        .count();
+
+    if json {
+        println!("{}", lint_report(&results, failed == 0).to_json());
+    } else {
+        for (file, findings) in &results {
+            if findings.is_empty() {
+                println!("{}: ok", file);
+            }
+            for finding in findings {
+                let at = match finding.line {
+                    Some(line) => format!("{}:{}", file, line),
+                    None => file.to_string(),
+                };
+                let warning = if finding.is_error() { "" } else { "warning: " };
+                println!("{}: {}{} [{}]", at, warning, finding.message, finding.rule);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Failed(format!(
+            "{} of {} files have lint errors",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+fn lint_report(results: &[(&str, Vec<Finding>)], passed: bool) -> Document {
+    let files = results.iter().map(|(file, findings)| {
+        let list: Vec<Value> = findings
+            .iter()
+            .map(|f| {
+                let finding: Block = [
+                    ("rule", Value::from(f.rule.as_str())),
+                    ("severity", Value::from(f.severity.as_str())),
+                    ("path", f.path.as_deref().map_or(Value::Null, Value::from)),
+                    ("message", Value::from(f.message.as_str())),
+                    ("line", f.line.map_or(Value::Null, |n| Value::Int(n as i64))),
+                ]
+                .into_iter()
+                .collect();
+                Value::Block(finding)
+            })
+            .collect();
+        let file: Block = [
+            ("file", Value::from(*file)),
+            (
+                "passed",
+                Value::from(!findings.iter().any(Finding::is_error)),
+            ),
+            ("findings", Value::List(list)),
+        ]
+        .into_iter()
+        .collect();
+        Value::Block(file)
+    });
+    Document::builder()
+        .value("passed", passed)
+        .list("files", files)
+        .build()
+}
+
+fn patch(args: &[String]) -> Result<(), Error> {
+    let mut positional = Vec::new();
+    let mut out = None;
+    let mut rewrite = Rewrite::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if rewrite.flag(arg) {
+            continue;
+        }
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?;
+                if out.replace(value).is_some() {
+                    return Err(Error::Usage(format!("`{}` given twice", arg)));
+                }
+            }
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let [file, patch] = positional[..] else {
+        return Err(Error::Usage("expected a file and a patch".to_string()));
+    };
+    let target = rewrite.target(file, out)?;
+    let patch =
+        Patch::parse(&read(patch)?).map_err(|e| Error::Failed(format!("{}: {}", patch, e)))?;
+    let mut doc = load(file)?;
+    doc.apply_patch(&patch)
+        .map_err(|e| Error::Failed(format!("{}: {}", file, e)))?;
+    rewrite.finish(file, &read(file)?, &uplang::to_string(&doc), target)
+}
+
+fn policy(args: &[String]) -> Result<(), Error> {
+    let args = match args.split_first() {
+        Some((command, args)) if command == "check" => args,
+        Some((command, _)) => {
+            return Err(Error::Usage(format!(
+                "unknown policy command `{}`",
+                command
+            )))
+        }
+        None => return Err(Error::Usage("expected `policy check`".to_string())),
+    };
+    let mut positional = Vec::new();
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => json = false,
+                Some("json") => json = true,
+                Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+                None => return Err(Error::Usage("`--format` needs a value".to_string())),
+            },
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let (policy_file, files) = match positional.split_first() {
+        Some((policy, files)) if !files.is_empty() => (policy, files),
+        _ => return Err(Error::Usage("expected a policy and files".to_string())),
+    };
+    let policy = Policy::parse(&read(policy_file)?)
+        .map_err(|e| Error::Failed(format!("{}: {}", policy_file, e)))?;
+
+    let mut results = Vec::new();
+    for file in files {
+        results.push((file.as_str(), policy.check(&load(file)?)));
+    }
+    let errors = results
+        .iter()
+        .flat_map(|(_, violations)| violations)
+        .filter(|v| v.is_error())
+        .count();
+
+    if json {
+        println!("{}", policy_report(&results, errors == 0).to_json());
+    } else {
+        for (file, violations) in &results {
+            if violations.is_empty() {
+                println!("{}: ok", file);
+            }
+            for violation in violations {
+                match violation.origin {
+                    Some(_) => println!("{}", violation),
+                    None => println!("{}: {}", file, violation),
+                }
+            }
+        }
+    }
+
+    if errors > 0 {
+        return Err(Error::Failed(format!(
+            "{} policy violation(s) in {} file(s)",
+            errors,
+            results
+                .iter()
+                .filter(|(_, violations)| violations.iter().any(Violation::is_error))
+                .count()
+        )));
+    }
+    Ok(())
+}
+
+fn policy_report(results: &[(&str, Vec<Violation>)], passed: bool) -> Document {
+    let files = results.iter().map(|(file, violations)| {
+        let list: Vec<Value> = violations
+            .iter()
+            .map(|v| {
+                let violation: Block = [
+                    ("rule", Value::from(v.rule.as_str())),
+                    ("severity", Value::from(v.severity.as_str())),
+                    ("path", Value::from(v.path.as_str())),
+                    ("message", Value::from(v.message.as_str())),
+                    (
+                        "line",
+                        v.line().map_or(Value::Null, |n| Value::Int(n as i64)),
+                    ),
+                ]
+                .into_iter()
+                .collect();
+                Value::Block(violation)
+            })
+            .collect();
+        let file: Block = [
+            ("file", Value::from(*file)),
+            (
+                "passed",
+                Value::from(!violations.iter().any(Violation::is_error)),
+            ),
+            ("violations", Value::List(list)),
+        ]
+        .into_iter()
+        .collect();
+        Value::Block(file)
+    });
+    Document::builder()
+        .value("passed", passed)
+        .list("files", files)
+        .build()
+}
+
+fn schema(args: &[String]) -> Result<(), Error> {
+    let args = match args.split_first() {
+        Some((command, args)) if command == "export" => args,
+        Some((command, _)) => {
+            return Err(Error::Usage(format!(
+                "unknown schema command `{}`",
+                command
+            )))
+        }
+        None => return Err(Error::Usage("expected `schema export`".to_string())),
+    };
+    let mut file = None;
+    let mut out = None;
+    let mut example = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--example" => {
+                example = true;
+                continue;
+            }
+            "-o" | "--output" => &mut out,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => &mut file,
+        };
+        let value = match arg.as_str() {
+            "-o" | "--output" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?,
+            _ => arg,
+        };
+        if slot.replace(value).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let file = file.ok_or_else(|| Error::Usage("expected a schema file".to_string()))?;
+    let schema = if example {
+        Schema::from_example(&load(file)?)
+    } else {
+        Schema::parse(&read(file)?).map_err(|e| Error::Failed(format!("{}: {}", file, e)))?
+    };
+    let json = schema::to_json_schema(&schema);
+    match out {
+        Some(path) => fs::write(path, json).map_err(|e| Error::Failed(format!("{}: {}", path, e))),
+        None => {
+            print!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+fn set(args: &[String]) -> Result<(), Error> {
+    let mut positional = Vec::new();
+    let mut annotation = None;
+    let mut options = true;
+    let mut rewrite = Rewrite::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            _ if !options => positional.push(arg),
+            // Values such as `-1` come after `--`
+            "--" => options = false,
+            flag if rewrite.flag(flag) => {}
+            "--type" => match args.next().map(String::as_str) {
+                Some(name @ ("string" | "int" | "float" | "bool" | "null")) => {
+                    annotation = Some(name)
+                }
+                _ => {
+                    return Err(Error::Usage(
+                        "`--type` must be `string`, `int`, `float`, `bool`, or `null`".to_string(),
+                    ))
+                }
+            },
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let (path, value, patterns) = match positional.as_slice() {
+        [patterns @ .., path, value] if !patterns.is_empty() => (path, value, patterns),
+        _ => {
+            return Err(Error::Usage(
+                "expected files, a key path, and a value".to_string(),
+            ))
+        }
+    };
+    let mut files = Vec::new();
+    for pattern in patterns {
+        for file in expand_glob(pattern)? {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    // Several results on stdout could not be told apart
+    let report = rewrite.in_place || rewrite.dry_run;
+    if files.len() > 1 && !report && !rewrite.diff {
+        return Err(Error::Usage(
+            "setting a value in several files needs `--in-place` or `--dry-run`".to_string(),
+        ));
+    }
+
+    // Every file is edited before any is written, so that a file that
+    // cannot take the value leaves all of them as they were
+    let mut edits = Vec::new();
+    for file in &files {
+        let text = read(file)?;
+        let failed = |e: ParseError| Error::Failed(format!("{}: {}", file, e));
+        let mut doc = Parser::new().parse_document(&text).map_err(failed)?;
+        let annotation = match annotation {
+            Some(annotation) => Some(annotation.to_string()),
+            None => kept_annotation(&doc, path),
+        };
+        let value = typed_value(value, annotation.as_deref())
+            .map_err(|e| Error::Failed(format!("{}: `{}`: {}", file, path, e)))?;
+        if !doc.set_path(path, value) {
+            return Err(Error::Failed(format!(
+                "{}: cannot set `{}` in this document",
+                file, path
+            )));
+        }
+        if let Some(node) = doc.get_node_mut(path) {
+            node.type_annotation = annotation.filter(|annotation| annotation != "string");
+        }
+        // Read back, so that typed values carry the annotation they are
+        // written with
+        let doc = Parser::new()
+            .parse_document(&uplang::to_string(&doc))
+            .map_err(failed)?;
+        // Only the edited value is rewritten; the rest keeps its layout
+        let edited = Writer::new().format_changed(&text, &doc).map_err(failed)?;
+        edits.push((file, text, edited));
+    }
+
+    let mut changed = 0;
+    for (file, old, new) in &edits {
+        rewrite.finish(file, old, new, rewrite.target(file, None)?)?;
+        if !report {
+            continue;
+        }
+        if old != new {
+            changed += 1;
+            println!("{}: changed", file);
+        }
+    }
+    if !report {
+        return Ok(());
+    }
+    println!(
+        "{} {} of {} file(s)",
+        if rewrite.dry_run {
+            "Would change"
+        } else {
+            "Changed"
+        },
+        changed,
+        edits.len()
+    );
+    Ok(())
+}
+
+/// The annotation a value set at `path` keeps: that of the scalar it
+/// replaces, or for a list item the type of the item
+fn kept_annotation(doc: &Document, path: &str) -> Option<String> {
+    if let Some(node) = doc.get_node(path) {
+        // A reference names another key rather than a type, and the
+        // annotation of a block, list, or table does not fit a scalar
+        return node
+            .type_annotation
+            .clone()
+            .filter(|annotation| annotation != "ref" && node.value.scalar_text().is_some());
+    }
+    match doc.get(path)? {
+        Value::String(_) | Value::Empty => None,
+        Value::Custom(custom) => Some(custom.annotation().to_string()),
+        value => value
+            .scalar_text()
+            .is_some()
+            .then(|| value.type_name().to_string()),
+    }
+}
+
+/// Read `text` as a value of the type an annotation such as `int` gives,
+/// or as a string without one
+///
+/// Errors have no location, as `text` is not part of a file.
+fn typed_value(text: &str, annotation: Option<&str>) -> Result<Value, ParseError> {
+    match annotation {
+        None | Some("string") => Ok(Value::String(text.to_string())),
+        Some(annotation) => {
+            let doc = Parser::new()
+                .parse_document(&format!("value!{} {}", annotation, text))
+                .map_err(|mut e| {
+                    e.location = None;
+                    e
+                })?;
+            Ok(doc.get("value").cloned().unwrap_or(Value::Null))
+        }
+    }
+}
+
+/// The files matching a pattern such as `conf.d/*.up`, in order by name
+///
+/// `*` matches any run of characters within one path component and `?` any
+/// one character; a pattern with neither is a file name, returned as it is.
+fn expand_glob(pattern: &str) -> Result<Vec<String>, Error> {
+    let wild = |part: &str| part.contains(['*', '?']);
+    if !wild(pattern) {
+        return Ok(vec![pattern.to_string()]);
+    }
+    let mut paths = vec![PathBuf::new()];
+    for (i, part) in pattern.split('/').enumerate() {
+        let mut next = Vec::new();
+        for dir in paths {
+            if i == 0 && part.is_empty() {
+                next.push(PathBuf::from("/"));
+            } else if !wild(part) {
+                next.push(dir.join(part));
+            } else {
+                let listed = if dir.as_os_str().is_empty() {
+                    fs::read_dir(".")
+                } else {
+                    fs::read_dir(&dir)
+                };
+                // Directories that cannot be listed match nothing
+                let Ok(entries) = listed else {
+                    continue;
+                };
+                let mut names: Vec<String> = entries
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .filter(|name| glob_match(part, name))
+                    .collect();
+                names.sort();
+                next.extend(names.into_iter().map(|name| dir.join(name)));
+            }
+        }
+        paths = next;
+    }
+
+    let files: Vec<String> = paths
+        .into_iter()
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    if files.is_empty() {
+        return Err(Error::Failed(format!("no files match `{}`", pattern)));
+    }
+    Ok(files)
+}
+
+/// Whether a file name matches one component of a glob pattern; as in the
+/// shell, wildcards do not match the `.` of a hidden file
+fn glob_match(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // The last `*` seen, and where in the name it stopped matching
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            // Let the last `*` take one more character
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn table(args: &[String]) -> Result<(), Error> {
+    let mut positional = Vec::new();
+    let mut join = None;
+    let mut on = None;
+    let mut condition = None;
+    let mut sort = None;
+    let mut select = None;
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--format" => &mut format,
+            "--join" => &mut join,
+            "--on" => &mut on,
+            "--where" => &mut condition,
+            "--sort" => &mut sort,
+            "--select" => &mut select,
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => {
+                positional.push(arg.as_str());
+                continue;
+            }
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| Error::Usage(format!("`{}` needs a value", arg)))?;
+        if slot.replace(value.as_str()).is_some() {
+            return Err(Error::Usage(format!("`{}` given twice", arg)));
+        }
+    }
+
+    let [file, path] = positional[..] else {
+        return Err(Error::Usage("expected a file and a table path".to_string()));
+    };
+    let csv = match format {
+        None | Some("up") => false,
+        Some("csv") => true,
+        Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+    };
+    let doc = load(file)?;
+    let lookup = |path: &str| match doc.get(path) {
+        Some(value @ Value::Table { .. }) => Ok(value.clone()),
+        Some(value) => value
+            .to_table()
+            .ok_or_else(|| Error::Failed(format!("{}: `{}` is not a table", file, path))),
+        None => Err(Error::Failed(format!(
+            "{}: `{}` is not defined",
+            file, path
+        ))),
+    };
+    let check_columns = |table: &Value, names: &[&str]| {
+        let (columns, _) = table.as_table().expect("looked up as a table");
+        match names.iter().find(|name| {
+            !columns
+                .iter()
+                .any(|c| c.scalar_text().as_deref() == Some(**name))
+        }) {
+            Some(name) => Err(Error::Failed(format!("{}: no column `{}`", file, name))),
+            None => Ok(()),
+        }
+    };
+
+    let mut table = lookup(path)?;
+    match (join, on) {
+        (Some(other), Some(on)) => {
+            let other = lookup(other)?;
+            check_columns(&table, &[on])?;
+            check_columns(&other, &[on])?;
+            table = table.join(&other, on).expect("checked above");
+        }
+        (None, None) => {}
+        _ => {
+            return Err(Error::Usage(
+                "`--join <path>` and `--on <column>` go together".to_string(),
+            ))
+        }
+    }
+    if let Some(condition) = condition {
+        let (column, text) = condition
+            .split_once('=')
+            .ok_or_else(|| Error::Usage("`--where` must be `<column>=<value>`".to_string()))?;
+        check_columns(&table, &[column])?;
+        table = table
+            .filter(|row| row.get(column).and_then(Value::scalar_text).as_deref() == Some(text))
+            .expect("looked up as a table");
+    }
+    if let Some(column) = sort {
+        check_columns(&table, &[column])?;
+        table = table.sort_by_column(column).expect("checked above");
+    }
+    if let Some(select) = select {
+        let names: Vec<&str> = select.split(',').map(str::trim).collect();
+        check_columns(&table, &names)?;
+        table = table.select(&names).expect("checked above");
+    }
+
+    if csv {
+        print!("{}", table.table().expect("looked up as a table").to_csv());
+        return Ok(());
+    }
+    let key = path.rsplit('.').next().unwrap_or(path);
+    print!("{}", uplang::to_string(&table.to_document(key)));
+    Ok(())
+}
+
+fn validate(args: &[String]) -> Result<(), Error> {
+    let mut files = Vec::new();
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => json = false,
+                Some("json") => json = true,
+                Some(other) => return Err(Error::Usage(format!("unknown format `{}`", other))),
+                None => return Err(Error::Usage("`--format` needs a value".to_string())),
+            },
+            "-" => files.push(arg),
+            flag if flag.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option `{}`", flag)))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::Usage("expected at least one file".to_string()));
+    }
+
+    let results: Vec<(&str, Result<Document, ParseError>)> = files
+        .iter()
+        .map(|file| (file.as_str(), parse_input(file)))
+        .collect();
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+
+    if json {
+        println!("{}", validation_report(&results, failed == 0).to_json());
+    } else {
+        for (file, result) in &results {
+            match result {
+                Ok(_) => println!("{}: ok", file),
+                Err(e) => println!("{}: {}\n", file, e),
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Failed(format!(
+            "{} of {} files failed validation",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a file, or stdin when `file` is `-`
+fn parse_input(file: &str) -> Result<Document, ParseError> {
+    if file != "-" {
+        return Parser::new().parse_file(file);
+    }
+    Parser::new()
+        .parse_reader(io::stdin().lock())
+        .map_err(|mut e| {
+            if let Some(location) = &mut e.location {
+                location.file = Some(Arc::from(Path::new("<stdin>")));
+            }
+            e
+        })
+}
+
+/// The results of `validate` as a document, for `--format json`
+fn validation_report(results: &[(&str, Result<Document, ParseError>)], valid: bool) -> Document {
+    let files = results.iter().map(|(file, result)| {
+        let errors: Vec<Value> = result
+            .as_ref()
+            .err()
+            .map(|e| {
+                let location = e.location.as_ref();
+                let position = |n: Option<usize>| n.map_or(Value::Null, |n| Value::Int(n as i64));
+                let error: Block = [
+                    ("kind", Value::from(format!("{:?}", e.kind))),
+                    ("code", Value::from(e.kind.code())),
+                    ("message", Value::from(e.message.as_str())),
+                    ("line", position(location.map(|l| l.line))),
+                    ("column", position(location.map(|l| l.column))),
+                ]
+                .into_iter()
+                .collect();
+                Value::Block(error)
+            })
+            .into_iter()
+            .collect();
+        let file: Block = [
+            ("file", Value::from(*file)),
+            ("valid", Value::from(result.is_ok())),
+            ("errors", Value::List(errors)),
+        ]
+        .into_iter()
+        .collect();
+        Value::Block(file)
+    });
+    Document::builder()
+        .value("valid", valid)
+        .list("files", files)
+        .build()
+}
+
+fn print_stats(doc: &Document) {
+    let stats = doc.stats();
+
+    println!("values: {}", stats.total());
+    println!("\ntypes:");
+    for (name, count) in &stats.types {
+        println!("  {:<12} {}", name, count);
+    }
+    println!("\nkeys:");
+    for (path, count) in &stats.keys {
+        println!("  {:<32} {}", path, count);
+    }
+    if !stats.duplicates.is_empty() {
+        println!("\nduplicates:");
+        for (path, count) in &stats.duplicates {
+            println!("  {:<32} {}", path, count);
+        }
+    }
+}
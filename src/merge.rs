@@ -0,0 +1,245 @@
+//! Layering documents on top of each other
+
+use crate::keypath::{self, Segment};
+use crate::{Block, Document, Node, Origin, Value};
+
+/// How [`Document::merge`] combines a key defined in both documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MergeStrategy {
+    /// Merge blocks key by key, recursively; the overlay's lists and
+    /// scalars replace the base's, as with [`Document::overlay`]
+    #[default]
+    Deep,
+    /// Like `Deep`, but append the items of the overlay's lists to the
+    /// base's lists
+    AppendLists,
+    /// Replace each top-level value with the overlay's, blocks included
+    Shallow,
+}
+
+/// One definition of a value in a chain of overlays
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// Where this definition came from
+    pub origin: Option<Origin>,
+    /// The value as defined there, before later layers were applied
+    pub value: Value,
+}
+
+impl Document {
+    /// Overlay another document on top of this one
+    ///
+    /// Blocks present in both are merged key by key, recursively; any other
+    /// value in `overlay` replaces the existing one, along with its type
+    /// annotation. Keys only in `overlay` are appended.
+    pub fn overlay(&mut self, overlay: Document) {
+        self.overlay_impl(overlay, MergeStrategy::Deep, false);
+    }
+
+    /// Layer `overlay` on top of this document, combining keys defined in
+    /// both according to `strategy`
+    ///
+    /// Keys only in `overlay` are appended, and values of different kinds
+    /// (say, a block and a scalar) are always replaced, along with their
+    /// type annotation. Merging each layer in turn gives the usual
+    /// `defaults.up` < `environment.up` < `local.up` configuration:
+    ///
+    /// ```
+    /// use uplang::{parse, MergeStrategy};
+    ///
+    /// let defaults = parse("server {\n  host localhost\n  port 80\n}\nplugins [auth]").unwrap();
+    /// let local = parse("server {\n  port 8080\n}\nplugins [metrics]").unwrap();
+    ///
+    /// let merged = defaults.merge(&local, MergeStrategy::AppendLists);
+    /// assert_eq!(merged.get_str("server.host"), Some("localhost"));
+    /// assert_eq!(merged.get_str("server.port"), Some("8080"));
+    /// assert_eq!(merged.get_str("plugins[1]"), Some("metrics"));
+    /// ```
+    pub fn merge(&self, overlay: &Document, strategy: MergeStrategy) -> Document {
+        let mut merged = self.clone();
+        merged.overlay_impl(overlay.clone(), strategy, false);
+        merged
+    }
+
+    /// Like [`overlay`](Self::overlay), but remember every definition of each
+    /// key so that [`explain`](Self::explain) can show how a value was built
+    pub fn overlay_tracked(&mut self, overlay: Document) {
+        self.overlay_impl(overlay, MergeStrategy::Deep, true);
+    }
+
+    fn overlay_impl(&mut self, overlay: Document, strategy: MergeStrategy, track: bool) {
+        for node in overlay.nodes {
+            let existing = self.nodes.iter_mut().rev().find(|n| n.key == node.key);
+            let Some(existing) = existing else {
+                let mut node = node;
+                if track && node.provenance.is_empty() {
+                    node.provenance = vec![node.definition()];
+                }
+                self.nodes.push(node);
+                continue;
+            };
+
+            if track {
+                if existing.provenance.is_empty() {
+                    existing.provenance.push(existing.definition());
+                }
+                if node.provenance.is_empty() {
+                    existing.provenance.push(node.definition());
+                } else {
+                    existing.provenance.extend(node.provenance);
+                }
+            }
+
+            if let Some(value) = merge_values(&mut existing.value, node.value, strategy) {
+                existing.value = value;
+                existing.type_annotation = node.type_annotation;
+            }
+            existing.origin = node.origin;
+        }
+    }
+
+    /// List every definition of the value at `path`, oldest first
+    ///
+    /// Mirrors `git config --show-origin`: after layering `defaults.up`,
+    /// `production.up`, and a command-line override with
+    /// [`overlay_tracked`](Self::overlay_tracked), `explain("server.port")`
+    /// shows each file that set the port and what it set it to. The last
+    /// entry is the definition in effect. Untracked nodes report their own
+    /// definition only; an unknown path gives an empty list.
+    pub fn explain(&self, path: &str) -> Vec<Provenance> {
+        let Some(segments) = keypath::parse(path) else {
+            return Vec::new();
+        };
+        let Some((Segment::Key(key), rest)) = segments.split_first() else {
+            return Vec::new();
+        };
+        let Some(node) = self.nodes.iter().rev().find(|n| n.key == *key) else {
+            return Vec::new();
+        };
+
+        let own = [node.definition()];
+        let chain = if node.provenance.is_empty() {
+            &own[..]
+        } else {
+            &node.provenance[..]
+        };
+
+        chain
+            .iter()
+            .filter_map(|step| {
+                Some(Provenance {
+                    origin: step.origin.clone(),
+                    value: keypath::lookup(&step.value, rest)?.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Node {
+    fn definition(&self) -> Provenance {
+        Provenance {
+            origin: self.origin.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Merge `top` into `base`, or return it if it replaces `base`
+fn merge_values(base: &mut Value, top: Value, strategy: MergeStrategy) -> Option<Value> {
+    match (base, top, strategy) {
+        (_, top, MergeStrategy::Shallow) => Some(top),
+        (Value::Block(base), Value::Block(top), _) => {
+            merge_blocks(base, top, strategy);
+            None
+        }
+        (Value::List(base), Value::List(top), MergeStrategy::AppendLists) => {
+            base.extend(top);
+            None
+        }
+        (_, top, _) => Some(top),
+    }
+}
+
+/// Merge the nodes of `top` into `base`, key by key
+pub(crate) fn merge_blocks(base: &mut Block, top: Block, strategy: MergeStrategy) {
+    for mut node in top.into_nodes() {
+        if let Some(existing) = base.get_mut(&node.key) {
+            match merge_values(existing, node.value, strategy) {
+                Some(value) => node.value = value,
+                None => continue,
+            }
+        }
+        base.insert_node(node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_overlay_merges_blocks() {
+        let mut doc = parse("server {\nhost a\nport 80\n}\nname app").unwrap();
+        doc.overlay(parse("server {\nport 8080\n}\nname!label other\ndebug true").unwrap());
+
+        assert_eq!(doc.nodes.len(), 3);
+        let Value::Block(server) = &doc.nodes[0].value else {
+            panic!("Expected block");
+        };
+        assert_eq!(server.get("host"), Some(&string("a")));
+        assert_eq!(server.get("port"), Some(&string("8080")));
+        assert_eq!(doc.nodes[1].type_annotation.as_deref(), Some("label"));
+        assert_eq!(doc.explain("server.port")[0].value, string("8080"));
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let base = parse("server {\nhost a\ntags [x]\n}\nplugins [auth]\nport!int 80").unwrap();
+        let top = parse("server {\ntags [y]\n}\nplugins [metrics]\nport 8080").unwrap();
+
+        let deep = base.merge(&top, MergeStrategy::Deep);
+        assert_eq!(
+            deep,
+            parse("server {\nhost a\ntags [y]\n}\nplugins [metrics]\nport 8080").unwrap()
+        );
+
+        let appended = base.merge(&top, MergeStrategy::AppendLists);
+        assert_eq!(
+            appended,
+            parse("server {\nhost a\ntags [x, y]\n}\nplugins [auth, metrics]\nport 8080").unwrap()
+        );
+
+        let shallow = base.merge(&top, MergeStrategy::Shallow);
+        assert_eq!(shallow, top);
+
+        let mut overlaid = base.clone();
+        overlaid.overlay(top);
+        assert_eq!(overlaid, deep);
+    }
+
+    #[test]
+    fn test_explain_chain() {
+        let mut doc = parse("server {\nhost a\nport 80\n}").unwrap();
+        doc.overlay_tracked(parse("\nserver {\nport 8080\n}").unwrap());
+        doc.overlay_tracked(parse("\n\nserver {\nport 9090\n}").unwrap());
+
+        let chain = doc.explain("server.port");
+        let values: Vec<&Value> = chain.iter().map(|p| &p.value).collect();
+        assert_eq!(values, [&string("80"), &string("8080"), &string("9090")]);
+        let lines: Vec<usize> = chain
+            .iter()
+            .map(|p| p.origin.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, [1, 2, 3]);
+
+        assert_eq!(doc.explain("server.host").len(), 1);
+        assert!(doc.explain("server.missing").is_empty());
+        assert!(doc.explain("missing").is_empty());
+    }
+}
@@ -0,0 +1,464 @@
+//! Upgrading configuration documents written for older versions of an
+//! application
+
+use std::fmt;
+
+use crate::keypath::{self, Segment};
+use crate::types;
+use crate::{Block, Document, ErrorKind, Node, ParseError, Parser, Value};
+
+/// One upgrade of a document, from the version before [`version`] to it
+///
+/// Implement it for upgrades that need code; [`MigrationSteps`] covers
+/// renaming keys, moving them, and changing their types.
+///
+/// [`version`]: Migration::version
+pub trait Migration {
+    /// The version this migration upgrades documents to
+    fn version(&self) -> u64;
+
+    /// Upgrade `doc`, which is at the previous version
+    fn migrate(&self, doc: &mut Document) -> Result<(), MigrationError>;
+}
+
+impl<M: Migration + ?Sized> Migration for &M {
+    fn version(&self) -> u64 {
+        (**self).version()
+    }
+
+    fn migrate(&self, doc: &mut Document) -> Result<(), MigrationError> {
+        (**self).migrate(doc)
+    }
+}
+
+impl<M: Migration + ?Sized> Migration for Box<M> {
+    fn version(&self) -> u64 {
+        (**self).version()
+    }
+
+    fn migrate(&self, doc: &mut Document) -> Result<(), MigrationError> {
+        (**self).migrate(doc)
+    }
+}
+
+/// Why [`Document::migrate`] failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationError {
+    /// The version being migrated to
+    pub version: u64,
+    /// Details, without the version
+    pub message: String,
+}
+
+impl MigrationError {
+    /// Create an error for the migration to `version`
+    pub fn new(version: u64, message: impl Into<String>) -> Self {
+        Self {
+            version,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "migrating to version {}: {}", self.version, self.message)
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// A change made by [`MigrationSteps`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Give the key at `path` the name `to`, keeping its place in its block
+    Rename { path: String, to: String },
+    /// Move the key at `from` to the key path `to`, creating blocks on the
+    /// way
+    Move { from: String, to: String },
+    /// Annotate the scalar at `path` with `to`, converting its value
+    Retype { path: String, to: String },
+}
+
+/// A migration that applies a list of [`Step`]s in order
+///
+/// Keys a step names that are not in the document are skipped, since
+/// configurations rarely set every key. Steps that would overwrite a key, or
+/// values that cannot be converted, are errors.
+///
+/// ```
+/// use uplang::{parse, MigrationSteps};
+///
+/// let v2 = MigrationSteps::new(2)
+///     .rename("server.addr", "host")
+///     .move_key("tls", "server.tls")
+///     .retype("server.port", "int");
+///
+/// let mut doc = parse("server {\n  addr example.com\n  port 443\n}\ntls true").unwrap();
+/// doc.migrate(1, 2, &[v2]).unwrap();
+/// assert_eq!(doc.get_str("server.host"), Some("example.com"));
+/// assert_eq!(doc.get_int("server.port"), Some(443));
+/// assert_eq!(doc.get_str("server.tls"), Some("true"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationSteps {
+    version: u64,
+    steps: Vec<Step>,
+}
+
+impl MigrationSteps {
+    /// Create a migration to `version` with no steps
+    pub fn new(version: u64) -> Self {
+        Self {
+            version,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Add a step
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Rename the key at `path` to `to`, see [`Step::Rename`]
+    pub fn rename(self, path: impl Into<String>, to: impl Into<String>) -> Self {
+        self.step(Step::Rename {
+            path: path.into(),
+            to: to.into(),
+        })
+    }
+
+    /// Move the key at `from` to `to`, see [`Step::Move`]
+    pub fn move_key(self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.step(Step::Move {
+            from: from.into(),
+            to: to.into(),
+        })
+    }
+
+    /// Change the type of the scalar at `path`, see [`Step::Retype`]
+    pub fn retype(self, path: impl Into<String>, to: impl Into<String>) -> Self {
+        self.step(Step::Retype {
+            path: path.into(),
+            to: to.into(),
+        })
+    }
+
+    /// The steps, in order
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Read migrations written in UP
+    ///
+    /// Each top-level key is a version, holding a list of the steps that
+    /// upgrade to it, one per line:
+    ///
+    /// ```text
+    /// 2 [
+    ///   rename server.addr host
+    ///   move tls server.tls
+    ///   retype server.port int
+    /// ]
+    /// ```
+    pub fn parse_all(input: &str) -> Result<Vec<Self>, ParseError> {
+        let doc = Parser::new().coerce_types(false).parse_document(input)?;
+        doc.nodes
+            .iter()
+            .map(|node| {
+                from_node(node).map_err(|message| {
+                    let at = node
+                        .origin
+                        .as_ref()
+                        .map_or(String::new(), |origin| format!("{}: ", origin));
+                    ParseError::new(
+                        ErrorKind::InvalidValue,
+                        format!("{}`{}`: {}", at, node.key, message),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+fn from_node(node: &Node) -> Result<MigrationSteps, String> {
+    let version = node
+        .key
+        .parse()
+        .map_err(|_| "expected a version number".to_string())?;
+    let items = match &node.value {
+        Value::List(items) => items.as_slice(),
+        Value::String(text) if text.is_empty() => &[],
+        _ => return Err("expected a list of steps".to_string()),
+    };
+
+    let mut migration = MigrationSteps::new(version);
+    for item in items {
+        let text = item
+            .as_str()
+            .ok_or("expected a step, found a nested value")?;
+        let words: Vec<&str> = text.split_whitespace().collect();
+        migration = match words[..] {
+            ["rename", path, to] => migration.rename(path, to),
+            ["move", from, to] => migration.move_key(from, to),
+            ["retype", path, to] => migration.retype(path, to),
+            _ => {
+                return Err(format!(
+                    "unknown step `{}`, expected `rename <path> <key>`, `move <path> <path>`, or `retype <path> <type>`",
+                    text
+                ))
+            }
+        };
+    }
+    Ok(migration)
+}
+
+impl Migration for MigrationSteps {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn migrate(&self, doc: &mut Document) -> Result<(), MigrationError> {
+        for step in &self.steps {
+            apply(doc, step).map_err(|message| MigrationError::new(self.version, message))?;
+        }
+        Ok(())
+    }
+}
+
+impl Document {
+    /// Upgrade this document from version `from` to version `to`
+    ///
+    /// Applies the migration to each version after `from` up to `to`, in
+    /// order; every one of them must be in `migrations`. On error the
+    /// document is left unchanged. The version itself is not stored in the
+    /// document; applications that keep it in a key update it with
+    /// [`set_path`](Self::set_path).
+    pub fn migrate<M: Migration>(
+        &mut self,
+        from: u64,
+        to: u64,
+        migrations: &[M],
+    ) -> Result<(), MigrationError> {
+        if to < from {
+            return Err(MigrationError::new(
+                to,
+                format!("cannot downgrade from version {}", from),
+            ));
+        }
+        let mut doc = self.clone();
+        for version in from + 1..=to {
+            let migration = migrations
+                .iter()
+                .find(|m| m.version() == version)
+                .ok_or_else(|| MigrationError::new(version, "no migration to this version"))?;
+            migration.migrate(&mut doc)?;
+        }
+        *self = doc;
+        Ok(())
+    }
+}
+
+fn apply(doc: &mut Document, step: &Step) -> Result<(), String> {
+    match step {
+        Step::Rename { path, to } => {
+            let (mut parent, key) = parent(doc, path)?;
+            let Some(parent) = parent.as_mut() else {
+                return Ok(());
+            };
+            if !parent.contains(key) {
+                return Ok(());
+            }
+            if parent.contains(to) {
+                return Err(format!("cannot rename `{}`: `{}` already exists", path, to));
+            }
+            parent.rename(key, to);
+        }
+        Step::Move { from, to } => {
+            if doc.get(to).is_some() {
+                return Err(format!("cannot move `{}`: `{}` already exists", from, to));
+            }
+            let (mut source, key) = parent(doc, from)?;
+            let Some(mut node) = source.as_mut().and_then(|parent| parent.take(key)) else {
+                return Ok(());
+            };
+            let (_, new_key) = parent(doc, to)?;
+            node.key = new_key.to_string();
+            // Creates the blocks on the way, and a key to replace
+            if !doc.set_path(to, Value::Null) {
+                return Err(format!("cannot move `{}`: cannot create `{}`", from, to));
+            }
+            let (mut target, _) = parent(doc, to)?;
+            if let Some(slot) = target.as_mut().and_then(|parent| parent.get_mut(new_key)) {
+                *slot = node;
+            }
+        }
+        Step::Retype { path, to } => {
+            let (mut parent, key) = parent(doc, path)?;
+            let Some(node) = parent.as_mut().and_then(|parent| parent.get_mut(key)) else {
+                return Ok(());
+            };
+            let text = node
+                .value
+                .scalar_text()
+                .ok_or_else(|| format!("cannot retype `{}`: not a scalar", path))?
+                .into_owned();
+            node.value = match types::coerce(to, &text) {
+                Some(typed) => {
+                    typed.map_err(|e| format!("cannot retype `{}`: {}", path, e.message))?
+                }
+                None => Value::String(text),
+            };
+            node.type_annotation = Some(to.clone());
+        }
+    }
+    Ok(())
+}
+
+/// The nodes holding a key: the top level of a document, or a block
+enum Parent<'a> {
+    Top(&'a mut Vec<Node>),
+    Block(&'a mut Block),
+}
+
+impl Parent<'_> {
+    fn contains(&self, key: &str) -> bool {
+        match self {
+            Parent::Top(nodes) => nodes.iter().any(|node| node.key == key),
+            Parent::Block(block) => block.contains_key(key),
+        }
+    }
+
+    /// The node for `key`; the last one when a top-level key repeats
+    fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        match self {
+            Parent::Top(nodes) => nodes.iter_mut().rev().find(|node| node.key == key),
+            Parent::Block(block) => block.get_node_mut(key),
+        }
+    }
+
+    fn take(&mut self, key: &str) -> Option<Node> {
+        match self {
+            Parent::Top(nodes) => {
+                let i = nodes.iter().rposition(|node| node.key == key)?;
+                Some(nodes.remove(i))
+            }
+            Parent::Block(block) => block.remove_node(key),
+        }
+    }
+
+    fn rename(&mut self, key: &str, to: &str) {
+        match self {
+            Parent::Top(nodes) => {
+                for node in nodes.iter_mut().filter(|node| node.key == key) {
+                    node.key = to.to_string();
+                }
+            }
+            Parent::Block(block) => {
+                let Some(i) = block.position(key) else {
+                    return;
+                };
+                if let Some(mut node) = block.remove_node(key) {
+                    node.key = to.to_string();
+                    block.insert_node_at(i, node);
+                }
+            }
+        }
+    }
+}
+
+/// The parent of the key at `path`, if it exists, and the key
+fn parent<'d, 'p>(
+    doc: &'d mut Document,
+    path: &'p str,
+) -> Result<(Option<Parent<'d>>, &'p str), String> {
+    let segments = keypath::parse(path).ok_or_else(|| format!("invalid key path `{}`", path))?;
+    let Some((Segment::Key(key), parents)) = segments.split_last() else {
+        return Err(format!("`{}` does not end in a key", path));
+    };
+    let key = *key;
+    let Some((first, rest)) = parents.split_first() else {
+        return Ok((Some(Parent::Top(&mut doc.nodes)), key));
+    };
+    let Segment::Key(first) = first else {
+        return Err(format!("invalid key path `{}`", path));
+    };
+    let parent = doc
+        .nodes
+        .iter_mut()
+        .rev()
+        .find(|node| node.key == *first)
+        .and_then(|node| keypath::lookup_mut(&mut node.value, rest));
+    Ok((
+        match parent {
+            Some(Value::Block(block)) => Some(Parent::Block(block)),
+            _ => None,
+        },
+        key,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const MIGRATIONS: &str = "\
+2 [
+  rename server.addr host
+  move tls server.tls
+]
+3 [
+  retype server.port int
+  move name app.name
+]
+";
+
+    #[test]
+    fn test_migrate_steps() {
+        let migrations = MigrationSteps::parse_all(MIGRATIONS).unwrap();
+        let mut doc = parse(
+            "name web\nserver {\n  # Public address\n  addr example.com\n  port 443\n}\ntls!bool true\n",
+        )
+        .unwrap();
+        doc.migrate(1, 3, &migrations).unwrap();
+        assert_eq!(
+            crate::to_string(&doc),
+            "server {\n  # Public address\n  host example.com\n  port!int 443\n  tls!bool true\n}\napp {\n  name web\n}\n"
+        );
+
+        // Keys the document does not set are skipped
+        let mut doc = parse("server {\n  port 80\n}").unwrap();
+        doc.migrate(2, 3, &migrations).unwrap();
+        assert_eq!(doc.get("server.port"), Some(&Value::Int(80)));
+        doc.migrate(3, 3, &migrations).unwrap();
+    }
+
+    #[test]
+    fn test_migration_errors() {
+        let migrations = MigrationSteps::parse_all(MIGRATIONS).unwrap();
+        let original = parse("server {\n  addr a\n  host b\n}\n").unwrap();
+        let mut doc = original.clone();
+        let err = doc.migrate(1, 3, &migrations).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "migrating to version 2: cannot rename `server.addr`: `host` already exists"
+        );
+        assert_eq!(doc, original);
+
+        let err = doc.migrate(3, 4, &migrations).unwrap_err();
+        assert_eq!(err, MigrationError::new(4, "no migration to this version"));
+        assert!(doc.migrate(3, 1, &migrations).is_err());
+
+        let mut doc = parse("server {\n  port https\n}").unwrap();
+        let err = doc.migrate(2, 3, &migrations).unwrap_err();
+        assert!(err.message.starts_with("cannot retype `server.port`"));
+
+        let err = MigrationSteps::parse_all("2 [\n  delete x\n]").unwrap_err();
+        assert!(err
+            .message
+            .starts_with("line 1: `2`: unknown step `delete x`"));
+        assert!(MigrationSteps::parse_all("two []").is_err());
+    }
+}
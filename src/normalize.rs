@@ -0,0 +1,218 @@
+//! Cleaning up scalar values as they are parsed, see [`Normalizer`]
+//!
+//! Values pasted from documents, chat, or web pages often carry characters
+//! that look right but compare wrong: a non-breaking space after a port
+//! number, typographic quotes around a password, a zero-width space in a
+//! host name. A parser given normalizers with [`Parser::normalizer`] runs
+//! each scalar, quoted or not, through them, in the order they were added,
+//! before its type annotation is applied, so `port!int 8080` followed by a
+//! zero-width space still reads as a number.
+//!
+//! ```
+//! use uplang::normalize::{InvisibleChars, SmartQuotes, Trim};
+//! use uplang::Parser;
+//!
+//! let parser = Parser::new()
+//!     .normalizer(InvisibleChars)
+//!     .normalizer(SmartQuotes)
+//!     .normalizer(Trim::Both);
+//! let doc = parser
+//!     .parse_document("port!int 8080\u{200b}\nhost \"ex\u{200b}ample.com\u{a0}\"\ngreeting \u{201c}hi\u{201d}\n")
+//!     .unwrap();
+//! assert_eq!(doc.get_int("port"), Some(8080));
+//! assert_eq!(doc.get_str("host"), Some("example.com"));
+//! assert_eq!(doc.get_str("greeting"), Some("\"hi\""));
+//! ```
+//!
+//! Multiline strings are kept exactly as written, as are the values of
+//! [`Parser::parse_borrowed`], which are slices of the input.
+//!
+//! [`Parser::normalizer`]: crate::Parser::normalizer
+//! [`Parser::parse_borrowed`]: crate::Parser::parse_borrowed
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// Rewrites the text of scalar values at parse time, see the
+/// [module docs](self)
+///
+/// Normalizers return the new text, or `None` when `text` needs no change,
+/// so values that are already clean are not copied. Closures of the form
+/// `Fn(&str) -> Option<String>` implement it:
+///
+/// ```
+/// use uplang::Parser;
+///
+/// let parser = Parser::new().normalizer(|text: &str| {
+///     text.contains('\t').then(|| text.replace('\t', " "))
+/// });
+/// let doc = parser.parse_document("motd hello\tworld").unwrap();
+/// assert_eq!(doc.get_str("motd"), Some("hello world"));
+/// ```
+pub trait Normalizer: Send + Sync {
+    /// The normalized form of `text`, or `None` to keep it
+    fn normalize(&self, text: &str) -> Option<String>;
+}
+
+impl<F> Normalizer for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn normalize(&self, text: &str) -> Option<String> {
+        self(text)
+    }
+}
+
+/// Removes whitespace, including non-breaking and other Unicode spaces,
+/// from the ends of a value
+///
+/// Unquoted values never keep whitespace at their ends, so this matters for
+/// quoted values, and for spaces uncovered by an earlier normalizer such as
+/// [`InvisibleChars`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trim {
+    /// Trim the start of values
+    Start,
+    /// Trim the end of values
+    End,
+    /// Trim both ends of values
+    Both,
+}
+
+impl Normalizer for Trim {
+    fn normalize(&self, text: &str) -> Option<String> {
+        let trimmed = match self {
+            Trim::Start => text.trim_start(),
+            Trim::End => text.trim_end(),
+            Trim::Both => text.trim(),
+        };
+        (trimmed.len() != text.len()).then(|| trimmed.to_string())
+    }
+}
+
+/// Replaces typographic quotes, such as `“` and `’`, with `"` and `'`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SmartQuotes;
+
+impl Normalizer for SmartQuotes {
+    fn normalize(&self, text: &str) -> Option<String> {
+        let straight = |c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => Some('\''),
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => Some('"'),
+            _ => None,
+        };
+        text.chars()
+            .any(|c| straight(c).is_some())
+            .then(|| text.chars().map(|c| straight(c).unwrap_or(c)).collect())
+    }
+}
+
+/// Replaces non-breaking spaces with plain spaces and removes zero-width
+/// characters, soft hyphens, and byte order marks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InvisibleChars;
+
+impl Normalizer for InvisibleChars {
+    fn normalize(&self, text: &str) -> Option<String> {
+        let clean = |c| match c {
+            '\u{a0}' | '\u{2007}' | '\u{202f}' => Some(' '),
+            '\u{ad}' | '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' => None,
+            c => Some(c),
+        };
+        text.chars()
+            .any(|c| clean(c) != Some(c))
+            .then(|| text.chars().filter_map(clean).collect())
+    }
+}
+
+/// The normalizers of a parser, in the order they run
+#[derive(Clone, Default)]
+pub(crate) struct Normalizers(Vec<Arc<dyn Normalizer>>);
+
+impl Normalizers {
+    pub(crate) fn push(&mut self, normalizer: Arc<dyn Normalizer>) {
+        self.0.push(normalizer);
+    }
+
+    /// Run `text` through every normalizer
+    pub(crate) fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for normalizer in &self.0 {
+            if let Some(normalized) = normalizer.normalize(&text) {
+                text = Cow::Owned(normalized);
+            }
+        }
+        text
+    }
+}
+
+impl fmt::Debug for Normalizers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} normalizer(s)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Value};
+
+    #[test]
+    fn test_builtin_normalizers() {
+        assert_eq!(
+            Trim::Both.normalize("\u{a0} a b\u{3000}"),
+            Some("a b".into())
+        );
+        assert_eq!(
+            Trim::Start.normalize("\u{a0}a\u{a0}"),
+            Some("a\u{a0}".into())
+        );
+        assert_eq!(Trim::End.normalize("\u{a0}a\u{a0}"), Some("\u{a0}a".into()));
+        assert_eq!(Trim::Both.normalize("a b"), None);
+        assert_eq!(
+            SmartQuotes.normalize("\u{201c}it\u{2019}s\u{201d}"),
+            Some("\"it's\"".into())
+        );
+        assert_eq!(SmartQuotes.normalize("plain"), None);
+        assert_eq!(
+            InvisibleChars.normalize("a\u{a0}b\u{200b}c\u{feff}"),
+            Some("a bc".into())
+        );
+        assert_eq!(InvisibleChars.normalize("abc"), None);
+    }
+
+    #[test]
+    fn test_parser_normalizes_scalars() {
+        let parser = Parser::new()
+            .normalizer(InvisibleChars)
+            .normalizer(Trim::Both);
+        let input = "\
+port!int 8080\u{200b}
+hosts [a\u{200b}, b\u{a0}]
+tags [
+  x\u{2060}y
+]
+point { x!int 1\u{a0}, y 2 }
+quoted \"\u{a0}trimmed\u{a0}\"
+text ```
+\u{a0}kept
+```
+";
+        let doc = parser.parse_document(input).unwrap();
+        assert_eq!(doc.get_int("port"), Some(8080));
+        assert_eq!(
+            doc.get("hosts"),
+            Some(&Value::List(vec!["a".into(), "b".into()]))
+        );
+        assert_eq!(doc.get_str("tags[0]"), Some("xy"));
+        assert_eq!(doc.get_int("point.x"), Some(1));
+        assert_eq!(doc.get_str("quoted"), Some("trimmed"));
+        assert_eq!(doc.get_str("text"), Some("\u{a0}kept"));
+
+        // Without normalizers, nothing changes
+        let doc = Parser::new().parse_document("hosts [a\u{200b}]").unwrap();
+        assert_eq!(doc.get_str("hosts[0]"), Some("a\u{200b}"));
+        assert!(Parser::new().parse_document("port!int 80\u{200b}").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Folding ranges and outlines, for editors
+
+use crate::stream::Boundary;
+use crate::{Document, Node, Span, Value};
+
+/// Lines of a document an editor can fold away, see [`folding_ranges`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FoldingRange {
+    /// 1-based line that opens the range, such as `server {`
+    pub start_line: usize,
+    /// 1-based line that closes it, such as `}`
+    pub end_line: usize,
+    /// What the lines hold
+    pub kind: FoldKind,
+}
+
+/// What a [`FoldingRange`] holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FoldKind {
+    /// A block or table, from `{` to `}`
+    Block,
+    /// A multiline list, from `[` to `]`
+    List,
+    /// A fenced multiline string or embedded document
+    Multiline,
+    /// Two or more comment lines in a row
+    Comment,
+}
+
+/// The ranges of lines that can be folded in `input`, ordered by their
+/// first line
+///
+/// Works on the text alone, so it needs no parsing and copes with documents
+/// that do not parse, as they are while being edited. Blocks, lists, and
+/// multiline strings that span a single line are left out, as are those
+/// still open at the end of the input.
+///
+/// ```
+/// use uplang::{folding_ranges, FoldKind};
+///
+/// let input = "# Web server\n# settings\nserver {\n  ports [\n    80\n  ]\n}\n";
+/// let ranges: Vec<_> = folding_ranges(input)
+///     .iter()
+///     .map(|r| (r.start_line, r.end_line, r.kind))
+///     .collect();
+/// assert_eq!(ranges, [(1, 2, FoldKind::Comment), (3, 7, FoldKind::Block), (4, 6, FoldKind::List)]);
+/// ```
+pub fn folding_ranges(input: &str) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut boundary = Boundary::default();
+    // First lines of the open blocks and lists, and of the open fence
+    let mut open: Vec<(usize, FoldKind)> = Vec::new();
+    let mut fence = None;
+    let mut comments: Option<(usize, usize)> = None;
+
+    let mut fold = |start_line: usize, end_line: usize, kind| {
+        if end_line > start_line {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                kind,
+            });
+        }
+    };
+    for (i, line) in input.lines().enumerate() {
+        let line_num = i + 1;
+        let in_fence = boundary.in_fence();
+        let trimmed = line.trim();
+        if !in_fence && trimmed.starts_with('#') {
+            comments = match comments {
+                Some((start, _)) => Some((start, line_num)),
+                None => Some((line_num, line_num)),
+            };
+            continue;
+        }
+        if let Some((start, end)) = comments.take() {
+            fold(start, end, FoldKind::Comment);
+        }
+
+        let depth = boundary.depth();
+        boundary.line(line);
+        match (in_fence, boundary.in_fence()) {
+            (false, true) => fence = Some(line_num),
+            (true, false) => {
+                if let Some(start) = fence.take() {
+                    fold(start, line_num, FoldKind::Multiline);
+                }
+            }
+            _ => {}
+        }
+        if boundary.depth() > depth {
+            let kind = if boundary.in_list() {
+                FoldKind::List
+            } else {
+                FoldKind::Block
+            };
+            open.push((line_num, kind));
+        }
+        for _ in boundary.depth()..depth {
+            if let Some((start, kind)) = open.pop() {
+                fold(start, line_num, kind);
+            }
+        }
+    }
+    if let Some((start, end)) = comments {
+        fold(start, end, FoldKind::Comment);
+    }
+
+    ranges.sort_by_key(|range| range.start_line);
+    ranges
+}
+
+/// One key of a document's outline, see [`Document::outline`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem<'a> {
+    /// Key path of the node, e.g. `servers[2].host`
+    pub path: String,
+    /// The node itself
+    pub node: &'a Node,
+    /// Where the node is in the source
+    pub span: &'a Span,
+    /// Keys nested in the node's block, or in the blocks of its list
+    pub children: Vec<OutlineItem<'a>>,
+}
+
+impl Document {
+    /// The keys of the document as a tree, with where each one is in the
+    /// source, for an editor's outline view and breadcrumbs
+    ///
+    /// Only nodes with a [`Span`] are included, so the document must be
+    /// parsed with [`Parser::spans`](crate::Parser::spans); values copied
+    /// by `!ref` have none. The keys of blocks in a list are children of
+    /// the list's key.
+    ///
+    /// ```
+    /// let input = "server {\n  host h\n}\nbackends [\n  {\n    url a\n  }\n]\n";
+    /// let doc = uplang::Parser::new().spans(true).parse_document(input).unwrap();
+    /// let outline = doc.outline();
+    /// assert_eq!(outline[0].node.key, "server");
+    /// assert_eq!(outline[0].children[0].path, "server.host");
+    /// assert_eq!(outline[1].children[0].path, "backends[0].url");
+    /// assert_eq!(outline[1].span.end_line, 8);
+    /// ```
+    pub fn outline(&self) -> Vec<OutlineItem<'_>> {
+        let mut items = Vec::new();
+        for node in &self.nodes {
+            outline_node(node.key.clone(), node, &mut items);
+        }
+        items
+    }
+}
+
+fn outline_node<'a>(path: String, node: &'a Node, out: &mut Vec<OutlineItem<'a>>) {
+    let Some(span) = &node.span else {
+        return;
+    };
+    let mut children = Vec::new();
+    outline_value(&path, &node.value, &mut children);
+    out.push(OutlineItem {
+        path,
+        node,
+        span,
+        children,
+    });
+}
+
+fn outline_value<'a>(path: &str, value: &'a Value, out: &mut Vec<OutlineItem<'a>>) {
+    match value {
+        Value::Block(block) => {
+            for child in block.nodes() {
+                outline_node(format!("{}.{}", path, child.key), child, out);
+            }
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                outline_value(&format!("{}[{}]", path, i), item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_folding_ranges() {
+        let input = "# Service\nname app\nserver {\n  # One line\n  tls!table {\n    columns [a]\n    rows [\n      [1]\n    ]\n  }\n  script ```\n    run\n  ```\n  empty {\n  }\n  inline [a, b]\n}\nbackends [\n  {\n    url a\n  }\n]\n# Trailing\n# comments\n";
+        let found: Vec<_> = folding_ranges(input)
+            .iter()
+            .map(|r| (r.start_line, r.end_line, r.kind))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (3, 17, FoldKind::Block),
+                (5, 10, FoldKind::Block),
+                (7, 9, FoldKind::List),
+                (11, 13, FoldKind::Multiline),
+                (14, 15, FoldKind::Block),
+                (18, 22, FoldKind::List),
+                (19, 21, FoldKind::Block),
+                (23, 24, FoldKind::Comment),
+            ]
+        );
+
+        // Unfinished documents fold what is closed
+        let found = folding_ranges("a {\n  b [\n    1\n  ]\n  c ```\n    x\n");
+        assert_eq!(
+            found,
+            [FoldingRange {
+                start_line: 2,
+                end_line: 4,
+                kind: FoldKind::List
+            }]
+        );
+    }
+
+    #[test]
+    fn test_outline() {
+        let input =
+            "name app\nbase!anchor {\n  port!int 80\n}\nservers [\n  {\n    web!ref base\n  }\n]\n";
+        let doc = Parser::new().spans(true).parse_document(input).unwrap();
+        fn paths(items: &[OutlineItem<'_>], out: &mut Vec<String>) {
+            for item in items {
+                out.push(format!(
+                    "{} {}-{}",
+                    item.path, item.span.start_line, item.span.end_line
+                ));
+                paths(&item.children, out);
+            }
+        }
+        let mut found = Vec::new();
+        paths(&doc.outline(), &mut found);
+        assert_eq!(
+            found,
+            [
+                "name 1-1",
+                "base 2-4",
+                "base.port 3-3",
+                "servers 5-9",
+                "servers[0].web 7-7"
+            ]
+        );
+        assert!(Parser::new()
+            .parse_document(input)
+            .unwrap()
+            .outline()
+            .is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+//! Declarative changes to a document, see [`Patch`]
+
+use std::fmt;
+
+use crate::keypath;
+use crate::{Block, Document, ErrorKind, Node, ParseError, Parser, Value};
+
+/// One change made by a [`Patch`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    /// Set the key path `path`, which must not exist yet, creating blocks
+    /// on the way
+    Add {
+        path: String,
+        value: Value,
+        annotation: Option<String>,
+    },
+    /// Set the key path `path`, which must exist, replacing its value and
+    /// annotation
+    Replace {
+        path: String,
+        value: Value,
+        annotation: Option<String>,
+    },
+    /// Remove the key path `path`, which must exist
+    Remove { path: String },
+    /// Add `items` to the end of the list at `path`, creating the list if
+    /// it is missing
+    Append { path: String, items: Vec<Value> },
+}
+
+impl Op {
+    /// The operation's name as written in a patch, such as `add`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Add { .. } => "add",
+            Op::Replace { .. } => "replace",
+            Op::Remove { .. } => "remove",
+            Op::Append { .. } => "append",
+        }
+    }
+
+    /// The key path the operation changes
+    pub fn path(&self) -> &str {
+        match self {
+            Op::Add { path, .. }
+            | Op::Replace { path, .. }
+            | Op::Remove { path }
+            | Op::Append { path, .. } => path,
+        }
+    }
+}
+
+/// Changes to apply to a document, such as the overrides of one
+/// environment, see [`Document::apply_patch`]
+///
+/// A patch is written in UP as blocks named for their operation, each
+/// holding the key paths it changes, in the order they apply:
+///
+/// ```text
+/// replace {
+///   server.port!int 8443
+///   log.level warn
+/// }
+/// add {
+///   server.tls {
+///     cert /etc/tls/cert.pem
+///   }
+/// }
+/// remove [
+///   debug
+/// ]
+/// append {
+///   server.hosts [c.example.com]
+/// }
+/// ```
+///
+/// `append` adds each item of a list, or a single value. Writing a patch
+/// with [`to_string`](ToString::to_string) gives text that reads back as
+/// the same patch.
+///
+/// ```
+/// use uplang::{parse, Patch};
+///
+/// let patch = Patch::parse("replace {\n  server.port!int 8443\n}\nremove [debug]\n").unwrap();
+/// let mut doc = parse("server {\n  port!int 80\n}\ndebug true\n").unwrap();
+/// doc.apply_patch(&patch).unwrap();
+/// assert_eq!(doc.get_int("server.port"), Some(8443));
+/// assert!(doc.get("debug").is_none());
+///
+/// let patch = Patch::new().add("server.port", 8080);
+/// let err = doc.apply_patch(&patch).unwrap_err();
+/// assert_eq!(err.to_string(), "cannot add `server.port`: it already exists");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    ops: Vec<Op>,
+}
+
+impl Patch {
+    /// Create a patch that changes nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an operation
+    pub fn op(mut self, op: Op) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// Add a key that must not exist yet, see [`Op::Add`]
+    pub fn add(self, path: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.op(Op::Add {
+            path: path.into(),
+            value: value.into(),
+            annotation: None,
+        })
+    }
+
+    /// Replace the value of a key that must exist, see [`Op::Replace`]
+    pub fn replace(self, path: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.op(Op::Replace {
+            path: path.into(),
+            value: value.into(),
+            annotation: None,
+        })
+    }
+
+    /// Remove a key that must exist, see [`Op::Remove`]
+    pub fn remove(self, path: impl Into<String>) -> Self {
+        self.op(Op::Remove { path: path.into() })
+    }
+
+    /// Add items to the end of a list, see [`Op::Append`]
+    pub fn append(self, path: impl Into<String>, items: Vec<Value>) -> Self {
+        self.op(Op::Append {
+            path: path.into(),
+            items,
+        })
+    }
+
+    /// The operations, in the order they apply
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Read a patch written in UP, see the [type docs](Self)
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let doc = Parser::new().parse_document(input)?;
+        let mut patch = Self::new();
+        for node in &doc.nodes {
+            let error = |message: String| {
+                let at = node
+                    .origin
+                    .as_ref()
+                    .map_or(String::new(), |origin| format!("{}: ", origin));
+                ParseError::new(
+                    ErrorKind::InvalidValue,
+                    format!("{}`{}` {}", at, node.key, message),
+                )
+            };
+            if node.key == "remove" {
+                let paths = match &node.value {
+                    Value::List(items) => items.as_slice(),
+                    value => std::slice::from_ref(value),
+                };
+                for path in paths {
+                    let path = path
+                        .as_str()
+                        .ok_or_else(|| error("holds a nested value, not a key path".to_string()))?;
+                    patch = patch.remove(path);
+                }
+                continue;
+            }
+            let Value::Block(block) = &node.value else {
+                return Err(error(match node.key.as_str() {
+                    "add" | "replace" | "append" => "must be a block of key paths".to_string(),
+                    _ => "is not an operation; expected `add`, `replace`, `remove`, or `append`"
+                        .to_string(),
+                }));
+            };
+            for child in block.nodes() {
+                let path = child.key.clone();
+                let value = child.value.clone();
+                let annotation = child.type_annotation.clone();
+                patch =
+                    patch.op(match node.key.as_str() {
+                        "add" => Op::Add {
+                            path,
+                            value,
+                            annotation,
+                        },
+                        "replace" => Op::Replace {
+                            path,
+                            value,
+                            annotation,
+                        },
+                        "append" => Op::Append {
+                            path,
+                            items: match value {
+                                Value::List(items) => items,
+                                value => vec![value],
+                            },
+                        },
+                        _ => return Err(error(
+                            "is not an operation; expected `add`, `replace`, `remove`, or `append`"
+                                .to_string(),
+                        )),
+                    });
+            }
+        }
+        Ok(patch)
+    }
+
+    /// The patch as a UP document, one block or list for each run of
+    /// operations of the same kind
+    pub fn to_document(&self) -> Document {
+        let mut doc = Document::new();
+        for op in &self.ops {
+            // A path already in the last run starts another, to keep the order
+            let run = doc.nodes.last_mut().filter(|node| {
+                node.key == op.name()
+                    && match &node.value {
+                        Value::Block(block) => !block.contains_key(op.path()),
+                        _ => true,
+                    }
+            });
+            let node = match run {
+                Some(node) => node,
+                None => {
+                    let value = match op {
+                        Op::Remove { .. } => Value::List(Vec::new()),
+                        _ => Value::Block(Block::new()),
+                    };
+                    doc.nodes.push(Node::new(op.name(), value));
+                    doc.nodes.last_mut().unwrap_or_else(|| unreachable!())
+                }
+            };
+            match (&mut node.value, op) {
+                (Value::List(paths), Op::Remove { path }) => paths.push(Value::from(path.as_str())),
+                (
+                    Value::Block(block),
+                    Op::Add {
+                        path,
+                        value,
+                        annotation,
+                    }
+                    | Op::Replace {
+                        path,
+                        value,
+                        annotation,
+                    },
+                ) => {
+                    block.push_new(Node {
+                        type_annotation: annotation.clone(),
+                        ..Node::new(path.as_str(), value.clone())
+                    });
+                }
+                (Value::Block(block), Op::Append { path, items }) => {
+                    block.push_new(Node::new(path.as_str(), Value::List(items.clone())));
+                }
+                _ => unreachable!("runs hold operations of one kind"),
+            }
+        }
+        doc
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_document().fmt(f)
+    }
+}
+
+/// Why [`Document::apply_patch`] failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    /// Position of the operation that could not be applied in
+    /// [`Patch::ops`]
+    pub index: usize,
+    /// Its name, such as `add`
+    pub op: &'static str,
+    /// The key path it changes
+    pub path: String,
+    /// Why, without the operation
+    pub message: String,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot {} `{}`: {}", self.op, self.path, self.message)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl Document {
+    /// Apply the operations of `patch` in order
+    ///
+    /// On error the document is left unchanged.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        let mut doc = self.clone();
+        for (index, op) in patch.ops.iter().enumerate() {
+            apply(&mut doc, op).map_err(|message| PatchError {
+                index,
+                op: op.name(),
+                path: op.path().to_string(),
+                message,
+            })?;
+        }
+        *self = doc;
+        Ok(())
+    }
+}
+
+fn apply(doc: &mut Document, op: &Op) -> Result<(), String> {
+    let path = op.path();
+    let exists = doc.get(path).is_some();
+    match op {
+        Op::Add {
+            value, annotation, ..
+        }
+        | Op::Replace {
+            value, annotation, ..
+        } => {
+            match (op, exists) {
+                (Op::Add { .. }, true) => return Err("it already exists".to_string()),
+                (Op::Replace { .. }, false) => return Err("it does not exist".to_string()),
+                _ => {}
+            }
+            if !doc.set_path(path, value.clone()) {
+                return Err(unreachable_path(path));
+            }
+            if let Some(node) = doc.get_node_mut(path) {
+                node.type_annotation = annotation.clone();
+            }
+        }
+        Op::Remove { .. } => {
+            doc.remove_path(path)
+                .ok_or_else(|| "it does not exist".to_string())?;
+        }
+        Op::Append { items, .. } => {
+            if !exists {
+                if !doc.set_path(path, Value::List(items.clone())) {
+                    return Err(unreachable_path(path));
+                }
+                return Ok(());
+            }
+            let segments = keypath::parse(path).ok_or_else(|| unreachable_path(path))?;
+            match doc.lookup_mut(&segments) {
+                Some(Value::List(list)) => list.extend(items.iter().cloned()),
+                Some(other) => return Err(format!("it is a {}, not a list", other.type_name())),
+                None => return Err(unreachable_path(path)),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unreachable_path(path: &str) -> String {
+    match keypath::parse(path) {
+        Some(_) => "the path runs through a value that is not a block".to_string(),
+        None => "it is not a key path".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const PATCH: &str = "\
+replace {
+  server.port!int 8443
+  log.level warn
+}
+add {
+  server.tls {
+    cert /etc/tls/cert.pem
+  }
+}
+remove [
+  debug
+  servers[0]
+]
+append {
+  server.hosts [c.example.com]
+  server.tags prod
+}
+";
+
+    #[test]
+    fn test_apply_patch() {
+        let patch = Patch::parse(PATCH).unwrap();
+        assert_eq!(patch.ops().len(), 7);
+        assert_eq!(patch.ops()[4].name(), "remove");
+        assert_eq!(patch.ops()[4].path(), "servers[0]");
+
+        let mut doc = parse(
+            "server {\n  port!int 80\n  hosts [a.example.com, b.example.com]\n}\nlog {\n  level debug\n}\ndebug true\nservers [\n  old\n  new\n]\n",
+        )
+        .unwrap();
+        doc.apply_patch(&patch).unwrap();
+        assert_eq!(doc.get_int("server.port"), Some(8443));
+        assert_eq!(doc.get_str("log.level"), Some("warn"));
+        assert_eq!(doc.get_str("server.tls.cert"), Some("/etc/tls/cert.pem"));
+        assert!(doc.get("debug").is_none());
+        assert_eq!(doc.get_str("servers[0]"), Some("new"));
+        let hosts: Vec<&str> = doc
+            .get("server.hosts")
+            .and_then(Value::as_list)
+            .unwrap()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        assert_eq!(hosts, ["a.example.com", "b.example.com", "c.example.com"]);
+        assert_eq!(doc.get_str("server.tags[0]"), Some("prod"));
+        assert!(crate::to_string(&doc).contains("  port!int 8443\n"));
+
+        // Written out, a patch reads back the same
+        assert_eq!(Patch::parse(&patch.to_string()).unwrap(), patch);
+        let written = patch.to_string();
+        assert!(written.starts_with("replace {\n  server.port!int 8443\n"));
+        assert!(written.contains("remove [debug, \"servers[0]\"]\n"));
+        assert!(written.ends_with("  server.tags [prod]\n}\n"));
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let mut doc = parse("name app\nserver {\n  port!int 80\n}\n").unwrap();
+        let original = doc.clone();
+        for (patch, message) in [
+            (
+                Patch::new().add("server.port", 8080),
+                "cannot add `server.port`: it already exists",
+            ),
+            (
+                Patch::new().replace("server.host", "a"),
+                "cannot replace `server.host`: it does not exist",
+            ),
+            (
+                Patch::new().remove("tls"),
+                "cannot remove `tls`: it does not exist",
+            ),
+            (
+                Patch::new().append("name", vec![Value::from("x")]),
+                "cannot append `name`: it is a string, not a list",
+            ),
+            (
+                Patch::new().add("name.first", "x"),
+                "cannot add `name.first`: the path runs through a value that is not a block",
+            ),
+        ] {
+            // Earlier operations are undone too
+            let patch = Patch::new()
+                .replace("name", "other")
+                .op(patch.ops()[0].clone());
+            let err = doc.apply_patch(&patch).unwrap_err();
+            assert_eq!(err.to_string(), message);
+            assert_eq!(err.index, 1);
+            assert_eq!(doc, original);
+        }
+
+        let err = Patch::parse("name app\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "line 1: `name` is not an operation; expected `add`, `replace`, `remove`, or `append`"
+        );
+        let err = Patch::parse("add [x]\n").unwrap_err();
+        assert_eq!(err.message, "line 1: `add` must be a block of key paths");
+        let err = Patch::parse("remove [\n  {\n    a 1\n  }\n]\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "line 1: `remove` holds a nested value, not a key path"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Immutable documents with structural sharing
+
+use std::sync::Arc;
+
+use crate::keypath::{self, Segment};
+use crate::{Document, Node, Value};
+
+/// An immutable document where every edit returns a new version
+///
+/// Versions share all subtrees an edit did not touch, so keeping a history
+/// of config snapshots costs memory proportional to the changes, not to the
+/// document size. Versions are cheap to clone and can be shared across
+/// threads.
+#[derive(Debug, Clone, Default)]
+pub struct PersistentDocument {
+    nodes: Arc<Vec<SharedNode>>,
+}
+
+#[derive(Debug, Clone)]
+struct SharedNode {
+    key: Arc<str>,
+    type_annotation: Option<Arc<str>>,
+    value: Shared,
+}
+
+#[derive(Debug, Clone)]
+enum Shared {
+    Block(Arc<Vec<SharedNode>>),
+    List(Arc<Vec<Shared>>),
+    Leaf(Arc<Value>),
+}
+
+impl SharedNode {
+    fn from_node(node: &Node) -> Self {
+        Self {
+            key: Arc::from(node.key.as_str()),
+            type_annotation: node.type_annotation.as_deref().map(Arc::from),
+            value: Shared::from_value(&node.value),
+        }
+    }
+
+    /// Unannotated node holding a fresh subtree for `path`
+    fn new_at(key: &str, path: &[Segment<'_>], value: &Value) -> Option<Self> {
+        Some(Self {
+            key: Arc::from(key),
+            type_annotation: None,
+            value: Shared::new_at(path, value)?,
+        })
+    }
+
+    /// Replace (or remove) the target of `path` below this node
+    fn set(&mut self, path: &[Segment<'_>], value: Option<&Value>) -> Option<()> {
+        self.value = self.value.with(path, value)?;
+        if path.is_empty() {
+            // The annotation described the value being replaced
+            self.type_annotation = None;
+        }
+        Some(())
+    }
+
+    fn to_node(&self) -> Node {
+        Node {
+            type_annotation: self.type_annotation.as_deref().map(str::to_string),
+            ..Node::new(&*self.key, self.value.to_value())
+        }
+    }
+}
+
+impl Shared {
+    fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Block(block) => {
+                Shared::Block(Arc::new(block.nodes().map(SharedNode::from_node).collect()))
+            }
+            Value::List(items) => {
+                Shared::List(Arc::new(items.iter().map(Shared::from_value).collect()))
+            }
+            other => Shared::Leaf(Arc::new(other.clone())),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Shared::Block(entries) => {
+                Value::Block(entries.iter().map(SharedNode::to_node).collect())
+            }
+            Shared::List(items) => Value::List(items.iter().map(Shared::to_value).collect()),
+            Shared::Leaf(value) => (**value).clone(),
+        }
+    }
+
+    fn get(&self, path: &[Segment<'_>]) -> Option<&Shared> {
+        match (path.first(), self) {
+            (None, _) => Some(self),
+            (Some(Segment::Key(key)), Shared::Block(entries)) => entries
+                .iter()
+                .find(|entry| &*entry.key == *key)
+                .and_then(|entry| entry.value.get(&path[1..])),
+            (Some(Segment::Index(i)), Shared::List(items)) => items.get(*i)?.get(&path[1..]),
+            _ => None,
+        }
+    }
+
+    /// Copy the spine along `path` and replace (or remove) its target
+    fn with(&self, path: &[Segment<'_>], value: Option<&Value>) -> Option<Shared> {
+        let (first, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return value.map(Shared::from_value),
+        };
+
+        match (first, self) {
+            (Segment::Key(key), Shared::Block(entries)) => {
+                let mut entries = (**entries).clone();
+                match entries.iter().position(|entry| &*entry.key == *key) {
+                    Some(i) if rest.is_empty() && value.is_none() => {
+                        entries.remove(i);
+                    }
+                    Some(i) => entries[i].set(rest, value)?,
+                    None => entries.push(SharedNode::new_at(key, rest, value?)?),
+                }
+                Some(Shared::Block(Arc::new(entries)))
+            }
+            (Segment::Index(i), Shared::List(items)) if *i < items.len() => {
+                let mut items = (**items).clone();
+                if rest.is_empty() && value.is_none() {
+                    items.remove(*i);
+                } else {
+                    items[*i] = items[*i].with(rest, value)?;
+                }
+                Some(Shared::List(Arc::new(items)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build a fresh subtree for a path that does not exist yet
+    fn new_at(path: &[Segment<'_>], value: &Value) -> Option<Shared> {
+        match path.split_first() {
+            None => Some(Shared::from_value(value)),
+            Some((Segment::Key(key), rest)) => {
+                Some(Shared::Block(Arc::new(vec![SharedNode::new_at(
+                    key, rest, value,
+                )?])))
+            }
+            Some((Segment::Index(_), _)) => None,
+        }
+    }
+}
+
+impl PersistentDocument {
+    /// Create an empty document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert back into an ordinary, mutable document
+    pub fn to_document(&self) -> Document {
+        Document {
+            nodes: self.nodes.iter().map(SharedNode::to_node).collect(),
+        }
+    }
+
+    /// Read the value at a key path such as `server.tls.cert` or `servers[0]`
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let segments = keypath::parse(path)?;
+        let (key, rest) = match segments.split_first()? {
+            (Segment::Key(key), rest) => (*key, rest),
+            _ => return None,
+        };
+        self.node_position(key)
+            .and_then(|i| self.nodes[i].value.get(rest))
+            .map(Shared::to_value)
+    }
+
+    /// Return a new version with `value` stored at `path`
+    ///
+    /// Missing blocks along the path are created. Returns `None` if the path
+    /// is malformed or runs through a scalar or a missing list index.
+    pub fn set(&self, path: &str, value: Value) -> Option<Self> {
+        self.update(path, Some(&value))
+    }
+
+    /// Return a new version without the value at `path`
+    ///
+    /// Returns `None` if nothing exists at that path.
+    pub fn remove(&self, path: &str) -> Option<Self> {
+        self.update(path, None)
+    }
+
+    fn update(&self, path: &str, value: Option<&Value>) -> Option<Self> {
+        let segments = keypath::parse(path)?;
+        let (key, rest) = match segments.split_first()? {
+            (Segment::Key(key), rest) => (*key, rest),
+            _ => return None,
+        };
+
+        let mut nodes = (*self.nodes).clone();
+        match self.node_position(key) {
+            Some(i) if rest.is_empty() && value.is_none() => {
+                nodes.remove(i);
+            }
+            Some(i) => nodes[i].set(rest, value)?,
+            None => nodes.push(SharedNode::new_at(key, rest, value?)?),
+        }
+
+        Some(Self {
+            nodes: Arc::new(nodes),
+        })
+    }
+
+    /// Position of the effective (last) top-level node with `key`
+    fn node_position(&self, key: &str) -> Option<usize> {
+        self.nodes.iter().rposition(|node| &*node.key == key)
+    }
+}
+
+impl From<&Document> for PersistentDocument {
+    fn from(doc: &Document) -> Self {
+        Self {
+            nodes: Arc::new(doc.nodes.iter().map(SharedNode::from_node).collect()),
+        }
+    }
+}
+
+impl From<Document> for PersistentDocument {
+    fn from(doc: Document) -> Self {
+        Self::from(&doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn shared_at<'a>(doc: &'a PersistentDocument, path: &str) -> &'a Shared {
+        let segments = keypath::parse(path).unwrap();
+        let Segment::Key(key) = segments[0] else {
+            unreachable!()
+        };
+        let node = &doc.nodes[doc.node_position(key).unwrap()];
+        node.value.get(&segments[1..]).unwrap()
+    }
+
+    fn ptr_eq(a: &Shared, b: &Shared) -> bool {
+        match (a, b) {
+            (Shared::Block(a), Shared::Block(b)) => Arc::ptr_eq(a, b),
+            (Shared::List(a), Shared::List(b)) => Arc::ptr_eq(a, b),
+            (Shared::Leaf(a), Shared::Leaf(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_set_shares_untouched_subtrees() {
+        let input = "server {\nport 80\n}\ndatabase {\nhost db\n}\ntags [a, b]";
+        let v1 = PersistentDocument::from(parse(input).unwrap());
+        let v2 = v1.set("server.port", Value::String("8080".into())).unwrap();
+
+        assert_eq!(v1.get("server.port"), Some(Value::String("80".into())));
+        assert_eq!(v2.get("server.port"), Some(Value::String("8080".into())));
+        assert!(ptr_eq(
+            shared_at(&v1, "database"),
+            shared_at(&v2, "database")
+        ));
+        assert!(ptr_eq(shared_at(&v1, "tags"), shared_at(&v2, "tags")));
+        assert!(!ptr_eq(shared_at(&v1, "server"), shared_at(&v2, "server")));
+    }
+
+    #[test]
+    fn test_set_creates_and_remove_deletes() {
+        let v1 = PersistentDocument::new();
+        let v2 = v1.set("a.b.c", Value::String("x".into())).unwrap();
+        assert_eq!(v2.get("a.b.c"), Some(Value::String("x".into())));
+
+        let v3 = v2.remove("a.b").unwrap();
+        assert_eq!(v3.get("a.b"), None);
+        assert!(v3.get("a").is_some());
+        assert!(v3.remove("a.b").is_none());
+        assert!(v2.set("a.b.c[0]", Value::Null).is_none());
+
+        let doc = v2.to_document();
+        assert_eq!(doc.nodes.len(), 1);
+        assert_eq!(v1.to_document(), Document::new());
+    }
+}
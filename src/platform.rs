@@ -0,0 +1,490 @@
+//! Writing documents as platform configuration files: XML property lists
+//! for macOS and iOS, and registry files for Windows
+//!
+//! Both follow the data model of [`Document::to_json`]: blocks become
+//! dictionaries or registry keys, lists and tables become arrays, integers,
+//! floats, and booleans keep their type, and a repeated top-level key holds
+//! its last value. Binary data keeps its type too, as property list
+//! `<data>` and registry `hex:` values, and so do dates and timestamps in
+//! property lists, as `<date>`s. Every other scalar is written as the text
+//! it would have in UP. Neither format has a null, so a document holding
+//! one is an [`ErrorKind::InvalidValue`] error.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{types, Document, ErrorKind, ParseError, Value};
+
+const PLIST_HEADER: &str = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+";
+
+/// The registry hives a registry file can write keys under
+const HIVES: [&str; 5] = [
+    "HKEY_LOCAL_MACHINE",
+    "HKEY_CURRENT_USER",
+    "HKEY_CLASSES_ROOT",
+    "HKEY_USERS",
+    "HKEY_CURRENT_CONFIG",
+];
+
+impl Document {
+    /// Convert to an XML property list
+    ///
+    /// Blocks become `<dict>`s, lists and tables `<array>`s, integers
+    /// `<integer>`s, floats `<real>`s, booleans `<true/>` or `<false/>`,
+    /// binary data `<data>` in base64, dates and timestamps `<date>`s at
+    /// UTC in whole seconds, and every other scalar a `<string>`.
+    ///
+    /// ```
+    /// let doc = uplang::parse("name app\nwindow {\n  width!int 800\n  resizable!bool true\n}\n").unwrap();
+    /// let plist = doc.to_plist().unwrap();
+    /// assert!(plist.ends_with(
+    ///     "<dict>\n\t<key>name</key>\n\t<string>app</string>\n\t<key>window</key>\n\t<dict>\n\t\t<key>width</key>\n\t\t<integer>800</integer>\n\t\t<key>resizable</key>\n\t\t<true/>\n\t</dict>\n</dict>\n</plist>\n"
+    /// ));
+    /// ```
+    pub fn to_plist(&self) -> Result<String, ParseError> {
+        let mut out = PLIST_HEADER.to_string();
+        plist(&mut out, &Item::document(self), 0, "")?;
+        out.push_str("</plist>\n");
+        Ok(out)
+    }
+
+    /// Convert to a Windows registry file, for `regedit` to import under
+    /// `root`, such as `HKEY_CURRENT_USER\Software\App`
+    ///
+    /// Blocks become subkeys and scalars become values: booleans and
+    /// integers from 0 to 2³²−1 `dword`s, other integers `qword`s, binary
+    /// data `hex:` (`REG_BINARY`) values, and everything else strings. A
+    /// list of scalars becomes a multi-string value, and a list holding
+    /// blocks or lists a key with a subkey or value for each item, named by
+    /// its index. Strings that are not printable ASCII are written as hex
+    /// bytes, so the file stays ASCII and `regedit` reads them the same
+    /// whatever its code page. Lines end with `\r\n`.
+    ///
+    /// Fails with [`ErrorKind::InvalidValue`] if `root` is not under a
+    /// registry hive, such as `HKEY_LOCAL_MACHINE`, or a block's key holds
+    /// a `\`, which registry key names cannot.
+    ///
+    /// ```
+    /// let doc = uplang::parse("name app\nport!int 8080\nserver {\n  tls!bool true\n}\n").unwrap();
+    /// assert_eq!(
+    ///     doc.to_reg(r"HKEY_CURRENT_USER\Software\App").unwrap(),
+    ///     "Windows Registry Editor Version 5.00\r\n\
+    ///      \r\n\
+    ///      [HKEY_CURRENT_USER\\Software\\App]\r\n\
+    ///      \"name\"=\"app\"\r\n\
+    ///      \"port\"=dword:00001f90\r\n\
+    ///      \r\n\
+    ///      [HKEY_CURRENT_USER\\Software\\App\\server]\r\n\
+    ///      \"tls\"=dword:00000001\r\n"
+    /// );
+    /// ```
+    pub fn to_reg(&self, root: &str) -> Result<String, ParseError> {
+        let root = root.trim_end_matches('\\');
+        let hive = root.split('\\').next().unwrap_or_default();
+        if !HIVES.contains(&hive) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!(
+                    "`{}` is not under a registry hive such as HKEY_CURRENT_USER",
+                    root
+                ),
+            ));
+        }
+        let mut out = "Windows Registry Editor Version 5.00\r\n".to_string();
+        reg_key(&mut out, root, &Item::document(self))?;
+        Ok(out)
+    }
+}
+
+/// A value as both formats arrange it, with its scalars as they are so
+/// that each format can pick its own type for them
+enum Item<'a> {
+    Scalar(&'a Value),
+    Array(Vec<Item<'a>>),
+    Dict(Vec<(Cow<'a, str>, Item<'a>)>),
+}
+
+impl<'a> Item<'a> {
+    fn document(doc: &'a Document) -> Self {
+        let last: HashMap<&str, usize> = doc
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.key.as_str(), i))
+            .collect();
+        Item::Dict(
+            doc.nodes
+                .iter()
+                .enumerate()
+                .filter(|(i, node)| last[node.key.as_str()] == *i)
+                .map(|(_, node)| (Cow::Borrowed(node.key.as_str()), Item::of(&node.value)))
+                .collect(),
+        )
+    }
+
+    fn of(value: &'a Value) -> Self {
+        match value {
+            Value::Block(block) => Item::Dict(
+                block
+                    .iter()
+                    .map(|(key, value)| (Cow::Borrowed(key), Item::of(value)))
+                    .collect(),
+            ),
+            Value::List(items) => Item::Array(items.iter().map(Item::of).collect()),
+            Value::Table { columns, rows } => Item::Array(
+                rows.iter()
+                    .map(|row| {
+                        Item::Dict(
+                            columns
+                                .iter()
+                                .zip(row)
+                                .map(|(column, cell)| {
+                                    (column.scalar_text().unwrap_or_default(), Item::of(cell))
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            scalar => Item::Scalar(scalar),
+        }
+    }
+}
+
+fn null_error(format: &str, path: &str) -> ParseError {
+    ParseError::new(
+        ErrorKind::InvalidValue,
+        format!("{} have no null value, found at `{}`", format, path),
+    )
+}
+
+/// Write `item` at `depth` tabs; `path` locates it for errors
+fn plist(out: &mut String, item: &Item<'_>, depth: usize, path: &str) -> Result<(), ParseError> {
+    let indent = "\t".repeat(depth);
+    match item {
+        Item::Scalar(scalar) => plist_scalar(out, scalar, &indent, path)?,
+        Item::Array(items) if items.is_empty() => {
+            let _ = writeln!(out, "{}<array/>", indent);
+        }
+        Item::Array(items) => {
+            let _ = writeln!(out, "{}<array>", indent);
+            for (i, item) in items.iter().enumerate() {
+                plist(out, item, depth + 1, &format!("{}[{}]", path, i))?;
+            }
+            let _ = writeln!(out, "{}</array>", indent);
+        }
+        Item::Dict(entries) if entries.is_empty() => {
+            let _ = writeln!(out, "{}<dict/>", indent);
+        }
+        Item::Dict(entries) => {
+            let _ = writeln!(out, "{}<dict>", indent);
+            for (key, value) in entries {
+                let _ = writeln!(out, "{}\t<key>{}</key>", indent, xml_escape(key));
+                let path = match path {
+                    "" => key.to_string(),
+                    path => format!("{}.{}", path, key),
+                };
+                plist(out, value, depth + 1, &path)?;
+            }
+            let _ = writeln!(out, "{}</dict>", indent);
+        }
+    }
+    Ok(())
+}
+
+fn plist_scalar(
+    out: &mut String,
+    scalar: &Value,
+    indent: &str,
+    path: &str,
+) -> Result<(), ParseError> {
+    let (tag, text) = match scalar {
+        Value::Null => return Err(null_error("property lists", path)),
+        Value::Bool(b) => {
+            let _ = writeln!(out, "{}<{}/>", indent, b);
+            return Ok(());
+        }
+        Value::Int(n) => ("integer", n.to_string()),
+        Value::Float(x) if x.is_nan() => ("real", "nan".to_string()),
+        Value::Float(x) if x.is_infinite() && *x > 0.0 => ("real", "+infinity".to_string()),
+        Value::Float(x) if x.is_infinite() => ("real", "-infinity".to_string()),
+        Value::Float(x) => ("real", x.to_string()),
+        Value::Bytes(bytes) => ("data", types::encode_base64(bytes)),
+        Value::Date(date) => ("date", format!("{}T00:00:00Z", date)),
+        Value::Timestamp(ts) => ("date", ts.utc_string()),
+        other => {
+            let text = other.scalar_text().unwrap_or_default();
+            ("string", xml_escape(&text).into_owned())
+        }
+    };
+    let _ = writeln!(out, "{}<{}>{}</{}>", indent, tag, text, tag);
+    Ok(())
+}
+
+fn xml_escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+    )
+}
+
+/// Whether `item` is written as a registry key rather than a value
+fn is_key(item: &Item<'_>) -> bool {
+    match item {
+        Item::Dict(_) => true,
+        Item::Array(items) => items
+            .iter()
+            .any(|item| matches!(item, Item::Dict(_) | Item::Array(_))),
+        Item::Scalar(_) => false,
+    }
+}
+
+/// Write `item`, a block or list, as the key at `path`, followed by its
+/// subkeys
+fn reg_key(out: &mut String, path: &str, item: &Item<'_>) -> Result<(), ParseError> {
+    let children: Vec<(Cow<'_, str>, &Item<'_>)> = match item {
+        Item::Dict(entries) => entries
+            .iter()
+            .map(|(name, value)| (Cow::Borrowed(name.as_ref()), value))
+            .collect(),
+        Item::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (Cow::Owned(i.to_string()), item))
+            .collect(),
+        Item::Scalar(_) => unreachable!("only blocks and lists are keys"),
+    };
+    let _ = write!(out, "\r\n[{}]\r\n", path);
+    for (name, value) in children.iter().filter(|(_, value)| !is_key(value)) {
+        let _ = write!(out, "{}=", reg_string(name));
+        reg_value(out, value, path, name)?;
+        out.push_str("\r\n");
+    }
+    for (name, value) in children.iter().filter(|(_, value)| is_key(value)) {
+        if name.is_empty() || name.contains('\\') {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("`{}` in `{}` cannot be a registry key name", name, path),
+            ));
+        }
+        reg_key(out, &format!("{}\\{}", path, name), value)?;
+    }
+    Ok(())
+}
+
+/// Write the data of a registry value; `path` and `name` locate it for
+/// errors
+fn reg_value(out: &mut String, item: &Item<'_>, path: &str, name: &str) -> Result<(), ParseError> {
+    let text = |item: &Item<'_>| match item {
+        Item::Scalar(Value::Null) => {
+            Err(null_error("registry files", &format!("{}\\{}", path, name)))
+        }
+        Item::Scalar(scalar) => Ok(scalar.scalar_text().unwrap_or_default().into_owned()),
+        Item::Array(_) | Item::Dict(_) => unreachable!("lists of scalars only"),
+    };
+    match item {
+        Item::Scalar(Value::Bool(b)) => {
+            let _ = write!(out, "dword:{:08x}", u32::from(*b));
+        }
+        Item::Scalar(Value::Int(n)) => match u32::try_from(*n) {
+            Ok(n) => {
+                let _ = write!(out, "dword:{:08x}", n);
+            }
+            Err(_) => {
+                out.push_str("hex(b):");
+                hex_bytes(out, n.to_le_bytes());
+            }
+        },
+        Item::Scalar(Value::Bytes(bytes)) => {
+            out.push_str("hex:");
+            hex_bytes(out, bytes.iter().copied());
+        }
+        Item::Array(items) => {
+            let mut units = Vec::new();
+            for item in items {
+                units.extend(text(item)?.encode_utf16());
+                units.push(0);
+            }
+            units.push(0);
+            out.push_str("hex(7):");
+            hex_bytes(out, units.into_iter().flat_map(u16::to_le_bytes));
+        }
+        scalar => {
+            let text = text(scalar)?;
+            if text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+                out.push_str(&reg_string(&text));
+            } else {
+                out.push_str("hex(1):");
+                let units = text.encode_utf16().chain([0]);
+                hex_bytes(out, units.flat_map(u16::to_le_bytes));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `text` in double quotes, with `\` and `"` escaped
+fn reg_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Write `bytes` as comma-separated hex pairs
+fn hex_bytes(out: &mut String, bytes: impl IntoIterator<Item = u8>) {
+    for (i, byte) in bytes.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "{:02x}", byte);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, ErrorKind};
+
+    #[test]
+    fn test_to_plist() {
+        let doc = parse(
+            "\
+title Tom & Jerry <3
+ratio!float 1.5
+tags [a, b]
+empty {
+}
+users!table {
+  columns [id!int]
+  rows [
+    [7]
+  ]
+}
+",
+        )
+        .unwrap();
+        let plist = doc.to_plist().unwrap();
+        assert!(plist.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist"));
+        assert!(plist.ends_with(
+            "\
+<plist version=\"1.0\">
+<dict>
+\t<key>title</key>
+\t<string>Tom &amp; Jerry &lt;3</string>
+\t<key>ratio</key>
+\t<real>1.5</real>
+\t<key>tags</key>
+\t<array>
+\t\t<string>a</string>
+\t\t<string>b</string>
+\t</array>
+\t<key>empty</key>
+\t<dict/>
+\t<key>users</key>
+\t<array>
+\t\t<dict>
+\t\t\t<key>id</key>
+\t\t\t<integer>7</integer>
+\t\t</dict>
+\t</array>
+</dict>
+</plist>
+"
+        ));
+
+        // Binary data and dates have types of their own
+        let doc = parse("key!bytes AAEC/w==\nday!date 2024-03-15\nat!ts 2024-03-15T09:30:00.5+02:00\nopens!time 09:30\n").unwrap();
+        assert!(doc.to_plist().unwrap().ends_with(
+            "\
+<dict>
+\t<key>key</key>
+\t<data>AAEC/w==</data>
+\t<key>day</key>
+\t<date>2024-03-15T00:00:00Z</date>
+\t<key>at</key>
+\t<date>2024-03-15T07:30:00Z</date>
+\t<key>opens</key>
+\t<string>09:30:00</string>
+</dict>
+</plist>
+"
+        ));
+
+        let err = parse("a {\n  b!null\n}\n").unwrap().to_plist().unwrap_err();
+        assert_eq!(
+            err.message,
+            "property lists have no null value, found at `a.b`"
+        );
+    }
+
+    #[test]
+    fn test_to_reg() {
+        let doc = parse(
+            "\
+big!int 5000000000
+neg!int -1
+hosts [a, b]
+path C:\\Temp \"x\"
+city Zürich
+servers [
+  {
+    name a
+  }
+  solo
+]
+",
+        )
+        .unwrap();
+        let reg = doc.to_reg("HKEY_LOCAL_MACHINE\\Software\\App\\").unwrap();
+        assert_eq!(
+            reg,
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\Software\\App]\r\n\
+             \"big\"=hex(b):00,f2,05,2a,01,00,00,00\r\n\
+             \"neg\"=hex(b):ff,ff,ff,ff,ff,ff,ff,ff\r\n\
+             \"hosts\"=hex(7):61,00,00,00,62,00,00,00,00,00\r\n\
+             \"path\"=\"C:\\\\Temp \\\"x\\\"\"\r\n\
+             \"city\"=hex(1):5a,00,fc,00,72,00,69,00,63,00,68,00,00,00\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\Software\\App\\servers]\r\n\
+             \"1\"=\"solo\"\r\n\
+             \r\n\
+             [HKEY_LOCAL_MACHINE\\Software\\App\\servers\\0]\r\n\
+             \"name\"=\"a\"\r\n"
+        );
+
+        let doc = parse("key!bytes AAEC/w==\nempty!bytes\nday!date 2024-03-15\n").unwrap();
+        assert_eq!(
+            doc.to_reg("HKEY_CURRENT_USER").unwrap(),
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_CURRENT_USER]\r\n\
+             \"key\"=hex:00,01,02,ff\r\n\
+             \"empty\"=hex:\r\n\
+             \"day\"=\"2024-03-15\"\r\n"
+        );
+
+        let err = doc.to_reg("Software\\App").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        let err = parse("\"a\\\\b\" {\n  c 1\n}\n")
+            .unwrap()
+            .to_reg("HKEY_USERS")
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "`a\\b` in `HKEY_USERS` cannot be a registry key name"
+        );
+        let err = parse("a!null\n").unwrap().to_reg("HKEY_USERS").unwrap_err();
+        assert_eq!(
+            err.message,
+            "registry files have no null value, found at `HKEY_USERS\\a`"
+        );
+    }
+}
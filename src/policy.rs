@@ -0,0 +1,629 @@
+//! Rules about the values of a document, checked across many documents
+//!
+//! Where a [`Schema`](crate::schema::Schema) says which keys a document has
+//! and their types, a [`Policy`] says what their values must be, possibly
+//! depending on other values. A policy is written in UP, one block per
+//! rule, with the `#` comment above a rule as its description:
+//!
+//! ```text
+//! # Production traffic is encrypted
+//! tls-in-prod {
+//!   when env = prod
+//!   require [
+//!     tls.enabled = true
+//!     tls.cert exists
+//!   ]
+//! }
+//!
+//! # Secrets come from the environment, not from config files
+//! no-plaintext-secrets {
+//!   deny-annotation secret
+//! }
+//!
+//! no-debug {
+//!   severity warning
+//!   when env in prod staging
+//!   forbid log.level = debug
+//! }
+//! ```
+//!
+//! A rule applies to a document when all of its `when` conditions hold.
+//! Every `require` condition must then hold, no `forbid` condition may,
+//! and no value may have the annotation named by `deny-annotation`. Each of
+//! these takes one condition or a list of them.
+//!
+//! A condition is a key path followed by `exists`, `missing`, `in` and the
+//! values it may have separated by spaces, or a comparison: `=`, `!=`, `<`,
+//! `<=`, `>`, or `>=` and a value. Values are compared as they would be
+//! written in UP, and as numbers for `<` and the like; a comparison with a
+//! missing key does not hold, except for `!=`.
+
+use std::fmt;
+
+use crate::{Document, ErrorKind, Node, Origin, ParseError, Parser, Value};
+
+/// Rules that documents must follow, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+/// One named rule of a policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// Name of the rule, reported with its violations
+    pub name: String,
+    /// What the rule is for, from the comment above it
+    pub description: Option<String>,
+    /// How bad breaking the rule is
+    pub severity: Severity,
+    /// Conditions for the rule to apply; it always applies without any
+    pub when: Vec<Condition>,
+    /// Conditions that must hold
+    pub require: Vec<Condition>,
+    /// Conditions that must not hold
+    pub forbid: Vec<Condition>,
+    /// Annotations that no value may have, such as `secret`
+    pub deny_annotations: Vec<String>,
+}
+
+/// A test of the value at a key path, such as `tls.enabled = true`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    /// Key path of the value tested
+    pub path: String,
+    /// The test
+    pub test: Test,
+}
+
+/// What a [`Condition`] tests about a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Test {
+    /// The key is present
+    Exists,
+    /// The key is absent
+    Missing,
+    /// The value is written as this text
+    Equals(String),
+    /// The key is absent or its value is not written as this text
+    NotEquals(String),
+    /// The value is a number less than this one
+    Less(String),
+    /// The value is a number less than or equal to this one
+    LessOrEqual(String),
+    /// The value is a number greater than this one
+    Greater(String),
+    /// The value is a number greater than or equal to this one
+    GreaterOrEqual(String),
+    /// The value is written as one of these texts
+    In(Vec<String>),
+}
+
+/// How bad breaking a rule is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// The document must not be used as it is
+    #[default]
+    Error,
+    /// The document should be looked at
+    Warning,
+}
+
+impl Severity {
+    /// The severity's name, `error` or `warning`, as JSON reports write it
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One place where a document breaks a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Name of the rule broken
+    pub rule: String,
+    /// Severity of the rule
+    pub severity: Severity,
+    /// Key path of the offending value
+    pub path: String,
+    /// What is wrong, without the path
+    pub message: String,
+    /// Where the value was defined, or for a missing key the closest
+    /// enclosing block that was
+    pub origin: Option<Origin>,
+}
+
+impl Violation {
+    /// Whether the rule broken is an error rather than a warning
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Line number of the violation, if known
+    pub fn line(&self) -> Option<usize> {
+        self.origin.as_ref().map(|origin| origin.line)
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{}: ", origin)?;
+        }
+        if self.severity == Severity::Warning {
+            f.write_str("warning: ")?;
+        }
+        write!(f, "`{}` {} [{}]", self.path, self.message, self.rule)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.test {
+            Test::Exists => write!(f, "{} exists", self.path),
+            Test::Missing => write!(f, "{} missing", self.path),
+            Test::Equals(v) => write!(f, "{} = {}", self.path, v),
+            Test::NotEquals(v) => write!(f, "{} != {}", self.path, v),
+            Test::Less(v) => write!(f, "{} < {}", self.path, v),
+            Test::LessOrEqual(v) => write!(f, "{} <= {}", self.path, v),
+            Test::Greater(v) => write!(f, "{} > {}", self.path, v),
+            Test::GreaterOrEqual(v) => write!(f, "{} >= {}", self.path, v),
+            Test::In(values) => write!(f, "{} in {}", self.path, values.join(" ")),
+        }
+    }
+}
+
+impl Condition {
+    /// Read a condition such as `env = prod` or `tls.cert exists`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let error = || {
+            ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!(
+                    "`{}` is not a condition; expected a key path and `exists`, `missing`, `in`, or a comparison",
+                    text
+                ),
+            )
+        };
+        let (path, rest) = first_word(text);
+        let (op, value) = first_word(rest);
+        if path.is_empty() || op.is_empty() {
+            return Err(error());
+        }
+        let test = match (op, value) {
+            ("exists", "") => Test::Exists,
+            ("missing", "") => Test::Missing,
+            ("exists" | "missing", _) | (_, "") => return Err(error()),
+            ("=" | "==", v) => Test::Equals(v.to_string()),
+            ("!=", v) => Test::NotEquals(v.to_string()),
+            ("<", v) => Test::Less(v.to_string()),
+            ("<=", v) => Test::LessOrEqual(v.to_string()),
+            (">", v) => Test::Greater(v.to_string()),
+            (">=", v) => Test::GreaterOrEqual(v.to_string()),
+            ("in", v) => Test::In(v.split_whitespace().map(str::to_string).collect()),
+            _ => return Err(error()),
+        };
+        Ok(Self {
+            path: path.to_string(),
+            test,
+        })
+    }
+
+    /// Whether the condition holds in `doc`
+    pub fn holds(&self, doc: &Document) -> bool {
+        let value = doc.get(&self.path);
+        let text = value.and_then(Value::scalar_text);
+        let compare = |expected: &str, accept: fn(f64, f64) -> bool| match (
+            value.and_then(Value::as_float),
+            expected.parse::<f64>(),
+        ) {
+            (Some(actual), Ok(expected)) => accept(actual, expected),
+            _ => false,
+        };
+        match &self.test {
+            Test::Exists => value.is_some(),
+            Test::Missing => value.is_none(),
+            Test::Equals(expected) => text.as_deref() == Some(expected.as_str()),
+            Test::NotEquals(expected) => text.as_deref() != Some(expected.as_str()),
+            Test::Less(expected) => compare(expected, |a, b| a < b),
+            Test::LessOrEqual(expected) => compare(expected, |a, b| a <= b),
+            Test::Greater(expected) => compare(expected, |a, b| a > b),
+            Test::GreaterOrEqual(expected) => compare(expected, |a, b| a >= b),
+            Test::In(values) => text.is_some_and(|text| values.iter().any(|v| *v == text)),
+        }
+    }
+
+    /// What the value must be for the condition to hold, or with `negated`
+    /// not to
+    fn expectation(&self, negated: bool) -> String {
+        let (must, not) = if negated {
+            ("must not", "must")
+        } else {
+            ("must", "must not")
+        };
+        match &self.test {
+            Test::Exists if negated => "must not be set".to_string(),
+            Test::Exists => "is required".to_string(),
+            Test::Missing => format!("{} be set", not),
+            Test::Equals(v) => format!("{} be `{}`", must, v),
+            Test::NotEquals(v) => format!("{} be `{}`", not, v),
+            Test::Less(v) => format!("{} be less than {}", must, v),
+            Test::LessOrEqual(v) => format!("{} be at most {}", must, v),
+            Test::Greater(v) => format!("{} be greater than {}", must, v),
+            Test::GreaterOrEqual(v) => format!("{} be at least {}", must, v),
+            Test::In(values) => format!(
+                "{} be one of {}",
+                must,
+                values
+                    .iter()
+                    .map(|v| format!("`{}`", v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl Rule {
+    /// A rule that applies to every document and requires nothing yet
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            severity: Severity::Error,
+            when: Vec::new(),
+            require: Vec::new(),
+            forbid: Vec::new(),
+            deny_annotations: Vec::new(),
+        }
+    }
+
+    /// Apply the rule only to documents where `condition` holds
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.when.push(condition);
+        self
+    }
+
+    /// Require `condition` to hold
+    pub fn require(mut self, condition: Condition) -> Self {
+        self.require.push(condition);
+        self
+    }
+
+    /// Require `condition` not to hold
+    pub fn forbid(mut self, condition: Condition) -> Self {
+        self.forbid.push(condition);
+        self
+    }
+
+    /// Forbid values annotated `annotation`, such as `secret`
+    pub fn deny_annotation(mut self, annotation: impl Into<String>) -> Self {
+        self.deny_annotations.push(annotation.into());
+        self
+    }
+
+    /// Report violations with `severity` (default [`Severity::Error`])
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Check `doc` against this rule alone
+    pub fn check(&self, doc: &Document) -> Vec<Violation> {
+        let mut out = Vec::new();
+        if !self.when.iter().all(|condition| condition.holds(doc)) {
+            return out;
+        }
+        // Say why the rule applies, so the fix is clear
+        let context = match self.when.as_slice() {
+            [] => String::new(),
+            when => format!(
+                " when {}",
+                when.iter()
+                    .map(|condition| format!("`{}`", condition))
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+        };
+        let broken = self
+            .require
+            .iter()
+            .filter(|condition| !condition.holds(doc))
+            .map(|condition| (condition, false))
+            .chain(
+                self.forbid
+                    .iter()
+                    .filter(|condition| condition.holds(doc))
+                    .map(|condition| (condition, true)),
+            );
+        for (condition, negated) in broken {
+            out.push(self.violation(
+                &condition.path,
+                format!("{}{}", condition.expectation(negated), context),
+                origin_of(doc, &condition.path),
+            ));
+        }
+        if !self.deny_annotations.is_empty() {
+            for (path, node) in doc.walk() {
+                let denied = node
+                    .type_annotation
+                    .as_deref()
+                    .filter(|annotation| self.deny_annotations.iter().any(|a| a == annotation));
+                if let Some(annotation) = denied {
+                    out.push(self.violation(
+                        &path,
+                        format!("must not be `!{}`{}", annotation, context),
+                        node.origin.clone(),
+                    ));
+                }
+            }
+        }
+        out
+    }
+
+    fn violation(&self, path: &str, message: String, origin: Option<Origin>) -> Violation {
+        Violation {
+            rule: self.name.clone(),
+            severity: self.severity,
+            path: path.to_string(),
+            message,
+            origin,
+        }
+    }
+}
+
+impl Policy {
+    /// A policy without rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The rules, in the order they are checked
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Read a policy written in UP, see the [module docs](self)
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Self::from_document(&Parser::new().parse_document(input)?)
+    }
+
+    /// Read a policy from a document written in the policy syntax
+    pub fn from_document(doc: &Document) -> Result<Self, ParseError> {
+        let mut policy = Self::new();
+        for node in &doc.nodes {
+            let rule = parse_rule(node).map_err(|e| {
+                let at = node
+                    .origin
+                    .as_ref()
+                    .map_or(String::new(), |origin| format!("{}: ", origin));
+                ParseError::new(e.kind, format!("{}`{}`: {}", at, node.key, e.message))
+            })?;
+            policy = policy.rule(rule);
+        }
+        Ok(policy)
+    }
+
+    /// Check `doc` against every rule, returning the violations rule by
+    /// rule
+    pub fn check(&self, doc: &Document) -> Vec<Violation> {
+        self.rules.iter().flat_map(|rule| rule.check(doc)).collect()
+    }
+}
+
+fn parse_rule(node: &Node) -> Result<Rule, ParseError> {
+    let Value::Block(block) = &node.value else {
+        return Err(ParseError::new(
+            ErrorKind::InvalidSyntax,
+            "a rule must be a block",
+        ));
+    };
+    let mut rule = Rule::new(node.key.as_str());
+    rule.description = node.doc.clone();
+    for (key, value) in block {
+        let texts = texts(key, value)?;
+        match key {
+            "when" | "require" | "forbid" => {
+                let conditions = texts
+                    .iter()
+                    .map(|text| Condition::parse(text))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match key {
+                    "when" => rule.when.extend(conditions),
+                    "require" => rule.require.extend(conditions),
+                    _ => rule.forbid.extend(conditions),
+                }
+            }
+            "deny-annotation" => rule.deny_annotations.extend(texts),
+            "severity" => {
+                rule.severity = match texts.as_slice() {
+                    [level] if level == "error" => Severity::Error,
+                    [level] if level == "warning" => Severity::Warning,
+                    _ => {
+                        return Err(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            "`severity` must be `error` or `warning`",
+                        ))
+                    }
+                }
+            }
+            other => {
+                return Err(ParseError::new(
+                    ErrorKind::InvalidSyntax,
+                    format!(
+                        "unknown key `{}`; expected `when`, `require`, `forbid`, `deny-annotation`, or `severity`",
+                        other
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(rule)
+}
+
+/// The first word of `text`, and the rest of it trimmed
+fn first_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (&text[..end], text[end..].trim())
+}
+
+/// The text of a scalar, or of each item of a list of scalars
+fn texts(key: &str, value: &Value) -> Result<Vec<String>, ParseError> {
+    let items = match value {
+        Value::List(items) => items.as_slice(),
+        value => std::slice::from_ref(value),
+    };
+    items
+        .iter()
+        .map(|item| match item.scalar_text() {
+            Some(text) if !text.is_empty() => Ok(text.into_owned()),
+            _ => Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("`{}` must be a value or a list of values", key),
+            )),
+        })
+        .collect()
+}
+
+/// Where the value at `path` was defined, or the closest enclosing value
+/// that was
+fn origin_of(doc: &Document, path: &str) -> Option<Origin> {
+    let nodes: Vec<(String, &Node)> = doc.walk().collect();
+    let mut path = path;
+    loop {
+        let found = nodes
+            .iter()
+            .rev()
+            .find(|(p, _)| p == path)
+            .and_then(|(_, node)| node.origin.clone());
+        if found.is_some() {
+            return found;
+        }
+        path = &path[..path.rfind(['.', '['])?];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = "\
+# Production traffic is encrypted
+tls-in-prod {
+  when env = prod
+  require [
+    tls.enabled = true
+    tls.cert exists
+  ]
+}
+no-plaintext-secrets {
+  deny-annotation secret
+}
+no-debug {
+  severity warning
+  when env in prod staging
+  forbid log.level = debug
+}
+limits {
+  require [
+    workers >= 1
+    workers <= 64
+  ]
+}
+";
+
+    #[test]
+    fn test_check_policy() {
+        let policy = Policy::parse(POLICY).unwrap();
+        assert_eq!(policy.rules().len(), 4);
+        assert_eq!(
+            policy.rules()[0].description.as_deref(),
+            Some("Production traffic is encrypted")
+        );
+
+        let doc = crate::parse("env prod\ntls {\n  enabled false\n}\ndb {\n  password!secret hunter2\n}\nlog {\n  level debug\n}\nworkers!int 128\n").unwrap();
+        let violations = policy.check(&doc);
+        let found: Vec<(&str, &str, &str)> = violations
+            .iter()
+            .map(|v| (v.rule.as_str(), v.path.as_str(), v.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "tls-in-prod",
+                    "tls.enabled",
+                    "must be `true` when `env = prod`"
+                ),
+                ("tls-in-prod", "tls.cert", "is required when `env = prod`"),
+                (
+                    "no-plaintext-secrets",
+                    "db.password",
+                    "must not be `!secret`"
+                ),
+                (
+                    "no-debug",
+                    "log.level",
+                    "must not be `debug` when `env in prod staging`"
+                ),
+                ("limits", "workers", "must be at most 64"),
+            ]
+        );
+        // A missing key is reported where its block is
+        assert_eq!(violations[1].origin.as_ref().map(|o| o.line), Some(2));
+        assert_eq!(violations[2].line(), Some(6));
+        assert!(!violations[3].is_error());
+        assert_eq!(
+            violations[0].to_string(),
+            "line 3: `tls.enabled` must be `true` when `env = prod` [tls-in-prod]"
+        );
+        assert!(violations[3].to_string().contains(": warning: `log.level`"));
+
+        // Rules that do not apply find nothing
+        let doc = crate::parse("env dev\nlog {\n  level debug\n}\nworkers 4\n").unwrap();
+        assert!(policy.check(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_conditions() {
+        let doc = crate::parse("a 1\nb!float 2.5\nc x\nd!bool true\n").unwrap();
+        let holds = |text: &str| Condition::parse(text).unwrap().holds(&doc);
+        assert!(holds("a = 1"));
+        assert!(holds("b > 2"));
+        assert!(holds("d = true"));
+        assert!(holds("c in x y"));
+        assert!(holds("e != 1"));
+        assert!(holds("e missing"));
+        assert!(!holds("e = 1"));
+        assert!(!holds("c < 3"));
+        assert!(!holds("e exists"));
+
+        assert!(Condition::parse("a").is_err());
+        assert!(Condition::parse("a ~ 1").is_err());
+        assert!(Condition::parse("a exists now").is_err());
+        assert!(Condition::parse("a =").is_err());
+        assert_eq!(
+            Condition::parse("env  =  prod west").unwrap().test,
+            Test::Equals("prod west".to_string())
+        );
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        let err = Policy::parse("r 1\n").unwrap_err();
+        assert_eq!(err.message, "line 1: `r`: a rule must be a block");
+        let err = Policy::parse("r {\n  requires a exists\n}\n").unwrap_err();
+        assert!(err.message.contains("unknown key `requires`"));
+        assert!(Policy::parse("r {\n  severity fatal\n}\n").is_err());
+        assert!(Policy::parse("r {\n  require a\n}\n").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Finding nodes by their place in the source, for editor hover and
+//! go-to-definition
+
+use crate::{Document, Node, Span};
+
+/// A node found by its position in the source, see
+/// [`Document::node_at_offset`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeAt<'a> {
+    /// Key path of the node, e.g. `servers[2].host`
+    pub path: String,
+    /// The node itself
+    pub node: &'a Node,
+    /// Type annotation of the node, if it has one
+    pub annotation: Option<&'a str>,
+    /// Where the node is in the source
+    pub span: &'a Span,
+}
+
+impl Document {
+    /// The innermost node whose source text holds byte `offset`, for
+    /// showing its path and type in an editor hover
+    ///
+    /// Only nodes with a [`Span`] are found, so the document must be parsed
+    /// with [`Parser::spans`](crate::Parser::spans). Values copied from an
+    /// anchor by `!ref` have no text of their own, and are found at the
+    /// anchor instead.
+    ///
+    /// ```
+    /// let input = "server {\n  port!int 8080\n}\n";
+    /// let doc = uplang::Parser::new().spans(true).parse_document(input).unwrap();
+    /// let at = doc.node_at_offset(input.find("8080").unwrap()).unwrap();
+    /// assert_eq!(at.path, "server.port");
+    /// assert_eq!(at.annotation, Some("int"));
+    /// assert_eq!(at.span.start_line, 2);
+    /// ```
+    pub fn node_at_offset(&self, offset: usize) -> Option<NodeAt<'_>> {
+        let mut nodes = located(self);
+        let i = innermost(&nodes, offset)?;
+        Some(node_at(nodes.swap_remove(i)))
+    }
+
+    /// The `!anchor` node copied by the `key!ref name` node at byte
+    /// `offset`, for going to the definition of a reference
+    ///
+    /// The anchor is the one named by the reference in the nearest block
+    /// holding it. Like [`node_at_offset`](Self::node_at_offset), this
+    /// needs the document to be parsed with
+    /// [`Parser::spans`](crate::Parser::spans).
+    ///
+    /// ```
+    /// let input = "base!anchor {\n  port!int 80\n}\nweb!ref base\n";
+    /// let doc = uplang::Parser::new().spans(true).parse_document(input).unwrap();
+    /// let anchor = doc.definition_at(input.find("web").unwrap()).unwrap();
+    /// assert_eq!((anchor.path.as_str(), anchor.span.start_line), ("base", 1));
+    /// ```
+    pub fn definition_at(&self, offset: usize) -> Option<NodeAt<'_>> {
+        let mut nodes = located(self);
+        let i = innermost(&nodes, offset)?;
+        let (path, node) = &nodes[i];
+        let name = node.anchor.as_deref()?;
+        let scope = parent(path);
+        let anchor = nodes[..i]
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, node))| {
+                node.key == name
+                    && node.type_annotation.as_deref() == Some("anchor")
+                    && encloses(parent(path), scope)
+            })
+            .max_by_key(|(_, (path, _))| parent(path).len())
+            .map(|(i, _)| i)?;
+        Some(node_at(nodes.swap_remove(anchor)))
+    }
+}
+
+/// Nodes with spans and their key paths, parents before children
+fn located(doc: &Document) -> Vec<(String, &Node)> {
+    doc.walk().filter(|(_, node)| node.span.is_some()).collect()
+}
+
+/// Index of the innermost node whose text holds byte `offset`
+fn innermost(nodes: &[(String, &Node)], offset: usize) -> Option<usize> {
+    nodes.iter().rposition(|(_, node)| {
+        node.span
+            .as_ref()
+            .is_some_and(|span| span.bytes.start <= offset && offset <= span.bytes.end)
+    })
+}
+
+fn node_at((path, node): (String, &Node)) -> NodeAt<'_> {
+    NodeAt {
+        path,
+        node,
+        annotation: node.type_annotation.as_deref(),
+        span: node.span.as_ref().expect("located nodes have spans"),
+    }
+}
+
+/// Path of the block holding the node at `path`, such as `servers[0]`
+fn parent(path: &str) -> &str {
+    path.rsplit_once('.').map_or("", |(parent, _)| parent)
+}
+
+/// Whether the block at `outer` is, or holds, the block at `inner`
+fn encloses(outer: &str, inner: &str) -> bool {
+    outer.is_empty()
+        || inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    const INPUT: &str = "name app\nbase!anchor {\n  port!int 80\n}\nservers [\n  {\n    host a\n    web!ref base\n  }\n]\nzone {\n  base!anchor {\n    port!int 81\n  }\n  inner {\n    web!ref base {\n      tls!bool true\n    }\n  }\n}\n";
+
+    #[test]
+    fn test_node_at_offset() {
+        let doc = Parser::new().spans(true).parse_document(INPUT).unwrap();
+        let at = |text: &str| {
+            let at = doc.node_at_offset(INPUT.find(text).unwrap())?;
+            Some((at.path, at.annotation.map(str::to_string)))
+        };
+        assert_eq!(at("name"), Some(("name".into(), None)));
+        assert_eq!(at("app"), Some(("name".into(), None)));
+        assert_eq!(at("  {\n    host"), Some(("servers".into(), None)));
+        assert_eq!(at("host a"), Some(("servers[0].host".into(), None)));
+        assert_eq!(
+            at("tls"),
+            Some(("zone.inner.web.tls".into(), Some("bool".into())))
+        );
+        // Copies made by `!ref` are found at their anchor
+        assert_eq!(
+            at("port!int 81"),
+            Some(("zone.base.port".into(), Some("int".into())))
+        );
+        assert!(doc.node_at_offset(INPUT.len() + 10).is_none());
+
+        // Without spans nothing is found
+        let doc = Parser::new().parse_document(INPUT).unwrap();
+        assert!(doc.node_at_offset(0).is_none());
+    }
+
+    #[test]
+    fn test_definition_at() {
+        let doc = Parser::new().spans(true).parse_document(INPUT).unwrap();
+        let definition = |text: &str| {
+            let at = doc.definition_at(INPUT.find(text).unwrap())?;
+            Some((at.path, at.span.start_line))
+        };
+        assert_eq!(definition("web!ref base\n"), Some(("base".into(), 2)));
+        assert_eq!(definition("web!ref base {"), Some(("zone.base".into(), 12)));
+        assert_eq!(definition("host a"), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Key path lookups and typed accessors
+
+use std::ops::Index;
+
+use crate::keypath::{self, Segment};
+use crate::{Block, Document, Node, Value};
+
+impl Document {
+    /// Look up the value at a key path such as `server.tls.cert` or `servers[2].host`
+    ///
+    /// A top-level key that matches `path` exactly is preferred, so keys
+    /// that contain `.` keep working. When a key is repeated the last
+    /// definition wins. `None` means nothing exists at that path, as opposed
+    /// to present with an empty value ([`Value::is_empty`]) or explicitly
+    /// null ([`Value::is_null`]).
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        if let Some(value) = self.top_level(path) {
+            return Some(value);
+        }
+        let segments = keypath::parse(path)?;
+        let (Segment::Key(key), rest) = segments.split_first()? else {
+            return None;
+        };
+        keypath::lookup(self.top_level(key)?, rest)
+    }
+
+    /// Look up the node at a key path that ends in a key, with its
+    /// annotation and origin
+    ///
+    /// `None` for paths ending in a list index, whose items are values
+    /// rather than nodes.
+    ///
+    /// ```
+    /// let doc = uplang::parse("server {\n  timeout!dur 30s\n}\n").unwrap();
+    /// let node = doc.get_node("server.timeout").unwrap();
+    /// assert_eq!(node.type_annotation.as_deref(), Some("dur"));
+    /// ```
+    pub fn get_node(&self, path: &str) -> Option<&Node> {
+        let segments = keypath::parse(path)?;
+        let (Segment::Key(key), parent) = segments.split_last()? else {
+            return None;
+        };
+        let (first, rest) = match parent.split_first() {
+            None => return self.nodes.iter().rev().find(|node| node.key == *key),
+            Some((Segment::Key(first), rest)) => (first, rest),
+            Some(_) => return None,
+        };
+        match keypath::lookup(self.top_level(first)?, rest)? {
+            Value::Block(block) => block.get_node(key),
+            _ => None,
+        }
+    }
+
+    /// Look up a string at a key path, see [`get`](Self::get)
+    pub fn get_str(&self, path: &str) -> Option<&str> {
+        self.get(path)?.as_str()
+    }
+
+    /// Look up an integer at a key path, see [`Value::as_int`]
+    pub fn get_int(&self, path: &str) -> Option<i64> {
+        self.get(path)?.as_int()
+    }
+
+    /// Look up a boolean at a key path, see [`Value::as_bool`]
+    pub fn get_bool(&self, path: &str) -> Option<bool> {
+        self.get(path)?.as_bool()
+    }
+
+    fn top_level(&self, key: &str) -> Option<&Value> {
+        self.nodes
+            .iter()
+            .rev()
+            .find(|node| node.key == key)
+            .map(|node| &node.value)
+    }
+}
+
+impl Value {
+    /// Look up a nested value by key path, relative to this one
+    ///
+    /// Paths are written as for [`Document::get`]; one that starts with an
+    /// index (`[0].host`) steps into a list. An empty path returns `self`.
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        keypath::lookup(self, &keypath::parse_relative(path)?)
+    }
+
+    /// The text of a string value, `""` for a key without a value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            Value::Empty => Some(""),
+            _ => None,
+        }
+    }
+
+    /// An integer value, or a string that reads as one
+    ///
+    /// Strings are accepted so that unannotated values such as `port 8080`,
+    /// or documents parsed without type coercion, can be read as numbers.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// A boolean value, or the string `true` or `false`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// A floating-point value, an integer, or a string that reads as a
+    /// number
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(x) => Some(*x),
+            Value::Int(n) => Some(*n as f64),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The entries of a block
+    pub fn as_block(&self) -> Option<&Block> {
+        match self {
+            Value::Block(block) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// The items of a list
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Iterate over the values of a block or the items of a list
+    ///
+    /// Other values have nothing to iterate over.
+    pub fn iter(&self) -> impl Iterator<Item = &Value> {
+        let items = self.as_list().unwrap_or_default();
+        let block = self.as_block().into_iter().flat_map(Block::values);
+        items.iter().chain(block)
+    }
+
+    /// The column names and rows of a table
+    pub fn as_table(&self) -> Option<(&[Value], &[Vec<Value>])> {
+        match self {
+            Value::Table { columns, rows } => Some((columns, rows)),
+            _ => None,
+        }
+    }
+}
+
+impl Index<&str> for Document {
+    type Output = Value;
+
+    /// The value at a key path, see [`Document::get`]
+    ///
+    /// # Panics
+    ///
+    /// If nothing exists at `path`.
+    fn index(&self, path: &str) -> &Value {
+        match self.get(path) {
+            Some(value) => value,
+            None => panic!("no value at `{}`", path),
+        }
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    /// The value of `key` in a block
+    ///
+    /// # Panics
+    ///
+    /// If this is not a block, or it has no such key.
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Block(block) => &block[key],
+            other => panic!("cannot look up `{}` in a {}", key, other.type_name()),
+        }
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    /// Item `i` of a list
+    ///
+    /// # Panics
+    ///
+    /// If this is not a list, or `i` is out of bounds.
+    fn index(&self, i: usize) -> &Value {
+        match self {
+            Value::List(items) => &items[i],
+            other => panic!("cannot index into a {}", other.type_name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    #[test]
+    fn test_get_paths() {
+        let doc = parse(
+            "server {\ntls {\ncert /etc/cert.pem\n}\nport!int 8443\n}\nservers [\n{\nhost a\n}\n{\nhost b\ndebug true\n}\n]\na.b dotted",
+        )
+        .unwrap();
+
+        assert_eq!(doc.get_str("server.tls.cert"), Some("/etc/cert.pem"));
+        assert_eq!(doc.get_int("server.port"), Some(8443));
+        assert_eq!(doc.get_str("servers[1].host"), Some("b"));
+        assert_eq!(doc.get_bool("servers[1].debug"), Some(true));
+        assert_eq!(doc.get_str("a.b"), Some("dotted"));
+
+        assert_eq!(doc.get("servers[2].host"), None);
+        assert_eq!(doc.get("server.tls.cert.extra"), None);
+        assert_eq!(doc.get("server..port"), None);
+        assert_eq!(doc.get_int("server.tls.cert"), None);
+    }
+
+    #[test]
+    fn test_value_pointer() {
+        let doc = parse("servers [\n{\nhost a\nport 80\n}\n]").unwrap();
+        let servers = doc.get("servers").unwrap();
+        assert_eq!(
+            servers.pointer("[0].host"),
+            Some(&Value::String("a".into()))
+        );
+        assert_eq!(
+            servers.pointer("[0].port").and_then(Value::as_int),
+            Some(80)
+        );
+        assert_eq!(servers.pointer(""), Some(servers));
+        assert_eq!(servers.pointer("host"), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let doc = parse("servers [\n{\nhost a\n}\n]\nname app\nname again").unwrap();
+        assert_eq!(doc["servers"][0]["host"], Value::String("a".into()));
+        assert_eq!(doc["servers[0].host"], doc["servers"][0]["host"]);
+        assert_eq!(doc["name"].as_str(), Some("again"));
+
+        let keys: Vec<&str> = doc.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["servers", "name", "name"]);
+        assert_eq!((&doc).into_iter().len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "no value at `port`")]
+    fn test_index_missing() {
+        let doc = parse("name app").unwrap();
+        let _ = &doc["port"];
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        let doc = parse(
+            "ratio!float 0.5
+n!int 2
+server {
+host a
+}
+tags [a, b]
+t!table {
+columns [x]
+rows [
+[1]
+]
+}",
+        )
+        .unwrap();
+        assert_eq!(doc.get("ratio").and_then(Value::as_float), Some(0.5));
+        assert_eq!(doc.get("n").and_then(Value::as_float), Some(2.0));
+        let server = doc.get("server").and_then(Value::as_block).unwrap();
+        assert_eq!(server.get("host").and_then(Value::as_str), Some("a"));
+        assert_eq!(
+            doc.get("tags").and_then(Value::as_list).map(<[_]>::len),
+            Some(2)
+        );
+        let (columns, rows) = doc.get("t").and_then(Value::as_table).unwrap();
+        assert_eq!((columns.len(), rows.len()), (1, 1));
+
+        let items: Vec<&Value> = doc["tags"].iter().collect();
+        assert_eq!(
+            items,
+            [&Value::String("a".into()), &Value::String("b".into())]
+        );
+        assert_eq!(doc["server"].iter().count(), 1);
+        assert_eq!(doc["n"].iter().count(), 0);
+
+        assert_eq!(doc.get("tags").and_then(Value::as_block), None);
+        assert_eq!(doc.get("server").and_then(Value::as_list), None);
+        assert_eq!(doc.get("server").and_then(Value::as_table), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Quoted keys and values with escape sequences
+//!
+//! A key or a whole scalar value may be written in double quotes, so it
+//! can hold whitespace, `!`, or characters that would otherwise start a
+//! comment, directive, block, or list. Inside the quotes `\n`, `\t`, `\r`,
+//! `\"`, `\\`, and `\u{...}` are escapes; any other backslash is an error.
+
+use std::borrow::Cow;
+
+use crate::{keyed_item, ErrorKind, ParseError};
+
+/// Length in bytes of the quoted string at the start of `text`, quotes
+/// included; `None` if `text` does not start with one that is closed
+pub(crate) fn quoted_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('"')?;
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some(i + 2),
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The inside of `text` if all of it is one quoted string
+pub(crate) fn whole(text: &str) -> Option<&str> {
+    (quoted_len(text) == Some(text.len())).then(|| &text[1..text.len() - 1])
+}
+
+/// Replace the escape sequences in the inside of a quoted string
+pub(crate) fn unescape(inner: &str) -> Result<Cow<'_, str>, ParseError> {
+    if !inner.contains('\\') {
+        return Ok(Cow::Borrowed(inner));
+    }
+    let invalid = |escape: &str| {
+        ParseError::new(
+            ErrorKind::InvalidSyntax,
+            format!("invalid escape `{}`", escape),
+        )
+    };
+    let mut text = String::with_capacity(inner.len());
+    let mut rest = inner;
+    while let Some(i) = rest.find('\\') {
+        text.push_str(&rest[..i]);
+        let escape = &rest[i..];
+        let mut chars = escape[1..].chars();
+        let (c, len) = match chars.next() {
+            Some('n') => ('\n', 2),
+            Some('t') => ('\t', 2),
+            Some('r') => ('\r', 2),
+            Some('"') => ('"', 2),
+            Some('\\') => ('\\', 2),
+            Some('u') => {
+                let end = escape.find('}').unwrap_or(escape.len());
+                let code = escape[..end]
+                    .strip_prefix("\\u{")
+                    .filter(|hex| (1..=6).contains(&hex.len()) && end < escape.len())
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| invalid(&escape[..escape.len().min(end + 1)]))?;
+                let c = char::from_u32(code).ok_or_else(|| {
+                    ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        format!("`{}` is not a Unicode character", &escape[..=end]),
+                    )
+                })?;
+                (c, end + 1)
+            }
+            Some(c) => return Err(invalid(&escape[..1 + c.len_utf8()])),
+            None => return Err(invalid("\\")),
+        };
+        text.push(c);
+        rest = &escape[len..];
+    }
+    text.push_str(rest);
+    Ok(Cow::Owned(text))
+}
+
+/// Split the key part of a line into the key and its annotation
+///
+/// A quoted key is unescaped and may be followed by `!annotation`; an
+/// unquoted key ends at its first `!`.
+pub(crate) fn split_key(key_part: &str) -> Result<(Cow<'_, str>, Option<&str>), ParseError> {
+    if !key_part.starts_with('"') {
+        return Ok(match key_part.split_once('!') {
+            Some((key, annotation)) => (Cow::Borrowed(key), Some(annotation)),
+            None => (Cow::Borrowed(key_part), None),
+        });
+    }
+    let len = quoted_len(key_part)
+        .ok_or_else(|| ParseError::new(ErrorKind::InvalidSyntax, "unterminated quoted key"))?;
+    let key = unescape(&key_part[1..len - 1])?;
+    match &key_part[len..] {
+        "" => Ok((key, None)),
+        rest => match rest.strip_prefix('!') {
+            Some(annotation) => Ok((key, Some(annotation))),
+            None => Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("unexpected `{}` after quoted key", rest),
+            )),
+        },
+    }
+}
+
+/// Write `text` in double quotes, escaping what [`unescape`] reads back
+pub(crate) fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Check whether a key must be quoted to read back unchanged
+pub(crate) fn key_needs_quotes(key: &str) -> bool {
+    key.is_empty()
+        || key.starts_with(['"', '#', '@', '{', '}', '[', ']'])
+        || key.starts_with("```")
+        || key.contains(|c: char| c.is_whitespace() || c.is_control() || c == '!')
+}
+
+/// Check whether a single-line scalar must be quoted to read back unchanged
+pub(crate) fn value_needs_quotes(text: &str) -> bool {
+    let trailing_backslashes = text.bytes().rev().take_while(|&b| b == b'\\').count();
+    text.trim() != text
+        || text == "{"
+        || text == "["
+        || text.starts_with(['"', '`'])
+        || (text.starts_with('[') && text.ends_with(']'))
+        || (text.starts_with('{') && text.ends_with('}'))
+        || trailing_backslashes % 2 == 1
+        || text.contains(|c: char| c.is_control() && c != '\t')
+}
+
+/// Check whether an item of a multiline list must be quoted to read back
+/// unchanged, rather than as a comment, a nested value, or a node
+pub(crate) fn item_needs_quotes(text: &str) -> bool {
+    value_needs_quotes(text)
+        || text.is_empty()
+        || text.starts_with(['#', '{', '[', ']', '}'])
+        || keyed_item(text).is_some()
+}
+
+/// Check whether text can be written as a fenced multiline value and read
+/// back unchanged
+pub(crate) fn fits_multiline(text: &str) -> bool {
+    !text.split('\n').any(|line| line.trim() == "```")
+        && !text.contains(|c: char| c.is_control() && c != '\n' && c != '\t')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("plain").unwrap(), "plain");
+        assert_eq!(
+            unescape(r#"a\nb\t\"c\" \\ \u{e9}\u{1F600}"#).unwrap(),
+            "a\nb\t\"c\" \\ é😀"
+        );
+        assert_eq!(unescape(r"\q").unwrap_err().message, r"invalid escape `\q`");
+        assert_eq!(
+            unescape(r"\u{zz}").unwrap_err().message,
+            r"invalid escape `\u{zz}`"
+        );
+        assert_eq!(
+            unescape(r"\u{41").unwrap_err().message,
+            r"invalid escape `\u{41`"
+        );
+        assert_eq!(
+            unescape(r"\u{d800}").unwrap_err().message,
+            r"`\u{d800}` is not a Unicode character"
+        );
+        assert!(unescape("x\\").is_err());
+    }
+
+    #[test]
+    fn test_quote_round_trip() {
+        for text in ["", "  padded  ", "say \"hi\"", "C:\\dir\\", "a\nb\r\u{7}"] {
+            let quoted = quote(text);
+            assert_eq!(quoted_len(&quoted), Some(quoted.len()));
+            assert_eq!(unescape(whole(&quoted).unwrap()).unwrap(), text);
+        }
+        assert_eq!(quoted_len(r#""a\"b" rest"#), Some(6));
+        assert_eq!(quoted_len(r#""open"#), None);
+        assert_eq!(whole(r#""a" "b""#), None);
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("port!int").unwrap(), ("port".into(), Some("int")));
+        assert_eq!(
+            split_key(r#""my key!"!int"#).unwrap(),
+            ("my key!".into(), Some("int"))
+        );
+        assert_eq!(split_key(r#""a\tb""#).unwrap(), ("a\tb".into(), None));
+        assert_eq!(
+            split_key(r#""a"b"#).unwrap_err().message,
+            "unexpected `b` after quoted key"
+        );
+        assert_eq!(
+            split_key(r#""a b"#).unwrap_err().message,
+            "unterminated quoted key"
+        );
+    }
+}
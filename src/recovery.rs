@@ -0,0 +1,324 @@
+//! Parsing past errors, see [`Parser::parse_with_recovery`]
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+use crate::comment::Comments;
+use crate::descent::Reader;
+use crate::lines::Cursor;
+use crate::policy::Severity;
+use crate::stream::Boundary;
+use crate::{Block, Document, ParseError, Parser, Value};
+
+/// Where [`Parser::parse_with_recovery`] collects the problems it skips
+pub(crate) type Diagnostics = Arc<Mutex<Vec<Diagnostic>>>;
+
+/// A problem found by [`Parser::parse_with_recovery`], and the lines left
+/// out of the document because of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// What went wrong, and where
+    pub error: ParseError,
+    /// 1-based lines that were skipped, first to last; `None` when the
+    /// problem concerns the whole document
+    pub skipped: Option<RangeInclusive<usize>>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+/// A document parsed with [`Parser::parse_output`], and the problems
+/// skipped on the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOutput {
+    /// The document, without the nodes that failed to parse
+    pub document: Document,
+    /// The problems found, in line order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseOutput {
+    /// Whether the input parsed without problems
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// The output as JSON, for tools that are not written in Rust
+    ///
+    /// The shape is stable: an object with `ok`, `document` (the
+    /// document's own JSON, see [`Document::to_json`]), and `diagnostics`,
+    /// a list of objects with
+    ///
+    /// - `code`: the [`ErrorKind::code`](crate::ErrorKind::code) of the
+    ///   problem, such as `type-mismatch`
+    /// - `severity`: always `error`, as every parse problem is one
+    /// - `message`: what is wrong, without the location
+    /// - `span`: `file` (or `null`), 1-based `line` and `column`, and the
+    ///   byte offsets `start` and `end` of the offending text, or `null`
+    ///   when the problem has no location
+    /// - `skipped`: the 1-based `first` and `last` lines left out of the
+    ///   document, or `null` when the whole document was
+    ///
+    /// ```
+    /// let output = uplang::Parser::new().parse_output("name app\nport!int eighty\n");
+    /// assert_eq!(
+    ///     output.to_json(),
+    ///     concat!(
+    ///         r#"{"ok":false,"document":{"name":"app"},"diagnostics":[{"code":"type-mismatch","#,
+    ///         r#""severity":"error","message":"expected int, found `eighty`","#,
+    ///         r#""span":{"file":null,"line":2,"column":10,"start":18,"end":24},"#,
+    ///         r#""skipped":{"first":2,"last":2}}]}"#
+    ///     )
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let number = |n: usize| Value::Int(n as i64);
+        let diagnostics = self.diagnostics.iter().map(|diagnostic| {
+            let error = &diagnostic.error;
+            let span = error.location.as_ref().map_or(Value::Null, |location| {
+                let file = location
+                    .file
+                    .as_ref()
+                    .map_or(Value::Null, |file| Value::from(file.display().to_string()));
+                let span: Block = [
+                    ("file", file),
+                    ("line", number(location.line)),
+                    ("column", number(location.column)),
+                    ("start", number(location.span.start)),
+                    ("end", number(location.span.end)),
+                ]
+                .into_iter()
+                .collect();
+                Value::Block(span)
+            });
+            let skipped = diagnostic.skipped.as_ref().map_or(Value::Null, |lines| {
+                let skipped: Block = [
+                    ("first", number(*lines.start())),
+                    ("last", number(*lines.end())),
+                ]
+                .into_iter()
+                .collect();
+                Value::Block(skipped)
+            });
+            let block: Block = [
+                ("code", Value::from(error.kind.code())),
+                ("severity", Value::from(Severity::Error.as_str())),
+                ("message", Value::from(error.message.as_str())),
+                ("span", span),
+                ("skipped", skipped),
+            ]
+            .into_iter()
+            .collect();
+            Value::Block(block)
+        });
+        let report = Document::builder()
+            .value("ok", self.is_ok())
+            .value("document", "")
+            .list("diagnostics", diagnostics)
+            .build();
+        // The document is spliced in as its own JSON, keeping its types
+        report.to_json().replacen(
+            "\"document\":\"\"",
+            &format!("\"document\":{}", self.document.to_json()),
+            1,
+        )
+    }
+}
+
+impl Parser {
+    /// Parse a document, skipping the nodes that fail instead of stopping
+    /// at the first error
+    ///
+    /// A node that does not parse is left out of the document and reported
+    /// as a [`Diagnostic`], and parsing carries on after its last line; an
+    /// error inside a block only drops the node holding it, not the whole
+    /// block. The result is the same as
+    /// [`parse_document`](Self::parse_document) when there are no errors.
+    /// Problems with the document as a whole, such as going past
+    /// [`Limits::max_size`](crate::Limits::max_size) or an undefined
+    /// reference with [`with_interpolation`](Self::with_interpolation),
+    /// leave the document empty.
+    ///
+    /// ```
+    /// let input = "name app\nport!int eighty\nserver {\n  host!bool maybe\n  tls!bool true\n}\n";
+    /// let (doc, diagnostics) = uplang::Parser::new().parse_with_recovery(input);
+    /// assert_eq!(uplang::to_string(&doc), "name app\nserver {\n  tls!bool true\n}\n");
+    /// let lines: Vec<_> = diagnostics.iter().map(|d| d.error.line()).collect();
+    /// assert_eq!(lines, [Some(2), Some(4)]);
+    /// ```
+    pub fn parse_with_recovery(&self, input: &str) -> (Document, Vec<Diagnostic>) {
+        let diagnostics = Diagnostics::default();
+        let parser = Parser {
+            recovery: Some(Arc::clone(&diagnostics)),
+            ..self.clone()
+        };
+        let result = parser.parse_document(input);
+        let mut diagnostics = diagnostics
+            .lock()
+            .map(|mut found| std::mem::take(&mut *found))
+            .unwrap_or_default();
+        match result {
+            Ok(document) => (document, diagnostics),
+            Err(error) => {
+                diagnostics.push(Diagnostic {
+                    error,
+                    skipped: None,
+                });
+                (Document::new(), diagnostics)
+            }
+        }
+    }
+
+    /// [`parse_with_recovery`](Self::parse_with_recovery) as a
+    /// [`ParseOutput`], which can be written as JSON
+    pub fn parse_output(&self, input: &str) -> ParseOutput {
+        let (document, diagnostics) = self.parse_with_recovery(input);
+        ParseOutput {
+            document,
+            diagnostics,
+        }
+    }
+}
+
+impl Reader<'_, '_> {
+    /// Handle `error` in the node starting at `line`, line `start` of the
+    /// input
+    ///
+    /// Without recovery the error is returned. With it, the error is
+    /// recorded and the lexer is moved past the node's last line.
+    pub(crate) fn recover(
+        &mut self,
+        line: &str,
+        start: usize,
+        error: ParseError,
+    ) -> Result<(), ParseError> {
+        let Some(diagnostics) = &self.parser.recovery else {
+            return Err(error);
+        };
+        let lines = Cursor::at(self.input, line, start);
+        let end = node_end(lines, start, &self.parser.comments);
+        self.lines.skip_through(end);
+        if let Ok(mut diagnostics) = diagnostics.lock() {
+            diagnostics.push(Diagnostic {
+                error,
+                skipped: Some(start + 1..=end + 1),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Index of the last line of the node starting at line `start`, the
+/// next line of `lines`
+///
+/// Only the lines of the node are read, so recovering from many errors
+/// does not read the input again from the start for each one.
+fn node_end(lines: Cursor<'_>, start: usize, comments: &Comments) -> usize {
+    let mut boundary = Boundary::new(comments);
+    let mut end = start;
+    for (line_num, line) in lines {
+        end = line_num;
+        if boundary.line(line.trim()) {
+            break;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string, ErrorKind, Limits};
+
+    #[test]
+    fn test_recovery_skips_bad_nodes() {
+        let input = "a 1\nb!int x\nlist [\n  {\n    c!int y\n    d 2\n  }\n]\n@let\n@include other.up\nblock {\n  e {\n    f!bool z\n  }\n  g 3\n}\nh!float w\nlast 4\n";
+        let (doc, diagnostics) = Parser::new().parse_with_recovery(input);
+        assert_eq!(
+            to_string(&doc),
+            "a 1\nlist [\n  {\n    d 2\n  }\n]\nblock {\n  e {\n  }\n  g 3\n}\nlast 4\n"
+        );
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.error.kind, d.error.line(), d.skipped.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ErrorKind::TypeMismatch, Some(2), 2..=2),
+                (ErrorKind::TypeMismatch, Some(5), 5..=5),
+                (ErrorKind::InvalidSyntax, Some(9), 9..=9),
+                (ErrorKind::InvalidValue, Some(10), 10..=10),
+                (ErrorKind::TypeMismatch, Some(13), 13..=13),
+                (ErrorKind::TypeMismatch, Some(17), 17..=17),
+            ]
+        );
+        assert!(diagnostics[0].to_string().starts_with("Type mismatch"));
+
+        // Without errors it is parse_document
+        let input = "a 1\nb {\n  c [x]\n}\n";
+        let (doc, diagnostics) = Parser::new().parse_with_recovery(input);
+        assert_eq!(doc, parse(input).unwrap());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_recovery_skips_whole_nodes() {
+        // A node that fails as a whole is skipped to its last line
+        let parser = Parser::new().limits(Limits::new().max_depth(1));
+        let input = "a {\n  b {\n    c 1\n  }\n  d 2\n}\ne 3\n";
+        let (doc, diagnostics) = parser.parse_with_recovery(input);
+        assert_eq!(to_string(&doc), "a {\n  d 2\n}\ne 3\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error.kind, ErrorKind::LimitExceeded);
+        assert_eq!(diagnostics[0].skipped, Some(2..=4));
+
+        let (doc, diagnostics) = Parser::new()
+            .strict(true)
+            .parse_with_recovery("a 1\nb {\n  c 2\n");
+        assert_eq!(to_string(&doc), "a 1\n");
+        assert_eq!(diagnostics[0].error.kind, ErrorKind::UnclosedDelimiter);
+        assert_eq!(diagnostics[0].skipped, Some(2..=3));
+
+        // Errors in embedded documents point into the enclosing input
+        let (doc, diagnostics) =
+            Parser::new().parse_with_recovery("x!up ```\n  y!int no\n```\nz 1\n");
+        assert_eq!(to_string(&doc), "z 1\n");
+        assert_eq!(diagnostics[0].error.line(), Some(2));
+        assert_eq!(diagnostics[0].skipped, Some(1..=3));
+
+        // Whole-document problems leave nothing
+        let parser = Parser::new().limits(Limits::new().max_size(2));
+        let (doc, diagnostics) = parser.parse_with_recovery("a 1\n");
+        assert!(doc.is_empty());
+        assert_eq!(diagnostics[0].error.kind, ErrorKind::LimitExceeded);
+        assert_eq!(diagnostics[0].skipped, None);
+    }
+
+    #[test]
+    fn test_parse_output_json() {
+        let output = Parser::new().parse_output("a 1\nb!bool maybe\n");
+        assert!(!output.is_ok());
+        let json = output.to_json();
+        assert!(json.starts_with("{\"ok\":false,\"document\":{\"a\":\"1\"},"));
+        assert!(json.contains("{\"code\":\"type-mismatch\",\"severity\":\"error\","));
+        assert!(json.contains("\"skipped\":{\"first\":2,\"last\":2}"));
+
+        // Whole-document problems have no span or skipped lines
+        let parser = Parser::new().limits(Limits::new().max_size(2));
+        let json = parser.parse_output("a 1\n").to_json();
+        assert!(json.contains("\"code\":\"limit-exceeded\""));
+        assert!(json.ends_with("\"span\":null,\"skipped\":null}]}"));
+
+        let output = Parser::new().parse_output("a 1\n");
+        assert!(output.is_ok());
+        assert_eq!(
+            output.to_json(),
+            "{\"ok\":true,\"document\":{\"a\":\"1\"},\"diagnostics\":[]}"
+        );
+    }
+}
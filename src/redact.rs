@@ -0,0 +1,96 @@
+//! Logging documents without leaking secrets
+
+use std::fmt;
+
+use crate::{Document, Node, Value, Writer};
+
+/// Text shown in place of a secret value
+pub(crate) const MASK: &str = "***";
+
+/// Display adapter that masks `!secret` values
+///
+/// Both `{}` (UP text) and `{:?}` (the document structure) print `***` for
+/// every node annotated `!secret`, including nested ones and their overlay
+/// history, so a whole config can be logged safely:
+///
+/// ```
+/// use uplang::{parse, Redacted};
+///
+/// let doc = parse("user admin\npassword!secret hunter2").unwrap();
+/// assert_eq!(
+///     Redacted(&doc).to_string(),
+///     "user admin\npassword!secret ***\n"
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct Redacted<'a>(pub &'a Document);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Writer::new().redact_secrets(true).to_string(self.0))
+    }
+}
+
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let masked = Document {
+            nodes: self.0.nodes.iter().map(mask_node).collect(),
+        };
+        f.debug_tuple("Redacted").field(&masked).finish()
+    }
+}
+
+/// Check whether a node's value must not be shown
+pub(crate) fn is_secret(node: &Node) -> bool {
+    node.type_annotation.as_deref() == Some("secret")
+}
+
+fn mask_node(node: &Node) -> Node {
+    let mut node = node.clone();
+    // The source text would show the value
+    node.trivia = None;
+    if is_secret(&node) {
+        node.value = Value::String(MASK.to_string());
+        for step in &mut node.provenance {
+            step.value = Value::String(MASK.to_string());
+        }
+    } else {
+        mask_value(&mut node.value);
+        for step in &mut node.provenance {
+            mask_value(&mut step.value);
+        }
+    }
+    node
+}
+
+fn mask_value(value: &mut Value) {
+    match value {
+        Value::Block(block) => {
+            for node in block.nodes_mut() {
+                *node = mask_node(node);
+            }
+        }
+        Value::List(items) => items.iter_mut().for_each(mask_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_nested_secrets_are_masked() {
+        let mut doc = parse("db {\nhost db1\ntoken!secret abc123\n}").unwrap();
+        doc.overlay_tracked(parse("db {\ntoken!secret def456\n}").unwrap());
+
+        let shown = Redacted(&doc).to_string();
+        assert_eq!(shown, "db {\n  host db1\n  token!secret ***\n}\n");
+
+        let debug = format!("{:?}", Redacted(&doc));
+        assert!(debug.starts_with("Redacted(Document {"));
+        assert!(debug.contains("db1"));
+        assert!(!debug.contains("abc123") && !debug.contains("def456"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Configuration reference tables for documentation
+
+use std::collections::HashMap;
+
+use crate::{Document, Node, Value};
+
+/// One documented key in a [`Reference`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceEntry {
+    /// Key path, e.g. `server.port` or `servers[0].host`
+    pub path: String,
+    /// Type annotation, or the value's type name when there is none
+    pub type_name: String,
+    /// Default value as UP text; `None` for blocks and undocumented defaults
+    pub default: Option<String>,
+    /// Doc comment written above the key
+    pub description: Option<String>,
+}
+
+/// Documentation of every key in a configuration, see [`Document::reference`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reference {
+    /// Documented keys in schema order
+    pub entries: Vec<ReferenceEntry>,
+}
+
+impl Document {
+    /// Describe every key in this document, filling in details from `schema`
+    ///
+    /// A schema is an ordinary UP document listing the known keys with their
+    /// type annotations, default values, and `#` doc comments. Keys come in
+    /// schema order, followed by keys that only this document uses. Without
+    /// a schema the document describes itself, so its values become the
+    /// defaults. Blocks are listed only when they carry a doc comment.
+    pub fn reference(&self, schema: Option<&Document>) -> Reference {
+        let own = KeyList::of(self);
+        let Some(schema) = schema else {
+            return Reference {
+                entries: own
+                    .nodes
+                    .iter()
+                    .map(|(path, node)| entry(path, node))
+                    .collect(),
+            };
+        };
+
+        let known = KeyList::of(schema);
+        let mut entries: Vec<ReferenceEntry> = known
+            .nodes
+            .iter()
+            .map(|(path, node)| {
+                let mut entry = entry(path, node);
+                if entry.description.is_none() {
+                    entry.description = own.get(path).and_then(|n| n.doc.clone());
+                }
+                entry
+            })
+            .collect();
+        for (path, node) in &own.nodes {
+            if known.get(path).is_none() {
+                entries.push(ReferenceEntry {
+                    default: None,
+                    ..entry(path, node)
+                });
+            }
+        }
+        Reference { entries }
+    }
+}
+
+impl Reference {
+    /// Render as a Markdown table with key, type, default, and description
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from(
+            "| Key | Type | Default | Description |\n|-----|------|---------|-------------|\n",
+        );
+        for entry in &self.entries {
+            let default = entry.default.as_deref().map_or_else(String::new, code);
+            let description = entry.description.as_deref().unwrap_or_default();
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                code(&entry.path),
+                code(&entry.type_name),
+                default,
+                cell(description)
+            ));
+        }
+        out
+    }
+}
+
+/// Documented nodes by path, in first-seen order; later definitions win
+#[derive(Default)]
+struct KeyList<'a> {
+    nodes: Vec<(String, &'a Node)>,
+    positions: HashMap<String, usize>,
+}
+
+impl<'a> KeyList<'a> {
+    fn of(doc: &'a Document) -> Self {
+        let mut list = Self::default();
+        for node in &doc.nodes {
+            list.add(node.key.clone(), node);
+        }
+        list
+    }
+
+    fn add(&mut self, path: String, node: &'a Node) {
+        if let Value::Block(block) = &node.value {
+            if node.doc.is_some() {
+                self.insert(path.clone(), node);
+            }
+            for child in block.nodes() {
+                self.add(format!("{}.{}", path, child.key), child);
+            }
+        } else {
+            self.insert(path, node);
+        }
+    }
+
+    fn insert(&mut self, path: String, node: &'a Node) {
+        match self.positions.get(&path) {
+            Some(&i) => self.nodes[i].1 = node,
+            None => {
+                self.positions.insert(path.clone(), self.nodes.len());
+                self.nodes.push((path, node));
+            }
+        }
+    }
+
+    fn get(&self, path: &str) -> Option<&'a Node> {
+        self.positions.get(path).map(|&i| self.nodes[i].1)
+    }
+}
+
+fn entry(path: &str, node: &Node) -> ReferenceEntry {
+    ReferenceEntry {
+        path: path.to_string(),
+        type_name: node
+            .type_annotation
+            .clone()
+            .unwrap_or_else(|| node.value.type_name().to_string()),
+        default: default_text(&node.value),
+        description: node.doc.clone(),
+    }
+}
+
+fn default_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Block(_) | Value::Table { .. } => None,
+        Value::Null => Some("null".to_string()),
+        Value::List(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| default_text(item).unwrap_or_else(|| "…".to_string()))
+                .collect();
+            Some(format!("[{}]", items.join(", ")))
+        }
+        scalar => {
+            let text = scalar.scalar_text().unwrap_or_default();
+            match text.split_once('\n') {
+                Some((first, _)) => Some(format!("{} …", first)),
+                None if text.is_empty() => None,
+                None => Some(text.into_owned()),
+            }
+        }
+    }
+}
+
+/// Escape text for a table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Format text as inline code in a table cell
+fn code(text: &str) -> String {
+    if text.contains('`') {
+        cell(text)
+    } else {
+        format!("`{}`", cell(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    #[test]
+    fn test_reference_from_schema() {
+        let schema = parse(
+            "# Network settings\nserver {\n# Listen port\nport!int 8080\nhost 0.0.0.0\n}\ntags [a, b]",
+        )
+        .unwrap();
+        let config =
+            parse("server {\n# Public host | name\nhost example.com\n}\nextra!bool true").unwrap();
+
+        let reference = config.reference(Some(&schema));
+        let paths: Vec<&str> = reference.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["server", "server.port", "server.host", "tags", "extra"]
+        );
+
+        let port = &reference.entries[1];
+        assert_eq!(port.type_name, "int");
+        assert_eq!(port.default.as_deref(), Some("8080"));
+        assert_eq!(port.description.as_deref(), Some("Listen port"));
+        assert_eq!(reference.entries[4].default, None);
+
+        let markdown = reference.to_markdown();
+        assert!(markdown.starts_with("| Key | Type | Default | Description |\n"));
+        assert!(markdown.contains("| `server.port` | `int` | `8080` | Listen port |\n"));
+        assert!(
+            markdown.contains("| `server.host` | `string` | `0.0.0.0` | Public host \\| name |\n")
+        );
+        assert!(markdown.contains("| `tags` | `list` | `[a, b]` |  |\n"));
+    }
+
+    #[test]
+    fn test_reference_without_schema() {
+        let doc = parse("# Service name\nname app\nname app-2\nserver {\nport 80\n}").unwrap();
+        let reference = doc.reference(None);
+        assert_eq!(reference.entries.len(), 2);
+        assert_eq!(reference.entries[0].default.as_deref(), Some("app-2"));
+        assert_eq!(reference.entries[0].description, None);
+        assert_eq!(reference.entries[1].path, "server.port");
+    }
+}
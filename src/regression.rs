@@ -0,0 +1,201 @@
+//! Checking the parser against a corpus of inputs, such as the one
+//! collected by fuzzing, see [`run_corpus`]
+//!
+//! Applications that embed this crate can keep a directory of inputs that
+//! once caused trouble, including those found by the fuzz targets in the
+//! repository's `fuzz/` directory, and pin the parser's behavior on them in
+//! their own tests:
+//!
+//! ```no_run
+//! #[test]
+//! fn corpus() {
+//!     uplang::regression::run_corpus("tests/corpus");
+//! }
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use crate::{to_string, Parser};
+
+/// A corpus input that failed a check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The input file, or the directory that could not be read
+    pub file: PathBuf,
+    /// What went wrong
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.message)
+    }
+}
+
+/// Check every file in `dir` and its subdirectories, panicking with a list
+/// of the failures if there are any
+///
+/// Returns the number of files checked. See [`check_input`] for what is
+/// checked; files whose name starts with `.` are skipped.
+pub fn run_corpus(dir: impl AsRef<Path>) -> usize {
+    match check_corpus(dir) {
+        Ok(checked) => checked,
+        Err(failures) => {
+            let list: Vec<String> = failures.iter().map(Failure::to_string).collect();
+            panic!(
+                "{} corpus input(s) failed:\n{}",
+                failures.len(),
+                list.join("\n")
+            );
+        }
+    }
+}
+
+/// Check every file in `dir` and its subdirectories, in path order,
+/// returning the number of files checked or every failure
+pub fn check_corpus(dir: impl AsRef<Path>) -> Result<usize, Vec<Failure>> {
+    let mut files = Vec::new();
+    let mut failures = Vec::new();
+    collect_files(dir.as_ref(), &mut files, &mut failures);
+    files.sort();
+    for file in &files {
+        let result = fs::read(file)
+            .map_err(|e| e.to_string())
+            .and_then(|input| check_input(&input));
+        if let Err(message) = result {
+            failures.push(Failure {
+                file: file.clone(),
+                message,
+            });
+        }
+    }
+    match failures.is_empty() {
+        true => Ok(files.len()),
+        false => Err(failures),
+    }
+}
+
+/// Check one input: every way of parsing it returns rather than panicking,
+/// and any document it parses to is stable when written and read back
+///
+/// The input is parsed as bytes, and when it is UTF-8 as a document with
+/// default, strict, and span-and-trivia-keeping parsers, with recovery,
+/// borrowed, from a reader, and by [`format`](crate::format). Each
+/// document parsed is then written with [`to_string`], and that text must
+/// parse. Unless the document kept its trivia, and so is written as it was
+/// read, the text it parses to must also write the same text again.
+///
+/// ```
+/// use uplang::regression::check_input;
+///
+/// assert_eq!(check_input(b"name app\nport!int 80\n"), Ok(()));
+/// assert_eq!(check_input(b"server {\n  port!int eighty\n"), Ok(()));
+/// ```
+pub fn check_input(input: &[u8]) -> Result<(), String> {
+    panic::catch_unwind(AssertUnwindSafe(|| check(input))).unwrap_or_else(|payload| {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => payload
+                .downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_default(),
+        };
+        Err(format!("panicked: {}", message))
+    })
+}
+
+fn check(input: &[u8]) -> Result<(), String> {
+    let _ = crate::parse_bytes(input);
+    let Ok(text) = std::str::from_utf8(input) else {
+        return Ok(());
+    };
+    let parsers = [
+        Parser::new(),
+        Parser::new().strict(true),
+        Parser::new().preserve_trivia(true).spans(true),
+    ];
+    for parser in &parsers {
+        let _ = parser.parse_with_recovery(text);
+        let _ = parser.parse_borrowed(text);
+        let _ = parser.parse_reader(text.as_bytes());
+        if let Ok(doc) = parser.parse_document(text) {
+            let written = to_string(&doc);
+            let back = Parser::new()
+                .parse_document(&written)
+                .map_err(|e| format!("written text does not parse: {}\n{}", e, written))?;
+            let again = to_string(&back);
+            if doc.nodes.iter().all(|node| node.trivia.is_none()) && again != written {
+                return Err(format!(
+                    "written text reads back differently:\n{}\nthen:\n{}",
+                    written, again
+                ));
+            }
+        }
+    }
+    let _ = crate::format(text);
+    Ok(())
+}
+
+/// Add the files under `dir` to `files`, and the directories that cannot
+/// be read to `failures`
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>, failures: &mut Vec<Failure>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            failures.push(Failure {
+                file: dir.to_path_buf(),
+                message: e.to_string(),
+            });
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, files, failures);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_check_corpus() {
+        let dir = env::temp_dir().join(format!("uplang-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("parse")).unwrap();
+        let inputs: [(&str, &[u8]); 6] = [
+            ("a", b"name app\nport!int 80\n"),
+            ("parse/b", b"server {\n  hosts [\n    a\n    b\n  ]\n"),
+            (
+                "parse/c",
+                b"}\n]\n```\n@let\n${\nx!int 9999999999999999999\n",
+            ),
+            ("parse/d", b"\xff\xfe\x00"),
+            ("parse/e", b"a { b { c {"),
+            (".hidden", b"not checked"),
+        ];
+        for (name, input) in inputs {
+            fs::write(dir.join(name), input).unwrap();
+        }
+        let result = check_corpus(&dir);
+        assert_eq!(result, Ok(5));
+        assert_eq!(run_corpus(&dir), 5);
+
+        let missing = dir.join("missing");
+        let failures = check_corpus(&missing).unwrap_err();
+        assert_eq!(failures[0].file, missing);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
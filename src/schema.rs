@@ -0,0 +1,827 @@
+//! Declaring the expected keys and types of a document, and checking
+//! documents against the declaration
+//!
+//! A [`Schema`] can be built in code or written in UP itself. In UP, each
+//! key of the schema declares a key of the document, with its type as the
+//! annotation:
+//!
+//! ```text
+//! # Service name
+//! name!string
+//! port?!int 8080
+//! server!block {
+//!   host!string
+//!   tls?!bool
+//! }
+//! tags?!list string
+//! backends!list {
+//!   url!string
+//! }
+//! ```
+//!
+//! Keys are required unless they end in `?` or have a default value. The
+//! value of a scalar key is its default; a `!block` holds the schema of the
+//! block; a `!list` holds the type of its items, or a block with the
+//! schema of each item. Keys without an annotation accept any value, and
+//! `#` comments become field descriptions. A comment line `@deprecated`
+//! marks a key as deprecated, optionally followed by the key path that
+//! replaces it:
+//!
+//! ```text
+//! # Address to listen on
+//! # @deprecated server.host
+//! listen?!string
+//! ```
+//!
+//! For editors, [`Schema::complete`] suggests the keys and values that fit
+//! at a cursor, and [`Schema::field_at`] finds the declaration of a key to
+//! show its documentation.
+//!
+//! [`to_json_schema`] publishes a schema as JSON Schema for other tools,
+//! and [`Schema::from_example`] infers one from an annotated document.
+
+mod complete;
+mod json_schema;
+
+pub use complete::{Completion, CompletionKind};
+pub use json_schema::to_json_schema;
+
+use std::fmt;
+
+use crate::{suggest, types};
+use crate::{Block, Document, ErrorKind, Node, Origin, ParseError, Parser, Value};
+
+/// The expected shape of a document or block
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    fields: Vec<(String, Field)>,
+    allow_unknown: bool,
+}
+
+/// One declared key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// Expected type of the value
+    pub ty: Type,
+    /// Whether the key must be present
+    pub required: bool,
+    /// Value used when the key is missing
+    pub default: Option<Value>,
+    /// Human-readable description
+    pub description: Option<String>,
+    /// Whether the key should no longer be used
+    pub deprecated: bool,
+    /// Key path to use instead of a deprecated key
+    pub replaced_by: Option<String>,
+}
+
+/// The type of a declared value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// Any value
+    Any,
+    /// A value of the type with this annotation name, e.g. `int`, `money`,
+    /// or `block`
+    ///
+    /// Unannotated scalars match if they can be read as the type, so
+    /// `port 8080` is an `int`. Annotations this crate has no type for,
+    /// such as `url`, accept any scalar.
+    Named(String),
+    /// A block with the given keys
+    Block(Schema),
+    /// A list whose items all have the given type
+    List(Box<Type>),
+}
+
+/// How a document breaks its schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    /// A required key is missing
+    Missing,
+    /// A key is not declared in the schema
+    Unknown,
+    /// A value has the wrong type
+    WrongType,
+    /// A deprecated key is used; a warning rather than an error
+    Deprecated,
+}
+
+/// One place where a document breaks its schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Key path of the offending value, e.g. `server.port` or
+    /// `backends[1].url`
+    pub path: String,
+    /// What is wrong
+    pub kind: ViolationKind,
+    /// Details, without the path
+    pub message: String,
+    /// Where the value, or for a missing key its enclosing block, was
+    /// defined
+    pub origin: Option<Origin>,
+    /// For a deprecated key, the key path to use instead; for an unknown
+    /// key, the known key path it most likely misspells
+    pub replacement: Option<String>,
+}
+
+impl Violation {
+    /// Whether the document is invalid, as opposed to using a deprecated key
+    pub fn is_error(&self) -> bool {
+        self.kind != ViolationKind::Deprecated
+    }
+
+    /// Line number of the violation, if known
+    pub fn line(&self) -> Option<usize> {
+        self.origin.as_ref().map(|origin| origin.line)
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{}: ", origin)?;
+        }
+        write!(f, "`{}` {}", self.path, self.message)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Any => f.write_str("any"),
+            Type::Named(name) => f.write_str(name),
+            Type::Block(_) => f.write_str("block"),
+            Type::List(item) => write!(f, "list of {}", item),
+        }
+    }
+}
+
+impl Field {
+    /// Declare a required key of type `ty`
+    pub fn new(ty: Type) -> Self {
+        Self {
+            ty,
+            required: true,
+            default: None,
+            description: None,
+            deprecated: false,
+            replaced_by: None,
+        }
+    }
+
+    /// Make the key optional
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    /// Give the key a default value, which makes it optional
+    pub fn default_value(mut self, value: Value) -> Self {
+        self.default = Some(value);
+        self.optional()
+    }
+
+    /// Describe the key
+    pub fn description(mut self, text: impl Into<String>) -> Self {
+        self.description = Some(text.into());
+        self
+    }
+
+    /// Mark the key as deprecated, which makes it optional
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self.optional()
+    }
+
+    /// Mark the key as deprecated in favor of the key at `path`
+    pub fn replaced_by(mut self, path: impl Into<String>) -> Self {
+        self.replaced_by = Some(path.into());
+        self.deprecated()
+    }
+}
+
+impl Field {
+    /// The key's type and whether it is required, e.g. `int, optional`
+    fn summary(&self) -> String {
+        let required = if self.required {
+            "required"
+        } else {
+            "optional"
+        };
+        format!("{}, {}", self.ty, required)
+    }
+
+    fn deprecation(&self, path: &str, origin: Option<&Origin>) -> Violation {
+        let message = match &self.replaced_by {
+            Some(replacement) => format!("is deprecated, use `{}` instead", replacement),
+            None => "is deprecated".to_string(),
+        };
+        Violation {
+            path: path.to_string(),
+            kind: ViolationKind::Deprecated,
+            message,
+            origin: origin.cloned(),
+            replacement: self.replaced_by.clone(),
+        }
+    }
+}
+
+impl Type {
+    /// A value with the given annotation name
+    pub fn named(name: impl Into<String>) -> Self {
+        Type::Named(name.into())
+    }
+
+    /// A list whose items have type `item`
+    pub fn list_of(item: Type) -> Self {
+        Type::List(Box::new(item))
+    }
+}
+
+impl Schema {
+    /// Create a schema with no keys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a key
+    pub fn field(mut self, key: impl Into<String>, field: Field) -> Self {
+        let key = key.into();
+        match self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = field,
+            None => self.fields.push((key, field)),
+        }
+        self
+    }
+
+    /// Accept keys the schema does not declare (default `false`)
+    ///
+    /// Applies to nested block schemas too.
+    pub fn allow_unknown(mut self, allow: bool) -> Self {
+        self.allow_unknown = allow;
+        self
+    }
+
+    /// Look up a declared key
+    pub fn get(&self, key: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, field)| field)
+    }
+
+    /// Iterate over the declared keys in order
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Field)> {
+        self.fields.iter().map(|(key, field)| (key.as_str(), field))
+    }
+
+    /// Read a schema written in UP, see the [module docs](self)
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        // Defaults are typed below, so `port!int` without one is not an error
+        let doc = Parser::new().coerce_types(false).parse_document(input)?;
+        Self::from_document(&doc)
+    }
+
+    /// Read a schema from a document written in the schema syntax
+    pub fn from_document(doc: &Document) -> Result<Self, ParseError> {
+        from_nodes(doc.nodes.iter())
+    }
+
+    /// A document with every key that has a value to start from, for
+    /// scaffolding a new configuration
+    ///
+    /// Keys with a default get it, blocks get their own keys, and required
+    /// keys without a default get an empty value to fill in: `""`, `0`,
+    /// `false`, or `[]` where the type has one, an empty string otherwise.
+    /// Optional keys without a default are left out. Descriptions become
+    /// doc comments.
+    pub fn default_document(&self) -> Document {
+        Document {
+            nodes: self.default_nodes(false).collect(),
+        }
+    }
+
+    /// A commented document with every key, for a new user to edit
+    ///
+    /// Like [`default_document`](Self::default_document), but optional keys
+    /// without a default get an empty value too, and each key's doc comment
+    /// gives its type and whether it is required after its description:
+    ///
+    /// ```
+    /// use uplang::schema::Schema;
+    ///
+    /// let schema = Schema::parse("# Listen port\nport?!int 8080\ndebug?!bool").unwrap();
+    /// assert_eq!(
+    ///     uplang::to_string(&schema.starter_document()),
+    ///     "# Listen port\n# int, optional\nport!int 8080\n# bool, optional\ndebug!bool false\n",
+    /// );
+    /// ```
+    pub fn starter_document(&self) -> Document {
+        Document {
+            nodes: self.default_nodes(true).collect(),
+        }
+    }
+
+    /// Nodes for the keys with a value to start from, or for every key with
+    /// a comment on its type if `starter`
+    fn default_nodes(&self, starter: bool) -> impl Iterator<Item = Node> + '_ {
+        self.fields.iter().filter_map(move |(key, field)| {
+            if field.deprecated {
+                return None;
+            }
+            let (annotation, value) = match (&field.ty, &field.default) {
+                (Type::Named(name), Some(value)) => (Some(name.as_str()), value.clone()),
+                (_, Some(value)) => (None, value.clone()),
+                (Type::Block(schema), None) => {
+                    (None, Value::Block(schema.default_nodes(starter).collect()))
+                }
+                (_, None) if !field.required && !starter => return None,
+                (Type::List(_), None) => (None, Value::List(Vec::new())),
+                (Type::Named(name), None) => match name.as_str() {
+                    "string" => (Some("string"), Value::String(String::new())),
+                    "int" => (Some("int"), Value::Int(0)),
+                    "float" => (Some("float"), Value::Float(0.0)),
+                    "bool" => (Some("bool"), Value::Bool(false)),
+                    "list" => (None, Value::List(Vec::new())),
+                    "block" => (None, Value::Block(Block::new())),
+                    _ => (None, Value::Empty),
+                },
+                (Type::Any, None) => (None, Value::Empty),
+            };
+            let mut node = Node::new(key.clone(), value);
+            node.type_annotation = annotation.map(str::to_string);
+            node.doc = field.description.clone();
+            if starter {
+                let summary = field.summary();
+                node.doc = Some(match node.doc {
+                    Some(description) => format!("{}\n{}", description, summary),
+                    None => summary,
+                });
+            }
+            Some(node)
+        })
+    }
+
+    /// Add the default value of every key missing from `doc`
+    ///
+    /// Works through the blocks present in the document, including blocks
+    /// in lists, so a nested default is added where its block exists.
+    /// Missing keys without a default, including whole blocks, are left
+    /// for [`validate`](Self::validate) to report.
+    pub fn fill_defaults(&self, doc: &mut Document) {
+        for node in &mut doc.nodes {
+            if let Some(field) = self.get(&node.key) {
+                field.ty.fill_defaults(&mut node.value);
+            }
+        }
+        for (key, field) in &self.fields {
+            if let (Some(default), false) = (&field.default, field.deprecated) {
+                if !doc.contains_key(key) {
+                    doc.nodes.push(default_node(key, field, default));
+                }
+            }
+        }
+    }
+
+    fn fill_block(&self, block: &mut Block) {
+        for (key, value) in block.iter_mut() {
+            if let Some(field) = self.get(key) {
+                field.ty.fill_defaults(value);
+            }
+        }
+        for (key, field) in &self.fields {
+            if let (Some(default), false) = (&field.default, field.deprecated) {
+                if !block.contains_key(key) {
+                    block.insert_node(default_node(key, field, default));
+                }
+            }
+        }
+    }
+
+    /// Check `doc` against the schema, returning every violation in
+    /// document order, followed by missing keys
+    ///
+    /// Deprecated keys the document uses are reported too, as
+    /// [`ViolationKind::Deprecated`] warnings; see [`Violation::is_error`].
+    pub fn validate(&self, doc: &Document) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check_nodes(
+            doc.nodes.iter(),
+            "",
+            None,
+            self.allow_unknown,
+            &mut violations,
+        );
+        violations
+    }
+
+    fn check_nodes<'a>(
+        &self,
+        nodes: impl Iterator<Item = &'a Node>,
+        prefix: &str,
+        parent: Option<&Origin>,
+        allow_unknown: bool,
+        out: &mut Vec<Violation>,
+    ) {
+        let mut seen = Vec::new();
+        for node in nodes {
+            let path = join(prefix, &node.key);
+            let origin = node.origin.as_ref().or(parent);
+            match self.get(&node.key) {
+                Some(field) => {
+                    seen.push(node.key.as_str());
+                    if field.deprecated {
+                        out.push(field.deprecation(&path, origin));
+                    }
+                    field
+                        .ty
+                        .check(&node.value, &path, origin, allow_unknown, out);
+                }
+                None if allow_unknown => {}
+                None => {
+                    let known = self
+                        .fields
+                        .iter()
+                        .filter(|(_, field)| !field.deprecated)
+                        .map(|(key, _)| key.as_str());
+                    let suggestion = suggest::closest(&node.key, known);
+                    let message = match suggestion {
+                        Some(key) => format!("is not a known key; did you mean `{}`?", key),
+                        None => "is not a known key".to_string(),
+                    };
+                    out.push(Violation {
+                        path,
+                        kind: ViolationKind::Unknown,
+                        message,
+                        origin: origin.cloned(),
+                        replacement: suggestion.map(|key| join(prefix, key)),
+                    })
+                }
+            }
+        }
+
+        for (key, field) in &self.fields {
+            if field.required && !seen.contains(&key.as_str()) {
+                out.push(Violation {
+                    path: join(prefix, key),
+                    kind: ViolationKind::Missing,
+                    message: format!("is required ({})", field.ty),
+                    origin: parent.cloned(),
+                    replacement: None,
+                });
+            }
+        }
+    }
+}
+
+impl Type {
+    fn fill_defaults(&self, value: &mut Value) {
+        match (self, value) {
+            (Type::Block(schema), Value::Block(block)) => schema.fill_block(block),
+            (Type::List(item), Value::List(items)) => {
+                for item_value in items {
+                    item.fill_defaults(item_value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check(
+        &self,
+        value: &Value,
+        path: &str,
+        origin: Option<&Origin>,
+        allow_unknown: bool,
+        out: &mut Vec<Violation>,
+    ) {
+        let ok = match (self, value) {
+            (Type::Any, _) => true,
+            (Type::Named(name), value) => matches(name, value),
+            (Type::Block(schema), Value::Block(block)) => {
+                schema.check_nodes(block.nodes(), path, origin, allow_unknown, out);
+                true
+            }
+            (Type::List(item), Value::List(items)) => {
+                for (i, value) in items.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    item.check(value, &path, origin, allow_unknown, out);
+                }
+                true
+            }
+            _ => false,
+        };
+        if !ok {
+            let found = match value.scalar_text() {
+                Some(text) if matches!(value, Value::String(_)) => format!("`{}`", text),
+                _ => value.type_name().to_string(),
+            };
+            out.push(Violation {
+                path: path.to_string(),
+                kind: ViolationKind::WrongType,
+                message: format!("should be {}, found {}", self, found),
+                origin: origin.cloned(),
+                replacement: None,
+            });
+        }
+    }
+}
+
+/// Check whether `value` is of the type named `name`
+fn matches(name: &str, value: &Value) -> bool {
+    if value.type_name() == name {
+        return true;
+    }
+    match value {
+        Value::Int(_) => name == "float",
+        Value::String(text) => match types::coerce(name, text) {
+            Some(typed) => typed.is_ok(),
+            None => !matches!(name, "block" | "list" | "table"),
+        },
+        _ => false,
+    }
+}
+
+fn default_node(key: &str, field: &Field, default: &Value) -> Node {
+    let mut node = Node::new(key, default.clone());
+    if let Type::Named(name) = &field.ty {
+        node.type_annotation = Some(name.clone());
+    }
+    node
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn from_nodes<'a>(nodes: impl Iterator<Item = &'a Node>) -> Result<Schema, ParseError> {
+    let mut schema = Schema::new();
+    for node in nodes {
+        let (key, optional) = match node.key.strip_suffix('?') {
+            Some(key) => (key, true),
+            None => (node.key.as_str(), false),
+        };
+        let mut field = parse_field(node).map_err(|e| {
+            let at = node
+                .origin
+                .as_ref()
+                .map_or(String::new(), |origin| format!("{}: ", origin));
+            ParseError::new(e.kind, format!("{}`{}`: {}", at, key, e.message))
+        })?;
+        if optional {
+            field = field.optional();
+        }
+        let mut description = Vec::new();
+        for line in node.doc.iter().flat_map(|doc| doc.lines()) {
+            match line.strip_prefix("@deprecated") {
+                Some(rest) if rest.is_empty() || rest.starts_with(' ') => {
+                    field = match rest.trim() {
+                        "" => field.deprecated(),
+                        replacement => field.replaced_by(replacement),
+                    };
+                }
+                _ => description.push(line),
+            }
+        }
+        field.description = (!description.is_empty()).then(|| description.join("\n"));
+        schema = schema.field(key, field);
+    }
+    Ok(schema)
+}
+
+fn parse_field(node: &Node) -> Result<Field, ParseError> {
+    let text = node.value.scalar_text().filter(|text| !text.is_empty());
+    let has_text = text.is_some();
+    let (ty, default) = match (node.type_annotation.as_deref(), &node.value) {
+        (None | Some("block"), Value::Block(block)) => {
+            (Type::Block(from_nodes(block.nodes())?), None)
+        }
+        (Some("list"), Value::Block(block)) => {
+            (Type::list_of(Type::Block(from_nodes(block.nodes())?)), None)
+        }
+        (Some("list"), Value::List(items)) => {
+            (Type::list_of(Type::Any), Some(Value::List(items.clone())))
+        }
+        (Some("list"), _) => match text.as_deref() {
+            Some(item) => (Type::list_of(item_type(item)), None),
+            None => (Type::named("list"), None),
+        },
+        (None | Some("any"), value) => (Type::Any, has_text.then(|| value.clone())),
+        (Some(name), _) => {
+            let default = match text {
+                Some(text) => Some(match types::coerce(name, &text) {
+                    Some(typed) => typed?,
+                    None => Value::String(text.into_owned()),
+                }),
+                None => None,
+            };
+            (Type::named(name), default)
+        }
+    };
+    if matches!(ty, Type::Named(ref name) if name == "block" || name == "table") && has_text {
+        return Err(ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("a {} cannot have a default", ty),
+        ));
+    }
+    let field = Field::new(ty);
+    Ok(match default {
+        Some(value) => field.default_value(value),
+        None => field,
+    })
+}
+
+fn item_type(name: &str) -> Type {
+    match name {
+        "any" => Type::Any,
+        name => Type::named(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const SCHEMA: &str = "# Service name\nname!string\nport?!int 8080\nserver!block {\n  host!string\n  tls?!bool\n}\ntags?!list string\nbackends!list {\n  url!string\n  weight?!int\n}\nextra?\n";
+
+    #[test]
+    fn test_parse_schema() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let expected = Schema::new()
+            .field(
+                "name",
+                Field::new(Type::named("string")).description("Service name"),
+            )
+            .field(
+                "port",
+                Field::new(Type::named("int")).default_value(Value::Int(8080)),
+            )
+            .field(
+                "server",
+                Field::new(Type::Block(
+                    Schema::new()
+                        .field("host", Field::new(Type::named("string")))
+                        .field("tls", Field::new(Type::named("bool")).optional()),
+                )),
+            )
+            .field(
+                "tags",
+                Field::new(Type::list_of(Type::named("string"))).optional(),
+            )
+            .field(
+                "backends",
+                Field::new(Type::list_of(Type::Block(
+                    Schema::new()
+                        .field("url", Field::new(Type::named("string")))
+                        .field("weight", Field::new(Type::named("int")).optional()),
+                ))),
+            )
+            .field("extra", Field::new(Type::Any).optional());
+        assert_eq!(schema, expected);
+
+        let err = Schema::parse("port!int eighty").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.message, "line 1: `port`: expected int, found `eighty`");
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let valid = parse("name app\nport 80\nserver {\n  host localhost\n}\nbackends [\n  {\n    url a\n    weight!int 2\n  }\n]\n").unwrap();
+        assert_eq!(schema.validate(&valid), []);
+
+        let doc = parse("port high\nserver {\n  tls maybe\n  debug true\n}\ntags!list [a, b]\nbackends [\n  {\n    weight x\n  }\n]\n").unwrap();
+        let found: Vec<(String, ViolationKind, Option<usize>)> = schema
+            .validate(&doc)
+            .into_iter()
+            .map(|v| {
+                let line = v.line();
+                (v.path, v.kind, line)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("port".into(), ViolationKind::WrongType, Some(1)),
+                ("server.tls".into(), ViolationKind::WrongType, Some(3)),
+                ("server.debug".into(), ViolationKind::Unknown, Some(4)),
+                ("server.host".into(), ViolationKind::Missing, Some(2)),
+                (
+                    "backends[0].weight".into(),
+                    ViolationKind::WrongType,
+                    Some(9)
+                ),
+                ("backends[0].url".into(), ViolationKind::Missing, Some(7)),
+                ("name".into(), ViolationKind::Missing, None),
+            ]
+        );
+        assert_eq!(
+            schema.validate(&doc)[0].to_string(),
+            "line 1: `port` should be int, found `high`"
+        );
+
+        let lenient = schema.allow_unknown(true);
+        let doc =
+            parse("name app\nserver {\n  host h\n  debug true\n}\nbackends [\n]\nother 1").unwrap();
+        assert_eq!(lenient.validate(&doc), []);
+    }
+
+    #[test]
+    fn test_unknown_key_suggestion() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let doc = parse("name app\nprot 80\nserver {\n  hots h\n}\nbackends []\nzzz 1").unwrap();
+        let violations = schema.validate(&doc);
+        let messages: Vec<String> = violations.iter().map(Violation::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 2: `prot` is not a known key; did you mean `port`?",
+                "line 4: `server.hots` is not a known key; did you mean `host`?",
+                "line 3: `server.host` is required (string)",
+                "line 7: `zzz` is not a known key",
+            ]
+        );
+        assert_eq!(violations[1].replacement.as_deref(), Some("server.host"));
+        assert_eq!(violations[3].replacement, None);
+    }
+
+    #[test]
+    fn test_default_document() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let doc = schema.default_document();
+        assert_eq!(
+            crate::to_string(&doc),
+            "# Service name\nname!string\nport!int 8080\nserver {\n  host!string\n}\nbackends []\n"
+        );
+        assert!(schema.validate(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_starter_document() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let doc = schema.starter_document();
+        assert_eq!(
+            crate::to_string(&doc),
+            "# Service name\n# string, required\nname!string\n# int, optional\nport!int 8080\n\
+             # block, required\nserver {\n  # string, required\n  host!string\n  # bool, optional\n  tls!bool false\n}\n\
+             # list of string, optional\ntags []\n# list of block, required\nbackends []\n# any, optional\nextra\n"
+        );
+        assert!(schema.validate(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_keys() {
+        let schema = Schema::parse(
+            "server {\n  host?!string\n  # Address to listen on\n  # @deprecated server.host\n  listen?!string 0.0.0.0\n}\n# @deprecated\nlegacy?\n",
+        )
+        .unwrap();
+        let Some(Type::Block(server)) = schema.get("server").map(|f| &f.ty) else {
+            panic!("expected block");
+        };
+        let listen = server.get("listen").unwrap();
+        assert_eq!(listen.description.as_deref(), Some("Address to listen on"));
+        assert_eq!(listen.replaced_by.as_deref(), Some("server.host"));
+
+        let doc = parse("server {\n  listen 127.0.0.1\n}\nlegacy on\n").unwrap();
+        let violations = schema.validate(&doc);
+        let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 2: `server.listen` is deprecated, use `server.host` instead",
+                "line 4: `legacy` is deprecated"
+            ]
+        );
+        assert_eq!(violations[0].kind, ViolationKind::Deprecated);
+        assert_eq!(violations[0].replacement.as_deref(), Some("server.host"));
+        assert!(!violations.iter().any(Violation::is_error));
+
+        // Deprecated keys are not scaffolded or filled in
+        let mut doc = parse("server {\n}\n").unwrap();
+        schema.fill_defaults(&mut doc);
+        assert!(schema.validate(&doc).is_empty());
+        assert_eq!(
+            crate::to_string(&schema.starter_document()),
+            "# block, required\nserver {\n  # string, optional\n  host!string\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_fill_defaults() {
+        let schema = Schema::parse("port?!int 8080\nserver!block {\n  host?!string localhost\n  tls?!bool\n}\nbackends!list {\n  weight?!int 1\n}\n").unwrap();
+        let mut doc = parse("backends [\n  {\n    weight 5\n  }\n  {\n  }\n]\n").unwrap();
+        schema.fill_defaults(&mut doc);
+        assert_eq!(
+            doc,
+            parse("backends [\n  {\n    weight 5\n  }\n  {\n    weight!int 1\n  }\n]\nport!int 8080\n").unwrap()
+        );
+        let violations = schema.validate(&doc);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "server");
+    }
+}
@@ -0,0 +1,453 @@
+//! What may be written where, for editor completion and hover
+
+use crate::keypath::{self, Segment};
+use crate::{continues, quote, text, Value};
+
+use super::{Field, Schema, Type};
+
+/// A suggestion for the text at a cursor, see [`Schema::complete`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Text to insert, quoted where the syntax needs it
+    pub label: String,
+    /// Whether the text is a key or a value
+    pub kind: CompletionKind,
+    /// For a key, its type and whether it is required, e.g.
+    /// `int, optional`; for a value, what it is, e.g. `default`
+    pub detail: String,
+    /// Description of the key from the schema
+    pub documentation: Option<String>,
+    /// Whether the key is deprecated
+    pub deprecated: bool,
+}
+
+/// What a [`Completion`] suggests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionKind {
+    /// A key of the enclosing block
+    Key,
+    /// A value for a key, or an item of a list
+    Value,
+}
+
+impl Schema {
+    /// Look up the declared key at a key path, such as `server.host` or
+    /// `backends[0].url`, for showing its documentation
+    ///
+    /// List indexes step into the item type, whatever the index.
+    pub fn field_at(&self, path: &str) -> Option<&Field> {
+        let segments = keypath::parse(path)?;
+        let (last, parents) = segments.split_last()?;
+        let Segment::Key(key) = last else {
+            return None;
+        };
+        block_schema(self, parents)?.get(key)
+    }
+
+    /// The keys that may be written in the block at `path`, in schema
+    /// order; `""` is the top level
+    ///
+    /// ```
+    /// use uplang::schema::Schema;
+    ///
+    /// let schema = Schema::parse("server!block {\n  # Host name\n  host!string\n  port?!int 80\n}").unwrap();
+    /// let keys = schema.complete_keys("server");
+    /// let labels: Vec<_> = keys.iter().map(|c| c.label.as_str()).collect();
+    /// assert_eq!(labels, ["host", "port"]);
+    /// assert_eq!(keys[0].detail, "string, required");
+    /// assert_eq!(keys[0].documentation.as_deref(), Some("Host name"));
+    /// ```
+    pub fn complete_keys(&self, path: &str) -> Vec<Completion> {
+        let segments = if path.is_empty() {
+            Vec::new()
+        } else {
+            match keypath::parse(path) {
+                Some(segments) => segments,
+                None => return Vec::new(),
+            }
+        };
+        match block_schema(self, &segments) {
+            Some(schema) => schema.key_completions(|_| true),
+            None => Vec::new(),
+        }
+    }
+
+    /// The values worth suggesting for the key at `path`: its default,
+    /// `true` and `false` for a `bool`, and `{` or `[` to open a block or
+    /// list
+    pub fn complete_values(&self, path: &str) -> Vec<Completion> {
+        match self.field_at(path) {
+            Some(field) => value_completions(&field.ty, Some(field)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Suggest what to write at byte `offset` of `input`, a possibly
+    /// incomplete document being edited
+    ///
+    /// At the start of a line, or within its first word, the suggestions
+    /// are the keys of the enclosing block that start with what has been
+    /// typed and are not already in the block. After a key, they are
+    /// values for it; inside a list, values for its items. Nothing is
+    /// suggested inside multiline strings, or for keys the schema does not
+    /// declare.
+    ///
+    /// ```
+    /// use uplang::schema::{CompletionKind, Schema};
+    ///
+    /// let schema = Schema::parse("name!string\nserver!block {\n  host!string\n  tls?!bool\n}").unwrap();
+    /// let input = "name app\nserver {\n  host h\n  t\n}\n";
+    /// let found = schema.complete(input, input.find("t\n}").unwrap() + 1);
+    /// assert_eq!(found[0].label, "tls");
+    /// assert_eq!(found[0].kind, CompletionKind::Key);
+    ///
+    /// let input = "server {\n  tls ";
+    /// let values: Vec<_> = schema.complete(input, input.len()).into_iter().map(|c| c.label).collect();
+    /// assert_eq!(values, ["true", "false"]);
+    /// ```
+    pub fn complete(&self, input: &str, offset: usize) -> Vec<Completion> {
+        let offset = text::floor_char_boundary(input, offset);
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+
+        let mut context = Context::default();
+        for line in input[..line_start].lines() {
+            context.line(line);
+        }
+        if context.in_fence || context.continued {
+            return Vec::new();
+        }
+        let before = input[line_start..offset].trim_start();
+
+        // Keys already in the cursor's block, after the cursor too
+        let depth = context.open.len();
+        let mut rest = Context {
+            open: context.open.clone(),
+            top: context.top.clone(),
+            ..Context::default()
+        };
+        for line in input[line_end..].lines() {
+            rest.line(line);
+            if rest.open.len() < depth {
+                break;
+            }
+        }
+        let present = match depth.checked_sub(1) {
+            Some(i) => rest
+                .open
+                .into_iter()
+                .chain(rest.closed)
+                .nth(i)
+                .map(|f| f.keys),
+            None => Some(rest.top),
+        }
+        .unwrap_or_default();
+
+        let segments: Vec<Segment<'_>> = context
+            .open
+            .iter()
+            .map(|frame| match &frame.step {
+                Step::Key(key) => Segment::Key(key),
+                Step::Item => Segment::Index(0),
+            })
+            .collect();
+        let in_list = context.open.last().is_some_and(|frame| frame.list);
+
+        if in_list {
+            let Some(Type::List(item)) = value_type(self, &segments) else {
+                return Vec::new();
+            };
+            return filtered(value_completions(item, None), before);
+        }
+        let Some(schema) = block_schema(self, &segments) else {
+            return Vec::new();
+        };
+        let key_end = quote::quoted_len(before).unwrap_or(0);
+        match before[key_end..].find(char::is_whitespace) {
+            None => schema.key_completions(|key| {
+                !present.iter().any(|p| p == key) && key.starts_with(before.trim_start_matches('"'))
+            }),
+            Some(i) => {
+                let Some(key) = key_of(&before[..key_end + i]) else {
+                    return Vec::new();
+                };
+                match schema.get(&key) {
+                    Some(field) => filtered(
+                        value_completions(&field.ty, Some(field)),
+                        before[key_end + i..].trim_start(),
+                    ),
+                    None => Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn key_completions(&self, mut wanted: impl FnMut(&str) -> bool) -> Vec<Completion> {
+        self.fields()
+            .filter(|(key, _)| wanted(key))
+            .map(|(key, field)| Completion {
+                label: if quote::key_needs_quotes(key) {
+                    quote::quote(key)
+                } else {
+                    key.to_string()
+                },
+                kind: CompletionKind::Key,
+                detail: field.summary(),
+                documentation: field.description.clone(),
+                deprecated: field.deprecated,
+            })
+            .collect()
+    }
+}
+
+/// The schema of the block reached by `path`, through blocks and list items
+fn block_schema<'s>(schema: &'s Schema, path: &[Segment<'_>]) -> Option<&'s Schema> {
+    if path.is_empty() {
+        return Some(schema);
+    }
+    match value_type(schema, path)? {
+        Type::Block(schema) => Some(schema),
+        _ => None,
+    }
+}
+
+/// The declared type of the value reached by a non-empty `path`
+fn value_type<'s>(schema: &'s Schema, path: &[Segment<'_>]) -> Option<&'s Type> {
+    let (first, rest) = path.split_first()?;
+    let Segment::Key(key) = first else {
+        return None;
+    };
+    let mut ty = &schema.get(key)?.ty;
+    for segment in rest {
+        ty = match (segment, ty) {
+            (Segment::Key(key), Type::Block(schema)) => &schema.get(key)?.ty,
+            (Segment::Index(_), Type::List(item)) => item,
+            _ => return None,
+        };
+    }
+    Some(ty)
+}
+
+fn value_completions(ty: &Type, field: Option<&Field>) -> Vec<Completion> {
+    let documentation = field.and_then(|field| field.description.clone());
+    let value = |label: &str, detail: &str| Completion {
+        label: label.to_string(),
+        kind: CompletionKind::Value,
+        detail: detail.to_string(),
+        documentation: documentation.clone(),
+        deprecated: field.is_some_and(|field| field.deprecated),
+    };
+    let mut found = Vec::new();
+    if let Some(text) = field
+        .and_then(|field| field.default.as_ref())
+        .and_then(Value::scalar_text)
+    {
+        let text = if quote::value_needs_quotes(&text) {
+            quote::quote(&text)
+        } else {
+            text.into_owned()
+        };
+        found.push(value(&text, "default"));
+    }
+    match ty {
+        Type::Named(name) if name == "bool" => {
+            for text in ["true", "false"] {
+                if found.iter().all(|c| c.label != text) {
+                    found.push(value(text, "bool"));
+                }
+            }
+        }
+        Type::Block(_) => found.push(value("{", "block")),
+        Type::Named(name) if name == "block" => found.push(value("{", "block")),
+        Type::List(_) => found.push(value("[", "list")),
+        Type::Named(name) if name == "list" => found.push(value("[", "list")),
+        _ => {}
+    }
+    found
+}
+
+fn filtered(completions: Vec<Completion>, prefix: &str) -> Vec<Completion> {
+    completions
+        .into_iter()
+        .filter(|c| c.label.starts_with(prefix))
+        .collect()
+}
+
+/// The key of a `key!annotation` key part, unquoted
+fn key_of(key_part: &str) -> Option<String> {
+    quote::split_key(key_part)
+        .ok()
+        .map(|(key, _)| key.into_owned())
+}
+
+/// How a block or list was entered from its parent
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Item,
+}
+
+/// An open block or list, and the keys seen in it so far
+#[derive(Debug, Clone)]
+struct Frame {
+    step: Step,
+    list: bool,
+    keys: Vec<String>,
+}
+
+/// Blocks and lists open at a point in a document, read line by line
+#[derive(Debug, Default)]
+struct Context {
+    open: Vec<Frame>,
+    /// Keys seen at the top level
+    top: Vec<String>,
+    /// The block or list closed last
+    closed: Option<Frame>,
+    in_fence: bool,
+    continued: bool,
+}
+
+impl Context {
+    fn line(&mut self, line: &str) {
+        let trimmed = line.trim();
+        if self.in_fence {
+            self.in_fence = trimmed != "```";
+            return;
+        }
+        if self.continued {
+            self.continued = continues(trimmed);
+            return;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return;
+        }
+
+        if self.open.last().is_some_and(|frame| frame.list) {
+            match trimmed {
+                "]" => self.pop(),
+                "{" => self.push(Step::Item, false),
+                "[" => self.push(Step::Item, true),
+                s if s.starts_with("```") => self.in_fence = true,
+                s => self.continued = continues(s),
+            }
+            return;
+        }
+        if trimmed == "}" {
+            self.pop();
+            return;
+        }
+        if trimmed.starts_with('@') {
+            return;
+        }
+
+        let key_end = quote::quoted_len(trimmed).unwrap_or(0);
+        let (key_part, value) = match trimmed[key_end..].find(char::is_whitespace) {
+            Some(i) => (&trimmed[..key_end + i], trimmed[key_end + i..].trim()),
+            None => (trimmed, ""),
+        };
+        let Some(key) = key_of(key_part) else {
+            return;
+        };
+        match self.open.last_mut() {
+            Some(frame) => frame.keys.push(key.clone()),
+            None => self.top.push(key.clone()),
+        }
+        if value == "[" {
+            self.push(Step::Key(key), true);
+        } else if value.ends_with('{') {
+            self.push(Step::Key(key), false);
+        } else if value.starts_with("```") {
+            self.in_fence = true;
+        } else {
+            self.continued = continues(value);
+        }
+    }
+
+    fn pop(&mut self) {
+        self.closed = self.open.pop().or(self.closed.take());
+    }
+
+    fn push(&mut self, step: Step, list: bool) {
+        self.open.push(Frame {
+            step,
+            list,
+            keys: Vec::new(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "# Service name\nname!string\nport?!int 8080\nserver!block {\n  host!string\n  tls?!bool\n}\ntags?!list string\nbackends!list {\n  url!string\n  weight?!int\n}\n# @deprecated server.host\nlisten?!string\n";
+
+    fn labels(completions: &[Completion]) -> Vec<&str> {
+        completions.iter().map(|c| c.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_complete_paths() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let keys = schema.complete_keys("");
+        assert_eq!(
+            labels(&keys),
+            ["name", "port", "server", "tags", "backends", "listen"]
+        );
+        assert_eq!(keys[0].detail, "string, required");
+        assert_eq!(keys[0].documentation.as_deref(), Some("Service name"));
+        assert!(keys[5].deprecated);
+        assert_eq!(
+            labels(&schema.complete_keys("backends[3]")),
+            ["url", "weight"]
+        );
+        assert!(schema.complete_keys("name").is_empty());
+        assert!(schema.complete_keys("nope").is_empty());
+
+        assert_eq!(labels(&schema.complete_values("port")), ["8080"]);
+        assert_eq!(
+            labels(&schema.complete_values("server.tls")),
+            ["true", "false"]
+        );
+        assert_eq!(labels(&schema.complete_values("server")), ["{"]);
+        assert_eq!(labels(&schema.complete_values("tags")), ["["]);
+
+        let field = schema.field_at("backends[0].weight").unwrap();
+        assert_eq!(field.ty, Type::named("int"));
+        assert!(schema.field_at("server.port").is_none());
+        assert!(schema.field_at("backends[0]").is_none());
+    }
+
+    #[test]
+    fn test_complete_at_cursor() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let at = |input: &str, marker: &str| {
+            let offset = input.find(marker).unwrap() + marker.len();
+            schema.complete(input, offset)
+        };
+
+        // Keys not yet in the block, before or after the cursor
+        let found = at("name app\n\nport 80\n", "name app\n");
+        assert_eq!(labels(&found), ["server", "tags", "backends", "listen"]);
+        let found = at("server {\n  ho\n  tls true\n}\n", "  ho");
+        assert_eq!(labels(&found), ["host"]);
+        assert_eq!(found[0].kind, CompletionKind::Key);
+        let found = at("backends [\n  {\n    url a\n    \n  }\n]\n", "url a\n    ");
+        assert_eq!(labels(&found), ["weight"]);
+
+        // Values after a key, and list items
+        let found = at("server {\n  tls f\n", "tls f");
+        assert_eq!(labels(&found), ["false"]);
+        assert_eq!(found[0].kind, CompletionKind::Value);
+        assert_eq!(labels(&at("port!int 8\n", "8")), ["8080"]);
+        assert_eq!(labels(&at("server ", "server ")), ["{"]);
+
+        // Nothing inside multiline strings or undeclared blocks
+        assert!(at("name ```\n  te\n```\n", "te").is_empty());
+        assert!(at("other {\n  a\n}\n", "  a").is_empty());
+        assert!(at("tags [\n  \n]\n", "[\n  ").is_empty());
+        assert_eq!(labels(&schema.complete("name", 100)), ["name"]);
+    }
+}
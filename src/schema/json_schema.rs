@@ -0,0 +1,230 @@
+//! Publishing schemas as JSON Schema
+
+use super::{Field, Schema, Type};
+use crate::{Block, Document, Node, Value};
+
+/// Dialect of the generated schemas
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Write `schema` as a JSON Schema for the JSON form of its documents, as
+/// [`Document::to_json`] writes them
+///
+/// Blocks become objects with their keys as properties, and lists become
+/// arrays. `int`, `float`, `bool`, `null`, and `string` map to the JSON
+/// types; every other annotation describes a string, with a `format` where
+/// JSON Schema has one (`date`, `time`, `ts`, `url`, `email`) and the
+/// annotation in `x-up-type`. Descriptions, defaults, and deprecation are
+/// kept. The result is indented by two spaces and ends with a newline.
+///
+/// ```
+/// use uplang::schema::{to_json_schema, Schema};
+///
+/// let schema = Schema::parse("# Port to listen on\nport?!int 8080").unwrap();
+/// let json = to_json_schema(&schema);
+/// assert!(json.contains(r#""port": {
+///       "type": "integer",
+///       "description": "Port to listen on",
+///       "default": 8080
+///     }"#));
+/// ```
+pub fn to_json_schema(schema: &Schema) -> String {
+    let mut root = Block::new();
+    root.insert("$schema", Value::from(DIALECT));
+    for node in object(schema, schema.allow_unknown).into_nodes() {
+        root.insert_node(node);
+    }
+    Document {
+        nodes: root.into_nodes(),
+    }
+    .to_json_pretty()
+}
+
+impl Schema {
+    /// Infer a schema from an example document
+    ///
+    /// Every key of the example is required and has the type its
+    /// annotation names; unannotated scalars are strings, blocks give the
+    /// schema of their keys, and lists the type of their first item. Doc
+    /// comments become descriptions.
+    ///
+    /// ```
+    /// let example = uplang::parse("name app\nport!int 8080\ntags [web]").unwrap();
+    /// let schema = uplang::schema::Schema::from_example(&example);
+    /// assert_eq!(schema.get("port").unwrap().ty.to_string(), "int");
+    /// assert_eq!(schema.get("tags").unwrap().ty.to_string(), "list of string");
+    /// ```
+    pub fn from_example(doc: &Document) -> Schema {
+        example_schema(doc.nodes.iter())
+    }
+}
+
+fn example_schema<'a>(nodes: impl Iterator<Item = &'a Node>) -> Schema {
+    let mut schema = Schema::new();
+    for node in nodes {
+        let mut field = Field::new(example_type(&node.value, node.type_annotation.as_deref()));
+        field.description = node.doc.clone();
+        schema = schema.field(node.key.as_str(), field);
+    }
+    schema
+}
+
+fn example_type(value: &Value, annotation: Option<&str>) -> Type {
+    match (value, annotation) {
+        (Value::Block(block), _) => Type::Block(example_schema(block.nodes())),
+        (Value::List(items), _) => Type::list_of(
+            items
+                .first()
+                .map_or(Type::Any, |item| example_type(item, None)),
+        ),
+        (_, Some(annotation)) => Type::named(annotation),
+        (value, None) => Type::named(value.type_name()),
+    }
+}
+
+/// The `type`, `properties`, and `required` of an object with the keys of
+/// `schema`
+fn object(schema: &Schema, allow_unknown: bool) -> Block {
+    let mut properties = Block::new();
+    let mut required = Vec::new();
+    for (key, field) in &schema.fields {
+        properties.insert(key.as_str(), Value::Block(property(field, allow_unknown)));
+        if field.required {
+            required.push(Value::from(key.as_str()));
+        }
+    }
+    let mut object = Block::new();
+    object.insert("type", Value::from("object"));
+    object.insert("properties", Value::Block(properties));
+    if !required.is_empty() {
+        object.insert("required", Value::List(required));
+    }
+    if !allow_unknown {
+        object.insert("additionalProperties", Value::Bool(false));
+    }
+    object
+}
+
+fn property(field: &Field, allow_unknown: bool) -> Block {
+    let mut property = json_type(&field.ty, allow_unknown);
+    if let Some(description) = &field.description {
+        property.insert("description", Value::from(description.as_str()));
+    }
+    if let Some(default) = &field.default {
+        property.insert("default", default.clone());
+    }
+    if field.deprecated {
+        property.insert("deprecated", Value::Bool(true));
+    }
+    property
+}
+
+fn json_type(ty: &Type, allow_unknown: bool) -> Block {
+    let mut out = Block::new();
+    match ty {
+        Type::Any => {}
+        Type::Block(schema) => out = object(schema, allow_unknown),
+        Type::List(item) => {
+            out.insert("type", Value::from("array"));
+            out.insert("items", Value::Block(json_type(item, allow_unknown)));
+        }
+        Type::Named(name) => {
+            let (json, format) = match name.as_str() {
+                "int" => ("integer", None),
+                "float" => ("number", None),
+                "bool" => ("boolean", None),
+                "null" => ("null", None),
+                "block" => ("object", None),
+                "list" => ("array", None),
+                "string" => ("string", None),
+                "date" => ("string", Some("date")),
+                "time" => ("string", Some("time")),
+                "ts" => ("string", Some("date-time")),
+                "url" => ("string", Some("uri")),
+                "email" => ("string", Some("email")),
+                _ => ("string", None),
+            };
+            out.insert("type", Value::from(json));
+            if let Some(format) = format {
+                out.insert("format", Value::from(format));
+            }
+            if !matches!(
+                name.as_str(),
+                "int" | "float" | "bool" | "null" | "block" | "list" | "string"
+            ) {
+                out.insert("x-up-type", Value::from(name.as_str()));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_json_schema() {
+        let schema = Schema::parse(
+            "name!string\nport?!int 8080\nserver!block {\n  host!string\n  tls?!bool\n}\ntags?!list string\nbackends!list {\n  url!url\n}\n# @deprecated\nlisten?!string\ntimeout?!dur 30s\nextra\n",
+        )
+        .unwrap();
+        let json = to_json_schema(&schema);
+        let doc = crate::Document::from_json(&json).unwrap();
+        assert_eq!(doc.get_str("$schema"), Some(DIALECT));
+        assert_eq!(doc.get_str("type"), Some("object"));
+        assert_eq!(doc.get_str("properties.name.type"), Some("string"));
+        assert_eq!(doc.get_str("properties.port.type"), Some("integer"));
+        assert_eq!(doc.get_int("properties.port.default"), Some(8080));
+        assert_eq!(
+            doc.get_str("properties.server.properties.tls.type"),
+            Some("boolean")
+        );
+        assert_eq!(doc.get_str("properties.server.required[0]"), Some("host"));
+        assert_eq!(doc.get_str("properties.tags.items.type"), Some("string"));
+        assert_eq!(
+            doc.get_str("properties.backends.items.properties.url.format"),
+            Some("uri")
+        );
+        assert_eq!(doc.get_bool("properties.listen.deprecated"), Some(true));
+        assert_eq!(doc.get_str("properties.timeout.x-up-type"), Some("dur"));
+        assert_eq!(doc.get_str("properties.timeout.default"), Some("30s"));
+        assert_eq!(
+            doc.get("properties.extra")
+                .and_then(Value::as_block)
+                .map(Block::len),
+            Some(0)
+        );
+        let required: Vec<&str> = doc
+            .get("required")
+            .and_then(Value::as_list)
+            .unwrap()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        assert_eq!(required, ["name", "server", "backends", "extra"]);
+        assert_eq!(doc.get_bool("additionalProperties"), Some(false));
+
+        let open = to_json_schema(&schema.clone().allow_unknown(true));
+        assert!(!open.contains("additionalProperties"));
+    }
+
+    #[test]
+    fn test_schema_from_example() {
+        let example = parse("# Service name\nname app\nport!int 8080\nserver {\n  tls!bool true\n}\nbackends [\n  {\n    url!url http://a\n  }\n]\nempty []\n").unwrap();
+        let schema = Schema::from_example(&example);
+        assert_eq!(
+            schema.get("name").unwrap().description.as_deref(),
+            Some("Service name")
+        );
+        // The example itself is valid
+        assert!(schema.validate(&example).is_empty());
+        let doc = crate::Document::from_json(&to_json_schema(&schema)).unwrap();
+        assert_eq!(
+            doc.get_str("properties.backends.items.properties.url.format"),
+            Some("uri")
+        );
+        assert_eq!(doc.get_str("properties.empty.type"), Some("array"));
+        assert!(doc.get("properties.empty.items.type").is_none());
+    }
+}
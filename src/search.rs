@@ -0,0 +1,76 @@
+//! Searching documents by predicate or key pattern
+
+use crate::{walk, Document, Value};
+
+/// A value found by [`Document::find`], together with its key path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    /// Path of the value, e.g. `database.password` or `servers[1].host`
+    pub path: String,
+    /// The matched value
+    pub value: &'a Value,
+}
+
+impl Document {
+    /// Find every value for which `predicate(path, value)` returns true
+    ///
+    /// Values are visited depth-first in document order, so a block is
+    /// reported before its children.
+    pub fn find<F>(&self, mut predicate: F) -> Vec<Match<'_>>
+    where
+        F: FnMut(&str, &Value) -> bool,
+    {
+        let mut matches = Vec::new();
+        walk::walk(self, &mut |path, value| {
+            if predicate(path, value) {
+                matches.push(Match {
+                    path: path.to_string(),
+                    value,
+                });
+            }
+        });
+        matches
+    }
+
+    /// Find every value whose key path matches a regular expression
+    ///
+    /// `doc.find_regex("(?i)password|secret")` lists all credentials-looking
+    /// keys regardless of nesting.
+    #[cfg(feature = "regex")]
+    pub fn find_regex(&self, pattern: &str) -> Result<Vec<Match<'_>>, regex::Error> {
+        let re = regex::Regex::new(pattern)?;
+        Ok(self.find(|path, _| re.is_match(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    const INPUT: &str = r#"
+database {
+host db.internal
+Password hunter2
+}
+api_token abc123
+ports [80, 443]
+"#;
+
+    #[test]
+    fn test_find_by_predicate() {
+        let doc = parse(INPUT).unwrap();
+        let found = doc.find(|_, value| *value == Value::String("443".to_string()));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "ports[1]");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_find_regex() {
+        let doc = parse(INPUT).unwrap();
+        let found = doc.find_regex("(?i)password|token").unwrap();
+        let paths: Vec<&str> = found.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, ["database.Password", "api_token"]);
+        assert!(doc.find_regex("(").is_err());
+    }
+}
@@ -0,0 +1,507 @@
+//! Serializing Rust values to UP with serde
+//!
+//! ```
+//! # #[cfg(feature = "serde")] {
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let text = uplang::ser::to_string(&Server { host: "localhost".into(), port: 8080 }).unwrap();
+//! assert_eq!(text, "host localhost\nport!int 8080\n");
+//! # }
+//! ```
+//!
+//! Structs and maps become blocks, sequences and tuples become lists, and
+//! non-string scalars get a type annotation (`!int`, `!float`, `!bool`).
+//! `None` and `()` are written as `!null`. Enum variants with data are
+//! written as a block with the variant name as its only key. Scalars inside
+//! lists carry no annotation, since list items cannot be annotated.
+
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::{Block, Document, Node, Value};
+
+/// Error serializing a value to UP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Serialize a struct or map as UP text
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    Ok(crate::to_string(&to_document(value)?))
+}
+
+/// Serialize a struct or map into a document, one node per field
+pub fn to_document<T: Serialize + ?Sized>(value: &T) -> Result<Document, Error> {
+    match value.serialize(ValueSerializer)?.value {
+        Value::Block(block) => Ok(Document {
+            nodes: block.into_nodes(),
+        }),
+        other => Err(Error(format!(
+            "top level must be a struct or map, not a {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// A serialized value with the annotation needed to read it back
+struct Typed {
+    annotation: Option<&'static str>,
+    value: Value,
+}
+
+impl Typed {
+    fn plain(value: Value) -> Self {
+        Self {
+            annotation: None,
+            value,
+        }
+    }
+
+    fn annotated(value: Value) -> Self {
+        Self {
+            annotation: Some(value.type_name()),
+            value,
+        }
+    }
+
+    fn int(n: impl TryInto<i64> + fmt::Display + Copy) -> Result<Self, Error> {
+        n.try_into()
+            .map(|n| Self::annotated(Value::Int(n)))
+            .map_err(|_| Error(format!("integer {} does not fit in an `!int`", n)))
+    }
+
+    fn into_node(self, key: impl Into<String>) -> Node {
+        Node {
+            type_annotation: self.annotation.map(str::to_string),
+            ..Node::new(key, self.value)
+        }
+    }
+}
+
+/// A block holding one key, used for enum variants with data
+fn variant_block(variant: &str, typed: Typed) -> Typed {
+    let mut block = Block::new();
+    block.insert_node(typed.into_node(variant));
+    Typed::plain(Value::Block(block))
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Typed;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = TupleVariantSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = StructVariantSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Typed, Error> {
+        Typed::int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Float(v.into())))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Float(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::List(
+            v.iter().map(|&b| Value::Int(b.into())).collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Null))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Typed, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Typed, Error> {
+        Ok(Typed::annotated(Value::Null))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Typed, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Typed, Error> {
+        Ok(variant_block(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<TupleVariantSerializer, Error> {
+        Ok(TupleVariantSerializer {
+            variant,
+            seq: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            block: Block::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructVariantSerializer, Error> {
+        Ok(StructVariantSerializer {
+            variant,
+            map: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?.value);
+        Ok(())
+    }
+
+    fn finish(self) -> Typed {
+        Typed::plain(Value::List(self.items))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(self.finish())
+    }
+}
+
+struct TupleVariantSerializer {
+    variant: &'static str,
+    seq: SeqSerializer,
+}
+
+impl ser::SerializeTupleVariant for TupleVariantSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.seq.push(value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(variant_block(self.variant, self.seq.finish()))
+    }
+}
+
+struct MapSerializer {
+    block: Block,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        let node = value.serialize(ValueSerializer)?.into_node(key);
+        self.block.insert_node(node);
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(ValueSerializer)?.value;
+        match key.scalar_text() {
+            Some(text) if !text.is_empty() => {
+                self.key = Some(text.into_owned());
+                Ok(())
+            }
+            _ => Err(Error(format!(
+                "map keys must be non-empty scalars, not a {}",
+                key.type_name()
+            ))),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("map value without a key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::Block(self.block)))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        Ok(Typed::plain(Value::Block(self.block)))
+    }
+}
+
+struct StructVariantSerializer {
+    variant: &'static str,
+    map: MapSerializer,
+}
+
+impl ser::SerializeStructVariant for StructVariantSerializer {
+    type Ok = Typed;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.map.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Typed, Error> {
+        let block = Typed::plain(Value::Block(self.map.block));
+        Ok(variant_block(self.variant, block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    enum Mode {
+        Fast,
+        Limit(u32),
+        Window { start: u8, end: u8 },
+    }
+
+    #[derive(serde::Serialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        ratio: f64,
+        tags: Vec<&'static str>,
+        owner: Option<String>,
+        mode: Mode,
+        limit: Mode,
+        window: Mode,
+        labels: BTreeMap<u16, &'static str>,
+    }
+
+    #[test]
+    fn test_to_string() {
+        let config = Config {
+            name: "app".to_string(),
+            debug: true,
+            ratio: 0.5,
+            tags: vec!["web", "a, b"],
+            owner: None,
+            mode: Mode::Fast,
+            limit: Mode::Limit(10),
+            window: Mode::Window { start: 1, end: 5 },
+            labels: [(80, "http"), (443, "https")].into_iter().collect(),
+        };
+        let text = to_string(&config).unwrap();
+        assert_eq!(
+            text,
+            "name app\n\
+             debug!bool true\n\
+             ratio!float 0.5\n\
+             tags [web, \"a, b\"]\n\
+             owner!null\n\
+             mode Fast\n\
+             limit {\n  Limit!int 10\n}\n\
+             window {\n  Window {\n    start!int 1\n    end!int 5\n  }\n}\n\
+             labels {\n  80 http\n  443 https\n}\n"
+        );
+        assert_eq!(parse(&text).unwrap(), to_document(&config).unwrap());
+    }
+
+    #[test]
+    fn test_top_level_must_be_a_block() {
+        assert!(to_string(&42).is_err());
+        assert!(to_string(&[("big", u64::MAX)].into_iter().collect::<BTreeMap<_, _>>()).is_err());
+        assert!(to_string(&vec![1, 2]).is_err());
+
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 2);
+        assert!(to_string(&map).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Guessing whether text is UP, see [`sniff`]
+
+/// Lines [`sniff`] reads at most, enough to tell formats apart without
+/// reading a whole file
+const SAMPLE_LINES: usize = 200;
+
+/// A format that text can look like instead of UP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lookalike {
+    /// `key: value` mappings and `- item` sequences
+    Yaml,
+    /// `key = "value"` pairs under `[table]` headers
+    Toml,
+    /// `key=value` pairs under `[section]` headers, with `;` comments
+    Ini,
+    /// A `{...}` object or `[...]` array
+    Json,
+}
+
+impl Lookalike {
+    /// The format's name, such as `yaml`
+    pub fn name(self) -> &'static str {
+        match self {
+            Lookalike::Yaml => "yaml",
+            Lookalike::Toml => "toml",
+            Lookalike::Ini => "ini",
+            Lookalike::Json => "json",
+        }
+    }
+}
+
+/// How sure [`sniff`] is that text is UP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Confidence {
+    /// From 0.0, certainly not UP, to 1.0, certainly UP; 0.5 when the text
+    /// gives nothing to go by
+    pub score: f32,
+    /// The other format the text looks most like, if it looks like one
+    pub lookalike: Option<Lookalike>,
+}
+
+impl Confidence {
+    /// Whether the text is more likely UP than not
+    pub fn is_up(&self) -> bool {
+        self.score > 0.5
+    }
+}
+
+/// Estimate whether `input` is UP rather than YAML, TOML, INI, or JSON,
+/// for editors and import pipelines that route files without extensions
+///
+/// The guess is made from the shape of the first lines: `key value` pairs,
+/// `!type` annotations, and `{` blocks count for UP, `key: value` for YAML,
+/// `key = value` and `[section]` headers for TOML or INI. It does not parse
+/// the text, so a file that is UP with mistakes in it still scores high.
+///
+/// ```
+/// use uplang::{sniff, Lookalike};
+///
+/// let up = sniff("name app\nport!int 8080\nserver {\n  host localhost\n}\n");
+/// assert!(up.is_up());
+///
+/// let yaml = sniff("name: app\nports:\n  - 8080\n");
+/// assert!(!yaml.is_up());
+/// assert_eq!(yaml.lookalike, Some(Lookalike::Yaml));
+///
+/// let ini = sniff("; settings\n[server]\nhost=localhost\n");
+/// assert_eq!(ini.lookalike, Some(Lookalike::Ini));
+/// ```
+pub fn sniff(input: &str) -> Confidence {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    if looks_like_json(input) {
+        return Confidence {
+            score: 0.0,
+            lookalike: Some(Lookalike::Json),
+        };
+    }
+
+    let mut votes = Votes::default();
+    let mut fenced = false;
+    for line in input.lines().take(SAMPLE_LINES) {
+        let line = line.trim();
+        if fenced {
+            fenced = line != "```";
+            continue;
+        }
+        if line.ends_with("```") && line.len() > 3 {
+            fenced = true;
+        }
+        votes.count(line);
+    }
+    votes.confidence()
+}
+
+/// Whether `input` starts like a JSON object or array, and not like a
+/// `[section]` header
+fn looks_like_json(input: &str) -> bool {
+    let text = input.trim_start();
+    if text.starts_with('{') {
+        return true;
+    }
+    let first = text.lines().next().unwrap_or("").trim();
+    first.starts_with('[') && section(first).is_none()
+}
+
+/// The name of a `[section]` or `[[table]]` header, and whether it was
+/// the doubled kind
+fn section(line: &str) -> Option<(&str, bool)> {
+    let (name, doubled) = match line.strip_prefix("[[") {
+        Some(rest) => (rest.strip_suffix("]]")?, true),
+        None => (line.strip_prefix('[')?.strip_suffix(']')?, false),
+    };
+    let plain = !name.trim().is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | ' ' | '"'));
+    plain.then_some((name, doubled))
+}
+
+/// Whether `key` reads as a key in any of the formats
+fn is_key(key: &str) -> bool {
+    let key = key.trim_matches('"');
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Weighted line counts for each format
+#[derive(Default)]
+struct Votes {
+    up: u32,
+    yaml: u32,
+    toml: u32,
+    ini: u32,
+    /// `[section]` headers, which TOML and INI share
+    sections: u32,
+}
+
+impl Votes {
+    fn count(&mut self, line: &str) {
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        if line.starts_with(';') {
+            self.ini += 1;
+            return;
+        }
+        if line == "---" || line == "..." || line == "-" || line.starts_with("- ") {
+            self.yaml += 1;
+            return;
+        }
+        if let Some((_, doubled)) = section(line) {
+            if doubled {
+                self.toml += 3;
+            } else {
+                self.sections += 1;
+            }
+            return;
+        }
+        if matches!(line, "}" | "]" | "```") {
+            self.up += 2;
+            return;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if is_key(key.trim()) {
+                let value = value.trim();
+                let typed = value.starts_with(['"', '\'', '[', '{'])
+                    || matches!(value, "true" | "false")
+                    || value.parse::<f64>().is_ok();
+                if typed {
+                    self.toml += 2;
+                } else {
+                    self.ini += 2;
+                }
+                return;
+            }
+        }
+        let (key, rest) = match line.split_once(char::is_whitespace) {
+            Some((key, rest)) => (key, rest.trim()),
+            None => (line, ""),
+        };
+        if key.len() > 1 && key.ends_with(':') && is_key(&key[..key.len() - 1]) {
+            self.yaml += 2;
+            return;
+        }
+        let annotated = key
+            .split_once('!')
+            .is_some_and(|(name, annotation)| is_key(name) && is_key(annotation));
+        let opens = rest.ends_with('{') || rest.ends_with('[') || rest.starts_with("```");
+        if annotated || opens {
+            self.up += 2;
+        } else if is_key(key) {
+            self.up += 1;
+        }
+    }
+
+    fn confidence(&self) -> Confidence {
+        // Headers go to whichever of TOML and INI their pairs look like
+        let (toml, ini) = if self.toml > self.ini {
+            (self.toml + self.sections, self.ini)
+        } else {
+            (self.toml, self.ini + self.sections)
+        };
+        let others = [
+            (Lookalike::Yaml, self.yaml),
+            (Lookalike::Toml, toml),
+            (Lookalike::Ini, ini),
+        ];
+        let total = self.up + others.iter().map(|(_, votes)| votes).sum::<u32>();
+        let lookalike = others
+            .iter()
+            .filter(|(_, votes)| *votes > 0)
+            .max_by_key(|(_, votes)| *votes)
+            .map(|(format, _)| *format);
+        let score = match total {
+            0 => 0.5,
+            total => self.up as f32 / total as f32,
+        };
+        Confidence { score, lookalike }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        let up = "\
+# service
+name api
+port!int 8080
+tags [web, api]
+server {
+  host localhost
+}
+notes ```
+key: value
+[section]
+```
+";
+        let confidence = sniff(up);
+        assert_eq!(confidence.score, 1.0);
+        assert_eq!(confidence.lookalike, None);
+
+        let yaml = "---\nname: api\nserver:\n  host: localhost\n  ports:\n    - 8080\n";
+        let confidence = sniff(yaml);
+        assert_eq!(confidence.score, 0.0);
+        assert_eq!(confidence.lookalike, Some(Lookalike::Yaml));
+
+        let toml = "title = \"api\"\n\n[server]\nport = 8080\n\n[[hosts]]\nname = \"a\"\n";
+        assert_eq!(sniff(toml).lookalike, Some(Lookalike::Toml));
+        assert!(!sniff(toml).is_up());
+
+        let ini = "[server]\nhost=localhost\nmode=fast\n";
+        assert_eq!(sniff(ini).lookalike, Some(Lookalike::Ini));
+        assert!(!sniff(ini).is_up());
+
+        assert_eq!(sniff("{\"a\": 1}").lookalike, Some(Lookalike::Json));
+        assert_eq!(sniff("\u{feff}[1, 2]").lookalike, Some(Lookalike::Json));
+
+        // Nothing to go by
+        assert_eq!(sniff("").score, 0.5);
+        assert_eq!(sniff("# only a comment\n").score, 0.5);
+
+        // Mostly UP, with a line that looks like YAML
+        let mixed = sniff("name api\nport!int 8080\nurl: x\n");
+        assert!(mixed.is_up());
+        assert_eq!(mixed.lookalike, Some(Lookalike::Yaml));
+    }
+}
@@ -0,0 +1,548 @@
+//! Loading documents from files and tracking where nodes came from
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::offset_in;
+use crate::{Document, ErrorKind, Location, Node, ParseError, Parser, Value};
+
+/// Where a node was defined
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    /// Source file, if the document was loaded from one
+    pub file: Option<Arc<Path>>,
+    /// 1-based line number of the node's key
+    pub line: usize,
+}
+
+impl Origin {
+    pub(crate) fn line(line: usize) -> Self {
+        Self { file: None, line }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+/// The text of a node in its source, recorded by [`Parser::spans`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// 1-based line number of the node's key
+    pub start_line: usize,
+    /// 1-based line number of the node's last line, such as the `}` that
+    /// closes a block
+    pub end_line: usize,
+    /// Byte offsets of the node in the parsed text (or stream), from the
+    /// start of its key to the end of its last line without the line ending
+    pub bytes: Range<usize>,
+}
+
+impl Span {
+    /// Span of the node whose key is on `line` (the `line_num`th line of
+    /// `input`, 0-based) and which ends before `next`
+    pub(crate) fn of(input: &str, line_num: usize, line: &str, next: Option<&str>) -> Option<Self> {
+        let start = offset_in(input, line.trim_start())?;
+        let rest = match next.and_then(|next| offset_in(input, next)) {
+            Some(next) => &input[start..next],
+            None => &input[start..],
+        };
+        let text = rest.trim_end();
+        Some(Self {
+            start_line: line_num + 1,
+            end_line: line_num + 1 + text.matches('\n').count(),
+            bytes: start..start + text.len(),
+        })
+    }
+}
+
+impl Parser {
+    /// Parse a document held as bytes, such as a network buffer or a
+    /// memory-mapped file
+    ///
+    /// The bytes must be UTF-8. If they are not, the error is an
+    /// [`ErrorKind::InvalidEncoding`] located at the first invalid byte,
+    /// with the line around it decoded lossily for the snippet.
+    ///
+    /// A byte order mark, as Windows tools often write, is skipped. Input
+    /// in UTF-16, found by its byte order mark or by the zero bytes of its
+    /// first character, is decoded with the `utf16` feature and an
+    /// [`ErrorKind::InvalidEncoding`] error without it. Once decoded, the
+    /// byte offsets of spans and errors count from after the byte order
+    /// mark, in the UTF-8 text.
+    ///
+    /// ```
+    /// use uplang::{ErrorKind, Parser};
+    ///
+    /// let doc = Parser::new().parse_bytes(b"name caf\xc3\xa9").unwrap();
+    /// assert_eq!(doc.get_str("name"), Some("café"));
+    ///
+    /// let err = Parser::new().parse_bytes(b"a 1\nname caf\xe9\n").unwrap_err();
+    /// assert_eq!(err.kind, ErrorKind::InvalidEncoding);
+    /// assert_eq!(err.message, "invalid UTF-8 byte 0xE9");
+    /// let location = err.location.unwrap();
+    /// assert_eq!((location.line, location.column), (2, 9));
+    ///
+    /// let doc = Parser::new().parse_bytes(b"\xef\xbb\xbfname app").unwrap();
+    /// assert_eq!(doc.nodes[0].key, "name");
+    /// ```
+    pub fn parse_bytes(&self, input: &[u8]) -> Result<Document, ParseError> {
+        self.limits.check_size(input.len())?;
+        self.parse_document(&decode(input)?)
+    }
+
+    /// Parse a file, recording it as the origin of every node
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<Document, ParseError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| file_error(path, e))?;
+        self.parse_file_bytes(path, &bytes)
+    }
+
+    /// Parse the contents of the file at `path`, already read as `bytes`
+    pub(crate) fn parse_file_bytes(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+    ) -> Result<Document, ParseError> {
+        let file: Arc<Path> = Arc::from(path);
+        let content = decode(bytes).map_err(|mut e| {
+            if let Some(location) = &mut e.location {
+                location.file = Some(Arc::clone(&file));
+            }
+            e
+        })?;
+        // Includes in the file are resolved relative to it
+        let mut parser = self.clone();
+        parser
+            .includes
+            .enter(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        let mut doc = parser.parse_document(&content).map_err(|mut e| {
+            match &mut e.location {
+                Some(location) => {
+                    location.file.get_or_insert(file.clone());
+                }
+                None => e.message = format!("{}: {}", path.display(), e.message),
+            }
+            e
+        })?;
+
+        for node in &mut doc.nodes {
+            set_file(node, &file);
+        }
+        Ok(doc)
+    }
+
+    /// Parse several files into one document, concatenating their nodes in order
+    ///
+    /// Each node keeps the file and line it was defined at in
+    /// [`Node::origin`](crate::Node::origin).
+    pub fn parse_files<I, P>(&self, paths: I) -> Result<Document, ParseError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut doc = Document::new();
+        for path in paths {
+            doc.nodes.extend(self.parse_file(path)?.nodes);
+        }
+        Ok(doc)
+    }
+
+    /// Parse every `*.up` file in a directory (e.g. `conf.d/`) in file name order
+    pub fn parse_dir(&self, dir: impl AsRef<Path>) -> Result<Document, ParseError> {
+        let dir = dir.as_ref();
+        let io_error =
+            |e: std::io::Error| ParseError::new(ErrorKind::Io, format!("{}: {}", dir.display(), e));
+
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "up") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        self.parse_files(paths)
+    }
+}
+
+/// The byte order mark some editors write at the start of a text file
+pub(crate) const BOM: char = '\u{FEFF}';
+
+/// The text of `input` without any byte order mark: UTF-8, or UTF-16 with
+/// the `utf16` feature
+fn decode(input: &[u8]) -> Result<Cow<'_, str>, ParseError> {
+    if let Some(big_endian) = utf16_order(input) {
+        return decode_utf16(input, big_endian).map(Cow::Owned);
+    }
+    let bom = [0xEF, 0xBB, 0xBF];
+    match input.strip_prefix(&bom) {
+        Some(text) => decode_utf8(text).map(Cow::Borrowed).map_err(|mut e| {
+            // Locate the error in the whole input
+            if let Some(location) = &mut e.location {
+                location.span = location.span.start + bom.len()..location.span.end + bom.len();
+            }
+            e
+        }),
+        None => decode_utf8(input).map(Cow::Borrowed),
+    }
+}
+
+/// Whether `input` is UTF-16, and if so whether it is big-endian
+///
+/// Without a byte order mark, UTF-16 text starting with an ASCII character
+/// has a zero byte before or after it, which UTF-8 text never has.
+fn utf16_order(input: &[u8]) -> Option<bool> {
+    match input {
+        [0xFE, 0xFF, ..] => Some(true),
+        [0xFF, 0xFE, ..] => Some(false),
+        [0, b, ..] if *b != 0 && b.is_ascii() => Some(true),
+        [b, 0, ..] if *b != 0 && b.is_ascii() => Some(false),
+        _ => None,
+    }
+}
+
+/// The text of UTF-16 `input`, without its byte order mark
+#[cfg(feature = "utf16")]
+fn decode_utf16(input: &[u8], big_endian: bool) -> Result<String, ParseError> {
+    let units = input.chunks_exact(2).map(|pair| match big_endian {
+        true => u16::from_be_bytes([pair[0], pair[1]]),
+        false => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+    let mut text = String::with_capacity(input.len() / 2);
+    let mut offset = 0;
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                offset += 2 * c.len_utf16();
+                text.push(c);
+            }
+            Err(e) => {
+                let message = format!(
+                    "invalid UTF-16: unpaired surrogate 0x{:04X}",
+                    e.unpaired_surrogate()
+                );
+                return Err(encoding_error(message, &text, offset..offset + 2));
+            }
+        }
+    }
+    if input.len() % 2 != 0 {
+        let end = input.len();
+        let message = "incomplete UTF-16 character at end of input".to_string();
+        return Err(encoding_error(message, &text, end - 1..end));
+    }
+    match text.strip_prefix(BOM) {
+        Some(rest) => Ok(rest.to_string()),
+        None => Ok(text),
+    }
+}
+
+#[cfg(not(feature = "utf16"))]
+fn decode_utf16(_input: &[u8], _big_endian: bool) -> Result<String, ParseError> {
+    Err(ParseError::new(
+        ErrorKind::InvalidEncoding,
+        "the input is UTF-16, which needs the `utf16` feature; convert it to UTF-8",
+    ))
+}
+
+/// An encoding error at bytes `span` of the input, after the text `before`
+#[cfg(feature = "utf16")]
+fn encoding_error(message: String, before: &str, span: Range<usize>) -> ParseError {
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before[line_start..].trim_start_matches(BOM);
+    let mut err = ParseError::new(ErrorKind::InvalidEncoding, message);
+    err.location = Some(Location {
+        file: None,
+        line: before.matches('\n').count() + 1,
+        column: line.chars().count() + 1,
+        span,
+        snippet: line.trim_end_matches('\r').to_string(),
+    });
+    err
+}
+
+/// The text of `input`, or an error located at its first byte that is not
+/// part of a UTF-8 character
+fn decode_utf8(input: &[u8]) -> Result<&str, ParseError> {
+    let e = match std::str::from_utf8(input) {
+        Ok(text) => return Ok(text),
+        Err(e) => e,
+    };
+    let start = e.valid_up_to();
+    let end = start + e.error_len().unwrap_or(input.len() - start);
+    let message = match e.error_len() {
+        Some(_) => format!("invalid UTF-8 byte 0x{:02X}", input[start]),
+        None => "incomplete UTF-8 character at end of input".to_string(),
+    };
+
+    // Everything before the bad byte is valid
+    let before = std::str::from_utf8(&input[..start]).unwrap_or_default();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(input.len(), |i| start + i);
+    let snippet = String::from_utf8_lossy(&input[line_start..line_end]);
+    let mut err = ParseError::new(ErrorKind::InvalidEncoding, message);
+    err.location = Some(Location {
+        file: None,
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+        span: start..end,
+        snippet: snippet.trim_end_matches('\r').to_string(),
+    });
+    Err(err)
+}
+
+/// An error reading the file at `path`
+pub(crate) fn file_error(path: &Path, e: std::io::Error) -> ParseError {
+    ParseError::new(ErrorKind::Io, format!("{}: {}", path.display(), e))
+}
+
+/// Record `file` as the source of `node` and the nodes nested in it, except
+/// those already known to come from an included file
+pub(crate) fn set_file(node: &mut Node, file: &Arc<Path>) {
+    update_nodes(node, &mut |node| {
+        if let Some(origin) = &mut node.origin {
+            origin.file.get_or_insert_with(|| Arc::clone(file));
+        }
+    });
+}
+
+/// Move the origins and spans of nodes parsed from part of a text `lines`
+/// lines and `bytes` bytes down, leaving nodes from included files where
+/// they are
+pub(crate) fn shift_lines(node: &mut Node, lines: usize, bytes: usize) {
+    update_nodes(node, &mut |node| {
+        let Some(origin) = node.origin.as_mut().filter(|o| o.file.is_none()) else {
+            return;
+        };
+        origin.line += lines;
+        if let Some(span) = &mut node.span {
+            span.start_line += lines;
+            span.end_line += lines;
+            span.bytes = span.bytes.start + bytes..span.bytes.end + bytes;
+        }
+    });
+}
+
+/// Forget the spans of the nodes in a value copied from elsewhere in the
+/// text, such as the value of an anchor
+pub(crate) fn clear_spans(value: &mut Value) {
+    update_nodes_in(value, &mut |node| node.span = None);
+}
+
+/// Apply `update` to `node` and to every node nested in it
+fn update_nodes(node: &mut Node, update: &mut impl FnMut(&mut Node)) {
+    update(node);
+    update_nodes_in(&mut node.value, update);
+}
+
+fn update_nodes_in(value: &mut Value, update: &mut impl FnMut(&mut Node)) {
+    match value {
+        Value::Block(block) => block
+            .nodes_mut()
+            .for_each(|node| update_nodes(node, update)),
+        Value::List(items) => items
+            .iter_mut()
+            .for_each(|item| update_nodes_in(item, update)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(format!("uplang-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_dir_records_origins() {
+        let dir = temp_dir("source");
+        fs::write(
+            dir.join("20-db.up"),
+            "# database\n\ndatabase {\nhost db\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("10-app.up"), "name app\n").unwrap();
+        fs::write(dir.join("README"), "not UP").unwrap();
+
+        let doc = Parser::new().parse_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(doc.nodes.len(), 2);
+        assert_eq!(doc.nodes[0].key, "name");
+        let origin = doc.nodes[1].origin.as_ref().unwrap();
+        assert_eq!(origin.line, 3);
+        assert_eq!(
+            origin.to_string(),
+            format!("{}:3", dir.join("20-db.up").display())
+        );
+        let Value::Block(database) = &doc.nodes[1].value else {
+            panic!("Expected block");
+        };
+        let nested = database.get_node("host").unwrap().origin.as_ref().unwrap();
+        assert_eq!(nested.line, 4);
+        assert_eq!(nested.file, origin.file);
+    }
+
+    #[test]
+    fn test_parse_file_error_location() {
+        let dir = temp_dir("source-error");
+        let path = dir.join("bad.up");
+        fs::write(&path, "name app\nport!int eighty\n").unwrap();
+
+        let err = Parser::new().parse_file(&path).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        let location = err.location.as_ref().unwrap();
+        assert_eq!(location.file.as_deref(), Some(path.as_path()));
+        assert!(err
+            .to_string()
+            .contains(&format!("--> {}:2:10", path.display())));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let err = Parser::new().parse_bytes(b"caf\xc3").unwrap_err();
+        assert_eq!(err.message, "incomplete UTF-8 character at end of input");
+        assert_eq!(err.location.as_ref().unwrap().span, 3..4);
+
+        let dir = temp_dir("source-utf8");
+        let path = dir.join("latin1.up");
+        fs::write(&path, b"name app\ncity M\xfcnchen\n").unwrap();
+        let err = Parser::new().parse_file(&path).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(err.kind, ErrorKind::InvalidEncoding);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Invalid encoding: invalid UTF-8 byte 0xFC\n --> {}:2:7\n  |\n2 | city M\u{FFFD}nchen\n  |       ^",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_byte_order_marks() {
+        let doc = Parser::new()
+            .parse_bytes(b"\xef\xbb\xbfname app\n")
+            .unwrap();
+        assert_eq!(doc.nodes[0].key, "name");
+        let err = Parser::new()
+            .parse_bytes(b"\xef\xbb\xbfa 1\nb \xff\n")
+            .unwrap_err();
+        let location = err.location.unwrap();
+        assert_eq!((location.line, location.column), (2, 3));
+        assert_eq!(location.span, 9..10);
+
+        let utf16 = |text: &str, big_endian: bool| -> Vec<u8> {
+            text.encode_utf16()
+                .flat_map(|unit| match big_endian {
+                    true => unit.to_be_bytes(),
+                    false => unit.to_le_bytes(),
+                })
+                .collect()
+        };
+        let inputs = [
+            utf16("\u{FEFF}name café\r\nport!int 80\r\n", false),
+            utf16("\u{FEFF}name café\nport!int 80\n", true),
+            utf16("name café\nport!int 80\n", false),
+            utf16("name café\nport!int 80\n", true),
+        ];
+        for input in &inputs {
+            let result = Parser::new().parse_bytes(input);
+            if cfg!(feature = "utf16") {
+                let doc = result.unwrap();
+                assert_eq!(doc.get_str("name"), Some("café"));
+                assert_eq!(doc.get_int("port"), Some(80));
+            } else {
+                let err = result.unwrap_err();
+                assert_eq!(err.kind, ErrorKind::InvalidEncoding);
+                assert!(err.message.contains("`utf16` feature"));
+            }
+        }
+
+        if cfg!(feature = "utf16") {
+            let mut input = utf16("\u{FEFF}a 1\nb x", false);
+            input.extend([0x00, 0xD8]);
+            let err = Parser::new().parse_bytes(&input).unwrap_err();
+            assert_eq!(err.message, "invalid UTF-16: unpaired surrogate 0xD800");
+            let location = err.location.unwrap();
+            assert_eq!((location.line, location.column), (2, 4));
+            assert_eq!(location.span, 16..18);
+            assert_eq!(location.snippet, "b x");
+
+            let err = Parser::new().parse_bytes(b"a\x00b").unwrap_err();
+            assert_eq!(err.message, "incomplete UTF-16 character at end of input");
+        }
+    }
+
+    #[test]
+    fn test_parse_file_errors_name_the_file() {
+        let err = Parser::new().parse_file("does/not/exist.up").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+        assert!(err.to_string().contains("does/not/exist.up"));
+    }
+
+    #[test]
+    fn test_spans() {
+        let input = "name app\r\nserver {\n  port!int 80\n  hosts [\n    {\n      host a\n    }\n  ]\n}\nnote ```\n  text\n```\n\nlast \\\n  continued\n";
+        let doc = Parser::new().spans(true).parse_document(input).unwrap();
+        let text = |node: &Node| {
+            let span = node.span.as_ref().unwrap();
+            (span.start_line, span.end_line, &input[span.bytes.clone()])
+        };
+        assert_eq!(text(&doc.nodes[0]), (1, 1, "name app"));
+        let (start, end, server) = text(&doc.nodes[1]);
+        assert_eq!((start, end), (2, 9));
+        assert!(server.starts_with("server {\n") && server.ends_with("  ]\n}"));
+        assert_eq!(text(&doc.nodes[2]), (10, 12, "note ```\n  text\n```"));
+        assert_eq!(text(&doc.nodes[3]), (14, 15, "last \\\n  continued"));
+        let Value::Block(block) = &doc.nodes[1].value else {
+            panic!("expected a block");
+        };
+        assert_eq!(
+            block.get_node("port").map(text),
+            Some((3, 3, "port!int 80"))
+        );
+        let host = &doc["server"]["hosts"][0]
+            .as_block()
+            .unwrap()
+            .get_node("host");
+        assert_eq!(host.map(text), Some((6, 6, "host a")));
+
+        // Off by default
+        assert!(Parser::new().parse_document(input).unwrap().nodes[0]
+            .span
+            .is_none());
+
+        // Streams count from the start of the stream
+        let parser = Parser::new().spans(true);
+        let nodes: Vec<Node> = parser
+            .read_nodes(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (streamed, parsed) in nodes.iter().zip(&doc.nodes) {
+            assert_eq!(streamed.span, parsed.span);
+        }
+    }
+}
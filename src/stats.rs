@@ -0,0 +1,88 @@
+//! Key usage statistics for config audits
+
+use std::collections::BTreeMap;
+
+use crate::{walk, Document};
+
+/// Summary of the keys and value types used in a document
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of values at each key path, with list indices collapsed
+    /// (`servers[].host` counts the `host` of every server)
+    pub keys: BTreeMap<String, usize>,
+    /// Number of values of each type, keyed by [`Value::type_name`](crate::Value::type_name)
+    pub types: BTreeMap<&'static str, usize>,
+    /// Exact paths defined more than once, with how often they occur
+    pub duplicates: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    /// Total number of values in the document, including nested ones
+    pub fn total(&self) -> usize {
+        self.types.values().sum()
+    }
+}
+
+impl Document {
+    /// Collect key usage statistics for this document
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        let mut exact = BTreeMap::<String, usize>::new();
+
+        walk::walk(self, &mut |path, value| {
+            *exact.entry(path.to_string()).or_default() += 1;
+            *stats.keys.entry(collapse_indices(path)).or_default() += 1;
+            *stats.types.entry(value.type_name()).or_default() += 1;
+        });
+
+        stats.duplicates = exact.into_iter().filter(|(_, n)| *n > 1).collect();
+        stats
+    }
+}
+
+/// Replace list indices with `[]` so all items of a list share one path
+fn collapse_indices(path: &str) -> String {
+    let mut collapsed = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                collapsed.push_str("[]");
+            }
+            ']' => in_index = false,
+            c if !in_index => collapsed.push(c),
+            _ => {}
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    #[test]
+    fn test_stats() {
+        let input = r#"
+name app
+name app-2
+servers [
+{
+host a
+}
+{
+host b
+}
+]
+"#;
+        let stats = parse(input).unwrap().stats();
+        assert_eq!(stats.keys["name"], 2);
+        assert_eq!(stats.keys["servers[].host"], 2);
+        assert_eq!(stats.types["string"], 4);
+        assert_eq!(stats.types["block"], 2);
+        assert_eq!(stats.total(), 7);
+        assert_eq!(stats.duplicates.len(), 1);
+        assert_eq!(stats.duplicates["name"], 2);
+    }
+}
@@ -0,0 +1,894 @@
+//! Parsing from readers one top-level node, record, or table row at a time
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Read};
+
+use crate::comment::Comments;
+use crate::descent::Reader;
+use crate::inline::is_inline_block;
+use crate::interpolate::Scope;
+use crate::lexer::Lexer;
+use crate::quote;
+use crate::source::{shift_lines, BOM};
+use crate::{
+    continues, keyed_item, let_definition, Document, ErrorKind, Node, ParseError, Parser, Value,
+};
+
+impl Parser {
+    /// Parse a document from a reader, such as a file or piped stdin
+    ///
+    /// Equivalent to collecting [`read_nodes`](Self::read_nodes) into a
+    /// document; only one top-level node is buffered as text at a time.
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> Result<Document, ParseError> {
+        let nodes = self.read_nodes(reader).collect::<Result<_, _>>()?;
+        Ok(Document { nodes })
+    }
+
+    /// Iterate over the top-level nodes of a document as they are read
+    ///
+    /// Each node is produced as soon as its last line has been read, so a
+    /// large file or a stream can be processed without holding all of it in
+    /// memory. Line numbers in origins and errors count from the start of
+    /// the stream. Iteration stops after the first error.
+    pub fn read_nodes<R: BufRead>(&self, reader: R) -> Nodes<'_, R> {
+        Nodes(ReadChunks::new(self, reader))
+    }
+
+    /// Iterate over the records of an append-only log, such as a file of
+    /// events written one after another
+    ///
+    /// Each record is a small document, separated from the next by one or
+    /// more blank lines; blank lines inside a block, list, or multiline
+    /// string do not end a record. Records are parsed independently, so
+    /// `@let` definitions do not carry over, and an invalid record yields
+    /// an error without stopping the iteration. Line numbers in origins and
+    /// errors count from the start of the stream.
+    ///
+    /// ```
+    /// let log = "event start\nat 1\n\nevent stop\nat 2\n";
+    /// let parser = uplang::Parser::new();
+    /// let events: Vec<_> = parser
+    ///     .read_records(log.as_bytes())
+    ///     .map(|record| record.unwrap().get_str("event").unwrap().to_string())
+    ///     .collect();
+    /// assert_eq!(events, ["start", "stop"]);
+    /// ```
+    pub fn read_records<R: BufRead>(&self, reader: R) -> Records<'_, R> {
+        Records {
+            parser: self,
+            reader,
+            lines_read: 0,
+            bytes_read: 0,
+            finished: false,
+        }
+    }
+
+    /// Iterate over the rows of the table `key` in a stream, such as a
+    /// large data file, one row at a time
+    ///
+    /// Only the line being parsed is held in memory, so a table with
+    /// millions of rows is read in flat memory. Cells are typed by their
+    /// column's annotation, as in [`parse_document`](Self::parse_document).
+    /// Top-level nodes before the table are skipped without being parsed,
+    /// `@let` variables defined before it can be used in its cells, and
+    /// reading stops at the end of the first top-level table named `key`.
+    /// A stream without one yields an [`ErrorKind::UnexpectedEof`]. Line
+    /// numbers in errors count from the start of the stream, and iteration
+    /// stops after the first error.
+    ///
+    /// ```
+    /// use uplang::Value;
+    ///
+    /// let data = "name metrics\nsamples!table {\n  columns [host, load!float]\n  rows [\n    [a, 0.5]\n    [b, 1.25]\n  ]\n}\n";
+    /// let parser = uplang::Parser::new();
+    /// let mut rows = parser.read_table(data.as_bytes(), "samples");
+    /// let first = rows.next().unwrap().unwrap();
+    /// assert_eq!(rows.columns().collect::<Vec<_>>(), ["host", "load"]);
+    /// assert_eq!(first, [Value::String("a".into()), Value::Float(0.5)]);
+    /// assert_eq!(rows.count(), 1);
+    /// ```
+    pub fn read_table<R: BufRead>(&self, reader: R, key: &str) -> Rows<'_, R> {
+        Rows {
+            parser: self,
+            reader,
+            key: key.to_string(),
+            scope: self.root_scope(),
+            columns: None,
+            state: TableState::Seeking(Boundary::new(&self.comments)),
+            rows: 0,
+            line: String::new(),
+            lines_read: 0,
+            bytes_read: 0,
+        }
+    }
+}
+
+/// Iterator over the top-level nodes of a stream, see [`Parser::read_nodes`]
+pub struct Nodes<'p, R>(ReadChunks<'p, R, Node>);
+
+impl<R: BufRead> Iterator for Nodes<'_, R> {
+    type Item = Result<Node, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// What a stream is read as, a top-level node's lines at a time: its
+/// nodes, or their events
+pub(crate) trait Streamed: Sized {
+    /// Read the items of `reader`'s input, defining `@let` variables in
+    /// `scope`
+    fn read(
+        reader: &mut Reader<'_, '_>,
+        scope: &mut Scope<'_>,
+        items: &mut Vec<Self>,
+    ) -> Result<(), ParseError>;
+
+    /// Count the lines of the item from `lines` lines and `bytes` bytes
+    /// further into the stream
+    fn shift(&mut self, lines: usize, bytes: usize);
+}
+
+impl Streamed for Node {
+    fn read(
+        reader: &mut Reader<'_, '_>,
+        scope: &mut Scope<'_>,
+        items: &mut Vec<Self>,
+    ) -> Result<(), ParseError> {
+        reader.nodes(items, scope)
+    }
+
+    fn shift(&mut self, lines: usize, bytes: usize) {
+        shift_lines(self, lines, bytes);
+    }
+}
+
+/// The items of a blocking reader, read a top-level node at a time
+pub(crate) struct ReadChunks<'p, R, T> {
+    chunks: NodeChunks<'p, T>,
+    reader: R,
+    finished: bool,
+}
+
+impl<'p, R: BufRead, T: Streamed> ReadChunks<'p, R, T> {
+    pub(crate) fn new(parser: &'p Parser, reader: R) -> Self {
+        Self {
+            chunks: NodeChunks::new(parser),
+            reader,
+            finished: false,
+        }
+    }
+
+    /// The next item read and not yet taken, without reading more
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.chunks.pop()
+    }
+
+    /// Read the next line, parsing the node it ends, if any
+    fn read_line(&mut self) -> Result<(), ParseError> {
+        let start = self.chunks.buffer().len();
+        let read = (&mut self.reader)
+            .take(self.chunks.remaining())
+            .read_line(self.chunks.buffer())
+            .map_err(|e| ParseError::new(ErrorKind::Io, e.to_string()))?;
+        if read == 0 {
+            self.finished = true;
+            return self.chunks.finish();
+        }
+        self.chunks.line_read(start)
+    }
+}
+
+impl<R: BufRead, T: Streamed> Iterator for ReadChunks<'_, R, T> {
+    type Item = Result<T, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.chunks.pop() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.read_line() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// The top-level nodes of a stream, each parsed once its last line has
+/// been read, for the blocking and async readers of nodes and events
+pub(crate) struct NodeChunks<'p, T = Node> {
+    parser: &'p Parser,
+    scope: Scope<'p>,
+    queue: VecDeque<T>,
+    /// Lines read since the last node ended
+    chunk: String,
+    boundary: Boundary,
+    lines: usize,
+    /// Lines and bytes before the chunk
+    lines_read: usize,
+    bytes_read: usize,
+}
+
+impl<'p, T: Streamed> NodeChunks<'p, T> {
+    pub(crate) fn new(parser: &'p Parser) -> Self {
+        Self {
+            parser,
+            scope: parser.root_scope(),
+            queue: VecDeque::new(),
+            chunk: String::new(),
+            boundary: Boundary::new(&parser.comments),
+            lines: 0,
+            lines_read: 0,
+            bytes_read: 0,
+        }
+    }
+
+    /// Bytes the next line may take: one more than the parser's limits
+    /// allow, so that passing them is noticed
+    pub(crate) fn remaining(&self) -> u64 {
+        self.parser
+            .limits
+            .remaining(self.bytes_read + self.chunk.len())
+    }
+
+    /// Check that a next line of `len` bytes stays within the parser's
+    /// limits
+    pub(crate) fn check_size(&self, len: usize) -> Result<(), ParseError> {
+        let used = self.bytes_read + self.chunk.len() + len;
+        self.parser.limits.check_size(used)
+    }
+
+    /// The text read so far, to append the next line to
+    pub(crate) fn buffer(&mut self) -> &mut String {
+        &mut self.chunk
+    }
+
+    /// Note the line appended to the buffer from byte `start`, and parse
+    /// the node it ends, if any
+    pub(crate) fn line_read(&mut self, start: usize) -> Result<(), ParseError> {
+        if self.bytes_read == 0 {
+            self.bytes_read = skip_bom(&mut self.chunk);
+        }
+        self.check_size(0)?;
+        self.lines += 1;
+        let line = self.chunk[start..].trim_end_matches(['\n', '\r']);
+        if self.boundary.line(line) {
+            self.parse()?;
+        }
+        Ok(())
+    }
+
+    /// Parse the lines left at the end of the stream
+    pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
+        self.parse()
+    }
+
+    /// The next item parsed and not yet taken
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    /// Parse the lines read since the last node ended
+    fn parse(&mut self) -> Result<(), ParseError> {
+        let chunk = std::mem::take(&mut self.chunk);
+        self.boundary = Boundary::new(&self.parser.comments);
+        let (line_offset, byte_offset) = (self.lines_read, self.bytes_read);
+        self.lines_read += std::mem::take(&mut self.lines);
+        self.bytes_read += chunk.len();
+
+        let mut items = Vec::new();
+        let mut reader = Reader::new(self.parser, &chunk);
+        let result = T::read(&mut reader, &mut self.scope, &mut items);
+        for item in &mut items {
+            item.shift(line_offset, byte_offset);
+        }
+        self.queue.extend(items);
+
+        result.map_err(|mut e| {
+            if let Some(location) = e.location.as_mut().filter(|l| l.file.is_none()) {
+                location.line += line_offset;
+                location.span = location.span.start + byte_offset..location.span.end + byte_offset;
+            }
+            e
+        })
+    }
+}
+
+/// Iterator over the records of an append-only log, see
+/// [`Parser::read_records`]
+pub struct Records<'p, R> {
+    parser: &'p Parser,
+    reader: R,
+    lines_read: usize,
+    bytes_read: usize,
+    finished: bool,
+}
+
+impl<R: BufRead> Records<'_, R> {
+    /// Read the lines of the next record, with the blank lines before it;
+    /// `None` at the end of the stream
+    fn read_chunk(&mut self) -> Result<Option<(String, usize)>, ParseError> {
+        let mut chunk = String::new();
+        let mut boundary = Boundary::new(&self.parser.comments);
+        let mut lines = 0;
+        loop {
+            let start = chunk.len();
+            let limits = &self.parser.limits;
+            let read = (&mut self.reader)
+                .take(limits.remaining(start))
+                .read_line(&mut chunk)
+                .map_err(|e| ParseError::new(ErrorKind::Io, e.to_string()))?;
+            if read == 0 {
+                self.finished = true;
+                break;
+            }
+            if self.bytes_read == 0 {
+                self.bytes_read = skip_bom(&mut chunk);
+            }
+            limits.check_size(chunk.len())?;
+            lines += 1;
+            let line = chunk[start..].trim_end_matches(['\n', '\r']);
+            let blank = line.trim().is_empty();
+            let at_top = boundary.depth() == 0 && !boundary.in_fence() && !boundary.continuing();
+            if blank && at_top && !chunk[..start].trim().is_empty() {
+                break;
+            }
+            boundary.line(line);
+        }
+
+        let line_offset = self.lines_read;
+        self.lines_read += lines;
+        self.bytes_read += chunk.len();
+        if chunk.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((chunk, line_offset)))
+    }
+
+    /// Parse one record, counting lines and bytes from the stream start
+    fn parse(&self, chunk: &str, line_offset: usize) -> Result<Document, ParseError> {
+        let byte_offset = self.bytes_read - chunk.len();
+        let mut nodes = Vec::new();
+        let mut scope = self.parser.root_scope();
+        match self.parser.parse_nodes(chunk, &mut nodes, &mut scope) {
+            Ok(()) => {
+                for node in &mut nodes {
+                    shift_lines(node, line_offset, byte_offset);
+                }
+                Ok(Document { nodes })
+            }
+            Err(mut e) => {
+                if let Some(location) = e.location.as_mut().filter(|l| l.file.is_none()) {
+                    location.line += line_offset;
+                    location.span =
+                        location.span.start + byte_offset..location.span.end + byte_offset;
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<'_, R> {
+    type Item = Result<Document, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            match self.read_chunk() {
+                Ok(Some((chunk, line_offset))) => return Some(self.parse(&chunk, line_offset)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the rows of a table in a stream, see
+/// [`Parser::read_table`]
+pub struct Rows<'p, R> {
+    parser: &'p Parser,
+    reader: R,
+    key: String,
+    scope: Scope<'p>,
+    columns: Option<Vec<(String, Option<String>)>>,
+    state: TableState,
+    rows: usize,
+    /// The line being parsed, kept to reuse its buffer
+    line: String,
+    lines_read: usize,
+    bytes_read: usize,
+}
+
+/// How far [`Rows`] has read into the stream
+enum TableState {
+    /// Skipping the nodes before the table
+    Seeking(Boundary),
+    /// Inside the table, outside its rows
+    Table,
+    /// Inside the rows, which end at the given line
+    Rows(&'static str),
+    Done,
+}
+
+impl<R: BufRead> Rows<'_, R> {
+    /// Names of the table's columns, once its header has been read by the
+    /// first call to [`next`](Iterator::next)
+    pub fn columns(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().flatten().map(|(name, _)| name.as_str())
+    }
+
+    /// Read lines up to the next row; `None` at the end of the table
+    fn next_row(&mut self) -> Result<Option<Vec<Value>>, ParseError> {
+        let mut line = std::mem::take(&mut self.line);
+        let row = loop {
+            if !self.read_line(&mut line)? {
+                break match self.state {
+                    TableState::Seeking(_) => Err(ParseError::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("no table `{}` in the input", self.key),
+                    )),
+                    _ => self.parser.end_of_input(Value::Null, "table").map(|_| None),
+                };
+            }
+            match self.line(&line) {
+                Ok(None) => continue,
+                result => break result,
+            }
+        };
+        self.line = line;
+        row
+    }
+
+    /// Read the next line into `line`; `false` at the end of the stream
+    fn read_line(&mut self, line: &mut String) -> Result<bool, ParseError> {
+        line.clear();
+        let limits = &self.parser.limits;
+        let read = (&mut self.reader)
+            .take(limits.remaining(self.bytes_read))
+            .read_line(line)
+            .map_err(|e| ParseError::new(ErrorKind::Io, e.to_string()))?;
+        if self.bytes_read == 0 {
+            skip_bom(line);
+        }
+        self.bytes_read += read;
+        limits.check_size(self.bytes_read)?;
+        if read > 0 {
+            self.lines_read += 1;
+        }
+        Ok(read > 0)
+    }
+
+    /// Handle one line, returning the row it holds, if any; the end of the
+    /// table is an `Ok(None)` with the state set to done
+    fn line(&mut self, line: &str) -> Result<Option<Vec<Value>>, ParseError> {
+        let trimmed = self.parser.comments.strip(line).trim();
+        // Errors point into the line, counting from the start of the stream
+        let (line_offset, byte_offset) = (self.lines_read - 1, self.bytes_read - line.len());
+        let locate = |e: ParseError, text: &str| {
+            let mut e = e.locate(line, text);
+            if let Some(location) = e.location.as_mut().filter(|l| l.file.is_none()) {
+                location.line += line_offset;
+                location.span = location.span.start + byte_offset..location.span.end + byte_offset;
+            }
+            e
+        };
+        let located = |e: ParseError| locate(e, trimmed);
+        match &mut self.state {
+            TableState::Seeking(boundary) => {
+                if boundary.at_top() {
+                    if let Some(definition) = let_definition(trimmed) {
+                        return self
+                            .parser
+                            .define(
+                                &Lexer::new(line, &self.parser.comments),
+                                &mut self.scope,
+                                definition,
+                            )
+                            .map(|()| None)
+                            .map_err(located);
+                    }
+                    let (key_part, value) = self.parser.split_key_value(trimmed);
+                    let is_table = value == "{"
+                        && quote::split_key(key_part).is_ok_and(|(key, annotation)| {
+                            key == self.key && annotation == Some("table")
+                        });
+                    if is_table {
+                        self.parser.limits.check_depth(1).map_err(located)?;
+                        self.state = TableState::Table;
+                        return Ok(None);
+                    }
+                }
+                boundary.line(line.trim_end_matches(['\n', '\r']));
+            }
+            TableState::Table => {
+                if trimmed.is_empty() || self.parser.comments.is_comment(trimmed) {
+                    return Ok(None);
+                }
+                match self.parser.split_key_value(trimmed) {
+                    ("columns", header) if header.starts_with('[') && header.ends_with(']') => {
+                        let columns = self
+                            .parser
+                            .parse_columns(header)
+                            .map_err(|e| locate(e, header))?;
+                        self.columns = Some(columns);
+                    }
+                    ("rows", open @ ("{" | "[")) if self.columns.is_some() => {
+                        self.state = TableState::Rows(if open == "{" { "}" } else { "]" });
+                    }
+                    ("rows", "{" | "[") => {
+                        return Err(located(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            "table `rows` must come after its `columns`",
+                        )));
+                    }
+                    ("}", "") if self.columns.is_some() => {
+                        self.state = TableState::Done;
+                    }
+                    ("}", "") => {
+                        return Err(located(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            "table has no `columns`",
+                        )));
+                    }
+                    _ => {
+                        return Err(located(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            format!(
+                                "expected `columns [...]` or `rows {{` in table, found `{}`",
+                                trimmed
+                            ),
+                        )));
+                    }
+                }
+            }
+            TableState::Rows(close) if trimmed == *close => {
+                self.state = TableState::Table;
+            }
+            TableState::Rows(_)
+                if trimmed.is_empty() || self.parser.comments.is_comment(trimmed) => {}
+            TableState::Rows(_) => {
+                self.rows += 1;
+                let columns = self.columns.as_deref().unwrap_or_default();
+                return self
+                    .parser
+                    .limits
+                    .check_items(self.rows, "rows in a table")
+                    .and_then(|()| self.parser.parse_row(trimmed, columns, &self.scope))
+                    .map(Some)
+                    .map_err(located);
+            }
+            TableState::Done => {}
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Iterator for Rows<'_, R> {
+    type Item = Result<Vec<Value>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if matches!(self.state, TableState::Done) {
+            return None;
+        }
+        match self.next_row() {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => {
+                self.state = TableState::Done;
+                None
+            }
+            Err(e) => {
+                self.state = TableState::Done;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A block or list that is still open
+enum Open {
+    Block,
+    List,
+}
+
+/// Tracks nesting line by line, following the parser's rules, to find where
+/// each top-level node ends
+#[derive(Default)]
+pub(crate) struct Boundary {
+    open: Vec<Open>,
+    in_fence: bool,
+    /// Value joined so far from `\` continuation lines, and whether it is a
+    /// key's value (which may open a block) rather than a list item
+    continued: Option<(String, bool)>,
+    comments: Comments,
+}
+
+impl Boundary {
+    /// Track nesting for a parser that writes comments as `comments` says
+    pub(crate) fn new(comments: &Comments) -> Self {
+        Self {
+            comments: comments.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Feed the next line; `true` if it ends a top-level node or definition
+    pub(crate) fn line(&mut self, line: &str) -> bool {
+        let trimmed = line.trim();
+
+        if self.in_fence {
+            self.in_fence = trimmed != "```";
+            return self.at_top();
+        }
+
+        let line = self.comments.strip(line);
+        let trimmed = line.trim();
+        if let Some((mut value, is_key)) = self.continued.take() {
+            if continues(trimmed) {
+                value.push_str(&trimmed[..trimmed.len() - 1]);
+                self.continued = Some((value, is_key));
+                return false;
+            }
+            value.push_str(trimmed);
+            if is_key {
+                self.value(&value);
+            }
+            return self.at_top();
+        }
+
+        if trimmed.is_empty() || self.comments.is_comment(trimmed) {
+            return false;
+        }
+
+        match self.open.last() {
+            Some(Open::List) => {
+                if trimmed == "]" {
+                    self.open.pop();
+                } else if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                    // Inline list item
+                } else if is_inline_block(trimmed) {
+                    // Inline block item
+                } else if trimmed.starts_with('{') {
+                    self.open.push(Open::Block);
+                } else if trimmed == "[" {
+                    self.open.push(Open::List);
+                } else if trimmed.starts_with("```") {
+                    self.in_fence = true;
+                } else if let Some((key, value)) = keyed_item(trimmed) {
+                    return self.node(key, value);
+                } else if continues(trimmed) {
+                    self.continued = Some((String::new(), false));
+                }
+            }
+            Some(Open::Block) if trimmed == "}" => {
+                self.open.pop();
+            }
+            open => {
+                if let_definition(trimmed).is_some() {
+                    return self.at_top();
+                }
+                // Top-level lines are split untrimmed, as in the parser
+                let line = if open.is_none() { line } else { trimmed };
+                let key_end = quote::quoted_len(line).unwrap_or(0);
+                let (key, value) = match line[key_end..].find(char::is_whitespace) {
+                    Some(i) => (&line[..key_end + i], line[key_end + i..].trim()),
+                    None => (line, ""),
+                };
+                return self.node(key, value);
+            }
+        }
+        self.at_top()
+    }
+
+    /// Note what the value of a node line opens
+    fn node(&mut self, key: &str, value: &str) -> bool {
+        // `key!ref name {` merges a block into a copy of an anchor
+        if key.trim_start().ends_with("!ref") && value.ends_with('{') {
+            self.open.push(Open::Block);
+            return false;
+        }
+        if continues(value) {
+            self.continued = Some((value[..value.len() - 1].to_string(), true));
+            return false;
+        }
+        self.value(value);
+        self.at_top()
+    }
+
+    /// Note what a key's value opens
+    fn value(&mut self, value: &str) {
+        match value {
+            "{" => self.open.push(Open::Block),
+            "[" => self.open.push(Open::List),
+            v if v.starts_with("```") => self.in_fence = true,
+            _ => {}
+        }
+    }
+
+    /// Whether the next line starts a new top-level node
+    pub(crate) fn at_top(&self) -> bool {
+        self.open.is_empty() && !self.in_fence && self.continued.is_none()
+    }
+
+    /// Number of blocks and lists currently open
+    pub(crate) fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Whether the next line is inside a fenced multiline string
+    pub(crate) fn in_fence(&self) -> bool {
+        self.in_fence
+    }
+
+    /// Whether the next line continues a value ending in `\`
+    pub(crate) fn continuing(&self) -> bool {
+        self.continued.is_some()
+    }
+
+    /// Whether the innermost open value is a list
+    pub(crate) fn in_list(&self) -> bool {
+        matches!(self.open.last(), Some(Open::List))
+    }
+}
+
+/// Remove a byte order mark from the start of a stream's first line,
+/// returning how many bytes it took
+fn skip_bom(line: &mut String) -> usize {
+    if !line.starts_with(BOM) {
+        return 0;
+    }
+    line.drain(..BOM.len_utf8());
+    BOM.len_utf8()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::io::Cursor;
+
+    const INPUT: &str = "# Service\n@let base /srv\nname app\nserver {\nhost a \\\n  b\nports [\n{\nport 80\n}\n[1, 2]\n]\n}\nscript ```\n}\n```\npath ${base}/data\nusers!table {\ncolumns [id!int]\nrows {\n[1]\n}\n}\n\"my block\" {\nx 1\n}\n";
+
+    #[test]
+    fn test_read_nodes_matches_parse() {
+        let parser = Parser::new();
+        let nodes: Vec<Node> = parser
+            .read_nodes(Cursor::new(INPUT))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let keys: Vec<&str> = nodes.iter().map(|n| n.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["name", "server", "script", "path", "users", "my block"]
+        );
+
+        let expected = parse(INPUT).unwrap();
+        assert_eq!(Document { nodes }, expected);
+        assert_eq!(parser.parse_reader(INPUT.as_bytes()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_origins_and_errors_count_from_stream_start() {
+        let doc = Parser::new()
+            .parse_reader("a 1\n\nb {\nc 2\n}\n".as_bytes())
+            .unwrap();
+        assert_eq!(doc.nodes[1].origin.as_ref().unwrap().line, 3);
+        let Some(crate::Value::Block(b)) = doc.get("b") else {
+            panic!("Expected block");
+        };
+        assert_eq!(b.get_node("c").unwrap().origin.as_ref().unwrap().line, 4);
+
+        let input = "a 1\nb 2\nport!int eighty\nc 3\n";
+        let parser = Parser::new();
+        let mut nodes = parser.read_nodes(input.as_bytes());
+        assert!(nodes.next().unwrap().is_ok());
+        assert!(nodes.next().unwrap().is_ok());
+        let err = nodes.next().unwrap().unwrap_err();
+        assert_eq!(err, parse(input).unwrap_err());
+        assert!(nodes.next().is_none());
+    }
+
+    #[test]
+    fn test_streams_skip_byte_order_mark() {
+        let input = "\u{FEFF}name app\nport!int 80\n";
+        let parser = Parser::new().spans(true);
+        let doc = parser.parse_reader(input.as_bytes()).unwrap();
+        assert_eq!(doc.nodes[0].key, "name");
+        // Offsets still count the bytes of the stream
+        let span = doc.nodes[1].span.as_ref().unwrap();
+        assert_eq!(&input[span.bytes.clone()], "port!int 80");
+
+        let records: Vec<_> = parser.read_records(input.as_bytes()).collect();
+        assert_eq!(records[0].as_ref().unwrap().nodes[0].key, "name");
+    }
+
+    #[test]
+    fn test_read_records() {
+        let log = "\n\nevent start\nat 1\n\n\nevent deploy\nhosts [\n  a\n\n  b\n]\nnote ```\nfirst\n\nsecond\n```\n\nevent!int x\n\n# comment only\n\n@let who ops\nevent page\nto ${who}\n";
+        let parser = Parser::new();
+        let records: Vec<_> = parser.read_records(log.as_bytes()).collect();
+        assert_eq!(records.len(), 5);
+
+        let start = records[0].as_ref().unwrap();
+        assert_eq!(*start, parse("event start\nat 1").unwrap());
+        assert_eq!(start.nodes[1].origin.as_ref().unwrap().line, 4);
+
+        let deploy = records[1].as_ref().unwrap();
+        assert_eq!(deploy.nodes.len(), 3);
+        assert_eq!(deploy.get_str("note"), Some("first\n\nsecond"));
+
+        let err = records[2].as_ref().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        let location = err.location.as_ref().unwrap();
+        assert_eq!(location.line, 19);
+        assert_eq!(&log[location.span.clone()], "x");
+
+        assert!(records[3].as_ref().unwrap().nodes.is_empty());
+        assert_eq!(records[4].as_ref().unwrap().get_str("to"), Some("ops"));
+    }
+
+    #[test]
+    fn test_read_table() {
+        let data = "name data\nsamples [\n  x\n]\nother!table {\n  columns [a]\n  rows [\n    [1]\n  ]\n}\n@let region eu\nhosts!table {\n  # Hosts\n  columns [host, port!int, region]\n  rows {\n    [a, 80, ${region}]\n\n    [b, 8080, us]\n  }\n}\nafter 1\n";
+        let parser = Parser::new();
+        let rows: Vec<_> = parser
+            .read_table(data.as_bytes(), "hosts")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let Some(Value::Table { rows: parsed, .. }) = parse(data).unwrap().get("hosts").cloned()
+        else {
+            panic!("expected a table");
+        };
+        assert_eq!(rows, parsed);
+        assert_eq!(rows[0][2], Value::String("eu".into()));
+
+        let mut rows = parser.read_table(data.as_bytes(), "hosts");
+        assert_eq!(rows.columns().count(), 0);
+        rows.next();
+        assert_eq!(
+            rows.columns().collect::<Vec<_>>(),
+            ["host", "port", "region"]
+        );
+
+        // Errors count from the start of the stream and end the iteration
+        let bad = data.replace("8080", "eighty");
+        let mut rows = parser.read_table(bad.as_bytes(), "hosts");
+        assert!(rows.next().unwrap().is_ok());
+        let err = rows.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        let location = err.location.unwrap();
+        assert_eq!(location.line, 18);
+        assert_eq!(&bad[location.span], "[b, eighty, us]");
+        assert!(rows.next().is_none());
+
+        let err = parser
+            .read_table(data.as_bytes(), "samples")
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedEof);
+        assert_eq!(err.message, "no table `samples` in the input");
+
+        // An unclosed table ends with the input unless the parser is strict
+        let open = "t!table {\n  columns [a]\n  rows [\n    [1]\n";
+        assert_eq!(parser.read_table(open.as_bytes(), "t").count(), 1);
+        let strict = Parser::new().strict(true);
+        let rows: Vec<_> = strict.read_table(open.as_bytes(), "t").collect();
+        assert_eq!(
+            rows[1].as_ref().unwrap_err().kind,
+            ErrorKind::UnclosedDelimiter
+        );
+
+        let limited = Parser::new().limits(crate::Limits::new().max_items(1));
+        let rows: Vec<_> = limited.read_table(data.as_bytes(), "hosts").collect();
+        assert_eq!(rows[1].as_ref().unwrap_err().kind, ErrorKind::LimitExceeded);
+    }
+}
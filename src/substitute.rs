@@ -0,0 +1,389 @@
+//! Expanding `${...}` references to keys and environment variables after
+//! parsing, see [`Parser::with_interpolation`]
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::offset_in;
+use crate::interpolate::{self, Evaluated, Functions};
+use crate::{Document, ErrorKind, Location, Node, Origin, ParseError, Parser, Value};
+
+/// A string value of the document, whose references are expanded
+struct Entry<'a> {
+    /// Key path, e.g. `servers[0].host`
+    path: String,
+    text: &'a mut String,
+    /// Where the node holding the value was defined
+    origin: Option<&'a Origin>,
+}
+
+/// Expands the references of every entry, each one at most once
+struct Expander<'e, 'a> {
+    input: &'e str,
+    entries: &'e [Entry<'a>],
+    /// Entry for each key path; the last one when a top-level key repeats
+    paths: HashMap<&'e str, usize>,
+    /// Key paths of blocks, lists, and other values that are not text
+    others: HashSet<String>,
+    expanded: Vec<Option<String>>,
+    /// Entries being expanded, for finding cycles
+    stack: Vec<usize>,
+    functions: &'e Functions,
+    /// Whether names that are not keys, and `env:NAME`, read the environment
+    env_access: bool,
+}
+
+impl Parser {
+    /// Expand the references left in the string values of `doc`, parsed
+    /// from `input` without type coercion, and then coerce them
+    pub(crate) fn substitute(&self, input: &str, doc: &mut Document) -> Result<(), ParseError> {
+        let mut entries = Vec::new();
+        let mut others = HashSet::new();
+        for node in &mut doc.nodes {
+            let mut path = node.key.clone();
+            collect(
+                &mut path,
+                &mut node.value,
+                node.origin.as_ref(),
+                &mut entries,
+                &mut others,
+            );
+        }
+
+        let mut expander = Expander {
+            input,
+            entries: &entries,
+            paths: entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| (entry.path.as_str(), i))
+                .collect(),
+            others,
+            expanded: vec![None; entries.len()],
+            stack: Vec::new(),
+            functions: &self.functions,
+            env_access: self.env_access,
+        };
+        for i in 0..entries.len() {
+            expander.resolve(i)?;
+        }
+        let expanded = expander.expanded;
+        for (entry, text) in entries.iter_mut().zip(expanded) {
+            *entry.text = text.expect("every entry is expanded");
+        }
+
+        if self.coerce_types {
+            for node in &mut doc.nodes {
+                self.coerce_node(input, node)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Give a node's value, and those of nested nodes, its annotated type
+    fn coerce_node(&self, input: &str, node: &mut Node) -> Result<(), ParseError> {
+        match &mut node.value {
+            Value::String(text) => {
+                let text = std::mem::take(text);
+                node.value = self
+                    .parse_scalar(Cow::Owned(text), node.type_annotation.as_deref())
+                    .map_err(|e| match source_line(input, node.origin.as_ref()) {
+                        Some((_, line)) => e.locate(input, line.trim()),
+                        None => e,
+                    })?;
+            }
+            Value::Block(block) => {
+                for child in block.nodes_mut() {
+                    self.coerce_node(input, child)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Add the string values in `value` to `entries`, and the paths of the
+/// other values to `others`
+fn collect<'a>(
+    path: &mut String,
+    value: &'a mut Value,
+    origin: Option<&'a Origin>,
+    entries: &mut Vec<Entry<'a>>,
+    others: &mut HashSet<String>,
+) {
+    let len = path.len();
+    match value {
+        Value::String(text) => {
+            entries.push(Entry {
+                path: path.clone(),
+                text,
+                origin,
+            });
+            return;
+        }
+        Value::Block(block) => {
+            for node in block.nodes_mut() {
+                let Node {
+                    key, value, origin, ..
+                } = node;
+                path.push('.');
+                path.push_str(key);
+                collect(path, value, origin.as_ref(), entries, others);
+                path.truncate(len);
+            }
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                path.push_str(&format!("[{}]", i));
+                collect(path, item, origin, entries, others);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    others.insert(path.clone());
+}
+
+/// The line an origin points at in `input`, with its byte offset
+fn source_line<'i>(input: &'i str, origin: Option<&Origin>) -> Option<(usize, &'i str)> {
+    // Nodes from included files were not parsed from `input`
+    let origin = origin.filter(|origin| origin.file.is_none())?;
+    let line = input.lines().nth(origin.line.checked_sub(1)?)?;
+    Some((offset_in(input, line)?, line))
+}
+
+impl Expander<'_, '_> {
+    /// Expand entry `i`, unless it already has been
+    fn resolve(&mut self, i: usize) -> Result<String, ParseError> {
+        if let Some(text) = &self.expanded[i] {
+            return Ok(text.clone());
+        }
+        self.stack.push(i);
+        let text = self.expand(i)?;
+        self.stack.pop();
+        self.expanded[i] = Some(text.clone());
+        Ok(text)
+    }
+
+    /// Replace each `${...}` in entry `i` with its value, and each `$${`
+    /// with `${`
+    fn expand(&mut self, i: usize) -> Result<String, ParseError> {
+        let text = self.entries[i].text.as_str();
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            if let Some(before) = rest[..start].strip_suffix('$') {
+                out.push_str(before);
+                out.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let value = self.lookup(i, &rest[..=end])?;
+            out.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Value of `reference`, written as `${...}` in entry `i`
+    ///
+    /// The reference is a key path, or an expression over keys as in
+    /// `@let` interpolation, such as `${port + 1}`.
+    fn lookup(&mut self, i: usize, reference: &str) -> Result<String, ParseError> {
+        let text = reference[2..reference.len() - 1].trim();
+        if let Some(value) = self.key(i, reference, text)? {
+            return Ok(value);
+        }
+        let (functions, env_access) = (self.functions, self.env_access);
+        let mut names = |name: &str| match self.key(i, reference, name)? {
+            Some(value) => Ok(Some(value)),
+            None if env_access && is_env_name(name) => Ok(std::env::var(name).ok()),
+            None => Ok(None),
+        };
+        let evaluated = interpolate::evaluate(text, functions, env_access, &mut names);
+        let message = match evaluated.map_err(|e| self.locate(i, reference, e))? {
+            Evaluated::Value(value) => return Ok(value),
+            Evaluated::Malformed => {
+                let message = format!("`{}` is not a valid expression", reference);
+                return Err(self.error(i, reference, ErrorKind::InvalidSyntax, message));
+            }
+            Evaluated::Undefined(name) => match name.strip_prefix("env:") {
+                Some(var) if env_access => format!("environment variable `{}` is not set", var),
+                Some(var) => format!(
+                    "environment variable `{}` cannot be read without environment access",
+                    var
+                ),
+                None if env_access => {
+                    format!("`{}` is neither a key nor an environment variable", name)
+                }
+                None => format!("`{}` is not a key", name),
+            },
+        };
+        Err(self.error(i, reference, ErrorKind::UndefinedReference, message))
+    }
+
+    /// Value of the key `path`, or `None` if there is none
+    fn key(&mut self, i: usize, reference: &str, path: &str) -> Result<Option<String>, ParseError> {
+        if let Some(&j) = self.paths.get(path) {
+            if let Some(start) = self.stack.iter().position(|&k| k == j) {
+                let chain: Vec<String> = self.stack[start..]
+                    .iter()
+                    .chain([&j])
+                    .map(|&k| format!("`{}`", self.entries[k].path))
+                    .collect();
+                let message = format!("keys refer to each other: {}", chain.join(" -> "));
+                return Err(self.error(i, reference, ErrorKind::ReferenceCycle, message));
+            }
+            return self.resolve(j).map(Some);
+        }
+        if self.others.contains(path) {
+            let message = format!("`{}` is not a scalar and cannot be interpolated", path);
+            return Err(self.error(i, reference, ErrorKind::InvalidValue, message));
+        }
+        Ok(None)
+    }
+
+    /// Error about `reference` in entry `i`, located where it was written
+    fn error(&self, i: usize, reference: &str, kind: ErrorKind, message: String) -> ParseError {
+        self.locate(i, reference, ParseError::new(kind, message))
+    }
+
+    /// Locate `e`, unless it already is, where `reference` in entry `i` was
+    /// written
+    fn locate(&self, i: usize, reference: &str, mut e: ParseError) -> ParseError {
+        if e.location.is_some() {
+            return e;
+        }
+        let entry = &self.entries[i];
+        if let Some((start, _)) = source_line(self.input, entry.origin) {
+            // A multiline value starts on the lines after its key
+            e.location = self.input[start..].find(reference).and_then(|i| {
+                Location::of(self.input, &self.input[start + i..][..reference.len()])
+            });
+        }
+        e
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Document, ParseError> {
+        Parser::new().with_interpolation(true).parse_document(input)
+    }
+
+    #[test]
+    fn test_expands_keys_and_env() {
+        std::env::set_var("UPLANG_TEST_HOST", "db.internal");
+        let parser = Parser::new().with_interpolation(true).env_access(true);
+        let doc = parser.parse_document(
+            "url postgres://${server.host}:${port}/${name}\nport!int ${server.port}\nserver {\n  host ${UPLANG_TEST_HOST}\n  port 5432\n}\nname app\nhosts [${env:UPLANG_TEST_HOST}, $${literal}]\n",
+        )
+        .unwrap();
+        assert_eq!(doc.get_str("url"), Some("postgres://db.internal:5432/app"));
+        assert_eq!(doc.get("port"), Some(&Value::Int(5432)));
+        assert_eq!(
+            doc.get("hosts"),
+            Some(&Value::List(vec![
+                Value::String("db.internal".into()),
+                Value::String("${literal}".into())
+            ]))
+        );
+
+        // `@let` variables are expanded while parsing, before the pass
+        let doc = parse("@let base /srv\ndir ${base}/${name}\nname app").unwrap();
+        assert_eq!(doc.get_str("dir"), Some("/srv/app"));
+
+        // Without the pass, references are left as written
+        let doc = Parser::new().parse_document("a ${b}\nb 1").unwrap();
+        assert_eq!(doc.get_str("a"), Some("${b}"));
+    }
+
+    #[test]
+    fn test_env_access() {
+        std::env::set_var("UPLANG_TEST_USER", "admin");
+        let input = "user ${env:UPLANG_TEST_USER}\nhome ${UPLANG_TEST_USER}";
+
+        let err = parse(input).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UndefinedReference);
+        assert_eq!(
+            err.message,
+            "environment variable `UPLANG_TEST_USER` cannot be read without environment access"
+        );
+        let err = parse("home ${UPLANG_TEST_USER}").unwrap_err();
+        assert_eq!(err.message, "`UPLANG_TEST_USER` is not a key");
+        let doc = parse("user ${default(env:UPLANG_TEST_USER, guest)}\nguest nobody").unwrap();
+        assert_eq!(doc.get_str("user"), Some("nobody"));
+
+        let parser = Parser::new().with_interpolation(true).env_access(true);
+        let doc = parser.parse_document(input).unwrap();
+        assert_eq!(doc.get_str("user"), Some("admin"));
+        assert_eq!(doc.get_str("home"), Some("admin"));
+    }
+
+    #[test]
+    fn test_expressions() {
+        let doc = parse(
+            "a ${port + 1}\nport 5\nb ${upper(name)}-${server.ports[1] * 2}\nname web\nc ${default(env:UPLANG_TEST_NOPE, name)}\nserver {\n  ports [1, 2]\n}",
+        )
+        .unwrap();
+        assert_eq!(doc.get_str("a"), Some("6"));
+        assert_eq!(doc.get_str("b"), Some("WEB-4"));
+        assert_eq!(doc.get_str("c"), Some("web"));
+
+        let err = parse("a x\nb ${1 + }").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.message, "`${1 + }` is not a valid expression");
+        assert_eq!(err.line(), Some(2));
+
+        let err = parse("a ${name + 1}\nname web").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.message, "`web` is not a number in `${name + 1}`");
+        assert_eq!(err.line(), Some(1));
+
+        let err = parse("a ${default(b, c)}").unwrap_err();
+        assert_eq!(err.message, "`c` is not a key");
+    }
+
+    #[test]
+    fn test_substitution_errors() {
+        let parser = Parser::new().with_interpolation(true).env_access(true);
+        let err = parser
+            .parse_document("a 1\nb x ${UPLANG_TEST_UNSET}")
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UndefinedReference);
+        assert_eq!(
+            err.message,
+            "`UPLANG_TEST_UNSET` is neither a key nor an environment variable"
+        );
+        let location = err.location.unwrap();
+        assert_eq!((location.line, location.column), (2, 5));
+
+        let err = parse("a ${b}\nb ${c}\nc ${a}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(
+            err.message,
+            "keys refer to each other: `a` -> `b` -> `c` -> `a`"
+        );
+        assert_eq!(err.line(), Some(3));
+
+        let err = parse("a ${b}\nb {\n  c 1\n}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+
+        let err = parse("port!int ${p}\np eighty").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert_eq!(err.line(), Some(1));
+    }
+}
@@ -0,0 +1,598 @@
+//! Working with tables (`!table`)
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Index;
+
+use crate::writer::column_annotation;
+use crate::{quote, Block, FromUp, FromUpError, Node, Value};
+
+/// A table value, with its columns and rows looked up by name
+///
+/// Cells are typed by the annotations of their columns when the table is
+/// parsed, so `columns [name, port!int]` gives integer cells under `port`.
+///
+/// ```
+/// let doc = uplang::parse("users!table {\n  columns [name, age!int]\n  rows [\n    [Ann, 41]\n    [Bo, 9]\n  ]\n}").unwrap();
+/// let users = doc["users"].table().unwrap();
+/// assert_eq!(users.column_type("age"), Some("int"));
+/// let ages: Vec<i64> = users.column("age").unwrap().filter_map(|cell| cell.as_int()).collect();
+/// assert_eq!(ages, [41, 9]);
+/// for row in users.rows() {
+///     println!("{:?} is {:?}", row["name"], row["age"]);
+/// }
+/// assert_eq!(users.to_csv(), "name,age\nAnn,41\nBo,9\n");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Table<'a> {
+    columns: &'a [Value],
+    rows: &'a [Vec<Value>],
+}
+
+impl<'a> Table<'a> {
+    /// The column names, in order
+    pub fn columns(&self) -> impl Iterator<Item = &'a str> {
+        self.columns
+            .iter()
+            .map(|column| column.as_str().unwrap_or_default())
+    }
+
+    /// The cells of column `name`, from the first row to the last; `None`
+    /// if there is no such column
+    pub fn column(&self, name: &str) -> Option<impl Iterator<Item = &'a Value>> {
+        let i = column_index(self.columns, name)?;
+        Some(self.rows.iter().filter_map(move |row| row.get(i)))
+    }
+
+    /// The annotation of column `name` as it is written in the table's
+    /// `columns [...]`, such as `int`; `None` for a column of text or with
+    /// no rows, or if there is no such column
+    pub fn column_type(&self, name: &str) -> Option<&'static str> {
+        column_annotation(self.rows, column_index(self.columns, name)?)
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Row `i`, counting from 0
+    pub fn row(&self, i: usize) -> Option<Row<'a>> {
+        let columns = self.columns;
+        self.rows.get(i).map(|cells| Row { columns, cells })
+    }
+
+    /// The rows, in order
+    pub fn rows(&self) -> impl Iterator<Item = Row<'a>> {
+        let columns = self.columns;
+        self.rows.iter().map(move |cells| Row { columns, cells })
+    }
+
+    /// Read every row, as a block of its cells, into a [`FromUp`] type
+    ///
+    /// Errors name the row, such as `[2].port`.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// let doc = uplang::parse("hosts!table {\n  columns [name, port!int]\n  rows [\n    [a, 80]\n  ]\n}").unwrap();
+    /// let hosts: Vec<BTreeMap<String, String>> = doc["hosts"].table().unwrap().rows_as().unwrap();
+    /// assert_eq!(hosts[0]["port"], "80");
+    /// ```
+    pub fn rows_as<T: FromUp>(&self) -> Result<Vec<T>, FromUpError> {
+        self.rows()
+            .enumerate()
+            .map(|(i, row)| T::from_up(&Value::Block(row.to_block())).map_err(|e| e.in_index(i)))
+            .collect()
+    }
+
+    /// The table as CSV: a header of column names, then one line per row
+    ///
+    /// Cells are written as their UP text, `!null` cells as nothing, and
+    /// quoted when they hold a comma, quote, or line break. Lines end in
+    /// `\n`.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        write_csv_line(&mut out, self.columns);
+        for row in self.rows {
+            write_csv_line(&mut out, row);
+        }
+        out
+    }
+}
+
+fn write_csv_line(out: &mut String, cells: &[Value]) {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let text = cell.scalar_text().unwrap_or_default();
+        if text.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&text.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(&text);
+        }
+    }
+    out.push('\n');
+}
+
+/// One row of a table, with its cells looked up by column name
+///
+/// Handed to the closure of [`Value::filter`].
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    columns: &'a [Value],
+    cells: &'a [Value],
+}
+
+impl<'a> Row<'a> {
+    /// The cell in column `column`
+    pub fn get(&self, column: &str) -> Option<&'a Value> {
+        let i = column_index(self.columns, column)?;
+        self.cells.get(i)
+    }
+
+    /// The cells of the row, in column order
+    pub fn cells(&self) -> &'a [Value] {
+        self.cells
+    }
+
+    /// Column names and cells, in column order
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a Value)> {
+        self.columns
+            .iter()
+            .map(|column| column.as_str().unwrap_or_default())
+            .zip(self.cells)
+    }
+
+    /// The row as a block, one node per column, annotated with the types
+    /// of their cells
+    pub fn to_block(&self) -> Block {
+        self.iter()
+            .map(|(column, cell)| Node {
+                type_annotation: implied(cell).map(str::to_string),
+                ..Node::new(column, cell.clone())
+            })
+            .collect()
+    }
+}
+
+impl Index<&str> for Row<'_> {
+    type Output = Value;
+
+    /// The cell in column `column`
+    ///
+    /// # Panics
+    ///
+    /// If the table has no such column.
+    fn index(&self, column: &str) -> &Value {
+        match self.get(column) {
+            Some(cell) => cell,
+            None => panic!("no column `{}` in table", column),
+        }
+    }
+}
+
+impl Value {
+    /// A view of this table with its columns looked up by name; `None` if
+    /// this is not a table
+    pub fn table(&self) -> Option<Table<'_>> {
+        let (columns, rows) = self.as_table()?;
+        Some(Table { columns, rows })
+    }
+
+    /// Turn a list of uniform blocks into a table
+    ///
+    /// The blocks must be two or more, hold the same keys, and have the same
+    /// type of scalar under each key, so the table holds everything the
+    /// list did: the first block's keys become the columns and each block a
+    /// row. `None` for any other value, or when a field carries a doc
+    /// comment or an annotation its value does not imply (such as
+    /// `!secret`), which a table has no room for.
+    ///
+    /// ```
+    /// use uplang::Value;
+    ///
+    /// let doc = uplang::parse("users [\n  {\n    id!int 1\n    name Ann\n  }\n  {\n    id!int 2\n    name Bo\n  }\n]").unwrap();
+    /// let Some(Value::Table { columns, rows }) = doc.get("users").unwrap().to_table() else {
+    ///     panic!("not uniform");
+    /// };
+    /// assert_eq!(columns, [Value::String("id".into()), Value::String("name".into())]);
+    /// assert_eq!(rows[1], [Value::Int(2), Value::String("Bo".into())]);
+    /// ```
+    pub fn to_table(&self) -> Option<Value> {
+        let Value::List(items) = self else {
+            return None;
+        };
+        let rows = items
+            .iter()
+            .map(|item| match item {
+                Value::Block(row) => Some(row),
+                _ => None,
+            })
+            .collect::<Option<Vec<&Block>>>()?;
+        let [first, ..] = rows[..] else {
+            return None;
+        };
+        if rows.len() < 2 || first.is_empty() || !rows.iter().all(|row| row.len() == first.len()) {
+            return None;
+        }
+        let uniform = first.nodes().all(|column| {
+            !quote::key_needs_quotes(&column.key)
+                && !column.key.contains(',')
+                && rows.iter().all(|row| {
+                    row.get_node(&column.key).is_some_and(|cell| {
+                        is_cell(&cell.value)
+                            && cell.value.type_name() == column.value.type_name()
+                            && cell.doc.is_none()
+                            && cell.type_annotation.as_deref() == implied(&cell.value)
+                    })
+                })
+        });
+        if !uniform {
+            return None;
+        }
+        Some(Value::Table {
+            columns: first.keys().map(|key| Value::String(key.into())).collect(),
+            rows: rows
+                .iter()
+                .map(|row| {
+                    first
+                        .keys()
+                        .map(|key| row.get(key).cloned().unwrap_or(Value::Null))
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+
+    /// A new table with only the given columns, in the order given
+    ///
+    /// `None` if this is not a table or it has no column by one of the
+    /// names.
+    ///
+    /// ```
+    /// use uplang::Value;
+    ///
+    /// let doc = uplang::parse("hosts!table {\n  columns [name, port!int, region]\n  rows [\n    [a, 80, eu]\n    [b, 8080, us]\n  ]\n}").unwrap();
+    /// let hosts = &doc["hosts"];
+    /// let Some(Value::Table { columns, rows }) = hosts.select(&["region", "name"]) else {
+    ///     panic!("not a table");
+    /// };
+    /// assert_eq!(columns, [Value::String("region".into()), Value::String("name".into())]);
+    /// assert_eq!(rows[1], [Value::String("us".into()), Value::String("b".into())]);
+    ///
+    /// let high = hosts.filter(|row| row["port"].as_int() > Some(1024)).unwrap();
+    /// let (_, rows) = high.as_table().unwrap();
+    /// assert_eq!(rows[0][0], Value::String("b".into()));
+    /// ```
+    pub fn select(&self, names: &[&str]) -> Option<Value> {
+        let (columns, rows) = self.as_table()?;
+        let picked = names
+            .iter()
+            .map(|name| column_index(columns, name))
+            .collect::<Option<Vec<usize>>>()?;
+        Some(Value::Table {
+            columns: picked.iter().map(|&i| columns[i].clone()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| {
+                    picked
+                        .iter()
+                        .map(|&i| row.get(i).cloned().unwrap_or(Value::Null))
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+
+    /// A new table with the rows of this one for which `keep` returns
+    /// `true`, in their order
+    ///
+    /// `None` if this is not a table. See [`select`](Self::select) for an
+    /// example.
+    pub fn filter(&self, mut keep: impl FnMut(Row<'_>) -> bool) -> Option<Value> {
+        let (columns, rows) = self.as_table()?;
+        Some(Value::Table {
+            columns: columns.to_vec(),
+            rows: rows
+                .iter()
+                .filter(|cells| keep(Row { columns, cells }))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// A new table with the rows of this one sorted by the cells of
+    /// `column`, smallest first
+    ///
+    /// Numbers, and strings that read as numbers, compare by value; other
+    /// cells compare by their text, and rows with equal cells keep their
+    /// order. `None` if this is not a table or it has no such column.
+    ///
+    /// ```
+    /// use uplang::Value;
+    ///
+    /// let doc = uplang::parse("people!table {\n  columns [name, age]\n  rows [\n    [Ann, 41]\n    [Bo, 9]\n  ]\n}").unwrap();
+    /// let sorted = doc["people"].sort_by_column("age").unwrap();
+    /// let (_, rows) = sorted.as_table().unwrap();
+    /// assert_eq!(rows[0][0], Value::String("Bo".into()));
+    /// ```
+    pub fn sort_by_column(&self, column: &str) -> Option<Value> {
+        let (columns, rows) = self.as_table()?;
+        let i = column_index(columns, column)?;
+        let mut rows = rows.to_vec();
+        rows.sort_by(|a, b| match (a.get(i), b.get(i)) {
+            (Some(a), Some(b)) => compare_cells(a, b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        });
+        Some(Value::Table {
+            columns: columns.to_vec(),
+            rows,
+        })
+    }
+
+    /// A new table pairing each row of this one with every row of `other`
+    /// that has the same text in column `on`
+    ///
+    /// The result has this table's columns followed by the rest of
+    /// `other`'s; rows without a match on either side are left out. `None`
+    /// if either value is not a table or lacks the column `on`.
+    ///
+    /// ```
+    /// use uplang::Value;
+    ///
+    /// let doc = uplang::parse("hosts!table {\n  columns [host, role]\n  rows [\n    [a, web]\n    [b, db]\n    [c, web]\n  ]\n}\nroles!table {\n  columns [role, port!int]\n  rows [\n    [web, 443]\n    [db, 5432]\n  ]\n}").unwrap();
+    /// let joined = doc["hosts"].join(&doc["roles"], "role").unwrap();
+    /// let (columns, rows) = joined.as_table().unwrap();
+    /// assert_eq!(columns.len(), 3);
+    /// assert_eq!(rows[1], [Value::String("b".into()), Value::String("db".into()), Value::Int(5432)]);
+    /// ```
+    pub fn join(&self, other: &Value, on: &str) -> Option<Value> {
+        let (columns, rows) = self.as_table()?;
+        let (other_columns, other_rows) = other.as_table()?;
+        let key = column_index(columns, on)?;
+        let other_key = column_index(other_columns, on)?;
+
+        let mut matches: HashMap<String, Vec<&Vec<Value>>> = HashMap::new();
+        for row in other_rows {
+            if let Some(text) = row.get(other_key).and_then(Value::scalar_text) {
+                matches.entry(text.into_owned()).or_default().push(row);
+            }
+        }
+        let rest = |row: &[Value]| -> Vec<Value> {
+            row.iter()
+                .enumerate()
+                .filter(|&(i, _)| i != other_key)
+                .map(|(_, cell)| cell.clone())
+                .collect()
+        };
+        let mut joined = Vec::new();
+        for row in rows {
+            let Some(text) = row.get(key).and_then(Value::scalar_text) else {
+                continue;
+            };
+            for other_row in matches.get(text.as_ref()).into_iter().flatten() {
+                let mut cells = row.clone();
+                cells.extend(rest(other_row));
+                joined.push(cells);
+            }
+        }
+        Some(Value::Table {
+            columns: columns
+                .iter()
+                .chain(rest(other_columns).iter())
+                .cloned()
+                .collect(),
+            rows: joined,
+        })
+    }
+}
+
+/// Order two cells for [`Value::sort_by_column`]
+fn compare_cells(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Duration(a), Value::Duration(b)) => a.cmp(b),
+        _ => match (a.as_float(), b.as_float()) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => a.scalar_text().cmp(&b.scalar_text()),
+        },
+    }
+}
+
+/// Position of the column named `name`
+fn column_index(columns: &[Value], name: &str) -> Option<usize> {
+    columns
+        .iter()
+        .position(|column| column.scalar_text().as_deref() == Some(name))
+}
+
+/// Whether `value` can be a table cell: a scalar written on one line
+fn is_cell(value: &Value) -> bool {
+    !value.is_null() && value.scalar_text().is_some_and(|text| !text.contains('\n'))
+}
+
+/// Annotation a cell's column implies for it
+fn implied(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::String(_) | Value::Empty => None,
+        typed => Some(typed.type_name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_to_table() {
+        let doc = parse(
+            "servers [\n  {\n    host a\n    port!int 80\n  }\n  {\n    port!int 81\n    host b\n  }\n]",
+        )
+        .unwrap();
+        assert_eq!(
+            doc.get("servers").unwrap().to_table(),
+            Some(Value::Table {
+                columns: vec![Value::String("host".into()), Value::String("port".into())],
+                rows: vec![
+                    vec![Value::String("a".into()), Value::Int(80)],
+                    vec![Value::String("b".into()), Value::Int(81)],
+                ],
+            })
+        );
+
+        for mixed in [
+            "x [\n  {\n    a 1\n  }\n]",
+            "x [\n  {\n    a 1\n  }\n  {\n    b 1\n  }\n]",
+            "x [\n  {\n    a 1\n  }\n  {\n    a 1\n    b 2\n  }\n]",
+            "x [\n  {\n    a 1\n  }\n  plain\n]",
+            "x [\n  {\n    a!int 1\n  }\n  {\n    a 1\n  }\n]",
+            "x [\n  {\n    a!secret 1\n  }\n  {\n    a!secret 2\n  }\n]",
+            "x [\n  {\n    a [1]\n  }\n  {\n    a [2]\n  }\n]",
+            "x [\n  {\n    a!null\n  }\n  {\n    a!null\n  }\n]",
+            "x [\n  {\n    # Doc\n    a 1\n  }\n  {\n    a 2\n  }\n]",
+            "x [a, b]",
+            "x {\n  a 1\n}",
+        ] {
+            let doc = parse(mixed).unwrap();
+            assert_eq!(doc.get("x").unwrap().to_table(), None, "{:?}", mixed);
+        }
+    }
+
+    #[test]
+    fn test_select_and_filter() {
+        let doc = parse(
+            "hosts!table {\n  columns [name, port!int, region]\n  rows [\n    [a, 80, eu]\n    [b, 8080, us]\n    [c, 443, eu]\n  ]\n}\nplain [x]",
+        )
+        .unwrap();
+        let hosts = &doc["hosts"];
+        let names = |table: &Value| -> Vec<String> {
+            let (_, rows) = table.as_table().unwrap();
+            rows.iter()
+                .map(|row| row[0].scalar_text().unwrap().into_owned())
+                .collect()
+        };
+
+        let selected = hosts.select(&["port", "name", "port"]).unwrap();
+        let (columns, rows) = selected.as_table().unwrap();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(
+            rows[2],
+            [Value::Int(443), Value::String("c".into()), Value::Int(443)]
+        );
+        assert_eq!(hosts.select(&[]).unwrap().as_table().unwrap().1[0], []);
+        assert_eq!(hosts.select(&["name", "missing"]), None);
+        assert_eq!(doc["plain"].select(&["x"]), None);
+
+        let eu = hosts
+            .filter(|row| row.get("region").and_then(Value::as_str) == Some("eu"))
+            .unwrap();
+        assert_eq!(names(&eu), ["a", "c"]);
+        let none = hosts.filter(|_| false).unwrap();
+        assert_eq!(none.as_table().unwrap().0, hosts.as_table().unwrap().0);
+        assert!(none.is_empty());
+        assert!(doc["plain"].filter(|_| true).is_none());
+
+        // Chained, then written back as UP
+        let chained = hosts
+            .filter(|row| row.cells()[1].as_int() < Some(1000))
+            .and_then(|table| table.select(&["name", "port"]))
+            .unwrap();
+        let text = crate::to_string(&chained.to_document("low"));
+        assert_eq!(
+            text,
+            "low!table {\n  columns [name, port!int]\n  rows [\n    [a, 80]\n    [c, 443]\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_by_column() {
+        let doc = parse(
+            "t!table {\n  columns [name, size, took!dur]\n  rows [\n    [b, 10, 1m]\n    [a, 9, 30s]\n    [c, 10, 2h]\n    [d, x, 0s]\n  ]\n}",
+        )
+        .unwrap();
+        let order = |column: &str| -> String {
+            let sorted = doc["t"].sort_by_column(column).unwrap();
+            let (_, rows) = sorted.as_table().unwrap();
+            rows.iter().map(|row| row[0].as_str().unwrap()).collect()
+        };
+        assert_eq!(order("name"), "abcd");
+        // Numbers by value, equal rows in order, text after numbers
+        assert_eq!(order("size"), "abcd");
+        assert_eq!(order("took"), "dabc");
+        assert_eq!(doc["t"].sort_by_column("missing"), None);
+    }
+
+    #[test]
+    fn test_table_view() {
+        let doc = parse(
+            "t!table {\n  columns [name, port!int, note, took!dur]\n  rows [\n    [a, 80, \"x, y\", 1m]\n    [b, 443, \"say \\\"hi\\\"\", 30s]\n  ]\n}\nempty!table {\n  columns [a]\n  rows [\n  ]\n}",
+        )
+        .unwrap();
+        let table = doc["t"].table().unwrap();
+        assert_eq!(
+            table.columns().collect::<Vec<_>>(),
+            ["name", "port", "note", "took"]
+        );
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.column_type("port"), Some("int"));
+        assert_eq!(table.column_type("took"), Some("dur"));
+        assert_eq!(table.column_type("name"), None);
+        assert!(table.column("missing").is_none());
+        let ports: Vec<i64> = table
+            .column("port")
+            .unwrap()
+            .filter_map(Value::as_int)
+            .collect();
+        assert_eq!(ports, [80, 443]);
+        assert_eq!(table.row(1).unwrap()["name"], Value::from("b"));
+        assert!(table.row(2).is_none());
+
+        let block = table.row(0).unwrap().to_block();
+        assert_eq!(
+            block.get_node("port").unwrap().type_annotation.as_deref(),
+            Some("int")
+        );
+        assert_eq!(block.get_node("name").unwrap().type_annotation, None);
+        let pairs: Vec<(&str, &Value)> = table.row(0).unwrap().iter().collect();
+        assert_eq!(pairs[1], ("port", &Value::Int(80)));
+
+        let rows: Vec<HashMap<String, String>> = table.rows_as().unwrap();
+        assert_eq!(rows[1]["took"], "30s");
+        let err = table.rows_as::<HashMap<String, i64>>().unwrap_err();
+        assert_eq!(err.path, "[0].name");
+
+        assert_eq!(
+            table.to_csv(),
+            "name,port,note,took\na,80,\"x, y\",1m\nb,443,\"say \"\"hi\"\"\",30s\n"
+        );
+        let empty = doc["empty"].table().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.column_type("a"), None);
+        assert_eq!(empty.to_csv(), "a\n");
+        assert!(Value::from("x").table().is_none());
+    }
+
+    #[test]
+    fn test_join() {
+        let doc = parse(
+            "hosts!table {\n  columns [host, role]\n  rows [\n    [a, web]\n    [b, db]\n    [c, cache]\n    [d, web]\n  ]\n}\nroles!table {\n  columns [port!int, role]\n  rows [\n    [443, web]\n    [80, web]\n    [5432, db]\n  ]\n}",
+        )
+        .unwrap();
+        let joined = doc["hosts"].join(&doc["roles"], "role").unwrap();
+        assert_eq!(
+            crate::to_string(&joined.to_document("hosts")),
+            "hosts!table {\n  columns [host, role, port!int]\n  rows [\n    [a, web, 443]\n    [a, web, 80]\n    [b, db, 5432]\n    [d, web, 443]\n    [d, web, 80]\n  ]\n}\n"
+        );
+        assert_eq!(doc["hosts"].join(&doc["roles"], "host"), None);
+        assert_eq!(doc["hosts"].join(&Value::Null, "role"), None);
+    }
+}
@@ -0,0 +1,297 @@
+//! Cutting and measuring text without splitting what readers see as one
+//! character
+//!
+//! Text is cut between extended grapheme clusters, as Unicode Standard
+//! Annex #29 defines them with the tables in `tables`: accented letters,
+//! Hangul syllables written as jamo, flags, and emoji joined with
+//! zero-width joiners each stay whole, and every cut falls on a char
+//! boundary, so slicing never panics. The one rule left out is the one
+//! for Indic conjuncts, so a consonant that follows a virama starts a
+//! cluster of its own, as it did before Unicode 15.1. Widths are rougher:
+//! CJK ideographs, Hangul, and most emoji count two columns, from a few
+//! hand-written ranges rather than the East Asian width tables.
+
+use std::cmp::Ordering;
+
+mod tables;
+
+/// The grapheme cluster break property of a `char`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Break {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+}
+
+/// Whether `c` falls in one of the `ranges`, which are in order
+fn find<T>(ranges: &[T], c: char, range: impl Fn(&T) -> (char, char)) -> Option<&T> {
+    let i = ranges
+        .binary_search_by(|entry| match range(entry) {
+            (_, end) if end < c => Ordering::Less,
+            (start, _) if start > c => Ordering::Greater,
+            _ => Ordering::Equal,
+        })
+        .ok()?;
+    Some(&ranges[i])
+}
+
+fn break_property(c: char) -> Break {
+    find(tables::BREAKS, c, |&(start, end, _)| (start, end)).map_or(Break::Other, |entry| entry.2)
+}
+
+fn is_extended_pictographic(c: char) -> bool {
+    find(tables::EXTENDED_PICTOGRAPHIC, c, |&range| range).is_some()
+}
+
+/// What the boundary rules need to know about the text read so far
+#[derive(Debug)]
+struct Boundaries {
+    last: Break,
+    /// Regional indicators in a row at the end
+    indicators: usize,
+    /// Whether the text ends with an emoji and any extending chars
+    emoji: bool,
+    /// Whether it ends with those and then a zero-width joiner
+    emoji_zwj: bool,
+}
+
+impl Boundaries {
+    fn new() -> Self {
+        Self {
+            last: Break::Control,
+            indicators: 0,
+            emoji: false,
+            emoji_zwj: false,
+        }
+    }
+
+    /// Read `c`, returning whether a cluster ends before it
+    fn next(&mut self, c: char) -> bool {
+        use Break::*;
+
+        let next = break_property(c);
+        let pictographic = is_extended_pictographic(c);
+        let boundary = match (self.last, next) {
+            (Cr, Lf) => false,
+            (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => true,
+            (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => false,
+            (_, Extend | Zwj | SpacingMark) | (Prepend, _) => false,
+            (Zwj, _) => !(self.emoji_zwj && pictographic),
+            (RegionalIndicator, RegionalIndicator) => self.indicators % 2 == 0,
+            _ => true,
+        };
+        self.emoji_zwj = self.emoji && next == Zwj;
+        self.emoji = pictographic || (self.emoji && next == Extend);
+        self.indicators = match next {
+            RegionalIndicator => self.indicators + 1,
+            _ => 0,
+        };
+        self.last = next;
+        boundary
+    }
+}
+
+/// Whether `c` takes two columns in a terminal
+fn is_wide(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1100}'..='\u{115f}'
+            | '\u{2e80}'..='\u{303e}'
+            | '\u{3041}'..='\u{33ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{a000}'..='\u{a4cf}'
+            | '\u{ac00}'..='\u{d7a3}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{fe30}'..='\u{fe4f}'
+            | '\u{ff00}'..='\u{ff60}'
+            | '\u{ffe0}'..='\u{ffe6}'
+            | '\u{1f1e6}'..='\u{1f1ff}'
+            | '\u{1f300}'..='\u{1f64f}'
+            | '\u{1f680}'..='\u{1f6ff}'
+            | '\u{1f900}'..='\u{1f9ff}'
+            | '\u{20000}'..='\u{3fffd}'
+    )
+}
+
+/// The extended grapheme clusters of `text`, in order
+pub(crate) fn graphemes(text: &str) -> impl Iterator<Item = &str> {
+    let mut boundaries = Boundaries::new();
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, first) = chars.next()?;
+        // Later clusters start with the char that ended the one before
+        if start == 0 {
+            boundaries.next(first);
+        }
+        let mut end = text.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if boundaries.next(c) {
+                end = i;
+                break;
+            }
+            chars.next();
+        }
+        Some(&text[start..end])
+    })
+}
+
+/// The first `max` clusters of `text`, or `None` if it has no more than
+/// that
+pub(crate) fn truncate(text: &str, max: usize) -> Option<&str> {
+    let mut end = 0;
+    for (i, cluster) in graphemes(text).enumerate() {
+        if i == max {
+            return Some(&text[..end]);
+        }
+        end += cluster.len();
+    }
+    None
+}
+
+/// The columns `text` takes in a terminal, roughly: two for each cluster
+/// with a wide character or emoji, one for any other
+pub(crate) fn display_width(text: &str) -> usize {
+    graphemes(text)
+        .map(|cluster| {
+            let wide = cluster.contains(|c| is_wide(c) || c == '\u{fe0f}');
+            1 + usize::from(wide)
+        })
+        .sum()
+}
+
+/// The largest char boundary of `text` at or before byte `index`
+pub(crate) fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphemes() {
+        let text = "e\u{301}a\u{1f44d}\u{1f3fd}\u{1f1e9}\u{1f1ea}\u{1f1eb}\u{1f1f7}\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}!";
+        let clusters: Vec<&str> = graphemes(text).collect();
+        assert_eq!(
+            clusters,
+            [
+                "e\u{301}",
+                "a",
+                "\u{1f44d}\u{1f3fd}",
+                "\u{1f1e9}\u{1f1ea}",
+                "\u{1f1eb}\u{1f1f7}",
+                "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+                "!"
+            ]
+        );
+        assert_eq!(graphemes("").count(), 0);
+    }
+
+    #[test]
+    fn test_truncate_and_measure() {
+        assert_eq!(truncate("cafe\u{301} au lait", 4), Some("cafe\u{301}"));
+        assert_eq!(
+            truncate("\u{1f1e9}\u{1f1ea}\u{1f1eb}\u{1f1f7}", 1),
+            Some("\u{1f1e9}\u{1f1ea}")
+        );
+        assert_eq!(truncate("short", 5), None);
+        assert_eq!(truncate("日本語", 0), Some(""));
+
+        assert_eq!(display_width("port"), 4);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}\u{1f44d}\u{1f3fd}"), 3);
+
+        assert_eq!(floor_char_boundary("日本", 4), 3);
+        assert_eq!(floor_char_boundary("日本", 99), 6);
+        assert_eq!(floor_char_boundary("ab", 1), 1);
+    }
+
+    #[test]
+    fn test_cuts_are_char_boundaries() {
+        let text = "e\u{301}日\u{1f1e9}\u{1f1ea}\u{1f468}\u{200d}\u{1f469}\u{1100}\u{1161}";
+        for max in 0..=text.chars().count() {
+            if let Some(cut) = truncate(text, max) {
+                assert!(text.is_char_boundary(cut.len()));
+            }
+        }
+        for index in 0..=text.len() {
+            assert!(text.is_char_boundary(floor_char_boundary(text, index)));
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        // Regional indicators pair up in order; an odd one is left alone
+        let clusters: Vec<&str> = graphemes("\u{1f1e9}\u{1f1ea}\u{1f1eb}").collect();
+        assert_eq!(clusters, ["\u{1f1e9}\u{1f1ea}", "\u{1f1eb}"]);
+        assert_eq!(display_width("\u{1f1e9}\u{1f1ea}\u{1f1eb}"), 4);
+        // Tag sequences such as the flag of England stay whole
+        let england = "\u{1f3f4}\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}";
+        assert_eq!(graphemes(england).count(), 1);
+        assert_eq!(display_width(england), 2);
+    }
+
+    #[test]
+    fn test_zero_width_joiners() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(graphemes(family).count(), 1);
+        assert_eq!(display_width(family), 2);
+        assert_eq!(truncate(&format!("{}{}", family, family), 1), Some(family));
+
+        // A joiner stays with what comes before it, but joins only emoji
+        let clusters: Vec<&str> =
+            graphemes("a\u{200d}b\u{1f469}\u{200d}\u{2764}\u{fe0f}").collect();
+        assert_eq!(
+            clusters,
+            ["a\u{200d}", "b", "\u{1f469}\u{200d}\u{2764}\u{fe0f}"]
+        );
+        let kiss =
+            "\u{1f469}\u{1f3fd}\u{200d}\u{2764}\u{fe0f}\u{200d}\u{1f48b}\u{200d}\u{1f468}\u{1f3fb}";
+        assert_eq!(graphemes(kiss).count(), 1);
+        assert_eq!(display_width(kiss), 2);
+    }
+
+    #[test]
+    fn test_marks_and_jamo() {
+        // Marks from any script, however many, stay with their base
+        let clusters: Vec<&str> =
+            graphemes("\u{a15}\u{a3c}\u{a4d}z\u{334}\u{335}\u{336}").collect();
+        assert_eq!(
+            clusters,
+            ["\u{a15}\u{a3c}\u{a4d}", "z\u{334}\u{335}\u{336}"]
+        );
+        assert_eq!(truncate("\u{e01}\u{e33}\u{e01}", 1), Some("\u{e01}\u{e33}"));
+        assert_eq!(truncate("\u{915}\u{93f}\u{924}", 1), Some("\u{915}\u{93f}"));
+
+        // Hangul jamo compose into syllables, and precomposed syllables
+        // take trailing jamo
+        let clusters: Vec<&str> =
+            graphemes("\u{1100}\u{1161}\u{11a8}\u{ac00}\u{11a8}\u{1100}").collect();
+        assert_eq!(
+            clusters,
+            ["\u{1100}\u{1161}\u{11a8}", "\u{ac00}\u{11a8}", "\u{1100}"]
+        );
+        assert_eq!(display_width("\u{1100}\u{1161}\u{11a8}"), 2);
+
+        // Prepended marks go with what follows, and CR LF is one cluster
+        assert_eq!(graphemes("\u{600}1").count(), 1);
+        let clusters: Vec<&str> = graphemes("a\r\n\u{301}").collect();
+        assert_eq!(clusters, ["a", "\r\n", "\u{301}"]);
+    }
+}
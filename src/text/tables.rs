@@ -0,0 +1,1469 @@
+//! Grapheme cluster break properties from the Unicode Character Database,
+//! version 16.0.0: `GraphemeBreakProperty.txt`, and `Extended_Pictographic`
+//! from `emoji-data.txt`
+
+use super::Break;
+
+/// Ranges of the characters whose break property is not `Other`, in order
+#[rustfmt::skip]
+pub(super) const BREAKS: &[(char, char, Break)] = &[
+    ('\u{0}', '\u{9}', Break::Control),
+    ('\u{a}', '\u{a}', Break::Lf),
+    ('\u{b}', '\u{c}', Break::Control),
+    ('\u{d}', '\u{d}', Break::Cr),
+    ('\u{e}', '\u{1f}', Break::Control),
+    ('\u{7f}', '\u{9f}', Break::Control),
+    ('\u{ad}', '\u{ad}', Break::Control),
+    ('\u{300}', '\u{36f}', Break::Extend),
+    ('\u{483}', '\u{489}', Break::Extend),
+    ('\u{591}', '\u{5bd}', Break::Extend),
+    ('\u{5bf}', '\u{5bf}', Break::Extend),
+    ('\u{5c1}', '\u{5c2}', Break::Extend),
+    ('\u{5c4}', '\u{5c5}', Break::Extend),
+    ('\u{5c7}', '\u{5c7}', Break::Extend),
+    ('\u{600}', '\u{605}', Break::Prepend),
+    ('\u{610}', '\u{61a}', Break::Extend),
+    ('\u{61c}', '\u{61c}', Break::Control),
+    ('\u{64b}', '\u{65f}', Break::Extend),
+    ('\u{670}', '\u{670}', Break::Extend),
+    ('\u{6d6}', '\u{6dc}', Break::Extend),
+    ('\u{6dd}', '\u{6dd}', Break::Prepend),
+    ('\u{6df}', '\u{6e4}', Break::Extend),
+    ('\u{6e7}', '\u{6e8}', Break::Extend),
+    ('\u{6ea}', '\u{6ed}', Break::Extend),
+    ('\u{70f}', '\u{70f}', Break::Prepend),
+    ('\u{711}', '\u{711}', Break::Extend),
+    ('\u{730}', '\u{74a}', Break::Extend),
+    ('\u{7a6}', '\u{7b0}', Break::Extend),
+    ('\u{7eb}', '\u{7f3}', Break::Extend),
+    ('\u{7fd}', '\u{7fd}', Break::Extend),
+    ('\u{816}', '\u{819}', Break::Extend),
+    ('\u{81b}', '\u{823}', Break::Extend),
+    ('\u{825}', '\u{827}', Break::Extend),
+    ('\u{829}', '\u{82d}', Break::Extend),
+    ('\u{859}', '\u{85b}', Break::Extend),
+    ('\u{890}', '\u{891}', Break::Prepend),
+    ('\u{897}', '\u{89f}', Break::Extend),
+    ('\u{8ca}', '\u{8e1}', Break::Extend),
+    ('\u{8e2}', '\u{8e2}', Break::Prepend),
+    ('\u{8e3}', '\u{902}', Break::Extend),
+    ('\u{903}', '\u{903}', Break::SpacingMark),
+    ('\u{93a}', '\u{93a}', Break::Extend),
+    ('\u{93b}', '\u{93b}', Break::SpacingMark),
+    ('\u{93c}', '\u{93c}', Break::Extend),
+    ('\u{93e}', '\u{940}', Break::SpacingMark),
+    ('\u{941}', '\u{948}', Break::Extend),
+    ('\u{949}', '\u{94c}', Break::SpacingMark),
+    ('\u{94d}', '\u{94d}', Break::Extend),
+    ('\u{94e}', '\u{94f}', Break::SpacingMark),
+    ('\u{951}', '\u{957}', Break::Extend),
+    ('\u{962}', '\u{963}', Break::Extend),
+    ('\u{981}', '\u{981}', Break::Extend),
+    ('\u{982}', '\u{983}', Break::SpacingMark),
+    ('\u{9bc}', '\u{9bc}', Break::Extend),
+    ('\u{9be}', '\u{9be}', Break::Extend),
+    ('\u{9bf}', '\u{9c0}', Break::SpacingMark),
+    ('\u{9c1}', '\u{9c4}', Break::Extend),
+    ('\u{9c7}', '\u{9c8}', Break::SpacingMark),
+    ('\u{9cb}', '\u{9cc}', Break::SpacingMark),
+    ('\u{9cd}', '\u{9cd}', Break::Extend),
+    ('\u{9d7}', '\u{9d7}', Break::Extend),
+    ('\u{9e2}', '\u{9e3}', Break::Extend),
+    ('\u{9fe}', '\u{9fe}', Break::Extend),
+    ('\u{a01}', '\u{a02}', Break::Extend),
+    ('\u{a03}', '\u{a03}', Break::SpacingMark),
+    ('\u{a3c}', '\u{a3c}', Break::Extend),
+    ('\u{a3e}', '\u{a40}', Break::SpacingMark),
+    ('\u{a41}', '\u{a42}', Break::Extend),
+    ('\u{a47}', '\u{a48}', Break::Extend),
+    ('\u{a4b}', '\u{a4d}', Break::Extend),
+    ('\u{a51}', '\u{a51}', Break::Extend),
+    ('\u{a70}', '\u{a71}', Break::Extend),
+    ('\u{a75}', '\u{a75}', Break::Extend),
+    ('\u{a81}', '\u{a82}', Break::Extend),
+    ('\u{a83}', '\u{a83}', Break::SpacingMark),
+    ('\u{abc}', '\u{abc}', Break::Extend),
+    ('\u{abe}', '\u{ac0}', Break::SpacingMark),
+    ('\u{ac1}', '\u{ac5}', Break::Extend),
+    ('\u{ac7}', '\u{ac8}', Break::Extend),
+    ('\u{ac9}', '\u{ac9}', Break::SpacingMark),
+    ('\u{acb}', '\u{acc}', Break::SpacingMark),
+    ('\u{acd}', '\u{acd}', Break::Extend),
+    ('\u{ae2}', '\u{ae3}', Break::Extend),
+    ('\u{afa}', '\u{aff}', Break::Extend),
+    ('\u{b01}', '\u{b01}', Break::Extend),
+    ('\u{b02}', '\u{b03}', Break::SpacingMark),
+    ('\u{b3c}', '\u{b3c}', Break::Extend),
+    ('\u{b3e}', '\u{b3f}', Break::Extend),
+    ('\u{b40}', '\u{b40}', Break::SpacingMark),
+    ('\u{b41}', '\u{b44}', Break::Extend),
+    ('\u{b47}', '\u{b48}', Break::SpacingMark),
+    ('\u{b4b}', '\u{b4c}', Break::SpacingMark),
+    ('\u{b4d}', '\u{b4d}', Break::Extend),
+    ('\u{b55}', '\u{b57}', Break::Extend),
+    ('\u{b62}', '\u{b63}', Break::Extend),
+    ('\u{b82}', '\u{b82}', Break::Extend),
+    ('\u{bbe}', '\u{bbe}', Break::Extend),
+    ('\u{bbf}', '\u{bbf}', Break::SpacingMark),
+    ('\u{bc0}', '\u{bc0}', Break::Extend),
+    ('\u{bc1}', '\u{bc2}', Break::SpacingMark),
+    ('\u{bc6}', '\u{bc8}', Break::SpacingMark),
+    ('\u{bca}', '\u{bcc}', Break::SpacingMark),
+    ('\u{bcd}', '\u{bcd}', Break::Extend),
+    ('\u{bd7}', '\u{bd7}', Break::Extend),
+    ('\u{c00}', '\u{c00}', Break::Extend),
+    ('\u{c01}', '\u{c03}', Break::SpacingMark),
+    ('\u{c04}', '\u{c04}', Break::Extend),
+    ('\u{c3c}', '\u{c3c}', Break::Extend),
+    ('\u{c3e}', '\u{c40}', Break::Extend),
+    ('\u{c41}', '\u{c44}', Break::SpacingMark),
+    ('\u{c46}', '\u{c48}', Break::Extend),
+    ('\u{c4a}', '\u{c4d}', Break::Extend),
+    ('\u{c55}', '\u{c56}', Break::Extend),
+    ('\u{c62}', '\u{c63}', Break::Extend),
+    ('\u{c81}', '\u{c81}', Break::Extend),
+    ('\u{c82}', '\u{c83}', Break::SpacingMark),
+    ('\u{cbc}', '\u{cbc}', Break::Extend),
+    ('\u{cbe}', '\u{cbe}', Break::SpacingMark),
+    ('\u{cbf}', '\u{cc0}', Break::Extend),
+    ('\u{cc1}', '\u{cc1}', Break::SpacingMark),
+    ('\u{cc2}', '\u{cc2}', Break::Extend),
+    ('\u{cc3}', '\u{cc4}', Break::SpacingMark),
+    ('\u{cc6}', '\u{cc8}', Break::Extend),
+    ('\u{cca}', '\u{ccd}', Break::Extend),
+    ('\u{cd5}', '\u{cd6}', Break::Extend),
+    ('\u{ce2}', '\u{ce3}', Break::Extend),
+    ('\u{cf3}', '\u{cf3}', Break::SpacingMark),
+    ('\u{d00}', '\u{d01}', Break::Extend),
+    ('\u{d02}', '\u{d03}', Break::SpacingMark),
+    ('\u{d3b}', '\u{d3c}', Break::Extend),
+    ('\u{d3e}', '\u{d3e}', Break::Extend),
+    ('\u{d3f}', '\u{d40}', Break::SpacingMark),
+    ('\u{d41}', '\u{d44}', Break::Extend),
+    ('\u{d46}', '\u{d48}', Break::SpacingMark),
+    ('\u{d4a}', '\u{d4c}', Break::SpacingMark),
+    ('\u{d4d}', '\u{d4d}', Break::Extend),
+    ('\u{d4e}', '\u{d4e}', Break::Prepend),
+    ('\u{d57}', '\u{d57}', Break::Extend),
+    ('\u{d62}', '\u{d63}', Break::Extend),
+    ('\u{d81}', '\u{d81}', Break::Extend),
+    ('\u{d82}', '\u{d83}', Break::SpacingMark),
+    ('\u{dca}', '\u{dca}', Break::Extend),
+    ('\u{dcf}', '\u{dcf}', Break::Extend),
+    ('\u{dd0}', '\u{dd1}', Break::SpacingMark),
+    ('\u{dd2}', '\u{dd4}', Break::Extend),
+    ('\u{dd6}', '\u{dd6}', Break::Extend),
+    ('\u{dd8}', '\u{dde}', Break::SpacingMark),
+    ('\u{ddf}', '\u{ddf}', Break::Extend),
+    ('\u{df2}', '\u{df3}', Break::SpacingMark),
+    ('\u{e31}', '\u{e31}', Break::Extend),
+    ('\u{e33}', '\u{e33}', Break::SpacingMark),
+    ('\u{e34}', '\u{e3a}', Break::Extend),
+    ('\u{e47}', '\u{e4e}', Break::Extend),
+    ('\u{eb1}', '\u{eb1}', Break::Extend),
+    ('\u{eb3}', '\u{eb3}', Break::SpacingMark),
+    ('\u{eb4}', '\u{ebc}', Break::Extend),
+    ('\u{ec8}', '\u{ece}', Break::Extend),
+    ('\u{f18}', '\u{f19}', Break::Extend),
+    ('\u{f35}', '\u{f35}', Break::Extend),
+    ('\u{f37}', '\u{f37}', Break::Extend),
+    ('\u{f39}', '\u{f39}', Break::Extend),
+    ('\u{f3e}', '\u{f3f}', Break::SpacingMark),
+    ('\u{f71}', '\u{f7e}', Break::Extend),
+    ('\u{f7f}', '\u{f7f}', Break::SpacingMark),
+    ('\u{f80}', '\u{f84}', Break::Extend),
+    ('\u{f86}', '\u{f87}', Break::Extend),
+    ('\u{f8d}', '\u{f97}', Break::Extend),
+    ('\u{f99}', '\u{fbc}', Break::Extend),
+    ('\u{fc6}', '\u{fc6}', Break::Extend),
+    ('\u{102d}', '\u{1030}', Break::Extend),
+    ('\u{1031}', '\u{1031}', Break::SpacingMark),
+    ('\u{1032}', '\u{1037}', Break::Extend),
+    ('\u{1039}', '\u{103a}', Break::Extend),
+    ('\u{103b}', '\u{103c}', Break::SpacingMark),
+    ('\u{103d}', '\u{103e}', Break::Extend),
+    ('\u{1056}', '\u{1057}', Break::SpacingMark),
+    ('\u{1058}', '\u{1059}', Break::Extend),
+    ('\u{105e}', '\u{1060}', Break::Extend),
+    ('\u{1071}', '\u{1074}', Break::Extend),
+    ('\u{1082}', '\u{1082}', Break::Extend),
+    ('\u{1084}', '\u{1084}', Break::SpacingMark),
+    ('\u{1085}', '\u{1086}', Break::Extend),
+    ('\u{108d}', '\u{108d}', Break::Extend),
+    ('\u{109d}', '\u{109d}', Break::Extend),
+    ('\u{1100}', '\u{115f}', Break::L),
+    ('\u{1160}', '\u{11a7}', Break::V),
+    ('\u{11a8}', '\u{11ff}', Break::T),
+    ('\u{135d}', '\u{135f}', Break::Extend),
+    ('\u{1712}', '\u{1715}', Break::Extend),
+    ('\u{1732}', '\u{1734}', Break::Extend),
+    ('\u{1752}', '\u{1753}', Break::Extend),
+    ('\u{1772}', '\u{1773}', Break::Extend),
+    ('\u{17b4}', '\u{17b5}', Break::Extend),
+    ('\u{17b6}', '\u{17b6}', Break::SpacingMark),
+    ('\u{17b7}', '\u{17bd}', Break::Extend),
+    ('\u{17be}', '\u{17c5}', Break::SpacingMark),
+    ('\u{17c6}', '\u{17c6}', Break::Extend),
+    ('\u{17c7}', '\u{17c8}', Break::SpacingMark),
+    ('\u{17c9}', '\u{17d3}', Break::Extend),
+    ('\u{17dd}', '\u{17dd}', Break::Extend),
+    ('\u{180b}', '\u{180d}', Break::Extend),
+    ('\u{180e}', '\u{180e}', Break::Control),
+    ('\u{180f}', '\u{180f}', Break::Extend),
+    ('\u{1885}', '\u{1886}', Break::Extend),
+    ('\u{18a9}', '\u{18a9}', Break::Extend),
+    ('\u{1920}', '\u{1922}', Break::Extend),
+    ('\u{1923}', '\u{1926}', Break::SpacingMark),
+    ('\u{1927}', '\u{1928}', Break::Extend),
+    ('\u{1929}', '\u{192b}', Break::SpacingMark),
+    ('\u{1930}', '\u{1931}', Break::SpacingMark),
+    ('\u{1932}', '\u{1932}', Break::Extend),
+    ('\u{1933}', '\u{1938}', Break::SpacingMark),
+    ('\u{1939}', '\u{193b}', Break::Extend),
+    ('\u{1a17}', '\u{1a18}', Break::Extend),
+    ('\u{1a19}', '\u{1a1a}', Break::SpacingMark),
+    ('\u{1a1b}', '\u{1a1b}', Break::Extend),
+    ('\u{1a55}', '\u{1a55}', Break::SpacingMark),
+    ('\u{1a56}', '\u{1a56}', Break::Extend),
+    ('\u{1a57}', '\u{1a57}', Break::SpacingMark),
+    ('\u{1a58}', '\u{1a5e}', Break::Extend),
+    ('\u{1a60}', '\u{1a60}', Break::Extend),
+    ('\u{1a62}', '\u{1a62}', Break::Extend),
+    ('\u{1a65}', '\u{1a6c}', Break::Extend),
+    ('\u{1a6d}', '\u{1a72}', Break::SpacingMark),
+    ('\u{1a73}', '\u{1a7c}', Break::Extend),
+    ('\u{1a7f}', '\u{1a7f}', Break::Extend),
+    ('\u{1ab0}', '\u{1ace}', Break::Extend),
+    ('\u{1b00}', '\u{1b03}', Break::Extend),
+    ('\u{1b04}', '\u{1b04}', Break::SpacingMark),
+    ('\u{1b34}', '\u{1b3d}', Break::Extend),
+    ('\u{1b3e}', '\u{1b41}', Break::SpacingMark),
+    ('\u{1b42}', '\u{1b44}', Break::Extend),
+    ('\u{1b6b}', '\u{1b73}', Break::Extend),
+    ('\u{1b80}', '\u{1b81}', Break::Extend),
+    ('\u{1b82}', '\u{1b82}', Break::SpacingMark),
+    ('\u{1ba1}', '\u{1ba1}', Break::SpacingMark),
+    ('\u{1ba2}', '\u{1ba5}', Break::Extend),
+    ('\u{1ba6}', '\u{1ba7}', Break::SpacingMark),
+    ('\u{1ba8}', '\u{1bad}', Break::Extend),
+    ('\u{1be6}', '\u{1be6}', Break::Extend),
+    ('\u{1be7}', '\u{1be7}', Break::SpacingMark),
+    ('\u{1be8}', '\u{1be9}', Break::Extend),
+    ('\u{1bea}', '\u{1bec}', Break::SpacingMark),
+    ('\u{1bed}', '\u{1bed}', Break::Extend),
+    ('\u{1bee}', '\u{1bee}', Break::SpacingMark),
+    ('\u{1bef}', '\u{1bf3}', Break::Extend),
+    ('\u{1c24}', '\u{1c2b}', Break::SpacingMark),
+    ('\u{1c2c}', '\u{1c33}', Break::Extend),
+    ('\u{1c34}', '\u{1c35}', Break::SpacingMark),
+    ('\u{1c36}', '\u{1c37}', Break::Extend),
+    ('\u{1cd0}', '\u{1cd2}', Break::Extend),
+    ('\u{1cd4}', '\u{1ce0}', Break::Extend),
+    ('\u{1ce1}', '\u{1ce1}', Break::SpacingMark),
+    ('\u{1ce2}', '\u{1ce8}', Break::Extend),
+    ('\u{1ced}', '\u{1ced}', Break::Extend),
+    ('\u{1cf4}', '\u{1cf4}', Break::Extend),
+    ('\u{1cf7}', '\u{1cf7}', Break::SpacingMark),
+    ('\u{1cf8}', '\u{1cf9}', Break::Extend),
+    ('\u{1dc0}', '\u{1dff}', Break::Extend),
+    ('\u{200b}', '\u{200b}', Break::Control),
+    ('\u{200c}', '\u{200c}', Break::Extend),
+    ('\u{200d}', '\u{200d}', Break::Zwj),
+    ('\u{200e}', '\u{200f}', Break::Control),
+    ('\u{2028}', '\u{202e}', Break::Control),
+    ('\u{2060}', '\u{206f}', Break::Control),
+    ('\u{20d0}', '\u{20f0}', Break::Extend),
+    ('\u{2cef}', '\u{2cf1}', Break::Extend),
+    ('\u{2d7f}', '\u{2d7f}', Break::Extend),
+    ('\u{2de0}', '\u{2dff}', Break::Extend),
+    ('\u{302a}', '\u{302f}', Break::Extend),
+    ('\u{3099}', '\u{309a}', Break::Extend),
+    ('\u{a66f}', '\u{a672}', Break::Extend),
+    ('\u{a674}', '\u{a67d}', Break::Extend),
+    ('\u{a69e}', '\u{a69f}', Break::Extend),
+    ('\u{a6f0}', '\u{a6f1}', Break::Extend),
+    ('\u{a802}', '\u{a802}', Break::Extend),
+    ('\u{a806}', '\u{a806}', Break::Extend),
+    ('\u{a80b}', '\u{a80b}', Break::Extend),
+    ('\u{a823}', '\u{a824}', Break::SpacingMark),
+    ('\u{a825}', '\u{a826}', Break::Extend),
+    ('\u{a827}', '\u{a827}', Break::SpacingMark),
+    ('\u{a82c}', '\u{a82c}', Break::Extend),
+    ('\u{a880}', '\u{a881}', Break::SpacingMark),
+    ('\u{a8b4}', '\u{a8c3}', Break::SpacingMark),
+    ('\u{a8c4}', '\u{a8c5}', Break::Extend),
+    ('\u{a8e0}', '\u{a8f1}', Break::Extend),
+    ('\u{a8ff}', '\u{a8ff}', Break::Extend),
+    ('\u{a926}', '\u{a92d}', Break::Extend),
+    ('\u{a947}', '\u{a951}', Break::Extend),
+    ('\u{a952}', '\u{a952}', Break::SpacingMark),
+    ('\u{a953}', '\u{a953}', Break::Extend),
+    ('\u{a960}', '\u{a97c}', Break::L),
+    ('\u{a980}', '\u{a982}', Break::Extend),
+    ('\u{a983}', '\u{a983}', Break::SpacingMark),
+    ('\u{a9b3}', '\u{a9b3}', Break::Extend),
+    ('\u{a9b4}', '\u{a9b5}', Break::SpacingMark),
+    ('\u{a9b6}', '\u{a9b9}', Break::Extend),
+    ('\u{a9ba}', '\u{a9bb}', Break::SpacingMark),
+    ('\u{a9bc}', '\u{a9bd}', Break::Extend),
+    ('\u{a9be}', '\u{a9bf}', Break::SpacingMark),
+    ('\u{a9c0}', '\u{a9c0}', Break::Extend),
+    ('\u{a9e5}', '\u{a9e5}', Break::Extend),
+    ('\u{aa29}', '\u{aa2e}', Break::Extend),
+    ('\u{aa2f}', '\u{aa30}', Break::SpacingMark),
+    ('\u{aa31}', '\u{aa32}', Break::Extend),
+    ('\u{aa33}', '\u{aa34}', Break::SpacingMark),
+    ('\u{aa35}', '\u{aa36}', Break::Extend),
+    ('\u{aa43}', '\u{aa43}', Break::Extend),
+    ('\u{aa4c}', '\u{aa4c}', Break::Extend),
+    ('\u{aa4d}', '\u{aa4d}', Break::SpacingMark),
+    ('\u{aa7c}', '\u{aa7c}', Break::Extend),
+    ('\u{aab0}', '\u{aab0}', Break::Extend),
+    ('\u{aab2}', '\u{aab4}', Break::Extend),
+    ('\u{aab7}', '\u{aab8}', Break::Extend),
+    ('\u{aabe}', '\u{aabf}', Break::Extend),
+    ('\u{aac1}', '\u{aac1}', Break::Extend),
+    ('\u{aaeb}', '\u{aaeb}', Break::SpacingMark),
+    ('\u{aaec}', '\u{aaed}', Break::Extend),
+    ('\u{aaee}', '\u{aaef}', Break::SpacingMark),
+    ('\u{aaf5}', '\u{aaf5}', Break::SpacingMark),
+    ('\u{aaf6}', '\u{aaf6}', Break::Extend),
+    ('\u{abe3}', '\u{abe4}', Break::SpacingMark),
+    ('\u{abe5}', '\u{abe5}', Break::Extend),
+    ('\u{abe6}', '\u{abe7}', Break::SpacingMark),
+    ('\u{abe8}', '\u{abe8}', Break::Extend),
+    ('\u{abe9}', '\u{abea}', Break::SpacingMark),
+    ('\u{abec}', '\u{abec}', Break::SpacingMark),
+    ('\u{abed}', '\u{abed}', Break::Extend),
+    ('\u{ac00}', '\u{ac00}', Break::Lv),
+    ('\u{ac01}', '\u{ac1b}', Break::Lvt),
+    ('\u{ac1c}', '\u{ac1c}', Break::Lv),
+    ('\u{ac1d}', '\u{ac37}', Break::Lvt),
+    ('\u{ac38}', '\u{ac38}', Break::Lv),
+    ('\u{ac39}', '\u{ac53}', Break::Lvt),
+    ('\u{ac54}', '\u{ac54}', Break::Lv),
+    ('\u{ac55}', '\u{ac6f}', Break::Lvt),
+    ('\u{ac70}', '\u{ac70}', Break::Lv),
+    ('\u{ac71}', '\u{ac8b}', Break::Lvt),
+    ('\u{ac8c}', '\u{ac8c}', Break::Lv),
+    ('\u{ac8d}', '\u{aca7}', Break::Lvt),
+    ('\u{aca8}', '\u{aca8}', Break::Lv),
+    ('\u{aca9}', '\u{acc3}', Break::Lvt),
+    ('\u{acc4}', '\u{acc4}', Break::Lv),
+    ('\u{acc5}', '\u{acdf}', Break::Lvt),
+    ('\u{ace0}', '\u{ace0}', Break::Lv),
+    ('\u{ace1}', '\u{acfb}', Break::Lvt),
+    ('\u{acfc}', '\u{acfc}', Break::Lv),
+    ('\u{acfd}', '\u{ad17}', Break::Lvt),
+    ('\u{ad18}', '\u{ad18}', Break::Lv),
+    ('\u{ad19}', '\u{ad33}', Break::Lvt),
+    ('\u{ad34}', '\u{ad34}', Break::Lv),
+    ('\u{ad35}', '\u{ad4f}', Break::Lvt),
+    ('\u{ad50}', '\u{ad50}', Break::Lv),
+    ('\u{ad51}', '\u{ad6b}', Break::Lvt),
+    ('\u{ad6c}', '\u{ad6c}', Break::Lv),
+    ('\u{ad6d}', '\u{ad87}', Break::Lvt),
+    ('\u{ad88}', '\u{ad88}', Break::Lv),
+    ('\u{ad89}', '\u{ada3}', Break::Lvt),
+    ('\u{ada4}', '\u{ada4}', Break::Lv),
+    ('\u{ada5}', '\u{adbf}', Break::Lvt),
+    ('\u{adc0}', '\u{adc0}', Break::Lv),
+    ('\u{adc1}', '\u{addb}', Break::Lvt),
+    ('\u{addc}', '\u{addc}', Break::Lv),
+    ('\u{addd}', '\u{adf7}', Break::Lvt),
+    ('\u{adf8}', '\u{adf8}', Break::Lv),
+    ('\u{adf9}', '\u{ae13}', Break::Lvt),
+    ('\u{ae14}', '\u{ae14}', Break::Lv),
+    ('\u{ae15}', '\u{ae2f}', Break::Lvt),
+    ('\u{ae30}', '\u{ae30}', Break::Lv),
+    ('\u{ae31}', '\u{ae4b}', Break::Lvt),
+    ('\u{ae4c}', '\u{ae4c}', Break::Lv),
+    ('\u{ae4d}', '\u{ae67}', Break::Lvt),
+    ('\u{ae68}', '\u{ae68}', Break::Lv),
+    ('\u{ae69}', '\u{ae83}', Break::Lvt),
+    ('\u{ae84}', '\u{ae84}', Break::Lv),
+    ('\u{ae85}', '\u{ae9f}', Break::Lvt),
+    ('\u{aea0}', '\u{aea0}', Break::Lv),
+    ('\u{aea1}', '\u{aebb}', Break::Lvt),
+    ('\u{aebc}', '\u{aebc}', Break::Lv),
+    ('\u{aebd}', '\u{aed7}', Break::Lvt),
+    ('\u{aed8}', '\u{aed8}', Break::Lv),
+    ('\u{aed9}', '\u{aef3}', Break::Lvt),
+    ('\u{aef4}', '\u{aef4}', Break::Lv),
+    ('\u{aef5}', '\u{af0f}', Break::Lvt),
+    ('\u{af10}', '\u{af10}', Break::Lv),
+    ('\u{af11}', '\u{af2b}', Break::Lvt),
+    ('\u{af2c}', '\u{af2c}', Break::Lv),
+    ('\u{af2d}', '\u{af47}', Break::Lvt),
+    ('\u{af48}', '\u{af48}', Break::Lv),
+    ('\u{af49}', '\u{af63}', Break::Lvt),
+    ('\u{af64}', '\u{af64}', Break::Lv),
+    ('\u{af65}', '\u{af7f}', Break::Lvt),
+    ('\u{af80}', '\u{af80}', Break::Lv),
+    ('\u{af81}', '\u{af9b}', Break::Lvt),
+    ('\u{af9c}', '\u{af9c}', Break::Lv),
+    ('\u{af9d}', '\u{afb7}', Break::Lvt),
+    ('\u{afb8}', '\u{afb8}', Break::Lv),
+    ('\u{afb9}', '\u{afd3}', Break::Lvt),
+    ('\u{afd4}', '\u{afd4}', Break::Lv),
+    ('\u{afd5}', '\u{afef}', Break::Lvt),
+    ('\u{aff0}', '\u{aff0}', Break::Lv),
+    ('\u{aff1}', '\u{b00b}', Break::Lvt),
+    ('\u{b00c}', '\u{b00c}', Break::Lv),
+    ('\u{b00d}', '\u{b027}', Break::Lvt),
+    ('\u{b028}', '\u{b028}', Break::Lv),
+    ('\u{b029}', '\u{b043}', Break::Lvt),
+    ('\u{b044}', '\u{b044}', Break::Lv),
+    ('\u{b045}', '\u{b05f}', Break::Lvt),
+    ('\u{b060}', '\u{b060}', Break::Lv),
+    ('\u{b061}', '\u{b07b}', Break::Lvt),
+    ('\u{b07c}', '\u{b07c}', Break::Lv),
+    ('\u{b07d}', '\u{b097}', Break::Lvt),
+    ('\u{b098}', '\u{b098}', Break::Lv),
+    ('\u{b099}', '\u{b0b3}', Break::Lvt),
+    ('\u{b0b4}', '\u{b0b4}', Break::Lv),
+    ('\u{b0b5}', '\u{b0cf}', Break::Lvt),
+    ('\u{b0d0}', '\u{b0d0}', Break::Lv),
+    ('\u{b0d1}', '\u{b0eb}', Break::Lvt),
+    ('\u{b0ec}', '\u{b0ec}', Break::Lv),
+    ('\u{b0ed}', '\u{b107}', Break::Lvt),
+    ('\u{b108}', '\u{b108}', Break::Lv),
+    ('\u{b109}', '\u{b123}', Break::Lvt),
+    ('\u{b124}', '\u{b124}', Break::Lv),
+    ('\u{b125}', '\u{b13f}', Break::Lvt),
+    ('\u{b140}', '\u{b140}', Break::Lv),
+    ('\u{b141}', '\u{b15b}', Break::Lvt),
+    ('\u{b15c}', '\u{b15c}', Break::Lv),
+    ('\u{b15d}', '\u{b177}', Break::Lvt),
+    ('\u{b178}', '\u{b178}', Break::Lv),
+    ('\u{b179}', '\u{b193}', Break::Lvt),
+    ('\u{b194}', '\u{b194}', Break::Lv),
+    ('\u{b195}', '\u{b1af}', Break::Lvt),
+    ('\u{b1b0}', '\u{b1b0}', Break::Lv),
+    ('\u{b1b1}', '\u{b1cb}', Break::Lvt),
+    ('\u{b1cc}', '\u{b1cc}', Break::Lv),
+    ('\u{b1cd}', '\u{b1e7}', Break::Lvt),
+    ('\u{b1e8}', '\u{b1e8}', Break::Lv),
+    ('\u{b1e9}', '\u{b203}', Break::Lvt),
+    ('\u{b204}', '\u{b204}', Break::Lv),
+    ('\u{b205}', '\u{b21f}', Break::Lvt),
+    ('\u{b220}', '\u{b220}', Break::Lv),
+    ('\u{b221}', '\u{b23b}', Break::Lvt),
+    ('\u{b23c}', '\u{b23c}', Break::Lv),
+    ('\u{b23d}', '\u{b257}', Break::Lvt),
+    ('\u{b258}', '\u{b258}', Break::Lv),
+    ('\u{b259}', '\u{b273}', Break::Lvt),
+    ('\u{b274}', '\u{b274}', Break::Lv),
+    ('\u{b275}', '\u{b28f}', Break::Lvt),
+    ('\u{b290}', '\u{b290}', Break::Lv),
+    ('\u{b291}', '\u{b2ab}', Break::Lvt),
+    ('\u{b2ac}', '\u{b2ac}', Break::Lv),
+    ('\u{b2ad}', '\u{b2c7}', Break::Lvt),
+    ('\u{b2c8}', '\u{b2c8}', Break::Lv),
+    ('\u{b2c9}', '\u{b2e3}', Break::Lvt),
+    ('\u{b2e4}', '\u{b2e4}', Break::Lv),
+    ('\u{b2e5}', '\u{b2ff}', Break::Lvt),
+    ('\u{b300}', '\u{b300}', Break::Lv),
+    ('\u{b301}', '\u{b31b}', Break::Lvt),
+    ('\u{b31c}', '\u{b31c}', Break::Lv),
+    ('\u{b31d}', '\u{b337}', Break::Lvt),
+    ('\u{b338}', '\u{b338}', Break::Lv),
+    ('\u{b339}', '\u{b353}', Break::Lvt),
+    ('\u{b354}', '\u{b354}', Break::Lv),
+    ('\u{b355}', '\u{b36f}', Break::Lvt),
+    ('\u{b370}', '\u{b370}', Break::Lv),
+    ('\u{b371}', '\u{b38b}', Break::Lvt),
+    ('\u{b38c}', '\u{b38c}', Break::Lv),
+    ('\u{b38d}', '\u{b3a7}', Break::Lvt),
+    ('\u{b3a8}', '\u{b3a8}', Break::Lv),
+    ('\u{b3a9}', '\u{b3c3}', Break::Lvt),
+    ('\u{b3c4}', '\u{b3c4}', Break::Lv),
+    ('\u{b3c5}', '\u{b3df}', Break::Lvt),
+    ('\u{b3e0}', '\u{b3e0}', Break::Lv),
+    ('\u{b3e1}', '\u{b3fb}', Break::Lvt),
+    ('\u{b3fc}', '\u{b3fc}', Break::Lv),
+    ('\u{b3fd}', '\u{b417}', Break::Lvt),
+    ('\u{b418}', '\u{b418}', Break::Lv),
+    ('\u{b419}', '\u{b433}', Break::Lvt),
+    ('\u{b434}', '\u{b434}', Break::Lv),
+    ('\u{b435}', '\u{b44f}', Break::Lvt),
+    ('\u{b450}', '\u{b450}', Break::Lv),
+    ('\u{b451}', '\u{b46b}', Break::Lvt),
+    ('\u{b46c}', '\u{b46c}', Break::Lv),
+    ('\u{b46d}', '\u{b487}', Break::Lvt),
+    ('\u{b488}', '\u{b488}', Break::Lv),
+    ('\u{b489}', '\u{b4a3}', Break::Lvt),
+    ('\u{b4a4}', '\u{b4a4}', Break::Lv),
+    ('\u{b4a5}', '\u{b4bf}', Break::Lvt),
+    ('\u{b4c0}', '\u{b4c0}', Break::Lv),
+    ('\u{b4c1}', '\u{b4db}', Break::Lvt),
+    ('\u{b4dc}', '\u{b4dc}', Break::Lv),
+    ('\u{b4dd}', '\u{b4f7}', Break::Lvt),
+    ('\u{b4f8}', '\u{b4f8}', Break::Lv),
+    ('\u{b4f9}', '\u{b513}', Break::Lvt),
+    ('\u{b514}', '\u{b514}', Break::Lv),
+    ('\u{b515}', '\u{b52f}', Break::Lvt),
+    ('\u{b530}', '\u{b530}', Break::Lv),
+    ('\u{b531}', '\u{b54b}', Break::Lvt),
+    ('\u{b54c}', '\u{b54c}', Break::Lv),
+    ('\u{b54d}', '\u{b567}', Break::Lvt),
+    ('\u{b568}', '\u{b568}', Break::Lv),
+    ('\u{b569}', '\u{b583}', Break::Lvt),
+    ('\u{b584}', '\u{b584}', Break::Lv),
+    ('\u{b585}', '\u{b59f}', Break::Lvt),
+    ('\u{b5a0}', '\u{b5a0}', Break::Lv),
+    ('\u{b5a1}', '\u{b5bb}', Break::Lvt),
+    ('\u{b5bc}', '\u{b5bc}', Break::Lv),
+    ('\u{b5bd}', '\u{b5d7}', Break::Lvt),
+    ('\u{b5d8}', '\u{b5d8}', Break::Lv),
+    ('\u{b5d9}', '\u{b5f3}', Break::Lvt),
+    ('\u{b5f4}', '\u{b5f4}', Break::Lv),
+    ('\u{b5f5}', '\u{b60f}', Break::Lvt),
+    ('\u{b610}', '\u{b610}', Break::Lv),
+    ('\u{b611}', '\u{b62b}', Break::Lvt),
+    ('\u{b62c}', '\u{b62c}', Break::Lv),
+    ('\u{b62d}', '\u{b647}', Break::Lvt),
+    ('\u{b648}', '\u{b648}', Break::Lv),
+    ('\u{b649}', '\u{b663}', Break::Lvt),
+    ('\u{b664}', '\u{b664}', Break::Lv),
+    ('\u{b665}', '\u{b67f}', Break::Lvt),
+    ('\u{b680}', '\u{b680}', Break::Lv),
+    ('\u{b681}', '\u{b69b}', Break::Lvt),
+    ('\u{b69c}', '\u{b69c}', Break::Lv),
+    ('\u{b69d}', '\u{b6b7}', Break::Lvt),
+    ('\u{b6b8}', '\u{b6b8}', Break::Lv),
+    ('\u{b6b9}', '\u{b6d3}', Break::Lvt),
+    ('\u{b6d4}', '\u{b6d4}', Break::Lv),
+    ('\u{b6d5}', '\u{b6ef}', Break::Lvt),
+    ('\u{b6f0}', '\u{b6f0}', Break::Lv),
+    ('\u{b6f1}', '\u{b70b}', Break::Lvt),
+    ('\u{b70c}', '\u{b70c}', Break::Lv),
+    ('\u{b70d}', '\u{b727}', Break::Lvt),
+    ('\u{b728}', '\u{b728}', Break::Lv),
+    ('\u{b729}', '\u{b743}', Break::Lvt),
+    ('\u{b744}', '\u{b744}', Break::Lv),
+    ('\u{b745}', '\u{b75f}', Break::Lvt),
+    ('\u{b760}', '\u{b760}', Break::Lv),
+    ('\u{b761}', '\u{b77b}', Break::Lvt),
+    ('\u{b77c}', '\u{b77c}', Break::Lv),
+    ('\u{b77d}', '\u{b797}', Break::Lvt),
+    ('\u{b798}', '\u{b798}', Break::Lv),
+    ('\u{b799}', '\u{b7b3}', Break::Lvt),
+    ('\u{b7b4}', '\u{b7b4}', Break::Lv),
+    ('\u{b7b5}', '\u{b7cf}', Break::Lvt),
+    ('\u{b7d0}', '\u{b7d0}', Break::Lv),
+    ('\u{b7d1}', '\u{b7eb}', Break::Lvt),
+    ('\u{b7ec}', '\u{b7ec}', Break::Lv),
+    ('\u{b7ed}', '\u{b807}', Break::Lvt),
+    ('\u{b808}', '\u{b808}', Break::Lv),
+    ('\u{b809}', '\u{b823}', Break::Lvt),
+    ('\u{b824}', '\u{b824}', Break::Lv),
+    ('\u{b825}', '\u{b83f}', Break::Lvt),
+    ('\u{b840}', '\u{b840}', Break::Lv),
+    ('\u{b841}', '\u{b85b}', Break::Lvt),
+    ('\u{b85c}', '\u{b85c}', Break::Lv),
+    ('\u{b85d}', '\u{b877}', Break::Lvt),
+    ('\u{b878}', '\u{b878}', Break::Lv),
+    ('\u{b879}', '\u{b893}', Break::Lvt),
+    ('\u{b894}', '\u{b894}', Break::Lv),
+    ('\u{b895}', '\u{b8af}', Break::Lvt),
+    ('\u{b8b0}', '\u{b8b0}', Break::Lv),
+    ('\u{b8b1}', '\u{b8cb}', Break::Lvt),
+    ('\u{b8cc}', '\u{b8cc}', Break::Lv),
+    ('\u{b8cd}', '\u{b8e7}', Break::Lvt),
+    ('\u{b8e8}', '\u{b8e8}', Break::Lv),
+    ('\u{b8e9}', '\u{b903}', Break::Lvt),
+    ('\u{b904}', '\u{b904}', Break::Lv),
+    ('\u{b905}', '\u{b91f}', Break::Lvt),
+    ('\u{b920}', '\u{b920}', Break::Lv),
+    ('\u{b921}', '\u{b93b}', Break::Lvt),
+    ('\u{b93c}', '\u{b93c}', Break::Lv),
+    ('\u{b93d}', '\u{b957}', Break::Lvt),
+    ('\u{b958}', '\u{b958}', Break::Lv),
+    ('\u{b959}', '\u{b973}', Break::Lvt),
+    ('\u{b974}', '\u{b974}', Break::Lv),
+    ('\u{b975}', '\u{b98f}', Break::Lvt),
+    ('\u{b990}', '\u{b990}', Break::Lv),
+    ('\u{b991}', '\u{b9ab}', Break::Lvt),
+    ('\u{b9ac}', '\u{b9ac}', Break::Lv),
+    ('\u{b9ad}', '\u{b9c7}', Break::Lvt),
+    ('\u{b9c8}', '\u{b9c8}', Break::Lv),
+    ('\u{b9c9}', '\u{b9e3}', Break::Lvt),
+    ('\u{b9e4}', '\u{b9e4}', Break::Lv),
+    ('\u{b9e5}', '\u{b9ff}', Break::Lvt),
+    ('\u{ba00}', '\u{ba00}', Break::Lv),
+    ('\u{ba01}', '\u{ba1b}', Break::Lvt),
+    ('\u{ba1c}', '\u{ba1c}', Break::Lv),
+    ('\u{ba1d}', '\u{ba37}', Break::Lvt),
+    ('\u{ba38}', '\u{ba38}', Break::Lv),
+    ('\u{ba39}', '\u{ba53}', Break::Lvt),
+    ('\u{ba54}', '\u{ba54}', Break::Lv),
+    ('\u{ba55}', '\u{ba6f}', Break::Lvt),
+    ('\u{ba70}', '\u{ba70}', Break::Lv),
+    ('\u{ba71}', '\u{ba8b}', Break::Lvt),
+    ('\u{ba8c}', '\u{ba8c}', Break::Lv),
+    ('\u{ba8d}', '\u{baa7}', Break::Lvt),
+    ('\u{baa8}', '\u{baa8}', Break::Lv),
+    ('\u{baa9}', '\u{bac3}', Break::Lvt),
+    ('\u{bac4}', '\u{bac4}', Break::Lv),
+    ('\u{bac5}', '\u{badf}', Break::Lvt),
+    ('\u{bae0}', '\u{bae0}', Break::Lv),
+    ('\u{bae1}', '\u{bafb}', Break::Lvt),
+    ('\u{bafc}', '\u{bafc}', Break::Lv),
+    ('\u{bafd}', '\u{bb17}', Break::Lvt),
+    ('\u{bb18}', '\u{bb18}', Break::Lv),
+    ('\u{bb19}', '\u{bb33}', Break::Lvt),
+    ('\u{bb34}', '\u{bb34}', Break::Lv),
+    ('\u{bb35}', '\u{bb4f}', Break::Lvt),
+    ('\u{bb50}', '\u{bb50}', Break::Lv),
+    ('\u{bb51}', '\u{bb6b}', Break::Lvt),
+    ('\u{bb6c}', '\u{bb6c}', Break::Lv),
+    ('\u{bb6d}', '\u{bb87}', Break::Lvt),
+    ('\u{bb88}', '\u{bb88}', Break::Lv),
+    ('\u{bb89}', '\u{bba3}', Break::Lvt),
+    ('\u{bba4}', '\u{bba4}', Break::Lv),
+    ('\u{bba5}', '\u{bbbf}', Break::Lvt),
+    ('\u{bbc0}', '\u{bbc0}', Break::Lv),
+    ('\u{bbc1}', '\u{bbdb}', Break::Lvt),
+    ('\u{bbdc}', '\u{bbdc}', Break::Lv),
+    ('\u{bbdd}', '\u{bbf7}', Break::Lvt),
+    ('\u{bbf8}', '\u{bbf8}', Break::Lv),
+    ('\u{bbf9}', '\u{bc13}', Break::Lvt),
+    ('\u{bc14}', '\u{bc14}', Break::Lv),
+    ('\u{bc15}', '\u{bc2f}', Break::Lvt),
+    ('\u{bc30}', '\u{bc30}', Break::Lv),
+    ('\u{bc31}', '\u{bc4b}', Break::Lvt),
+    ('\u{bc4c}', '\u{bc4c}', Break::Lv),
+    ('\u{bc4d}', '\u{bc67}', Break::Lvt),
+    ('\u{bc68}', '\u{bc68}', Break::Lv),
+    ('\u{bc69}', '\u{bc83}', Break::Lvt),
+    ('\u{bc84}', '\u{bc84}', Break::Lv),
+    ('\u{bc85}', '\u{bc9f}', Break::Lvt),
+    ('\u{bca0}', '\u{bca0}', Break::Lv),
+    ('\u{bca1}', '\u{bcbb}', Break::Lvt),
+    ('\u{bcbc}', '\u{bcbc}', Break::Lv),
+    ('\u{bcbd}', '\u{bcd7}', Break::Lvt),
+    ('\u{bcd8}', '\u{bcd8}', Break::Lv),
+    ('\u{bcd9}', '\u{bcf3}', Break::Lvt),
+    ('\u{bcf4}', '\u{bcf4}', Break::Lv),
+    ('\u{bcf5}', '\u{bd0f}', Break::Lvt),
+    ('\u{bd10}', '\u{bd10}', Break::Lv),
+    ('\u{bd11}', '\u{bd2b}', Break::Lvt),
+    ('\u{bd2c}', '\u{bd2c}', Break::Lv),
+    ('\u{bd2d}', '\u{bd47}', Break::Lvt),
+    ('\u{bd48}', '\u{bd48}', Break::Lv),
+    ('\u{bd49}', '\u{bd63}', Break::Lvt),
+    ('\u{bd64}', '\u{bd64}', Break::Lv),
+    ('\u{bd65}', '\u{bd7f}', Break::Lvt),
+    ('\u{bd80}', '\u{bd80}', Break::Lv),
+    ('\u{bd81}', '\u{bd9b}', Break::Lvt),
+    ('\u{bd9c}', '\u{bd9c}', Break::Lv),
+    ('\u{bd9d}', '\u{bdb7}', Break::Lvt),
+    ('\u{bdb8}', '\u{bdb8}', Break::Lv),
+    ('\u{bdb9}', '\u{bdd3}', Break::Lvt),
+    ('\u{bdd4}', '\u{bdd4}', Break::Lv),
+    ('\u{bdd5}', '\u{bdef}', Break::Lvt),
+    ('\u{bdf0}', '\u{bdf0}', Break::Lv),
+    ('\u{bdf1}', '\u{be0b}', Break::Lvt),
+    ('\u{be0c}', '\u{be0c}', Break::Lv),
+    ('\u{be0d}', '\u{be27}', Break::Lvt),
+    ('\u{be28}', '\u{be28}', Break::Lv),
+    ('\u{be29}', '\u{be43}', Break::Lvt),
+    ('\u{be44}', '\u{be44}', Break::Lv),
+    ('\u{be45}', '\u{be5f}', Break::Lvt),
+    ('\u{be60}', '\u{be60}', Break::Lv),
+    ('\u{be61}', '\u{be7b}', Break::Lvt),
+    ('\u{be7c}', '\u{be7c}', Break::Lv),
+    ('\u{be7d}', '\u{be97}', Break::Lvt),
+    ('\u{be98}', '\u{be98}', Break::Lv),
+    ('\u{be99}', '\u{beb3}', Break::Lvt),
+    ('\u{beb4}', '\u{beb4}', Break::Lv),
+    ('\u{beb5}', '\u{becf}', Break::Lvt),
+    ('\u{bed0}', '\u{bed0}', Break::Lv),
+    ('\u{bed1}', '\u{beeb}', Break::Lvt),
+    ('\u{beec}', '\u{beec}', Break::Lv),
+    ('\u{beed}', '\u{bf07}', Break::Lvt),
+    ('\u{bf08}', '\u{bf08}', Break::Lv),
+    ('\u{bf09}', '\u{bf23}', Break::Lvt),
+    ('\u{bf24}', '\u{bf24}', Break::Lv),
+    ('\u{bf25}', '\u{bf3f}', Break::Lvt),
+    ('\u{bf40}', '\u{bf40}', Break::Lv),
+    ('\u{bf41}', '\u{bf5b}', Break::Lvt),
+    ('\u{bf5c}', '\u{bf5c}', Break::Lv),
+    ('\u{bf5d}', '\u{bf77}', Break::Lvt),
+    ('\u{bf78}', '\u{bf78}', Break::Lv),
+    ('\u{bf79}', '\u{bf93}', Break::Lvt),
+    ('\u{bf94}', '\u{bf94}', Break::Lv),
+    ('\u{bf95}', '\u{bfaf}', Break::Lvt),
+    ('\u{bfb0}', '\u{bfb0}', Break::Lv),
+    ('\u{bfb1}', '\u{bfcb}', Break::Lvt),
+    ('\u{bfcc}', '\u{bfcc}', Break::Lv),
+    ('\u{bfcd}', '\u{bfe7}', Break::Lvt),
+    ('\u{bfe8}', '\u{bfe8}', Break::Lv),
+    ('\u{bfe9}', '\u{c003}', Break::Lvt),
+    ('\u{c004}', '\u{c004}', Break::Lv),
+    ('\u{c005}', '\u{c01f}', Break::Lvt),
+    ('\u{c020}', '\u{c020}', Break::Lv),
+    ('\u{c021}', '\u{c03b}', Break::Lvt),
+    ('\u{c03c}', '\u{c03c}', Break::Lv),
+    ('\u{c03d}', '\u{c057}', Break::Lvt),
+    ('\u{c058}', '\u{c058}', Break::Lv),
+    ('\u{c059}', '\u{c073}', Break::Lvt),
+    ('\u{c074}', '\u{c074}', Break::Lv),
+    ('\u{c075}', '\u{c08f}', Break::Lvt),
+    ('\u{c090}', '\u{c090}', Break::Lv),
+    ('\u{c091}', '\u{c0ab}', Break::Lvt),
+    ('\u{c0ac}', '\u{c0ac}', Break::Lv),
+    ('\u{c0ad}', '\u{c0c7}', Break::Lvt),
+    ('\u{c0c8}', '\u{c0c8}', Break::Lv),
+    ('\u{c0c9}', '\u{c0e3}', Break::Lvt),
+    ('\u{c0e4}', '\u{c0e4}', Break::Lv),
+    ('\u{c0e5}', '\u{c0ff}', Break::Lvt),
+    ('\u{c100}', '\u{c100}', Break::Lv),
+    ('\u{c101}', '\u{c11b}', Break::Lvt),
+    ('\u{c11c}', '\u{c11c}', Break::Lv),
+    ('\u{c11d}', '\u{c137}', Break::Lvt),
+    ('\u{c138}', '\u{c138}', Break::Lv),
+    ('\u{c139}', '\u{c153}', Break::Lvt),
+    ('\u{c154}', '\u{c154}', Break::Lv),
+    ('\u{c155}', '\u{c16f}', Break::Lvt),
+    ('\u{c170}', '\u{c170}', Break::Lv),
+    ('\u{c171}', '\u{c18b}', Break::Lvt),
+    ('\u{c18c}', '\u{c18c}', Break::Lv),
+    ('\u{c18d}', '\u{c1a7}', Break::Lvt),
+    ('\u{c1a8}', '\u{c1a8}', Break::Lv),
+    ('\u{c1a9}', '\u{c1c3}', Break::Lvt),
+    ('\u{c1c4}', '\u{c1c4}', Break::Lv),
+    ('\u{c1c5}', '\u{c1df}', Break::Lvt),
+    ('\u{c1e0}', '\u{c1e0}', Break::Lv),
+    ('\u{c1e1}', '\u{c1fb}', Break::Lvt),
+    ('\u{c1fc}', '\u{c1fc}', Break::Lv),
+    ('\u{c1fd}', '\u{c217}', Break::Lvt),
+    ('\u{c218}', '\u{c218}', Break::Lv),
+    ('\u{c219}', '\u{c233}', Break::Lvt),
+    ('\u{c234}', '\u{c234}', Break::Lv),
+    ('\u{c235}', '\u{c24f}', Break::Lvt),
+    ('\u{c250}', '\u{c250}', Break::Lv),
+    ('\u{c251}', '\u{c26b}', Break::Lvt),
+    ('\u{c26c}', '\u{c26c}', Break::Lv),
+    ('\u{c26d}', '\u{c287}', Break::Lvt),
+    ('\u{c288}', '\u{c288}', Break::Lv),
+    ('\u{c289}', '\u{c2a3}', Break::Lvt),
+    ('\u{c2a4}', '\u{c2a4}', Break::Lv),
+    ('\u{c2a5}', '\u{c2bf}', Break::Lvt),
+    ('\u{c2c0}', '\u{c2c0}', Break::Lv),
+    ('\u{c2c1}', '\u{c2db}', Break::Lvt),
+    ('\u{c2dc}', '\u{c2dc}', Break::Lv),
+    ('\u{c2dd}', '\u{c2f7}', Break::Lvt),
+    ('\u{c2f8}', '\u{c2f8}', Break::Lv),
+    ('\u{c2f9}', '\u{c313}', Break::Lvt),
+    ('\u{c314}', '\u{c314}', Break::Lv),
+    ('\u{c315}', '\u{c32f}', Break::Lvt),
+    ('\u{c330}', '\u{c330}', Break::Lv),
+    ('\u{c331}', '\u{c34b}', Break::Lvt),
+    ('\u{c34c}', '\u{c34c}', Break::Lv),
+    ('\u{c34d}', '\u{c367}', Break::Lvt),
+    ('\u{c368}', '\u{c368}', Break::Lv),
+    ('\u{c369}', '\u{c383}', Break::Lvt),
+    ('\u{c384}', '\u{c384}', Break::Lv),
+    ('\u{c385}', '\u{c39f}', Break::Lvt),
+    ('\u{c3a0}', '\u{c3a0}', Break::Lv),
+    ('\u{c3a1}', '\u{c3bb}', Break::Lvt),
+    ('\u{c3bc}', '\u{c3bc}', Break::Lv),
+    ('\u{c3bd}', '\u{c3d7}', Break::Lvt),
+    ('\u{c3d8}', '\u{c3d8}', Break::Lv),
+    ('\u{c3d9}', '\u{c3f3}', Break::Lvt),
+    ('\u{c3f4}', '\u{c3f4}', Break::Lv),
+    ('\u{c3f5}', '\u{c40f}', Break::Lvt),
+    ('\u{c410}', '\u{c410}', Break::Lv),
+    ('\u{c411}', '\u{c42b}', Break::Lvt),
+    ('\u{c42c}', '\u{c42c}', Break::Lv),
+    ('\u{c42d}', '\u{c447}', Break::Lvt),
+    ('\u{c448}', '\u{c448}', Break::Lv),
+    ('\u{c449}', '\u{c463}', Break::Lvt),
+    ('\u{c464}', '\u{c464}', Break::Lv),
+    ('\u{c465}', '\u{c47f}', Break::Lvt),
+    ('\u{c480}', '\u{c480}', Break::Lv),
+    ('\u{c481}', '\u{c49b}', Break::Lvt),
+    ('\u{c49c}', '\u{c49c}', Break::Lv),
+    ('\u{c49d}', '\u{c4b7}', Break::Lvt),
+    ('\u{c4b8}', '\u{c4b8}', Break::Lv),
+    ('\u{c4b9}', '\u{c4d3}', Break::Lvt),
+    ('\u{c4d4}', '\u{c4d4}', Break::Lv),
+    ('\u{c4d5}', '\u{c4ef}', Break::Lvt),
+    ('\u{c4f0}', '\u{c4f0}', Break::Lv),
+    ('\u{c4f1}', '\u{c50b}', Break::Lvt),
+    ('\u{c50c}', '\u{c50c}', Break::Lv),
+    ('\u{c50d}', '\u{c527}', Break::Lvt),
+    ('\u{c528}', '\u{c528}', Break::Lv),
+    ('\u{c529}', '\u{c543}', Break::Lvt),
+    ('\u{c544}', '\u{c544}', Break::Lv),
+    ('\u{c545}', '\u{c55f}', Break::Lvt),
+    ('\u{c560}', '\u{c560}', Break::Lv),
+    ('\u{c561}', '\u{c57b}', Break::Lvt),
+    ('\u{c57c}', '\u{c57c}', Break::Lv),
+    ('\u{c57d}', '\u{c597}', Break::Lvt),
+    ('\u{c598}', '\u{c598}', Break::Lv),
+    ('\u{c599}', '\u{c5b3}', Break::Lvt),
+    ('\u{c5b4}', '\u{c5b4}', Break::Lv),
+    ('\u{c5b5}', '\u{c5cf}', Break::Lvt),
+    ('\u{c5d0}', '\u{c5d0}', Break::Lv),
+    ('\u{c5d1}', '\u{c5eb}', Break::Lvt),
+    ('\u{c5ec}', '\u{c5ec}', Break::Lv),
+    ('\u{c5ed}', '\u{c607}', Break::Lvt),
+    ('\u{c608}', '\u{c608}', Break::Lv),
+    ('\u{c609}', '\u{c623}', Break::Lvt),
+    ('\u{c624}', '\u{c624}', Break::Lv),
+    ('\u{c625}', '\u{c63f}', Break::Lvt),
+    ('\u{c640}', '\u{c640}', Break::Lv),
+    ('\u{c641}', '\u{c65b}', Break::Lvt),
+    ('\u{c65c}', '\u{c65c}', Break::Lv),
+    ('\u{c65d}', '\u{c677}', Break::Lvt),
+    ('\u{c678}', '\u{c678}', Break::Lv),
+    ('\u{c679}', '\u{c693}', Break::Lvt),
+    ('\u{c694}', '\u{c694}', Break::Lv),
+    ('\u{c695}', '\u{c6af}', Break::Lvt),
+    ('\u{c6b0}', '\u{c6b0}', Break::Lv),
+    ('\u{c6b1}', '\u{c6cb}', Break::Lvt),
+    ('\u{c6cc}', '\u{c6cc}', Break::Lv),
+    ('\u{c6cd}', '\u{c6e7}', Break::Lvt),
+    ('\u{c6e8}', '\u{c6e8}', Break::Lv),
+    ('\u{c6e9}', '\u{c703}', Break::Lvt),
+    ('\u{c704}', '\u{c704}', Break::Lv),
+    ('\u{c705}', '\u{c71f}', Break::Lvt),
+    ('\u{c720}', '\u{c720}', Break::Lv),
+    ('\u{c721}', '\u{c73b}', Break::Lvt),
+    ('\u{c73c}', '\u{c73c}', Break::Lv),
+    ('\u{c73d}', '\u{c757}', Break::Lvt),
+    ('\u{c758}', '\u{c758}', Break::Lv),
+    ('\u{c759}', '\u{c773}', Break::Lvt),
+    ('\u{c774}', '\u{c774}', Break::Lv),
+    ('\u{c775}', '\u{c78f}', Break::Lvt),
+    ('\u{c790}', '\u{c790}', Break::Lv),
+    ('\u{c791}', '\u{c7ab}', Break::Lvt),
+    ('\u{c7ac}', '\u{c7ac}', Break::Lv),
+    ('\u{c7ad}', '\u{c7c7}', Break::Lvt),
+    ('\u{c7c8}', '\u{c7c8}', Break::Lv),
+    ('\u{c7c9}', '\u{c7e3}', Break::Lvt),
+    ('\u{c7e4}', '\u{c7e4}', Break::Lv),
+    ('\u{c7e5}', '\u{c7ff}', Break::Lvt),
+    ('\u{c800}', '\u{c800}', Break::Lv),
+    ('\u{c801}', '\u{c81b}', Break::Lvt),
+    ('\u{c81c}', '\u{c81c}', Break::Lv),
+    ('\u{c81d}', '\u{c837}', Break::Lvt),
+    ('\u{c838}', '\u{c838}', Break::Lv),
+    ('\u{c839}', '\u{c853}', Break::Lvt),
+    ('\u{c854}', '\u{c854}', Break::Lv),
+    ('\u{c855}', '\u{c86f}', Break::Lvt),
+    ('\u{c870}', '\u{c870}', Break::Lv),
+    ('\u{c871}', '\u{c88b}', Break::Lvt),
+    ('\u{c88c}', '\u{c88c}', Break::Lv),
+    ('\u{c88d}', '\u{c8a7}', Break::Lvt),
+    ('\u{c8a8}', '\u{c8a8}', Break::Lv),
+    ('\u{c8a9}', '\u{c8c3}', Break::Lvt),
+    ('\u{c8c4}', '\u{c8c4}', Break::Lv),
+    ('\u{c8c5}', '\u{c8df}', Break::Lvt),
+    ('\u{c8e0}', '\u{c8e0}', Break::Lv),
+    ('\u{c8e1}', '\u{c8fb}', Break::Lvt),
+    ('\u{c8fc}', '\u{c8fc}', Break::Lv),
+    ('\u{c8fd}', '\u{c917}', Break::Lvt),
+    ('\u{c918}', '\u{c918}', Break::Lv),
+    ('\u{c919}', '\u{c933}', Break::Lvt),
+    ('\u{c934}', '\u{c934}', Break::Lv),
+    ('\u{c935}', '\u{c94f}', Break::Lvt),
+    ('\u{c950}', '\u{c950}', Break::Lv),
+    ('\u{c951}', '\u{c96b}', Break::Lvt),
+    ('\u{c96c}', '\u{c96c}', Break::Lv),
+    ('\u{c96d}', '\u{c987}', Break::Lvt),
+    ('\u{c988}', '\u{c988}', Break::Lv),
+    ('\u{c989}', '\u{c9a3}', Break::Lvt),
+    ('\u{c9a4}', '\u{c9a4}', Break::Lv),
+    ('\u{c9a5}', '\u{c9bf}', Break::Lvt),
+    ('\u{c9c0}', '\u{c9c0}', Break::Lv),
+    ('\u{c9c1}', '\u{c9db}', Break::Lvt),
+    ('\u{c9dc}', '\u{c9dc}', Break::Lv),
+    ('\u{c9dd}', '\u{c9f7}', Break::Lvt),
+    ('\u{c9f8}', '\u{c9f8}', Break::Lv),
+    ('\u{c9f9}', '\u{ca13}', Break::Lvt),
+    ('\u{ca14}', '\u{ca14}', Break::Lv),
+    ('\u{ca15}', '\u{ca2f}', Break::Lvt),
+    ('\u{ca30}', '\u{ca30}', Break::Lv),
+    ('\u{ca31}', '\u{ca4b}', Break::Lvt),
+    ('\u{ca4c}', '\u{ca4c}', Break::Lv),
+    ('\u{ca4d}', '\u{ca67}', Break::Lvt),
+    ('\u{ca68}', '\u{ca68}', Break::Lv),
+    ('\u{ca69}', '\u{ca83}', Break::Lvt),
+    ('\u{ca84}', '\u{ca84}', Break::Lv),
+    ('\u{ca85}', '\u{ca9f}', Break::Lvt),
+    ('\u{caa0}', '\u{caa0}', Break::Lv),
+    ('\u{caa1}', '\u{cabb}', Break::Lvt),
+    ('\u{cabc}', '\u{cabc}', Break::Lv),
+    ('\u{cabd}', '\u{cad7}', Break::Lvt),
+    ('\u{cad8}', '\u{cad8}', Break::Lv),
+    ('\u{cad9}', '\u{caf3}', Break::Lvt),
+    ('\u{caf4}', '\u{caf4}', Break::Lv),
+    ('\u{caf5}', '\u{cb0f}', Break::Lvt),
+    ('\u{cb10}', '\u{cb10}', Break::Lv),
+    ('\u{cb11}', '\u{cb2b}', Break::Lvt),
+    ('\u{cb2c}', '\u{cb2c}', Break::Lv),
+    ('\u{cb2d}', '\u{cb47}', Break::Lvt),
+    ('\u{cb48}', '\u{cb48}', Break::Lv),
+    ('\u{cb49}', '\u{cb63}', Break::Lvt),
+    ('\u{cb64}', '\u{cb64}', Break::Lv),
+    ('\u{cb65}', '\u{cb7f}', Break::Lvt),
+    ('\u{cb80}', '\u{cb80}', Break::Lv),
+    ('\u{cb81}', '\u{cb9b}', Break::Lvt),
+    ('\u{cb9c}', '\u{cb9c}', Break::Lv),
+    ('\u{cb9d}', '\u{cbb7}', Break::Lvt),
+    ('\u{cbb8}', '\u{cbb8}', Break::Lv),
+    ('\u{cbb9}', '\u{cbd3}', Break::Lvt),
+    ('\u{cbd4}', '\u{cbd4}', Break::Lv),
+    ('\u{cbd5}', '\u{cbef}', Break::Lvt),
+    ('\u{cbf0}', '\u{cbf0}', Break::Lv),
+    ('\u{cbf1}', '\u{cc0b}', Break::Lvt),
+    ('\u{cc0c}', '\u{cc0c}', Break::Lv),
+    ('\u{cc0d}', '\u{cc27}', Break::Lvt),
+    ('\u{cc28}', '\u{cc28}', Break::Lv),
+    ('\u{cc29}', '\u{cc43}', Break::Lvt),
+    ('\u{cc44}', '\u{cc44}', Break::Lv),
+    ('\u{cc45}', '\u{cc5f}', Break::Lvt),
+    ('\u{cc60}', '\u{cc60}', Break::Lv),
+    ('\u{cc61}', '\u{cc7b}', Break::Lvt),
+    ('\u{cc7c}', '\u{cc7c}', Break::Lv),
+    ('\u{cc7d}', '\u{cc97}', Break::Lvt),
+    ('\u{cc98}', '\u{cc98}', Break::Lv),
+    ('\u{cc99}', '\u{ccb3}', Break::Lvt),
+    ('\u{ccb4}', '\u{ccb4}', Break::Lv),
+    ('\u{ccb5}', '\u{cccf}', Break::Lvt),
+    ('\u{ccd0}', '\u{ccd0}', Break::Lv),
+    ('\u{ccd1}', '\u{cceb}', Break::Lvt),
+    ('\u{ccec}', '\u{ccec}', Break::Lv),
+    ('\u{cced}', '\u{cd07}', Break::Lvt),
+    ('\u{cd08}', '\u{cd08}', Break::Lv),
+    ('\u{cd09}', '\u{cd23}', Break::Lvt),
+    ('\u{cd24}', '\u{cd24}', Break::Lv),
+    ('\u{cd25}', '\u{cd3f}', Break::Lvt),
+    ('\u{cd40}', '\u{cd40}', Break::Lv),
+    ('\u{cd41}', '\u{cd5b}', Break::Lvt),
+    ('\u{cd5c}', '\u{cd5c}', Break::Lv),
+    ('\u{cd5d}', '\u{cd77}', Break::Lvt),
+    ('\u{cd78}', '\u{cd78}', Break::Lv),
+    ('\u{cd79}', '\u{cd93}', Break::Lvt),
+    ('\u{cd94}', '\u{cd94}', Break::Lv),
+    ('\u{cd95}', '\u{cdaf}', Break::Lvt),
+    ('\u{cdb0}', '\u{cdb0}', Break::Lv),
+    ('\u{cdb1}', '\u{cdcb}', Break::Lvt),
+    ('\u{cdcc}', '\u{cdcc}', Break::Lv),
+    ('\u{cdcd}', '\u{cde7}', Break::Lvt),
+    ('\u{cde8}', '\u{cde8}', Break::Lv),
+    ('\u{cde9}', '\u{ce03}', Break::Lvt),
+    ('\u{ce04}', '\u{ce04}', Break::Lv),
+    ('\u{ce05}', '\u{ce1f}', Break::Lvt),
+    ('\u{ce20}', '\u{ce20}', Break::Lv),
+    ('\u{ce21}', '\u{ce3b}', Break::Lvt),
+    ('\u{ce3c}', '\u{ce3c}', Break::Lv),
+    ('\u{ce3d}', '\u{ce57}', Break::Lvt),
+    ('\u{ce58}', '\u{ce58}', Break::Lv),
+    ('\u{ce59}', '\u{ce73}', Break::Lvt),
+    ('\u{ce74}', '\u{ce74}', Break::Lv),
+    ('\u{ce75}', '\u{ce8f}', Break::Lvt),
+    ('\u{ce90}', '\u{ce90}', Break::Lv),
+    ('\u{ce91}', '\u{ceab}', Break::Lvt),
+    ('\u{ceac}', '\u{ceac}', Break::Lv),
+    ('\u{cead}', '\u{cec7}', Break::Lvt),
+    ('\u{cec8}', '\u{cec8}', Break::Lv),
+    ('\u{cec9}', '\u{cee3}', Break::Lvt),
+    ('\u{cee4}', '\u{cee4}', Break::Lv),
+    ('\u{cee5}', '\u{ceff}', Break::Lvt),
+    ('\u{cf00}', '\u{cf00}', Break::Lv),
+    ('\u{cf01}', '\u{cf1b}', Break::Lvt),
+    ('\u{cf1c}', '\u{cf1c}', Break::Lv),
+    ('\u{cf1d}', '\u{cf37}', Break::Lvt),
+    ('\u{cf38}', '\u{cf38}', Break::Lv),
+    ('\u{cf39}', '\u{cf53}', Break::Lvt),
+    ('\u{cf54}', '\u{cf54}', Break::Lv),
+    ('\u{cf55}', '\u{cf6f}', Break::Lvt),
+    ('\u{cf70}', '\u{cf70}', Break::Lv),
+    ('\u{cf71}', '\u{cf8b}', Break::Lvt),
+    ('\u{cf8c}', '\u{cf8c}', Break::Lv),
+    ('\u{cf8d}', '\u{cfa7}', Break::Lvt),
+    ('\u{cfa8}', '\u{cfa8}', Break::Lv),
+    ('\u{cfa9}', '\u{cfc3}', Break::Lvt),
+    ('\u{cfc4}', '\u{cfc4}', Break::Lv),
+    ('\u{cfc5}', '\u{cfdf}', Break::Lvt),
+    ('\u{cfe0}', '\u{cfe0}', Break::Lv),
+    ('\u{cfe1}', '\u{cffb}', Break::Lvt),
+    ('\u{cffc}', '\u{cffc}', Break::Lv),
+    ('\u{cffd}', '\u{d017}', Break::Lvt),
+    ('\u{d018}', '\u{d018}', Break::Lv),
+    ('\u{d019}', '\u{d033}', Break::Lvt),
+    ('\u{d034}', '\u{d034}', Break::Lv),
+    ('\u{d035}', '\u{d04f}', Break::Lvt),
+    ('\u{d050}', '\u{d050}', Break::Lv),
+    ('\u{d051}', '\u{d06b}', Break::Lvt),
+    ('\u{d06c}', '\u{d06c}', Break::Lv),
+    ('\u{d06d}', '\u{d087}', Break::Lvt),
+    ('\u{d088}', '\u{d088}', Break::Lv),
+    ('\u{d089}', '\u{d0a3}', Break::Lvt),
+    ('\u{d0a4}', '\u{d0a4}', Break::Lv),
+    ('\u{d0a5}', '\u{d0bf}', Break::Lvt),
+    ('\u{d0c0}', '\u{d0c0}', Break::Lv),
+    ('\u{d0c1}', '\u{d0db}', Break::Lvt),
+    ('\u{d0dc}', '\u{d0dc}', Break::Lv),
+    ('\u{d0dd}', '\u{d0f7}', Break::Lvt),
+    ('\u{d0f8}', '\u{d0f8}', Break::Lv),
+    ('\u{d0f9}', '\u{d113}', Break::Lvt),
+    ('\u{d114}', '\u{d114}', Break::Lv),
+    ('\u{d115}', '\u{d12f}', Break::Lvt),
+    ('\u{d130}', '\u{d130}', Break::Lv),
+    ('\u{d131}', '\u{d14b}', Break::Lvt),
+    ('\u{d14c}', '\u{d14c}', Break::Lv),
+    ('\u{d14d}', '\u{d167}', Break::Lvt),
+    ('\u{d168}', '\u{d168}', Break::Lv),
+    ('\u{d169}', '\u{d183}', Break::Lvt),
+    ('\u{d184}', '\u{d184}', Break::Lv),
+    ('\u{d185}', '\u{d19f}', Break::Lvt),
+    ('\u{d1a0}', '\u{d1a0}', Break::Lv),
+    ('\u{d1a1}', '\u{d1bb}', Break::Lvt),
+    ('\u{d1bc}', '\u{d1bc}', Break::Lv),
+    ('\u{d1bd}', '\u{d1d7}', Break::Lvt),
+    ('\u{d1d8}', '\u{d1d8}', Break::Lv),
+    ('\u{d1d9}', '\u{d1f3}', Break::Lvt),
+    ('\u{d1f4}', '\u{d1f4}', Break::Lv),
+    ('\u{d1f5}', '\u{d20f}', Break::Lvt),
+    ('\u{d210}', '\u{d210}', Break::Lv),
+    ('\u{d211}', '\u{d22b}', Break::Lvt),
+    ('\u{d22c}', '\u{d22c}', Break::Lv),
+    ('\u{d22d}', '\u{d247}', Break::Lvt),
+    ('\u{d248}', '\u{d248}', Break::Lv),
+    ('\u{d249}', '\u{d263}', Break::Lvt),
+    ('\u{d264}', '\u{d264}', Break::Lv),
+    ('\u{d265}', '\u{d27f}', Break::Lvt),
+    ('\u{d280}', '\u{d280}', Break::Lv),
+    ('\u{d281}', '\u{d29b}', Break::Lvt),
+    ('\u{d29c}', '\u{d29c}', Break::Lv),
+    ('\u{d29d}', '\u{d2b7}', Break::Lvt),
+    ('\u{d2b8}', '\u{d2b8}', Break::Lv),
+    ('\u{d2b9}', '\u{d2d3}', Break::Lvt),
+    ('\u{d2d4}', '\u{d2d4}', Break::Lv),
+    ('\u{d2d5}', '\u{d2ef}', Break::Lvt),
+    ('\u{d2f0}', '\u{d2f0}', Break::Lv),
+    ('\u{d2f1}', '\u{d30b}', Break::Lvt),
+    ('\u{d30c}', '\u{d30c}', Break::Lv),
+    ('\u{d30d}', '\u{d327}', Break::Lvt),
+    ('\u{d328}', '\u{d328}', Break::Lv),
+    ('\u{d329}', '\u{d343}', Break::Lvt),
+    ('\u{d344}', '\u{d344}', Break::Lv),
+    ('\u{d345}', '\u{d35f}', Break::Lvt),
+    ('\u{d360}', '\u{d360}', Break::Lv),
+    ('\u{d361}', '\u{d37b}', Break::Lvt),
+    ('\u{d37c}', '\u{d37c}', Break::Lv),
+    ('\u{d37d}', '\u{d397}', Break::Lvt),
+    ('\u{d398}', '\u{d398}', Break::Lv),
+    ('\u{d399}', '\u{d3b3}', Break::Lvt),
+    ('\u{d3b4}', '\u{d3b4}', Break::Lv),
+    ('\u{d3b5}', '\u{d3cf}', Break::Lvt),
+    ('\u{d3d0}', '\u{d3d0}', Break::Lv),
+    ('\u{d3d1}', '\u{d3eb}', Break::Lvt),
+    ('\u{d3ec}', '\u{d3ec}', Break::Lv),
+    ('\u{d3ed}', '\u{d407}', Break::Lvt),
+    ('\u{d408}', '\u{d408}', Break::Lv),
+    ('\u{d409}', '\u{d423}', Break::Lvt),
+    ('\u{d424}', '\u{d424}', Break::Lv),
+    ('\u{d425}', '\u{d43f}', Break::Lvt),
+    ('\u{d440}', '\u{d440}', Break::Lv),
+    ('\u{d441}', '\u{d45b}', Break::Lvt),
+    ('\u{d45c}', '\u{d45c}', Break::Lv),
+    ('\u{d45d}', '\u{d477}', Break::Lvt),
+    ('\u{d478}', '\u{d478}', Break::Lv),
+    ('\u{d479}', '\u{d493}', Break::Lvt),
+    ('\u{d494}', '\u{d494}', Break::Lv),
+    ('\u{d495}', '\u{d4af}', Break::Lvt),
+    ('\u{d4b0}', '\u{d4b0}', Break::Lv),
+    ('\u{d4b1}', '\u{d4cb}', Break::Lvt),
+    ('\u{d4cc}', '\u{d4cc}', Break::Lv),
+    ('\u{d4cd}', '\u{d4e7}', Break::Lvt),
+    ('\u{d4e8}', '\u{d4e8}', Break::Lv),
+    ('\u{d4e9}', '\u{d503}', Break::Lvt),
+    ('\u{d504}', '\u{d504}', Break::Lv),
+    ('\u{d505}', '\u{d51f}', Break::Lvt),
+    ('\u{d520}', '\u{d520}', Break::Lv),
+    ('\u{d521}', '\u{d53b}', Break::Lvt),
+    ('\u{d53c}', '\u{d53c}', Break::Lv),
+    ('\u{d53d}', '\u{d557}', Break::Lvt),
+    ('\u{d558}', '\u{d558}', Break::Lv),
+    ('\u{d559}', '\u{d573}', Break::Lvt),
+    ('\u{d574}', '\u{d574}', Break::Lv),
+    ('\u{d575}', '\u{d58f}', Break::Lvt),
+    ('\u{d590}', '\u{d590}', Break::Lv),
+    ('\u{d591}', '\u{d5ab}', Break::Lvt),
+    ('\u{d5ac}', '\u{d5ac}', Break::Lv),
+    ('\u{d5ad}', '\u{d5c7}', Break::Lvt),
+    ('\u{d5c8}', '\u{d5c8}', Break::Lv),
+    ('\u{d5c9}', '\u{d5e3}', Break::Lvt),
+    ('\u{d5e4}', '\u{d5e4}', Break::Lv),
+    ('\u{d5e5}', '\u{d5ff}', Break::Lvt),
+    ('\u{d600}', '\u{d600}', Break::Lv),
+    ('\u{d601}', '\u{d61b}', Break::Lvt),
+    ('\u{d61c}', '\u{d61c}', Break::Lv),
+    ('\u{d61d}', '\u{d637}', Break::Lvt),
+    ('\u{d638}', '\u{d638}', Break::Lv),
+    ('\u{d639}', '\u{d653}', Break::Lvt),
+    ('\u{d654}', '\u{d654}', Break::Lv),
+    ('\u{d655}', '\u{d66f}', Break::Lvt),
+    ('\u{d670}', '\u{d670}', Break::Lv),
+    ('\u{d671}', '\u{d68b}', Break::Lvt),
+    ('\u{d68c}', '\u{d68c}', Break::Lv),
+    ('\u{d68d}', '\u{d6a7}', Break::Lvt),
+    ('\u{d6a8}', '\u{d6a8}', Break::Lv),
+    ('\u{d6a9}', '\u{d6c3}', Break::Lvt),
+    ('\u{d6c4}', '\u{d6c4}', Break::Lv),
+    ('\u{d6c5}', '\u{d6df}', Break::Lvt),
+    ('\u{d6e0}', '\u{d6e0}', Break::Lv),
+    ('\u{d6e1}', '\u{d6fb}', Break::Lvt),
+    ('\u{d6fc}', '\u{d6fc}', Break::Lv),
+    ('\u{d6fd}', '\u{d717}', Break::Lvt),
+    ('\u{d718}', '\u{d718}', Break::Lv),
+    ('\u{d719}', '\u{d733}', Break::Lvt),
+    ('\u{d734}', '\u{d734}', Break::Lv),
+    ('\u{d735}', '\u{d74f}', Break::Lvt),
+    ('\u{d750}', '\u{d750}', Break::Lv),
+    ('\u{d751}', '\u{d76b}', Break::Lvt),
+    ('\u{d76c}', '\u{d76c}', Break::Lv),
+    ('\u{d76d}', '\u{d787}', Break::Lvt),
+    ('\u{d788}', '\u{d788}', Break::Lv),
+    ('\u{d789}', '\u{d7a3}', Break::Lvt),
+    ('\u{d7b0}', '\u{d7c6}', Break::V),
+    ('\u{d7cb}', '\u{d7fb}', Break::T),
+    ('\u{fb1e}', '\u{fb1e}', Break::Extend),
+    ('\u{fe00}', '\u{fe0f}', Break::Extend),
+    ('\u{fe20}', '\u{fe2f}', Break::Extend),
+    ('\u{feff}', '\u{feff}', Break::Control),
+    ('\u{ff9e}', '\u{ff9f}', Break::Extend),
+    ('\u{fff0}', '\u{fffb}', Break::Control),
+    ('\u{101fd}', '\u{101fd}', Break::Extend),
+    ('\u{102e0}', '\u{102e0}', Break::Extend),
+    ('\u{10376}', '\u{1037a}', Break::Extend),
+    ('\u{10a01}', '\u{10a03}', Break::Extend),
+    ('\u{10a05}', '\u{10a06}', Break::Extend),
+    ('\u{10a0c}', '\u{10a0f}', Break::Extend),
+    ('\u{10a38}', '\u{10a3a}', Break::Extend),
+    ('\u{10a3f}', '\u{10a3f}', Break::Extend),
+    ('\u{10ae5}', '\u{10ae6}', Break::Extend),
+    ('\u{10d24}', '\u{10d27}', Break::Extend),
+    ('\u{10d69}', '\u{10d6d}', Break::Extend),
+    ('\u{10eab}', '\u{10eac}', Break::Extend),
+    ('\u{10efc}', '\u{10eff}', Break::Extend),
+    ('\u{10f46}', '\u{10f50}', Break::Extend),
+    ('\u{10f82}', '\u{10f85}', Break::Extend),
+    ('\u{11000}', '\u{11000}', Break::SpacingMark),
+    ('\u{11001}', '\u{11001}', Break::Extend),
+    ('\u{11002}', '\u{11002}', Break::SpacingMark),
+    ('\u{11038}', '\u{11046}', Break::Extend),
+    ('\u{11070}', '\u{11070}', Break::Extend),
+    ('\u{11073}', '\u{11074}', Break::Extend),
+    ('\u{1107f}', '\u{11081}', Break::Extend),
+    ('\u{11082}', '\u{11082}', Break::SpacingMark),
+    ('\u{110b0}', '\u{110b2}', Break::SpacingMark),
+    ('\u{110b3}', '\u{110b6}', Break::Extend),
+    ('\u{110b7}', '\u{110b8}', Break::SpacingMark),
+    ('\u{110b9}', '\u{110ba}', Break::Extend),
+    ('\u{110bd}', '\u{110bd}', Break::Prepend),
+    ('\u{110c2}', '\u{110c2}', Break::Extend),
+    ('\u{110cd}', '\u{110cd}', Break::Prepend),
+    ('\u{11100}', '\u{11102}', Break::Extend),
+    ('\u{11127}', '\u{1112b}', Break::Extend),
+    ('\u{1112c}', '\u{1112c}', Break::SpacingMark),
+    ('\u{1112d}', '\u{11134}', Break::Extend),
+    ('\u{11145}', '\u{11146}', Break::SpacingMark),
+    ('\u{11173}', '\u{11173}', Break::Extend),
+    ('\u{11180}', '\u{11181}', Break::Extend),
+    ('\u{11182}', '\u{11182}', Break::SpacingMark),
+    ('\u{111b3}', '\u{111b5}', Break::SpacingMark),
+    ('\u{111b6}', '\u{111be}', Break::Extend),
+    ('\u{111bf}', '\u{111bf}', Break::SpacingMark),
+    ('\u{111c0}', '\u{111c0}', Break::Extend),
+    ('\u{111c2}', '\u{111c3}', Break::Prepend),
+    ('\u{111c9}', '\u{111cc}', Break::Extend),
+    ('\u{111ce}', '\u{111ce}', Break::SpacingMark),
+    ('\u{111cf}', '\u{111cf}', Break::Extend),
+    ('\u{1122c}', '\u{1122e}', Break::SpacingMark),
+    ('\u{1122f}', '\u{11231}', Break::Extend),
+    ('\u{11232}', '\u{11233}', Break::SpacingMark),
+    ('\u{11234}', '\u{11237}', Break::Extend),
+    ('\u{1123e}', '\u{1123e}', Break::Extend),
+    ('\u{11241}', '\u{11241}', Break::Extend),
+    ('\u{112df}', '\u{112df}', Break::Extend),
+    ('\u{112e0}', '\u{112e2}', Break::SpacingMark),
+    ('\u{112e3}', '\u{112ea}', Break::Extend),
+    ('\u{11300}', '\u{11301}', Break::Extend),
+    ('\u{11302}', '\u{11303}', Break::SpacingMark),
+    ('\u{1133b}', '\u{1133c}', Break::Extend),
+    ('\u{1133e}', '\u{1133e}', Break::Extend),
+    ('\u{1133f}', '\u{1133f}', Break::SpacingMark),
+    ('\u{11340}', '\u{11340}', Break::Extend),
+    ('\u{11341}', '\u{11344}', Break::SpacingMark),
+    ('\u{11347}', '\u{11348}', Break::SpacingMark),
+    ('\u{1134b}', '\u{1134c}', Break::SpacingMark),
+    ('\u{1134d}', '\u{1134d}', Break::Extend),
+    ('\u{11357}', '\u{11357}', Break::Extend),
+    ('\u{11362}', '\u{11363}', Break::SpacingMark),
+    ('\u{11366}', '\u{1136c}', Break::Extend),
+    ('\u{11370}', '\u{11374}', Break::Extend),
+    ('\u{113b8}', '\u{113b8}', Break::Extend),
+    ('\u{113b9}', '\u{113ba}', Break::SpacingMark),
+    ('\u{113bb}', '\u{113c0}', Break::Extend),
+    ('\u{113c2}', '\u{113c2}', Break::Extend),
+    ('\u{113c5}', '\u{113c5}', Break::Extend),
+    ('\u{113c7}', '\u{113c9}', Break::Extend),
+    ('\u{113ca}', '\u{113ca}', Break::SpacingMark),
+    ('\u{113cc}', '\u{113cd}', Break::SpacingMark),
+    ('\u{113ce}', '\u{113d0}', Break::Extend),
+    ('\u{113d1}', '\u{113d1}', Break::Prepend),
+    ('\u{113d2}', '\u{113d2}', Break::Extend),
+    ('\u{113e1}', '\u{113e2}', Break::Extend),
+    ('\u{11435}', '\u{11437}', Break::SpacingMark),
+    ('\u{11438}', '\u{1143f}', Break::Extend),
+    ('\u{11440}', '\u{11441}', Break::SpacingMark),
+    ('\u{11442}', '\u{11444}', Break::Extend),
+    ('\u{11445}', '\u{11445}', Break::SpacingMark),
+    ('\u{11446}', '\u{11446}', Break::Extend),
+    ('\u{1145e}', '\u{1145e}', Break::Extend),
+    ('\u{114b0}', '\u{114b0}', Break::Extend),
+    ('\u{114b1}', '\u{114b2}', Break::SpacingMark),
+    ('\u{114b3}', '\u{114b8}', Break::Extend),
+    ('\u{114b9}', '\u{114b9}', Break::SpacingMark),
+    ('\u{114ba}', '\u{114ba}', Break::Extend),
+    ('\u{114bb}', '\u{114bc}', Break::SpacingMark),
+    ('\u{114bd}', '\u{114bd}', Break::Extend),
+    ('\u{114be}', '\u{114be}', Break::SpacingMark),
+    ('\u{114bf}', '\u{114c0}', Break::Extend),
+    ('\u{114c1}', '\u{114c1}', Break::SpacingMark),
+    ('\u{114c2}', '\u{114c3}', Break::Extend),
+    ('\u{115af}', '\u{115af}', Break::Extend),
+    ('\u{115b0}', '\u{115b1}', Break::SpacingMark),
+    ('\u{115b2}', '\u{115b5}', Break::Extend),
+    ('\u{115b8}', '\u{115bb}', Break::SpacingMark),
+    ('\u{115bc}', '\u{115bd}', Break::Extend),
+    ('\u{115be}', '\u{115be}', Break::SpacingMark),
+    ('\u{115bf}', '\u{115c0}', Break::Extend),
+    ('\u{115dc}', '\u{115dd}', Break::Extend),
+    ('\u{11630}', '\u{11632}', Break::SpacingMark),
+    ('\u{11633}', '\u{1163a}', Break::Extend),
+    ('\u{1163b}', '\u{1163c}', Break::SpacingMark),
+    ('\u{1163d}', '\u{1163d}', Break::Extend),
+    ('\u{1163e}', '\u{1163e}', Break::SpacingMark),
+    ('\u{1163f}', '\u{11640}', Break::Extend),
+    ('\u{116ab}', '\u{116ab}', Break::Extend),
+    ('\u{116ac}', '\u{116ac}', Break::SpacingMark),
+    ('\u{116ad}', '\u{116ad}', Break::Extend),
+    ('\u{116ae}', '\u{116af}', Break::SpacingMark),
+    ('\u{116b0}', '\u{116b7}', Break::Extend),
+    ('\u{1171d}', '\u{1171d}', Break::Extend),
+    ('\u{1171e}', '\u{1171e}', Break::SpacingMark),
+    ('\u{1171f}', '\u{1171f}', Break::Extend),
+    ('\u{11722}', '\u{11725}', Break::Extend),
+    ('\u{11726}', '\u{11726}', Break::SpacingMark),
+    ('\u{11727}', '\u{1172b}', Break::Extend),
+    ('\u{1182c}', '\u{1182e}', Break::SpacingMark),
+    ('\u{1182f}', '\u{11837}', Break::Extend),
+    ('\u{11838}', '\u{11838}', Break::SpacingMark),
+    ('\u{11839}', '\u{1183a}', Break::Extend),
+    ('\u{11930}', '\u{11930}', Break::Extend),
+    ('\u{11931}', '\u{11935}', Break::SpacingMark),
+    ('\u{11937}', '\u{11938}', Break::SpacingMark),
+    ('\u{1193b}', '\u{1193e}', Break::Extend),
+    ('\u{1193f}', '\u{1193f}', Break::Prepend),
+    ('\u{11940}', '\u{11940}', Break::SpacingMark),
+    ('\u{11941}', '\u{11941}', Break::Prepend),
+    ('\u{11942}', '\u{11942}', Break::SpacingMark),
+    ('\u{11943}', '\u{11943}', Break::Extend),
+    ('\u{119d1}', '\u{119d3}', Break::SpacingMark),
+    ('\u{119d4}', '\u{119d7}', Break::Extend),
+    ('\u{119da}', '\u{119db}', Break::Extend),
+    ('\u{119dc}', '\u{119df}', Break::SpacingMark),
+    ('\u{119e0}', '\u{119e0}', Break::Extend),
+    ('\u{119e4}', '\u{119e4}', Break::SpacingMark),
+    ('\u{11a01}', '\u{11a0a}', Break::Extend),
+    ('\u{11a33}', '\u{11a38}', Break::Extend),
+    ('\u{11a39}', '\u{11a39}', Break::SpacingMark),
+    ('\u{11a3a}', '\u{11a3a}', Break::Prepend),
+    ('\u{11a3b}', '\u{11a3e}', Break::Extend),
+    ('\u{11a47}', '\u{11a47}', Break::Extend),
+    ('\u{11a51}', '\u{11a56}', Break::Extend),
+    ('\u{11a57}', '\u{11a58}', Break::SpacingMark),
+    ('\u{11a59}', '\u{11a5b}', Break::Extend),
+    ('\u{11a84}', '\u{11a89}', Break::Prepend),
+    ('\u{11a8a}', '\u{11a96}', Break::Extend),
+    ('\u{11a97}', '\u{11a97}', Break::SpacingMark),
+    ('\u{11a98}', '\u{11a99}', Break::Extend),
+    ('\u{11c2f}', '\u{11c2f}', Break::SpacingMark),
+    ('\u{11c30}', '\u{11c36}', Break::Extend),
+    ('\u{11c38}', '\u{11c3d}', Break::Extend),
+    ('\u{11c3e}', '\u{11c3e}', Break::SpacingMark),
+    ('\u{11c3f}', '\u{11c3f}', Break::Extend),
+    ('\u{11c92}', '\u{11ca7}', Break::Extend),
+    ('\u{11ca9}', '\u{11ca9}', Break::SpacingMark),
+    ('\u{11caa}', '\u{11cb0}', Break::Extend),
+    ('\u{11cb1}', '\u{11cb1}', Break::SpacingMark),
+    ('\u{11cb2}', '\u{11cb3}', Break::Extend),
+    ('\u{11cb4}', '\u{11cb4}', Break::SpacingMark),
+    ('\u{11cb5}', '\u{11cb6}', Break::Extend),
+    ('\u{11d31}', '\u{11d36}', Break::Extend),
+    ('\u{11d3a}', '\u{11d3a}', Break::Extend),
+    ('\u{11d3c}', '\u{11d3d}', Break::Extend),
+    ('\u{11d3f}', '\u{11d45}', Break::Extend),
+    ('\u{11d46}', '\u{11d46}', Break::Prepend),
+    ('\u{11d47}', '\u{11d47}', Break::Extend),
+    ('\u{11d8a}', '\u{11d8e}', Break::SpacingMark),
+    ('\u{11d90}', '\u{11d91}', Break::Extend),
+    ('\u{11d93}', '\u{11d94}', Break::SpacingMark),
+    ('\u{11d95}', '\u{11d95}', Break::Extend),
+    ('\u{11d96}', '\u{11d96}', Break::SpacingMark),
+    ('\u{11d97}', '\u{11d97}', Break::Extend),
+    ('\u{11ef3}', '\u{11ef4}', Break::Extend),
+    ('\u{11ef5}', '\u{11ef6}', Break::SpacingMark),
+    ('\u{11f00}', '\u{11f01}', Break::Extend),
+    ('\u{11f02}', '\u{11f02}', Break::Prepend),
+    ('\u{11f03}', '\u{11f03}', Break::SpacingMark),
+    ('\u{11f34}', '\u{11f35}', Break::SpacingMark),
+    ('\u{11f36}', '\u{11f3a}', Break::Extend),
+    ('\u{11f3e}', '\u{11f3f}', Break::SpacingMark),
+    ('\u{11f40}', '\u{11f42}', Break::Extend),
+    ('\u{11f5a}', '\u{11f5a}', Break::Extend),
+    ('\u{13430}', '\u{1343f}', Break::Control),
+    ('\u{13440}', '\u{13440}', Break::Extend),
+    ('\u{13447}', '\u{13455}', Break::Extend),
+    ('\u{1611e}', '\u{16129}', Break::Extend),
+    ('\u{1612a}', '\u{1612c}', Break::SpacingMark),
+    ('\u{1612d}', '\u{1612f}', Break::Extend),
+    ('\u{16af0}', '\u{16af4}', Break::Extend),
+    ('\u{16b30}', '\u{16b36}', Break::Extend),
+    ('\u{16d63}', '\u{16d63}', Break::V),
+    ('\u{16d67}', '\u{16d6a}', Break::V),
+    ('\u{16f4f}', '\u{16f4f}', Break::Extend),
+    ('\u{16f51}', '\u{16f87}', Break::SpacingMark),
+    ('\u{16f8f}', '\u{16f92}', Break::Extend),
+    ('\u{16fe4}', '\u{16fe4}', Break::Extend),
+    ('\u{16ff0}', '\u{16ff1}', Break::Extend),
+    ('\u{1bc9d}', '\u{1bc9e}', Break::Extend),
+    ('\u{1bca0}', '\u{1bca3}', Break::Control),
+    ('\u{1cf00}', '\u{1cf2d}', Break::Extend),
+    ('\u{1cf30}', '\u{1cf46}', Break::Extend),
+    ('\u{1d165}', '\u{1d169}', Break::Extend),
+    ('\u{1d16d}', '\u{1d172}', Break::Extend),
+    ('\u{1d173}', '\u{1d17a}', Break::Control),
+    ('\u{1d17b}', '\u{1d182}', Break::Extend),
+    ('\u{1d185}', '\u{1d18b}', Break::Extend),
+    ('\u{1d1aa}', '\u{1d1ad}', Break::Extend),
+    ('\u{1d242}', '\u{1d244}', Break::Extend),
+    ('\u{1da00}', '\u{1da36}', Break::Extend),
+    ('\u{1da3b}', '\u{1da6c}', Break::Extend),
+    ('\u{1da75}', '\u{1da75}', Break::Extend),
+    ('\u{1da84}', '\u{1da84}', Break::Extend),
+    ('\u{1da9b}', '\u{1da9f}', Break::Extend),
+    ('\u{1daa1}', '\u{1daaf}', Break::Extend),
+    ('\u{1e000}', '\u{1e006}', Break::Extend),
+    ('\u{1e008}', '\u{1e018}', Break::Extend),
+    ('\u{1e01b}', '\u{1e021}', Break::Extend),
+    ('\u{1e023}', '\u{1e024}', Break::Extend),
+    ('\u{1e026}', '\u{1e02a}', Break::Extend),
+    ('\u{1e08f}', '\u{1e08f}', Break::Extend),
+    ('\u{1e130}', '\u{1e136}', Break::Extend),
+    ('\u{1e2ae}', '\u{1e2ae}', Break::Extend),
+    ('\u{1e2ec}', '\u{1e2ef}', Break::Extend),
+    ('\u{1e4ec}', '\u{1e4ef}', Break::Extend),
+    ('\u{1e5ee}', '\u{1e5ef}', Break::Extend),
+    ('\u{1e8d0}', '\u{1e8d6}', Break::Extend),
+    ('\u{1e944}', '\u{1e94a}', Break::Extend),
+    ('\u{1f1e6}', '\u{1f1ff}', Break::RegionalIndicator),
+    ('\u{1f3fb}', '\u{1f3ff}', Break::Extend),
+    ('\u{e0000}', '\u{e001f}', Break::Control),
+    ('\u{e0020}', '\u{e007f}', Break::Extend),
+    ('\u{e0080}', '\u{e00ff}', Break::Control),
+    ('\u{e0100}', '\u{e01ef}', Break::Extend),
+    ('\u{e01f0}', '\u{e0fff}', Break::Control),
+];
+
+/// Ranges of the `Extended_Pictographic` characters, in order
+#[rustfmt::skip]
+pub(super) const EXTENDED_PICTOGRAPHIC: &[(char, char)] = &[
+    ('\u{a9}', '\u{a9}'),
+    ('\u{ae}', '\u{ae}'),
+    ('\u{203c}', '\u{203c}'),
+    ('\u{2049}', '\u{2049}'),
+    ('\u{2122}', '\u{2122}'),
+    ('\u{2139}', '\u{2139}'),
+    ('\u{2194}', '\u{2199}'),
+    ('\u{21a9}', '\u{21aa}'),
+    ('\u{231a}', '\u{231b}'),
+    ('\u{2328}', '\u{2328}'),
+    ('\u{2388}', '\u{2388}'),
+    ('\u{23cf}', '\u{23cf}'),
+    ('\u{23e9}', '\u{23f3}'),
+    ('\u{23f8}', '\u{23fa}'),
+    ('\u{24c2}', '\u{24c2}'),
+    ('\u{25aa}', '\u{25ab}'),
+    ('\u{25b6}', '\u{25b6}'),
+    ('\u{25c0}', '\u{25c0}'),
+    ('\u{25fb}', '\u{25fe}'),
+    ('\u{2600}', '\u{2605}'),
+    ('\u{2607}', '\u{2612}'),
+    ('\u{2614}', '\u{2685}'),
+    ('\u{2690}', '\u{2705}'),
+    ('\u{2708}', '\u{2712}'),
+    ('\u{2714}', '\u{2714}'),
+    ('\u{2716}', '\u{2716}'),
+    ('\u{271d}', '\u{271d}'),
+    ('\u{2721}', '\u{2721}'),
+    ('\u{2728}', '\u{2728}'),
+    ('\u{2733}', '\u{2734}'),
+    ('\u{2744}', '\u{2744}'),
+    ('\u{2747}', '\u{2747}'),
+    ('\u{274c}', '\u{274c}'),
+    ('\u{274e}', '\u{274e}'),
+    ('\u{2753}', '\u{2755}'),
+    ('\u{2757}', '\u{2757}'),
+    ('\u{2763}', '\u{2767}'),
+    ('\u{2795}', '\u{2797}'),
+    ('\u{27a1}', '\u{27a1}'),
+    ('\u{27b0}', '\u{27b0}'),
+    ('\u{27bf}', '\u{27bf}'),
+    ('\u{2934}', '\u{2935}'),
+    ('\u{2b05}', '\u{2b07}'),
+    ('\u{2b1b}', '\u{2b1c}'),
+    ('\u{2b50}', '\u{2b50}'),
+    ('\u{2b55}', '\u{2b55}'),
+    ('\u{3030}', '\u{3030}'),
+    ('\u{303d}', '\u{303d}'),
+    ('\u{3297}', '\u{3297}'),
+    ('\u{3299}', '\u{3299}'),
+    ('\u{1f000}', '\u{1f0ff}'),
+    ('\u{1f10d}', '\u{1f10f}'),
+    ('\u{1f12f}', '\u{1f12f}'),
+    ('\u{1f16c}', '\u{1f171}'),
+    ('\u{1f17e}', '\u{1f17f}'),
+    ('\u{1f18e}', '\u{1f18e}'),
+    ('\u{1f191}', '\u{1f19a}'),
+    ('\u{1f1ad}', '\u{1f1e5}'),
+    ('\u{1f201}', '\u{1f20f}'),
+    ('\u{1f21a}', '\u{1f21a}'),
+    ('\u{1f22f}', '\u{1f22f}'),
+    ('\u{1f232}', '\u{1f23a}'),
+    ('\u{1f23c}', '\u{1f23f}'),
+    ('\u{1f249}', '\u{1f3fa}'),
+    ('\u{1f400}', '\u{1f53d}'),
+    ('\u{1f546}', '\u{1f64f}'),
+    ('\u{1f680}', '\u{1f6ff}'),
+    ('\u{1f774}', '\u{1f77f}'),
+    ('\u{1f7d5}', '\u{1f7ff}'),
+    ('\u{1f80c}', '\u{1f80f}'),
+    ('\u{1f848}', '\u{1f84f}'),
+    ('\u{1f85a}', '\u{1f85f}'),
+    ('\u{1f888}', '\u{1f88f}'),
+    ('\u{1f8ae}', '\u{1f8ff}'),
+    ('\u{1f90c}', '\u{1f93a}'),
+    ('\u{1f93c}', '\u{1f945}'),
+    ('\u{1f947}', '\u{1faff}'),
+    ('\u{1fc00}', '\u{1fffd}'),
+];
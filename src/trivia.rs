@@ -0,0 +1,160 @@
+//! Source text kept around nodes for lossless rewriting
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::error::offset_in;
+use crate::{Node, Writer};
+
+/// The source text of a node and the comments and blank lines around it
+///
+/// Recorded by [`Parser::preserve_trivia`](crate::Parser::preserve_trivia).
+/// The [`Writer`] emits a node that has not been changed since parsing
+/// exactly as it was written; a changed node is written afresh, still
+/// surrounded by its `leading` and `trailing` text. All three strings
+/// include their line endings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    /// Blank lines, detached comments, and `@let` definitions above the
+    /// node (its own doc comment is part of `source`)
+    pub leading: String,
+    /// The node's doc comment and lines as written
+    pub source: String,
+    /// Comments and blank lines after the last node of a block or document
+    pub trailing: String,
+    /// Fingerprint of the node as parsed
+    parsed: u64,
+}
+
+impl Trivia {
+    /// Check whether `node` is still as it was parsed, so `source` can be
+    /// written in its place
+    pub fn is_unchanged(&self, node: &Node) -> bool {
+        fingerprint(node) == self.parsed
+    }
+}
+
+/// Hash of a node's canonical text, which ignores trivia
+fn fingerprint(node: &Node) -> u64 {
+    let mut text = String::new();
+    Writer::new()
+        .preserve_trivia(false)
+        .write_node(&mut text, node, 0);
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Splits the input of one block or document into the trivia of its nodes
+pub(crate) struct TriviaTracker {
+    /// Start of the text not yet given to a node
+    start: usize,
+    /// Start of the comment lines directly above the next node
+    doc_start: usize,
+}
+
+impl TriviaTracker {
+    /// Start tracking at `next`, the first line of the block's contents
+    pub(crate) fn new(input: &str, next: Option<&str>) -> Self {
+        let start = line_start(input, next);
+        Self {
+            start,
+            doc_start: start,
+        }
+    }
+
+    /// Note a blank or comment line, before it is added to `comment`
+    pub(crate) fn skipped(&mut self, input: &str, line: &str, comment: &[&str]) {
+        if comment.is_empty() {
+            self.doc_start = line_start(input, Some(line));
+        }
+    }
+
+    /// Give `node`, whose key is on `line` and which ends before `next`,
+    /// the text since the previous node
+    pub(crate) fn attach(&mut self, input: &str, node: &mut Node, line: &str, next: Option<&str>) {
+        let key_start = line_start(input, Some(line));
+        let node_start = if node.doc.is_some() {
+            self.doc_start.min(key_start)
+        } else {
+            key_start
+        };
+        let end = line_start(input, next);
+        let leading = input.get(self.start..node_start).unwrap_or_default();
+        let source = input.get(node_start..end).unwrap_or_default();
+        node.trivia = Some(Box::new(Trivia {
+            leading: leading.to_string(),
+            source: source.to_string(),
+            trailing: String::new(),
+            parsed: fingerprint(node),
+        }));
+        self.start = end;
+        self.doc_start = end;
+    }
+
+    /// Give the text left before `end` (a closing line, or the end of the
+    /// input) to the block's last node
+    pub(crate) fn finish(&self, input: &str, last: Option<&mut Node>, end: Option<&str>) {
+        let end = line_start(input, end);
+        if let Some(trivia) = last.and_then(|node| node.trivia.as_mut()) {
+            trivia.trailing = input.get(self.start..end).unwrap_or_default().to_string();
+        }
+    }
+}
+
+/// Byte offset of `line` (a slice of `input`), or the end of the input
+fn line_start(input: &str, line: Option<&str>) -> usize {
+    line.and_then(|line| offset_in(input, line))
+        .unwrap_or(input.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Parser, Value};
+
+    const INPUT: &str = "#!/usr/bin/env up\n\n# Name\nname   app\r\n\n\n@let port 8080\nserver {\n  # detached\n\n  # Port\n  port!int  ${port}\n  tags [a,b]\n  # end of server\n}\n\n# end of file\nlast  1";
+
+    #[test]
+    fn test_round_trip() {
+        let parser = Parser::new().preserve_trivia(true);
+        let doc = parser.parse_document(INPUT).unwrap();
+        assert_eq!(crate::to_string(&doc), INPUT);
+
+        let trivia = doc.nodes[0].trivia.as_ref().unwrap();
+        assert_eq!(trivia.leading, "#!/usr/bin/env up\n\n");
+        assert_eq!(trivia.source, "# Name\nname   app\r\n");
+        assert_eq!(doc.nodes[0].doc.as_deref(), Some("Name"));
+        let Value::Block(server) = &doc.nodes[1].value else {
+            panic!("expected a block");
+        };
+        let port = server.get_node("port").unwrap().trivia.as_ref().unwrap();
+        assert_eq!(port.leading, "  # detached\n\n");
+        let tags = server.get_node("tags").unwrap().trivia.as_ref().unwrap();
+        assert_eq!(tags.trailing, "  # end of server\n");
+        let last = doc.nodes[2].trivia.as_ref().unwrap();
+        assert_eq!(
+            (last.leading.as_str(), last.source.as_str()),
+            ("\n", "# end of file\nlast  1")
+        );
+        // Equality ignores trivia
+        assert_eq!(doc, parse(INPUT).unwrap());
+        assert!(parse(INPUT).unwrap().nodes[0].trivia.is_none());
+    }
+
+    #[test]
+    fn test_changed_nodes_are_rewritten() {
+        let parser = Parser::new().preserve_trivia(true);
+        let mut doc = parser.parse_document(INPUT).unwrap();
+        doc.set_path("server.port", Value::Int(9090));
+        doc.set_path("added", Value::Bool(true));
+
+        assert_eq!(
+            crate::to_string(&doc),
+            "#!/usr/bin/env up\n\n# Name\nname   app\r\n\n\n@let port 8080\nserver {\n  # detached\n\n  # Port\n  port!int 9090\n  tags [a,b]\n  # end of server\n}\n\n# end of file\nlast  1\nadded!bool true\n"
+        );
+        assert_eq!(
+            crate::Writer::new().preserve_trivia(false).to_string(&doc),
+            "# Name\nname app\nserver {\n  # Port\n  port!int 9090\n  tags [a, b]\n}\n# end of file\nlast 1\nadded!bool true\n"
+        );
+    }
+}
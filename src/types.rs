@@ -0,0 +1,77 @@
+//! Typed scalar values produced from type annotations
+
+mod bytes;
+mod catalog;
+mod color;
+mod datetime;
+mod duration;
+mod geo;
+mod mime;
+mod money;
+mod path;
+mod registry;
+#[cfg(feature = "semver")]
+mod version;
+
+pub use catalog::{Annotation, AnnotationCatalog, Shape};
+pub use color::Color;
+pub use datetime::{Date, Time, Timestamp};
+pub use geo::GeoPoint;
+pub use mime::MediaType;
+pub use money::Money;
+pub use path::PathCheck;
+pub use registry::{CustomValue, TypeHandler, TypeRegistry};
+
+pub(crate) use bytes::encode as encode_base64;
+pub(crate) use duration::{format as format_duration, parse as parse_duration};
+pub(crate) use path::check as check_path;
+
+use crate::{ErrorKind, ParseError, Value};
+
+/// Convert a scalar into a typed value according to its annotation
+///
+/// Returns `None` for annotations without a dedicated type, in which case the
+/// scalar stays a plain string.
+pub(crate) fn coerce(annotation: &str, text: &str) -> Option<Result<Value, ParseError>> {
+    match annotation {
+        "base64" | "bytes" => Some(bytes::decode(text).map(Value::Bytes)),
+        "bool" => Some(match text {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(mismatch("bool", text)),
+        }),
+        "color" => Some(text.parse().map(Value::Color)),
+        "date" => Some(text.parse().map(Value::Date)),
+        "dur" => Some(duration::parse(text).map(Value::Duration)),
+        "float" => Some(
+            text.parse()
+                .map(Value::Float)
+                .map_err(|_| mismatch("float", text)),
+        ),
+        "geo" => Some(text.parse().map(Value::Geo)),
+        "int" => Some(
+            text.parse()
+                .map(Value::Int)
+                .map_err(|_| mismatch("int", text)),
+        ),
+        "mime" => Some(text.parse().map(Value::Mime)),
+        "money" => Some(text.parse().map(Value::Money)),
+        "null" if text.is_empty() || text == "null" => Some(Ok(Value::Null)),
+        "null" => Some(Err(mismatch("null", text))),
+        "path" => Some(Ok(Value::Path(path::normalize(text)))),
+        #[cfg(feature = "semver")]
+        "semver" => Some(version::parse_version(text)),
+        #[cfg(feature = "semver")]
+        "semver-req" => Some(version::parse_version_req(text)),
+        "time" => Some(text.parse().map(Value::Time)),
+        "ts" => Some(text.parse().map(Value::Timestamp)),
+        _ => None,
+    }
+}
+
+fn mismatch(annotation: &str, text: &str) -> ParseError {
+    ParseError::new(
+        ErrorKind::TypeMismatch,
+        format!("expected {}, found `{}`", annotation, text),
+    )
+}
@@ -0,0 +1,83 @@
+//! Binary data (`!bytes`, `!base64`), written in base64
+
+use crate::{ErrorKind, ParseError};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decode standard base64, with or without padding
+///
+/// Whitespace is skipped, so long values can be wrapped across the lines
+/// of a multiline block.
+pub(crate) fn decode(text: &str) -> Result<Vec<u8>, ParseError> {
+    let invalid = |message: String| ParseError::new(ErrorKind::InvalidValue, message);
+    let symbols: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let data = symbols
+        .strip_suffix(b"==")
+        .or_else(|| symbols.strip_suffix(b"="))
+        .unwrap_or(&symbols);
+    if data.len() % 4 == 1 || (data.len() < symbols.len() && symbols.len() % 4 != 0) {
+        return Err(invalid(format!("invalid base64 length in `{}`", text)));
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &symbol in data {
+        let value = ALPHABET
+            .iter()
+            .position(|&c| c == symbol)
+            .ok_or_else(|| invalid(format!("invalid base64 character `{}`", symbol as char)))?;
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Encode bytes as padded standard base64
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(bytes), text);
+            assert_eq!(decode(text).unwrap(), bytes);
+        }
+        assert_eq!(decode("Zm9v\n  YmE").unwrap(), b"fooba");
+        assert!(decode("Zm9vY").is_err());
+        assert!(decode("Zm=").is_err());
+        assert_eq!(
+            decode("Zm9!").unwrap_err().message,
+            "invalid base64 character `!`"
+        );
+    }
+}
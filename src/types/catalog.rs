@@ -0,0 +1,276 @@
+//! Registry of known type annotations
+
+use std::collections::BTreeMap;
+
+/// What kind of value an annotation expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// A single-line scalar, e.g. `price!money 12.50 EUR`
+    Scalar,
+    /// A fenced multiline string
+    Multiline,
+    /// A `{ ... }` block
+    Block,
+    /// An inline or multiline list
+    List,
+    /// A table with `columns` and `rows`
+    Table,
+    /// No particular shape
+    Any,
+}
+
+/// Description of one annotation, e.g. `!money`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Name as written after `!`
+    pub name: String,
+    /// Expected value shape
+    pub shape: Shape,
+    /// One-line human-readable description
+    pub description: String,
+}
+
+impl Annotation {
+    /// Describe an annotation
+    pub fn new(name: impl Into<String>, shape: Shape, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            shape,
+            description: description.into(),
+        }
+    }
+}
+
+/// The annotations a document may use, for editors, linters, and docs
+///
+/// [`AnnotationCatalog::builtin`] lists everything this crate understands;
+/// applications add their own types with [`register`](Self::register).
+/// Numeric annotations such as `!4` are dedent widths for multiline strings
+/// and are always known.
+#[derive(Debug, Clone)]
+pub struct AnnotationCatalog {
+    entries: BTreeMap<String, Annotation>,
+    dedent: Annotation,
+}
+
+impl AnnotationCatalog {
+    /// Create a catalog with no named annotations
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            dedent: Annotation::new(
+                "N",
+                Shape::Multiline,
+                "Strip N leading columns from each line of a multiline string",
+            ),
+        }
+    }
+
+    /// Create a catalog of the annotations built into this crate
+    pub fn builtin() -> Self {
+        let mut catalog = Self::new();
+        let builtin = [
+            (
+                "anchor",
+                Shape::Any,
+                "Value that later `!ref` nodes copy by the node's key",
+            ),
+            (
+                "base64",
+                Shape::Scalar,
+                "Binary data in base64; the same as `bytes`",
+            ),
+            ("bool", Shape::Scalar, "Boolean: true or false"),
+            (
+                "bytes",
+                Shape::Scalar,
+                "Binary data, written in standard base64",
+            ),
+            (
+                "color",
+                Shape::Scalar,
+                "RGBA color: #RGB, #RRGGBB, #RRGGBBAA, rgb(), rgba(), or a CSS name",
+            ),
+            ("date", Shape::Scalar, "Calendar date such as 2024-05-01"),
+            (
+                "dedent",
+                Shape::Multiline,
+                "Strip the leading whitespace shared by every line of a multiline string",
+            ),
+            (
+                "dur",
+                Shape::Scalar,
+                "Duration such as 1h30m, 250ms, or 1.5s",
+            ),
+            ("float", Shape::Scalar, "Floating-point number"),
+            (
+                "fold",
+                Shape::Scalar,
+                "Layout hint: write a long string over several lines joined by `\\`",
+            ),
+            (
+                "geo",
+                Shape::Scalar,
+                "Latitude,longitude pair in decimal degrees",
+            ),
+            (
+                "inline",
+                Shape::Block,
+                "Layout hint: write the block on one line, as `{ x 1, y 2 }`",
+            ),
+            ("int", Shape::Scalar, "64-bit signed integer"),
+            (
+                "internal",
+                Shape::Any,
+                "Seen only by the `internal` audience, see `Document::view_for`",
+            ),
+            #[cfg(feature = "json")]
+            (
+                "json",
+                Shape::Multiline,
+                "JSON document, parsed into a value",
+            ),
+            ("list", Shape::List, "List of values"),
+            (
+                "mime",
+                Shape::Scalar,
+                "Media type such as text/html; charset=utf-8",
+            ),
+            (
+                "money",
+                Shape::Scalar,
+                "Exact decimal amount with an ISO 4217 currency code",
+            ),
+            (
+                "multiline",
+                Shape::Any,
+                "Layout hint: write the block, list, or string over several lines",
+            ),
+            (
+                "null",
+                Shape::Scalar,
+                "Explicit null; the value must be empty or `null`",
+            ),
+            (
+                "path",
+                Shape::Scalar,
+                "Filesystem path, normalized for the platform",
+            ),
+            (
+                "public",
+                Shape::Any,
+                "Seen by every audience, see `Document::view_for`",
+            ),
+            (
+                "ref",
+                Shape::Any,
+                "Copy of the `!anchor` named by the value, optionally merged with a block",
+            ),
+            #[cfg(feature = "semver")]
+            ("semver", Shape::Scalar, "Semantic version such as 1.2.3"),
+            #[cfg(feature = "semver")]
+            (
+                "semver-req",
+                Shape::Scalar,
+                "Semantic version requirement such as ^1.2",
+            ),
+            (
+                "secret",
+                Shape::Any,
+                "Sensitive value, masked when logged through `Redacted`",
+            ),
+            ("string", Shape::Scalar, "Plain text"),
+            ("table", Shape::Table, "Table with columns and rows"),
+            (
+                "time",
+                Shape::Scalar,
+                "Time of day such as 09:30 or 09:30:15.5",
+            ),
+            (
+                "ts",
+                Shape::Scalar,
+                "RFC 3339 timestamp with a UTC offset, such as 2024-05-01T09:30:00Z",
+            ),
+            (
+                "up",
+                Shape::Multiline,
+                "Nested UP document, parsed into a block",
+            ),
+            (
+                "vars",
+                Shape::Block,
+                "Variables for `${...}` references, see `Document::resolve_vars`",
+            ),
+            #[cfg(feature = "yaml")]
+            (
+                "yaml",
+                Shape::Multiline,
+                "YAML document, parsed into a value",
+            ),
+        ];
+        for (name, shape, description) in builtin {
+            catalog.register(Annotation::new(name, shape, description));
+        }
+        catalog
+    }
+
+    /// Add or replace an annotation, returning the one it replaced
+    pub fn register(&mut self, annotation: Annotation) -> Option<Annotation> {
+        self.entries.insert(annotation.name.clone(), annotation)
+    }
+
+    /// Look up an annotation by name
+    pub fn get(&self, name: &str) -> Option<&Annotation> {
+        if !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(&self.dedent);
+        }
+        self.entries.get(name)
+    }
+
+    /// Check whether `name` is a known annotation
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Iterate over the named annotations in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.entries.values()
+    }
+}
+
+impl Default for AnnotationCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog() {
+        let catalog = AnnotationCatalog::builtin();
+        assert_eq!(catalog.get("money").unwrap().shape, Shape::Scalar);
+        assert_eq!(catalog.get("table").unwrap().shape, Shape::Table);
+        assert_eq!(catalog.get("4").unwrap().shape, Shape::Multiline);
+        assert_eq!(catalog.get("dedent").unwrap().shape, Shape::Multiline);
+        assert!(!catalog.contains("uuid"));
+
+        let names: Vec<&str> = catalog.iter().map(|a| a.name.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn test_register_custom_annotation() {
+        let mut catalog = AnnotationCatalog::builtin();
+        let uuid = Annotation::new("uuid", Shape::Scalar, "RFC 4122 UUID");
+        assert!(catalog.register(uuid.clone()).is_none());
+        assert_eq!(catalog.get("uuid"), Some(&uuid));
+
+        let replaced = catalog.register(Annotation::new("int", Shape::Scalar, "Integer"));
+        assert_eq!(replaced.unwrap().description, "64-bit signed integer");
+    }
+}
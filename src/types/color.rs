@@ -0,0 +1,176 @@
+//! RGBA colors (`!color`)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ErrorKind, ParseError};
+
+/// An 8-bit-per-channel RGBA color
+///
+/// Accepts hex notation (`#F80`, `#FF8800`, `#FF880080`), CSS-style
+/// `rgb(255, 136, 0)` / `rgba(255, 136, 0, 0.5)`, and the basic CSS color
+/// names. Always displays as hex, omitting the alpha channel when opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+    /// Alpha channel (255 is fully opaque)
+    pub a: u8,
+}
+
+const NAMED_COLORS: &[(&str, Color)] = &[
+    ("black", Color::rgb(0, 0, 0)),
+    ("silver", Color::rgb(192, 192, 192)),
+    ("gray", Color::rgb(128, 128, 128)),
+    ("grey", Color::rgb(128, 128, 128)),
+    ("white", Color::rgb(255, 255, 255)),
+    ("maroon", Color::rgb(128, 0, 0)),
+    ("red", Color::rgb(255, 0, 0)),
+    ("purple", Color::rgb(128, 0, 128)),
+    ("fuchsia", Color::rgb(255, 0, 255)),
+    ("magenta", Color::rgb(255, 0, 255)),
+    ("green", Color::rgb(0, 128, 0)),
+    ("lime", Color::rgb(0, 255, 0)),
+    ("olive", Color::rgb(128, 128, 0)),
+    ("yellow", Color::rgb(255, 255, 0)),
+    ("navy", Color::rgb(0, 0, 128)),
+    ("blue", Color::rgb(0, 0, 255)),
+    ("teal", Color::rgb(0, 128, 128)),
+    ("aqua", Color::rgb(0, 255, 255)),
+    ("cyan", Color::rgb(0, 255, 255)),
+    ("orange", Color::rgb(255, 165, 0)),
+    ("transparent", Color::rgba(0, 0, 0, 0)),
+];
+
+impl Color {
+    /// Create an opaque color
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    /// Create a color with an alpha channel
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize, width: usize| {
+            let v = u8::from_str_radix(&hex[i * width..(i + 1) * width], 16).ok()?;
+            Some(if width == 1 { v * 17 } else { v })
+        };
+        match hex.len() {
+            3 | 6 => {
+                let w = hex.len() / 3;
+                Some(Self::rgb(channel(0, w)?, channel(1, w)?, channel(2, w)?))
+            }
+            4 | 8 => {
+                let w = hex.len() / 4;
+                Some(Self::rgba(
+                    channel(0, w)?,
+                    channel(1, w)?,
+                    channel(2, w)?,
+                    channel(3, w)?,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn from_function(args: &str, with_alpha: bool) -> Option<Self> {
+        let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+        if parts.len() != if with_alpha { 4 } else { 3 } {
+            return None;
+        }
+        let r = parts[0].parse().ok()?;
+        let g = parts[1].parse().ok()?;
+        let b = parts[2].parse().ok()?;
+        let a = if with_alpha {
+            let alpha: f64 = parts[3].parse().ok()?;
+            if !(0.0..=1.0).contains(&alpha) {
+                return None;
+            }
+            (alpha * 255.0).round() as u8
+        } else {
+            255
+        };
+        Some(Self::rgba(r, g, b, a))
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let lower = s.to_ascii_lowercase();
+
+        let color = if let Some(hex) = s.strip_prefix('#') {
+            Self::from_hex(hex)
+        } else if let Some(args) = lower
+            .strip_prefix("rgba(")
+            .and_then(|r| r.strip_suffix(')'))
+        {
+            Self::from_function(args, true)
+        } else if let Some(args) = lower.strip_prefix("rgb(").and_then(|r| r.strip_suffix(')')) {
+            Self::from_function(args, false)
+        } else {
+            NAMED_COLORS
+                .iter()
+                .find(|(name, _)| *name == lower)
+                .map(|(_, color)| *color)
+        };
+
+        color.ok_or_else(|| {
+            ParseError::new(ErrorKind::InvalidValue, format!("invalid color `{}`", s))
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02X}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color_forms() {
+        let orange = Color::rgb(0xFF, 0x88, 0x00);
+        assert_eq!("#FF8800".parse::<Color>().unwrap(), orange);
+        assert_eq!("#f80".parse::<Color>().unwrap(), orange);
+        assert_eq!("rgb(255, 136, 0)".parse::<Color>().unwrap(), orange);
+        assert_eq!(
+            "rgba(255, 136, 0, 0.5)".parse::<Color>().unwrap(),
+            Color::rgba(255, 136, 0, 128)
+        );
+        assert_eq!("Navy".parse::<Color>().unwrap(), Color::rgb(0, 0, 128));
+    }
+
+    #[test]
+    fn test_color_display() {
+        assert_eq!(Color::rgb(255, 136, 0).to_string(), "#FF8800");
+        assert_eq!(Color::rgba(255, 136, 0, 128).to_string(), "#FF880080");
+    }
+
+    #[test]
+    fn test_invalid_color() {
+        assert!("#FF880".parse::<Color>().is_err());
+        assert!("rgb(256, 0, 0)".parse::<Color>().is_err());
+        assert!("rgba(0, 0, 0, 2)".parse::<Color>().is_err());
+        assert!("chartreuse-ish".parse::<Color>().is_err());
+    }
+}
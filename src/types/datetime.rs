@@ -0,0 +1,366 @@
+//! Calendar dates, times of day, and timestamps (`!date`, `!time`, `!ts`)
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{ErrorKind, ParseError};
+
+/// A calendar date in the proleptic Gregorian calendar
+///
+/// Written as `YYYY-MM-DD` (e.g. `2024-02-29`), as in RFC 3339.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    /// Year, 0 through 9999
+    pub year: u16,
+    /// Month, 1 through 12
+    pub month: u8,
+    /// Day of the month, starting at 1
+    pub day: u8,
+}
+
+/// A time of day without a time zone
+///
+/// Written as `HH:MM:SS` with an optional fraction of a second
+/// (e.g. `09:30:00.250`); the seconds may be left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    /// Hour, 0 through 23
+    pub hour: u8,
+    /// Minute, 0 through 59
+    pub minute: u8,
+    /// Second, 0 through 59
+    pub second: u8,
+    /// Fraction of the second in nanoseconds
+    pub nanosecond: u32,
+}
+
+/// A point in time: a local date and time with its offset from UTC
+///
+/// Written in RFC 3339 form, such as `2024-05-01T09:30:00Z` or
+/// `2024-05-01 09:30:00+02:00`. Two timestamps for the same instant in
+/// different offsets are not equal; compare [`unix_seconds`](Self::unix_seconds)
+/// for that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timestamp {
+    /// Local date
+    pub date: Date,
+    /// Local time of day
+    pub time: Time,
+    /// Offset from UTC in minutes, positive east
+    pub offset: i16,
+}
+
+impl Date {
+    /// Create a date, checking that the day exists
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, ParseError> {
+        if year > 9999 || !(1..=12).contains(&month) {
+            return Err(invalid("date", &format!("{}-{}-{}", year, month, day)));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("day {} out of range for {:04}-{:02}", day, year, month),
+            ));
+        }
+        Ok(Self { year, month, day })
+    }
+
+    /// Days since 1970-01-01, negative before it
+    pub fn days_since_epoch(&self) -> i64 {
+        // Howard Hinnant's days_from_civil, with years starting in March
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+}
+
+impl Time {
+    /// Create a time of day, checking the ranges of its fields
+    pub fn new(hour: u8, minute: u8, second: u8, nanosecond: u32) -> Result<Self, ParseError> {
+        if hour > 23 || minute > 59 || second > 59 || nanosecond >= 1_000_000_000 {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("time {:02}:{:02}:{:02} out of range", hour, minute, second),
+            ));
+        }
+        Ok(Self {
+            hour,
+            minute,
+            second,
+            nanosecond,
+        })
+    }
+
+    /// Time since midnight
+    pub fn since_midnight(&self) -> Duration {
+        let seconds =
+            u64::from(self.hour) * 3600 + u64::from(self.minute) * 60 + u64::from(self.second);
+        Duration::new(seconds, self.nanosecond)
+    }
+}
+
+impl Timestamp {
+    /// Whole seconds since the Unix epoch, negative before it
+    pub fn unix_seconds(&self) -> i64 {
+        self.date.days_since_epoch() * 86_400 + self.time.since_midnight().as_secs() as i64
+            - i64::from(self.offset) * 60
+    }
+
+    /// The instant at UTC in whole seconds, as `YYYY-MM-DDTHH:MM:SSZ`
+    pub(crate) fn utc_string(&self) -> String {
+        let seconds = self.unix_seconds();
+        let (days, second) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+        // Howard Hinnant's civil_from_days, the inverse of days_since_epoch
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            second / 3600,
+            second / 60 % 60,
+            second % 60
+        )
+    }
+
+    /// The instant as a [`SystemTime`]
+    pub fn to_system_time(&self) -> SystemTime {
+        let seconds = self.unix_seconds();
+        let fraction = Duration::from_nanos(u64::from(self.time.nanosecond));
+        if seconds >= 0 {
+            UNIX_EPOCH + Duration::from_secs(seconds as u64) + fraction
+        } else {
+            UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()) + fraction
+        }
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn invalid(kind: &str, text: &str) -> ParseError {
+    ParseError::new(
+        ErrorKind::InvalidValue,
+        format!("invalid {} `{}`", kind, text),
+    )
+}
+
+/// A number of exactly `len` ASCII digits
+fn digits<T: FromStr>(text: &str, len: usize) -> Option<T> {
+    if text.len() != len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+impl FromStr for Date {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("date", s));
+        };
+        match (digits(year, 4), digits(month, 2), digits(day, 2)) {
+            (Some(year), Some(month), Some(day)) => Self::new(year, month, day),
+            _ => Err(invalid("date", s)),
+        }
+    }
+}
+
+impl FromStr for Time {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (clock, fraction) = match s.split_once('.') {
+            Some((clock, fraction)) => (clock, Some(fraction)),
+            None => (s, None),
+        };
+        let mut parts = clock.split(':');
+        let (Some(hour), Some(minute), second, None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("time", s));
+        };
+        let nanosecond = match fraction {
+            Some(f) if (1..=9).contains(&f.len()) && second.is_some() => {
+                digits::<u32>(f, f.len()).map(|n| n * 10u32.pow(9 - f.len() as u32))
+            }
+            Some(_) => None,
+            None => Some(0),
+        };
+        let second = match second {
+            Some(second) => digits(second, 2),
+            None => Some(0),
+        };
+        match (digits(hour, 2), digits(minute, 2), second, nanosecond) {
+            (Some(hour), Some(minute), Some(second), Some(nanosecond)) => {
+                Self::new(hour, minute, second, nanosecond)
+            }
+            _ => Err(invalid("time", s)),
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, rest) = s
+            .split_once(['T', 't', ' '])
+            .ok_or_else(|| invalid("timestamp", s))?;
+        let (time, offset) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+            (time, 0)
+        } else {
+            let sign = rest.rfind(['+', '-']).ok_or_else(|| {
+                ParseError::new(
+                    ErrorKind::InvalidValue,
+                    format!("timestamp `{}` needs a UTC offset such as `Z`", s),
+                )
+            })?;
+            let (time, offset) = rest.split_at(sign);
+            let minutes = offset[1..]
+                .split_once(':')
+                .and_then(|(h, m)| Some((digits::<i16>(h, 2)?, digits::<i16>(m, 2)?)))
+                .filter(|&(h, m)| h < 24 && m < 60)
+                .map(|(h, m)| h * 60 + m)
+                .ok_or_else(|| invalid("timestamp", s))?;
+            (
+                time,
+                if offset.starts_with('-') {
+                    -minutes
+                } else {
+                    minutes
+                },
+            )
+        };
+        Ok(Self {
+            date: date.parse()?,
+            time: time.parse()?,
+            offset,
+        })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)?;
+        if self.offset == 0 {
+            return f.write_str("Z");
+        }
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let minutes = self.offset.unsigned_abs();
+        write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_and_time() {
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date, Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2024-13-01".parse::<Date>().is_err());
+        assert!("24-01-01".parse::<Date>().is_err());
+
+        let time: Time = "09:30:05.250".parse().unwrap();
+        assert_eq!(time, Time::new(9, 30, 5, 250_000_000).unwrap());
+        assert_eq!(time.to_string(), "09:30:05.25");
+        assert_eq!("23:59".parse::<Time>().unwrap().to_string(), "23:59:00");
+        assert!("24:00:00".parse::<Time>().is_err());
+        assert_eq!(
+            Time::new(7, 5, 60, 0).unwrap_err().message,
+            "time 07:05:60 out of range"
+        );
+        assert!("9:30".parse::<Time>().is_err());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let ts: Timestamp = "2024-05-01T09:30:00+02:00".parse().unwrap();
+        assert_eq!(ts.offset, 120);
+        assert_eq!(ts.to_string(), "2024-05-01T09:30:00+02:00");
+        assert_eq!(ts.unix_seconds(), 1_714_548_600);
+
+        let utc: Timestamp = "2024-05-01 07:30:00Z".parse().unwrap();
+        assert_eq!(utc.unix_seconds(), ts.unix_seconds());
+        assert_ne!(utc, ts);
+        assert_eq!(utc.to_string(), "2024-05-01T07:30:00Z");
+
+        let early: Timestamp = "1969-12-31T23:59:59.5-00:30".parse().unwrap();
+        assert_eq!(early.unix_seconds(), 1799);
+        assert_eq!(
+            early.to_system_time(),
+            UNIX_EPOCH + Duration::from_millis(1_799_500)
+        );
+
+        let err = "2024-05-01T09:30:00".parse::<Timestamp>().unwrap_err();
+        assert_eq!(
+            err.message,
+            "timestamp `2024-05-01T09:30:00` needs a UTC offset such as `Z`"
+        );
+        assert!("2024-05-01".parse::<Timestamp>().is_err());
+    }
+
+    #[test]
+    fn test_utc_string() {
+        let utc = |text: &str| text.parse::<Timestamp>().unwrap().utc_string();
+        assert_eq!(utc("2024-05-01T09:30:00.75+02:00"), "2024-05-01T07:30:00Z");
+        assert_eq!(utc("2024-01-01T00:30:00+01:00"), "2023-12-31T23:30:00Z");
+        assert_eq!(utc("2024-02-28T23:00:00-02:00"), "2024-02-29T01:00:00Z");
+        assert_eq!(utc("1969-12-31T23:59:59Z"), "1969-12-31T23:59:59Z");
+        assert_eq!(utc("2000-03-01T00:00:00Z"), "2000-03-01T00:00:00Z");
+    }
+}
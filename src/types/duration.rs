@@ -0,0 +1,142 @@
+//! Durations (`!dur`)
+
+use std::time::Duration;
+
+use crate::{ErrorKind, ParseError};
+
+const UNITS: [(&str, u128); 8] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
+/// Parse a duration written as amounts with units, such as `1h30m`,
+/// `250ms`, or `1.5s`
+///
+/// Units are `d`, `h`, `m`, `s`, `ms`, `us` (or `µs`), and `ns`; a bare
+/// `0` is also accepted.
+pub(crate) fn parse(text: &str) -> Result<Duration, ParseError> {
+    let invalid = || {
+        ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("invalid duration `{}`", text),
+        )
+    };
+    if text == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if text.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut nanos: u128 = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let (number, tail) = rest.split_at(number_len);
+        if number.is_empty() {
+            return Err(invalid());
+        }
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let (_, scale) = UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(|| {
+                ParseError::new(
+                    ErrorKind::InvalidValue,
+                    format!("unknown unit `{}` in duration `{}`", unit, text),
+                )
+            })?;
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+            return Err(invalid());
+        }
+        let mut amount = if whole.is_empty() {
+            0
+        } else {
+            whole
+                .parse::<u128>()
+                .ok()
+                .and_then(|n| n.checked_mul(*scale))
+                .ok_or_else(invalid)?
+        };
+        let mut place = *scale;
+        for digit in fraction.bytes() {
+            place /= 10;
+            amount += u128::from(digit - b'0') * place;
+        }
+        nanos = nanos.checked_add(amount).ok_or_else(invalid)?;
+        rest = tail;
+    }
+
+    let seconds = u64::try_from(nanos / 1_000_000_000).map_err(|_| invalid())?;
+    Ok(Duration::new(seconds, (nanos % 1_000_000_000) as u32))
+}
+
+/// Write a duration in the form [`parse`] reads, largest units first
+pub(crate) fn format(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".to_string();
+    }
+    let mut out = String::new();
+    let mut seconds = duration.as_secs();
+    for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if seconds >= size {
+            out.push_str(&format!("{}{}", seconds / size, unit));
+            seconds %= size;
+        }
+    }
+    let nanos = duration.subsec_nanos();
+    if nanos > 0 {
+        let (amount, unit) = if nanos % 1_000_000 == 0 {
+            (nanos / 1_000_000, "ms")
+        } else if nanos % 1_000 == 0 {
+            (nanos / 1_000, "us")
+        } else {
+            (nanos, "ns")
+        };
+        out.push_str(&format!("{}{}", amount, unit));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse("2d").unwrap(), Duration::from_secs(172_800));
+        assert_eq!(parse("3µs").unwrap(), Duration::from_micros(3));
+        assert_eq!(parse("0").unwrap(), Duration::ZERO);
+
+        assert_eq!(
+            parse("5 min").unwrap_err().message,
+            "unknown unit ` min` in duration `5 min`"
+        );
+        for bad in ["", "h", "10", "1.2.3s", ".s", "-1s"] {
+            assert!(parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        for text in ["1h30m", "250ms", "1s500ms", "2d", "3us", "1m7ns", "0s"] {
+            assert_eq!(format(parse(text).unwrap()), text);
+        }
+    }
+}
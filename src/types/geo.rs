@@ -0,0 +1,84 @@
+//! Geographic coordinates (`!geo`)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ErrorKind, ParseError};
+
+/// A WGS 84 latitude/longitude pair in decimal degrees
+///
+/// Written as `lat,lon` (e.g. `52.5200,13.4050`). Latitude must lie within
+/// ±90° and longitude within ±180°.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude in degrees, positive north
+    pub lat: f64,
+    /// Longitude in degrees, positive east
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// Create a point, validating the coordinate ranges
+    pub fn new(lat: f64, lon: f64) -> Result<Self, ParseError> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("latitude {} out of range [-90, 90]", lat),
+            ));
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("longitude {} out of range [-180, 180]", lon),
+            ));
+        }
+        Ok(Self { lat, lon })
+    }
+}
+
+impl FromStr for GeoPoint {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("invalid coordinate `{}`", s),
+            )
+        };
+
+        let (lat, lon) = s.split_once(',').ok_or_else(invalid)?;
+        let lat: f64 = lat.trim().parse().map_err(|_| invalid())?;
+        let lon: f64 = lon.trim().parse().map_err(|_| invalid())?;
+
+        Self::new(lat, lon)
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.lat, self.lon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_geo() {
+        let point: GeoPoint = "52.5200, 13.4050".parse().unwrap();
+        assert_eq!(point.lat, 52.52);
+        assert_eq!(point.lon, 13.405);
+        assert_eq!(point.to_string(), "52.52,13.405");
+    }
+
+    #[test]
+    fn test_geo_range_validation() {
+        assert!("90,180".parse::<GeoPoint>().is_ok());
+        assert!("90.1,0".parse::<GeoPoint>().is_err());
+        assert!("0,-180.5".parse::<GeoPoint>().is_err());
+        assert!("NaN,0".parse::<GeoPoint>().is_err());
+        assert!("52.52".parse::<GeoPoint>().is_err());
+    }
+}
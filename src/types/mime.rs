@@ -0,0 +1,157 @@
+//! Media types (`!mime`)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ErrorKind, ParseError};
+
+/// A MIME media type such as `application/json` or `text/html; charset=utf-8`
+///
+/// Type and subtype are validated against the RFC 6838 restricted-name
+/// grammar and lowercased; parameter values may be tokens or quoted strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MediaType {
+    kind: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    /// The top-level type (`application` in `application/json`)
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// The subtype, including any `+suffix` (`json` in `application/json`)
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Parameters in the order they were written
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Look up a parameter by (case-insensitive) name
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn is_restricted_name(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    matches!(bytes.next(), Some(b) if b.is_ascii_alphanumeric())
+        && s.len() <= 127
+        && bytes.all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?=".contains(&b))
+}
+
+fn parse_param_value(raw: &str) -> Option<String> {
+    match raw.strip_prefix('"') {
+        Some(quoted) => {
+            let inner = quoted.strip_suffix('"')?;
+            let mut value = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.push(chars.next()?),
+                    '"' => return None,
+                    c => value.push(c),
+                }
+            }
+            Some(value)
+        }
+        None if is_token(raw) => Some(raw.to_string()),
+        None => None,
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("invalid media type `{}`", s),
+            )
+        };
+
+        let mut sections = s.split(';');
+        let essence = sections.next().unwrap_or_default().trim();
+        let (kind, subtype) = essence.split_once('/').ok_or_else(invalid)?;
+        if !is_restricted_name(kind) || !is_restricted_name(subtype) {
+            return Err(invalid());
+        }
+
+        let params = sections
+            .map(|param| {
+                let (name, value) = param.trim().split_once('=')?;
+                if !is_token(name) {
+                    return None;
+                }
+                Some((name.to_ascii_lowercase(), parse_param_value(value)?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        Ok(Self {
+            kind: kind.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params,
+        })
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind, self.subtype)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "; {}=\"{}\"", name, escaped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_media_type() {
+        let mime: MediaType = "Application/Vnd.API+JSON".parse().unwrap();
+        assert_eq!(mime.kind(), "application");
+        assert_eq!(mime.subtype(), "vnd.api+json");
+
+        let mime: MediaType = r#"text/plain; charset=UTF-8; name="a b""#.parse().unwrap();
+        assert_eq!(mime.param("Charset"), Some("UTF-8"));
+        assert_eq!(mime.param("name"), Some("a b"));
+        assert_eq!(mime.to_string(), r#"text/plain; charset=UTF-8; name="a b""#);
+    }
+
+    #[test]
+    fn test_invalid_media_type() {
+        for input in [
+            "json",
+            "application/",
+            "/json",
+            "text/pl ain",
+            "text/plain; charset",
+        ] {
+            assert!(input.parse::<MediaType>().is_err(), "accepted {}", input);
+        }
+    }
+}
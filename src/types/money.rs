@@ -0,0 +1,141 @@
+//! Exact decimal money amounts (`!money`)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ErrorKind, ParseError};
+
+/// A monetary amount with an ISO 4217 currency code
+///
+/// The amount is stored as an integer number of minor units together with
+/// the number of decimal places it was written with, so `19.99 USD` is kept
+/// exactly as `1999` with a scale of `2` and never goes through a float.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor_units: i128,
+    scale: u32,
+    currency: String,
+}
+
+impl Money {
+    /// Create an amount from minor units, e.g. `Money::new(1999, 2, "USD")` for `19.99 USD`
+    pub fn new(minor_units: i128, scale: u32, currency: impl Into<String>) -> Self {
+        Self {
+            minor_units,
+            scale,
+            currency: currency.into(),
+        }
+    }
+
+    /// The amount in minor units (`1999` for `19.99`)
+    pub fn minor_units(&self) -> i128 {
+        self.minor_units
+    }
+
+    /// Number of decimal places the amount was written with
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// The three-letter currency code
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+}
+
+impl FromStr for Money {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("invalid money value `{}`", s),
+            )
+        };
+
+        let mut parts = s.split_whitespace();
+        let (amount, currency) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(amount), Some(currency), None) => (amount, currency),
+            _ => return Err(invalid()),
+        };
+
+        if currency.len() != 3 || !currency.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidValue,
+                format!("invalid currency code `{}`", currency),
+            ));
+        }
+
+        let (negative, digits) = match amount.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, amount.strip_prefix('+').unwrap_or(amount)),
+        };
+        let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty()
+            || !whole.bytes().all(|b| b.is_ascii_digit())
+            || !frac.bytes().all(|b| b.is_ascii_digit())
+            || (digits.contains('.') && frac.is_empty())
+        {
+            return Err(invalid());
+        }
+
+        let mut minor_units: i128 = 0;
+        for b in whole.bytes().chain(frac.bytes()) {
+            minor_units = minor_units
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(i128::from(b - b'0')))
+                .ok_or_else(invalid)?;
+        }
+        if negative {
+            minor_units = -minor_units;
+        }
+
+        Ok(Self::new(minor_units, frac.len() as u32, currency))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.minor_units.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+
+        if scale == 0 {
+            return write!(f, "{}{} {}", sign, digits, self.currency);
+        }
+
+        let padded = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, frac) = padded.split_at(padded.len() - scale);
+        write!(f, "{}{}.{} {}", sign, whole, frac, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_money() {
+        let money: Money = "19.99 USD".parse().unwrap();
+        assert_eq!(money.minor_units(), 1999);
+        assert_eq!(money.scale(), 2);
+        assert_eq!(money.currency(), "USD");
+    }
+
+    #[test]
+    fn test_money_display_round_trip() {
+        for input in ["19.99 USD", "-0.05 EUR", "1000 JPY", "0.500 BHD"] {
+            let money: Money = input.parse().unwrap();
+            assert_eq!(money.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_money() {
+        assert!("19.99".parse::<Money>().is_err());
+        assert!("19.99 usd".parse::<Money>().is_err());
+        assert!("19. USD".parse::<Money>().is_err());
+        assert!("1e3 USD".parse::<Money>().is_err());
+    }
+}
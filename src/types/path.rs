@@ -0,0 +1,103 @@
+//! Filesystem paths (`!path`)
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{ErrorKind, ParseError};
+
+/// How `!path` values are checked against the filesystem while parsing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathCheck {
+    /// Do not touch the filesystem (default)
+    #[default]
+    None,
+    /// The path must exist
+    Exists,
+    /// The path must exist and be readable (files can be opened, directories listed)
+    Readable,
+}
+
+/// Normalize a path for the current platform
+///
+/// Separators are converted to the native form, repeated separators and `.`
+/// components are dropped. `..` is kept, since resolving it requires looking
+/// at symlinks on disk.
+pub(crate) fn normalize(text: &str) -> PathBuf {
+    let normalized: PathBuf = Path::new(text.trim())
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+
+    if normalized.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        normalized
+    }
+}
+
+/// Verify a path according to `check`, reading a relative path from
+/// `base`, or the current directory without one
+pub(crate) fn check(path: &Path, base: Option<&Path>, check: PathCheck) -> Result<(), ParseError> {
+    let joined;
+    let path = match base {
+        Some(base) if path.is_relative() => {
+            joined = base.join(path);
+            joined.as_path()
+        }
+        _ => path,
+    };
+    let fail = |reason: &str| {
+        Err(ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("path `{}` {}", path.display(), reason),
+        ))
+    };
+
+    match check {
+        PathCheck::None => Ok(()),
+        PathCheck::Exists if !path.exists() => fail("does not exist"),
+        PathCheck::Exists => Ok(()),
+        PathCheck::Readable => {
+            let readable = match fs::metadata(path) {
+                Err(_) => return fail("does not exist"),
+                Ok(meta) if meta.is_dir() => fs::read_dir(path).is_ok(),
+                Ok(_) => fs::File::open(path).is_ok(),
+            };
+            if readable {
+                Ok(())
+            } else {
+                fail("is not readable")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("./a//b/./c"), Path::new("a").join("b").join("c"));
+        assert_eq!(normalize("a/../b"), Path::new("a").join("..").join("b"));
+        assert_eq!(normalize("."), PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_check() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert!(check(manifest, None, PathCheck::Readable).is_ok());
+        assert!(check(&manifest.join("Cargo.toml"), None, PathCheck::Readable).is_ok());
+
+        let missing = manifest.join("does-not-exist.up");
+        assert!(check(&missing, None, PathCheck::None).is_ok());
+        assert!(check(&missing, None, PathCheck::Exists).is_err());
+        assert!(check(&missing, None, PathCheck::Readable).is_err());
+
+        // Relative paths are read from the base, absolute ones as they are
+        let src = Path::new("src");
+        assert!(check(Path::new("lib.rs"), Some(src), PathCheck::Exists).is_ok());
+        assert!(check(Path::new("lib.rs"), None, PathCheck::Exists).is_err());
+        assert!(check(manifest, Some(src), PathCheck::Exists).is_ok());
+    }
+}
@@ -0,0 +1,160 @@
+//! Application-defined annotations, see [`TypeRegistry`]
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{ErrorKind, ParseError, Value};
+
+/// Parses scalars carrying one custom annotation, see [`TypeRegistry`]
+///
+/// Handlers receive the scalar's text and return its value, or a message
+/// explaining why the text is invalid. Closures of the form
+/// `Fn(&str) -> Result<Value, String>` implement it.
+pub trait TypeHandler: Send + Sync {
+    /// Convert `text` into a value
+    fn parse(&self, text: &str) -> Result<Value, String>;
+}
+
+impl<F> TypeHandler for F
+where
+    F: Fn(&str) -> Result<Value, String> + Send + Sync,
+{
+    fn parse(&self, text: &str) -> Result<Value, String> {
+        self(text)
+    }
+}
+
+/// Handlers for annotations beyond the built-in ones, by name
+///
+/// Give a parser the registry with [`Parser::type_registry`]. A handler
+/// registered under a built-in name such as `int` replaces the built-in
+/// conversion.
+///
+/// ```
+/// use uplang::types::{CustomValue, TypeRegistry};
+/// use uplang::{Parser, Value};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Port(u16);
+///
+/// let types = TypeRegistry::new().register("port", |text: &str| {
+///     let port = text.parse().map_err(|_| format!("`{}` is not a port", text))?;
+///     Ok(Value::Custom(CustomValue::new("port", text, Port(port))))
+/// });
+/// let parser = Parser::new().type_registry(types);
+///
+/// let doc = parser.parse_document("listen!port 8080").unwrap();
+/// let Some(Value::Custom(port)) = doc.get("listen") else { panic!() };
+/// assert_eq!(port.downcast_ref::<Port>(), Some(&Port(8080)));
+///
+/// let err = parser.parse_document("listen!port http").unwrap_err();
+/// assert_eq!(err.message, "`http` is not a port");
+/// ```
+///
+/// [`Parser::type_registry`]: crate::Parser::type_registry
+#[derive(Clone, Default)]
+pub struct TypeRegistry(BTreeMap<String, Arc<dyn TypeHandler>>);
+
+impl TypeRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle scalars annotated with `!name`
+    pub fn register(
+        mut self,
+        name: impl Into<String>,
+        handler: impl TypeHandler + 'static,
+    ) -> Self {
+        self.0.insert(name.into(), Arc::new(handler));
+        self
+    }
+
+    /// Whether a handler is registered for `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Convert a scalar with the handler for its annotation
+    ///
+    /// Returns `None` when no handler is registered for `annotation`.
+    pub(crate) fn coerce(&self, annotation: &str, text: &str) -> Option<Result<Value, ParseError>> {
+        let handler = self.0.get(annotation)?;
+        Some(
+            handler
+                .parse(text)
+                .map_err(|message| ParseError::new(ErrorKind::InvalidValue, message)),
+        )
+    }
+}
+
+impl fmt::Debug for TypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// A scalar converted by a [`TypeHandler`] into an application type
+///
+/// Keeps the annotation and source text alongside the converted data, so
+/// the value can be written back out unchanged. Equality compares the
+/// annotation and text.
+#[derive(Clone)]
+pub struct CustomValue {
+    annotation: String,
+    text: String,
+    data: Arc<dyn Any + Send + Sync>,
+}
+
+impl CustomValue {
+    /// Wrap `data`, parsed from `text` annotated with `!annotation`
+    pub fn new(
+        annotation: impl Into<String>,
+        text: impl Into<String>,
+        data: impl Any + Send + Sync,
+    ) -> Self {
+        Self {
+            annotation: annotation.into(),
+            text: text.into(),
+            data: Arc::new(data),
+        }
+    }
+
+    /// A tagged string: `text` marked with its annotation, without data
+    pub fn tagged(annotation: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::new(annotation, text, ())
+    }
+
+    /// Name of the annotation, without the `!`
+    pub fn annotation(&self) -> &str {
+        &self.annotation
+    }
+
+    /// Text the value was parsed from
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The converted data, if it is a `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.data.downcast_ref()
+    }
+}
+
+impl PartialEq for CustomValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.annotation == other.annotation && self.text == other.text
+    }
+}
+
+impl fmt::Debug for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomValue")
+            .field("annotation", &self.annotation)
+            .field("text", &self.text)
+            .finish_non_exhaustive()
+    }
+}
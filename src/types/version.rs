@@ -0,0 +1,92 @@
+//! Semantic versions and version requirements (`!semver`, `!semver-req`)
+//!
+//! Both are held as [`Value::Custom`] values carrying the parsed
+//! [`semver`] type, so the `semver` feature adds no variants to [`Value`];
+//! read them with [`Value::semver`] and [`Value::semver_req`].
+
+use semver::{Version, VersionReq};
+
+use crate::{CustomValue, ErrorKind, ParseError, Value};
+
+pub(crate) fn parse_version(s: &str) -> Result<Value, ParseError> {
+    let version = Version::parse(s.trim()).map_err(|e| {
+        ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("invalid version `{}`: {}", s, e),
+        )
+    })?;
+    Ok(Value::Custom(CustomValue::new(
+        "semver",
+        version.to_string(),
+        version,
+    )))
+}
+
+pub(crate) fn parse_version_req(s: &str) -> Result<Value, ParseError> {
+    let req = VersionReq::parse(s.trim()).map_err(|e| {
+        ParseError::new(
+            ErrorKind::InvalidValue,
+            format!("invalid version requirement `{}`: {}", s, e),
+        )
+    })?;
+    Ok(Value::Custom(CustomValue::new(
+        "semver-req",
+        req.to_string(),
+        req,
+    )))
+}
+
+impl Value {
+    /// The version of a `!semver` value
+    pub fn semver(&self) -> Option<&Version> {
+        match self {
+            Value::Custom(custom) if custom.annotation() == "semver" => custom.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// The requirement of a `!semver-req` value
+    pub fn semver_req(&self) -> Option<&VersionReq> {
+        match self {
+            Value::Custom(custom) if custom.annotation() == "semver-req" => custom.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Check whether a `!semver-req` value is satisfied by `version`
+    ///
+    /// Always `false` for values that are not version requirements.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.semver_req().is_some_and(|req| req.matches(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, to_string, CustomValue, Value};
+
+    #[test]
+    fn test_requirement_matches() {
+        let doc = parse("version!semver 1.4.2\nrequires!semver-req >=1.4, <2").unwrap();
+        let version = doc.nodes[0].value.semver().unwrap();
+        assert_eq!(*version, semver::Version::new(1, 4, 2));
+        assert!(doc.nodes[1].value.matches(version));
+        assert!(!doc.nodes[1].value.matches(&semver::Version::new(2, 0, 0)));
+        assert!(!doc.nodes[0].value.matches(version));
+        assert!(doc.nodes[0].value.semver_req().is_none());
+        assert_eq!(
+            to_string(&doc),
+            "version!semver 1.4.2\nrequires!semver-req >=1.4, <2\n"
+        );
+
+        // A value tagged `semver` without a parsed version has none
+        let tagged = Value::Custom(CustomValue::tagged("semver", "1.4.2"));
+        assert!(tagged.semver().is_none());
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        assert!(parse("version!semver 1.4").is_err());
+        assert!(parse("requires!semver-req >>1").is_err());
+    }
+}
@@ -0,0 +1,348 @@
+//! Variables defined inside a document, see [`Document::resolve_vars`]
+
+use std::collections::HashMap;
+
+use crate::keypath::{self, Segment};
+use crate::suggest::closest;
+use crate::{types, Document, ErrorKind, Node, ParseError, Value};
+
+/// Annotation of the top-level blocks that define variables
+const VARS: &str = "vars";
+
+impl Document {
+    /// Replace references to the document's own variables with their values
+    ///
+    /// Variables are defined in top-level blocks annotated `!vars`, and
+    /// referred to by key path in any later value, so with
+    /// `vars!vars { host db.local }` the value `${vars.host}:5432` reads
+    /// `db.local:5432`. Variables may refer to each other. A value that is
+    /// a single reference takes the variable's value and type, block and
+    /// list included; in other text a variable must be a scalar.
+    /// Annotated values are read as their type when parsed, so references
+    /// in them need a parser with [`coerce_types`](crate::Parser::coerce_types)
+    /// off, and are read as their type once resolved.
+    ///
+    /// Until this is called the document holds the references as written,
+    /// so it can be edited and written back unchanged. Afterwards the
+    /// `!vars` blocks are removed. References whose first key is not a
+    /// `!vars` block, such as `${env:HOME}`, and those escaped as `$${...}`,
+    /// are left for [`Parser::with_interpolation`](crate::Parser::with_interpolation)
+    /// and the application.
+    ///
+    /// # Errors
+    ///
+    /// [`ErrorKind::UndefinedReference`] for a reference to a variable that
+    /// is not defined, [`ErrorKind::ReferenceCycle`] for variables that
+    /// refer to each other in a loop, and [`ErrorKind::InvalidValue`] for a
+    /// block or list in text. Nothing is replaced when resolving fails.
+    ///
+    /// ```
+    /// let mut doc = uplang::parse("vars!vars {\n  host db.local\n  port 5432\n}\nurl postgres://${vars.host}:${vars.port}/app\nport ${vars.port}\n").unwrap();
+    /// doc.resolve_vars().unwrap();
+    /// assert_eq!(doc.get_str("url"), Some("postgres://db.local:5432/app"));
+    /// assert_eq!(doc.get_int("port"), Some(5432));
+    /// assert!(doc.get("vars").is_none());
+    ///
+    /// let mut doc = uplang::parse("vars!vars {\n  host db.local\n}\nurl ${vars.hots}\n").unwrap();
+    /// let err = doc.resolve_vars().unwrap_err();
+    /// assert_eq!(err.message, "line 4: `url` refers to `vars.hots`, which is not defined; did you mean `vars.host`?");
+    /// ```
+    pub fn resolve_vars(&mut self) -> Result<(), ParseError> {
+        if !self.nodes.iter().any(is_vars) {
+            return Ok(());
+        }
+        let mut vars = Vars::new(self.nodes.iter().filter(|node| is_vars(node)));
+        // Resolved in a copy, so the document is unchanged on failure
+        let mut nodes: Vec<Node> = self
+            .nodes
+            .iter()
+            .filter(|node| !is_vars(node))
+            .cloned()
+            .collect();
+        for node in &mut nodes {
+            let path = node.key.clone();
+            vars.resolve_node(&path, node)?;
+        }
+        self.nodes = nodes;
+        Ok(())
+    }
+}
+
+fn is_vars(node: &Node) -> bool {
+    node.type_annotation.as_deref() == Some(VARS) && matches!(node.value, Value::Block(_))
+}
+
+/// The variables of a document, resolved as they are used
+struct Vars {
+    /// `!vars` blocks by key; a later block of the same key wins
+    blocks: HashMap<String, Value>,
+    /// Path of every variable, for suggestions
+    paths: Vec<String>,
+    resolved: HashMap<String, Value>,
+    /// Variables being resolved, for finding cycles
+    stack: Vec<String>,
+}
+
+impl Vars {
+    fn new<'d>(defined: impl Iterator<Item = &'d Node>) -> Self {
+        let mut blocks = HashMap::new();
+        let mut paths = Vec::new();
+        for node in defined {
+            collect_paths(node.key.clone(), &node.value, &mut paths);
+            blocks.insert(node.key.clone(), node.value.clone());
+        }
+        Self {
+            blocks,
+            paths,
+            resolved: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Resolve the references in `node`, at key path `path`, and read an
+    /// annotated scalar as its type
+    fn resolve_node(&mut self, path: &str, node: &mut Node) -> Result<(), ParseError> {
+        // Where a failure is reported
+        let site = match node.origin.as_ref() {
+            Some(origin) => format!("line {}: `{}`", origin.line, path),
+            None => format!("`{}`", path),
+        };
+        let value = std::mem::replace(&mut node.value, Value::Null);
+        node.value = self.resolve(value, path, &site)?;
+        if let (Value::String(text), Some(annotation)) = (&node.value, &node.type_annotation) {
+            if let Some(typed) = types::coerce(annotation, text) {
+                node.value = typed.map_err(|mut e| {
+                    e.message = format!("{}: {}", site, e.message);
+                    e
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `value`, at key path `path`, with the references in its text replaced
+    fn resolve(&mut self, value: Value, path: &str, site: &str) -> Result<Value, ParseError> {
+        Ok(match value {
+            Value::String(text) => self.resolve_text(text, site)?,
+            Value::List(items) => Value::List(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| self.resolve(item, &format!("{}[{}]", path, i), site))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Block(mut block) => {
+                for node in block.nodes_mut() {
+                    let path = format!("{}.{}", path, node.key);
+                    self.resolve_node(&path, node)?;
+                }
+                Value::Block(block)
+            }
+            other => other,
+        })
+    }
+
+    /// Text with its references replaced, or the value of the one variable
+    /// it refers to
+    fn resolve_text(&mut self, text: String, site: &str) -> Result<Value, ParseError> {
+        if !text.contains("${") {
+            return Ok(Value::String(text));
+        }
+        if let Some(name) = whole_reference(&text).filter(|name| self.defines(name)) {
+            return self.value(name, site);
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("${") {
+            // `$${` stays as it is, for the interpolation pass to unescape
+            let escaped = rest[..start].ends_with('$');
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let name = rest[2..end].trim();
+            if escaped || !self.defines(name) {
+                out.push_str(&rest[..=end]);
+            } else {
+                match self.value(name, site)? {
+                    Value::Block(_) | Value::List(_) | Value::Table { .. } => {
+                        return Err(ParseError::new(
+                            ErrorKind::InvalidValue,
+                            format!("{} puts `{}` in text, but it is not a scalar", site, name),
+                        ));
+                    }
+                    value => out.push_str(&value.scalar_text().unwrap_or_default()),
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(Value::String(out))
+    }
+
+    /// Whether `name` refers into a `!vars` block
+    fn defines(&self, name: &str) -> bool {
+        let key = name.split(['.', '[']).next().unwrap_or_default();
+        self.blocks.contains_key(key)
+    }
+
+    /// The resolved value of the variable at `name`, referred to at `site`
+    fn value(&mut self, name: &str, site: &str) -> Result<Value, ParseError> {
+        if let Some(value) = self.resolved.get(name) {
+            return Ok(value.clone());
+        }
+        if let Some(start) = self.stack.iter().position(|var| var == name) {
+            let chain: Vec<String> = self.stack[start..]
+                .iter()
+                .map(String::as_str)
+                .chain([name])
+                .map(|var| format!("`{}`", var))
+                .collect();
+            return Err(ParseError::new(
+                ErrorKind::ReferenceCycle,
+                format!(
+                    "{} refers to variables that refer to each other: {}",
+                    site,
+                    chain.join(" -> ")
+                ),
+            ));
+        }
+        let Some(value) = self.lookup(name).cloned() else {
+            let mut message = format!("{} refers to `{}`, which is not defined", site, name);
+            if let Some(similar) = closest(name, self.paths.iter().map(String::as_str)) {
+                message.push_str(&format!("; did you mean `{}`?", similar));
+            }
+            return Err(ParseError::new(ErrorKind::UndefinedReference, message));
+        };
+
+        self.stack.push(name.to_string());
+        let value = self.resolve(value, name, site);
+        self.stack.pop();
+        let value = value?;
+        self.resolved.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        let segments = keypath::parse(name)?;
+        let (Segment::Key(key), rest) = segments.split_first()? else {
+            return None;
+        };
+        keypath::lookup(self.blocks.get(*key)?, rest)
+    }
+}
+
+/// The name in text that is a single `${name}` and nothing else
+fn whole_reference(text: &str) -> Option<&str> {
+    let name = text.trim().strip_prefix("${")?.strip_suffix('}')?;
+    (!name.contains(['{', '}'])).then(|| name.trim())
+}
+
+/// Add the path of every value in `value`, at `path`, to `paths`
+fn collect_paths(path: String, value: &Value, paths: &mut Vec<String>) {
+    match value {
+        Value::Block(block) => {
+            for node in block.nodes() {
+                collect_paths(format!("{}.{}", path, node.key), &node.value, paths);
+            }
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_paths(format!("{}[{}]", path, i), item, paths);
+            }
+        }
+        _ => {}
+    }
+    paths.push(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, to_string, ErrorKind, Parser, Value};
+
+    #[test]
+    fn test_resolve_vars() {
+        let input = "# Shared settings\nvars!vars {\n  host db.local\n  port!int 5432\n  url postgres://${vars.host}:${vars.port}\n  tags [a, b]\n}\nurl ${vars.url}/app\nport ${vars.port}\nmax 1${vars.port}\ndb {\n  hosts [${vars.host}, backup.local]\n}\ntags ${vars.tags}\nhome ${env:HOME}\nliteral $${vars.host}\n";
+        let mut doc = parse(input).unwrap();
+        // Unresolved, the document writes back as it was read
+        assert_eq!(
+            to_string(&doc),
+            to_string(&parse(&to_string(&doc)).unwrap())
+        );
+        assert_eq!(doc.get_str("url"), Some("${vars.url}/app"));
+
+        doc.resolve_vars().unwrap();
+        assert_eq!(doc.get_str("url"), Some("postgres://db.local:5432/app"));
+        assert_eq!(doc.get("port"), Some(&Value::Int(5432)));
+        assert_eq!(doc.get_str("max"), Some("15432"));
+        assert_eq!(doc.get_str("db.hosts[0]"), Some("db.local"));
+        assert_eq!(
+            doc.get("tags").and_then(Value::as_list).map(<[Value]>::len),
+            Some(2)
+        );
+        assert_eq!(doc.get_str("home"), Some("${env:HOME}"));
+        assert_eq!(doc.get_str("literal"), Some("$${vars.host}"));
+        assert!(doc.get("vars").is_none());
+
+        // Nothing to resolve
+        let mut doc = parse("a ${b}\nb 1").unwrap();
+        doc.resolve_vars().unwrap();
+        assert_eq!(doc.get_str("a"), Some("${b}"));
+    }
+
+    #[test]
+    fn test_resolve_vars_errors() {
+        let input =
+            "vars!vars {\n  a ${vars.b}\n  b x${vars.a}\n  list [1]\n}\nfirst ok\nloop ${vars.a}\n";
+        let mut doc = parse(input).unwrap();
+        let err = doc.resolve_vars().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+        assert_eq!(
+            err.message,
+            "line 7: `loop` refers to variables that refer to each other: `vars.a` -> `vars.b` -> `vars.a`"
+        );
+        // The document is left as it was
+        assert_eq!(doc, parse(input).unwrap());
+
+        let mut doc = parse("vars!vars {\n  list [1]\n}\ns x${vars.list}\n").unwrap();
+        let err = doc.resolve_vars().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidValue);
+        assert_eq!(
+            err.message,
+            "line 4: `s` puts `vars.list` in text, but it is not a scalar"
+        );
+
+        // Annotated values are read as their type once resolved
+        let input =
+            "vars!vars {\n  port 80\n  bad eighty\n}\nport!int ${vars.port}\nbad!int ${vars.bad}\n";
+        assert!(parse(input).is_err());
+        let mut doc = Parser::new()
+            .coerce_types(false)
+            .parse_document(input)
+            .unwrap();
+        let err = doc.resolve_vars().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch);
+        assert!(
+            err.message.starts_with("line 6: `bad`: "),
+            "{}",
+            err.message
+        );
+        assert_eq!(doc.get_str("port"), Some("${vars.port}"));
+        let mut doc = Parser::new()
+            .coerce_types(false)
+            .parse_document(&input.replace("eighty", "8"))
+            .unwrap();
+        doc.resolve_vars().unwrap();
+        assert_eq!(doc.get("port"), Some(&Value::Int(80)));
+
+        let mut doc = parse("vars!vars {\n  port eighty\n}\nn ${vars.missing}\n").unwrap();
+        let err = doc.resolve_vars().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UndefinedReference);
+        assert_eq!(
+            err.message,
+            "line 4: `n` refers to `vars.missing`, which is not defined"
+        );
+    }
+}
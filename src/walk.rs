@@ -0,0 +1,114 @@
+//! Depth-first traversal of documents with key paths
+
+use crate::{Document, Node, Value};
+
+/// Visit every value in `doc` in document order, parents before children
+///
+/// Paths use dots between block keys and `[i]` for list items, e.g.
+/// `servers[2].host`.
+pub(crate) fn walk<'a>(doc: &'a Document, visit: &mut dyn FnMut(&str, &'a Value)) {
+    for node in &doc.nodes {
+        walk_node(node, visit);
+    }
+}
+
+/// Visit a single top-level node and everything below it
+pub(crate) fn walk_node<'a>(node: &'a Node, visit: &mut dyn FnMut(&str, &'a Value)) {
+    let mut path = node.key.clone();
+    walk_value(&mut path, &node.value, visit);
+}
+
+fn walk_value<'a>(path: &mut String, value: &'a Value, visit: &mut dyn FnMut(&str, &'a Value)) {
+    visit(path, value);
+
+    let len = path.len();
+    match value {
+        Value::Block(block) => {
+            for (key, child) in block {
+                path.push('.');
+                path.push_str(key);
+                walk_value(path, child, visit);
+                path.truncate(len);
+            }
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push_str(&format!("[{}]", i));
+                walk_value(path, item, visit);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Document {
+    /// Every node of the document with its key path, depth first in
+    /// document order, parents before children
+    ///
+    /// Nodes inside list items have paths like `servers[0].host`.
+    ///
+    /// ```
+    /// let doc = uplang::parse("server {\n  port 80\n}\nbackends [\n  {\n    url a\n  }\n]").unwrap();
+    /// let paths: Vec<String> = doc.walk().map(|(path, _)| path).collect();
+    /// assert_eq!(paths, ["server", "server.port", "backends", "backends[0].url"]);
+    /// ```
+    pub fn walk(&self) -> impl Iterator<Item = (String, &Node)> {
+        let mut out = Vec::new();
+        for node in &self.nodes {
+            collect_nodes(node.key.clone(), node, &mut out);
+        }
+        out.into_iter()
+    }
+}
+
+fn collect_nodes<'a>(path: String, node: &'a Node, out: &mut Vec<(String, &'a Node)>) {
+    let mut children = Vec::new();
+    nested_nodes(&path, &node.value, &mut children);
+    out.push((path, node));
+    for (path, child) in children {
+        collect_nodes(path, child, out);
+    }
+}
+
+/// The nodes directly inside `value`, through any lists, with their paths
+fn nested_nodes<'a>(path: &str, value: &'a Value, out: &mut Vec<(String, &'a Node)>) {
+    match value {
+        Value::Block(block) => {
+            out.extend(
+                block
+                    .nodes()
+                    .map(|node| (format!("{}.{}", path, node.key), node)),
+            );
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                nested_nodes(&format!("{}[{}]", path, i), item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    #[test]
+    fn test_walk_nodes() {
+        let doc = parse("a {\n  b {\n    c 1\n  }\n  d [\n    x\n    {\n      e 2\n    }\n    [y]\n  ]\n}\nf 3\n").unwrap();
+        let found: Vec<(String, usize)> = doc
+            .walk()
+            .map(|(path, node)| (path, node.origin.as_ref().unwrap().line))
+            .collect();
+        let expected = [
+            ("a", 1),
+            ("a.b", 2),
+            ("a.b.c", 3),
+            ("a.d", 5),
+            ("a.d[1].e", 8),
+            ("f", 13),
+        ];
+        assert_eq!(found, expected.map(|(path, line)| (path.to_string(), line)));
+    }
+}
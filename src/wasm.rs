@@ -0,0 +1,66 @@
+//! JavaScript bindings, for browsers and Node, built with `wasm-bindgen`
+//!
+//! [`parse`] turns UP text into plain JavaScript objects, arrays, strings,
+//! numbers, booleans, and nulls, shaped as [`Document::to_json`] writes
+//! them, and [`stringify()`] turns such an object back into canonical UP, as
+//! [`Document::from_json`] reads it. Parse errors are thrown as `Error`
+//! objects whose `kind` is the [`ErrorKind::code`] and, when the error has
+//! a location, with its `line` and `column`.
+//!
+//! Build the module with the `wasm` feature for `wasm32-unknown-unknown`
+//! as a `cdylib`, then generate the JavaScript glue:
+//!
+//! ```text
+//! cargo rustc --lib --release --features wasm --target wasm32-unknown-unknown --crate-type cdylib
+//! wasm-bindgen target/wasm32-unknown-unknown/release/uplang.wasm --out-dir pkg --target web
+//! ```
+//!
+//! ```js
+//! import init, { parse, stringify } from "./pkg/uplang.js";
+//!
+//! await init();
+//! const config = parse("server {\n  port!int 8080\n}");
+//! config.server.port += 1;
+//! stringify(config); // "server {\n  port!int 8081\n}\n"
+//! ```
+
+use js_sys::{Error, Reflect, JSON};
+use wasm_bindgen::prelude::*;
+
+use crate::{to_string, Document, ErrorKind, ParseError};
+
+/// Parse UP text into a plain JavaScript object
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<JsValue, JsValue> {
+    let doc = crate::parse(input).map_err(js_error)?;
+    JSON::parse(&doc.to_json())
+}
+
+/// Write a plain JavaScript object as canonical UP text
+///
+/// Numbers, booleans, and nulls are annotated so that they parse back as
+/// the same type, and arrays of objects with the same keys become tables.
+#[wasm_bindgen]
+pub fn stringify(value: &JsValue) -> Result<String, JsValue> {
+    let json = JSON::stringify(value)?;
+    let json = json.as_string().ok_or_else(|| {
+        js_error(ParseError::new(
+            ErrorKind::InvalidValue,
+            "value cannot be written as JSON",
+        ))
+    })?;
+    let doc = Document::from_json(&json).map_err(js_error)?;
+    Ok(to_string(&doc))
+}
+
+/// A JavaScript `Error` for `e`, with its kind and location as properties
+fn js_error(e: ParseError) -> JsValue {
+    let error = Error::new(&e.to_string());
+    let set = |key: &str, value: JsValue| Reflect::set(&error, &key.into(), &value);
+    let _ = set("kind", e.kind.code().into());
+    if let Some(location) = &e.location {
+        let _ = set("line", location.line.into());
+        let _ = set("column", location.column.into());
+    }
+    error.into()
+}
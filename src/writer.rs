@@ -0,0 +1,961 @@
+//! Emitting UP text from documents
+
+use std::fmt;
+use std::io;
+
+use crate::keypath::{self, Segment};
+use crate::{quote, redact, text};
+use crate::{Block, Document, Locale, Node, Value};
+
+/// Formats documents as UP text
+///
+/// Output parses back into an equal document for everything the parser can
+/// produce. Strings with embedded newlines are written as fenced multiline
+/// strings; keys and other values that would not read back as written
+/// (whitespace, `!`, a leading `{`, `[`, or `"`, a trailing `\`) are
+/// quoted. Typed values without an annotation get one from
+/// [`Value::type_name`].
+///
+/// Some values built in code have no UP spelling and are written as closely
+/// as possible: a multiline line that is exactly ```` ``` ````, and items
+/// of multiline lists that read as something else, such as `size!int 5`
+/// or `{`.
+///
+/// Nodes annotated with a layout hint are written the way it asks, so
+/// generated documents keep the layout their authors chose: `!inline`
+/// writes a block on one line whatever its width, `!multiline` writes a
+/// block or list over several lines and a string in a fence, and `!fold`
+/// breaks a long string into lines of about 80 columns joined by `\`.
+/// The hint is an annotation like any other, so it is kept in the document
+/// and written back.
+///
+/// ```
+/// use uplang::parse;
+///
+/// let doc = parse("point!inline {\n  x!int 1\n  y!int 2\n}\ntags!multiline [a, b]\n").unwrap();
+/// assert_eq!(
+///     uplang::to_string(&doc),
+///     "point!inline { x!int 1, y!int 2 }\ntags!multiline [\n  a\n  b\n]\n"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Writer {
+    indent: usize,
+    redact_secrets: bool,
+    preserve_trivia: bool,
+    prefer_tables: bool,
+    inline_width: usize,
+    locale: Locale,
+    pub(crate) verify: bool,
+}
+
+impl Writer {
+    /// Create a writer with the default settings
+    pub fn new() -> Self {
+        Self {
+            indent: 2,
+            redact_secrets: false,
+            preserve_trivia: true,
+            prefer_tables: false,
+            inline_width: 0,
+            locale: Locale::new(),
+            verify: true,
+        }
+    }
+
+    /// Set the number of spaces per nesting level (default 2)
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = width;
+        self
+    }
+
+    /// Write `***` instead of the value of every `!secret` node (default off)
+    pub fn redact_secrets(mut self, redact: bool) -> Self {
+        self.redact_secrets = redact;
+        self
+    }
+
+    /// Write nodes parsed with [`Parser::preserve_trivia`] as they were
+    /// (default on)
+    ///
+    /// A node that has not changed since parsing is written byte for byte
+    /// from its [`Trivia`](crate::Trivia), and every node keeps the
+    /// comments and blank lines around it. Turn this off to rewrite the
+    /// whole document in this writer's style. Secrets are never copied
+    /// from the source when [`redact_secrets`](Self::redact_secrets) is on.
+    ///
+    /// [`Parser::preserve_trivia`]: crate::Parser::preserve_trivia
+    pub fn preserve_trivia(mut self, preserve: bool) -> Self {
+        self.preserve_trivia = preserve;
+        self
+    }
+
+    /// Write lists of uniform blocks as tables (default off)
+    ///
+    /// Every list that [`Value::to_table`] can turn into a table is written
+    /// as one, with a `!table` annotation, which is far shorter and easier
+    /// to scan for long lists of records. Reading the output back gives the
+    /// table, not the list. Lists with an annotation of their own are left
+    /// as they are.
+    pub fn prefer_tables(mut self, prefer: bool) -> Self {
+        self.prefer_tables = prefer;
+        self
+    }
+
+    /// Write a block on one line, as `point { x 1, y 2 }`, when the line
+    /// fits in `width` columns (default 0, never)
+    ///
+    /// Only blocks of scalars, inline lists, and blocks that can be inline
+    /// themselves are written this way; a doc comment or a multiline
+    /// string keeps a block on several lines.
+    pub fn inline_width(mut self, width: usize) -> Self {
+        self.inline_width = width;
+        self
+    }
+
+    /// Write numbers and dates in the conventions of `locale` (default
+    /// none)
+    ///
+    /// Values written differently become plain strings; see [`Locale`].
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Check the text of [`format`](Self::format) and
+    /// [`format_changed`](Self::format_changed) before returning it
+    /// (default on)
+    ///
+    /// With the check, they fail rather than return text that parses to a
+    /// different document than they were given, or that `format` would
+    /// change again, so formatting can never corrupt a configuration. Turn
+    /// it off to see what the formatter makes of a document it gets wrong.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Format a document as a string
+    pub fn to_string(&self, doc: &Document) -> String {
+        let localized = self.locale.localize(doc);
+        let doc = localized.as_ref().unwrap_or(doc);
+        let mut out = String::new();
+        for node in &doc.nodes {
+            self.write_node(&mut out, node, 0);
+        }
+        out
+    }
+
+    /// Write a document to `out`, one top-level node at a time
+    pub fn write_document(&self, doc: &Document, mut out: impl io::Write) -> io::Result<()> {
+        let localized = self.locale.localize(doc);
+        let doc = localized.as_ref().unwrap_or(doc);
+        let mut buf = String::new();
+        for node in &doc.nodes {
+            // Keep the previous line's end, if it was copied without one
+            let ended = buf.is_empty() || buf.ends_with('\n');
+            buf.clear();
+            if !ended {
+                buf.push('\n');
+            }
+            self.write_node(&mut buf, node, 0);
+            out.write_all(buf.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn pad(&self, out: &mut String, depth: usize) {
+        out.extend(std::iter::repeat(' ').take(self.indent * depth));
+    }
+
+    pub(crate) fn write_node(&self, out: &mut String, node: &Node, depth: usize) {
+        match node.trivia.as_deref().filter(|_| self.preserve_trivia) {
+            Some(trivia) => {
+                end_line(out);
+                out.push_str(&trivia.leading);
+                if !self.redact_secrets && trivia.is_unchanged(node) {
+                    out.push_str(&trivia.source);
+                } else {
+                    end_line(out);
+                    self.write_plain_node(out, node, depth);
+                }
+                if !trivia.trailing.is_empty() {
+                    end_line(out);
+                    out.push_str(&trivia.trailing);
+                }
+            }
+            None => {
+                end_line(out);
+                self.write_plain_node(out, node, depth);
+            }
+        }
+    }
+
+    /// Write a node in this writer's style, ignoring its own trivia
+    fn write_plain_node(&self, out: &mut String, node: &Node, depth: usize) {
+        for line in node.doc.iter().flat_map(|doc| doc.split('\n')) {
+            self.pad(out, depth);
+            out.push('#');
+            if !line.is_empty() {
+                out.push(' ');
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+        if let Some(from) = &node.generated_from {
+            self.pad(out, depth);
+            out.push_str("# from ");
+            out.push_str(from);
+            out.push('\n');
+        }
+        self.pad(out, depth);
+        if quote::key_needs_quotes(&node.key) {
+            out.push_str(&quote::quote(&node.key));
+        } else {
+            out.push_str(&node.key);
+        }
+        let table = match &node.value {
+            Value::List(_) if self.prefer_tables && node.type_annotation.is_none() => {
+                node.value.to_table()
+            }
+            _ => None,
+        };
+        let value = table.as_ref().unwrap_or(&node.value);
+        let annotation = node
+            .type_annotation
+            .as_deref()
+            .or_else(|| implied_annotation(value));
+        if let Some(annotation) = annotation {
+            out.push('!');
+            out.push_str(annotation);
+        }
+        if self.redact_secrets && redact::is_secret(node) {
+            out.push(' ');
+            out.push_str(redact::MASK);
+            out.push('\n');
+            return;
+        }
+
+        let hint = node.type_annotation.as_deref();
+        match value {
+            Value::Block(block) => {
+                let inline = match hint {
+                    Some("inline") => self.inline_block(block),
+                    Some("multiline") => None,
+                    _ => self.fitting_inline(out, block),
+                };
+                if let Some(text) = inline {
+                    out.push(' ');
+                    out.push_str(&text);
+                    out.push('\n');
+                    return;
+                }
+                out.push_str(" {\n");
+                for child in block.nodes() {
+                    self.write_node(out, child, depth + 1);
+                }
+                self.pad(out, depth);
+                out.push_str("}\n");
+            }
+            Value::List(items) => self.write_list(out, items, depth, hint == Some("multiline")),
+            Value::Table { columns, rows } => {
+                out.push_str(" {\n");
+                self.pad(out, depth + 1);
+                out.push_str("columns ");
+                let header: Vec<Value> = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| match column_annotation(rows, i) {
+                        Some(annotation) => Value::String(format!(
+                            "{}!{}",
+                            column.scalar_text().unwrap_or_default(),
+                            annotation
+                        )),
+                        None => column.clone(),
+                    })
+                    .collect();
+                write_inline_list(out, &header);
+                out.push('\n');
+                self.pad(out, depth + 1);
+                out.push_str("rows [\n");
+                for row in rows {
+                    self.pad(out, depth + 2);
+                    write_inline_list(out, row);
+                    out.push('\n');
+                }
+                self.pad(out, depth + 1);
+                out.push_str("]\n");
+                self.pad(out, depth);
+                out.push_str("}\n");
+            }
+            Value::String(text)
+                if (node.lang.is_some() || text.contains('\n') || hint == Some("multiline"))
+                    && quote::fits_multiline(text) =>
+            {
+                let dedent = match annotation {
+                    // Indent the lines like the rest of the document
+                    Some("dedent") => self.indent * (depth + 1),
+                    annotation => annotation
+                        .and_then(|a| a.parse::<usize>().ok())
+                        .unwrap_or(0),
+                };
+                let lang = node.lang.as_deref();
+                out.push(' ');
+                self.write_multiline(out, text, lang, dedent, depth);
+            }
+            Value::String(text) if hint == Some("fold") && !quote::value_needs_quotes(text) => {
+                let lines = fold(text, FOLD_WIDTH);
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str("\\\n");
+                        self.pad(out, depth + 1);
+                    } else if !line.is_empty() {
+                        out.push(' ');
+                    }
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+            scalar => {
+                let text = scalar.scalar_text().unwrap_or_default();
+                if quotes_empty(scalar, annotation) || quote::value_needs_quotes(&text) {
+                    out.push(' ');
+                    out.push_str(&quote::quote(&text));
+                } else if !text.is_empty() {
+                    out.push(' ');
+                    out.push_str(&text);
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    fn write_multiline(
+        &self,
+        out: &mut String,
+        text: &str,
+        lang: Option<&str>,
+        dedent: usize,
+        depth: usize,
+    ) {
+        out.push_str("```");
+        out.push_str(lang.unwrap_or_default());
+        out.push('\n');
+        for line in text.split('\n') {
+            if !line.is_empty() {
+                out.extend(std::iter::repeat(' ').take(dedent));
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        self.pad(out, depth);
+        out.push_str("```\n");
+    }
+
+    fn write_list(&self, out: &mut String, items: &[Value], depth: usize, multiline: bool) {
+        if !multiline && items.iter().all(is_inline_item) {
+            out.push(' ');
+            write_inline_list(out, items);
+            out.push('\n');
+            return;
+        }
+
+        out.push_str(" [\n");
+        self.write_items(out, items, depth + 1);
+        self.pad(out, depth);
+        out.push_str("]\n");
+    }
+
+    /// Write the items of a multiline list, one per line at `depth`
+    fn write_items(&self, out: &mut String, items: &[Value], depth: usize) {
+        for item in items {
+            self.pad(out, depth);
+            match item {
+                Value::Block(block) => {
+                    if let Some(text) = self.fitting_inline(out, block) {
+                        out.push_str(&text);
+                        out.push('\n');
+                        continue;
+                    }
+                    out.push_str("{\n");
+                    for child in block.nodes() {
+                        self.write_node(out, child, depth + 1);
+                    }
+                    self.pad(out, depth);
+                    out.push_str("}\n");
+                }
+                Value::List(inner) if inner.iter().all(is_inline_item) => {
+                    write_inline_list(out, inner);
+                    out.push('\n');
+                }
+                Value::List(inner) => {
+                    out.push_str("[\n");
+                    self.write_items(out, inner, depth + 1);
+                    self.pad(out, depth);
+                    out.push_str("]\n");
+                }
+                Value::String(text) if text.contains('\n') && quote::fits_multiline(text) => {
+                    self.write_multiline(out, text, None, 0, depth);
+                }
+                scalar => {
+                    let text = scalar.scalar_text().unwrap_or_default();
+                    if quote::item_needs_quotes(&text) {
+                        out.push_str(&quote::quote(&text));
+                    } else {
+                        out.push_str(&text);
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+    }
+}
+
+impl Writer {
+    /// `block` on one line, if inline blocks are on and it fits after
+    /// the text of the line `out` ends with
+    fn fitting_inline(&self, out: &str, block: &Block) -> Option<String> {
+        if self.inline_width == 0 {
+            return None;
+        }
+        let text = self.inline_block(block)?;
+        let line = &out[out.rfind('\n').map_or(0, |i| i + 1)..];
+        let width = text::display_width(line) + 1 + text::display_width(&text);
+        (width <= self.inline_width).then_some(text)
+    }
+
+    /// `block` written as `{ x 1, y 2 }`, if all its nodes can be
+    fn inline_block(&self, block: &Block) -> Option<String> {
+        let mut entries = Vec::new();
+        for node in block.nodes() {
+            // Comments and source text of their own need lines of their own
+            let trivia = node.trivia.is_some() && self.preserve_trivia;
+            if node.doc.is_some() || node.generated_from.is_some() || node.lang.is_some() || trivia
+            {
+                return None;
+            }
+            let mut entry = if quote::key_needs_quotes(&node.key) || breaks_inline(&node.key) {
+                quote::quote(&node.key)
+            } else {
+                node.key.clone()
+            };
+            let annotation = node
+                .type_annotation
+                .as_deref()
+                .or_else(|| implied_annotation(&node.value));
+            if let Some(annotation) = annotation {
+                entry.push('!');
+                entry.push_str(annotation);
+            }
+            if self.redact_secrets && redact::is_secret(node) {
+                entry.push(' ');
+                entry.push_str(redact::MASK);
+                entries.push(entry);
+                continue;
+            }
+            match &node.value {
+                Value::Block(inner) => {
+                    entry.push(' ');
+                    entry.push_str(&self.inline_block(inner)?);
+                }
+                Value::List(_) if self.prefer_tables && node.value.to_table().is_some() => {
+                    return None
+                }
+                // Braces in items would end the block early
+                Value::List(items)
+                    if items.iter().all(|item| {
+                        is_inline_item(item)
+                            && !item.scalar_text().is_some_and(|t| t.contains(['{', '}']))
+                    }) =>
+                {
+                    entry.push(' ');
+                    write_inline_list(&mut entry, items);
+                }
+                Value::List(_) | Value::Table { .. } => return None,
+                Value::String(text) if text.contains('\n') => return None,
+                scalar => {
+                    let text = scalar.scalar_text().unwrap_or_default();
+                    if quotes_empty(scalar, annotation)
+                        || quote::value_needs_quotes(&text)
+                        || breaks_inline(&text)
+                    {
+                        entry.push(' ');
+                        entry.push_str(&quote::quote(&text));
+                    } else if !text.is_empty() {
+                        entry.push(' ');
+                        entry.push_str(&text);
+                    }
+                }
+            }
+            entries.push(entry);
+        }
+        match entries.is_empty() {
+            true => Some("{}".to_string()),
+            false => Some(format!("{{ {} }}", entries.join(", "))),
+        }
+    }
+}
+
+/// Whether text would end or split an entry of an inline block
+fn breaks_inline(text: &str) -> bool {
+    text.contains([',', '{', '}', '[', ']', '"'])
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Format a document as UP text with the default [`Writer`]
+pub fn to_string(doc: &Document) -> String {
+    Writer::new().to_string(doc)
+}
+
+/// The document as UP text, as [`to_string`] writes it
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_string(self))
+    }
+}
+
+/// The node as UP text, with its doc comment and without a final newline
+///
+/// ```
+/// let doc = uplang::parse("server {\n  port!int 80\n}").unwrap();
+/// assert_eq!(doc.nodes[0].to_string(), "server {\n  port!int 80\n}");
+/// ```
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        Writer::new().write_plain_node(&mut out, self, 0);
+        f.write_str(out.strip_suffix('\n').unwrap_or(&out))
+    }
+}
+
+/// The value as UP text, as it would be written as an item of a list
+///
+/// Scalars are quoted where UP needs it, lists that fit are written on one
+/// line, and blocks and multiline strings over several lines. Typed values
+/// are written as their text without an annotation, so `Value::Int(5)` is
+/// `5`, and [`Value::Null`] is `null`. See [`Value::truncated`] for a form that stays on one line, and
+/// [`Value::to_json_string`] for JSON.
+///
+/// ```
+/// use uplang::{up_value, Value};
+///
+/// assert_eq!(Value::from("two words").to_string(), "two words");
+/// assert_eq!(Value::from("[x").to_string(), "\"[x\"");
+/// assert_eq!(up_value!([a, "b, c", 3]).to_string(), "[a, \"b, c\", 3]");
+/// assert_eq!(up_value!({ host h port 80 }).to_string(), "{\n  host h\n  port!int 80\n}");
+/// ```
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        let writer = Writer::new();
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::List(items) if items.iter().all(is_inline_item) => {
+                write_inline_list(&mut out, items);
+            }
+            Value::Table { .. } => {
+                // Tables are only written as the value of a key
+                writer.write_plain_node(&mut out, &Node::new("t", self.clone()), 0);
+                out.replace_range(.."t!table ".len(), "");
+            }
+            value => writer.write_items(&mut out, std::slice::from_ref(value), 0),
+        }
+        f.write_str(out.strip_suffix('\n').unwrap_or(&out))
+    }
+}
+
+impl Document {
+    /// Write this document as UP text with the default [`Writer`]
+    pub fn write_to(&self, out: impl io::Write) -> io::Result<()> {
+        Writer::new().write_document(self, out)
+    }
+
+    /// Write the value at a key path as a standalone UP document
+    ///
+    /// A block's entries become the top-level keys, so `server` can be split
+    /// out into its own file; any other value is written under the last key
+    /// of `path`. `None` if nothing exists at `path`.
+    pub fn emit_subtree(&self, path: &str) -> Option<String> {
+        let value = self.get(path)?;
+        let key = keypath::parse(path)
+            .and_then(|segments| {
+                segments.iter().rev().find_map(|segment| match segment {
+                    Segment::Key(key) => Some(key.to_string()),
+                    Segment::Index(_) => None,
+                })
+            })
+            .unwrap_or_else(|| path.to_string());
+        Some(to_string(&value.to_document(&key)))
+    }
+}
+
+impl Value {
+    /// Turn this value into a document of its own
+    ///
+    /// A block's entries, with their annotations and doc comments, become
+    /// the top-level nodes. Any other value becomes the single node `key`.
+    pub fn to_document(&self, key: &str) -> Document {
+        let nodes = match self {
+            Value::Block(block) => block.nodes().cloned().collect(),
+            value => vec![Node::new(key, value.clone())],
+        };
+        Document { nodes }
+    }
+}
+
+/// End the last line written from source text that had no final newline
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Annotation needed to read a value back as the same type
+fn implied_annotation(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::String(_) | Value::Empty | Value::Block(_) | Value::List(_) => None,
+        Value::Table { .. } => Some("table"),
+        typed => Some(typed.type_name()),
+    }
+}
+
+/// Characters of text per line of a string written with `!fold`
+const FOLD_WIDTH: usize = 80;
+
+/// `text` cut after spaces into lines of about `width` columns
+///
+/// Lines joined by a trailing `\` are trimmed when read, so no line but the
+/// first starts with whitespace.
+fn fold(text: &str, width: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let (mut start, mut end, mut columns) = (0, 0, 0);
+    for word in text.split_inclusive(' ') {
+        let word_columns = text::display_width(word);
+        if end > start && columns + word_columns > width && !word.starts_with(char::is_whitespace) {
+            lines.push(&text[start..end]);
+            start = end;
+            columns = 0;
+        }
+        end += word.len();
+        columns += word_columns;
+    }
+    lines.push(&text[start..]);
+    lines
+}
+
+/// Whether `value` is an empty string that is quoted to read back as one,
+/// since a key alone reads as [`Value::Empty`]
+fn quotes_empty(value: &Value, annotation: Option<&str>) -> bool {
+    annotation.is_none() && matches!(value, Value::String(text) if text.is_empty())
+}
+
+/// Annotation shared by every cell in column `i`, written on the column name
+pub(crate) fn column_annotation(rows: &[Vec<Value>], i: usize) -> Option<&'static str> {
+    let mut cells = rows
+        .iter()
+        .map(|row| row.get(i).and_then(implied_annotation));
+    let first = cells.next()??;
+    cells
+        .all(|annotation| annotation == Some(first))
+        .then_some(first)
+}
+
+fn is_inline_item(value: &Value) -> bool {
+    match value.scalar_text() {
+        Some(text) => !text.contains('\n'),
+        None => false,
+    }
+}
+
+pub(crate) fn write_inline_list(out: &mut String, items: &[Value]) {
+    out.push('[');
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let text = item.scalar_text().unwrap_or_default();
+        let quote = text.is_empty()
+            || text.trim() != text
+            || text.starts_with('{')
+            || text.contains([',', '"', '\\', '[', ']']);
+        if quote {
+            out.push('"');
+            for c in text.chars() {
+                if matches!(c, '"' | '\\') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+        } else {
+            out.push_str(&text);
+        }
+    }
+    out.push(']');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Block, Money};
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"
+name app
+server {
+port!int 8080
+tls {
+cert /etc/cert.pem
+}
+}
+tags [web, "a, b", ""]
+servers [
+{
+host a
+}
+[x, y]
+plain
+]
+price!money 12.50 EUR
+nothing!null
+empty
+script!4 ```
+    echo hi
+      indented
+
+    done
+```
+raw ```
+  leading space
+```
+"#;
+        let doc = parse(input).unwrap();
+        let text = to_string(&doc);
+        assert_eq!(parse(&text).unwrap(), doc);
+        assert!(
+            text.contains("server {\n  port!int 8080\n  tls {\n    cert /etc/cert.pem\n  }\n}\n")
+        );
+    }
+
+    #[test]
+    fn test_values_built_in_code() {
+        let block: Block = [
+            ("price", Value::Money("3 USD".parse::<Money>().unwrap())),
+            ("note", Value::String(" padded".to_string())),
+            ("list", Value::String("[not, a, list]".to_string())),
+            ("my key!", Value::String("\"quoted\"".to_string())),
+            ("nothing", Value::Null),
+        ]
+        .into_iter()
+        .collect();
+        let doc = Document {
+            nodes: vec![Node::new("config", Value::Block(block))],
+        };
+
+        let text = Writer::new().indent(4).to_string(&doc);
+        assert!(text.contains("\n    price!money 3 USD\n"));
+        assert!(text.contains("\n    nothing!null\n"));
+        assert!(text.contains("\n    note \" padded\"\n"));
+        assert!(text.contains("\n    \"my key!\" \"\\\"quoted\\\"\"\n"));
+        let back = parse(&text).unwrap();
+        let Some(Value::Block(config)) = back.get("config") else {
+            panic!("Expected block");
+        };
+        assert_eq!(config.get("note"), Some(&Value::String(" padded".into())));
+        assert_eq!(
+            config.get("list"),
+            Some(&Value::String("[not, a, list]".into()))
+        );
+        assert_eq!(
+            config.get("my key!"),
+            Some(&Value::String("\"quoted\"".into()))
+        );
+    }
+
+    #[test]
+    fn test_layout_hints() {
+        let words = "lorem ipsum dolor sit amet ".repeat(6);
+        let mut doc = parse(
+            "point!multiline { x 1, y 2 }\nsmall!inline {\n  a b\n}\nnote!multiline one line\n",
+        )
+        .unwrap();
+        let mut text = Node::new("text", Value::from(words.trim_end()));
+        text.type_annotation = Some("fold".into());
+        doc.nodes.push(text);
+        let written = Writer::new().inline_width(80).to_string(&doc);
+        assert_eq!(
+            written,
+            "point!multiline {\n  x 1\n  y 2\n}\nsmall!inline { a b }\nnote!multiline ```\none line\n```\n\
+             text!fold lorem ipsum dolor sit amet lorem ipsum dolor sit amet lorem ipsum dolor sit \\\n  \
+             amet lorem ipsum dolor sit amet lorem ipsum dolor sit amet lorem ipsum dolor \\\n  \
+             sit amet\n"
+        );
+        assert_eq!(parse(&written).unwrap(), doc);
+
+        // Folding keeps runs of spaces, which continuation lines would trim
+        assert_eq!(fold("aaa  bbb ccc", 4), ["aaa  ", "bbb ", "ccc"]);
+        assert_eq!(fold("", 4), [""]);
+    }
+
+    #[test]
+    fn test_table() {
+        let table = Value::Table {
+            columns: vec![Value::String("id".into()), Value::String("name".into())],
+            rows: vec![vec![Value::String("1".into()), Value::String("Ann".into())]],
+        };
+        let doc = Document {
+            nodes: vec![Node::new("users", table)],
+        };
+        assert_eq!(
+            to_string(&doc),
+            "users!table {\n  columns [id, name]\n  rows [\n    [1, Ann]\n  ]\n}\n"
+        );
+
+        let doc = parse("users!table {\ncolumns [id!int, name]\nrows {\n[1, Ann]\n[2, Bo]\n}\n}")
+            .unwrap();
+        let text = to_string(&doc);
+        assert!(text.contains("columns [id!int, name]\n"));
+        assert_eq!(parse(&text).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_prefer_tables() {
+        let input = "users [\n  {\n    id!int 1\n    name Ann\n  }\n  {\n    id!int 2\n    name Bo, Jr.\n  }\n]\nmixed [\n  {\n    a 1\n  }\n  {\n    b 2\n  }\n]\n";
+        let doc = parse(input).unwrap();
+        assert_eq!(to_string(&doc), input);
+
+        let text = Writer::new().prefer_tables(true).to_string(&doc);
+        assert!(text.starts_with(
+            "users!table {\n  columns [id!int, name]\n  rows [\n    [1, Ann]\n    [2, \"Bo, Jr.\"]\n  ]\n}\nmixed [\n"
+        ));
+        let back = parse(&text).unwrap();
+        assert_eq!(
+            back.get("users"),
+            doc.get("users").unwrap().to_table().as_ref()
+        );
+        assert_eq!(back.get("mixed"), doc.get("mixed"));
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "# The server\nserver {\n  host a b\n  tags [x, y z]\n}\nscript ```\nline one\nline two\n```\nusers!table {\n  columns [id!int, name]\n  rows [\n    [1, ann]\n  ]\n}\n";
+        let doc = parse(input).unwrap();
+        assert_eq!(doc.to_string(), to_string(&doc));
+        assert_eq!(
+            doc.nodes[0].to_string(),
+            "# The server\nserver {\n  host a b\n  tags [x, y z]\n}"
+        );
+        let server = doc.get("server").unwrap();
+        assert_eq!(server.to_string(), "{\n  host a b\n  tags [x, y z]\n}");
+        assert_eq!(doc.get("server.tags").unwrap().to_string(), "[x, y z]");
+        assert_eq!(
+            doc.get("script").unwrap().to_string(),
+            "```\nline one\nline two\n```"
+        );
+        assert_eq!(
+            doc.get("users").unwrap().to_string(),
+            "{\n  columns [id!int, name]\n  rows [\n    [1, ann]\n  ]\n}"
+        );
+        assert_eq!(Value::Null.to_string(), "null");
+        assert_eq!(Value::from("").to_string(), "\"\"");
+        let nested = Value::List(vec![Value::List(vec![Value::from(1)]), Value::from("a")]);
+        assert_eq!(nested.to_string(), "[\n  [1]\n  a\n]");
+    }
+
+    #[test]
+    fn test_write_to() {
+        let doc = parse("a 1\n# The b block\nb {\n#\n# c\nc 2\n}").unwrap();
+        let mut out = Vec::new();
+        doc.write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a 1\n# The b block\nb {\n  #\n  # c\n  c 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_emit_subtree() {
+        let doc = parse(
+            "name app\nserver {\n# Listen port\nport!int 8080\ntls {\ncert c.pem\n}\n}\nservers [\n{\nhost a\n}\n]",
+        )
+        .unwrap();
+        assert_eq!(
+            doc.emit_subtree("server").unwrap(),
+            "# Listen port\nport!int 8080\ntls {\n  cert c.pem\n}\n"
+        );
+        assert_eq!(doc.emit_subtree("server.tls").unwrap(), "cert c.pem\n");
+        assert_eq!(doc.emit_subtree("servers[0]").unwrap(), "host a\n");
+        assert_eq!(doc.emit_subtree("server.port").unwrap(), "port!int 8080\n");
+        assert_eq!(doc.emit_subtree("missing"), None);
+
+        let split = parse(&doc.emit_subtree("server").unwrap()).unwrap();
+        assert_eq!(
+            Some(&Value::Block(split.nodes.into_iter().collect())),
+            doc.get("server")
+        );
+    }
+
+    #[test]
+    fn test_generated_documents_round_trip() {
+        // Text that looks like syntax, control characters, and wide
+        // characters, in keys, values, and list items
+        const PIECES: &[&str] = &[
+            "a", "9", " ", "\t", "\n", "\r", "\0", "\u{85}", "\u{3000}", "é", "🙂", "#", "!", "\"",
+            "\\", "{", "}", "[", "]", ",", "```", "@let", "-", "int",
+        ];
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        let mut next = move |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        fn text(next: &mut impl FnMut(usize) -> usize) -> String {
+            (0..next(6)).map(|_| PIECES[next(PIECES.len())]).collect()
+        }
+        fn item(next: &mut impl FnMut(usize) -> usize, depth: usize) -> Value {
+            match next(if depth < 4 { 3 } else { 1 }) {
+                0 => Value::String(text(next)),
+                1 => Value::Block(nodes(next, depth + 1).into_iter().collect()),
+                _ => Value::List((0..next(4)).map(|_| item(next, depth + 1)).collect()),
+            }
+        }
+        fn nodes(next: &mut impl FnMut(usize) -> usize, depth: usize) -> Vec<Node> {
+            let mut nodes: Vec<Node> = Vec::new();
+            for _ in 0..next(4) {
+                let key = text(next);
+                let (annotation, value) = match next(5) {
+                    0 => (Some("int"), Value::Int(next(2000) as i64 - 1000)),
+                    1 => (Some("float"), Value::Float(next(2000) as f64 / 7.0)),
+                    2 => (Some("bool"), Value::Bool(next(2) == 0)),
+                    3 => (Some("null"), Value::Null),
+                    _ => (None, item(next, depth)),
+                };
+                if nodes.iter().all(|node| node.key != key) {
+                    nodes.push(Node {
+                        type_annotation: annotation.map(str::to_string),
+                        ..Node::new(key, value)
+                    });
+                }
+            }
+            nodes
+        }
+
+        for _ in 0..2000 {
+            let doc = Document {
+                nodes: nodes(&mut next, 0),
+            };
+            let text = to_string(&doc);
+            assert_eq!(parse(&text).as_ref(), Ok(&doc), "{:?}", text);
+            assert_eq!(crate::format(&text).as_ref(), Ok(&text));
+            let inline = Writer::new().inline_width(40).to_string(&doc);
+            assert_eq!(parse(&inline).as_ref(), Ok(&doc), "{:?}", inline);
+        }
+    }
+}
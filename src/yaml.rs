@@ -0,0 +1,576 @@
+//! Reading the common subset of YAML used in configuration files
+//!
+//! Supported: block mappings and sequences, plain, single-quoted, and
+//! double-quoted scalars, single-line flow collections (`[a, b]`,
+//! `{a: 1}`), literal and folded block scalars (`|`, `>`), and comments.
+//! Anchors, aliases, tags, and multiple documents are not; such text is
+//! read as plain strings where it can be.
+//!
+//! Plain scalars are typed the way YAML 1.2 does: `null`, `~`, and empty
+//! values become [`Value::Null`], `true`/`false` [`Value::Bool`], and
+//! numbers [`Value::Int`] or [`Value::Float`].
+
+use crate::{Block, ErrorKind, Limits, ParseError, Value};
+
+/// Parse YAML from `lines`, each a slice of `input`, which errors point into
+pub(crate) fn parse(input: &str, lines: &[&str]) -> Result<Value, ParseError> {
+    let mut reader = Reader {
+        input,
+        lines,
+        pos: 0,
+        partial: None,
+        depth: 0,
+    };
+    if let Some((_, "---")) = reader.peek()? {
+        reader.pos += 1;
+    }
+    let value = match reader.peek()? {
+        Some((indent, _)) => reader.node(indent)?,
+        None => Value::Null,
+    };
+    match reader.peek()? {
+        None | Some((_, "...")) => Ok(value),
+        Some((_, text)) => Err(reader.error("unexpected indentation", text)),
+    }
+}
+
+struct Reader<'i, 'l> {
+    input: &'i str,
+    lines: &'l [&'i str],
+    pos: usize,
+    /// The rest of line `.0` after a `- `, read as if indented by `.1`
+    partial: Option<(usize, usize, &'i str)>,
+    /// Mappings and sequences being read
+    depth: usize,
+}
+
+impl<'i> Reader<'i, '_> {
+    fn error(&self, message: &str, at: &str) -> ParseError {
+        ParseError::new(ErrorKind::InvalidSyntax, message).locate(self.input, at)
+    }
+
+    /// The indentation and content of the next line that is not blank or a
+    /// comment, skipping to it
+    fn peek(&mut self) -> Result<Option<(usize, &'i str)>, ParseError> {
+        if let Some((pos, indent, text)) = self.partial {
+            if pos == self.pos {
+                return Ok(Some((indent, text)));
+            }
+        }
+        while let Some(line) = self.lines.get(self.pos) {
+            let content = strip_comment(line).trim_end();
+            let text = content.trim_start_matches(' ');
+            if text.starts_with('\t') {
+                return Err(self.error("tabs are not allowed in indentation", line));
+            }
+            if !text.is_empty() {
+                return Ok(Some((content.len() - text.len(), text)));
+            }
+            self.pos += 1;
+        }
+        Ok(None)
+    }
+
+    /// Read the node starting on the next line, indented by `indent`
+    fn node(&mut self, indent: usize) -> Result<Value, ParseError> {
+        let Some((_, text)) = self.peek()? else {
+            return Ok(Value::Null);
+        };
+        self.depth += 1;
+        let value = self.nested_node(indent, text);
+        self.depth -= 1;
+        value
+    }
+
+    fn nested_node(&mut self, indent: usize, text: &'i str) -> Result<Value, ParseError> {
+        Limits::new()
+            .check_depth(self.depth)
+            .map_err(|e| e.locate(self.input, text))?;
+        if is_item(text) {
+            self.sequence(indent)
+        } else if split_key(text).is_some() {
+            self.mapping(indent)
+        } else {
+            self.pos += 1;
+            self.scalar(text)
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, ParseError> {
+        let mut items = Vec::new();
+        while let Some((line_indent, text)) = self.peek()? {
+            if line_indent != indent || !is_item(text) {
+                break;
+            }
+            let rest = text[1..].trim_start_matches(' ');
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent)?);
+            } else {
+                // `- key: value` starts a mapping indented to match `key`
+                let item_indent = indent + (text.len() - rest.len());
+                items.push(self.inline_node(item_indent, rest)?);
+            }
+        }
+        Ok(Value::List(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, ParseError> {
+        let mut block = Block::new();
+        while let Some((line_indent, text)) = self.peek()? {
+            if line_indent != indent {
+                if line_indent > indent {
+                    return Err(self.error("unexpected indentation", text));
+                }
+                break;
+            }
+            let Some((key, rest)) = split_key(text).filter(|_| !is_item(text)) else {
+                return Err(self.error("expected `key: value`", text));
+            };
+            let key = match key.chars().next() {
+                Some('"' | '\'') => match self.scalar(key)? {
+                    Value::String(key) => key,
+                    _ => unreachable!("quoted scalars are strings"),
+                },
+                _ => key.to_string(),
+            };
+            self.pos += 1;
+
+            let value = match rest {
+                "" => match self.peek()? {
+                    // A sequence may sit at the same indentation as its key
+                    Some((next, text)) if next == indent && is_item(text) => {
+                        self.sequence(indent)?
+                    }
+                    _ => self.nested(indent)?,
+                },
+                r if r.starts_with(['|', '>']) => self.block_scalar(indent, r)?,
+                r => self.scalar(r)?,
+            };
+            block.insert(key, value);
+        }
+        Ok(Value::Block(block))
+    }
+
+    /// Read the node on the following lines if they are indented past
+    /// `indent`, otherwise an empty (null) value
+    fn nested(&mut self, indent: usize) -> Result<Value, ParseError> {
+        match self.peek()? {
+            Some((next, _)) if next > indent => self.node(next),
+            _ => Ok(Value::Null),
+        }
+    }
+
+    /// Read a node whose first line starts partway through the current line
+    fn inline_node(&mut self, indent: usize, text: &'i str) -> Result<Value, ParseError> {
+        if is_item(text) || split_key(text).is_some() {
+            self.partial = Some((self.pos, indent, text));
+            return self.node(indent);
+        }
+        self.pos += 1;
+        self.scalar(text)
+    }
+
+    /// Read a literal (`|`) or folded (`>`) block scalar below a key
+    fn block_scalar(&mut self, indent: usize, header: &str) -> Result<Value, ParseError> {
+        let mut body = Vec::new();
+        let mut body_indent = None;
+        while let Some(line) = self.lines.get(self.pos) {
+            let text = line.trim_start_matches(' ');
+            if text.is_empty() {
+                body.push("");
+            } else {
+                let line_indent = line.len() - text.len();
+                if line_indent <= indent {
+                    break;
+                }
+                let body_indent = *body_indent.get_or_insert(line_indent);
+                if line_indent < body_indent {
+                    return Err(
+                        self.error("block scalar is less indented than its first line", line)
+                    );
+                }
+                body.push(&line[body_indent..]);
+            }
+            self.pos += 1;
+        }
+        while body.last() == Some(&"") {
+            body.pop();
+        }
+
+        let mut text = String::new();
+        for (i, line) in body.iter().enumerate() {
+            if header.starts_with('|') {
+                if i > 0 {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            } else if line.is_empty() {
+                text.push('\n');
+            } else {
+                // Folded: lines join with a space, blank lines become breaks
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push(' ');
+                }
+                text.push_str(line);
+            }
+        }
+        if !header.ends_with('-') && !text.is_empty() {
+            text.push('\n');
+        }
+        Ok(Value::String(text))
+    }
+
+    /// Read a whole scalar or flow collection
+    fn scalar(&self, text: &'i str) -> Result<Value, ParseError> {
+        let mut flow = Flow {
+            reader: self,
+            rest: text,
+            depth: self.depth,
+        };
+        let value = flow.value(false)?;
+        let rest = flow.rest.trim_start();
+        if !rest.is_empty() {
+            return Err(self.error("unexpected text after value", rest));
+        }
+        Ok(value)
+    }
+}
+
+/// A scalar or single-line flow collection being read
+struct Flow<'r, 'i, 'l> {
+    reader: &'r Reader<'i, 'l>,
+    rest: &'i str,
+    depth: usize,
+}
+
+impl<'i> Flow<'_, 'i, '_> {
+    /// Read a value; inside a flow collection plain scalars stop at `,]}`
+    fn value(&mut self, in_flow: bool) -> Result<Value, ParseError> {
+        self.rest = self.rest.trim_start();
+        match self.rest.chars().next() {
+            Some('[') => self.sequence(),
+            Some('{') => self.mapping(),
+            Some('"') => self.double_quoted().map(Value::String),
+            Some('\'') => self.single_quoted().map(Value::String),
+            _ => {
+                let end = if in_flow {
+                    self.rest.find([',', ']', '}']).unwrap_or(self.rest.len())
+                } else {
+                    self.rest.len()
+                };
+                let plain = self.rest[..end].trim_end();
+                self.rest = &self.rest[end..];
+                Ok(plain_scalar(plain))
+            }
+        }
+    }
+
+    fn expect(&mut self, token: char) -> Result<(), ParseError> {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                Ok(())
+            }
+            None => Err(self.error(&format!("expected `{}`", token))),
+        }
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        let at = match self.rest.char_indices().nth(1) {
+            Some((end, _)) => &self.rest[..end],
+            None => self.rest,
+        };
+        self.reader.error(message, at)
+    }
+
+    /// Read the items of a flow collection up to `close`
+    fn items(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), ParseError>,
+    ) -> Result<(), ParseError> {
+        self.depth += 1;
+        Limits::new()
+            .check_depth(self.depth)
+            .map_err(|e| e.locate(self.reader.input, &self.rest[..1]))?;
+        self.rest = self.rest[1..].trim_start();
+        if let Some(rest) = self.rest.strip_prefix(close) {
+            self.rest = rest;
+            self.depth -= 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.rest = self.rest.trim_start();
+            match self.rest.chars().next() {
+                Some(',') => self.rest = &self.rest[1..],
+                Some(c) if c == close => {
+                    self.rest = &self.rest[1..];
+                    self.depth -= 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error(&format!("expected `,` or `{}`", close))),
+            }
+        }
+    }
+
+    fn sequence(&mut self) -> Result<Value, ParseError> {
+        let mut items = Vec::new();
+        self.items(']', |flow| {
+            items.push(flow.value(true)?);
+            Ok(())
+        })?;
+        Ok(Value::List(items))
+    }
+
+    fn mapping(&mut self) -> Result<Value, ParseError> {
+        let mut block = Block::new();
+        self.items('}', |flow| {
+            let key = flow.key()?;
+            flow.expect(':')?;
+            let value = flow.value(true)?;
+            block.insert(key, value);
+            Ok(())
+        })?;
+        Ok(Value::Block(block))
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        self.rest = self.rest.trim_start();
+        match self.rest.chars().next() {
+            Some('"') => self.double_quoted(),
+            Some('\'') => self.single_quoted(),
+            _ => {
+                let end = self.rest.find([':', ',', '}']).unwrap_or(self.rest.len());
+                let key = self.rest[..end].trim_end().to_string();
+                self.rest = &self.rest[end..];
+                Ok(key)
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<String, ParseError> {
+        let mut text = String::new();
+        let mut chars = self.rest[1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 2..];
+                    return Ok(text);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(c @ ('"' | '\\' | '/' | ' ')) => c,
+                        Some(u @ ('x' | 'u' | 'U')) => {
+                            let width = match u {
+                                'x' => 2,
+                                'u' => 4,
+                                _ => 8,
+                            };
+                            let digits: String =
+                                chars.by_ref().take(width).map(|(_, c)| c).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .filter(|_| digits.len() == width)
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        }
+        Err(self.error("unterminated quoted string"))
+    }
+
+    fn single_quoted(&mut self) -> Result<String, ParseError> {
+        let mut text = String::new();
+        let mut rest = &self.rest[1..];
+        loop {
+            let Some(end) = rest.find('\'') else {
+                return Err(self.error("unterminated quoted string"));
+            };
+            text.push_str(&rest[..end]);
+            rest = &rest[end + 1..];
+            match rest.strip_prefix('\'') {
+                Some(after) => {
+                    text.push('\'');
+                    rest = after;
+                }
+                None => {
+                    self.rest = rest;
+                    return Ok(text);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a line starts a sequence item
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` (or `key:`) into the key and its value text
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with(['[', '{']) {
+        return None;
+    }
+    let end = match text.chars().next()? {
+        quote @ ('"' | '\'') => text[1..].find(quote)? + 2,
+        _ => 0,
+    };
+    let colon = match text[end..].find(": ") {
+        Some(i) => end + i,
+        None if text.ends_with(':') => text.len() - 1,
+        None => return None,
+    };
+    let key = text[..colon].trim_end();
+    (!key.is_empty()).then(|| (key, text[colon + 1..].trim_start()))
+}
+
+/// Remove a trailing `# comment` that is not inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous == ' ' || previous == '\t' => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Type a plain (unquoted) scalar
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" => return Value::Float(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => return Value::Float(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Value::Float(f64::NAN),
+        _ => {}
+    }
+    let numeric = text.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'));
+    if numeric {
+        if let Ok(n) = text.parse() {
+            return Value::Int(n);
+        }
+        if let Ok(x) = text.parse() {
+            return Value::Float(x);
+        }
+    }
+    Value::String(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(input: &str) -> Result<Value, ParseError> {
+        let lines: Vec<&str> = input.lines().collect();
+        parse(input, &lines)
+    }
+
+    #[test]
+    fn test_mappings_and_sequences() {
+        let value = yaml(
+            "# Service\nname: api\nport: 8080\nratio: 0.5\ndebug: false\nowner: ~\nempty:\nserver:\n  host: \"a b\"   # quoted\n  tags: [web, 'it''s', 2]\n  limits: {cpu: 2, mem: 1Gi}\nservers:\n  - host: a\n    port: 80\n  - host: b\n  -\n    host: c\nplain:\n- x\n- - y\n  - z\nurl: http://example.com/#top\n",
+        )
+        .unwrap();
+
+        let expected = crate::parse(
+            "name api\nport!int 8080\nratio!float 0.5\ndebug!bool false\nowner!null\nempty!null\nserver {\nhost a b\ntags [web, it's, 2]\nlimits {\ncpu!int 2\nmem 1Gi\n}\n}\nservers [\n{\nhost a\nport!int 80\n}\n{\nhost b\n}\n{\nhost c\n}\n]\nplain [\nx\n[y, z]\n]\nurl http://example.com/#top",
+        )
+        .unwrap();
+        let Value::Block(block) = value else {
+            panic!("Expected block");
+        };
+        assert_eq!(block.get("name"), expected.get("name"));
+        assert_eq!(block.get("port"), expected.get("port"));
+        assert_eq!(block.get("ratio"), expected.get("ratio"));
+        assert_eq!(block.get("owner"), Some(&Value::Null));
+        assert_eq!(block.get("empty"), Some(&Value::Null));
+        assert_eq!(block.get("url"), expected.get("url"));
+
+        let server = block.get("server").unwrap();
+        assert_eq!(server.pointer("host"), Some(&Value::String("a b".into())));
+        assert_eq!(
+            server.pointer("tags"),
+            Some(&Value::List(vec![
+                Value::String("web".into()),
+                Value::String("it's".into()),
+                Value::Int(2),
+            ]))
+        );
+        assert_eq!(server.pointer("limits.cpu"), Some(&Value::Int(2)));
+        assert_eq!(
+            server.pointer("limits.mem"),
+            Some(&Value::String("1Gi".into()))
+        );
+
+        let servers = block.get("servers").unwrap();
+        assert_eq!(servers.pointer("[0].port"), Some(&Value::Int(80)));
+        assert_eq!(
+            servers.pointer("[2].host"),
+            Some(&Value::String("c".into()))
+        );
+        assert_eq!(block.get("plain"), expected.get("plain"));
+    }
+
+    #[test]
+    fn test_block_scalars() {
+        let value = yaml("script: |\n  echo a\n    indented\n\n  echo b\nfolded: >-\n  one\n  two\n\n  three\nnext: 1\n").unwrap();
+        assert_eq!(
+            value.pointer("script"),
+            Some(&Value::String("echo a\n  indented\n\necho b\n".into()))
+        );
+        assert_eq!(
+            value.pointer("folded"),
+            Some(&Value::String("one two\nthree".into()))
+        );
+        assert_eq!(value.pointer("next"), Some(&Value::Int(1)));
+    }
+
+    #[test]
+    fn test_scalars_and_sequences_at_top() {
+        assert_eq!(
+            yaml("--- \n\"a\\tb\"\n").unwrap(),
+            Value::String("a\tb".into())
+        );
+        assert_eq!(
+            yaml("- 1\n- two\n").unwrap(),
+            Value::List(vec![Value::Int(1), Value::String("two".into())])
+        );
+        assert_eq!(yaml("").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = "a: 1\n    b: 2\n";
+        let err = yaml(input).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSyntax);
+        assert_eq!(err.line(), Some(2));
+
+        let err = yaml("a: [1, 2\n").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        assert!(yaml("a: \"open\n").is_err());
+        assert!(yaml("a:\n\tb: 1\n").is_err());
+    }
+}
@@ -0,0 +1,926 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn up(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_up"))
+        .args(args)
+        .output()
+        .expect("Failed to run up")
+}
+
+fn write_temp(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("uplang-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_explain_layers() {
+    let base = write_temp("explain-base.up", "server {\nhost a\nport 80\n}\n");
+    let prod = write_temp("explain-prod.up", "\nserver {\nport 8080\n}\n");
+
+    let output = up(&[
+        "explain",
+        base.to_str().unwrap(),
+        prod.to_str().unwrap(),
+        "server.port",
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "server.port = 8080");
+    assert!(lines[1].contains("explain-base.up:1") && lines[1].ends_with(" 80"));
+    assert!(lines[2].contains("explain-prod.up:2") && lines[2].ends_with("8080  (effective)"));
+}
+
+#[test]
+fn test_merge_layers() {
+    let base = write_temp("merge-base.up", "server {\nhost a\nport 80\n}\ntags [x]\n");
+    let local = write_temp("merge-local.up", "server {\nport 8080\n}\ntags [y]\n");
+    let files = [base.to_str().unwrap(), local.to_str().unwrap()];
+
+    let output = up(&["merge", files[0], files[1]]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "server {\n  host a\n  port 8080\n}\ntags [y]\n"
+    );
+
+    let output = up(&["merge", files[0], files[1], "--strategy", "append-lists"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("tags [x, y]\n"));
+
+    let output = up(&["merge", files[0], "--strategy", "wide"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_rewrite_flags() {
+    let base = write_temp("rewrite-base.up", "name app\nport 80\nhost a\n");
+    let local = write_temp("rewrite-local.up", "port 8080\n");
+    let files = [base.to_str().unwrap(), local.to_str().unwrap()];
+
+    let output = up(&["merge", files[0], files[1], "--in-place", "--dry-run"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "--- {0}\n+++ {0}\n@@ -1,3 +1,3 @@\n name app\n-port 80\n+port 8080\n host a\n",
+            files[0]
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(&base).unwrap(),
+        "name app\nport 80\nhost a\n"
+    );
+
+    let output = up(&["merge", files[0], files[1], "-i", "--diff", "--backup"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\n+port 8080\n"));
+    assert_eq!(
+        fs::read_to_string(&base).unwrap(),
+        "name app\nport 8080\nhost a\n"
+    );
+    assert_eq!(
+        fs::read_to_string(format!("{}.bak", files[0])).unwrap(),
+        "name app\nport 80\nhost a\n"
+    );
+    // Nothing is left behind by the atomic write
+    let leftovers = fs::read_dir(base.parent().unwrap())
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("tmp".as_ref()))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    let output = up(&["merge", files[0], "-o", files[1], "--in-place"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let file = write_temp("rewrite-fmt.up", "name    app\n");
+    let output = up(&["fmt", "--dry-run", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("@@ -1 +1 @@\n-name    app\n+name app\n"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "name    app\n");
+}
+
+#[test]
+fn test_get() {
+    let config = write_temp(
+        "get.up",
+        "server {\n  host \"a b\"\n  port!int 8080\n  tags [web, api]\n}\n",
+    );
+    let config = config.to_str().unwrap();
+    let get = |args: &[&str]| {
+        let output = up(&[&["get", config], args].concat());
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(get(&["server.port"]), "8080\n");
+    assert_eq!(get(&["server.host"]), "a b\n");
+    assert_eq!(get(&["server.tags"]), "[web, api]\n");
+    assert_eq!(get(&["server.tags[1]"]), "api\n");
+    assert_eq!(
+        get(&["server", "--json"]),
+        "{\"host\":\"a b\",\"port\":8080,\"tags\":[\"web\",\"api\"]}\n"
+    );
+    assert_eq!(
+        get(&["server"]),
+        "host a b\nport!int 8080\ntags [web, api]\n"
+    );
+
+    let output = up(&["get", config, "server.tls"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("no value at `server.tls`"));
+}
+
+#[test]
+fn test_set_glob() {
+    let dir = write_temp("set", "").with_extension("d");
+    fs::create_dir_all(&dir).unwrap();
+    write_temp(
+        "set.d/a.up",
+        "# Service a\nlogging {\n  level   info\n}\nport!int 80\n",
+    );
+    write_temp("set.d/b.up", "logging {\n  level debug\n}\n");
+    write_temp("set.d/c.up", "name c\n");
+    write_temp("set.d/notes.txt", "logging { level info }\n");
+    write_temp("set.d/.hidden.up", "name hidden\n");
+    let pattern = format!("{}/*.up", dir.display());
+    let file = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+
+    let output = up(&["set", &pattern, "logging.level", "debug", "--dry-run"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("-  level   info\n+  level debug\n"));
+    assert!(stdout.ends_with("Would change 2 of 3 file(s)\n"));
+    assert_eq!(file("c.up"), "name c\n");
+
+    // Several files are only edited in place
+    let output = up(&["set", &pattern, "logging.level", "debug"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(file("c.up"), "name c\n");
+
+    let output = up(&["set", &pattern, "logging.level", "debug", "--in-place"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("a.up: changed\n"));
+    assert!(!stdout.contains("b.up"));
+    assert!(stdout.ends_with("Changed 2 of 3 file(s)\n"));
+    // Only the edited line is rewritten
+    assert_eq!(
+        file("a.up"),
+        "# Service a\nlogging {\n  level debug\n}\nport!int 80\n"
+    );
+    assert_eq!(file("c.up"), "name c\nlogging {\n  level debug\n}\n");
+    assert_eq!(file("notes.txt"), "logging { level info }\n");
+    assert_eq!(file(".hidden.up"), "name hidden\n");
+
+    // Values keep the type they replace; without `--in-place` the result
+    // is printed
+    let a = dir.join("a.up");
+    let output = up(&["set", a.to_str().unwrap(), "port", "8080"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "# Service a\nlogging {\n  level debug\n}\nport!int 8080\n"
+    );
+    assert!(file("a.up").ends_with("port!int 80\n"));
+    let output = up(&["set", a.to_str().unwrap(), "port", "8080", "-i"]);
+    assert!(output.status.success());
+    assert!(file("a.up").ends_with("port!int 8080\n"));
+
+    // One file that cannot take the value stops every write
+    let output = up(&["set", &pattern, "port", "eighty", "--in-place"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("a.up"));
+    assert_eq!(file("b.up"), "logging {\n  level debug\n}\n");
+
+    let output = up(&[
+        "set",
+        &pattern,
+        "--in-place",
+        "--type",
+        "int",
+        "--",
+        "retries",
+        "-1",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        file("b.up"),
+        "logging {\n  level debug\n}\nretries!int -1\n"
+    );
+
+    let missing = format!("{}/*.json", dir.display());
+    let output = up(&["set", &missing, "a", "b"]);
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("no files match"));
+    assert_eq!(up(&["set", &pattern, "a"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_set_keeps_annotation() {
+    let file = write_temp(
+        "set-annotated.up",
+        "timeout!dur 30s\ntoken!secret abc\nserver {\n  ratio!float 0.5\n}\n",
+    );
+    let file = file.to_str().unwrap();
+    let stdout = |args: &[&str]| {
+        let output = up(args);
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(stdout(&["set", file, "timeout", "45s"]).starts_with("timeout!dur 45s\n"));
+    assert!(stdout(&["set", file, "token", "xyz"]).contains("token!secret xyz\n"));
+    assert!(stdout(&["set", file, "server.ratio", "2"]).contains("  ratio!float 2\n"));
+
+    let output = up(&["set", file, "timeout", "bogus"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("`timeout`: Invalid value"), "{}", stderr);
+    assert!(!stderr.contains("line 1"), "{}", stderr);
+}
+
+#[test]
+fn test_policy_check() {
+    let policy = write_temp(
+        "policy.up",
+        "tls-in-prod {\n  when env = prod\n  require tls.enabled = true\n}\nno-debug {\n  severity warning\n  forbid debug = true\n}\n",
+    );
+    let good = write_temp("policy-good.up", "env prod\ntls {\n  enabled true\n}\n");
+    let bad = write_temp(
+        "policy-bad.up",
+        "env prod\ntls {\n  enabled false\n}\ndebug true\n",
+    );
+    let warned = write_temp("policy-warned.up", "env dev\ndebug true\n");
+    let policy = policy.to_str().unwrap();
+
+    let output = up(&[
+        "policy",
+        "check",
+        policy,
+        good.to_str().unwrap(),
+        bad.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("policy-good.up: ok\n"));
+    assert!(stdout.contains(
+        "policy-bad.up:3: `tls.enabled` must be `true` when `env = prod` [tls-in-prod]\n"
+    ));
+    assert!(stdout.contains("policy-bad.up:5: warning: `debug` must not be `true` [no-debug]\n"));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("1 policy violation(s) in 1 file(s)"));
+
+    // Warnings alone pass
+    let output = up(&["policy", "check", policy, warned.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let output = up(&[
+        "policy",
+        "check",
+        policy,
+        bad.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"passed\":false,\"files\":[{"));
+    assert!(
+        stdout.contains("\"rule\":\"tls-in-prod\",\"severity\":\"error\",\"path\":\"tls.enabled\"")
+    );
+
+    assert_eq!(up(&["policy", "lint", policy]).status.code(), Some(2));
+    assert_eq!(up(&["policy", "check", policy]).status.code(), Some(2));
+}
+
+#[test]
+fn test_lint() {
+    let clean = write_temp("lint-clean.up", "name app\nserver {\n  port!int 80\n}\n");
+    let warned = write_temp("lint-warned.up", "name app\ncache {\n}\nname other\n");
+    let broken = write_temp("lint-broken.up", "name app\nserver {\n  port!int 80\n");
+
+    let output = up(&["lint", clean.to_str().unwrap(), warned.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("lint-clean.up: ok\n"));
+    assert!(stdout.contains("lint-warned.up:2: warning: `cache` is an empty block [empty-block]\n"));
+    assert!(stdout.contains(
+        "lint-warned.up:4: warning: duplicate key `name` (first defined on line 1) [duplicate-key]\n"
+    ));
+
+    let output = up(&["lint", broken.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("lint-broken.up:2: block is never closed [unclosed-delimiter]\n"));
+
+    let output = up(&[
+        "lint",
+        warned.to_str().unwrap(),
+        "--allow",
+        "empty-block",
+        "--format",
+        "json",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"passed\":true,\"files\":[{"));
+    assert!(stdout.contains("\"rule\":\"duplicate-key\",\"severity\":\"warning\",\"path\":null"));
+    assert!(!stdout.contains("empty-block"));
+
+    assert_eq!(up(&["lint"]).status.code(), Some(2));
+    assert_eq!(up(&["lint", "--allow"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_lint_suggest() {
+    let file = write_temp(
+        "lint-suggest.up",
+        "users [\n  { id 1, name a }\n  { id 2, name b }\n  { id 3, name c }\n]\n",
+    );
+    let file = file.to_str().unwrap();
+    let output = up(&["lint", file]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{}: ok\n", file)
+    );
+
+    let output = up(&["lint", file, "--suggest", "--format", "json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(
+        "\"rule\":\"use-table\",\"severity\":\"warning\",\"path\":\"users\",\"message\":\"`users` holds 3 blocks with the same keys; write it as a `!table`\",\"line\":1"
+    ));
+}
+
+#[test]
+fn test_schema_export() {
+    let schema = write_temp("export-schema.up", "name!string\nport?!int 8080\n");
+    let output = up(&["schema", "export", schema.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        "{\n  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n  \"type\": \"object\","
+    ));
+    assert!(stdout.contains("\"required\": [\n    \"name\"\n  ]"));
+
+    let example = write_temp("export-example.up", "port!int 80\n");
+    let output = up(&["schema", "export", "--example", example.to_str().unwrap()]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\"port\": {\n      \"type\": \"integer\"\n    }"));
+
+    assert_eq!(up(&["schema", "import"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_migrate() {
+    let migrations = write_temp(
+        "migrate-steps.up",
+        "2 [\n  rename server.addr host\n]\n3 [\n  retype server.port int\n]\n",
+    );
+    let config = write_temp(
+        "migrate-config.up",
+        "version 2\nserver {\n  addr a\n  port 80\n}\n",
+    );
+
+    let output = up(&[
+        "migrate",
+        config.to_str().unwrap(),
+        "--migrations",
+        migrations.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "version!int 3\nserver {\n  addr a\n  port!int 80\n}\n"
+    );
+
+    let output = up(&[
+        "migrate",
+        config.to_str().unwrap(),
+        "--migrations",
+        migrations.to_str().unwrap(),
+        "--from",
+        "1",
+        "--to",
+        "4",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("migrating to version 4: no migration to this version"));
+}
+
+#[test]
+fn test_patch() {
+    let config = write_temp(
+        "patch-config.up",
+        "server {\n  port!int 80\n  hosts [a]\n}\ndebug true\n",
+    );
+    let patch = write_temp(
+        "patch-changes.up",
+        "replace {\n  server.port!int 8443\n}\nremove debug\nappend {\n  server.hosts b\n}\n",
+    );
+
+    let output = up(&["patch", config.to_str().unwrap(), patch.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "server {\n  port!int 8443\n  hosts [a, b]\n}\n"
+    );
+
+    let failing = write_temp("patch-failing.up", "add {\n  server.port 1\n}\n");
+    let output = up(&["patch", config.to_str().unwrap(), failing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("cannot add `server.port`: it already exists"));
+
+    let output = up(&["patch", config.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_docs_with_schema() {
+    let schema = write_temp(
+        "docs-schema.up",
+        "server {\n# Listen port\nport!int 8080\n}\n",
+    );
+    let config = write_temp("docs-config.up", "server {\nport 9000\n}\n");
+    let out = schema.with_file_name("CONFIG.md");
+
+    let output = up(&[
+        "docs",
+        config.to_str().unwrap(),
+        "--schema",
+        schema.to_str().unwrap(),
+        "-o",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let markdown = fs::read_to_string(&out).unwrap();
+    assert!(markdown.contains("| `server.port` | `int` | `8080` | Listen port |"));
+}
+
+#[test]
+fn test_usage_errors() {
+    let output = up(&["frobnicate"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown command"));
+
+    let output = up(&["explain", "server.port"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = up(&["docs", "a.up", "--schema"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_convert() {
+    let config = write_temp(
+        "convert.up",
+        "name app\nserver {\nport!int 8080\n}\ntags [a, b]\n",
+    );
+
+    let output = up(&["convert", config.to_str().unwrap(), "--to", "json"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"name\":\"app\",\"server\":{\"port\":8080},\"tags\":[\"a\",\"b\"]}\n"
+    );
+
+    let output = up(&["convert", config.to_str().unwrap(), "--to", "toml"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("[server]\nport = 8080\n"));
+
+    let json = config.with_file_name("convert.json");
+    let output = up(&[
+        "convert",
+        config.to_str().unwrap(),
+        "--to",
+        "json",
+        "--pretty",
+        "-o",
+        json.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(&json)
+        .unwrap()
+        .starts_with("{\n  \"name\": \"app\",\n"));
+
+    let output = up(&["convert", json.to_str().unwrap(), "--to", "up"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        fs::read_to_string(&config)
+            .unwrap()
+            .replace("{\nport", "{\n  port")
+    );
+
+    let typed = write_temp("convert-typed.up", "timeout!dur 30s\nport!int 80\n");
+    let output = up(&[
+        "convert",
+        typed.to_str().unwrap(),
+        "--to",
+        "json",
+        "--typed",
+    ]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        text,
+        "{\"timeout\":{\"$type\":\"dur\",\"value\":\"30s\"},\"port\":80}\n"
+    );
+    let typed_json = typed.with_file_name("convert-typed.json");
+    fs::write(&typed_json, text).unwrap();
+    let output = up(&[
+        "convert",
+        typed_json.to_str().unwrap(),
+        "--to",
+        "up",
+        "--typed",
+    ]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "timeout!dur 30s\nport!int 80\n"
+    );
+
+    let output = up(&["convert", config.to_str().unwrap(), "--to", "xml"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let scoped = write_temp(
+        "convert-scoped.up",
+        "name app\ndb!internal {\n  host db.local\n}\n",
+    );
+    let output = up(&[
+        "convert",
+        scoped.to_str().unwrap(),
+        "--to",
+        "json",
+        "--audience",
+        "public",
+    ]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"name\":\"app\"}\n"
+    );
+}
+
+#[test]
+fn test_convert_plist_and_reg() {
+    let config = write_temp("platform.up", "name app\nport!int 8080\n");
+    let config = config.to_str().unwrap();
+    let output = up(&["convert", config, "--to", "plist"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().ends_with(
+        "<dict>\n\t<key>name</key>\n\t<string>app</string>\n\t<key>port</key>\n\t<integer>8080</integer>\n</dict>\n</plist>\n"
+    ));
+
+    let output = up(&[
+        "convert",
+        config,
+        "--to",
+        "reg",
+        "--reg-key",
+        "HKEY_CURRENT_USER\\Software\\App",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Windows Registry Editor Version 5.00\r\n\r\n[HKEY_CURRENT_USER\\Software\\App]\r\n\"name\"=\"app\"\r\n\"port\"=dword:00001f90\r\n"
+    );
+
+    // `export` is `convert` for the platform formats
+    let output = up(&[
+        "export",
+        config,
+        "--format",
+        "reg",
+        "--reg-key",
+        "HKEY_CURRENT_USER\\Software\\App",
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("\"port\"=dword:00001f90\r\n"));
+    let output = up(&["export", config, "--format", "plist"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("</plist>\n"));
+    assert_eq!(up(&["export", config]).status.code(), Some(2));
+    let output = up(&["export", config, "--format", "json"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = up(&["convert", config, "--to", "reg"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = up(&["convert", config, "--to", "reg", "--reg-key", "Software"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_convert_ini_and_properties() {
+    let ini = write_temp("legacy.ini", "; main\n[server]\nhost = localhost\n");
+    let output = up(&["convert", ini.to_str().unwrap(), "--to", "up"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "# main\nserver {\n  host localhost\n}\n"
+    );
+
+    let properties = write_temp("legacy.properties", "server.port=8080\n");
+    let output = up(&["convert", properties.to_str().unwrap(), "--to", "json"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"server.port\":\"8080\"}\n"
+    );
+
+    let broken = write_temp("broken.ini", "[server\n");
+    let output = up(&["convert", broken.to_str().unwrap(), "--to", "up"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected `[section name]`"));
+}
+
+#[test]
+fn test_parse_formats() {
+    let file = write_temp(
+        "parse.up",
+        "name app\ntimeout!dur 30s\nserver {\n  port!int 80\n}\n",
+    );
+    let file = file.to_str().unwrap();
+
+    let output = up(&["parse", file]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"name\": \"app\",\n  \"timeout\": {\n    \"$type\": \"dur\",\n    \"value\": \"30s\"\n  },\n  \"server\": {\n    \"port\": 80\n  }\n}\n"
+    );
+
+    let output = up(&["parse", file, "--format", "up"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "name app\ntimeout!dur 30s\nserver {\n  port!int 80\n}\n"
+    );
+
+    let output = up(&["parse", "--format", "debug", file]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Document {"));
+
+    let output = up(&["parse", file, "--format", "xml"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_validate() {
+    let good = write_temp("validate-good.up", "name app\n");
+    let bad = write_temp("validate-bad.up", "name app\nport!int eighty\n");
+
+    let output = up(&["validate", good.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let output = up(&["validate", good.to_str().unwrap(), bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("validate-good.up: ok"));
+    assert!(stdout.contains("validate-bad.up:2:10"));
+
+    let output = up(&["validate", "--format", "json", bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"valid\":false,\"files\":[{\"file\":"));
+    assert!(stdout.contains("\"errors\":[{\"kind\":\"TypeMismatch\","));
+    assert!(stdout.contains("\"line\":2,\"column\":10}"));
+}
+
+#[test]
+fn test_validate_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_up"))
+        .args(["validate", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"a {\nb 1\n}\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "-: ok\n");
+}
+
+#[test]
+fn test_fmt() {
+    let file = write_temp("fmt.up", "server   {\n    port!int  8080\n}\n");
+
+    let output = up(&["fmt", "--check", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("-   1 | server   {"));
+    assert!(stdout.contains("+   2 |   port!int 8080"));
+
+    let output = up(&["fmt", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "server {\n  port!int 8080\n}\n"
+    );
+
+    let output = up(&["fmt", "--check", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = up(&["fmt", "--no-verify", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    let output = up(&["fmt", "--no-verify=yes", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_fmt_changed() {
+    let original = write_temp(
+        "fmt-original.up",
+        "name   app\nserver   {\n    port!int  80\n}\n",
+    );
+    let file = write_temp(
+        "fmt-edited.up",
+        "name   app\nserver {\nport!int  8080\n}\nextra   1\n",
+    );
+
+    let output = up(&[
+        "fmt",
+        "--changed",
+        original.to_str().unwrap(),
+        file.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "name   app\nserver {\n  port!int 8080\n}\nextra 1\n"
+    );
+
+    let output = up(&[
+        "fmt",
+        "--changed",
+        original.to_str().unwrap(),
+        "a.up",
+        "b.up",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_init() {
+    let schema = write_temp(
+        "init-schema.up",
+        "# Service name\nname!string\nport?!int 8080\ndebug?!bool\n",
+    );
+    let output = up(&["init", "--schema", schema.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "# Service name\n# string, required\nname!string\n# int, optional\nport!int 8080\n# bool, optional\ndebug!bool false\n"
+    );
+
+    // Without a schema, the built-in template is written
+    let out = write_temp("init-out.up", "");
+    fs::remove_file(&out).unwrap();
+    let output = up(&["init", "-o", out.to_str().unwrap()]);
+    assert!(output.status.success());
+    let doc = uplang::Parser::new().parse_file(&out).unwrap();
+    assert_eq!(doc.get_int("server.port"), Some(8080));
+    let output = up(&["init", "-o", out.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("file already exists"));
+
+    let bad = write_temp("init-bad.up", "port!int eighty\n");
+    let output = up(&["init", bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("expected int, found `eighty`"));
+}
+
+#[test]
+fn test_table() {
+    let file = write_temp(
+        "table.up",
+        "hosts!table {\n  columns [host, role]\n  rows [\n    [c, web]\n    [a, db]\n    [b, web]\n  ]\n}\nroles [\n  {\n    role web\n    port!int 443\n  }\n  {\n    role db\n    port!int 5432\n  }\n]\n",
+    );
+    let file = file.to_str().unwrap();
+
+    let output = up(&[
+        "table",
+        file,
+        "hosts",
+        "--join",
+        "roles",
+        "--on",
+        "role",
+        "--where",
+        "role=web",
+        "--sort",
+        "host",
+        "--select",
+        "host,port",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "hosts!table {\n  columns [host, port!int]\n  rows [\n    [b, 443]\n    [c, 443]\n  ]\n}\n"
+    );
+
+    // Lists of uniform blocks read as tables
+    let output = up(&["table", file, "roles", "--sort", "port"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("  rows [\n    [web, 443]\n    [db, 5432]\n  ]\n"));
+
+    let output = up(&["table", file, "hosts", "--sort", "port"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("no column `port`"));
+    let output = up(&["table", file, "hosts", "--on", "role"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = up(&["table", file, "missing"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = up(&["table", file, "roles", "--format", "csv"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "role,port\nweb,443\ndb,5432\n"
+    );
+    let output = up(&["table", file, "hosts", "--format", "xml"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_diff() {
+    let old = write_temp(
+        "diff-old.up",
+        "server {\n  host a\n  port 80\n}\nusers [\n  {\n    id 1\n    role dev\n  }\n]\ndebug true\n",
+    );
+    let new = write_temp(
+        "diff-new.up",
+        "server {\n  host b\n  port 80\n}\nusers [\n  {\n    id 2\n    role ops\n  }\n  {\n    id 1\n    role admin\n  }\n]\n",
+    );
+    let files = [old.to_str().unwrap(), new.to_str().unwrap()];
+
+    let output = up(&["diff", files[0], files[1]]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "~ server.host: a -> b\n~ users[0].id: 1 -> 2\n~ users[0].role: dev -> ops\n+ users[1] = {id: 1, role: admin}\n- debug = true\n"
+    );
+
+    let output = up(&[
+        "diff",
+        files[0],
+        files[1],
+        "--list-key",
+        "id",
+        "--color",
+        "always",
+    ]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\x1b[33m~ server.host: a -> b\x1b[0m\n\x1b[32m+ users[0] = {id: 2, role: ops}\x1b[0m\n\x1b[33m~ users[1].role: dev -> admin\x1b[0m\n\x1b[31m- debug = true\x1b[0m\n"
+    );
+
+    let output = up(&["diff", files[0], files[1], "--format", "json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        r#"{"changes":[{"change":"modified","path":"server.host","old":"a","new":"b"},"#
+    ));
+    assert!(stdout.contains(r#"{"change":"removed","path":"debug","value":"true"}"#));
+
+    let output = up(&["diff", files[0]]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = up(&["diff", files[0], files[1], "--color", "sometimes"]);
+    assert_eq!(output.status.code(), Some(2));
+}
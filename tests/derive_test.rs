@@ -0,0 +1,136 @@
+#![cfg(feature = "derive")]
+
+use std::path::PathBuf;
+
+use uplang::{parse, FromUp};
+
+#[derive(Debug, PartialEq, FromUp)]
+#[up(deny_unknown_fields)]
+struct Config {
+    name: String,
+    server: Server,
+    #[up(default)]
+    tags: Vec<String>,
+    backends: Vec<Backend>,
+}
+
+#[derive(Debug, PartialEq, FromUp)]
+struct Server {
+    host: String,
+    #[up(default = "default_port")]
+    port: u16,
+    #[up(rename = "tls-cert")]
+    cert: Option<PathBuf>,
+    #[up(required)]
+    proxy: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromUp)]
+struct Backend {
+    url: String,
+    #[up(default)]
+    weight: u32,
+}
+
+#[derive(Debug, PartialEq, FromUp)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+#[test]
+fn test_derive_reads_document() {
+    let doc = parse(
+        "name app\nserver {\n  host localhost\n  tls-cert!path certs//app.pem\n  proxy!null\n}\nbackends [\n  {\n    url a\n    weight!int 2\n  }\n  {\n    url b\n  }\n]\n",
+    )
+    .unwrap();
+    let config = Config::from_document(&doc).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "app".into(),
+            server: Server {
+                host: "localhost".into(),
+                port: 8080,
+                cert: Some(PathBuf::from("certs/app.pem")),
+                proxy: None,
+            },
+            tags: Vec::new(),
+            backends: vec![
+                Backend {
+                    url: "a".into(),
+                    weight: 2,
+                },
+                Backend {
+                    url: "b".into(),
+                    weight: 0,
+                },
+            ],
+        }
+    );
+
+    let doc = parse("inner!int 3").unwrap();
+    assert_eq!(Wrapper::<u8>::from_document(&doc).unwrap().inner, 3);
+}
+
+#[test]
+fn test_derive_errors() {
+    let cases = [
+        (
+            "name app\nserver {\n  host h\n  port eighty\n  proxy p\n}\nbackends []\n",
+            "server.port (line 4): expected integer, found `eighty`",
+        ),
+        (
+            "name app\nserver {\n  host h\n}\nbackends []\n",
+            "server.proxy (line 2): missing required key",
+        ),
+        (
+            "name app\nserver {\n  host h\n  proxy!null\n}\nbackends [\n  {\n    weight 1\n  }\n]\n",
+            "backends[0].url (line 6): missing required key",
+        ),
+        (
+            "name app\nnmae typo\nserver {\n  host h\n  proxy!null\n}\nbackends []\n",
+            "nmae (line 2): unknown key; did you mean `name`?",
+        ),
+        (
+            "name app\nserver localhost\nbackends []\n",
+            "server (line 2): expected a `Server` block, found `localhost`",
+        ),
+    ];
+    for (input, expected) in cases {
+        let err = Config::from_document(&parse(input).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_derive_reports_every_error() {
+    let doc = parse(
+        "nmae app\nserver {\n  host h\n  port eighty\n  proxy p\n}\nbackends [\n  {\n    url a\n    weight heavy\n  }\n  {\n    weight 1\n  }\n]\ntags [x]\n",
+    )
+    .unwrap();
+    let errors = Config::from_document_all(&doc).unwrap_err();
+    assert_eq!(
+        errors.to_string(),
+        "nmae (line 1): unknown key; did you mean `name`?\n\
+         server.port (line 4): expected integer, found `eighty`\n\
+         backends[1].url (line 7): missing required key\n\
+         backends[0].weight (line 10): expected integer, found `heavy`\n\
+         name: missing required key"
+    );
+    // What was read is kept
+    assert_eq!(errors.decoded.get_str("server.host"), Some("h"));
+    assert_eq!(errors.decoded.get_str("backends[0].url"), Some("a"));
+    assert!(errors.decoded.get("server.port").is_none());
+    assert!(errors.decoded.get("nmae").is_none());
+    assert_eq!(errors.decoded.get_str("tags[0]"), Some("x"));
+
+    let doc = parse("name app\nserver {\n  host h\n  proxy!null\n}\nbackends []\n").unwrap();
+    assert_eq!(
+        Config::from_document_all(&doc).unwrap(),
+        Config::from_document(&doc).unwrap()
+    );
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use uplang::{Document, Value};
+use uplang_macros::include_up;
+
+#[test]
+fn test_include_up() {
+    let doc: Document = include_up!("tests/fixtures/embedded.up");
+    let parsed = uplang::parse(include_str!("fixtures/embedded.up")).unwrap();
+    assert_eq!(doc, parsed);
+    assert_eq!(doc.get_int("port"), Some(8080));
+    assert_eq!(doc.get_int("offset"), Some(-3));
+    assert_eq!(doc.get("proxy"), Some(&Value::Null));
+    assert_eq!(
+        doc.get("timeout"),
+        Some(&Value::Duration(Duration::from_secs(30)))
+    );
+    assert_eq!(doc.get_str("server.tags[1]"), Some("api"));
+    assert_eq!(doc.nodes[0].doc.as_deref(), Some("Service name"));
+    assert_eq!(doc.nodes[1].origin.as_ref().map(|o| o.line), Some(3));
+    let script = doc.nodes.iter().find(|node| node.key == "script").unwrap();
+    assert_eq!(script.lang.as_deref(), Some("bash"));
+    assert_eq!(doc.get("verbose"), Some(&Value::Empty));
+}
+
+#[cfg(feature = "semver")]
+#[test]
+fn test_include_up_semver() {
+    let doc: Document = include_up!("tests/fixtures/semver.up");
+    let parsed = uplang::parse(include_str!("fixtures/semver.up")).unwrap();
+    assert_eq!(doc, parsed);
+    assert_eq!(
+        doc.get("version").and_then(Value::semver),
+        Some(&semver::Version::new(1, 4, 2))
+    );
+    assert!(doc.get("requires").and_then(Value::semver_req).is_some());
+}
+
+#[test]
+fn test_include_up_as_type() {
+    let config = include_up!("tests/fixtures/embedded.up" as BTreeMap<String, Value>);
+    assert_eq!(config["name"], Value::from("app"));
+    assert_eq!(config["port"], Value::Int(8080));
+}
@@ -0,0 +1,54 @@
+//! Run with `wasm-bindgen-test-runner` as the cargo runner for
+//! `wasm32-unknown-unknown`, and `--features wasm`
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use js_sys::{Reflect, JSON};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn get(value: &JsValue, path: &[&str]) -> JsValue {
+    path.iter().fold(value.clone(), |value, key| {
+        Reflect::get(&value, &(*key).into()).unwrap()
+    })
+}
+
+#[wasm_bindgen_test]
+fn test_parse_to_objects() {
+    let config =
+        uplang::wasm::parse("name app\nserver {\n  port!int 8080\n  tags [api, web]\n}").unwrap();
+    assert_eq!(get(&config, &["name"]).as_string().as_deref(), Some("app"));
+    assert_eq!(get(&config, &["server", "port"]).as_f64(), Some(8080.0));
+    let tags = get(&config, &["server", "tags"]);
+    assert!(js_sys::Array::is_array(&tags));
+    assert_eq!(get(&tags, &["1"]).as_string().as_deref(), Some("web"));
+}
+
+#[wasm_bindgen_test]
+fn test_stringify_round_trip() {
+    let config = JSON::parse(r#"{"server":{"port":8081,"debug":false},"name":"app"}"#).unwrap();
+    let text = uplang::wasm::stringify(&config).unwrap();
+    assert_eq!(
+        text,
+        "server {\n  port!int 8081\n  debug!bool false\n}\nname app\n"
+    );
+    let parsed = uplang::wasm::parse(&text).unwrap();
+    assert_eq!(
+        JSON::stringify(&parsed).unwrap(),
+        JSON::stringify(&config).unwrap()
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_errors() {
+    let error = uplang::wasm::parse("name app\nport!int x").unwrap_err();
+    assert!(error.is_instance_of::<js_sys::Error>());
+    assert_eq!(
+        get(&error, &["kind"]).as_string().as_deref(),
+        Some("type-mismatch")
+    );
+    assert_eq!(get(&error, &["line"]).as_f64(), Some(2.0));
+    assert_eq!(get(&error, &["column"]).as_f64(), Some(10.0));
+
+    let error = uplang::wasm::stringify(&JsValue::from(1)).unwrap_err();
+    assert!(error.is_instance_of::<js_sys::Error>());
+}
@@ -0,0 +1,167 @@
+//! `#[derive(FromUp)]` for the `uplang` crate
+//!
+//! Use it through `uplang` with the `derive` feature; see `uplang::FromUp`
+//! for the supported attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr, Path};
+
+/// Implement `uplang::FromUp` for a struct with named fields
+#[proc_macro_derive(FromUp, attributes(up))]
+pub fn derive_from_up(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// How a missing key is filled in
+enum Missing {
+    /// `FromUp::from_missing`, so only `Option` fields may be left out
+    Type,
+    /// The key must be present
+    Required,
+    /// `Default::default()`
+    Default,
+    /// A function returning the value
+    Function(Path),
+}
+
+struct FieldAttrs {
+    rename: Option<String>,
+    missing: Missing,
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            name,
+            "`FromUp` can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            name,
+            "`FromUp` can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut deny_unknown = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("up")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("deny_unknown_fields") {
+                deny_unknown = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown `up` attribute"))
+            }
+        })?;
+    }
+
+    let mut keys = Vec::new();
+    let mut inits = Vec::new();
+    let mut reads = Vec::new();
+    let mut fills = Vec::new();
+    for (i, field) in fields.named.iter().enumerate() {
+        let ident = field.ident.as_ref().expect("named field");
+        let attrs = field_attrs(field)?;
+        let key = attrs.rename.unwrap_or_else(|| ident.to_string());
+        let missing = match attrs.missing {
+            Missing::Type => quote!(::uplang::FromUp::from_missing),
+            Missing::Required => quote!(|| ::core::option::Option::None),
+            Missing::Default => {
+                quote!(|| ::core::option::Option::Some(::core::default::Default::default()))
+            }
+            Missing::Function(path) => quote!(|| ::core::option::Option::Some(#path())),
+        };
+        inits.push(quote! {
+            #ident: ::uplang::__private::field(__block, #key, #missing)?
+        });
+        let read = format_ident!("__field{}", i);
+        reads.push(quote! {
+            let #read = ::uplang::__private::field_all(__block, #key, #missing, &mut __errors);
+        });
+        fills.push(quote! {
+            #ident: #read.expect("read without errors")
+        });
+        keys.push(key);
+    }
+
+    let check_unknown = deny_unknown.then(|| {
+        quote! {
+            ::uplang::__private::deny_unknown(__block, &[#(#keys),*])?;
+        }
+    });
+    let check_unknown_all = deny_unknown.then(|| {
+        quote! {
+            ::uplang::__private::deny_unknown_all(__block, &[#(#keys),*], &mut __errors);
+        }
+    });
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::uplang::FromUp));
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let type_name = name.to_string();
+
+    Ok(quote! {
+        impl #impl_generics ::uplang::FromUp for #name #ty_generics #where_clause {
+            fn from_up(
+                __value: &::uplang::Value,
+            ) -> ::core::result::Result<Self, ::uplang::FromUpError> {
+                let __block = ::uplang::__private::block(__value, #type_name)?;
+                #check_unknown
+                ::core::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+
+            fn from_up_all(
+                __value: &::uplang::Value,
+            ) -> ::core::result::Result<Self, ::std::vec::Vec<::uplang::FromUpError>> {
+                let __block = ::uplang::__private::block(__value, #type_name)
+                    .map_err(|e| ::std::vec![e])?;
+                let mut __errors = ::std::vec::Vec::new();
+                #check_unknown_all
+                #(#reads)*
+                if !__errors.is_empty() {
+                    return ::core::result::Result::Err(__errors);
+                }
+                ::core::result::Result::Ok(Self {
+                    #(#fills,)*
+                })
+            }
+        }
+    })
+}
+
+fn field_attrs(field: &syn::Field) -> Result<FieldAttrs, Error> {
+    let mut attrs = FieldAttrs {
+        rename: None,
+        missing: Missing::Type,
+    };
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("up")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                attrs.rename = Some(name.value());
+            } else if meta.path.is_ident("required") {
+                attrs.missing = Missing::Required;
+            } else if meta.path.is_ident("default") {
+                attrs.missing = if meta.input.peek(syn::Token![=]) {
+                    let function: LitStr = meta.value()?.parse()?;
+                    Missing::Function(function.parse()?)
+                } else {
+                    Missing::Default
+                };
+            } else {
+                return Err(meta.error("unknown `up` attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
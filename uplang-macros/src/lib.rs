@@ -0,0 +1,179 @@
+//! `include_up!` for the `uplang` crate
+//!
+//! Documents are parsed while the crate that includes them is compiled, so
+//! a file that does not parse fails the build with the parser's error
+//! rather than failing at run time. This crate depends on `uplang` for the
+//! parser, so it is a separate dependency rather than a feature of
+//! `uplang`; the code it expands to uses `uplang`, which must be a
+//! dependency too.
+
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Error, LitStr, Token, Type};
+use uplang::{Node, Parser, Value};
+
+/// Embed a UP file, parsed at compile time
+///
+/// The path is relative to the directory of the including crate's
+/// `Cargo.toml`. `include_up!("config/default.up")` is a
+/// `uplang::Document`, built node by node with no text to parse at run
+/// time, and `include_up!("config/default.up" as Config)` reads it into a
+/// type implementing `uplang::FromUp`, panicking if it does not fit.
+///
+/// The crate is rebuilt when the file changes. `@include`d files are
+/// embedded with it, but changes to them alone do not trigger a rebuild.
+///
+/// ```ignore
+/// use uplang_macros::include_up;
+///
+/// let defaults = include_up!("config/default.up");
+/// let config: Config = include_up!("config/default.up" as Config);
+/// ```
+#[proc_macro]
+pub fn include_up(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Include);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// `"path"` or `"path" as Type`
+struct Include {
+    path: LitStr,
+    ty: Option<Type>,
+}
+
+impl Parse for Include {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let ty = if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Self { path, ty })
+    }
+}
+
+fn expand(input: Include) -> Result<TokenStream2, Error> {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let path = manifest_dir.join(input.path.value());
+    let doc = Parser::new()
+        .parse_file(&path)
+        .map_err(|e| Error::new(input.path.span(), e))?;
+
+    let tracked = path.to_string_lossy().into_owned();
+    let nodes = doc.nodes.iter().map(node);
+    let doc = quote! {
+        {
+            // Rebuild when the file changes
+            const _: &[u8] = ::core::include_bytes!(#tracked);
+            ::uplang::Document {
+                nodes: ::std::vec![#(#nodes),*],
+            }
+        }
+    };
+    Ok(match input.ty {
+        None => doc,
+        Some(ty) => {
+            let file = input.path.value();
+            quote! {
+                match <#ty as ::uplang::FromUp>::from_document(&#doc) {
+                    ::core::result::Result::Ok(value) => value,
+                    ::core::result::Result::Err(err) => {
+                        ::core::panic!("{}: {}", #file, err)
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn node(node: &Node) -> TokenStream2 {
+    let key = &node.key;
+    let annotation = option(node.type_annotation.as_deref());
+    let value = match (&node.value, node.type_annotation.as_deref()) {
+        // An annotation of a type this build of the parser does not have,
+        // such as `!semver` without its feature, is typed at run time
+        (Value::String(text), Some(annotation)) => {
+            quote!(::uplang::__private::embedded_scalar(#annotation, #text))
+        }
+        (value, _) => self::value(value),
+    };
+    let doc = option(node.doc.as_deref());
+    let lang = option(node.lang.as_deref());
+    let line = node.origin.as_ref().map_or(0, |origin| origin.line);
+    quote! {
+        ::uplang::__private::embedded_node(#key, #annotation, #value, #doc, #lang, #line)
+    }
+}
+
+fn value(value: &Value) -> TokenStream2 {
+    match value {
+        Value::String(text) => quote!(::uplang::Value::String(::std::string::String::from(#text))),
+        Value::Block(block) => {
+            let nodes = block.nodes().map(node);
+            quote! {
+                ::uplang::Value::Block(
+                    ::std::vec![#(#nodes),*].into_iter().collect::<::uplang::Block>()
+                )
+            }
+        }
+        Value::List(items) => {
+            let items = items.iter().map(self::value);
+            quote!(::uplang::Value::List(::std::vec![#(#items),*]))
+        }
+        Value::Table { columns, rows } => {
+            let columns = columns.iter().map(self::value);
+            let rows = rows.iter().map(|row| {
+                let cells = row.iter().map(self::value);
+                quote!(::std::vec![#(#cells),*])
+            });
+            quote! {
+                ::uplang::Value::Table {
+                    columns: ::std::vec![#(#columns),*],
+                    rows: ::std::vec![#(#rows),*],
+                }
+            }
+        }
+        Value::Int(n) => {
+            let n = Literal::i64_suffixed(*n);
+            quote!(::uplang::Value::Int(#n))
+        }
+        Value::Float(x) => {
+            // Bits, so the value is exact and may be infinite or NaN
+            let bits = Literal::u64_suffixed(x.to_bits());
+            quote!(::uplang::Value::Float(::core::primitive::f64::from_bits(#bits)))
+        }
+        Value::Bool(b) => quote!(::uplang::Value::Bool(#b)),
+        Value::Null => quote!(::uplang::Value::Null),
+        Value::Empty => quote!(::uplang::Value::Empty),
+        Value::Custom(custom) => {
+            let annotation = custom.annotation();
+            let text = custom.text();
+            quote!(::uplang::__private::embedded_custom(#annotation, #text))
+        }
+        // Scalars of the other types are read again from their text, which
+        // parsed once already
+        other => {
+            let name = other.type_name();
+            let text = other.scalar_text().unwrap_or_default();
+            let text = text.as_ref();
+            quote!(::uplang::__private::embedded_scalar(#name, #text))
+        }
+    }
+}
+
+fn option(text: Option<&str>) -> TokenStream2 {
+    match text {
+        Some(text) => quote!(::core::option::Option::Some(#text)),
+        None => quote!(::core::option::Option::None),
+    }
+}